@@ -0,0 +1,4922 @@
+//! Generated by `cargo gen-api-client` from the endpoint manifest of the server, don't edit it by hand.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// The request is sent as the query
+    Get,
+    /// The request is sent as the JSON body
+    Post,
+}
+
+pub trait Endpoint {
+    const METHOD: HttpMethod;
+    /// The full path under `/api`
+    const PATH: &'static str;
+    type Req: Serialize;
+    type Resp: DeserializeOwned;
+}
+
+/// The data of a response with `"ok": false`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommonError {
+    pub code: String,
+    pub msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_msg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The error responded by the server
+    Api(CommonError),
+    Http(reqwest::Error),
+    Decode(serde_json::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Api(e) => write!(f, "{}: {}", e.code, e.msg),
+            Error::Http(e) => write!(f, "{e}"),
+            Error::Decode(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Error::Http(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Decode(value)
+    }
+}
+
+#[derive(Deserialize)]
+struct WebResponse {
+    ok: bool,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// `base_url` is the URL of `/api`, e.g. `https://example.com/api`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Client { http: reqwest::Client::new(), base_url: base_url.into(), token: None }
+    }
+
+    /// Set the access token sent as the bearer token
+    pub fn set_token(&mut self, token: impl Into<String>) {
+        self.token = Some(token.into());
+    }
+
+    pub async fn call<E: Endpoint>(&self, req: &E::Req) -> Result<E::Resp, Error> {
+        let url = format!("{}{}", self.base_url, E::PATH);
+        let builder = match E::METHOD {
+            HttpMethod::Get => self.http.get(url).query(req),
+            HttpMethod::Post => self.http.post(url).json(req),
+        };
+        let builder = match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        };
+        let resp: WebResponse = builder.send().await?.json().await?;
+        if resp.ok {
+            Ok(serde_json::from_value(resp.data)?)
+        } else {
+            Err(Error::Api(serde_json::from_value(resp.data)?))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptReq {
+    pub items: Vec<LegalAcceptance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddFavoriteReq {
+    pub playlist_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddFeaturedReq {
+    /// e.g. `onboarding` for the starter set of the new users, or `weekly_picks` for the next weekly selection
+    pub collection: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Smaller first, re-adding a song moves it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<i32>,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddSongReq {
+    pub playlist_id: i64,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddSongResp {
+    /// The number of songs in the playlist after adding
+    pub songs_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddSongsItem {
+    pub result: AddSongsResult,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddSongsReq {
+    pub playlist_id: i64,
+    /// Added in this order
+    pub song_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddSongsResp {
+    pub results: Vec<AddSongsItem>,
+    /// The number of songs in the playlist after adding
+    pub songs_count: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AddSongsResult {
+    #[serde(rename = "added")]
+    Added,
+    #[serde(rename = "song_not_found")]
+    SongNotFound,
+    #[serde(rename = "playlist_full")]
+    PlaylistFull,
+    /// Already in the playlist, or repeated in the request
+    #[serde(rename = "existed")]
+    Existed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddTagAliasReq {
+    /// May be the name of another tag, which is resolved to the canonical tag when publishing since then
+    pub alias: String,
+    /// The canonical tag
+    pub tag_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddTagAliasResp {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApproveReviewReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioRendition {
+    pub bitrate_kbps: i32,
+    /// `aac` in M4A or `opus` in Ogg
+    pub codec: String,
+    pub size: i64,
+    /// The true peak in dBTP of the rendition itself, usually above the `true_peak` of the song after the lossy
+    /// encoding, so the gain applied to the rendition should be limited by it instead
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub true_peak: Option<f32>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanUserReq {
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootstrapResp {
+    pub limits: LimitsCfg,
+    pub playlist_max_songs: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedEmail {
+    pub create_time: String,
+    pub plain: String,
+    pub subject: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeJmidReq {
+    pub new_jmid: String,
+    pub old_jmid: String,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeOrderReq {
+    pub playlist_id: i64,
+    pub song_id: i64,
+    /// Start from 0
+    pub target_order: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckContributorResp {
+    pub is_contributor: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckFavoriteReq {
+    pub playlist_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckFavoriteResp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add_time: Option<String>,
+    pub is_favorite: bool,
+    pub playlist_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkedUploadReq {
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkedUploadStatusResp {
+    pub chunk_count: u32,
+    pub chunk_size: u64,
+    /// The indexes of the uploaded chunks in ascending order, the others are to be uploaded when resuming
+    pub uploaded: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentIdReq {
+    pub comment_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentItem {
+    pub author: PublicUserProfile,
+    pub content: String,
+    pub create_time: String,
+    /// A deleted comment is kept in the thread as a placeholder, without the author and content.
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<bool>,
+    pub id: i64,
+    /// The root comment of the thread, `None` for the root comments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
+    /// Always 0 for the replies
+    pub reply_count: i64,
+    /// The author of the replied comment, `None` if replying to the root comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<PublicUserProfile>,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmAudioUploadReq {
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedAccountItem {
+    pub id: String,
+    pub name: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionItem {
+    pub id: String,
+    pub name: String,
+    pub public: bool,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionResp {
+    pub items: Vec<ConnectionItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionSetVisibilityReq {
+    pub r#type: String,
+    pub visible: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionSyncReq {
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionUnlinkReq {
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateChunkedUploadReq {
+    /// `mp3`, `aac` or `flac`
+    pub format: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateChunkedUploadResp {
+    pub chunk_count: u32,
+    /// The size of every chunk but the last, which is the rest
+    pub chunk_size: u64,
+    pub expire_time: String,
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCommentReq {
+    pub content: String,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCommentResp {
+    pub comment_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePlaylistReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub name: String,
+    /// Generate the cover from the first songs, defaults to true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_song_cover: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePlaylistResp {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReq {
+    pub content: String,
+    pub content_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_file_id: Option<String>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateResp {
+    pub id: i64,
+    pub mentions: Vec<MentionEntity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreationInfo {
+    /// 0: original, 1: derivative work, 2: tertiary work
+    pub creation_type: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derivative_info: Option<CreationTypeInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_info: Option<CreationTypeInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreationTypeInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    pub origin_type: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song_display_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrewInvitation {
+    /// The roles the user is credited as
+    pub roles: Vec<String>,
+    pub song_display_id: String,
+    pub song_id: i64,
+    pub song_title: String,
+    pub uploader_uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrewInvitationsResp {
+    pub invitations: Vec<CrewInvitation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrewRespondReq {
+    pub song_id: i64,
+}
+
+/// The whole query of `/publish/review/cursor_contributor`, [CursorContributorReq] along with the cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorContributorQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// The prefix of the display id to search
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    /// 0: pending, 1: approved, 2: rejected, all if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<i32>,
+}
+
+/// The unified response envelope of cursor-based list endpoints.
+///
+/// `next_cursor` is `null` when there are no more items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorPage {
+    pub items: Vec<PlayHistoryItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// The unified response envelope of cursor-based list endpoints.
+///
+/// `next_cursor` is `null` when there are no more items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorPage2 {
+    pub items: Vec<SongPublishReviewBrief>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// The raw cursor parameters in the query string, e.g. `?cursor=xxx&page_size=20`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+}
+
+/// The number of reviews submitted in a day, in UTC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyCount {
+    pub count: i64,
+    pub date: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardResp {
+    pub approved_count: i64,
+    /// It's cached for a minute
+    pub create_time: String,
+    /// The submissions of the last 14 days in UTC, oldest first, including the days without submissions
+    pub daily_submissions: Vec<DailyCount>,
+    /// The reviews approved or rejected by the caller
+    pub my_stats: MyReviewStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oldest_pending_age_secs: Option<i64>,
+    /// `None` if nothing is pending
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oldest_pending_submit_time: Option<String>,
+    pub pending_count: i64,
+    pub rejected_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletePlaylistReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteReq2 {
+    pub history_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteTagAliasReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteVersionReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailByIdReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailReq {
+    /// Actually the JMID
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailReq2 {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailReq3 {
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetailResp {
+    /// @since 260121
+    pub creator_profile: PublicUserProfile,
+    pub playlist_info: PlaylistItem,
+    pub songs: Vec<SongItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceItem {
+    pub create_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_info: Option<String>,
+    /// @since 260504
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_name: Option<String>,
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_address: Option<String>,
+    /// The ISO 3166-1 alpha-2 country code of `ip_address` looked up by the GeoIP service of the region gate.
+    /// `None` if unknown, and always `None` if the `region_gate` section is absent or disabled.
+    /// @since 260504
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_time: Option<String>,
+    /// @since 260504
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceListResp {
+    pub devices: Vec<DeviceItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceLogoutReq {
+    pub device_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceRenameReq {
+    pub device_id: i64,
+    /// Cleared if blank
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTrustReq {
+    /// The refresh token of the current device, only the current device can be trusted
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceTrustResp {
+    pub expire_time: String,
+    /// Kept by the device for `/auth/login/email`, it's only returned once
+    pub trusted_device_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_file_id: Option<String>,
+    pub post_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditResp {
+    /// The users mentioned in the content after the edit
+    pub mentions: Vec<MentionEntity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditSongReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Why the song is edited, saved in the audit record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub song_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_ids: Option<Vec<i64>>,
+    /// Fields that are `None` are kept unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailRegisterReq {
+    /// The legal documents accepted, must cover the latest mandatory versions from `/legal/latest`
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    pub captcha_key: String,
+    pub code: String,
+    pub device_info: String,
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailRegisterResp {
+    pub generated_username: String,
+    pub token: TokenPair,
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTemplateReq {
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalLink {
+    pub platform: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FavoritePlaylistItem {
+    pub add_time: String,
+    pub metadata: PlaylistMetadata,
+    pub order_index: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeaturedSong {
+    pub collection: String,
+    pub create_time: String,
+    pub featured_by: i64,
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Smaller first
+    pub position: i32,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowItem {
+    pub follow_time: String,
+    pub user: PublicUserProfile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowReq {
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateCaptchaResp {
+    pub captcha_key: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateChallengeReq {
+    pub provider_account_id: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateChallengeResp {
+    pub challenge: String,
+    pub challenge_id: String,
+    pub provider_account_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetProfileReq {
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetQueueResp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueData>,
+    /// 0 if there is no queue saved
+    pub revision: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuidelineListResp {
+    pub snippets: Vec<GuidelineSnippetItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuidelineSnippetItem {
+    /// Inserted into the comments as is
+    pub content: String,
+    pub slug: String,
+    pub title: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuidelineVersionsReq {
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuidelineVersionsResp {
+    /// Newest first
+    pub versions: Vec<ReviewGuidelineSnippetVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HideSongReq {
+    /// `false` to unhide
+    pub hidden: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HideSongResp {
+    /// Whether the song was not in the status yet
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HotResp {
+    pub songs: Vec<PublicSongDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestsReq {
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestsResp {
+    /// Today's recommendations re-rolled with the picked interests
+    pub songs: Vec<PublicSongDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidCheckPReq {
+    pub jmid_prefix: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidCheckPResp {
+    pub result: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidCheckReq {
+    pub jmid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidCheckResp {
+    pub result: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidGetNextResp {
+    pub jmid: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JmidMineResp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jmid_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Lang {
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageData {
+    /// The language of the emails, `zh-CN` or `en`. `None` to follow the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+/// The last run of a job, kept in Redis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastRun {
+    pub duration_ms: u64,
+    /// `None` if it succeeded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub instance: String,
+    pub scheduled_time: String,
+    pub start_time: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestResp {
+    pub documents: Vec<LegalDocumentItem>,
+    /// The versions the current user has to accept, always empty if not logged in
+    pub pending: Vec<LegalAcceptance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersionBatchItem {
+    /// The error code if it failed to look up, the client should keep the current version of the variant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub variant: String,
+    /// `None` if there is no release of the variant, or it failed to look up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<LatestVersionResp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersionBatchReq {
+    /// See [LatestVersionReq::channel]
+    /// @since 260503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub variants: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersionBatchResp {
+    /// In the order of the requested variants
+    pub results: Vec<LatestVersionBatchItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersionReq {
+    /// `stable`, `beta` or `nightly`, the channel opted in by the user (or `stable`) if absent.
+    /// The releases of the more stable channels are included.
+    /// @since 260503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub variant: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatestVersionResp {
+    pub changelog: String,
+    /// @since 260503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub release_time: String,
+    pub url: String,
+    pub variant: String,
+    pub version_name: String,
+    pub version_number: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalAcceptance {
+    pub kind: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegalDocumentItem {
+    /// The version accepted by the current user, `None` if not logged in or never accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_version: Option<i32>,
+    /// Markdown
+    pub content: String,
+    /// `terms` or `privacy`
+    pub kind: String,
+    /// Whether it must be accepted before logging in
+    pub mandatory: bool,
+    pub publish_time: String,
+    pub title: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playback_position_secs: Option<i32>,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeResp {
+    /// The like count of the song after this operation
+    pub like_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeStatusReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikeStatusResp {
+    pub liked: bool,
+}
+
+/// Optional `limits` section of the config file, the absent fields take the defaults.
+///
+/// The limits are returned by `/bootstrap`, so the clients can validate before uploading.
+///
+/// ```yaml
+/// limits:
+///   audio_max_bytes: 20971520
+///   audio_chunked_max_bytes: 209715200
+///   audio_direct_max_bytes: 209715200
+///   image_max_bytes: 10485760
+///   bio_max_chars: 300
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsCfg {
+    /// Audio uploaded in chunks, see [AUDIO_CHUNK_BYTES]
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_chunked_max_bytes: Option<u64>,
+    /// Audio uploaded to the storage directly with `/song/upload/presign`
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_direct_max_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_max_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio_max_chars: Option<u64>,
+    /// Song comments, review comments, and the comments of approvals and rejections
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment_max_chars: Option<u64>,
+    /// Covers, avatars and post images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_max_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist_description_max_chars: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist_name_max_chars: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_content_max_chars: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_title_max_chars: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_name_max_chars: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_max_chars: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkAccountReq {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedAccountItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_login_time: Option<String>,
+    pub link_time: String,
+    pub provider: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_email: Option<String>,
+    pub provider_user_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedAccountsResp {
+    /// Whether the user can log in by the password, the last linked account can't be unlinked without it
+    pub has_password: bool,
+    pub items: Vec<LinkedAccountItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListContainingReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListContainingResp {
+    pub playlist_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFeaturedReq {
+    pub collection: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListFeaturedResp {
+    pub songs: Vec<FeaturedSong>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListGuidelinesResp {
+    /// Including the archived ones
+    pub snippets: Vec<ReviewGuidelineSnippet>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListLegalHoldsResp {
+    pub items: Vec<UserLegalHold>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPublicByUserReq {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPublicByUserResp {
+    pub playlists: Vec<PlaylistMetadata>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListResp {
+    pub playlists: Vec<PlaylistItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListShadowBansResp {
+    pub items: Vec<UserShadowBan>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListTagAliasesResp {
+    pub items: Vec<SongTagAlias>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalizedTitleItem {
+    /// BCP 47 language tag, e.g. `en`, `ja`, `zh-TW`
+    pub lang: String,
+    /// The description for the playlists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginReq {
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    pub captcha_key: String,
+    /// The TOTP or recovery code, required if the user enabled the 2FA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub device_info: String,
+    pub email: String,
+    pub password: String,
+    /// Returned by `/auth/device/trust`, the captcha and the 2FA code are not required if it's valid
+    /// @since 260504
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trusted_device_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginResp {
+    pub token: TokenPair,
+    pub uid: i64,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MagicLinkRequestReq {
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MagicLinkVerifyReq {
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    /// The TOTP or recovery code, required if the user enabled the 2FA
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub device_info: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkReadReq {
+    /// Mark all the notifications read if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkReadResp {
+    /// The notifications changed from unread to read
+    pub count: u64,
+}
+
+/// A mentioned user, `name` is the name as written so the clients can find `@{name}` in the content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MentionEntity {
+    pub name: String,
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifyReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_temp_id: Option<String>,
+    pub creation_info: CreationInfo,
+    pub description: String,
+    pub explicit: bool,
+    pub external_links: Vec<ExternalLink>,
+    /// Replace the titles in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    pub lyrics: String,
+    pub production_crew: Vec<ProductionItem>,
+    pub song_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song_temp_id: Option<String>,
+    pub subtitle: String,
+    pub tag_ids: Vec<i64>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifyResp {
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MyLikeItem {
+    pub liked_time: String,
+    pub song_data: PublicSongDetail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MyReviewStats {
+    pub approved_count: i64,
+    pub last_14_days_count: i64,
+    pub rejected_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotInterestedReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationItem {
+    /// `None` for the system, or if the actor is not found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<PublicUserProfile>,
+    pub create_time: String,
+    /// Depends on the type
+    pub data: serde_json::Value,
+    pub id: i64,
+    pub read: bool,
+    /// See the `TYPE_*` constants of `service::notification`
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "template")]
+pub enum NotificationTemplate {
+    /// To the uploader, the new song is published
+    #[serde(rename = "review_approved")]
+    ReviewApproved {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        comment: Option<String>,
+        song_display_id: String,
+        song_title: String,
+        user_name: String,
+    },
+    /// To the uploader, the new song is sent back
+    #[serde(rename = "review_rejected")]
+    ReviewRejected {
+        comment: String,
+        song_display_id: String,
+        song_title: String,
+        user_name: String,
+    },
+    /// To the uploader, the modification of a published song is applied
+    #[serde(rename = "review_modify_approved")]
+    ReviewModifyApproved {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        comment: Option<String>,
+        song_display_id: String,
+        user_name: String,
+    },
+    /// To the uploader, the modification of a published song is sent back
+    #[serde(rename = "review_modify_rejected")]
+    ReviewModifyRejected {
+        comment: String,
+        song_display_id: String,
+        user_name: String,
+    },
+    /// To a registered user credited in the production crew
+    #[serde(rename = "crew_invitation")]
+    CrewInvitation {
+        song_display_id: String,
+        song_title: String,
+        uploader_name: String,
+        user_name: String,
+    },
+    /// To the uploader and the contributors, someone commented on the review
+    #[serde(rename = "review_comment")]
+    ReviewComment {
+        actor_name: String,
+        content: String,
+        song_display_id: String,
+    },
+    /// To the contributors, the uploader updated the review
+    #[serde(rename = "review_modified")]
+    ReviewModified {
+        actor_name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+        song_display_id: String,
+    },
+    /// To the maintainer, a new review is submitted
+    #[serde(rename = "review_pending")]
+    ReviewPending {
+        author: String,
+        song_title: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthAuthorizeReq {
+    /// `github` or `google`
+    pub provider: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthAuthorizeResp {
+    pub state: String,
+    /// Open it in the browser, the provider redirects back to the configured page with the `code` and `state`
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthCallbackReq {
+    /// The legal documents accepted, the new users have to accept the mandatory ones of `/legal/latest`
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    pub code: String,
+    pub device_info: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthLoginResp {
+    /// The user is registered by this login, the client may guide them to edit the profile
+    pub first_access: bool,
+    pub token: TokenPair,
+    pub uid: i64,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthTwoFactorReq {
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    /// The TOTP or recovery code
+    pub code: String,
+    pub device_info: String,
+    /// The `ticket` in the detail of the `2fa_required` error of the callback
+    pub ticket: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfficialWeeklyArchiveItem {
+    pub playlist: PlaylistMetadata,
+    pub week_start: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfficialWeeklyReq {
+    /// The Monday of an archived week, the latest week if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub week_start: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfficialWeeklyResp {
+    pub detail: DetailResp,
+    pub week_start: String,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page {
+    pub items: Vec<FollowItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page10 {
+    pub items: Vec<ReviewCommentItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page11 {
+    pub items: Vec<ReviewHistoryItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page12 {
+    pub items: Vec<PostItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page13 {
+    pub items: Vec<SongReportItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page2 {
+    pub items: Vec<NotificationItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page3 {
+    pub items: Vec<PublicSongDetail>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page4 {
+    pub items: Vec<MyLikeItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page5 {
+    pub items: Vec<CommentItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page6 {
+    pub items: Vec<PlaylistMetadata>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page7 {
+    pub items: Vec<FavoritePlaylistItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page8 {
+    pub items: Vec<OfficialWeeklyArchiveItem>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page9 {
+    pub items: Vec<SongPublishReviewBrief>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The whole query of `/song/page_by_user`, [PageByUserReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageByUserQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    pub user_id: i64,
+}
+
+/// The whole query of `/playlist/page_by_user`, [PageByUserReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageByUserQuery2 {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    pub user_id: i64,
+}
+
+/// The whole query of `/song/comment/page`, [PageCommentReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageCommentQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    /// Page the replies of the root comment instead of the root comments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<i64>,
+    pub song_id: i64,
+}
+
+/// The whole query of `/user/followers` and `/user/following`, [PageFollowReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageFollowQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    pub uid: i64,
+}
+
+/// The whole query of `/user/notifications/page`, [PageNotificationReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageNotificationQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unread_only: Option<bool>,
+}
+
+/// The raw paging parameters in the query string, e.g. `?page_index=0&page_size=20`.
+///
+/// `page` and `size` are accepted as aliases for the legacy endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+}
+
+/// The whole query of `/admin/song/report/page`, [PageSongReportsReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageSongReportsQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    /// 0: pending, 1: resolved, 2: dismissed, all if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<i16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageVersionsReq {
+    /// Only the releases of the channel if set
+    /// @since 260503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub page_index: i64,
+    pub page_size: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageVersionsResp {
+    pub data: Vec<LatestVersionResp>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayHistoryItem {
+    /// @deprecated since 260331
+    /// @remove in 260901
+    pub id: i64,
+    pub play_time: String,
+    pub song_info: PublicSongDetail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    pub create_time: String,
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_description: Option<String>,
+    /// The original name and description if they are replaced.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub id: i64,
+    pub is_public: bool,
+    /// The names (title) and descriptions (subtitle) in the other languages, the `name` and `description` are
+    /// replaced by the one preferred by `Accept-Language` in the detail.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    pub name: String,
+    /// The language of the `name` if it's localized, `None` for the original name.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_lang: Option<String>,
+    pub songs_count: i64,
+    /// @since 260122
+    pub update_time: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    pub create_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub id: i64,
+    pub name: String,
+    /// The language of the `name` if it's localized, see [PlaylistItem::name_lang].
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_lang: Option<String>,
+    pub songs_count: i64,
+    pub update_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_avatar_url: Option<String>,
+    pub user_id: i64,
+    pub user_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostIdReq {
+    pub post_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostItem {
+    pub author: PublicUserProfile,
+    pub content: String,
+    pub content_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+    pub create_time: String,
+    /// A deleted post is returned by the detail as a placeholder, without the author and content.
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<bool>,
+    pub id: i64,
+    /// The users mentioned in the content, empty if the content is not returned
+    pub mentions: Vec<MentionEntity>,
+    pub title: String,
+    pub update_time: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreferencesResp {
+    pub blocked_tags: Vec<TagItem>,
+    pub favorite_tags: Vec<TagItem>,
+    /// The favorite tags are inferred from the play history since the user never set them
+    pub inferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignAudioUploadReq {
+    /// `mp3`, `aac` or `flac`
+    pub format: String,
+    /// The hex of the SHA-256 of the file
+    pub sha256: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignAudioUploadResp {
+    pub expire_time: String,
+    /// Send the file as the body of the request, with all the headers
+    pub request: PresignedRequest,
+    /// Confirm with `/song/upload/confirm` after uploading
+    pub upload_id: String,
+}
+
+/// A signed request for the client to send as is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresignedRequest {
+    /// Must be sent along with the request
+    pub headers: Vec<(String, String)>,
+    pub method: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewNotificationReq {
+    /// @since 260505
+    /// The language to render in, the request language if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<Lang>,
+    /// The template name, e.g. `review_rejected`
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewNotificationResp {
+    /// The sample data filled in the template
+    pub data: NotificationTemplate,
+    pub html: String,
+    pub plain: String,
+    pub subject: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductionItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicSongDetail {
+    /// The lower-bitrate renditions of `audio_url` for streaming, empty until they are transcoded.
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_renditions: Option<Vec<AudioRendition>>,
+    pub audio_url: String,
+    pub cover_url: String,
+    /// @since 251102
+    pub create_time: String,
+    pub creation_type: i32,
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_subtitle: Option<String>,
+    /// The original title and subtitle if they are replaced.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_title: Option<String>,
+    pub description: String,
+    pub display_id: String,
+    pub duration_seconds: i32,
+    /// @since 251105
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    pub external_links: Vec<ExternalLink>,
+    /// @since 251105
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain: Option<f32>,
+    pub id: i64,
+    pub like_count: i64,
+    /// The titles in the other languages, the `title` and `subtitle` are replaced by the one preferred by
+    /// `Accept-Language` in the detail and search responses.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    /// The EBU R128 integrated loudness in LUFS, to normalize the volume across the platforms unlike `gain`.
+    /// `None` for the songs not measured yet.
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loudness_lufs: Option<f32>,
+    pub lyrics: String,
+    pub origin_infos: Vec<CreationTypeInfo>,
+    pub play_count: i64,
+    pub production_crew: Vec<SongProductionCrew>,
+    /// @since 251102
+    pub release_time: String,
+    /// The times shared to the other platforms, see [crate::service::song_share]
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_count: Option<i64>,
+    pub subtitle: String,
+    pub tags: Vec<TagItem>,
+    pub title: String,
+    /// The language of the `title` if it's localized, `None` for the original title.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_lang: Option<String>,
+    /// The true peak in dBTP, the limit of the gain applied without clipping
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub true_peak: Option<f32>,
+    pub uploader_name: String,
+    /// The links to support the uploader, see [crate::service::support_link]
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uploader_support_links: Option<Vec<SupportLink>>,
+    pub uploader_uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicUserProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    /// @since 260402
+    pub connected_accounts: Vec<ConnectedAccountItem>,
+    /// @since 260501
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follower_count: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender: Option<i32>,
+    pub is_banned: bool,
+    /// The links to support the user on the donation platforms
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub support_links: Option<Vec<SupportLink>>,
+    pub uid: i64,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishLegalReq {
+    /// Markdown
+    pub content: String,
+    /// `terms` or `privacy`
+    pub kind: String,
+    /// The users must accept it before logging in again
+    pub mandatory: bool,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishLegalResp {
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishReq {
+    /// @since 251114
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// The cover embedded in the audio is used if empty, see [UploadAudioFileResp::cover_temp_id]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_temp_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_info: Option<CreationInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// @since 251105, should be required in new client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_links: Option<Vec<ExternalLink>>,
+    /// @since 251114, should be required in new client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jmid: Option<String>,
+    /// The titles in the other languages.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lyrics: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production_crew: Option<Vec<ProductionItem>>,
+    pub song_temp_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_ids: Option<Vec<i64>>,
+    /// The template exported by `/publish/export_template`, fills the empty fields above.
+    /// @since 260427
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<PublishTemplate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishResp {
+    pub review_id: i64,
+    pub song_display_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishSongPublishReviewData {
+    pub audio_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub cover_url: String,
+    pub creation_type: i32,
+    pub description: String,
+    pub display_id: String,
+    pub duration_seconds: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    pub external_link: Vec<ExternalLink>,
+    /// The titles in the other languages, `None` if they are unchanged by a modification
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    pub lyrics: String,
+    pub origin_infos: Vec<CreationTypeInfo>,
+    pub production_crew: Vec<SongProductionCrew>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_comment: Option<String>,
+    pub review_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_time: Option<String>,
+    /// The published songs with nearly the same title and lyrics, only filled in the detail of the pending
+    /// reviews for the contributors
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similar_songs: Option<Vec<SimilarSong>>,
+    pub status: i32,
+    pub submit_time: String,
+    pub subtitle: String,
+    pub tags: Vec<TagItem>,
+    pub title: String,
+    pub uploader_name: String,
+    pub uploader_uid: i64,
+}
+
+/// The reusable metadata of a publication, which is [PublishReq] without the temp ids, the jmid and the comment.
+///
+/// Exported by `/publish/export_template` and accepted by `/song/publish` to prefill the repeated fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishTemplate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_info: Option<CreationInfo>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_links: Option<Vec<ExternalLink>>,
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lyrics: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub production_crew: Option<Vec<ProductionItem>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_ids: Option<Vec<i64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishVersionReq {
+    pub changelog: String,
+    /// The channel to release in, `stable` if absent
+    /// @since 260503
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub release_time: String,
+    pub url: String,
+    pub variant: String,
+    pub version_name: String,
+    pub version_number: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishVersionResp {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishWeeklySelectionReq {
+    /// The account publishing the playlist instead of `weekly_selection.official_uid`
+    pub official_uid: i64,
+    /// Any day of the week to publish
+    pub week: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishWeeklySelectionResp {
+    /// `None` if the week is published already or there are no songs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub playlist_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrApproveReq {
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrApproveResp {
+    /// The device info of the desktop client being logged in
+    pub device_info: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrCreateReq {
+    pub device_info: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrCreateResp {
+    /// Displayed as the QR code for the mobile app
+    pub code: String,
+    /// Seconds until the session expires
+    pub expires_in: u64,
+    /// Kept by the desktop client for polling, never display it
+    pub poll_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrPollReq {
+    pub code: String,
+    pub poll_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrPollResp {
+    /// `None` if the code is not approved yet, poll again later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login: Option<LoginResp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrScanReq {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QrScanResp {
+    pub create_time: String,
+    /// The device info of the desktop client requesting the login
+    pub device_info: String,
+    /// The IP the desktop client created the code from
+    pub ip: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueData {
+    pub current_index: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub position_ms: u64,
+    pub song_ids: Vec<i64>,
+    pub update_time: String,
+}
+
+/// Since 251102
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentResp {
+    pub songs: Vec<PublicSongDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecommendResp {
+    pub songs: Vec<PublicSongDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecountSongReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecountSongResp {
+    /// The counts before recounting differed from the plays and likes, and are repaired
+    pub drifted: bool,
+    pub like_count: i64,
+    pub old_like_count: i64,
+    pub old_play_count: i64,
+    pub play_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenReq {
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_legal: Option<Vec<LegalAcceptance>>,
+    pub device_info: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectReviewReq {
+    pub comment: String,
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseChannelData {
+    /// `stable`, `beta` or `nightly`, used by `/version/latest` if the client doesn't specify one
+    pub channel: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFavoriteReq {
+    pub playlist_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveFeaturedReq {
+    pub collection: String,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSongReq {
+    pub playlist_id: i64,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoveSongResp {
+    /// The number of songs in the playlist after removing
+    pub songs_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyCommentReq {
+    /// The root comment or a reply in its thread
+    pub comment_id: i64,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportCommentReq {
+    pub comment_id: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSongReq {
+    /// `copyright`, `explicit_content` or `wrong_origin_info`
+    pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSongResp {
+    /// `None` if reported already and not handled yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResetPasswordReq {
+    pub captcha_key: String,
+    pub code: String,
+    pub email: String,
+    pub logout_all_devices: bool,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolveSongReportReq {
+    /// Dismiss the report as invalid instead of resolving it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dismiss: Option<bool>,
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCommentCreateReq {
+    pub content: String,
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCommentCreateResp {
+    pub id: i64,
+    pub mentions: Vec<MentionEntity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCommentDeleteReq {
+    pub comment_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCommentItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<PublicUserProfile>,
+    pub content: String,
+    pub create_time: String,
+    pub id: i64,
+    pub mentions: Vec<MentionEntity>,
+    pub review_id: i64,
+    pub update_time: String,
+}
+
+/// The whole query of `/publish/review/comment/list`, [ReviewCommentListReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewCommentListQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    pub review_id: i64,
+}
+
+/// A piece of the review guidance, see [crate::service::review_guideline]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewGuidelineSnippet {
+    pub archived: bool,
+    pub content: String,
+    pub create_time: String,
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    pub update_by: i64,
+    pub update_time: String,
+    pub version: i32,
+}
+
+/// A snapshot of a snippet, saved on every edit including the first one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewGuidelineSnippetVersion {
+    pub archived: bool,
+    pub content: String,
+    pub create_time: String,
+    pub editor_uid: i64,
+    pub id: i64,
+    pub snippet_id: i64,
+    pub title: String,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewHistoryItem {
+    pub action_type: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<PublicUserProfile>,
+    pub create_time: String,
+    pub id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub review_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<PublishSongPublishReviewData>,
+}
+
+/// The whole query of `/publish/review/history/list`, [ReviewHistoryListReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewHistoryListQuery {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_index: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<i64>,
+    pub review_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewModifyReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_temp_id: Option<String>,
+    pub creation_info: CreationInfo,
+    pub description: String,
+    pub explicit: bool,
+    pub external_links: Vec<ExternalLink>,
+    /// Replace the titles in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    pub lyrics: String,
+    pub production_crew: Vec<ProductionItem>,
+    pub review_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song_temp_id: Option<String>,
+    pub subtitle: String,
+    pub tag_ids: Vec<i64>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleReq {
+    /// e.g. `contributor`
+    pub role: String,
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleResp {
+    /// Whether the roles of the user changed, `false` if they had or didn't have the role already
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveGuidelineReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+    pub content: String,
+    pub slug: String,
+    pub title: String,
+    /// The version being edited, `None` to create the snippet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerJobItem {
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<LastRun>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_time: Option<String>,
+    pub schedule: String,
+    pub timezone: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerJobsResp {
+    pub items: Vec<SchedulerJobItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchFeedbackReq {
+    /// The `query_id` returned by `/song/search`
+    pub query_id: String,
+    /// The clicked hit
+    pub song_id: i64,
+}
+
+/// The clicks at a position summed over the days
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPositionTotal {
+    pub clicks: i64,
+    pub position: i32,
+    pub sort_by: String,
+}
+
+/// The counts of a query summed over the days
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchQueryTotal {
+    pub click_position_sum: i64,
+    pub clicked_searches: i64,
+    pub clicks: i64,
+    pub query: String,
+    pub searches: i64,
+    pub sort_by: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchReq {
+    pub page: u32,
+    pub q: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchReq2 {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub q: String,
+    /// Since 260114
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchReq3 {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    pub q: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResp {
+    pub hits: Vec<PublicUserProfile>,
+    pub limit: u64,
+    pub offset: u64,
+    pub processing_time_ms: u64,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_hits: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResp2 {
+    pub hits: Vec<SearchSongItem>,
+    pub limit: u64,
+    pub offset: u64,
+    pub processing_time_ms: u64,
+    pub query: String,
+    /// Since 260427, report the clicked hits to `/search/feedback` with it. `None` if unavailable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_hits: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResp3 {
+    pub hits: Vec<PlaylistMetadata>,
+    pub limit: u64,
+    pub offset: u64,
+    pub processing_time_ms: u64,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_hits: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSongItem {
+    pub artist: String,
+    /// Deprecated since 260114.
+    /// Use detail endpoint to get audio URL
+    pub audio_url: String,
+    pub cover_art_url: String,
+    pub description: String,
+    pub display_id: String,
+    pub duration_seconds: i32,
+    /// since 251105
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    pub id: i64,
+    pub like_count: i64,
+    /// since 260114
+    pub original_artists: Vec<String>,
+    /// since 260114
+    pub original_titles: Vec<String>,
+    pub play_count: i64,
+    pub subtitle: String,
+    pub title: String,
+    /// The language of the `title` if it's localized, see [PublicSongDetail::title_lang].
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_lang: Option<String>,
+    pub uploader_name: String,
+    pub uploader_uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchStatsReq {
+    /// Since this many days ago, 7 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<i64>,
+    /// The most searched queries, 100 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchStatsResp {
+    /// The clicks by the zero-based position of the hit
+    pub positions: Vec<SearchPositionTotal>,
+    pub queries: Vec<SearchQueryTotal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSuggestReq {
+    /// 5 by default, at most 10
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    pub q: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSuggestResp {
+    pub hits: Vec<SongSuggestion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendVerificationReq {
+    pub email: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerVersion {
+    pub min_version: i32,
+    pub version: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLegalHoldReq {
+    /// `false` to release the hold
+    pub hold: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLegalHoldResp {
+    /// Whether the hold status changed
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetQueueReq {
+    pub current_index: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub position_ms: u64,
+    /// The revision the queue is based on, from `/player/queue/get` or the last save
+    pub revision: i64,
+    pub song_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetQueueResp {
+    pub revision: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetSupportLinksReq {
+    /// Replaces all the links, in the order shown on the profile
+    pub links: Vec<SupportLink>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowBanUserReq {
+    /// The shadow-banned features, see [shadow_ban::FEATURES], empty to lift the shadow ban
+    pub features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub uid: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SharePlatform {
+    #[serde(rename = "qq")]
+    Qq,
+    #[serde(rename = "wechat")]
+    Wechat,
+    #[serde(rename = "weibo")]
+    Weibo,
+    #[serde(rename = "bilibili")]
+    Bilibili,
+    #[serde(rename = "twitter")]
+    Twitter,
+    #[serde(rename = "other")]
+    Other,
+    /// Copied the link
+    #[serde(rename = "link")]
+    Link,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareReq {
+    pub platform: SharePlatform,
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarSong {
+    pub display_id: String,
+    /// The differing bits of the 64-bit hashes, 0 means the same content
+    pub distance: u32,
+    pub song_id: i64,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongItem {
+    pub add_time: String,
+    pub cover_url: String,
+    pub duration_seconds: i32,
+    pub order_index: i32,
+    pub song_display_id: String,
+    pub song_id: i64,
+    pub subtitle: String,
+    pub title: String,
+    pub uploader_name: String,
+    pub uploader_uid: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongProductionCrew {
+    pub id: i64,
+    /// The credited user hasn't confirmed yet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub person_name: Option<String>,
+    pub role: String,
+    pub song_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongPublishReviewBrief {
+    pub artist: String,
+    pub cover_url: String,
+    pub display_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_comment: Option<String>,
+    pub review_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_time: Option<String>,
+    pub status: i32,
+    pub submit_time: String,
+    pub subtitle: String,
+    pub title: String,
+    /// @since 251117
+    pub r#type: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongReportItem {
+    pub category: String,
+    pub create_time: String,
+    pub id: i64,
+    pub reason: String,
+    pub reporter_uid: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution_note: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolve_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<i64>,
+    /// `None` if the song is deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song_display_id: Option<String>,
+    /// Whether the song is hidden by `/admin/song/hide`
+    pub song_hidden: bool,
+    pub song_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub song_title: Option<String>,
+    pub status: i16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongReviewHistoryReq {
+    pub display_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongReviewHistoryResp {
+    /// Oldest first
+    pub rounds: Vec<SongReviewRound>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongReviewRound {
+    /// The comment of the submitter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Oldest first
+    pub comments: Vec<ReviewCommentItem>,
+    /// The reason of the approval or rejection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_comment: Option<String>,
+    pub review_id: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewer: Option<PublicUserProfile>,
+    /// 0: pending, 1: approved, 2: rejected
+    pub status: i32,
+    pub submit_time: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitter: Option<PublicUserProfile>,
+    /// The title submitted in this round, `None` if the data can't be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// 0: create, 1: modify
+    pub r#type: i32,
+}
+
+/// The least of a song for the suggestions while typing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongSuggestion {
+    pub artist: String,
+    pub display_id: String,
+    pub id: i64,
+    pub title: String,
+}
+
+/// Another spelling of the canonical tag, the tag named by the alias is resolved to the canonical one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SongTagAlias {
+    pub alias: String,
+    pub create_time: String,
+    pub id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StarterResp {
+    /// The starter set featured by the contributors, or the weekly hot songs if it's empty
+    pub songs: Vec<PublicSongDetail>,
+    /// The tags to pick the interests from
+    pub tags: Vec<TagItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageUsageResp {
+    pub audio_bytes: i64,
+    pub image_bytes: i64,
+    /// `None` if unlimited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_bytes: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tier: Option<String>,
+    pub used_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitCaptchaReq {
+    pub captcha_key: String,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportLink {
+    /// `afdian`, `patreon`, `kofi`, `paypal` or `github_sponsors`
+    pub platform: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCreateReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCreateResp {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRecommendItem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub id: i64,
+    pub name: String,
+    pub score: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagRecommendResp {
+    pub result: Vec<TagRecommendItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagSearchReq {
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagSearchResp {
+    pub result: Vec<TagItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestEmailsReq {
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestEmailsResp {
+    /// Latest first
+    pub emails: Vec<CapturedEmail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketResp {
+    /// Seconds until it expires if unused
+    pub expires_in: u64,
+    /// Pass it as the `ticket` query of `/events`, it can be used once
+    pub ticket: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub expires_in: String,
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TouchReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorConfirmReq {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorConfirmResp {
+    /// Only returned once, each of them can be used once in place of a TOTP code
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorDisableReq {
+    /// The TOTP or recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorEnrollResp {
+    /// The `otpauth://` URI, displayed as the QR code for the authenticator to scan
+    pub provisioning_uri: String,
+    /// Base32, for entering into the authenticator manually
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlikeReq {
+    pub song_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnlinkAccountReq {
+    pub provider: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadCountResp {
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePlaylistReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub id: i64,
+    pub is_public: bool,
+    /// Replace the names and descriptions in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
+    pub name: String,
+    /// Generate the cover from the first songs, unchanged if absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_song_cover: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateProfileReq {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gender: Option<i32>,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadAudioFileResp {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artist: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<String>,
+    /// The cover embedded in the audio, usable as the `cover_temp_id` of the publishing.
+    /// It's counted in the storage usage only if it's used.
+    /// @since 260505
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_temp_id: Option<String>,
+    pub duration_secs: u64,
+    pub temp_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// A user whose data is kept regardless of the retention, see [crate::service::retention]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserLegalHold {
+    pub create_time: String,
+    pub held_by: i64,
+    pub reason: String,
+    pub user_id: i64,
+}
+
+/// A shadow-banned user, see [crate::service::shadow_ban]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserShadowBan {
+    pub create_time: String,
+    pub features: Vec<String>,
+    pub reason: String,
+    pub update_by: i64,
+    pub update_time: String,
+    pub user_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyChallengeReq {
+    pub challenge_id: String,
+}
+
+pub mod endpoints {
+    use super::*;
+
+    /// `POST /auth/send_email_code`
+    pub struct AuthSendEmailCode;
+
+    impl Endpoint for AuthSendEmailCode {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/send_email_code";
+        type Req = SendVerificationReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/resend_email_code`
+    pub struct AuthResendEmailCode;
+
+    impl Endpoint for AuthResendEmailCode {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/resend_email_code";
+        type Req = SendVerificationReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/register/email`
+    pub struct AuthRegisterEmail;
+
+    impl Endpoint for AuthRegisterEmail {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/register/email";
+        type Req = EmailRegisterReq;
+        type Resp = EmailRegisterResp;
+    }
+
+    /// `POST /auth/login/email`
+    pub struct AuthLoginEmail;
+
+    impl Endpoint for AuthLoginEmail {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/login/email";
+        type Req = LoginReq;
+        type Resp = LoginResp;
+    }
+
+    /// `POST /auth/login/magic_link/request`
+    pub struct AuthLoginMagicLinkRequest;
+
+    impl Endpoint for AuthLoginMagicLinkRequest {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/login/magic_link/request";
+        type Req = MagicLinkRequestReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/login/magic_link/verify`
+    pub struct AuthLoginMagicLinkVerify;
+
+    impl Endpoint for AuthLoginMagicLinkVerify {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/login/magic_link/verify";
+        type Req = MagicLinkVerifyReq;
+        type Resp = LoginResp;
+    }
+
+    /// `GET /auth/oauth/authorize`
+    pub struct AuthOAuthAuthorize;
+
+    impl Endpoint for AuthOAuthAuthorize {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/auth/oauth/authorize";
+        type Req = OAuthAuthorizeReq;
+        type Resp = OAuthAuthorizeResp;
+    }
+
+    /// `POST /auth/oauth/callback`
+    pub struct AuthOAuthCallback;
+
+    impl Endpoint for AuthOAuthCallback {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/oauth/callback";
+        type Req = OAuthCallbackReq;
+        type Resp = OAuthLoginResp;
+    }
+
+    /// `POST /auth/oauth/2fa`
+    pub struct AuthOAuthTwoFactor;
+
+    impl Endpoint for AuthOAuthTwoFactor {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/oauth/2fa";
+        type Req = OAuthTwoFactorReq;
+        type Resp = OAuthLoginResp;
+    }
+
+    /// `POST /auth/2fa/enroll`
+    pub struct AuthTwoFactorEnroll;
+
+    impl Endpoint for AuthTwoFactorEnroll {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/2fa/enroll";
+        type Req = ();
+        type Resp = TwoFactorEnrollResp;
+    }
+
+    /// `POST /auth/2fa/confirm`
+    pub struct AuthTwoFactorConfirm;
+
+    impl Endpoint for AuthTwoFactorConfirm {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/2fa/confirm";
+        type Req = TwoFactorConfirmReq;
+        type Resp = TwoFactorConfirmResp;
+    }
+
+    /// `POST /auth/2fa/disable`
+    pub struct AuthTwoFactorDisable;
+
+    impl Endpoint for AuthTwoFactorDisable {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/2fa/disable";
+        type Req = TwoFactorDisableReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/qr/create`
+    pub struct AuthQrCreate;
+
+    impl Endpoint for AuthQrCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/qr/create";
+        type Req = QrCreateReq;
+        type Resp = QrCreateResp;
+    }
+
+    /// `POST /auth/qr/scan`
+    pub struct AuthQrScan;
+
+    impl Endpoint for AuthQrScan {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/qr/scan";
+        type Req = QrScanReq;
+        type Resp = QrScanResp;
+    }
+
+    /// `POST /auth/qr/approve`
+    pub struct AuthQrApprove;
+
+    impl Endpoint for AuthQrApprove {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/qr/approve";
+        type Req = QrApproveReq;
+        type Resp = QrApproveResp;
+    }
+
+    /// `POST /auth/qr/poll`
+    pub struct AuthQrPoll;
+
+    impl Endpoint for AuthQrPoll {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/qr/poll";
+        type Req = QrPollReq;
+        type Resp = QrPollResp;
+    }
+
+    /// `POST /auth/refresh_token`
+    pub struct AuthRefreshToken;
+
+    impl Endpoint for AuthRefreshToken {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/refresh_token";
+        type Req = RefreshTokenReq;
+        type Resp = TokenPair;
+    }
+
+    /// `POST /auth/reset_password`
+    pub struct AuthResetPassword;
+
+    impl Endpoint for AuthResetPassword {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/reset_password";
+        type Req = ResetPasswordReq;
+        type Resp = ();
+    }
+
+    /// `GET /auth/device/list`
+    pub struct AuthDeviceList;
+
+    impl Endpoint for AuthDeviceList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/auth/device/list";
+        type Req = ();
+        type Resp = DeviceListResp;
+    }
+
+    /// `POST /auth/device/logout`
+    pub struct AuthDeviceLogout;
+
+    impl Endpoint for AuthDeviceLogout {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/device/logout";
+        type Req = DeviceLogoutReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/device/rename`
+    pub struct AuthDeviceRename;
+
+    impl Endpoint for AuthDeviceRename {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/device/rename";
+        type Req = DeviceRenameReq;
+        type Resp = ();
+    }
+
+    /// `POST /auth/device/trust`
+    pub struct AuthDeviceTrust;
+
+    impl Endpoint for AuthDeviceTrust {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/device/trust";
+        type Req = DeviceTrustReq;
+        type Resp = DeviceTrustResp;
+    }
+
+    /// `POST /auth/device/untrust`
+    pub struct AuthDeviceUntrust;
+
+    impl Endpoint for AuthDeviceUntrust {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/device/untrust";
+        type Req = DeviceLogoutReq;
+        type Resp = ();
+    }
+
+    /// `GET /auth/captcha/generate`
+    pub struct AuthCaptchaGenerate;
+
+    impl Endpoint for AuthCaptchaGenerate {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/auth/captcha/generate";
+        type Req = ();
+        type Resp = GenerateCaptchaResp;
+    }
+
+    /// `POST /auth/captcha/submit`
+    pub struct AuthCaptchaSubmit;
+
+    impl Endpoint for AuthCaptchaSubmit {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/auth/captcha/submit";
+        type Req = SubmitCaptchaReq;
+        type Resp = ();
+    }
+
+    /// `GET /auth/protected`
+    pub struct AuthProtected;
+
+    impl Endpoint for AuthProtected {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/auth/protected";
+        type Req = ();
+        type Resp = ();
+    }
+
+    /// `GET /user/profile`
+    pub struct UserProfile;
+
+    impl Endpoint for UserProfile {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/profile";
+        type Req = GetProfileReq;
+        type Resp = PublicUserProfile;
+    }
+
+    /// `GET /user/greet`
+    pub struct UserGreet;
+
+    impl Endpoint for UserGreet {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/greet";
+        type Req = ();
+        type Resp = String;
+    }
+
+    /// `POST /user/update_profile`
+    pub struct UserUpdateProfile;
+
+    impl Endpoint for UserUpdateProfile {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/update_profile";
+        type Req = UpdateProfileReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/search`
+    pub struct UserSearch;
+
+    impl Endpoint for UserSearch {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/search";
+        type Req = SearchReq;
+        type Resp = SearchResp;
+    }
+
+    /// `GET /user/linked_accounts`
+    pub struct UserLinkedAccounts;
+
+    impl Endpoint for UserLinkedAccounts {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/linked_accounts";
+        type Req = ();
+        type Resp = LinkedAccountsResp;
+    }
+
+    /// `POST /user/unlink_account`
+    pub struct UserUnlinkAccount;
+
+    impl Endpoint for UserUnlinkAccount {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/unlink_account";
+        type Req = UnlinkAccountReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/link_account/authorize`
+    pub struct UserLinkAccountAuthorize;
+
+    impl Endpoint for UserLinkAccountAuthorize {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/link_account/authorize";
+        type Req = OAuthAuthorizeReq;
+        type Resp = OAuthAuthorizeResp;
+    }
+
+    /// `POST /user/link_account`
+    pub struct UserLinkAccount;
+
+    impl Endpoint for UserLinkAccount {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/link_account";
+        type Req = LinkAccountReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/storage_usage`
+    pub struct UserStorageUsage;
+
+    impl Endpoint for UserStorageUsage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/storage_usage";
+        type Req = ();
+        type Resp = StorageUsageResp;
+    }
+
+    /// `POST /user/follow`
+    pub struct UserFollow;
+
+    impl Endpoint for UserFollow {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/follow";
+        type Req = FollowReq;
+        type Resp = ();
+    }
+
+    /// `POST /user/unfollow`
+    pub struct UserUnfollow;
+
+    impl Endpoint for UserUnfollow {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/unfollow";
+        type Req = FollowReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/followers`
+    pub struct UserFollowers;
+
+    impl Endpoint for UserFollowers {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/followers";
+        type Req = PageFollowQuery;
+        type Resp = Page;
+    }
+
+    /// `GET /user/following`
+    pub struct UserFollowing;
+
+    impl Endpoint for UserFollowing {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/following";
+        type Req = PageFollowQuery;
+        type Resp = Page;
+    }
+
+    /// `GET /user/release_channel`
+    pub struct UserReleaseChannel;
+
+    impl Endpoint for UserReleaseChannel {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/release_channel";
+        type Req = ();
+        type Resp = ReleaseChannelData;
+    }
+
+    /// `POST /user/set_release_channel`
+    pub struct UserSetReleaseChannel;
+
+    impl Endpoint for UserSetReleaseChannel {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/set_release_channel";
+        type Req = ReleaseChannelData;
+        type Resp = ();
+    }
+
+    /// `GET /user/language`
+    pub struct UserLanguage;
+
+    impl Endpoint for UserLanguage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/language";
+        type Req = ();
+        type Resp = LanguageData;
+    }
+
+    /// `POST /user/set_language`
+    pub struct UserSetLanguage;
+
+    impl Endpoint for UserSetLanguage {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/set_language";
+        type Req = LanguageData;
+        type Resp = ();
+    }
+
+    /// `POST /user/set_support_links`
+    pub struct UserSetSupportLinks;
+
+    impl Endpoint for UserSetSupportLinks {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/set_support_links";
+        type Req = SetSupportLinksReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/preferences`
+    pub struct UserPreferences;
+
+    impl Endpoint for UserPreferences {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/preferences";
+        type Req = ();
+        type Resp = PreferencesResp;
+    }
+
+    /// `GET /user/connection/list`
+    pub struct UserConnectionList;
+
+    impl Endpoint for UserConnectionList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/connection/list";
+        type Req = ();
+        type Resp = ConnectionResp;
+    }
+
+    /// `POST /user/connection/unlink`
+    pub struct UserConnectionUnlink;
+
+    impl Endpoint for UserConnectionUnlink {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/connection/unlink";
+        type Req = ConnectionUnlinkReq;
+        type Resp = ();
+    }
+
+    /// `POST /user/connection/set_visibility`
+    pub struct UserConnectionSetVisibility;
+
+    impl Endpoint for UserConnectionSetVisibility {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/connection/set_visibility";
+        type Req = ConnectionSetVisibilityReq;
+        type Resp = ();
+    }
+
+    /// `POST /user/connection/sync`
+    pub struct UserConnectionSync;
+
+    impl Endpoint for UserConnectionSync {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/connection/sync";
+        type Req = ConnectionSyncReq;
+        type Resp = ();
+    }
+
+    /// `POST /user/connection/generate_challenge`
+    pub struct UserConnectionGenerateChallenge;
+
+    impl Endpoint for UserConnectionGenerateChallenge {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/connection/generate_challenge";
+        type Req = GenerateChallengeReq;
+        type Resp = GenerateChallengeResp;
+    }
+
+    /// `POST /user/connection/verify_challenge`
+    pub struct UserConnectionVerifyChallenge;
+
+    impl Endpoint for UserConnectionVerifyChallenge {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/connection/verify_challenge";
+        type Req = VerifyChallengeReq;
+        type Resp = ();
+    }
+
+    /// `GET /user/notifications/page`
+    pub struct UserNotificationPage;
+
+    impl Endpoint for UserNotificationPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/notifications/page";
+        type Req = PageNotificationQuery;
+        type Resp = Page2;
+    }
+
+    /// `POST /user/notifications/mark_read`
+    pub struct UserNotificationMarkRead;
+
+    impl Endpoint for UserNotificationMarkRead {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/user/notifications/mark_read";
+        type Req = MarkReadReq;
+        type Resp = MarkReadResp;
+    }
+
+    /// `GET /user/notifications/unread_count`
+    pub struct UserNotificationUnreadCount;
+
+    impl Endpoint for UserNotificationUnreadCount {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/user/notifications/unread_count";
+        type Req = ();
+        type Resp = UnreadCountResp;
+    }
+
+    /// `POST /events/ticket`
+    pub struct EventsTicket;
+
+    impl Endpoint for EventsTicket {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/events/ticket";
+        type Req = ();
+        type Resp = TicketResp;
+    }
+
+    /// `GET /onboarding/starter`
+    pub struct OnboardingStarter;
+
+    impl Endpoint for OnboardingStarter {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/onboarding/starter";
+        type Req = ();
+        type Resp = StarterResp;
+    }
+
+    /// `POST /onboarding/interests`
+    pub struct OnboardingInterests;
+
+    impl Endpoint for OnboardingInterests {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/onboarding/interests";
+        type Req = InterestsReq;
+        type Resp = InterestsResp;
+    }
+
+    /// `GET /song/detail`
+    pub struct SongDetail;
+
+    impl Endpoint for SongDetail {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/detail";
+        type Req = DetailReq;
+        type Resp = PublicSongDetail;
+    }
+
+    /// `GET /song/detail_by_id`
+    pub struct SongDetailById;
+
+    impl Endpoint for SongDetailById {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/detail_by_id";
+        type Req = DetailByIdReq;
+        type Resp = PublicSongDetail;
+    }
+
+    /// `GET /song/page_by_user`
+    pub struct SongPageByUser;
+
+    impl Endpoint for SongPageByUser {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/page_by_user";
+        type Req = PageByUserQuery;
+        type Resp = Page3;
+    }
+
+    /// `POST /song/publish`
+    pub struct SongPublish;
+
+    impl Endpoint for SongPublish {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/publish";
+        type Req = PublishReq;
+        type Resp = PublishResp;
+    }
+
+    /// `GET /song/review_history`
+    pub struct SongReviewHistory;
+
+    impl Endpoint for SongReviewHistory {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/review_history";
+        type Req = SongReviewHistoryReq;
+        type Resp = SongReviewHistoryResp;
+    }
+
+    /// `POST /song/play`
+    pub struct SongPlay;
+
+    impl Endpoint for SongPlay {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/play";
+        type Req = TouchReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/share`
+    pub struct SongShare;
+
+    impl Endpoint for SongShare {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/share";
+        type Req = ShareReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/report`
+    pub struct SongReport;
+
+    impl Endpoint for SongReport {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/report";
+        type Req = ReportSongReq;
+        type Resp = ReportSongResp;
+    }
+
+    /// `POST /song/delete`
+    pub struct SongDelete;
+
+    impl Endpoint for SongDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/delete";
+        type Req = DeleteReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/likes/like`
+    pub struct SongLikesLike;
+
+    impl Endpoint for SongLikesLike {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/likes/like";
+        type Req = LikeReq;
+        type Resp = LikeResp;
+    }
+
+    /// `POST /song/likes/unlike`
+    pub struct SongLikesUnlike;
+
+    impl Endpoint for SongLikesUnlike {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/likes/unlike";
+        type Req = UnlikeReq;
+        type Resp = LikeResp;
+    }
+
+    /// `GET /song/likes/status`
+    pub struct SongLikesStatus;
+
+    impl Endpoint for SongLikesStatus {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/likes/status";
+        type Req = LikeStatusReq;
+        type Resp = LikeStatusResp;
+    }
+
+    /// `GET /song/likes/page_my_likes`
+    pub struct SongLikesPageMyLikes;
+
+    impl Endpoint for SongLikesPageMyLikes {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/likes/page_my_likes";
+        type Req = PageQuery;
+        type Resp = Page4;
+    }
+
+    /// `GET /play_history/cursor`
+    pub struct PlayHistoryCursor;
+
+    impl Endpoint for PlayHistoryCursor {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/play_history/cursor";
+        type Req = CursorQuery;
+        type Resp = CursorPage;
+    }
+
+    /// `POST /play_history/touch`
+    pub struct PlayHistoryTouch;
+
+    impl Endpoint for PlayHistoryTouch {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/play_history/touch";
+        type Req = TouchReq;
+        type Resp = ();
+    }
+
+    /// `POST /play_history/touch_anonymous`
+    pub struct PlayHistoryTouchAnonymous;
+
+    impl Endpoint for PlayHistoryTouchAnonymous {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/play_history/touch_anonymous";
+        type Req = TouchReq;
+        type Resp = ();
+    }
+
+    /// `POST /play_history/delete`
+    pub struct PlayHistoryDelete;
+
+    impl Endpoint for PlayHistoryDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/play_history/delete";
+        type Req = DeleteReq2;
+        type Resp = ();
+    }
+
+    /// `GET /player/queue/get`
+    pub struct PlayerQueueGet;
+
+    impl Endpoint for PlayerQueueGet {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/player/queue/get";
+        type Req = ();
+        type Resp = GetQueueResp;
+    }
+
+    /// `POST /player/queue/set`
+    pub struct PlayerQueueSet;
+
+    impl Endpoint for PlayerQueueSet {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/player/queue/set";
+        type Req = SetQueueReq;
+        type Resp = SetQueueResp;
+    }
+
+    /// `GET /legal/latest`
+    pub struct LegalLatest;
+
+    impl Endpoint for LegalLatest {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/legal/latest";
+        type Req = ();
+        type Resp = LatestResp;
+    }
+
+    /// `POST /legal/accept`
+    pub struct LegalAccept;
+
+    impl Endpoint for LegalAccept {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/legal/accept";
+        type Req = AcceptReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/upload/presign`
+    pub struct SongUploadPresign;
+
+    impl Endpoint for SongUploadPresign {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/upload/presign";
+        type Req = PresignAudioUploadReq;
+        type Resp = PresignAudioUploadResp;
+    }
+
+    /// `POST /song/upload/confirm`
+    pub struct SongUploadConfirm;
+
+    impl Endpoint for SongUploadConfirm {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/upload/confirm";
+        type Req = ConfirmAudioUploadReq;
+        type Resp = UploadAudioFileResp;
+    }
+
+    /// `POST /song/comment/create`
+    pub struct SongCommentCreate;
+
+    impl Endpoint for SongCommentCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/comment/create";
+        type Req = CreateCommentReq;
+        type Resp = CreateCommentResp;
+    }
+
+    /// `POST /song/comment/reply`
+    pub struct SongCommentReply;
+
+    impl Endpoint for SongCommentReply {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/comment/reply";
+        type Req = ReplyCommentReq;
+        type Resp = CreateCommentResp;
+    }
+
+    /// `POST /song/comment/delete`
+    pub struct SongCommentDelete;
+
+    impl Endpoint for SongCommentDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/comment/delete";
+        type Req = CommentIdReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/comment/report`
+    pub struct SongCommentReport;
+
+    impl Endpoint for SongCommentReport {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/comment/report";
+        type Req = ReportCommentReq;
+        type Resp = ();
+    }
+
+    /// `GET /song/comment/page`
+    pub struct SongCommentPage;
+
+    impl Endpoint for SongCommentPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/comment/page";
+        type Req = PageCommentQuery;
+        type Resp = Page5;
+    }
+
+    /// `GET /song/recent_v2`
+    pub struct SongRecentV2;
+
+    impl Endpoint for SongRecentV2 {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/recent_v2";
+        type Req = RecentReq;
+        type Resp = RecentResp;
+    }
+
+    /// `GET /song/recommend`
+    pub struct SongRecommend;
+
+    impl Endpoint for SongRecommend {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/recommend";
+        type Req = ();
+        type Resp = RecommendResp;
+    }
+
+    /// `GET /song/recommend_anonymous`
+    pub struct SongRecommendAnonymous;
+
+    impl Endpoint for SongRecommendAnonymous {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/recommend_anonymous";
+        type Req = ();
+        type Resp = RecommendResp;
+    }
+
+    /// `GET /song/hot/weekly`
+    pub struct SongHotWeekly;
+
+    impl Endpoint for SongHotWeekly {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/hot/weekly";
+        type Req = ();
+        type Resp = HotResp;
+    }
+
+    /// `GET /song/search`
+    pub struct SongSearch;
+
+    impl Endpoint for SongSearch {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/search";
+        type Req = SearchReq2;
+        type Resp = SearchResp2;
+    }
+
+    /// `GET /song/search/suggest`
+    pub struct SongSearchSuggest;
+
+    impl Endpoint for SongSearchSuggest {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/search/suggest";
+        type Req = SearchSuggestReq;
+        type Resp = SearchSuggestResp;
+    }
+
+    /// `POST /search/feedback`
+    pub struct SearchFeedback;
+
+    impl Endpoint for SearchFeedback {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/search/feedback";
+        type Req = SearchFeedbackReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/not_interested`
+    pub struct SongNotInterested;
+
+    impl Endpoint for SongNotInterested {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/not_interested";
+        type Req = NotInterestedReq;
+        type Resp = ();
+    }
+
+    /// `POST /song/tag/create`
+    pub struct SongTagCreate;
+
+    impl Endpoint for SongTagCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/song/tag/create";
+        type Req = TagCreateReq;
+        type Resp = TagCreateResp;
+    }
+
+    /// `GET /song/tag/search`
+    pub struct SongTagSearch;
+
+    impl Endpoint for SongTagSearch {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/tag/search";
+        type Req = TagSearchReq;
+        type Resp = TagSearchResp;
+    }
+
+    /// `GET /song/tag/recommend`
+    pub struct SongTagRecommend;
+
+    impl Endpoint for SongTagRecommend {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/tag/recommend";
+        type Req = ();
+        type Resp = TagRecommendResp;
+    }
+
+    /// `GET /song/tag/recommend_anonymous`
+    pub struct SongTagRecommendAnonymous;
+
+    impl Endpoint for SongTagRecommendAnonymous {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/song/tag/recommend_anonymous";
+        type Req = ();
+        type Resp = TagRecommendResp;
+    }
+
+    /// `POST /playlist/create`
+    pub struct PlaylistCreate;
+
+    impl Endpoint for PlaylistCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/create";
+        type Req = CreatePlaylistReq;
+        type Resp = CreatePlaylistResp;
+    }
+
+    /// `GET /playlist/list`
+    pub struct PlaylistList;
+
+    impl Endpoint for PlaylistList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/list";
+        type Req = ();
+        type Resp = ListResp;
+    }
+
+    /// `GET /playlist/detail`
+    pub struct PlaylistDetail;
+
+    impl Endpoint for PlaylistDetail {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/detail";
+        type Req = DetailReq2;
+        type Resp = DetailResp;
+    }
+
+    /// `GET /playlist/detail_private`
+    pub struct PlaylistDetailPrivate;
+
+    impl Endpoint for PlaylistDetailPrivate {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/detail_private";
+        type Req = DetailReq2;
+        type Resp = DetailResp;
+    }
+
+    /// `GET /playlist/detail_public`
+    pub struct PlaylistDetailPublic;
+
+    impl Endpoint for PlaylistDetailPublic {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/detail_public";
+        type Req = DetailReq2;
+        type Resp = DetailResp;
+    }
+
+    /// `GET /playlist/list_public_by_user`
+    pub struct PlaylistListPublicByUser;
+
+    impl Endpoint for PlaylistListPublicByUser {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/list_public_by_user";
+        type Req = ListPublicByUserReq;
+        type Resp = ListPublicByUserResp;
+    }
+
+    /// `GET /playlist/page_by_user`
+    pub struct PlaylistPageByUser;
+
+    impl Endpoint for PlaylistPageByUser {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/page_by_user";
+        type Req = PageByUserQuery2;
+        type Resp = Page6;
+    }
+
+    /// `POST /playlist/update`
+    pub struct PlaylistUpdate;
+
+    impl Endpoint for PlaylistUpdate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/update";
+        type Req = UpdatePlaylistReq;
+        type Resp = ();
+    }
+
+    /// `POST /playlist/delete`
+    pub struct PlaylistDelete;
+
+    impl Endpoint for PlaylistDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/delete";
+        type Req = DeletePlaylistReq;
+        type Resp = ();
+    }
+
+    /// `GET /playlist/list_containing`
+    pub struct PlaylistListContaining;
+
+    impl Endpoint for PlaylistListContaining {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/list_containing";
+        type Req = ListContainingReq;
+        type Resp = ListContainingResp;
+    }
+
+    /// `POST /playlist/add_song`
+    pub struct PlaylistAddSong;
+
+    impl Endpoint for PlaylistAddSong {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/add_song";
+        type Req = AddSongReq;
+        type Resp = AddSongResp;
+    }
+
+    /// `POST /playlist/add_songs`
+    pub struct PlaylistAddSongs;
+
+    impl Endpoint for PlaylistAddSongs {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/add_songs";
+        type Req = AddSongsReq;
+        type Resp = AddSongsResp;
+    }
+
+    /// `POST /playlist/remove_song`
+    pub struct PlaylistRemoveSong;
+
+    impl Endpoint for PlaylistRemoveSong {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/remove_song";
+        type Req = RemoveSongReq;
+        type Resp = RemoveSongResp;
+    }
+
+    /// `POST /playlist/change_order`
+    pub struct PlaylistChangeOrder;
+
+    impl Endpoint for PlaylistChangeOrder {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/change_order";
+        type Req = ChangeOrderReq;
+        type Resp = ();
+    }
+
+    /// `GET /playlist/search`
+    pub struct PlaylistSearch;
+
+    impl Endpoint for PlaylistSearch {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/search";
+        type Req = SearchReq3;
+        type Resp = SearchResp3;
+    }
+
+    /// `POST /playlist/favorite/add`
+    pub struct PlaylistFavoriteAdd;
+
+    impl Endpoint for PlaylistFavoriteAdd {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/favorite/add";
+        type Req = AddFavoriteReq;
+        type Resp = ();
+    }
+
+    /// `POST /playlist/favorite/remove`
+    pub struct PlaylistFavoriteRemove;
+
+    impl Endpoint for PlaylistFavoriteRemove {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/playlist/favorite/remove";
+        type Req = RemoveFavoriteReq;
+        type Resp = ();
+    }
+
+    /// `GET /playlist/favorite/check`
+    pub struct PlaylistFavoriteCheck;
+
+    impl Endpoint for PlaylistFavoriteCheck {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/favorite/check";
+        type Req = CheckFavoriteReq;
+        type Resp = CheckFavoriteResp;
+    }
+
+    /// `GET /playlist/favorite/page`
+    pub struct PlaylistFavoritePage;
+
+    impl Endpoint for PlaylistFavoritePage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/favorite/page";
+        type Req = PageQuery;
+        type Resp = Page7;
+    }
+
+    /// `GET /playlist/official/weekly`
+    pub struct PlaylistOfficialWeekly;
+
+    impl Endpoint for PlaylistOfficialWeekly {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/official/weekly";
+        type Req = OfficialWeeklyReq;
+        type Resp = OfficialWeeklyResp;
+    }
+
+    /// `GET /playlist/official/weekly/archive`
+    pub struct PlaylistOfficialWeeklyArchive;
+
+    impl Endpoint for PlaylistOfficialWeeklyArchive {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/playlist/official/weekly/archive";
+        type Req = PageQuery;
+        type Resp = Page8;
+    }
+
+    /// `GET /publish/jmid/check_prefix`
+    pub struct PublishJmidCheckPrefix;
+
+    impl Endpoint for PublishJmidCheckPrefix {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/jmid/check_prefix";
+        type Req = JmidCheckPReq;
+        type Resp = JmidCheckPResp;
+    }
+
+    /// `GET /publish/jmid/get_next`
+    pub struct PublishJmidGetNext;
+
+    impl Endpoint for PublishJmidGetNext {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/jmid/get_next";
+        type Req = ();
+        type Resp = JmidGetNextResp;
+    }
+
+    /// `GET /publish/jmid/check`
+    pub struct PublishJmidCheck;
+
+    impl Endpoint for PublishJmidCheck {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/jmid/check";
+        type Req = JmidCheckReq;
+        type Resp = JmidCheckResp;
+    }
+
+    /// `GET /publish/jmid/mine`
+    pub struct PublishJmidMine;
+
+    impl Endpoint for PublishJmidMine {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/jmid/mine";
+        type Req = ();
+        type Resp = JmidMineResp;
+    }
+
+    /// `POST /publish/publish`
+    pub struct PublishPublish;
+
+    impl Endpoint for PublishPublish {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/publish";
+        type Req = PublishReq;
+        type Resp = PublishResp;
+    }
+
+    /// `POST /publish/modify`
+    pub struct PublishModify;
+
+    impl Endpoint for PublishModify {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/modify";
+        type Req = ModifyReq;
+        type Resp = ModifyResp;
+    }
+
+    /// `POST /publish/delete`
+    pub struct PublishDelete;
+
+    impl Endpoint for PublishDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/delete";
+        type Req = DeleteReq;
+        type Resp = ();
+    }
+
+    /// `POST /publish/change_jmid`
+    pub struct PublishChangeJmid;
+
+    impl Endpoint for PublishChangeJmid {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/change_jmid";
+        type Req = ChangeJmidReq;
+        type Resp = ();
+    }
+
+    /// `GET /publish/export_template`
+    pub struct PublishExportTemplate;
+
+    impl Endpoint for PublishExportTemplate {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/export_template";
+        type Req = ExportTemplateReq;
+        type Resp = PublishTemplate;
+    }
+
+    /// `POST /publish/upload_audio_chunk/create`
+    pub struct PublishAudioChunkCreate;
+
+    impl Endpoint for PublishAudioChunkCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/upload_audio_chunk/create";
+        type Req = CreateChunkedUploadReq;
+        type Resp = CreateChunkedUploadResp;
+    }
+
+    /// `GET /publish/upload_audio_chunk/status`
+    pub struct PublishAudioChunkStatus;
+
+    impl Endpoint for PublishAudioChunkStatus {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/upload_audio_chunk/status";
+        type Req = ChunkedUploadReq;
+        type Resp = ChunkedUploadStatusResp;
+    }
+
+    /// `POST /publish/upload_audio_chunk/complete`
+    pub struct PublishAudioChunkComplete;
+
+    impl Endpoint for PublishAudioChunkComplete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/upload_audio_chunk/complete";
+        type Req = ChunkedUploadReq;
+        type Resp = UploadAudioFileResp;
+    }
+
+    /// `POST /publish/upload_audio_chunk/abort`
+    pub struct PublishAudioChunkAbort;
+
+    impl Endpoint for PublishAudioChunkAbort {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/upload_audio_chunk/abort";
+        type Req = ChunkedUploadReq;
+        type Resp = ();
+    }
+
+    /// `GET /publish/review/dashboard`
+    pub struct PublishReviewDashboard;
+
+    impl Endpoint for PublishReviewDashboard {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/dashboard";
+        type Req = ();
+        type Resp = DashboardResp;
+    }
+
+    /// `GET /publish/review/page`
+    pub struct PublishReviewPage;
+
+    impl Endpoint for PublishReviewPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/page";
+        type Req = PageQuery;
+        type Resp = Page9;
+    }
+
+    /// `GET /publish/review/page_contributor`
+    pub struct PublishReviewPageContributor;
+
+    impl Endpoint for PublishReviewPageContributor {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/page_contributor";
+        type Req = PageQuery;
+        type Resp = Page9;
+    }
+
+    /// `GET /publish/review/cursor_contributor`
+    pub struct PublishReviewCursorContributor;
+
+    impl Endpoint for PublishReviewCursorContributor {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/cursor_contributor";
+        type Req = CursorContributorQuery;
+        type Resp = CursorPage2;
+    }
+
+    /// `GET /publish/review/detail`
+    pub struct PublishReviewDetail;
+
+    impl Endpoint for PublishReviewDetail {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/detail";
+        type Req = DetailReq3;
+        type Resp = PublishSongPublishReviewData;
+    }
+
+    /// `GET /publish/review/guideline/list`
+    pub struct PublishReviewGuidelineList;
+
+    impl Endpoint for PublishReviewGuidelineList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/guideline/list";
+        type Req = ();
+        type Resp = GuidelineListResp;
+    }
+
+    /// `POST /publish/review/approve`
+    pub struct PublishReviewApprove;
+
+    impl Endpoint for PublishReviewApprove {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/review/approve";
+        type Req = ApproveReviewReq;
+        type Resp = ();
+    }
+
+    /// `POST /publish/review/reject`
+    pub struct PublishReviewReject;
+
+    impl Endpoint for PublishReviewReject {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/review/reject";
+        type Req = RejectReviewReq;
+        type Resp = ();
+    }
+
+    /// `POST /publish/review/modify`
+    pub struct PublishReviewModify;
+
+    impl Endpoint for PublishReviewModify {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/review/modify";
+        type Req = ReviewModifyReq;
+        type Resp = ();
+    }
+
+    /// `POST /publish/review/comment/create`
+    pub struct PublishReviewCommentCreate;
+
+    impl Endpoint for PublishReviewCommentCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/review/comment/create";
+        type Req = ReviewCommentCreateReq;
+        type Resp = ReviewCommentCreateResp;
+    }
+
+    /// `POST /publish/review/comment/delete`
+    pub struct PublishReviewCommentDelete;
+
+    impl Endpoint for PublishReviewCommentDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/review/comment/delete";
+        type Req = ReviewCommentDeleteReq;
+        type Resp = ();
+    }
+
+    /// `GET /publish/review/comment/list`
+    pub struct PublishReviewCommentList;
+
+    impl Endpoint for PublishReviewCommentList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/comment/list";
+        type Req = ReviewCommentListQuery;
+        type Resp = Page10;
+    }
+
+    /// `GET /publish/review/history/list`
+    pub struct PublishReviewHistoryList;
+
+    impl Endpoint for PublishReviewHistoryList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/review/history/list";
+        type Req = ReviewHistoryListQuery;
+        type Resp = Page11;
+    }
+
+    /// `GET /publish/crew/invitations`
+    pub struct PublishCrewInvitations;
+
+    impl Endpoint for PublishCrewInvitations {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/publish/crew/invitations";
+        type Req = ();
+        type Resp = CrewInvitationsResp;
+    }
+
+    /// `POST /publish/crew/confirm`
+    pub struct PublishCrewConfirm;
+
+    impl Endpoint for PublishCrewConfirm {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/crew/confirm";
+        type Req = CrewRespondReq;
+        type Resp = ();
+    }
+
+    /// `POST /publish/crew/decline`
+    pub struct PublishCrewDecline;
+
+    impl Endpoint for PublishCrewDecline {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/publish/crew/decline";
+        type Req = CrewRespondReq;
+        type Resp = ();
+    }
+
+    /// `GET /post/page`
+    pub struct PostPage;
+
+    impl Endpoint for PostPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/post/page";
+        type Req = PageQuery;
+        type Resp = Page12;
+    }
+
+    /// `GET /post/detail`
+    pub struct PostDetail;
+
+    impl Endpoint for PostDetail {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/post/detail";
+        type Req = PostIdReq;
+        type Resp = PostItem;
+    }
+
+    /// `POST /post/create`
+    pub struct PostCreate;
+
+    impl Endpoint for PostCreate {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/post/create";
+        type Req = CreateReq;
+        type Resp = CreateResp;
+    }
+
+    /// `POST /post/edit`
+    pub struct PostEdit;
+
+    impl Endpoint for PostEdit {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/post/edit";
+        type Req = EditReq;
+        type Resp = EditResp;
+    }
+
+    /// `POST /post/delete`
+    pub struct PostDelete;
+
+    impl Endpoint for PostDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/post/delete";
+        type Req = PostIdReq;
+        type Resp = ();
+    }
+
+    /// `GET /contributor/notification/preview`
+    pub struct ContributorNotificationPreview;
+
+    impl Endpoint for ContributorNotificationPreview {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/contributor/notification/preview";
+        type Req = PreviewNotificationReq;
+        type Resp = PreviewNotificationResp;
+    }
+
+    /// `GET /contributor/check`
+    pub struct ContributorCheck;
+
+    impl Endpoint for ContributorCheck {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/contributor/check";
+        type Req = ();
+        type Resp = CheckContributorResp;
+    }
+
+    /// `POST /admin/user/ban`
+    pub struct AdminUserBan;
+
+    impl Endpoint for AdminUserBan {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/user/ban";
+        type Req = BanUserReq;
+        type Resp = ();
+    }
+
+    /// `POST /admin/user/unban`
+    pub struct AdminUserUnban;
+
+    impl Endpoint for AdminUserUnban {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/user/unban";
+        type Req = BanUserReq;
+        type Resp = ();
+    }
+
+    /// `POST /admin/user/shadow_ban`
+    pub struct AdminUserShadowBan;
+
+    impl Endpoint for AdminUserShadowBan {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/user/shadow_ban";
+        type Req = ShadowBanUserReq;
+        type Resp = ();
+    }
+
+    /// `GET /admin/user/shadow_ban/list`
+    pub struct AdminUserShadowBanList;
+
+    impl Endpoint for AdminUserShadowBanList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/user/shadow_ban/list";
+        type Req = ();
+        type Resp = ListShadowBansResp;
+    }
+
+    /// `POST /admin/user/legal_hold`
+    pub struct AdminUserLegalHold;
+
+    impl Endpoint for AdminUserLegalHold {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/user/legal_hold";
+        type Req = SetLegalHoldReq;
+        type Resp = SetLegalHoldResp;
+    }
+
+    /// `GET /admin/user/legal_hold/list`
+    pub struct AdminUserLegalHoldList;
+
+    impl Endpoint for AdminUserLegalHoldList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/user/legal_hold/list";
+        type Req = ();
+        type Resp = ListLegalHoldsResp;
+    }
+
+    /// `POST /admin/song/edit`
+    pub struct AdminSongEdit;
+
+    impl Endpoint for AdminSongEdit {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/song/edit";
+        type Req = EditSongReq;
+        type Resp = ();
+    }
+
+    /// `POST /admin/song/recount`
+    pub struct AdminSongRecount;
+
+    impl Endpoint for AdminSongRecount {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/song/recount";
+        type Req = RecountSongReq;
+        type Resp = RecountSongResp;
+    }
+
+    /// `POST /admin/song/hide`
+    pub struct AdminSongHide;
+
+    impl Endpoint for AdminSongHide {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/song/hide";
+        type Req = HideSongReq;
+        type Resp = HideSongResp;
+    }
+
+    /// `GET /admin/song/report/page`
+    pub struct AdminSongReportPage;
+
+    impl Endpoint for AdminSongReportPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/song/report/page";
+        type Req = PageSongReportsQuery;
+        type Resp = Page13;
+    }
+
+    /// `POST /admin/song/report/resolve`
+    pub struct AdminSongReportResolve;
+
+    impl Endpoint for AdminSongReportResolve {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/song/report/resolve";
+        type Req = ResolveSongReportReq;
+        type Resp = ();
+    }
+
+    /// `GET /admin/featured/list`
+    pub struct AdminFeaturedList;
+
+    impl Endpoint for AdminFeaturedList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/featured/list";
+        type Req = ListFeaturedReq;
+        type Resp = ListFeaturedResp;
+    }
+
+    /// `POST /admin/featured/add`
+    pub struct AdminFeaturedAdd;
+
+    impl Endpoint for AdminFeaturedAdd {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/featured/add";
+        type Req = AddFeaturedReq;
+        type Resp = ();
+    }
+
+    /// `POST /admin/featured/remove`
+    pub struct AdminFeaturedRemove;
+
+    impl Endpoint for AdminFeaturedRemove {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/featured/remove";
+        type Req = RemoveFeaturedReq;
+        type Resp = ();
+    }
+
+    /// `GET /admin/search/stats`
+    pub struct AdminSearchStats;
+
+    impl Endpoint for AdminSearchStats {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/search/stats";
+        type Req = SearchStatsReq;
+        type Resp = SearchStatsResp;
+    }
+
+    /// `GET /admin/guideline/list`
+    pub struct AdminGuidelineList;
+
+    impl Endpoint for AdminGuidelineList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/guideline/list";
+        type Req = ();
+        type Resp = ListGuidelinesResp;
+    }
+
+    /// `POST /admin/guideline/save`
+    pub struct AdminGuidelineSave;
+
+    impl Endpoint for AdminGuidelineSave {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/guideline/save";
+        type Req = SaveGuidelineReq;
+        type Resp = ReviewGuidelineSnippet;
+    }
+
+    /// `GET /admin/guideline/versions`
+    pub struct AdminGuidelineVersions;
+
+    impl Endpoint for AdminGuidelineVersions {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/guideline/versions";
+        type Req = GuidelineVersionsReq;
+        type Resp = GuidelineVersionsResp;
+    }
+
+    /// `GET /admin/scheduler/jobs`
+    pub struct AdminSchedulerJobs;
+
+    impl Endpoint for AdminSchedulerJobs {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/scheduler/jobs";
+        type Req = ();
+        type Resp = SchedulerJobsResp;
+    }
+
+    /// `GET /admin/tag/alias/list`
+    pub struct AdminTagAliasList;
+
+    impl Endpoint for AdminTagAliasList {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/admin/tag/alias/list";
+        type Req = ();
+        type Resp = ListTagAliasesResp;
+    }
+
+    /// `POST /admin/tag/alias/add`
+    pub struct AdminTagAliasAdd;
+
+    impl Endpoint for AdminTagAliasAdd {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/tag/alias/add";
+        type Req = AddTagAliasReq;
+        type Resp = AddTagAliasResp;
+    }
+
+    /// `POST /admin/tag/alias/delete`
+    pub struct AdminTagAliasDelete;
+
+    impl Endpoint for AdminTagAliasDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/tag/alias/delete";
+        type Req = DeleteTagAliasReq;
+        type Resp = ();
+    }
+
+    /// `POST /admin/role/grant`
+    pub struct AdminRoleGrant;
+
+    impl Endpoint for AdminRoleGrant {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/role/grant";
+        type Req = RoleReq;
+        type Resp = RoleResp;
+    }
+
+    /// `POST /admin/role/revoke`
+    pub struct AdminRoleRevoke;
+
+    impl Endpoint for AdminRoleRevoke {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/role/revoke";
+        type Req = RoleReq;
+        type Resp = RoleResp;
+    }
+
+    /// `POST /admin/legal/publish`
+    pub struct AdminLegalPublish;
+
+    impl Endpoint for AdminLegalPublish {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/admin/legal/publish";
+        type Req = PublishLegalReq;
+        type Resp = PublishLegalResp;
+    }
+
+    /// `GET /version/latest`
+    pub struct VersionLatest;
+
+    impl Endpoint for VersionLatest {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/version/latest";
+        type Req = LatestVersionReq;
+        type Resp = Option<LatestVersionResp>;
+    }
+
+    /// `POST /version/latest_batch`
+    pub struct VersionLatestBatch;
+
+    impl Endpoint for VersionLatestBatch {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/version/latest_batch";
+        type Req = LatestVersionBatchReq;
+        type Resp = Vec<LatestVersionResp>;
+    }
+
+    /// `POST /version/latest_batch_v2`
+    pub struct VersionLatestBatchV2;
+
+    impl Endpoint for VersionLatestBatchV2 {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/version/latest_batch_v2";
+        type Req = LatestVersionBatchReq;
+        type Resp = LatestVersionBatchResp;
+    }
+
+    /// `GET /version/server`
+    pub struct VersionServer;
+
+    impl Endpoint for VersionServer {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/version/server";
+        type Req = ();
+        type Resp = ServerVersion;
+    }
+
+    /// `GET /version/page`
+    pub struct VersionPage;
+
+    impl Endpoint for VersionPage {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/version/page";
+        type Req = PageVersionsReq;
+        type Resp = PageVersionsResp;
+    }
+
+    /// `POST /version/publish`
+    pub struct VersionPublish;
+
+    impl Endpoint for VersionPublish {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/version/publish";
+        type Req = PublishVersionReq;
+        type Resp = PublishVersionResp;
+    }
+
+    /// `POST /version/delete`
+    pub struct VersionDelete;
+
+    impl Endpoint for VersionDelete {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/version/delete";
+        type Req = DeleteVersionReq;
+        type Resp = ();
+    }
+
+    /// `GET /bootstrap`
+    pub struct Bootstrap;
+
+    impl Endpoint for Bootstrap {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/bootstrap";
+        type Req = ();
+        type Resp = BootstrapResp;
+    }
+
+    /// `GET /test/emails`
+    pub struct TestEmails;
+
+    impl Endpoint for TestEmails {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/test/emails";
+        type Req = TestEmailsReq;
+        type Resp = TestEmailsResp;
+    }
+
+    /// `POST /test/weekly_selection/publish`
+    pub struct TestWeeklySelectionPublish;
+
+    impl Endpoint for TestWeeklySelectionPublish {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/test/weekly_selection/publish";
+        type Req = PublishWeeklySelectionReq;
+        type Resp = PublishWeeklySelectionResp;
+    }
+}
@@ -0,0 +1,2661 @@
+// Generated by `cargo gen-api-client` from the endpoint manifest of the server, don't edit it by hand.
+
+export interface CommonError {
+  code: string;
+  msg: string;
+  localized_msg?: string;
+  detail?: unknown;
+}
+
+export type WebResponse<T> = { ok: true; data: T } | { ok: false; data: CommonError };
+
+export interface AcceptReq {
+  items: LegalAcceptance[];
+}
+
+export interface AddFavoriteReq {
+  playlist_id: number;
+}
+
+export interface AddFeaturedReq {
+  /** e.g. `onboarding` for the starter set of the new users, or `weekly_picks` for the next weekly selection */
+  collection: string;
+  note?: string | null;
+  /** Smaller first, re-adding a song moves it */
+  position?: number;
+  song_id: number;
+}
+
+export interface AddSongReq {
+  playlist_id: number;
+  song_id: number;
+}
+
+export interface AddSongResp {
+  /** The number of songs in the playlist after adding */
+  songs_count: number;
+}
+
+export interface AddSongsItem {
+  result: AddSongsResult;
+  song_id: number;
+}
+
+export interface AddSongsReq {
+  playlist_id: number;
+  /** Added in this order */
+  song_ids: number[];
+}
+
+export interface AddSongsResp {
+  results: AddSongsItem[];
+  /** The number of songs in the playlist after adding */
+  songs_count: number;
+}
+
+export type AddSongsResult = "added" | "song_not_found" | "playlist_full" | "existed";
+
+export interface AddTagAliasReq {
+  /** May be the name of another tag, which is resolved to the canonical tag when publishing since then */
+  alias: string;
+  /** The canonical tag */
+  tag_id: number;
+}
+
+export interface AddTagAliasResp {
+  id: number;
+}
+
+export interface ApproveReviewReq {
+  comment?: string | null;
+  review_id: number;
+}
+
+export interface AudioRendition {
+  bitrate_kbps: number;
+  /** `aac` in M4A or `opus` in Ogg */
+  codec: string;
+  size: number;
+  /**
+   * The true peak in dBTP of the rendition itself, usually above the `true_peak` of the song after the lossy
+   * encoding, so the gain applied to the rendition should be limited by it instead
+   * @since 260505
+   */
+  true_peak?: number | null;
+  url: string;
+}
+
+export interface BanUserReq {
+  uid: number;
+}
+
+export interface BootstrapResp {
+  limits: LimitsCfg;
+  playlist_max_songs: number;
+}
+
+export interface CapturedEmail {
+  create_time: string;
+  plain: string;
+  subject: string;
+  to: string;
+}
+
+export interface ChangeJmidReq {
+  new_jmid: string;
+  old_jmid: string;
+  song_id: number;
+}
+
+export interface ChangeOrderReq {
+  playlist_id: number;
+  song_id: number;
+  /** Start from 0 */
+  target_order: number;
+}
+
+export interface CheckContributorResp {
+  is_contributor: boolean;
+}
+
+export interface CheckFavoriteReq {
+  playlist_id: number;
+}
+
+export interface CheckFavoriteResp {
+  add_time?: string | null;
+  is_favorite: boolean;
+  playlist_id: number;
+}
+
+export interface ChunkedUploadReq {
+  upload_id: string;
+}
+
+export interface ChunkedUploadStatusResp {
+  chunk_count: number;
+  chunk_size: number;
+  /** The indexes of the uploaded chunks in ascending order, the others are to be uploaded when resuming */
+  uploaded: number[];
+}
+
+export interface CommentIdReq {
+  comment_id: number;
+}
+
+export interface CommentItem {
+  author: PublicUserProfile;
+  content: string;
+  create_time: string;
+  /**
+   * A deleted comment is kept in the thread as a placeholder, without the author and content.
+   * @since 260505
+   */
+  deleted?: boolean;
+  id: number;
+  /** The root comment of the thread, `None` for the root comments */
+  parent_id?: number | null;
+  /** Always 0 for the replies */
+  reply_count: number;
+  /** The author of the replied comment, `None` if replying to the root comment */
+  reply_to?: PublicUserProfile | null;
+  song_id: number;
+}
+
+export interface ConfirmAudioUploadReq {
+  upload_id: string;
+}
+
+export interface ConnectedAccountItem {
+  id: string;
+  name: string;
+  type: string;
+}
+
+export interface ConnectionItem {
+  id: string;
+  name: string;
+  public: boolean;
+  type: string;
+}
+
+export interface ConnectionResp {
+  items: ConnectionItem[];
+}
+
+export interface ConnectionSetVisibilityReq {
+  type: string;
+  visible: boolean;
+}
+
+export interface ConnectionSyncReq {
+  type: string;
+}
+
+export interface ConnectionUnlinkReq {
+  type: string;
+}
+
+export interface CreateChunkedUploadReq {
+  /** `mp3`, `aac` or `flac` */
+  format: string;
+  size: number;
+}
+
+export interface CreateChunkedUploadResp {
+  chunk_count: number;
+  /** The size of every chunk but the last, which is the rest */
+  chunk_size: number;
+  expire_time: string;
+  upload_id: string;
+}
+
+export interface CreateCommentReq {
+  content: string;
+  song_id: number;
+}
+
+export interface CreateCommentResp {
+  comment_id: number;
+}
+
+export interface CreatePlaylistReq {
+  description?: string | null;
+  is_public: boolean;
+  name: string;
+  /** Generate the cover from the first songs, defaults to true */
+  use_song_cover?: boolean | null;
+}
+
+export interface CreatePlaylistResp {
+  id: number;
+}
+
+export interface CreateReq {
+  content: string;
+  content_type: string;
+  cover_file_id?: string | null;
+  title: string;
+}
+
+export interface CreateResp {
+  id: number;
+  mentions: MentionEntity[];
+}
+
+export interface CreationInfo {
+  /** 0: original, 1: derivative work, 2: tertiary work */
+  creation_type: number;
+  derivative_info?: CreationTypeInfo | null;
+  origin_info?: CreationTypeInfo | null;
+}
+
+export interface CreationTypeInfo {
+  artist?: string | null;
+  origin_type: number;
+  song_display_id?: string | null;
+  title?: string | null;
+  url?: string | null;
+}
+
+export interface CrewInvitation {
+  /** The roles the user is credited as */
+  roles: string[];
+  song_display_id: string;
+  song_id: number;
+  song_title: string;
+  uploader_uid: number;
+}
+
+export interface CrewInvitationsResp {
+  invitations: CrewInvitation[];
+}
+
+export interface CrewRespondReq {
+  song_id: number;
+}
+
+/** The whole query of `/publish/review/cursor_contributor`, [CursorContributorReq] along with the cursor */
+export interface CursorContributorQuery {
+  cursor?: string | null;
+  /** The prefix of the display id to search */
+  display_id?: string | null;
+  page_size?: number;
+  /** 0: pending, 1: approved, 2: rejected, all if absent */
+  status?: number | null;
+}
+
+/**
+ * The unified response envelope of cursor-based list endpoints.
+ *
+ * `next_cursor` is `null` when there are no more items.
+ */
+export interface CursorPage {
+  items: PlayHistoryItem[];
+  next_cursor?: string | null;
+}
+
+/**
+ * The unified response envelope of cursor-based list endpoints.
+ *
+ * `next_cursor` is `null` when there are no more items.
+ */
+export interface CursorPage2 {
+  items: SongPublishReviewBrief[];
+  next_cursor?: string | null;
+}
+
+/** The raw cursor parameters in the query string, e.g. `?cursor=xxx&page_size=20`. */
+export interface CursorQuery {
+  cursor?: string | null;
+  page_size?: number;
+}
+
+/** The number of reviews submitted in a day, in UTC */
+export interface DailyCount {
+  count: number;
+  date: string;
+}
+
+export interface DashboardResp {
+  approved_count: number;
+  /** It's cached for a minute */
+  create_time: string;
+  /** The submissions of the last 14 days in UTC, oldest first, including the days without submissions */
+  daily_submissions: DailyCount[];
+  /** The reviews approved or rejected by the caller */
+  my_stats: MyReviewStats;
+  oldest_pending_age_secs?: number | null;
+  /** `None` if nothing is pending */
+  oldest_pending_submit_time?: string | null;
+  pending_count: number;
+  rejected_count: number;
+}
+
+export interface DeletePlaylistReq {
+  id: number;
+}
+
+export interface DeleteReq {
+  song_id: number;
+}
+
+export interface DeleteReq2 {
+  history_id: number;
+}
+
+export interface DeleteTagAliasReq {
+  id: number;
+}
+
+export interface DeleteVersionReq {
+  id: number;
+}
+
+export interface DetailByIdReq {
+  id: number;
+}
+
+export interface DetailReq {
+  /** Actually the JMID */
+  id: string;
+}
+
+export interface DetailReq2 {
+  id: number;
+}
+
+export interface DetailReq3 {
+  review_id: number;
+}
+
+export interface DetailResp {
+  /** @since 260121 */
+  creator_profile: PublicUserProfile;
+  playlist_info: PlaylistItem;
+  songs: SongItem[];
+}
+
+export interface DeviceItem {
+  create_time: string;
+  device_info?: string | null;
+  /** @since 260504 */
+  device_name?: string | null;
+  id: number;
+  ip_address?: string | null;
+  /**
+   * The ISO 3166-1 alpha-2 country code of `ip_address` looked up by the GeoIP service of the region gate.
+   * `None` if unknown, and always `None` if the `region_gate` section is absent or disabled.
+   * @since 260504
+   */
+  last_location?: string | null;
+  last_used_time?: string | null;
+  /** @since 260504 */
+  trusted?: boolean;
+}
+
+export interface DeviceListResp {
+  devices: DeviceItem[];
+}
+
+export interface DeviceLogoutReq {
+  device_id: number;
+}
+
+export interface DeviceRenameReq {
+  device_id: number;
+  /** Cleared if blank */
+  name: string;
+}
+
+export interface DeviceTrustReq {
+  /** The refresh token of the current device, only the current device can be trusted */
+  refresh_token: string;
+}
+
+export interface DeviceTrustResp {
+  expire_time: string;
+  /** Kept by the device for `/auth/login/email`, it's only returned once */
+  trusted_device_token: string;
+}
+
+export interface EditReq {
+  content?: string | null;
+  content_type?: string | null;
+  cover_file_id?: string | null;
+  post_id: number;
+  title?: string | null;
+}
+
+export interface EditResp {
+  /** The users mentioned in the content after the edit */
+  mentions: MentionEntity[];
+}
+
+export interface EditSongReq {
+  description?: string | null;
+  /** Why the song is edited, saved in the audit record */
+  note?: string | null;
+  song_id: number;
+  subtitle?: string | null;
+  tag_ids?: number[] | null;
+  /** Fields that are `None` are kept unchanged */
+  title?: string | null;
+}
+
+export interface EmailRegisterReq {
+  /**
+   * The legal documents accepted, must cover the latest mandatory versions from `/legal/latest`
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  captcha_key: string;
+  code: string;
+  device_info: string;
+  email: string;
+  password: string;
+}
+
+export interface EmailRegisterResp {
+  generated_username: string;
+  token: TokenPair;
+  uid: number;
+}
+
+export interface ExportTemplateReq {
+  review_id: number;
+}
+
+export interface ExternalLink {
+  platform: string;
+  url: string;
+}
+
+export interface FavoritePlaylistItem {
+  add_time: string;
+  metadata: PlaylistMetadata;
+  order_index: number;
+}
+
+export interface FeaturedSong {
+  collection: string;
+  create_time: string;
+  featured_by: number;
+  id: number;
+  note?: string | null;
+  /** Smaller first */
+  position: number;
+  song_id: number;
+}
+
+export interface FollowItem {
+  follow_time: string;
+  user: PublicUserProfile;
+}
+
+export interface FollowReq {
+  uid: number;
+}
+
+export interface GenerateCaptchaResp {
+  captcha_key: string;
+  url: string;
+}
+
+export interface GenerateChallengeReq {
+  provider_account_id: string;
+  type: string;
+}
+
+export interface GenerateChallengeResp {
+  challenge: string;
+  challenge_id: string;
+  provider_account_name: string;
+}
+
+export interface GetProfileReq {
+  uid: number;
+}
+
+export interface GetQueueResp {
+  queue?: QueueData | null;
+  /** 0 if there is no queue saved */
+  revision: number;
+}
+
+export interface GuidelineListResp {
+  snippets: GuidelineSnippetItem[];
+}
+
+export interface GuidelineSnippetItem {
+  /** Inserted into the comments as is */
+  content: string;
+  slug: string;
+  title: string;
+  version: number;
+}
+
+export interface GuidelineVersionsReq {
+  slug: string;
+}
+
+export interface GuidelineVersionsResp {
+  /** Newest first */
+  versions: ReviewGuidelineSnippetVersion[];
+}
+
+export interface HideSongReq {
+  /** `false` to unhide */
+  hidden: boolean;
+  reason?: string;
+  song_id: number;
+}
+
+export interface HideSongResp {
+  /** Whether the song was not in the status yet */
+  changed: boolean;
+}
+
+export interface HotResp {
+  songs: PublicSongDetail[];
+}
+
+export interface InterestsReq {
+  tag_ids: number[];
+}
+
+export interface InterestsResp {
+  /** Today's recommendations re-rolled with the picked interests */
+  songs: PublicSongDetail[];
+}
+
+export interface JmidCheckPReq {
+  jmid_prefix: string;
+}
+
+export interface JmidCheckPResp {
+  result: boolean;
+}
+
+export interface JmidCheckReq {
+  jmid: string;
+}
+
+export interface JmidCheckResp {
+  result: boolean;
+}
+
+export interface JmidGetNextResp {
+  jmid: string;
+}
+
+export interface JmidMineResp {
+  jmid_prefix?: string | null;
+}
+
+export type Lang = "zh-CN" | "en";
+
+export interface LanguageData {
+  /** The language of the emails, `zh-CN` or `en`. `None` to follow the default */
+  language?: string | null;
+}
+
+/** The last run of a job, kept in Redis */
+export interface LastRun {
+  duration_ms: number;
+  /** `None` if it succeeded */
+  error?: string | null;
+  instance: string;
+  scheduled_time: string;
+  start_time: string;
+}
+
+export interface LatestResp {
+  documents: LegalDocumentItem[];
+  /** The versions the current user has to accept, always empty if not logged in */
+  pending: LegalAcceptance[];
+}
+
+export interface LatestVersionBatchItem {
+  /** The error code if it failed to look up, the client should keep the current version of the variant */
+  error?: string | null;
+  variant: string;
+  /** `None` if there is no release of the variant, or it failed to look up */
+  version?: LatestVersionResp | null;
+}
+
+export interface LatestVersionBatchReq {
+  /**
+   * See [LatestVersionReq::channel]
+   * @since 260503
+   */
+  channel?: string | null;
+  variants: string[];
+}
+
+export interface LatestVersionBatchResp {
+  /** In the order of the requested variants */
+  results: LatestVersionBatchItem[];
+}
+
+export interface LatestVersionReq {
+  /**
+   * `stable`, `beta` or `nightly`, the channel opted in by the user (or `stable`) if absent.
+   * The releases of the more stable channels are included.
+   * @since 260503
+   */
+  channel?: string | null;
+  variant: string;
+}
+
+export interface LatestVersionResp {
+  changelog: string;
+  /** @since 260503 */
+  channel?: string;
+  release_time: string;
+  url: string;
+  variant: string;
+  version_name: string;
+  version_number: number;
+}
+
+export interface LegalAcceptance {
+  kind: string;
+  version: number;
+}
+
+export interface LegalDocumentItem {
+  /** The version accepted by the current user, `None` if not logged in or never accepted */
+  accepted_version?: number | null;
+  /** Markdown */
+  content: string;
+  /** `terms` or `privacy` */
+  kind: string;
+  /** Whether it must be accepted before logging in */
+  mandatory: boolean;
+  publish_time: string;
+  title: string;
+  version: number;
+}
+
+export interface LikeReq {
+  playback_position_secs?: number | null;
+  song_id: number;
+}
+
+export interface LikeResp {
+  /** The like count of the song after this operation */
+  like_count: number;
+}
+
+export interface LikeStatusReq {
+  song_id: number;
+}
+
+export interface LikeStatusResp {
+  liked: boolean;
+}
+
+/**
+ * Optional `limits` section of the config file, the absent fields take the defaults.
+ *
+ * The limits are returned by `/bootstrap`, so the clients can validate before uploading.
+ *
+ * ```yaml
+ * limits:
+ *   audio_max_bytes: 20971520
+ *   audio_chunked_max_bytes: 209715200
+ *   audio_direct_max_bytes: 209715200
+ *   image_max_bytes: 10485760
+ *   bio_max_chars: 300
+ * ```
+ */
+export interface LimitsCfg {
+  /**
+   * Audio uploaded in chunks, see [AUDIO_CHUNK_BYTES]
+   * @since 260505
+   */
+  audio_chunked_max_bytes?: number;
+  /**
+   * Audio uploaded to the storage directly with `/song/upload/presign`
+   * @since 260505
+   */
+  audio_direct_max_bytes?: number;
+  audio_max_bytes?: number;
+  bio_max_chars?: number;
+  /** Song comments, review comments, and the comments of approvals and rejections */
+  comment_max_chars?: number;
+  /** Covers, avatars and post images */
+  image_max_bytes?: number;
+  playlist_description_max_chars?: number;
+  playlist_name_max_chars?: number;
+  post_content_max_chars?: number;
+  post_title_max_chars?: number;
+  tag_name_max_chars?: number;
+  username_max_chars?: number;
+}
+
+export interface LinkAccountReq {
+  code: string;
+  state: string;
+}
+
+export interface LinkedAccountItem {
+  last_login_time?: string | null;
+  link_time: string;
+  provider: string;
+  provider_email?: string | null;
+  provider_user_name: string;
+}
+
+export interface LinkedAccountsResp {
+  /** Whether the user can log in by the password, the last linked account can't be unlinked without it */
+  has_password: boolean;
+  items: LinkedAccountItem[];
+}
+
+export interface ListContainingReq {
+  song_id: number;
+}
+
+export interface ListContainingResp {
+  playlist_ids: number[];
+}
+
+export interface ListFeaturedReq {
+  collection: string;
+}
+
+export interface ListFeaturedResp {
+  songs: FeaturedSong[];
+}
+
+export interface ListGuidelinesResp {
+  /** Including the archived ones */
+  snippets: ReviewGuidelineSnippet[];
+}
+
+export interface ListLegalHoldsResp {
+  items: UserLegalHold[];
+}
+
+export interface ListPublicByUserReq {
+  user_id: number;
+}
+
+export interface ListPublicByUserResp {
+  playlists: PlaylistMetadata[];
+}
+
+export interface ListResp {
+  playlists: PlaylistItem[];
+}
+
+export interface ListShadowBansResp {
+  items: UserShadowBan[];
+}
+
+export interface ListTagAliasesResp {
+  items: SongTagAlias[];
+}
+
+export interface LocalizedTitleItem {
+  /** BCP 47 language tag, e.g. `en`, `ja`, `zh-TW` */
+  lang: string;
+  /** The description for the playlists */
+  subtitle?: string;
+  title: string;
+}
+
+export interface LoginReq {
+  /**
+   * The legal documents accepted, required if `legal_acceptance_required` was returned
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  captcha_key: string;
+  /** The TOTP or recovery code, required if the user enabled the 2FA */
+  code?: string | null;
+  device_info: string;
+  email: string;
+  password: string;
+  /**
+   * Returned by `/auth/device/trust`, the captcha and the 2FA code are not required if it's valid
+   * @since 260504
+   */
+  trusted_device_token?: string | null;
+}
+
+export interface LoginResp {
+  token: TokenPair;
+  uid: number;
+  username: string;
+}
+
+export interface MagicLinkRequestReq {
+  email: string;
+}
+
+export interface MagicLinkVerifyReq {
+  /**
+   * The legal documents accepted, required if `legal_acceptance_required` was returned
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  /** The TOTP or recovery code, required if the user enabled the 2FA */
+  code?: string | null;
+  device_info: string;
+  token: string;
+}
+
+export interface MarkReadReq {
+  /** Mark all the notifications read if absent */
+  ids?: number[] | null;
+}
+
+export interface MarkReadResp {
+  /** The notifications changed from unread to read */
+  count: number;
+}
+
+/** A mentioned user, `name` is the name as written so the clients can find `@{name}` in the content */
+export interface MentionEntity {
+  name: string;
+  uid: number;
+}
+
+export interface ModifyReq {
+  comment?: string | null;
+  cover_temp_id?: string | null;
+  creation_info: CreationInfo;
+  description: string;
+  explicit: boolean;
+  external_links: ExternalLink[];
+  /**
+   * Replace the titles in the other languages, unchanged if absent.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[] | null;
+  lyrics: string;
+  production_crew: ProductionItem[];
+  song_id: number;
+  song_temp_id?: string | null;
+  subtitle: string;
+  tag_ids: number[];
+  title: string;
+}
+
+export interface ModifyResp {
+  review_id: number;
+}
+
+export interface MyLikeItem {
+  liked_time: string;
+  song_data: PublicSongDetail;
+}
+
+export interface MyReviewStats {
+  approved_count: number;
+  last_14_days_count: number;
+  rejected_count: number;
+}
+
+export interface NotInterestedReq {
+  song_id: number;
+}
+
+export interface NotificationItem {
+  /** `None` for the system, or if the actor is not found */
+  actor?: PublicUserProfile | null;
+  create_time: string;
+  /** Depends on the type */
+  data: unknown;
+  id: number;
+  read: boolean;
+  /** See the `TYPE_*` constants of `service::notification` */
+  type: string;
+}
+
+export type NotificationTemplate =
+  /** To the uploader, the new song is published */
+  | {
+    template: "review_approved";
+    comment?: string | null;
+    song_display_id: string;
+    song_title: string;
+    user_name: string;
+  }
+  /** To the uploader, the new song is sent back */
+  | {
+    template: "review_rejected";
+    comment: string;
+    song_display_id: string;
+    song_title: string;
+    user_name: string;
+  }
+  /** To the uploader, the modification of a published song is applied */
+  | {
+    template: "review_modify_approved";
+    comment?: string | null;
+    song_display_id: string;
+    user_name: string;
+  }
+  /** To the uploader, the modification of a published song is sent back */
+  | {
+    template: "review_modify_rejected";
+    comment: string;
+    song_display_id: string;
+    user_name: string;
+  }
+  /** To a registered user credited in the production crew */
+  | {
+    template: "crew_invitation";
+    song_display_id: string;
+    song_title: string;
+    uploader_name: string;
+    user_name: string;
+  }
+  /** To the uploader and the contributors, someone commented on the review */
+  | {
+    template: "review_comment";
+    actor_name: string;
+    content: string;
+    song_display_id: string;
+  }
+  /** To the contributors, the uploader updated the review */
+  | {
+    template: "review_modified";
+    actor_name: string;
+    note?: string | null;
+    song_display_id: string;
+  }
+  /** To the maintainer, a new review is submitted */
+  | {
+    template: "review_pending";
+    author: string;
+    song_title: string;
+  };
+
+export interface OAuthAuthorizeReq {
+  /** `github` or `google` */
+  provider: string;
+}
+
+export interface OAuthAuthorizeResp {
+  state: string;
+  /** Open it in the browser, the provider redirects back to the configured page with the `code` and `state` */
+  url: string;
+}
+
+export interface OAuthCallbackReq {
+  /**
+   * The legal documents accepted, the new users have to accept the mandatory ones of `/legal/latest`
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  code: string;
+  device_info: string;
+  state: string;
+}
+
+export interface OAuthLoginResp {
+  /** The user is registered by this login, the client may guide them to edit the profile */
+  first_access: boolean;
+  token: TokenPair;
+  uid: number;
+  username: string;
+}
+
+export interface OAuthTwoFactorReq {
+  /** The legal documents accepted, required if `legal_acceptance_required` was returned */
+  accepted_legal?: LegalAcceptance[];
+  /** The TOTP or recovery code */
+  code: string;
+  device_info: string;
+  /** The `ticket` in the detail of the `2fa_required` error of the callback */
+  ticket: string;
+}
+
+export interface OfficialWeeklyArchiveItem {
+  playlist: PlaylistMetadata;
+  week_start: string;
+}
+
+export interface OfficialWeeklyReq {
+  /** The Monday of an archived week, the latest week if absent */
+  week_start?: string | null;
+}
+
+export interface OfficialWeeklyResp {
+  detail: DetailResp;
+  week_start: string;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page {
+  items: FollowItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page10 {
+  items: ReviewCommentItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page11 {
+  items: ReviewHistoryItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page12 {
+  items: PostItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page13 {
+  items: SongReportItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page2 {
+  items: NotificationItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page3 {
+  items: PublicSongDetail[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page4 {
+  items: MyLikeItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page5 {
+  items: CommentItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page6 {
+  items: PlaylistMetadata[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page7 {
+  items: FavoritePlaylistItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page8 {
+  items: OfficialWeeklyArchiveItem[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/**
+ * The unified response envelope of page-based list endpoints.
+ *
+ * ```json
+ * {
+ *     "items": [],
+ *     "page_index": 0,
+ *     "page_size": 20,
+ *     "total": 100
+ * }
+ * ```
+ */
+export interface Page9 {
+  items: SongPublishReviewBrief[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+/** The whole query of `/song/page_by_user`, [PageByUserReq] along with the pagination */
+export interface PageByUserQuery {
+  page_index?: number;
+  page_size?: number;
+  user_id: number;
+}
+
+/** The whole query of `/playlist/page_by_user`, [PageByUserReq] along with the pagination */
+export interface PageByUserQuery2 {
+  page_index?: number;
+  page_size?: number;
+  user_id: number;
+}
+
+/** The whole query of `/song/comment/page`, [PageCommentReq] along with the pagination */
+export interface PageCommentQuery {
+  page_index?: number;
+  page_size?: number;
+  /** Page the replies of the root comment instead of the root comments */
+  parent_id?: number | null;
+  song_id: number;
+}
+
+/** The whole query of `/user/followers` and `/user/following`, [PageFollowReq] along with the pagination */
+export interface PageFollowQuery {
+  page_index?: number;
+  page_size?: number;
+  uid: number;
+}
+
+/** The whole query of `/user/notifications/page`, [PageNotificationReq] along with the pagination */
+export interface PageNotificationQuery {
+  page_index?: number;
+  page_size?: number;
+  unread_only?: boolean;
+}
+
+/**
+ * The raw paging parameters in the query string, e.g. `?page_index=0&page_size=20`.
+ *
+ * `page` and `size` are accepted as aliases for the legacy endpoints.
+ */
+export interface PageQuery {
+  page_index?: number;
+  page_size?: number;
+}
+
+/** The whole query of `/admin/song/report/page`, [PageSongReportsReq] along with the pagination */
+export interface PageSongReportsQuery {
+  page_index?: number;
+  page_size?: number;
+  /** 0: pending, 1: resolved, 2: dismissed, all if absent */
+  status?: number | null;
+}
+
+export interface PageVersionsReq {
+  /**
+   * Only the releases of the channel if set
+   * @since 260503
+   */
+  channel?: string | null;
+  page_index: number;
+  page_size: number;
+  variant?: string | null;
+}
+
+export interface PageVersionsResp {
+  data: LatestVersionResp[];
+  page_index: number;
+  page_size: number;
+  total: number;
+}
+
+export interface PlayHistoryItem {
+  /**
+   * @deprecated since 260331
+   * @remove in 260901
+   */
+  id: number;
+  play_time: string;
+  song_info: PublicSongDetail;
+}
+
+export interface PlaylistItem {
+  cover_url?: string | null;
+  create_time: string;
+  /** @since 260430 */
+  default_description?: string | null;
+  /**
+   * The original name and description if they are replaced.
+   * @since 260430
+   */
+  default_name?: string | null;
+  description?: string | null;
+  id: number;
+  is_public: boolean;
+  /**
+   * The names (title) and descriptions (subtitle) in the other languages, the `name` and `description` are
+   * replaced by the one preferred by `Accept-Language` in the detail.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[];
+  name: string;
+  /**
+   * The language of the `name` if it's localized, `None` for the original name.
+   * @since 260430
+   */
+  name_lang?: string | null;
+  songs_count: number;
+  /** @since 260122 */
+  update_time: string;
+}
+
+export interface PlaylistMetadata {
+  cover_url?: string | null;
+  create_time: string;
+  description?: string | null;
+  id: number;
+  name: string;
+  /**
+   * The language of the `name` if it's localized, see [PlaylistItem::name_lang].
+   * @since 260430
+   */
+  name_lang?: string | null;
+  songs_count: number;
+  update_time: string;
+  user_avatar_url?: string | null;
+  user_id: number;
+  user_name: string;
+}
+
+export interface PostIdReq {
+  post_id: number;
+}
+
+export interface PostItem {
+  author: PublicUserProfile;
+  content: string;
+  content_type: string;
+  cover_url?: string | null;
+  create_time: string;
+  /**
+   * A deleted post is returned by the detail as a placeholder, without the author and content.
+   * @since 260505
+   */
+  deleted?: boolean;
+  id: number;
+  /** The users mentioned in the content, empty if the content is not returned */
+  mentions: MentionEntity[];
+  title: string;
+  update_time: string;
+}
+
+export interface PreferencesResp {
+  blocked_tags: TagItem[];
+  favorite_tags: TagItem[];
+  /** The favorite tags are inferred from the play history since the user never set them */
+  inferred: boolean;
+}
+
+export interface PresignAudioUploadReq {
+  /** `mp3`, `aac` or `flac` */
+  format: string;
+  /** The hex of the SHA-256 of the file */
+  sha256: string;
+  size: number;
+}
+
+export interface PresignAudioUploadResp {
+  expire_time: string;
+  /** Send the file as the body of the request, with all the headers */
+  request: PresignedRequest;
+  /** Confirm with `/song/upload/confirm` after uploading */
+  upload_id: string;
+}
+
+/** A signed request for the client to send as is */
+export interface PresignedRequest {
+  /** Must be sent along with the request */
+  headers: [string, string][];
+  method: string;
+  url: string;
+}
+
+export interface PreviewNotificationReq {
+  /**
+   * @since 260505
+   * The language to render in, the request language if absent
+   */
+  lang?: Lang | null;
+  /** The template name, e.g. `review_rejected` */
+  template: string;
+}
+
+export interface PreviewNotificationResp {
+  /** The sample data filled in the template */
+  data: NotificationTemplate;
+  html: string;
+  plain: string;
+  subject: string;
+}
+
+export interface ProductionItem {
+  name?: string | null;
+  role: string;
+  uid?: number | null;
+}
+
+export interface PublicSongDetail {
+  /**
+   * The lower-bitrate renditions of `audio_url` for streaming, empty until they are transcoded.
+   * @since 260505
+   */
+  audio_renditions?: AudioRendition[];
+  audio_url: string;
+  cover_url: string;
+  /** @since 251102 */
+  create_time: string;
+  creation_type: number;
+  /** @since 260430 */
+  default_subtitle?: string | null;
+  /**
+   * The original title and subtitle if they are replaced.
+   * @since 260430
+   */
+  default_title?: string | null;
+  description: string;
+  display_id: string;
+  duration_seconds: number;
+  /** @since 251105 */
+  explicit?: boolean | null;
+  external_links: ExternalLink[];
+  /** @since 251105 */
+  gain?: number | null;
+  id: number;
+  like_count: number;
+  /**
+   * The titles in the other languages, the `title` and `subtitle` are replaced by the one preferred by
+   * `Accept-Language` in the detail and search responses.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[];
+  /**
+   * The EBU R128 integrated loudness in LUFS, to normalize the volume across the platforms unlike `gain`.
+   * `None` for the songs not measured yet.
+   * @since 260505
+   */
+  loudness_lufs?: number | null;
+  lyrics: string;
+  origin_infos: CreationTypeInfo[];
+  play_count: number;
+  production_crew: SongProductionCrew[];
+  /** @since 251102 */
+  release_time: string;
+  /**
+   * The times shared to the other platforms, see [crate::service::song_share]
+   * @since 260505
+   */
+  share_count?: number;
+  subtitle: string;
+  tags: TagItem[];
+  title: string;
+  /**
+   * The language of the `title` if it's localized, `None` for the original title.
+   * @since 260430
+   */
+  title_lang?: string | null;
+  /**
+   * The true peak in dBTP, the limit of the gain applied without clipping
+   * @since 260505
+   */
+  true_peak?: number | null;
+  uploader_name: string;
+  /**
+   * The links to support the uploader, see [crate::service::support_link]
+   * @since 260505
+   */
+  uploader_support_links?: SupportLink[];
+  uploader_uid: number;
+}
+
+export interface PublicUserProfile {
+  avatar_url?: string | null;
+  bio?: string | null;
+  /** @since 260402 */
+  connected_accounts: ConnectedAccountItem[];
+  /** @since 260501 */
+  follower_count?: number;
+  gender?: number | null;
+  is_banned: boolean;
+  /**
+   * The links to support the user on the donation platforms
+   * @since 260505
+   */
+  support_links?: SupportLink[];
+  uid: number;
+  username: string;
+}
+
+export interface PublishLegalReq {
+  /** Markdown */
+  content: string;
+  /** `terms` or `privacy` */
+  kind: string;
+  /** The users must accept it before logging in again */
+  mandatory: boolean;
+  title: string;
+}
+
+export interface PublishLegalResp {
+  version: number;
+}
+
+export interface PublishReq {
+  /** @since 251114 */
+  comment?: string | null;
+  /** The cover embedded in the audio is used if empty, see [UploadAudioFileResp::cover_temp_id] */
+  cover_temp_id?: string;
+  creation_info?: CreationInfo;
+  description?: string;
+  /** @since 251105, should be required in new client. */
+  explicit?: boolean | null;
+  external_links?: ExternalLink[];
+  /** @since 251114, should be required in new client. */
+  jmid?: string | null;
+  /**
+   * The titles in the other languages.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[];
+  lyrics?: string;
+  production_crew?: ProductionItem[];
+  song_temp_id: string;
+  subtitle?: string;
+  tag_ids?: number[];
+  /**
+   * The template exported by `/publish/export_template`, fills the empty fields above.
+   * @since 260427
+   */
+  template?: PublishTemplate | null;
+  title?: string;
+}
+
+export interface PublishResp {
+  review_id: number;
+  song_display_id: string;
+}
+
+export interface PublishSongPublishReviewData {
+  audio_url: string;
+  comment?: string | null;
+  cover_url: string;
+  creation_type: number;
+  description: string;
+  display_id: string;
+  duration_seconds: number;
+  explicit?: boolean | null;
+  external_link: ExternalLink[];
+  /**
+   * The titles in the other languages, `None` if they are unchanged by a modification
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[] | null;
+  lyrics: string;
+  origin_infos: CreationTypeInfo[];
+  production_crew: SongProductionCrew[];
+  review_comment?: string | null;
+  review_id: number;
+  review_time?: string | null;
+  /**
+   * The published songs with nearly the same title and lyrics, only filled in the detail of the pending
+   * reviews for the contributors
+   * @since 260430
+   */
+  similar_songs?: SimilarSong[];
+  status: number;
+  submit_time: string;
+  subtitle: string;
+  tags: TagItem[];
+  title: string;
+  uploader_name: string;
+  uploader_uid: number;
+}
+
+/**
+ * The reusable metadata of a publication, which is [PublishReq] without the temp ids, the jmid and the comment.
+ *
+ * Exported by `/publish/export_template` and accepted by `/song/publish` to prefill the repeated fields.
+ */
+export interface PublishTemplate {
+  creation_info?: CreationInfo;
+  description?: string;
+  explicit?: boolean | null;
+  external_links?: ExternalLink[];
+  /** @since 260430 */
+  localized_titles?: LocalizedTitleItem[];
+  lyrics?: string;
+  production_crew?: ProductionItem[];
+  subtitle?: string;
+  tag_ids?: number[];
+  title?: string;
+}
+
+export interface PublishVersionReq {
+  changelog: string;
+  /**
+   * The channel to release in, `stable` if absent
+   * @since 260503
+   */
+  channel?: string | null;
+  release_time: string;
+  url: string;
+  variant: string;
+  version_name: string;
+  version_number: number;
+}
+
+export interface PublishVersionResp {
+  id: number;
+}
+
+export interface PublishWeeklySelectionReq {
+  /** The account publishing the playlist instead of `weekly_selection.official_uid` */
+  official_uid: number;
+  /** Any day of the week to publish */
+  week: string;
+}
+
+export interface PublishWeeklySelectionResp {
+  /** `None` if the week is published already or there are no songs */
+  playlist_id?: number | null;
+}
+
+export interface QrApproveReq {
+  /**
+   * The legal documents accepted, required if `legal_acceptance_required` was returned
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  code: string;
+}
+
+export interface QrApproveResp {
+  /** The device info of the desktop client being logged in */
+  device_info: string;
+}
+
+export interface QrCreateReq {
+  device_info: string;
+}
+
+export interface QrCreateResp {
+  /** Displayed as the QR code for the mobile app */
+  code: string;
+  /** Seconds until the session expires */
+  expires_in: number;
+  /** Kept by the desktop client for polling, never display it */
+  poll_token: string;
+}
+
+export interface QrPollReq {
+  code: string;
+  poll_token: string;
+}
+
+export interface QrPollResp {
+  /** `None` if the code is not approved yet, poll again later */
+  login?: LoginResp | null;
+}
+
+export interface QrScanReq {
+  code: string;
+}
+
+export interface QrScanResp {
+  create_time: string;
+  /** The device info of the desktop client requesting the login */
+  device_info: string;
+  /** The IP the desktop client created the code from */
+  ip: string;
+}
+
+export interface QueueData {
+  current_index: number;
+  device?: string | null;
+  position_ms: number;
+  song_ids: number[];
+  update_time: string;
+}
+
+/** Since 251102 */
+export interface RecentReq {
+  after?: boolean | null;
+  cursor?: string | null;
+  limit?: number | null;
+}
+
+export interface RecentResp {
+  songs: PublicSongDetail[];
+}
+
+export interface RecommendResp {
+  songs: PublicSongDetail[];
+}
+
+export interface RecountSongReq {
+  id: number;
+}
+
+export interface RecountSongResp {
+  /** The counts before recounting differed from the plays and likes, and are repaired */
+  drifted: boolean;
+  like_count: number;
+  old_like_count: number;
+  old_play_count: number;
+  play_count: number;
+}
+
+export interface RefreshTokenReq {
+  /**
+   * The legal documents accepted, required if `legal_acceptance_required` was returned
+   * @since 260505
+   */
+  accepted_legal?: LegalAcceptance[];
+  device_info: string;
+  refresh_token: string;
+}
+
+export interface RejectReviewReq {
+  comment: string;
+  review_id: number;
+}
+
+export interface ReleaseChannelData {
+  /** `stable`, `beta` or `nightly`, used by `/version/latest` if the client doesn't specify one */
+  channel: string;
+}
+
+export interface RemoveFavoriteReq {
+  playlist_id: number;
+}
+
+export interface RemoveFeaturedReq {
+  collection: string;
+  song_id: number;
+}
+
+export interface RemoveSongReq {
+  playlist_id: number;
+  song_id: number;
+}
+
+export interface RemoveSongResp {
+  /** The number of songs in the playlist after removing */
+  songs_count: number;
+}
+
+export interface ReplyCommentReq {
+  /** The root comment or a reply in its thread */
+  comment_id: number;
+  content: string;
+}
+
+export interface ReportCommentReq {
+  comment_id: number;
+  reason: string;
+}
+
+export interface ReportSongReq {
+  /** `copyright`, `explicit_content` or `wrong_origin_info` */
+  category: string;
+  reason?: string;
+  song_id: number;
+}
+
+export interface ReportSongResp {
+  /** `None` if reported already and not handled yet */
+  report_id?: number | null;
+}
+
+export interface ResetPasswordReq {
+  captcha_key: string;
+  code: string;
+  email: string;
+  logout_all_devices: boolean;
+  new_password: string;
+}
+
+export interface ResolveSongReportReq {
+  /** Dismiss the report as invalid instead of resolving it */
+  dismiss?: boolean;
+  id: number;
+  note?: string | null;
+}
+
+export interface ReviewCommentCreateReq {
+  content: string;
+  review_id: number;
+}
+
+export interface ReviewCommentCreateResp {
+  id: number;
+  mentions: MentionEntity[];
+}
+
+export interface ReviewCommentDeleteReq {
+  comment_id: number;
+}
+
+export interface ReviewCommentItem {
+  author?: PublicUserProfile | null;
+  content: string;
+  create_time: string;
+  id: number;
+  mentions: MentionEntity[];
+  review_id: number;
+  update_time: string;
+}
+
+/** The whole query of `/publish/review/comment/list`, [ReviewCommentListReq] along with the pagination */
+export interface ReviewCommentListQuery {
+  page_index?: number;
+  page_size?: number;
+  review_id: number;
+}
+
+/** A piece of the review guidance, see [crate::service::review_guideline] */
+export interface ReviewGuidelineSnippet {
+  archived: boolean;
+  content: string;
+  create_time: string;
+  id: number;
+  slug: string;
+  title: string;
+  update_by: number;
+  update_time: string;
+  version: number;
+}
+
+/** A snapshot of a snippet, saved on every edit including the first one */
+export interface ReviewGuidelineSnippetVersion {
+  archived: boolean;
+  content: string;
+  create_time: string;
+  editor_uid: number;
+  id: number;
+  snippet_id: number;
+  title: string;
+  version: number;
+}
+
+export interface ReviewHistoryItem {
+  action_type: number;
+  author?: PublicUserProfile | null;
+  create_time: string;
+  id: number;
+  note?: string | null;
+  review_id: number;
+  snapshot?: PublishSongPublishReviewData | null;
+}
+
+/** The whole query of `/publish/review/history/list`, [ReviewHistoryListReq] along with the pagination */
+export interface ReviewHistoryListQuery {
+  page_index?: number;
+  page_size?: number;
+  review_id: number;
+}
+
+export interface ReviewModifyReq {
+  comment?: string | null;
+  cover_temp_id?: string | null;
+  creation_info: CreationInfo;
+  description: string;
+  explicit: boolean;
+  external_links: ExternalLink[];
+  /**
+   * Replace the titles in the other languages, unchanged if absent.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[] | null;
+  lyrics: string;
+  production_crew: ProductionItem[];
+  review_id: number;
+  song_temp_id?: string | null;
+  subtitle: string;
+  tag_ids: number[];
+  title: string;
+}
+
+export interface RoleReq {
+  /** e.g. `contributor` */
+  role: string;
+  uid: number;
+}
+
+export interface RoleResp {
+  /** Whether the roles of the user changed, `false` if they had or didn't have the role already */
+  changed: boolean;
+}
+
+export interface SaveGuidelineReq {
+  archived?: boolean;
+  content: string;
+  slug: string;
+  title: string;
+  /** The version being edited, `None` to create the snippet */
+  version?: number | null;
+}
+
+export interface SchedulerJobItem {
+  enabled: boolean;
+  last_run?: LastRun | null;
+  name: string;
+  next_time?: string | null;
+  schedule: string;
+  timezone: string;
+}
+
+export interface SchedulerJobsResp {
+  items: SchedulerJobItem[];
+}
+
+export interface SearchFeedbackReq {
+  /** The `query_id` returned by `/song/search` */
+  query_id: string;
+  /** The clicked hit */
+  song_id: number;
+}
+
+/** The clicks at a position summed over the days */
+export interface SearchPositionTotal {
+  clicks: number;
+  position: number;
+  sort_by: string;
+}
+
+/** The counts of a query summed over the days */
+export interface SearchQueryTotal {
+  click_position_sum: number;
+  clicked_searches: number;
+  clicks: number;
+  query: string;
+  searches: number;
+  sort_by: string;
+}
+
+export interface SearchReq {
+  page: number;
+  q: string;
+  size?: number;
+}
+
+export interface SearchReq2 {
+  filter?: string | null;
+  limit?: number | null;
+  offset?: number | null;
+  q: string;
+  /** Since 260114 */
+  sort_by?: string | null;
+}
+
+export interface SearchReq3 {
+  limit?: number | null;
+  offset?: number | null;
+  q: string;
+  sort_by?: string | null;
+  user_id?: number | null;
+}
+
+export interface SearchResp {
+  hits: PublicUserProfile[];
+  limit: number;
+  offset: number;
+  processing_time_ms: number;
+  query: string;
+  total_hits?: number | null;
+}
+
+export interface SearchResp2 {
+  hits: SearchSongItem[];
+  limit: number;
+  offset: number;
+  processing_time_ms: number;
+  query: string;
+  /** Since 260427, report the clicked hits to `/search/feedback` with it. `None` if unavailable */
+  query_id?: string | null;
+  total_hits?: number | null;
+}
+
+export interface SearchResp3 {
+  hits: PlaylistMetadata[];
+  limit: number;
+  offset: number;
+  processing_time_ms: number;
+  query: string;
+  total_hits?: number | null;
+}
+
+export interface SearchSongItem {
+  artist: string;
+  /**
+   * Deprecated since 260114.
+   * Use detail endpoint to get audio URL
+   */
+  audio_url: string;
+  cover_art_url: string;
+  description: string;
+  display_id: string;
+  duration_seconds: number;
+  /** since 251105 */
+  explicit?: boolean | null;
+  id: number;
+  like_count: number;
+  /** since 260114 */
+  original_artists: string[];
+  /** since 260114 */
+  original_titles: string[];
+  play_count: number;
+  subtitle: string;
+  title: string;
+  /**
+   * The language of the `title` if it's localized, see [PublicSongDetail::title_lang].
+   * @since 260430
+   */
+  title_lang?: string | null;
+  uploader_name: string;
+  uploader_uid: number;
+}
+
+export interface SearchStatsReq {
+  /** Since this many days ago, 7 by default */
+  days?: number | null;
+  /** The most searched queries, 100 by default */
+  limit?: number | null;
+}
+
+export interface SearchStatsResp {
+  /** The clicks by the zero-based position of the hit */
+  positions: SearchPositionTotal[];
+  queries: SearchQueryTotal[];
+}
+
+export interface SearchSuggestReq {
+  /** 5 by default, at most 10 */
+  limit?: number | null;
+  q: string;
+}
+
+export interface SearchSuggestResp {
+  hits: SongSuggestion[];
+}
+
+export interface SendVerificationReq {
+  email: string;
+}
+
+export interface ServerVersion {
+  min_version: number;
+  version: number;
+}
+
+export interface SetLegalHoldReq {
+  /** `false` to release the hold */
+  hold: boolean;
+  reason?: string;
+  uid: number;
+}
+
+export interface SetLegalHoldResp {
+  /** Whether the hold status changed */
+  changed: boolean;
+}
+
+export interface SetQueueReq {
+  current_index: number;
+  device?: string | null;
+  position_ms: number;
+  /** The revision the queue is based on, from `/player/queue/get` or the last save */
+  revision: number;
+  song_ids: number[];
+}
+
+export interface SetQueueResp {
+  revision: number;
+}
+
+export interface SetSupportLinksReq {
+  /** Replaces all the links, in the order shown on the profile */
+  links: SupportLink[];
+}
+
+export interface ShadowBanUserReq {
+  /** The shadow-banned features, see [shadow_ban::FEATURES], empty to lift the shadow ban */
+  features: string[];
+  reason?: string;
+  uid: number;
+}
+
+export type SharePlatform = "qq" | "wechat" | "weibo" | "bilibili" | "twitter" | "other" | "link";
+
+export interface ShareReq {
+  platform: SharePlatform;
+  song_id: number;
+}
+
+export interface SimilarSong {
+  display_id: string;
+  /** The differing bits of the 64-bit hashes, 0 means the same content */
+  distance: number;
+  song_id: number;
+  title: string;
+}
+
+export interface SongItem {
+  add_time: string;
+  cover_url: string;
+  duration_seconds: number;
+  order_index: number;
+  song_display_id: string;
+  song_id: number;
+  subtitle: string;
+  title: string;
+  uploader_name: string;
+  uploader_uid: number;
+}
+
+export interface SongProductionCrew {
+  id: number;
+  /** The credited user hasn't confirmed yet */
+  pending?: boolean;
+  person_name?: string | null;
+  role: string;
+  song_id: number;
+  uid?: number | null;
+}
+
+export interface SongPublishReviewBrief {
+  artist: string;
+  cover_url: string;
+  display_id: string;
+  review_comment?: string | null;
+  review_id: number;
+  review_time?: string | null;
+  status: number;
+  submit_time: string;
+  subtitle: string;
+  title: string;
+  /** @since 251117 */
+  type: number;
+}
+
+export interface SongReportItem {
+  category: string;
+  create_time: string;
+  id: number;
+  reason: string;
+  reporter_uid: number;
+  resolution_note?: string | null;
+  resolve_time?: string | null;
+  resolved_by?: number | null;
+  /** `None` if the song is deleted */
+  song_display_id?: string | null;
+  /** Whether the song is hidden by `/admin/song/hide` */
+  song_hidden: boolean;
+  song_id: number;
+  song_title?: string | null;
+  status: number;
+}
+
+export interface SongReviewHistoryReq {
+  display_id: string;
+}
+
+export interface SongReviewHistoryResp {
+  /** Oldest first */
+  rounds: SongReviewRound[];
+}
+
+export interface SongReviewRound {
+  /** The comment of the submitter */
+  comment?: string | null;
+  /** Oldest first */
+  comments: ReviewCommentItem[];
+  /** The reason of the approval or rejection */
+  review_comment?: string | null;
+  review_id: number;
+  review_time?: string | null;
+  reviewer?: PublicUserProfile | null;
+  /** 0: pending, 1: approved, 2: rejected */
+  status: number;
+  submit_time: string;
+  submitter?: PublicUserProfile | null;
+  /** The title submitted in this round, `None` if the data can't be decoded */
+  title?: string | null;
+  /** 0: create, 1: modify */
+  type: number;
+}
+
+/** The least of a song for the suggestions while typing */
+export interface SongSuggestion {
+  artist: string;
+  display_id: string;
+  id: number;
+  title: string;
+}
+
+/** Another spelling of the canonical tag, the tag named by the alias is resolved to the canonical one */
+export interface SongTagAlias {
+  alias: string;
+  create_time: string;
+  id: number;
+  tag_id: number;
+}
+
+export interface StarterResp {
+  /** The starter set featured by the contributors, or the weekly hot songs if it's empty */
+  songs: PublicSongDetail[];
+  /** The tags to pick the interests from */
+  tags: TagItem[];
+}
+
+export interface StorageUsageResp {
+  audio_bytes: number;
+  image_bytes: number;
+  /** `None` if unlimited */
+  quota_bytes?: number | null;
+  tier?: string | null;
+  used_bytes: number;
+}
+
+export interface SubmitCaptchaReq {
+  captcha_key: string;
+  token: string;
+}
+
+export interface SupportLink {
+  /** `afdian`, `patreon`, `kofi`, `paypal` or `github_sponsors` */
+  platform: string;
+  url: string;
+}
+
+export interface TagCreateReq {
+  description?: string | null;
+  name: string;
+}
+
+export interface TagCreateResp {
+  id: number;
+}
+
+export interface TagItem {
+  description?: string | null;
+  id: number;
+  name: string;
+}
+
+export interface TagRecommendItem {
+  description?: string | null;
+  id: number;
+  name: string;
+  score: number;
+}
+
+export interface TagRecommendResp {
+  result: TagRecommendItem[];
+}
+
+export interface TagSearchReq {
+  query: string;
+}
+
+export interface TagSearchResp {
+  result: TagItem[];
+}
+
+export interface TestEmailsReq {
+  to: string;
+}
+
+export interface TestEmailsResp {
+  /** Latest first */
+  emails: CapturedEmail[];
+}
+
+export interface TicketResp {
+  /** Seconds until it expires if unused */
+  expires_in: number;
+  /** Pass it as the `ticket` query of `/events`, it can be used once */
+  ticket: string;
+}
+
+export interface TokenPair {
+  access_token: string;
+  expires_in: string;
+  refresh_token: string;
+}
+
+export interface TouchReq {
+  song_id: number;
+}
+
+export interface TwoFactorConfirmReq {
+  code: string;
+}
+
+export interface TwoFactorConfirmResp {
+  /** Only returned once, each of them can be used once in place of a TOTP code */
+  recovery_codes: string[];
+}
+
+export interface TwoFactorDisableReq {
+  /** The TOTP or recovery code */
+  code: string;
+}
+
+export interface TwoFactorEnrollResp {
+  /** The `otpauth://` URI, displayed as the QR code for the authenticator to scan */
+  provisioning_uri: string;
+  /** Base32, for entering into the authenticator manually */
+  secret: string;
+}
+
+export interface UnlikeReq {
+  song_id: number;
+}
+
+export interface UnlinkAccountReq {
+  provider: string;
+}
+
+export interface UnreadCountResp {
+  count: number;
+}
+
+export interface UpdatePlaylistReq {
+  description?: string | null;
+  id: number;
+  is_public: boolean;
+  /**
+   * Replace the names and descriptions in the other languages, unchanged if absent.
+   * @since 260430
+   */
+  localized_titles?: LocalizedTitleItem[] | null;
+  name: string;
+  /** Generate the cover from the first songs, unchanged if absent */
+  use_song_cover?: boolean | null;
+}
+
+export interface UpdateProfileReq {
+  bio?: string | null;
+  gender?: number | null;
+  username: string;
+}
+
+export interface UploadAudioFileResp {
+  artist?: string | null;
+  bitrate?: string | null;
+  /**
+   * The cover embedded in the audio, usable as the `cover_temp_id` of the publishing.
+   * It's counted in the storage usage only if it's used.
+   * @since 260505
+   */
+  cover_temp_id?: string | null;
+  duration_secs: number;
+  temp_id: string;
+  title?: string | null;
+}
+
+/** A user whose data is kept regardless of the retention, see [crate::service::retention] */
+export interface UserLegalHold {
+  create_time: string;
+  held_by: number;
+  reason: string;
+  user_id: number;
+}
+
+/** A shadow-banned user, see [crate::service::shadow_ban] */
+export interface UserShadowBan {
+  create_time: string;
+  features: string[];
+  reason: string;
+  update_by: number;
+  update_time: string;
+  user_id: number;
+}
+
+export interface VerifyChallengeReq {
+  challenge_id: string;
+}
+
+export interface Endpoints {
+  AuthSendEmailCode: { method: "POST"; path: "/auth/send_email_code"; req: SendVerificationReq; resp: null };
+  AuthResendEmailCode: { method: "POST"; path: "/auth/resend_email_code"; req: SendVerificationReq; resp: null };
+  AuthRegisterEmail: { method: "POST"; path: "/auth/register/email"; req: EmailRegisterReq; resp: EmailRegisterResp };
+  AuthLoginEmail: { method: "POST"; path: "/auth/login/email"; req: LoginReq; resp: LoginResp };
+  AuthLoginMagicLinkRequest: { method: "POST"; path: "/auth/login/magic_link/request"; req: MagicLinkRequestReq; resp: null };
+  AuthLoginMagicLinkVerify: { method: "POST"; path: "/auth/login/magic_link/verify"; req: MagicLinkVerifyReq; resp: LoginResp };
+  AuthOAuthAuthorize: { method: "GET"; path: "/auth/oauth/authorize"; req: OAuthAuthorizeReq; resp: OAuthAuthorizeResp };
+  AuthOAuthCallback: { method: "POST"; path: "/auth/oauth/callback"; req: OAuthCallbackReq; resp: OAuthLoginResp };
+  AuthOAuthTwoFactor: { method: "POST"; path: "/auth/oauth/2fa"; req: OAuthTwoFactorReq; resp: OAuthLoginResp };
+  AuthTwoFactorEnroll: { method: "POST"; path: "/auth/2fa/enroll"; req: null; resp: TwoFactorEnrollResp };
+  AuthTwoFactorConfirm: { method: "POST"; path: "/auth/2fa/confirm"; req: TwoFactorConfirmReq; resp: TwoFactorConfirmResp };
+  AuthTwoFactorDisable: { method: "POST"; path: "/auth/2fa/disable"; req: TwoFactorDisableReq; resp: null };
+  AuthQrCreate: { method: "POST"; path: "/auth/qr/create"; req: QrCreateReq; resp: QrCreateResp };
+  AuthQrScan: { method: "POST"; path: "/auth/qr/scan"; req: QrScanReq; resp: QrScanResp };
+  AuthQrApprove: { method: "POST"; path: "/auth/qr/approve"; req: QrApproveReq; resp: QrApproveResp };
+  AuthQrPoll: { method: "POST"; path: "/auth/qr/poll"; req: QrPollReq; resp: QrPollResp };
+  AuthRefreshToken: { method: "POST"; path: "/auth/refresh_token"; req: RefreshTokenReq; resp: TokenPair };
+  AuthResetPassword: { method: "POST"; path: "/auth/reset_password"; req: ResetPasswordReq; resp: null };
+  AuthDeviceList: { method: "GET"; path: "/auth/device/list"; req: null; resp: DeviceListResp };
+  AuthDeviceLogout: { method: "POST"; path: "/auth/device/logout"; req: DeviceLogoutReq; resp: null };
+  AuthDeviceRename: { method: "POST"; path: "/auth/device/rename"; req: DeviceRenameReq; resp: null };
+  AuthDeviceTrust: { method: "POST"; path: "/auth/device/trust"; req: DeviceTrustReq; resp: DeviceTrustResp };
+  AuthDeviceUntrust: { method: "POST"; path: "/auth/device/untrust"; req: DeviceLogoutReq; resp: null };
+  AuthCaptchaGenerate: { method: "GET"; path: "/auth/captcha/generate"; req: null; resp: GenerateCaptchaResp };
+  AuthCaptchaSubmit: { method: "POST"; path: "/auth/captcha/submit"; req: SubmitCaptchaReq; resp: null };
+  AuthProtected: { method: "GET"; path: "/auth/protected"; req: null; resp: null };
+  UserProfile: { method: "GET"; path: "/user/profile"; req: GetProfileReq; resp: PublicUserProfile };
+  UserGreet: { method: "GET"; path: "/user/greet"; req: null; resp: string };
+  UserUpdateProfile: { method: "POST"; path: "/user/update_profile"; req: UpdateProfileReq; resp: null };
+  UserSearch: { method: "GET"; path: "/user/search"; req: SearchReq; resp: SearchResp };
+  UserLinkedAccounts: { method: "GET"; path: "/user/linked_accounts"; req: null; resp: LinkedAccountsResp };
+  UserUnlinkAccount: { method: "POST"; path: "/user/unlink_account"; req: UnlinkAccountReq; resp: null };
+  UserLinkAccountAuthorize: { method: "GET"; path: "/user/link_account/authorize"; req: OAuthAuthorizeReq; resp: OAuthAuthorizeResp };
+  UserLinkAccount: { method: "POST"; path: "/user/link_account"; req: LinkAccountReq; resp: null };
+  UserStorageUsage: { method: "GET"; path: "/user/storage_usage"; req: null; resp: StorageUsageResp };
+  UserFollow: { method: "POST"; path: "/user/follow"; req: FollowReq; resp: null };
+  UserUnfollow: { method: "POST"; path: "/user/unfollow"; req: FollowReq; resp: null };
+  UserFollowers: { method: "GET"; path: "/user/followers"; req: PageFollowQuery; resp: Page };
+  UserFollowing: { method: "GET"; path: "/user/following"; req: PageFollowQuery; resp: Page };
+  UserReleaseChannel: { method: "GET"; path: "/user/release_channel"; req: null; resp: ReleaseChannelData };
+  UserSetReleaseChannel: { method: "POST"; path: "/user/set_release_channel"; req: ReleaseChannelData; resp: null };
+  UserLanguage: { method: "GET"; path: "/user/language"; req: null; resp: LanguageData };
+  UserSetLanguage: { method: "POST"; path: "/user/set_language"; req: LanguageData; resp: null };
+  UserSetSupportLinks: { method: "POST"; path: "/user/set_support_links"; req: SetSupportLinksReq; resp: null };
+  UserPreferences: { method: "GET"; path: "/user/preferences"; req: null; resp: PreferencesResp };
+  UserConnectionList: { method: "GET"; path: "/user/connection/list"; req: null; resp: ConnectionResp };
+  UserConnectionUnlink: { method: "POST"; path: "/user/connection/unlink"; req: ConnectionUnlinkReq; resp: null };
+  UserConnectionSetVisibility: { method: "POST"; path: "/user/connection/set_visibility"; req: ConnectionSetVisibilityReq; resp: null };
+  UserConnectionSync: { method: "POST"; path: "/user/connection/sync"; req: ConnectionSyncReq; resp: null };
+  UserConnectionGenerateChallenge: { method: "POST"; path: "/user/connection/generate_challenge"; req: GenerateChallengeReq; resp: GenerateChallengeResp };
+  UserConnectionVerifyChallenge: { method: "POST"; path: "/user/connection/verify_challenge"; req: VerifyChallengeReq; resp: null };
+  UserNotificationPage: { method: "GET"; path: "/user/notifications/page"; req: PageNotificationQuery; resp: Page2 };
+  UserNotificationMarkRead: { method: "POST"; path: "/user/notifications/mark_read"; req: MarkReadReq; resp: MarkReadResp };
+  UserNotificationUnreadCount: { method: "GET"; path: "/user/notifications/unread_count"; req: null; resp: UnreadCountResp };
+  EventsTicket: { method: "POST"; path: "/events/ticket"; req: null; resp: TicketResp };
+  OnboardingStarter: { method: "GET"; path: "/onboarding/starter"; req: null; resp: StarterResp };
+  OnboardingInterests: { method: "POST"; path: "/onboarding/interests"; req: InterestsReq; resp: InterestsResp };
+  SongDetail: { method: "GET"; path: "/song/detail"; req: DetailReq; resp: PublicSongDetail };
+  SongDetailById: { method: "GET"; path: "/song/detail_by_id"; req: DetailByIdReq; resp: PublicSongDetail };
+  SongPageByUser: { method: "GET"; path: "/song/page_by_user"; req: PageByUserQuery; resp: Page3 };
+  SongPublish: { method: "POST"; path: "/song/publish"; req: PublishReq; resp: PublishResp };
+  SongReviewHistory: { method: "GET"; path: "/song/review_history"; req: SongReviewHistoryReq; resp: SongReviewHistoryResp };
+  SongPlay: { method: "POST"; path: "/song/play"; req: TouchReq; resp: null };
+  SongShare: { method: "POST"; path: "/song/share"; req: ShareReq; resp: null };
+  SongReport: { method: "POST"; path: "/song/report"; req: ReportSongReq; resp: ReportSongResp };
+  SongDelete: { method: "POST"; path: "/song/delete"; req: DeleteReq; resp: null };
+  SongLikesLike: { method: "POST"; path: "/song/likes/like"; req: LikeReq; resp: LikeResp };
+  SongLikesUnlike: { method: "POST"; path: "/song/likes/unlike"; req: UnlikeReq; resp: LikeResp };
+  SongLikesStatus: { method: "GET"; path: "/song/likes/status"; req: LikeStatusReq; resp: LikeStatusResp };
+  SongLikesPageMyLikes: { method: "GET"; path: "/song/likes/page_my_likes"; req: PageQuery; resp: Page4 };
+  PlayHistoryCursor: { method: "GET"; path: "/play_history/cursor"; req: CursorQuery; resp: CursorPage };
+  PlayHistoryTouch: { method: "POST"; path: "/play_history/touch"; req: TouchReq; resp: null };
+  PlayHistoryTouchAnonymous: { method: "POST"; path: "/play_history/touch_anonymous"; req: TouchReq; resp: null };
+  PlayHistoryDelete: { method: "POST"; path: "/play_history/delete"; req: DeleteReq2; resp: null };
+  PlayerQueueGet: { method: "GET"; path: "/player/queue/get"; req: null; resp: GetQueueResp };
+  PlayerQueueSet: { method: "POST"; path: "/player/queue/set"; req: SetQueueReq; resp: SetQueueResp };
+  LegalLatest: { method: "GET"; path: "/legal/latest"; req: null; resp: LatestResp };
+  LegalAccept: { method: "POST"; path: "/legal/accept"; req: AcceptReq; resp: null };
+  SongUploadPresign: { method: "POST"; path: "/song/upload/presign"; req: PresignAudioUploadReq; resp: PresignAudioUploadResp };
+  SongUploadConfirm: { method: "POST"; path: "/song/upload/confirm"; req: ConfirmAudioUploadReq; resp: UploadAudioFileResp };
+  SongCommentCreate: { method: "POST"; path: "/song/comment/create"; req: CreateCommentReq; resp: CreateCommentResp };
+  SongCommentReply: { method: "POST"; path: "/song/comment/reply"; req: ReplyCommentReq; resp: CreateCommentResp };
+  SongCommentDelete: { method: "POST"; path: "/song/comment/delete"; req: CommentIdReq; resp: null };
+  SongCommentReport: { method: "POST"; path: "/song/comment/report"; req: ReportCommentReq; resp: null };
+  SongCommentPage: { method: "GET"; path: "/song/comment/page"; req: PageCommentQuery; resp: Page5 };
+  SongRecentV2: { method: "GET"; path: "/song/recent_v2"; req: RecentReq; resp: RecentResp };
+  SongRecommend: { method: "GET"; path: "/song/recommend"; req: null; resp: RecommendResp };
+  SongRecommendAnonymous: { method: "GET"; path: "/song/recommend_anonymous"; req: null; resp: RecommendResp };
+  SongHotWeekly: { method: "GET"; path: "/song/hot/weekly"; req: null; resp: HotResp };
+  SongSearch: { method: "GET"; path: "/song/search"; req: SearchReq2; resp: SearchResp2 };
+  SongSearchSuggest: { method: "GET"; path: "/song/search/suggest"; req: SearchSuggestReq; resp: SearchSuggestResp };
+  SearchFeedback: { method: "POST"; path: "/search/feedback"; req: SearchFeedbackReq; resp: null };
+  SongNotInterested: { method: "POST"; path: "/song/not_interested"; req: NotInterestedReq; resp: null };
+  SongTagCreate: { method: "POST"; path: "/song/tag/create"; req: TagCreateReq; resp: TagCreateResp };
+  SongTagSearch: { method: "GET"; path: "/song/tag/search"; req: TagSearchReq; resp: TagSearchResp };
+  SongTagRecommend: { method: "GET"; path: "/song/tag/recommend"; req: null; resp: TagRecommendResp };
+  SongTagRecommendAnonymous: { method: "GET"; path: "/song/tag/recommend_anonymous"; req: null; resp: TagRecommendResp };
+  PlaylistCreate: { method: "POST"; path: "/playlist/create"; req: CreatePlaylistReq; resp: CreatePlaylistResp };
+  PlaylistList: { method: "GET"; path: "/playlist/list"; req: null; resp: ListResp };
+  PlaylistDetail: { method: "GET"; path: "/playlist/detail"; req: DetailReq2; resp: DetailResp };
+  PlaylistDetailPrivate: { method: "GET"; path: "/playlist/detail_private"; req: DetailReq2; resp: DetailResp };
+  PlaylistDetailPublic: { method: "GET"; path: "/playlist/detail_public"; req: DetailReq2; resp: DetailResp };
+  PlaylistListPublicByUser: { method: "GET"; path: "/playlist/list_public_by_user"; req: ListPublicByUserReq; resp: ListPublicByUserResp };
+  PlaylistPageByUser: { method: "GET"; path: "/playlist/page_by_user"; req: PageByUserQuery2; resp: Page6 };
+  PlaylistUpdate: { method: "POST"; path: "/playlist/update"; req: UpdatePlaylistReq; resp: null };
+  PlaylistDelete: { method: "POST"; path: "/playlist/delete"; req: DeletePlaylistReq; resp: null };
+  PlaylistListContaining: { method: "GET"; path: "/playlist/list_containing"; req: ListContainingReq; resp: ListContainingResp };
+  PlaylistAddSong: { method: "POST"; path: "/playlist/add_song"; req: AddSongReq; resp: AddSongResp };
+  PlaylistAddSongs: { method: "POST"; path: "/playlist/add_songs"; req: AddSongsReq; resp: AddSongsResp };
+  PlaylistRemoveSong: { method: "POST"; path: "/playlist/remove_song"; req: RemoveSongReq; resp: RemoveSongResp };
+  PlaylistChangeOrder: { method: "POST"; path: "/playlist/change_order"; req: ChangeOrderReq; resp: null };
+  PlaylistSearch: { method: "GET"; path: "/playlist/search"; req: SearchReq3; resp: SearchResp3 };
+  PlaylistFavoriteAdd: { method: "POST"; path: "/playlist/favorite/add"; req: AddFavoriteReq; resp: null };
+  PlaylistFavoriteRemove: { method: "POST"; path: "/playlist/favorite/remove"; req: RemoveFavoriteReq; resp: null };
+  PlaylistFavoriteCheck: { method: "GET"; path: "/playlist/favorite/check"; req: CheckFavoriteReq; resp: CheckFavoriteResp };
+  PlaylistFavoritePage: { method: "GET"; path: "/playlist/favorite/page"; req: PageQuery; resp: Page7 };
+  PlaylistOfficialWeekly: { method: "GET"; path: "/playlist/official/weekly"; req: OfficialWeeklyReq; resp: OfficialWeeklyResp };
+  PlaylistOfficialWeeklyArchive: { method: "GET"; path: "/playlist/official/weekly/archive"; req: PageQuery; resp: Page8 };
+  PublishJmidCheckPrefix: { method: "GET"; path: "/publish/jmid/check_prefix"; req: JmidCheckPReq; resp: JmidCheckPResp };
+  PublishJmidGetNext: { method: "GET"; path: "/publish/jmid/get_next"; req: null; resp: JmidGetNextResp };
+  PublishJmidCheck: { method: "GET"; path: "/publish/jmid/check"; req: JmidCheckReq; resp: JmidCheckResp };
+  PublishJmidMine: { method: "GET"; path: "/publish/jmid/mine"; req: null; resp: JmidMineResp };
+  PublishPublish: { method: "POST"; path: "/publish/publish"; req: PublishReq; resp: PublishResp };
+  PublishModify: { method: "POST"; path: "/publish/modify"; req: ModifyReq; resp: ModifyResp };
+  PublishDelete: { method: "POST"; path: "/publish/delete"; req: DeleteReq; resp: null };
+  PublishChangeJmid: { method: "POST"; path: "/publish/change_jmid"; req: ChangeJmidReq; resp: null };
+  PublishExportTemplate: { method: "GET"; path: "/publish/export_template"; req: ExportTemplateReq; resp: PublishTemplate };
+  PublishAudioChunkCreate: { method: "POST"; path: "/publish/upload_audio_chunk/create"; req: CreateChunkedUploadReq; resp: CreateChunkedUploadResp };
+  PublishAudioChunkStatus: { method: "GET"; path: "/publish/upload_audio_chunk/status"; req: ChunkedUploadReq; resp: ChunkedUploadStatusResp };
+  PublishAudioChunkComplete: { method: "POST"; path: "/publish/upload_audio_chunk/complete"; req: ChunkedUploadReq; resp: UploadAudioFileResp };
+  PublishAudioChunkAbort: { method: "POST"; path: "/publish/upload_audio_chunk/abort"; req: ChunkedUploadReq; resp: null };
+  PublishReviewDashboard: { method: "GET"; path: "/publish/review/dashboard"; req: null; resp: DashboardResp };
+  PublishReviewPage: { method: "GET"; path: "/publish/review/page"; req: PageQuery; resp: Page9 };
+  PublishReviewPageContributor: { method: "GET"; path: "/publish/review/page_contributor"; req: PageQuery; resp: Page9 };
+  PublishReviewCursorContributor: { method: "GET"; path: "/publish/review/cursor_contributor"; req: CursorContributorQuery; resp: CursorPage2 };
+  PublishReviewDetail: { method: "GET"; path: "/publish/review/detail"; req: DetailReq3; resp: PublishSongPublishReviewData };
+  PublishReviewGuidelineList: { method: "GET"; path: "/publish/review/guideline/list"; req: null; resp: GuidelineListResp };
+  PublishReviewApprove: { method: "POST"; path: "/publish/review/approve"; req: ApproveReviewReq; resp: null };
+  PublishReviewReject: { method: "POST"; path: "/publish/review/reject"; req: RejectReviewReq; resp: null };
+  PublishReviewModify: { method: "POST"; path: "/publish/review/modify"; req: ReviewModifyReq; resp: null };
+  PublishReviewCommentCreate: { method: "POST"; path: "/publish/review/comment/create"; req: ReviewCommentCreateReq; resp: ReviewCommentCreateResp };
+  PublishReviewCommentDelete: { method: "POST"; path: "/publish/review/comment/delete"; req: ReviewCommentDeleteReq; resp: null };
+  PublishReviewCommentList: { method: "GET"; path: "/publish/review/comment/list"; req: ReviewCommentListQuery; resp: Page10 };
+  PublishReviewHistoryList: { method: "GET"; path: "/publish/review/history/list"; req: ReviewHistoryListQuery; resp: Page11 };
+  PublishCrewInvitations: { method: "GET"; path: "/publish/crew/invitations"; req: null; resp: CrewInvitationsResp };
+  PublishCrewConfirm: { method: "POST"; path: "/publish/crew/confirm"; req: CrewRespondReq; resp: null };
+  PublishCrewDecline: { method: "POST"; path: "/publish/crew/decline"; req: CrewRespondReq; resp: null };
+  PostPage: { method: "GET"; path: "/post/page"; req: PageQuery; resp: Page12 };
+  PostDetail: { method: "GET"; path: "/post/detail"; req: PostIdReq; resp: PostItem };
+  PostCreate: { method: "POST"; path: "/post/create"; req: CreateReq; resp: CreateResp };
+  PostEdit: { method: "POST"; path: "/post/edit"; req: EditReq; resp: EditResp };
+  PostDelete: { method: "POST"; path: "/post/delete"; req: PostIdReq; resp: null };
+  ContributorNotificationPreview: { method: "GET"; path: "/contributor/notification/preview"; req: PreviewNotificationReq; resp: PreviewNotificationResp };
+  ContributorCheck: { method: "GET"; path: "/contributor/check"; req: null; resp: CheckContributorResp };
+  AdminUserBan: { method: "POST"; path: "/admin/user/ban"; req: BanUserReq; resp: null };
+  AdminUserUnban: { method: "POST"; path: "/admin/user/unban"; req: BanUserReq; resp: null };
+  AdminUserShadowBan: { method: "POST"; path: "/admin/user/shadow_ban"; req: ShadowBanUserReq; resp: null };
+  AdminUserShadowBanList: { method: "GET"; path: "/admin/user/shadow_ban/list"; req: null; resp: ListShadowBansResp };
+  AdminUserLegalHold: { method: "POST"; path: "/admin/user/legal_hold"; req: SetLegalHoldReq; resp: SetLegalHoldResp };
+  AdminUserLegalHoldList: { method: "GET"; path: "/admin/user/legal_hold/list"; req: null; resp: ListLegalHoldsResp };
+  AdminSongEdit: { method: "POST"; path: "/admin/song/edit"; req: EditSongReq; resp: null };
+  AdminSongRecount: { method: "POST"; path: "/admin/song/recount"; req: RecountSongReq; resp: RecountSongResp };
+  AdminSongHide: { method: "POST"; path: "/admin/song/hide"; req: HideSongReq; resp: HideSongResp };
+  AdminSongReportPage: { method: "GET"; path: "/admin/song/report/page"; req: PageSongReportsQuery; resp: Page13 };
+  AdminSongReportResolve: { method: "POST"; path: "/admin/song/report/resolve"; req: ResolveSongReportReq; resp: null };
+  AdminFeaturedList: { method: "GET"; path: "/admin/featured/list"; req: ListFeaturedReq; resp: ListFeaturedResp };
+  AdminFeaturedAdd: { method: "POST"; path: "/admin/featured/add"; req: AddFeaturedReq; resp: null };
+  AdminFeaturedRemove: { method: "POST"; path: "/admin/featured/remove"; req: RemoveFeaturedReq; resp: null };
+  AdminSearchStats: { method: "GET"; path: "/admin/search/stats"; req: SearchStatsReq; resp: SearchStatsResp };
+  AdminGuidelineList: { method: "GET"; path: "/admin/guideline/list"; req: null; resp: ListGuidelinesResp };
+  AdminGuidelineSave: { method: "POST"; path: "/admin/guideline/save"; req: SaveGuidelineReq; resp: ReviewGuidelineSnippet };
+  AdminGuidelineVersions: { method: "GET"; path: "/admin/guideline/versions"; req: GuidelineVersionsReq; resp: GuidelineVersionsResp };
+  AdminSchedulerJobs: { method: "GET"; path: "/admin/scheduler/jobs"; req: null; resp: SchedulerJobsResp };
+  AdminTagAliasList: { method: "GET"; path: "/admin/tag/alias/list"; req: null; resp: ListTagAliasesResp };
+  AdminTagAliasAdd: { method: "POST"; path: "/admin/tag/alias/add"; req: AddTagAliasReq; resp: AddTagAliasResp };
+  AdminTagAliasDelete: { method: "POST"; path: "/admin/tag/alias/delete"; req: DeleteTagAliasReq; resp: null };
+  AdminRoleGrant: { method: "POST"; path: "/admin/role/grant"; req: RoleReq; resp: RoleResp };
+  AdminRoleRevoke: { method: "POST"; path: "/admin/role/revoke"; req: RoleReq; resp: RoleResp };
+  AdminLegalPublish: { method: "POST"; path: "/admin/legal/publish"; req: PublishLegalReq; resp: PublishLegalResp };
+  VersionLatest: { method: "GET"; path: "/version/latest"; req: LatestVersionReq; resp: LatestVersionResp | null };
+  VersionLatestBatch: { method: "POST"; path: "/version/latest_batch"; req: LatestVersionBatchReq; resp: LatestVersionResp[] };
+  VersionLatestBatchV2: { method: "POST"; path: "/version/latest_batch_v2"; req: LatestVersionBatchReq; resp: LatestVersionBatchResp };
+  VersionServer: { method: "GET"; path: "/version/server"; req: null; resp: ServerVersion };
+  VersionPage: { method: "GET"; path: "/version/page"; req: PageVersionsReq; resp: PageVersionsResp };
+  VersionPublish: { method: "POST"; path: "/version/publish"; req: PublishVersionReq; resp: PublishVersionResp };
+  VersionDelete: { method: "POST"; path: "/version/delete"; req: DeleteVersionReq; resp: null };
+  Bootstrap: { method: "GET"; path: "/bootstrap"; req: null; resp: BootstrapResp };
+  TestEmails: { method: "GET"; path: "/test/emails"; req: TestEmailsReq; resp: TestEmailsResp };
+  TestWeeklySelectionPublish: { method: "POST"; path: "/test/weekly_selection/publish"; req: PublishWeeklySelectionReq; resp: PublishWeeklySelectionResp };
+}
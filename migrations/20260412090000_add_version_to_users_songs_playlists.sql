@@ -0,0 +1,7 @@
+-- Row version for optimistic locking, increased on every update
+ALTER TABLE users
+    ADD version BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE songs
+    ADD version BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE playlists
+    ADD version BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+CREATE TABLE song_edit_logs
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY NOT NULL,
+    song_id     BIGINT                                          NOT NULL,
+    editor_uid  BIGINT                                          NOT NULL,
+    note        TEXT,
+    before_data JSONB                                           NOT NULL,
+    after_data  JSONB                                           NOT NULL,
+    create_time TIMESTAMPTZ                                     NOT NULL
+);
+
+CREATE INDEX idx_song_edit_logs_song_id_create_time
+    ON song_edit_logs (song_id, create_time DESC);
@@ -0,0 +1,10 @@
+CREATE TABLE storage_orphan_objects
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY NOT NULL,
+    object_key  TEXT                                            NOT NULL UNIQUE,
+    size        BIGINT,
+    event_time  TIMESTAMPTZ                                     NOT NULL, -- When the object was created in the bucket
+    create_time TIMESTAMPTZ                                     NOT NULL
+);
+
+CREATE INDEX idx_storage_orphan_objects_event_time ON storage_orphan_objects (event_time);
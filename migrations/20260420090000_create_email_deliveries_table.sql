@@ -0,0 +1,15 @@
+CREATE TABLE email_deliveries
+(
+    id                  BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY NOT NULL,
+    email_type          TEXT                                            NOT NULL, -- verification_code, review_result, review_update
+    recipient_hash      TEXT                                            NOT NULL, -- Hex of SHA-256 of the lowercase address
+    provider            TEXT,                                                     -- NULL if every transport failed
+    provider_message_id TEXT,
+    status              TEXT                                            NOT NULL, -- sent, failed, delivered, bounced, complained
+    status_detail       TEXT,
+    create_time         TIMESTAMPTZ                                     NOT NULL,
+    update_time         TIMESTAMPTZ                                     NOT NULL
+);
+
+CREATE INDEX idx_email_deliveries_recipient ON email_deliveries (recipient_hash, create_time DESC);
+CREATE INDEX idx_email_deliveries_message_id ON email_deliveries (provider_message_id);
@@ -0,0 +1,15 @@
+-- Replace the dense order index with a fractional sort key, see `util::fractional_index`.
+-- The backfilled keys are zero-padded decimals ending with 1, which are valid base-62 keys.
+ALTER TABLE playlist_songs ADD COLUMN sort_key TEXT COLLATE "C";
+
+UPDATE playlist_songs ps
+SET sort_key = lpad((r.rank * 10 + 1)::TEXT, 8, '0')
+FROM (SELECT playlist_id, song_id, row_number() OVER (PARTITION BY playlist_id ORDER BY order_index, add_time) AS rank
+      FROM playlist_songs) r
+WHERE ps.playlist_id = r.playlist_id
+  AND ps.song_id = r.song_id;
+
+ALTER TABLE playlist_songs ALTER COLUMN sort_key SET NOT NULL;
+ALTER TABLE playlist_songs DROP COLUMN order_index;
+
+CREATE INDEX idx_playlist_songs_sort_key ON playlist_songs (playlist_id, sort_key);
@@ -0,0 +1,3 @@
+-- Playlists without a custom cover use a collage of their first songs' covers
+ALTER TABLE playlists ADD COLUMN use_song_cover BOOLEAN NOT NULL DEFAULT TRUE;
+UPDATE playlists SET use_song_cover = FALSE WHERE cover_url IS NOT NULL;
@@ -0,0 +1,9 @@
+CREATE TABLE user_preferences
+(
+    user_id          BIGINT PRIMARY KEY,
+    favorite_tag_ids BIGINT[]                 NOT NULL DEFAULT '{}',
+    blocked_tag_ids  BIGINT[]                 NOT NULL DEFAULT '{}',
+    create_time      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    update_time      TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+COMMENT ON TABLE user_preferences IS 'The listening preferences set by users, the preferences of users without a row are inferred from their play history.';
@@ -0,0 +1,12 @@
+CREATE TABLE featured_songs
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    collection  VARCHAR(64)              NOT NULL,
+    song_id     BIGINT                   NOT NULL,
+    position    INT                      NOT NULL DEFAULT 0,
+    note        TEXT,
+    featured_by BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (collection, song_id)
+);
+COMMENT ON TABLE featured_songs IS 'Songs picked by contributors into editorial collections, e.g. the onboarding starter set.';
@@ -0,0 +1,5 @@
+-- Credits of registered users other than the uploader are pending until the user confirms them
+ALTER TABLE song_production_crew
+    ADD COLUMN pending BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_song_production_crew_pending_uid ON song_production_crew (uid) WHERE pending;
@@ -0,0 +1,28 @@
+CREATE TABLE mentions
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    -- 'post', 'review_comment'
+    source_type VARCHAR(32)              NOT NULL,
+    source_id   BIGINT                   NOT NULL,
+    user_id     BIGINT                   NOT NULL,
+    -- The name as written after `@`, may differ from the username after a rename
+    name        VARCHAR(64)              NOT NULL,
+    author_uid  BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (source_type, source_id, user_id)
+);
+
+CREATE INDEX idx_mentions_user_id ON mentions (user_id);
+
+CREATE TABLE notifications
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id     BIGINT                   NOT NULL,
+    type        VARCHAR(32)              NOT NULL,
+    actor_uid   BIGINT,
+    data        JSONB                    NOT NULL DEFAULT '{}',
+    read_time   TIMESTAMP WITH TIME ZONE,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_notifications_user_id ON notifications (user_id, id DESC);
@@ -0,0 +1,24 @@
+-- Daily click-through rollups of the song search, for evaluating the ranking changes
+CREATE TABLE search_query_stats
+(
+    date               DATE         NOT NULL,
+    -- Trimmed and lowercased, empty for the filter-only searches
+    query              VARCHAR(128) NOT NULL,
+    sort_by            VARCHAR(32)  NOT NULL,
+    searches           BIGINT       NOT NULL DEFAULT 0,
+    -- The searches with at least one clicked hit
+    clicked_searches   BIGINT       NOT NULL DEFAULT 0,
+    clicks             BIGINT       NOT NULL DEFAULT 0,
+    -- The sum of the zero-based positions of the clicked hits
+    click_position_sum BIGINT       NOT NULL DEFAULT 0,
+    PRIMARY KEY (date, query, sort_by)
+);
+
+CREATE TABLE search_click_position_stats
+(
+    date     DATE        NOT NULL,
+    sort_by  VARCHAR(32) NOT NULL,
+    position INT         NOT NULL,
+    clicks   BIGINT      NOT NULL DEFAULT 0,
+    PRIMARY KEY (date, sort_by, position)
+);
@@ -0,0 +1,6 @@
+ALTER TABLE song_publishing_review
+    ADD COLUMN reviewer_uid BIGINT;
+COMMENT ON COLUMN song_publishing_review.reviewer_uid IS 'The contributor who approved or rejected the review, NULL for the pending and the old reviews.';
+
+CREATE INDEX idx_song_publishing_review_reviewer_uid ON song_publishing_review (reviewer_uid, review_time);
+CREATE INDEX idx_song_publishing_review_submit_time ON song_publishing_review (submit_time);
@@ -0,0 +1,28 @@
+-- The external identities a user can log in with, the password is the other login method
+CREATE TABLE user_oauth_identities
+(
+    id                 BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id            BIGINT                   NOT NULL,
+    -- 'github', 'google'
+    provider           VARCHAR(32)              NOT NULL,
+    provider_user_id   TEXT                     NOT NULL,
+    provider_user_name TEXT                     NOT NULL,
+    provider_email     TEXT,
+    create_time        TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_login_time    TIMESTAMP WITH TIME ZONE,
+    UNIQUE (provider, provider_user_id),
+    UNIQUE (user_id, provider)
+);
+
+CREATE TABLE user_account_audit_logs
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id     BIGINT                   NOT NULL,
+    -- 'oauth_link', 'oauth_unlink'
+    action      VARCHAR(32)              NOT NULL,
+    detail      JSONB                    NOT NULL DEFAULT '{}',
+    ip          TEXT,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_account_audit_logs_user_id ON user_account_audit_logs (user_id, create_time DESC);
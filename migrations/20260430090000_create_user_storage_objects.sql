@@ -0,0 +1,16 @@
+-- The uploaded objects accounted to the users, for the storage quota.
+-- An object is removed when it's deleted from the bucket, see the storage webhook.
+CREATE TABLE user_storage_objects
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id     BIGINT                   NOT NULL,
+    object_key  TEXT                     NOT NULL,
+    -- 'audio', 'image'
+    kind        VARCHAR(16)              NOT NULL,
+    size        BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- The images are stored by their hash, uploading the same image again is not counted twice
+    UNIQUE (user_id, object_key)
+);
+
+CREATE INDEX idx_user_storage_objects_object_key ON user_storage_objects (object_key);
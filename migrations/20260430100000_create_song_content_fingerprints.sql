@@ -0,0 +1,18 @@
+-- The SimHash of the title and lyrics of the songs, for finding the re-uploads in the reviews.
+-- The hash is split into 4 bands of 16 bits, two hashes within 3 bits of distance share at least one band.
+CREATE TABLE song_content_fingerprints
+(
+    song_id     BIGINT PRIMARY KEY,
+    -- NULL if the content is too short to be compared
+    simhash     BIGINT,
+    band0       INT,
+    band1       INT,
+    band2       INT,
+    band3       INT,
+    update_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_song_content_fingerprints_band0 ON song_content_fingerprints (band0);
+CREATE INDEX idx_song_content_fingerprints_band1 ON song_content_fingerprints (band1);
+CREATE INDEX idx_song_content_fingerprints_band2 ON song_content_fingerprints (band2);
+CREATE INDEX idx_song_content_fingerprints_band3 ON song_content_fingerprints (band3);
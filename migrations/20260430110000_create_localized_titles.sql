@@ -0,0 +1,14 @@
+-- The titles of the songs and playlists in the other languages, selected by the `Accept-Language` of the requests.
+-- For the playlists, the title and subtitle are the name and description.
+CREATE TABLE localized_titles
+(
+    -- 'song', 'playlist'
+    entity_type VARCHAR(16)              NOT NULL,
+    entity_id   BIGINT                   NOT NULL,
+    -- BCP 47 language tag, e.g. 'en', 'ja', 'zh-TW'
+    lang        VARCHAR(16)              NOT NULL,
+    title       TEXT                     NOT NULL,
+    subtitle    TEXT                     NOT NULL DEFAULT '',
+    update_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (entity_type, entity_id, lang)
+);
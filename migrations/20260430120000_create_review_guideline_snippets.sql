@@ -0,0 +1,28 @@
+-- The canned guidance inserted into the review comments by the reviewers, edited by the admins.
+CREATE TABLE review_guideline_snippets
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    slug        VARCHAR(64)              NOT NULL UNIQUE,
+    title       TEXT                     NOT NULL,
+    content     TEXT                     NOT NULL,
+    -- Increased on every edit, all the versions are kept in `review_guideline_snippet_versions`
+    version     INT                      NOT NULL DEFAULT 1,
+    -- Hidden from the reviewers, kept for the history
+    archived    BOOLEAN                  NOT NULL DEFAULT FALSE,
+    update_by   BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    update_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE review_guideline_snippet_versions
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    snippet_id  BIGINT                   NOT NULL,
+    version     INT                      NOT NULL,
+    title       TEXT                     NOT NULL,
+    content     TEXT                     NOT NULL,
+    archived    BOOLEAN                  NOT NULL,
+    editor_uid  BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (snippet_id, version)
+);
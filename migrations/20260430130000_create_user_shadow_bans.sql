@@ -0,0 +1,12 @@
+-- The spam accounts whose interactions are accepted but not counted for the others, see `service::shadow_ban`.
+-- Unlike `users.is_banned`, nothing changes for the user themselves.
+CREATE TABLE user_shadow_bans
+(
+    user_id     BIGINT PRIMARY KEY,
+    -- The shadow-banned features, e.g. `likes`, `plays`
+    features    TEXT[]                   NOT NULL,
+    reason      TEXT                     NOT NULL DEFAULT '',
+    update_by   BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    update_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,22 @@
+-- The TOTP second factor of the users, see `service::totp`
+CREATE TABLE user_totp_secrets
+(
+    user_id      BIGINT PRIMARY KEY,
+    -- Base32 without padding, as shown to the authenticator apps
+    secret       TEXT                     NOT NULL,
+    -- NULL until a code of the secret is confirmed, the 2FA is only required after that
+    confirm_time TIMESTAMP WITH TIME ZONE,
+    create_time  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- The single-use codes for logging in when the authenticator is lost, replaced on each confirmation
+CREATE TABLE user_recovery_codes
+(
+    id          BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id     BIGINT                   NOT NULL,
+    -- The hex of SHA-256 of the normalized code
+    code_hash   TEXT                     NOT NULL,
+    use_time    TIMESTAMP WITH TIME ZONE,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, code_hash)
+);
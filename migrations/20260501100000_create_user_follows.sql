@@ -0,0 +1,11 @@
+CREATE TABLE user_follows
+(
+    follower_id BIGINT                   NOT NULL,
+    followee_id BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (follower_id, followee_id),
+    CHECK (follower_id <> followee_id)
+);
+
+CREATE INDEX idx_user_follows_following ON user_follows (follower_id, create_time DESC);
+CREATE INDEX idx_user_follows_followers ON user_follows (followee_id, create_time DESC);
@@ -0,0 +1,26 @@
+CREATE TABLE song_comments
+(
+    id           BIGSERIAL PRIMARY KEY,
+    song_id      BIGINT                   NOT NULL,
+    user_id      BIGINT                   NOT NULL,
+    -- The root comment of the thread, null for the root comments
+    parent_id    BIGINT REFERENCES song_comments (id) ON DELETE CASCADE,
+    -- The author of the replied comment, null if replying to the root comment
+    reply_to_uid BIGINT,
+    content      TEXT                     NOT NULL,
+    create_time  TIMESTAMP WITH TIME ZONE NOT NULL,
+    update_time  TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+CREATE INDEX idx_song_comments_song_id ON song_comments (song_id, create_time DESC) WHERE parent_id IS NULL;
+CREATE INDEX idx_song_comments_parent_id ON song_comments (parent_id, create_time);
+
+CREATE TABLE song_comment_reports
+(
+    id           BIGSERIAL PRIMARY KEY,
+    comment_id   BIGINT                   NOT NULL REFERENCES song_comments (id) ON DELETE CASCADE,
+    reporter_uid BIGINT                   NOT NULL,
+    reason       TEXT                     NOT NULL,
+    create_time  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (comment_id, reporter_uid)
+);
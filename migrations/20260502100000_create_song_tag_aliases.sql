@@ -0,0 +1,10 @@
+CREATE TABLE song_tag_aliases
+(
+    id          BIGSERIAL PRIMARY KEY,
+    -- The canonical tag
+    tag_id      BIGINT                   NOT NULL REFERENCES song_tags (id) ON DELETE CASCADE,
+    alias       TEXT                     NOT NULL UNIQUE,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_song_tag_aliases_tag_id ON song_tag_aliases (tag_id);
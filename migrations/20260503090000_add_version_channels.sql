@@ -0,0 +1,13 @@
+ALTER TABLE version ADD COLUMN channel VARCHAR(16) NOT NULL DEFAULT 'stable';
+COMMENT ON COLUMN version.channel IS 'stable, beta or nightly, the clients of a channel also get the releases of the more stable channels';
+
+DROP INDEX version_variant_idx;
+CREATE INDEX version_variant_idx ON version (variant, channel, release_time DESC);
+
+CREATE TABLE user_release_channels
+(
+    user_id     BIGINT PRIMARY KEY,
+    channel     VARCHAR(16)              NOT NULL,
+    update_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+COMMENT ON TABLE user_release_channels IS 'The release channels opted in by users, the users without a row are on the stable channel.';
@@ -0,0 +1,5 @@
+ALTER TABLE refresh_tokens
+    ADD device_name      VARCHAR(64) DEFAULT NULL,
+    ADD trust_token_hash VARCHAR(64) DEFAULT NULL,
+    ADD trust_time       TIMESTAMP WITH TIME ZONE DEFAULT NULL;
+CREATE INDEX idx_refresh_tokens_trust_token_hash ON refresh_tokens (trust_token_hash) WHERE trust_token_hash IS NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN preferred_language VARCHAR(16) DEFAULT NULL;
+COMMENT ON COLUMN users.preferred_language IS 'The language tag of the emails and notifications, e.g. zh-CN or en, NULL for the default';
@@ -0,0 +1,15 @@
+-- The lower-bitrate renditions transcoded from the audio of the songs, for streaming on mobile networks.
+-- `source_url` is the audio they are transcoded from, the renditions of a replaced audio are stale.
+CREATE TABLE song_audio_renditions
+(
+    id           BIGSERIAL PRIMARY KEY,
+    song_id      BIGINT                   NOT NULL,
+    -- aac | opus
+    codec        TEXT                     NOT NULL,
+    bitrate_kbps INT                      NOT NULL,
+    file_url     TEXT                     NOT NULL,
+    size         BIGINT                   NOT NULL,
+    source_url   TEXT                     NOT NULL,
+    create_time  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (song_id, codec, bitrate_kbps)
+);
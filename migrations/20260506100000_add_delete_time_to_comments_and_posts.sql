@@ -0,0 +1,7 @@
+-- The deleted comments and posts are kept as tombstones with the content cleared, so the threads stay intact,
+-- and purged after the retention, see `service::tombstone`
+ALTER TABLE song_comments ADD COLUMN delete_time TIMESTAMP WITH TIME ZONE;
+ALTER TABLE posts ADD COLUMN delete_time TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_song_comments_delete_time ON song_comments (delete_time) WHERE delete_time IS NOT NULL;
+CREATE INDEX idx_posts_delete_time ON posts (delete_time) WHERE delete_time IS NOT NULL;
@@ -0,0 +1 @@
+CREATE INDEX idx_notifications_unread ON notifications (user_id) WHERE read_time IS NULL;
@@ -0,0 +1,20 @@
+CREATE TABLE roles
+(
+    name        VARCHAR(32) PRIMARY KEY,
+    description TEXT                     NOT NULL DEFAULT '',
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+COMMENT ON TABLE roles IS 'The roles that can be granted to users, see service::contributor';
+
+INSERT INTO roles (name, description) VALUES ('contributor', 'Reviews the songs and moderates the community');
+
+CREATE TABLE user_roles
+(
+    user_id     BIGINT                   NOT NULL,
+    role        VARCHAR(32)              NOT NULL REFERENCES roles (name),
+    -- NULL if granted from the config at the first startup
+    granted_by  BIGINT,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, role)
+);
+CREATE INDEX idx_user_roles_role ON user_roles (role);
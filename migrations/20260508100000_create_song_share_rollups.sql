@@ -0,0 +1,11 @@
+-- The shares of the songs counted per day and platform, the shares are not kept one by one
+CREATE TABLE song_share_rollups
+(
+    song_id     BIGINT      NOT NULL,
+    day         DATE        NOT NULL,
+    -- See service::song_share::SharePlatform
+    platform    VARCHAR(16) NOT NULL,
+    share_count BIGINT      NOT NULL DEFAULT 0,
+    PRIMARY KEY (song_id, day, platform)
+);
+CREATE INDEX idx_song_share_rollups_day ON song_share_rollups (day);
@@ -0,0 +1,26 @@
+-- The versions of the terms of service and the privacy policy
+CREATE TABLE legal_documents
+(
+    id           BIGSERIAL PRIMARY KEY,
+    -- terms | privacy
+    kind         VARCHAR(16)              NOT NULL,
+    version      INT                      NOT NULL,
+    title        TEXT                     NOT NULL,
+    -- Markdown
+    content      TEXT                     NOT NULL,
+    -- Whether the users must accept it again before logging in
+    mandatory    BOOLEAN                  NOT NULL,
+    publish_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    create_time  TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (kind, version)
+);
+
+-- The latest version of each kind accepted by the users
+CREATE TABLE user_legal_acceptances
+(
+    user_id     BIGINT                   NOT NULL,
+    kind        VARCHAR(16)              NOT NULL,
+    version     INT                      NOT NULL,
+    accept_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    PRIMARY KEY (user_id, kind)
+);
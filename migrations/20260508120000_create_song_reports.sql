@@ -0,0 +1,28 @@
+CREATE TABLE song_reports
+(
+    id              BIGSERIAL PRIMARY KEY,
+    song_id         BIGINT                   NOT NULL,
+    reporter_uid    BIGINT                   NOT NULL,
+    -- copyright | explicit_content | wrong_origin_info
+    category        VARCHAR(32)              NOT NULL,
+    reason          TEXT                     NOT NULL,
+    -- 0: pending, 1: resolved, 2: dismissed
+    status          SMALLINT                 NOT NULL DEFAULT 0,
+    resolution_note TEXT,
+    resolved_by     BIGINT,
+    resolve_time    TIMESTAMP WITH TIME ZONE,
+    create_time     TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+-- A user reports a song once until it's handled
+CREATE UNIQUE INDEX idx_song_reports_pending ON song_reports (song_id, reporter_uid) WHERE status = 0;
+CREATE INDEX idx_song_reports_status ON song_reports (status, create_time DESC);
+CREATE INDEX idx_song_reports_reporter ON song_reports (reporter_uid, create_time DESC);
+
+-- The songs hidden by the contributors, treated as not existing until unhidden
+CREATE TABLE hidden_songs
+(
+    song_id     BIGINT PRIMARY KEY,
+    reason      TEXT                     NOT NULL,
+    hidden_by   BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+-- The links to support the creators on the donation platforms, shown on their profiles and songs
+CREATE TABLE user_support_links
+(
+    user_id     BIGINT                   NOT NULL,
+    -- afdian | patreon | kofi | paypal | github_sponsors
+    platform    VARCHAR(32)              NOT NULL,
+    url         VARCHAR(500)             NOT NULL,
+    -- The order shown on the profile, from 0
+    position    INT                      NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, platform)
+);
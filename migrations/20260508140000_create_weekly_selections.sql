@@ -0,0 +1,8 @@
+-- The official weekly selection playlists, the ones of the previous weeks are kept as the archive
+CREATE TABLE weekly_selections
+(
+    -- The Monday of the week
+    week_start  DATE PRIMARY KEY,
+    playlist_id BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
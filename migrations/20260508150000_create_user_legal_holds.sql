@@ -0,0 +1,13 @@
+-- The users whose data is exempted from the retention purges, e.g. under an investigation
+CREATE TABLE user_legal_holds
+(
+    user_id     BIGINT PRIMARY KEY,
+    reason      TEXT                     NOT NULL,
+    held_by     BIGINT                   NOT NULL,
+    create_time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- For purging by the time
+CREATE INDEX idx_notifications_create_time ON notifications (create_time);
+CREATE INDEX idx_user_play_history_create_time ON user_play_history (create_time);
+CREATE INDEX idx_user_account_audit_logs_create_time ON user_account_audit_logs (create_time);
@@ -0,0 +1,10 @@
+-- The plays of the songs counted per day in UTC, rolled up from song_plays periodically for the hot songs,
+-- see service::song_play::ROLLUP_JOB
+CREATE TABLE song_play_counts_daily
+(
+    song_id    BIGINT NOT NULL,
+    day        DATE   NOT NULL,
+    play_count BIGINT NOT NULL,
+    PRIMARY KEY (song_id, day)
+);
+CREATE INDEX idx_song_play_counts_daily_day ON song_play_counts_daily (day);
@@ -0,0 +1,2 @@
+-- The review queue of the contributors, filtered by the status and paged by the submit time
+CREATE INDEX idx_song_publishing_review_status_submit_time ON song_publishing_review (status, submit_time DESC, id DESC);
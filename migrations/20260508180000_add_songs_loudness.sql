@@ -0,0 +1,3 @@
+ALTER TABLE songs
+    ADD COLUMN loudness_lufs REAL,
+    ADD COLUMN true_peak REAL;
@@ -0,0 +1,7 @@
+-- The plays of the songs purged from song_plays by the retention, so the play counts recounted from song_plays
+-- stay the same, see service::retention
+CREATE TABLE song_archived_play_counts
+(
+    song_id    BIGINT PRIMARY KEY,
+    play_count BIGINT NOT NULL
+);
@@ -0,0 +1,7 @@
+-- The lookups of the storage reconciliation and the GC, which check whether an object URL is referenced
+CREATE INDEX idx_songs_file_url ON songs (file_url);
+CREATE INDEX idx_songs_cover_art_url ON songs (cover_art_url);
+CREATE INDEX idx_song_audio_renditions_file_url ON song_audio_renditions (file_url);
+CREATE INDEX idx_users_avatar_url ON users (avatar_url);
+CREATE INDEX idx_playlists_cover_url ON playlists (cover_url);
+CREATE INDEX idx_posts_cover_url ON posts (cover_url);
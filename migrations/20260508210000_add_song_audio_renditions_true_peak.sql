@@ -0,0 +1,3 @@
+-- The true peak of each rendition in dBTP, the lossy encoding overshoots the peak of the source audio
+ALTER TABLE song_audio_renditions
+    ADD COLUMN true_peak REAL;
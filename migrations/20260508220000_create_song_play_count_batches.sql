@@ -0,0 +1,7 @@
+-- The batches of the pending plays added to songs.play_count, so a batch left in Redis by a flush that failed
+-- after committing is not added again, see service::song_play::flush_pending_plays
+CREATE TABLE song_play_count_batches
+(
+    batch_id    TEXT PRIMARY KEY,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now()
+);
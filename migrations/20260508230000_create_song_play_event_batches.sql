@@ -0,0 +1,7 @@
+-- The batches of the buffered play events inserted into song_plays, so a batch claimed again by a flush after the
+-- lock of a slow one expired is not inserted twice, see service::song_play::flush_play_events
+CREATE TABLE song_play_event_batches
+(
+    batch_id    TEXT PRIMARY KEY,
+    create_time TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,218 @@
+//! The integrated loudness and the true peak of EBU R128, measured as ITU-R BS.1770-4.
+//!
+//! Unlike the ReplayGain, they're comparable with the loudness of the other platforms, e.g. -14 LUFS of the streaming
+//! services, and the true peak accounts for the peaks between the samples, which clip after the lossy encoding.
+
+use std::f64::consts::PI;
+
+/// The gating blocks are 400ms, overlapping by 75%
+const SEGMENT_SECS: f64 = 0.1;
+const SEGMENTS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+/// The samples are oversampled by 4 to find the true peak
+const OVERSAMPLING: usize = 4;
+/// The taps on each side of the interpolation filter
+const HALF_TAPS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS
+    pub integrated_lufs: f32,
+    /// True peak in dBTP
+    pub true_peak_dbtp: f32,
+}
+
+/// Measure the interleaved samples, `None` if it's shorter than a gating block or silent.
+///
+/// The channels are weighted equally except the LFE and the surround channels of 5.1 audio.
+pub fn measure(samples: &[f32], channels: usize, sample_rate: u32) -> Option<Loudness> {
+    if channels == 0 || sample_rate == 0 {
+        return None;
+    }
+    let integrated_lufs = integrated_loudness(samples, channels, sample_rate)?;
+    let true_peak = true_peak(samples, channels);
+    if true_peak <= 0.0 {
+        return None;
+    }
+    Some(Loudness {
+        integrated_lufs: integrated_lufs as f32,
+        true_peak_dbtp: (20.0 * true_peak.log10()) as f32,
+    })
+}
+
+/// A second-order IIR filter in the direct form I
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0] - self.a[2] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// The K-weighting of BS.1770, the high shelf modeling the head followed by the high pass, derived for the sample
+/// rate from the coefficients of 48kHz
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+    [shelf, high_pass]
+}
+
+fn channel_weight(channels: usize, channel: usize) -> f64 {
+    match (channels, channel) {
+        // L, R, C, LFE, Ls, Rs
+        (6, 3) => 0.0,
+        (6, 4 | 5) => 1.41,
+        _ => 1.0,
+    }
+}
+
+fn energy_to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+fn integrated_loudness(samples: &[f32], channels: usize, sample_rate: u32) -> Option<f64> {
+    let segment_len = ((sample_rate as f64 * SEGMENT_SECS).round() as usize).max(1);
+    let mut filters = vec![k_weighting(sample_rate); channels];
+    let weights = (0..channels).map(|x| channel_weight(channels, x)).collect::<Vec<_>>();
+
+    // The weighted sum of the squared K-weighted samples of each 100ms segment, the incomplete last one is dropped
+    let mut segments = Vec::with_capacity(samples.len() / channels / segment_len + 1);
+    let mut sum = 0.0;
+    let mut count = 0;
+    for frame in samples.chunks_exact(channels) {
+        for (channel, sample) in frame.iter().enumerate() {
+            let [shelf, high_pass] = &mut filters[channel];
+            let y = high_pass.process(shelf.process(*sample as f64));
+            sum += weights[channel] * y * y;
+        }
+        count += 1;
+        if count == segment_len {
+            segments.push(sum);
+            sum = 0.0;
+            count = 0;
+        }
+    }
+
+    let blocks = segments.windows(SEGMENTS_PER_BLOCK)
+        .map(|x| x.iter().sum::<f64>() / (SEGMENTS_PER_BLOCK * segment_len) as f64)
+        .filter(|x| *x > 0.0 && energy_to_lufs(*x) > ABSOLUTE_GATE_LUFS)
+        .collect::<Vec<_>>();
+    if blocks.is_empty() {
+        return None;
+    }
+    let relative_gate = energy_to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) + RELATIVE_GATE_LU;
+    let gated = blocks.iter().filter(|x| energy_to_lufs(**x) > relative_gate).collect::<Vec<_>>();
+    if gated.is_empty() {
+        return None;
+    }
+    Some(energy_to_lufs(gated.iter().copied().sum::<f64>() / gated.len() as f64))
+}
+
+/// The taps of the Hann windowed sinc interpolating the samples at `phase / OVERSAMPLING` after each sample,
+/// from the sample `HALF_TAPS - 1` before it
+fn interpolation_taps() -> [[f64; HALF_TAPS * 2]; OVERSAMPLING - 1] {
+    let mut taps = [[0.0; HALF_TAPS * 2]; OVERSAMPLING - 1];
+    for (phase, taps) in taps.iter_mut().enumerate() {
+        let offset = (phase + 1) as f64 / OVERSAMPLING as f64;
+        for (i, tap) in taps.iter_mut().enumerate() {
+            let x = offset + (HALF_TAPS - 1) as f64 - i as f64;
+            let sinc = (PI * x).sin() / (PI * x);
+            let window = 0.5 * (1.0 + (PI * x / HALF_TAPS as f64).cos());
+            *tap = sinc * window;
+        }
+    }
+    taps
+}
+
+/// The absolute peak of the interleaved samples oversampled, of all channels
+fn true_peak(samples: &[f32], channels: usize) -> f64 {
+    let taps = interpolation_taps();
+    let frames = samples.len() / channels;
+    let mut peak = 0f64;
+    for channel in 0..channels {
+        let sample = |i: usize| samples[i * channels + channel] as f64;
+        for i in 0..frames {
+            peak = peak.max(sample(i).abs());
+            if i + 1 < HALF_TAPS || i + HALF_TAPS >= frames {
+                continue;
+            }
+            let start = i + 1 - HALF_TAPS;
+            for taps in &taps {
+                let y = taps.iter().enumerate().map(|(j, tap)| tap * sample(start + j)).sum::<f64>();
+                peak = peak.max(y.abs());
+            }
+        }
+    }
+    peak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, amplitude: f64, phase: f64, channels: usize, sample_rate: u32, secs: f64) -> Vec<f32> {
+        let frames = (sample_rate as f64 * secs) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let x = amplitude * (2.0 * PI * freq * i as f64 / sample_rate as f64 + phase).sin();
+                std::iter::repeat_n(x as f32, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_measure() {
+        // EBU Tech 3341 case 1, a stereo 1kHz sine at -23 dBFS measures -23 LUFS
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        for sample_rate in [44100, 48000] {
+            let loudness = measure(&sine(1000.0, amplitude, 0.0, 2, sample_rate, 20.0), 2, sample_rate).unwrap();
+            assert!((loudness.integrated_lufs + 23.0).abs() < 0.1, "{sample_rate}: {loudness:?}");
+            assert!((loudness.true_peak_dbtp + 23.0).abs() < 0.1, "{sample_rate}: {loudness:?}");
+        }
+        // Too short or silent
+        assert_eq!(None, measure(&sine(1000.0, 0.5, 0.0, 2, 48000, 0.3), 2, 48000));
+        assert_eq!(None, measure(&vec![0.0; 48000 * 2], 2, 48000));
+    }
+
+    #[test]
+    fn test_true_peak() {
+        // The samples of a quarter sample rate sine shifted by 45° are all at -3 dB of its peak
+        let samples = sine(12000.0, 1.0, PI / 4.0, 1, 48000, 1.0);
+        assert!(samples.iter().all(|x| (x.abs() - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.001));
+        let peak_dbtp = 20.0 * true_peak(&samples, 1).log10();
+        assert!(peak_dbtp > -0.5, "{peak_dbtp}");
+    }
+}
@@ -1,3 +1,5 @@
+pub mod loudness;
+
 use anyhow::{anyhow, Context};
 use replaygain::ReplayGain;
 use symphonia::core::audio::{SampleBuffer, SignalSpec};
@@ -6,7 +8,7 @@ use symphonia::core::codecs::{CodecType, DecoderOptions};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader, Track};
 use symphonia::core::io::{MediaSource, MediaSourceStream, MediaSourceStreamOptions};
-use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+use symphonia::core::meta::{MetadataOptions, StandardTagKey, StandardVisualKey, Visual};
 use symphonia::core::probe::Hint;
 use thiserror::Error;
 use tracing::{debug, info, warn};
@@ -37,7 +39,26 @@ pub struct PickedMetadata {
     pub sample_rate: u32,
     pub duration_secs: u64,
     pub peak: f32,
-    pub gain_db: f32
+    pub gain_db: f32,
+    /// `None` if it's too short or silent
+    pub loudness: Option<loudness::Loudness>,
+    pub cover: Option<EmbeddedCover>,
+}
+
+/// The cover art embedded in the audio, e.g. `APIC` of ID3 or `PICTURE` of FLAC
+#[derive(Clone)]
+pub struct EmbeddedCover {
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for EmbeddedCover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmbeddedCover")
+            .field("media_type", &self.media_type)
+            .field("len", &self.data.len())
+            .finish()
+    }
 }
 
 pub fn parse_and_validate(
@@ -54,6 +75,8 @@ pub fn parse_and_validate(
         duration_secs: 0,
         peak: 0f32,
         gain_db: 0f32,
+        loudness: None,
+        cover: None,
     };
 
     let media = MediaSourceStream::new(input, MediaSourceStreamOptions::default());
@@ -96,8 +119,7 @@ pub fn parse_and_validate(
         }
     }
 
-    // TODO: Retrieve cover image
-    // meta.visuals()
+    result.cover = pick_cover(metadata.visuals());
 
     // Find the default audio track
     let track = probed.format.default_track().ok_or_else(|| ParseError::TrackNotFound)?;
@@ -110,16 +132,58 @@ pub fn parse_and_validate(
     // Calculate duration
     result.duration_secs = calculate_duration_secs(&track)?.ok_or_else(|| ParseError::ParsingDurationError)?;
     result.sample_rate = track.codec_params.sample_rate.unwrap_or(0);
-    let (gain, peak) = calculate_gain_peak(&mut probed.format)
+    let (spec, samples) = read_interleaved_samples(&mut probed.format)
+        .and_then(|(spec, samples)| {
+            let (gain, peak) = calculate_gain_peak(&spec, &samples)?;
+            result.gain_db = gain;
+            result.peak = peak;
+            Ok((spec, samples))
+        })
         .map_err(|x| {
             warn!("Failed to calculate gain/peak: {x:?}");
             ParseError::CalculatingGainPeakError
         })?;
-    result.gain_db = gain;
-    result.peak = peak;
+    result.loudness = loudness::measure(&samples, spec.channels.count(), spec.rate);
     Ok(result)
 }
 
+/// Check the format from the beginning of a file, returns `None` if it's too short to tell.
+///
+/// It's cheap compared to [parse_and_validate], which decodes the whole file.
+pub fn probe_format(
+    head: Box<dyn MediaSource>,
+    file_name: Option<&str>,
+) -> Result<Option<&'static str>, ParseError> {
+    let media = MediaSourceStream::new(head, MediaSourceStreamOptions::default());
+    let mut hint = Hint::default();
+    if let Some((_, ext)) = file_name.and_then(|name| name.rsplit_once(".")) {
+        hint.with_extension(ext);
+    }
+
+    let probed = match symphonia::default::get_probe()
+        .format(&hint, media, &FormatOptions::default(), &MetadataOptions::default()) {
+        Ok(x) => x,
+        // The headers are not complete, e.g. the MP4 atoms at the end of the file
+        Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(SymphoniaError::Unsupported(_)) => return Err(ParseError::FormatUnsupported),
+        Err(e) => return Err(ParseError::Parse(e)),
+    };
+    let track = probed.format.default_track().ok_or(ParseError::TrackNotFound)?;
+    get_format_str(track.codec_params.codec).map(Some).ok_or(ParseError::FormatUnsupported)
+}
+
+/// The front cover, or the first picture if none is marked as the front cover
+fn pick_cover(visuals: &[Visual]) -> Option<EmbeddedCover> {
+    let visuals = visuals.iter().filter(|x| !x.data.is_empty()).collect::<Vec<_>>();
+    let visual = visuals.iter()
+        .find(|x| x.usage == Some(StandardVisualKey::FrontCover))
+        .or(visuals.first())?;
+    Some(EmbeddedCover {
+        media_type: visual.media_type.clone(),
+        data: visual.data.to_vec(),
+    })
+}
+
 fn get_format_str(codec_type: CodecType) -> Option<&'static str> {
     match codec_type {
         codecs::CODEC_TYPE_MP3 => Some("mp3"),
@@ -128,6 +192,17 @@ fn get_format_str(codec_type: CodecType) -> Option<&'static str> {
         _ => None
     }
 }
+
+/// The MIME type of a format returned by [parse_and_validate]
+pub fn mime_type_of(format: &str) -> Option<&'static str> {
+    match format {
+        "mp3" => Some("audio/mpeg"),
+        "aac" => Some("audio/aac"),
+        "flac" => Some("audio/flac"),
+        _ => None
+    }
+}
+
 fn calculate_duration_secs(track: &Track) -> Result<Option<u64>, ParseError> {
     let r = if let Some(tb) = track.codec_params.time_base {
         let frames = track.codec_params.n_frames.ok_or_else(|| ParseError::ParsingDurationError)?;
@@ -139,11 +214,10 @@ fn calculate_duration_secs(track: &Track) -> Result<Option<u64>, ParseError> {
     Ok(r)
 }
 
-fn calculate_gain_peak(format: &mut Box<dyn FormatReader>) -> anyhow::Result<(f32, f32)> {
-    let (spec, samples) = read_interleaved_samples(format)?;
+fn calculate_gain_peak(spec: &SignalSpec, samples: &[f32]) -> anyhow::Result<(f32, f32)> {
     let mut rg = ReplayGain::new(spec.rate as usize)
         .ok_or_else(|| anyhow!("This sample rate is not supported: {}", spec.rate))?;
-    rg.process_samples(&samples);
+    rg.process_samples(samples);
     let (gain, peak) = rg.finish();
     Ok((gain, peak))
 }
@@ -218,8 +292,33 @@ fn read_interleaved_samples(format: &mut Box<dyn FormatReader>) -> anyhow::Resul
 
 #[cfg(test)]
 mod tests {
-    use crate::audio::parse_and_validate;
+    use crate::audio::{parse_and_validate, pick_cover};
     use std::fs;
+    use symphonia::core::meta::{StandardVisualKey, Visual};
+
+    fn visual(usage: Option<StandardVisualKey>, data: &[u8]) -> Visual {
+        Visual {
+            media_type: "image/jpeg".to_string(),
+            dimensions: None,
+            bits_per_pixel: None,
+            color_mode: None,
+            usage,
+            tags: vec![],
+            data: data.into(),
+        }
+    }
+
+    #[test]
+    fn test_pick_cover() {
+        assert!(pick_cover(&[]).is_none());
+        assert!(pick_cover(&[visual(Some(StandardVisualKey::FrontCover), &[])]).is_none());
+        let visuals = [
+            visual(Some(StandardVisualKey::BackCover), &[1]),
+            visual(Some(StandardVisualKey::FrontCover), &[2]),
+        ];
+        assert_eq!(vec![2], pick_cover(&visuals).unwrap().data);
+        assert_eq!(vec![1], pick_cover(&visuals[..1]).unwrap().data);
+    }
 
     #[test]
     fn test_parse() {
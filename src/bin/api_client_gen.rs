@@ -0,0 +1,17 @@
+use hachimi_world_server::web::{api, api_codegen};
+use std::fs;
+use std::path::Path;
+
+/// Generate the Rust client crate and the TypeScript types in `clients/` from the endpoint manifest,
+/// run by `cargo gen-api-client`
+fn main() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let manifest = api::manifest();
+    for (path, content) in [
+        (api_codegen::RUST_CLIENT_PATH, api_codegen::rust_client(&manifest)),
+        (api_codegen::TYPESCRIPT_PATH, api_codegen::typescript(&manifest)),
+    ] {
+        fs::write(root.join(path), content).unwrap();
+        println!("Generated {path}");
+    }
+}
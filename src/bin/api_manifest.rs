@@ -0,0 +1,6 @@
+use hachimi_world_server::web::api;
+
+/// Print the endpoint manifest as JSON, the input of the client code generation
+fn main() {
+    println!("{}", serde_json::to_string_pretty(&api::manifest()).unwrap());
+}
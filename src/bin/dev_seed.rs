@@ -0,0 +1,254 @@
+//! Fill the database with fake users, songs, tags, plays and likes for local development.
+//!
+//! Only the `local` storage backend is allowed, so it never writes to the production bucket. A placeholder audio
+//! (`DEV_SEED_AUDIO`, default `.local/test_res/test.mp3`) and cover (`DEV_SEED_COVER`, default
+//! `.local/test_res/test.webp`) are uploaded once and shared by all the seeded songs.
+//!
+//! The scale is set by `DEV_SEED_USERS` (default 50), `DEV_SEED_SONGS` (200), `DEV_SEED_PLAYS` (5000) and
+//! `DEV_SEED_LIKES` (1000). The song popularity follows a long tail, so the hot lists and the recommendations have
+//! something to rank. All seeded users have the password `password`.
+//!
+//! The songs and users are added to the search index if `meilisearch` is configured.
+use chrono::{Duration, Utc};
+use hachimi_world_server::audio;
+use hachimi_world_server::config::Config;
+use hachimi_world_server::db::song::{ISongDao, Song, SongDao, SongLike, SongPlay};
+use hachimi_world_server::db::song_tag::{ISongTagDao, SongTag, SongTagDao};
+use hachimi_world_server::db::user::{User, UserDao};
+use hachimi_world_server::db::CrudDao;
+use hachimi_world_server::file_hosting::{self, StorageBackend, StorageCfg, UploadOptions};
+use hachimi_world_server::search;
+use hachimi_world_server::search::user::UserDocument;
+use hachimi_world_server::service::song::generate_song_display_id;
+use itertools::Itertools;
+use rand::distr::weighted::WeightedIndex;
+use rand::distr::Distribution;
+use rand::prelude::IndexedRandom;
+use rand::Rng;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::{env, fs};
+
+const TAGS: &[&str] = &["哈基米", "电子", "摇滚", "古典", "说唱", "纯音乐", "翻唱", "原创", "鬼畜", "治愈", "VOCALOID", "ACG", "Lo-Fi", "Remix"];
+const TITLE_WORDS: &[&str] = &["哈基米", "南北绿豆", "曼波", "夏天", "星空", "Night", "Dream", "Cat", "回忆", "Summer", "猫猫", "心跳", "Rain", "Tokyo", "流星", "Remix"];
+const NAME_WORDS: &[&str] = &["小", "大", "猫", "狗", "星", "月", "风", "雪", "Neko", "Mambo", "Hachi", "Lucky", "Sky"];
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    let cfg = Config::parse(env::var("CONFIG_PATH").unwrap_or_else(|_| String::from("config.yaml")))?;
+    let user_count = env_or("DEV_SEED_USERS", 50)?;
+    let song_count = env_or("DEV_SEED_SONGS", 200)?;
+    let play_count = env_or("DEV_SEED_PLAYS", 5000)?;
+    let like_count = env_or("DEV_SEED_LIKES", 1000)?;
+
+    if user_count == 0 || song_count == 0 {
+        anyhow::bail!("DEV_SEED_USERS and DEV_SEED_SONGS must be at least 1 to upload, play and like the songs");
+    }
+    if StorageCfg::load(&cfg)?.backend != StorageBackend::Local {
+        anyhow::bail!("dev_seed only works with the `local` storage backend");
+    }
+    let db_cfg: DatabaseConfig = cfg.get_and_parse("db")?;
+    let pool = PgPool::connect(&format!("postgres://{}:{}@{}/{}", db_cfg.username, db_cfg.password, db_cfg.address, db_cfg.database)).await?;
+    let file_host = file_hosting::from_config(&cfg).await?;
+
+    // Placeholders
+    let audio_bytes = fs::read(env::var("DEV_SEED_AUDIO").unwrap_or_else(|_| ".local/test_res/test.mp3".to_string()))?;
+    let cover_bytes = fs::read(env::var("DEV_SEED_COVER").unwrap_or_else(|_| ".local/test_res/test.webp".to_string()))?;
+    let metadata = audio::parse_and_validate(Box::new(Cursor::new(audio_bytes.clone())), Some("placeholder.mp3"))?;
+    let audio_url = file_host.upload(audio_bytes.into(), "songs/dev_seed_placeholder.mp3", &UploadOptions::audio("audio/mpeg")).await?.public_url;
+    let cover_url = file_host.upload(cover_bytes.into(), "images/cover/dev_seed_placeholder.webp", &UploadOptions::hashed_image("image/webp")).await?.public_url;
+
+    let mut rng = rand::rng();
+    let now = Utc::now();
+
+    // Tags
+    let mut tag_ids = Vec::new();
+    for name in TAGS {
+        let id = match SongTagDao::get_by_name(&pool, name).await? {
+            Some(x) => x.id,
+            None => SongTagDao::insert(&pool, &SongTag {
+                id: 0,
+                name: name.to_string(),
+                description: None,
+                is_active: true,
+                create_time: now,
+                update_time: now,
+            }).await?,
+        };
+        tag_ids.push(id);
+    }
+
+    // Users, the emails are suffixed by the run so it can be run repeatedly
+    let run = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+    let password_hash = bcrypt::hash("password", bcrypt::DEFAULT_COST)?;
+    let mut users = Vec::new();
+    for i in 0..user_count {
+        let name = format!("{}{}_{}{}", NAME_WORDS.choose(&mut rng).unwrap(), NAME_WORDS.choose(&mut rng).unwrap(), &run[..4], i);
+        let create_time = now - Duration::days(rng.random_range(30..365));
+        let mut user = User {
+            id: 0,
+            username: name,
+            email: format!("seed_{run}_{i}@example.com"),
+            password_hash: password_hash.clone(),
+            avatar_url: None,
+            bio: Some("Seeded for local development".to_string()),
+            gender: None,
+            is_banned: false,
+            last_login_time: None,
+            create_time,
+            update_time: create_time,
+            version: 0,
+            preferred_language: None,
+        };
+        user.id = UserDao::insert(&pool, &user).await?;
+        users.push(user);
+    }
+    println!("Seeded {} users, the first one is {}", users.len(), users.first().map_or("-", |x| x.email.as_str()));
+
+    // Songs, uploaded by a fifth of the users
+    let uploaders = &users[..users.len().div_ceil(5)];
+    let mut songs = Vec::new();
+    for _ in 0..song_count {
+        let release_time = now - Duration::minutes(rng.random_range(0..90 * 24 * 60));
+        let mut song = Song {
+            id: 0,
+            display_id: generate_song_display_id(),
+            title: TITLE_WORDS.choose_multiple(&mut rng, 2).join(" "),
+            subtitle: String::new(),
+            description: "Seeded for local development".to_string(),
+            artist: String::new(),
+            file_url: audio_url.clone(),
+            cover_art_url: cover_url.clone(),
+            lyrics: String::new(),
+            duration_seconds: metadata.duration_secs as i32,
+            uploader_uid: uploaders.choose(&mut rng).unwrap().id,
+            creation_type: rng.random_range(0..3),
+            play_count: 0,
+            like_count: 0,
+            is_private: false,
+            release_time,
+            create_time: release_time,
+            update_time: release_time,
+            explicit: Some(rng.random_bool(0.05)),
+            gain: Some(metadata.gain_db),
+            loudness_lufs: metadata.loudness.map(|x| x.integrated_lufs),
+            true_peak: metadata.loudness.map(|x| x.true_peak_dbtp),
+            version: 0,
+        };
+        // The display id is random, retry on the rare conflicts
+        song.id = match SongDao::insert(&pool, &song).await {
+            Ok(id) => id,
+            Err(_) => {
+                song.display_id = generate_song_display_id();
+                SongDao::insert(&pool, &song).await?
+            }
+        };
+        let tag_count = rng.random_range(1..=3);
+        for tag_id in tag_ids.choose_multiple(&mut rng, tag_count) {
+            sqlx::query!("INSERT INTO song_tag_refs (song_id, tag_id) VALUES ($1, $2)", song.id, tag_id)
+                .execute(&pool).await?;
+        }
+        songs.push(song);
+    }
+    println!("Seeded {} songs", songs.len());
+
+    // Plays, a long tail over the songs
+    let popularity = WeightedIndex::new((1..=songs.len()).map(|x| 1.0 / x as f64))?;
+    let plays = (0..play_count).map(|_| {
+        let song = &songs[popularity.sample(&mut rng)];
+        let since_release = (now - song.release_time).num_minutes().max(1);
+        SongPlay {
+            id: 0,
+            song_id: song.id,
+            user_id: Some(users.choose(&mut rng).unwrap().id),
+            anonymous_uid: None,
+            create_time: now - Duration::minutes(rng.random_range(0..since_release)),
+        }
+    }).collect_vec();
+    for chunk in plays.chunks(1000) {
+        SongDao::insert_plays(&pool, chunk).await?;
+    }
+    // The history keeps the latest play of each song per user
+    let mut history = HashMap::new();
+    for x in &plays {
+        let time = history.entry((x.user_id.unwrap(), x.song_id)).or_insert(x.create_time);
+        *time = (*time).max(x.create_time);
+    }
+    for chunk in history.into_iter().collect_vec().chunks(1000) {
+        sqlx::query!(
+            "INSERT INTO user_play_history (user_id, song_id, create_time)
+            SELECT * FROM UNNEST($1::bigint[], $2::bigint[], $3::timestamptz[])",
+            &chunk.iter().map(|((uid, _), _)| *uid).collect_vec(),
+            &chunk.iter().map(|((_, song_id), _)| *song_id).collect_vec(),
+            &chunk.iter().map(|(_, time)| *time).collect_vec(),
+        ).execute(&pool).await?;
+    }
+    println!("Seeded {} plays", plays.len());
+
+    // Likes, at most one per user and song
+    let mut liked = HashSet::new();
+    let max_likes = users.len() * songs.len();
+    let likes = std::iter::from_fn(|| {
+        let song = &songs[popularity.sample(&mut rng)];
+        let uid = users.choose(&mut rng).unwrap().id;
+        Some((song.id, uid))
+    })
+        .filter(|x| liked.insert(*x))
+        .take(like_count.min(max_likes))
+        .map(|(song_id, user_id)| SongLike {
+            song_id,
+            user_id,
+            playback_position_secs: None,
+            create_time: now,
+        })
+        .collect_vec();
+    for chunk in likes.chunks(1000) {
+        SongDao::insert_likes(&pool, chunk).await?;
+    }
+    println!("Seeded {} likes", likes.len());
+
+    // The counters of the songs are maintained by the play and like routes, which are bypassed here
+    for song in &songs {
+        SongDao::recount_stats(&pool, Some(song.id)).await?;
+    }
+    println!("Counted the plays and likes of the seeded songs");
+
+    if cfg.get("meilisearch")?.is_some() {
+        let meili_cfg: MeiliCfg = cfg.get_and_parse("meilisearch")?;
+        let client = meilisearch_sdk::client::Client::new(meili_cfg.host, Some(meili_cfg.api_key))?;
+        for chunk in songs.chunks(100) {
+            search::song::add_or_replace_document(&client, &pool, &chunk.iter().map(|x| x.id).collect_vec()).await?;
+        }
+        for x in &users {
+            search::user::update_user_document(&client, UserDocument {
+                id: x.id,
+                avatar_url: None,
+                name: x.username.clone(),
+                follower_count: 0,
+            }).await?;
+        }
+        println!("Indexed the seeded songs and users");
+    }
+    Ok(())
+}
+
+fn env_or(key: &str, default: usize) -> anyhow::Result<usize> {
+    Ok(env::var(key).ok().map(|x| x.parse()).transpose()?.unwrap_or(default))
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct DatabaseConfig {
+    pub address: String,
+    pub username: String,
+    pub password: String,
+    pub database: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct MeiliCfg {
+    pub host: String,
+    pub api_key: String,
+}
@@ -1,17 +1,18 @@
 use std::env;
-use aws_sdk_s3::config::Region;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tokio::time::Instant;
 use hachimi_world_server::config::Config;
-use hachimi_world_server::file_hosting::FileHost;
-use hachimi_world_server::service::upload::{scale_down_to_webp, ResizeType};
+use hachimi_world_server::file_hosting;
+use hachimi_world_server::file_hosting::UploadOptions;
+use hachimi_world_server::service::image::{self, ImageCfg, ImageProcessOptions};
+use hachimi_world_server::web::limits::LimitsCfg;
 
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
     let cfg = Config::parse(&env::var("COMPRESS_SONG_COVERS_CONFIG_PATH").unwrap()).unwrap();
     let db_cfg: DatabaseConfig = cfg.get_and_parse("db").unwrap();
-    let file_host = get_file_host(cfg).await.unwrap();
+    let file_host = file_hosting::from_config(&cfg).await.unwrap();
     let sql_pool = sqlx::PgPool::connect(&format!("postgres://{}:{}@{}/{}", db_cfg.username, db_cfg.password, db_cfg.address, db_cfg.database)).await.unwrap();
 
     let mut tx = sql_pool.begin().await.unwrap();
@@ -25,16 +26,18 @@ async fn main() {
             let start = Instant::now();
             let bytes = reqwest::get(&x.cover_art_url).await.unwrap().bytes().await.unwrap();
             let origin_size = bytes.len();
-            let data = scale_down_to_webp(1024, 1024, bytes, ResizeType::Fit, 90f32).unwrap();
+            let options = ImageProcessOptions { max_size: usize::MAX, ..ImageProcessOptions::song_cover(&ImageCfg::default(), &LimitsCfg::default()) };
+            let data = image::process(&bytes, &options).unwrap().data;
             let sha1 = openssl::sha::sha1(&data);
             let filename = format!("images/cover/{}.webp", hex::encode(sha1));
             let bytes = bytes::Bytes::from(data);
+            let upload_options = UploadOptions::hashed_image("image/webp");
             println!("Compress from {}bytes to {}bytes in {:?}.", origin_size, bytes.len(), start.elapsed());
 
-            let upload_result = file_host.upload(bytes.clone(), &filename).await.unwrap();
+            let upload_result = file_host.upload(bytes.clone(), &filename, &upload_options).await.unwrap();
             println!("Uploaded to {}", upload_result.public_url);
 
-            let result = file_host.upload(bytes, &filename).await.unwrap();
+            let result = file_host.upload(bytes, &filename, &upload_options).await.unwrap();
             sqlx::query!("UPDATE songs SET cover_art_url = $1 WHERE id = $2", &result.public_url, x.id).execute(&mut *tx).await.unwrap();
         }
     }
@@ -47,36 +50,3 @@ struct DatabaseConfig {
     pub password: String,
     pub database: String,
 }
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct S3Config {
-    pub bucket_name: String,
-    pub endpoint_url: String,
-    pub public_domain: String,
-    pub access_key_id: String,
-    pub access_key_secret: String,
-}
-async fn get_file_host(config: Config) -> anyhow::Result<FileHost> {
-    let cfg: S3Config = config.get_and_parse("s3")?;
-
-    // Configure the client
-    let config = aws_sdk_s3::Config::builder()
-        .endpoint_url(cfg.endpoint_url)
-        .credentials_provider(aws_sdk_s3::config::Credentials::new(
-            cfg.access_key_id,
-            cfg.access_key_secret,
-            None, // session token is not used with R2
-            None,
-            "R2",
-        ))
-        .region(Region::new("auto"))
-        .behavior_version_latest()
-        .build();
-
-    let client = aws_sdk_s3::Client::from_conf(config);
-    Ok(FileHost::new(
-        cfg.bucket_name,
-        cfg.public_domain,
-        client,
-    ))
-}
\ No newline at end of file
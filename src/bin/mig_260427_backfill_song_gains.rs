@@ -0,0 +1,155 @@
+//! Backfill the `gain`, `explicit` and the loudness of the songs published before the fields were added.
+//!
+//! The songs missing the gain or the loudness are processed by id in batches. Each audio is downloaded from the file host and
+//! run through the ReplayGain and the R128 pipelines, `BACKFILL_CONCURRENCY` (default 4) at a time. The songs are updated one by
+//! one and their detail caches are evicted after each batch.
+//!
+//! The last processed id is saved to `BACKFILL_CURSOR_PATH` (default `backfill_song_gains.cursor`), so an
+//! interrupted run continues from there. The songs failed to process are logged and skipped, delete the cursor
+//! file to retry them.
+//!
+//! The missing `explicit` flags are set to `false`, which is how the clients treat them already.
+use futures::{stream, StreamExt};
+use hachimi_world_server::audio;
+use hachimi_world_server::audio::PickedMetadata;
+use hachimi_world_server::config::Config;
+use hachimi_world_server::db::song::Song;
+use hachimi_world_server::file_hosting::{self, FileHost};
+use hachimi_world_server::service::song;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::{env, fs};
+use tokio::time::Instant;
+
+const BATCH_SIZE: i64 = 100;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    dotenv::dotenv().ok();
+    let cfg = Config::parse(&env::var("BACKFILL_SONG_GAINS_CONFIG_PATH")?)?;
+    let concurrency: usize = env::var("BACKFILL_CONCURRENCY").ok().map(|x| x.parse()).transpose()?.unwrap_or(4);
+    let cursor_path = env::var("BACKFILL_CURSOR_PATH").unwrap_or_else(|_| "backfill_song_gains.cursor".to_string());
+
+    let db_cfg: DatabaseConfig = cfg.get_and_parse("db")?;
+    let sql_pool = PgPool::connect(&format!("postgres://{}:{}@{}/{}", db_cfg.username, db_cfg.password, db_cfg.address, db_cfg.database)).await?;
+    let redis_cfg: RedisConfig = cfg.get_and_parse("redis")?;
+    let redis = redis::Client::open(redis_cfg.url())?.get_connection_manager().await?;
+    let file_host = file_hosting::from_config(&cfg).await?;
+
+    let mut cursor: i64 = match fs::read_to_string(&cursor_path) {
+        Ok(x) => x.trim().parse()?,
+        Err(_) => 0,
+    };
+    if cursor > 0 {
+        println!("Resuming after song id {cursor}");
+    }
+
+    let (mut updated, mut failed) = (0, 0);
+    loop {
+        let songs = sqlx::query_as!(
+            Song,
+            "SELECT * FROM songs WHERE (gain IS NULL OR loudness_lufs IS NULL) AND id > $1 ORDER BY id LIMIT $2",
+            cursor,
+            BATCH_SIZE,
+        ).fetch_all(&sql_pool).await?;
+        let Some(last) = songs.last() else {
+            break;
+        };
+        let last_id = last.id;
+
+        // Ordered, so the cursor never passes an unfinished song
+        let results: Vec<_> = stream::iter(songs.iter())
+            .map(|x| analyze(file_host.clone(), x))
+            .buffered(concurrency)
+            .collect().await;
+
+        let mut updated_songs = Vec::new();
+        for (x, result) in songs.iter().zip(results) {
+            match result {
+                Ok(metadata) => {
+                    sqlx::query!(
+                        "UPDATE songs SET
+                            gain = COALESCE(gain, $1),
+                            loudness_lufs = COALESCE(loudness_lufs, $2),
+                            true_peak = COALESCE(true_peak, $3)
+                        WHERE id = $4",
+                        metadata.gain_db,
+                        metadata.loudness.map(|x| x.integrated_lufs),
+                        metadata.loudness.map(|x| x.true_peak_dbtp),
+                        x.id,
+                    ).execute(&sql_pool).await?;
+                    updated_songs.push(x.clone());
+                }
+                Err(e) => {
+                    println!("Failed to process {} - {}: {:?}", x.display_id, x.title, e);
+                    failed += 1;
+                }
+            }
+        }
+        song::evict_detail_cache(redis.clone(), &updated_songs).await?;
+        updated += updated_songs.len();
+
+        cursor = last_id;
+        fs::write(&cursor_path, cursor.to_string())?;
+        println!("Processed up to song id {cursor}, {updated} updated, {failed} failed");
+    }
+
+    let songs = sqlx::query_as!(
+        Song,
+        "UPDATE songs SET explicit = false WHERE explicit IS NULL RETURNING *",
+    ).fetch_all(&sql_pool).await?;
+    song::evict_detail_cache(redis, &songs).await?;
+    println!("Set explicit of {} songs", songs.len());
+
+    println!("Done, {updated} updated, {failed} failed");
+    Ok(())
+}
+
+async fn analyze(file_host: Arc<dyn FileHost>, song: &Song) -> anyhow::Result<PickedMetadata> {
+    let start = Instant::now();
+    let key = file_host.key_of(&song.file_url)
+        .ok_or_else(|| anyhow::anyhow!("The file is not hosted here: {}", song.file_url))?;
+    let object = file_host.download(&key).await?
+        .ok_or_else(|| anyhow::anyhow!("The file is not found: {}", key))?;
+
+    // Decoding is CPU bound
+    let file_url = song.file_url.clone();
+    let metadata = tokio::task::spawn_blocking(move || {
+        audio::parse_and_validate(Box::new(Cursor::new(object.bytes)), Some(file_url.as_str()))
+    }).await??;
+    println!(
+        "Processed {} - {} in {:?}, gain: {}, loudness: {:?}",
+        song.display_id, song.title, start.elapsed(), metadata.gain_db, metadata.loudness,
+    );
+    Ok(metadata)
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct DatabaseConfig {
+    pub address: String,
+    pub username: String,
+    pub password: String,
+    pub database: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct RedisConfig {
+    pub address: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub database: Option<u16>,
+}
+
+impl RedisConfig {
+    fn url(&self) -> String {
+        format!(
+            "redis://{}{}{}{}",
+            self.username.clone().unwrap_or_default(),
+            self.password.as_ref().map_or(String::new(), |p| format!(":{}@", urlencoding::encode(p))),
+            self.address,
+            self.database.map_or(String::new(), |d| format!("/{d}")),
+        )
+    }
+}
@@ -3,23 +3,33 @@ use serde::de::DeserializeOwned;
 use serde_yaml::Value;
 use std::fs;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Config {
     value: Arc<Value>,
+    /// The file it's parsed from, for reloading
+    path: Option<PathBuf>,
 }
 
 impl Config {
     pub fn parse(path: impl AsRef<Path>) -> anyhow::Result<Self> {
         let content = fs::read_to_string(&path).with_context(|| format!("Failed to read config file: {:?}", path.as_ref()))?;
-        Self::parse_by_str(&content)
+        let mut config = Self::parse_by_str(&content)?;
+        config.path = Some(path.as_ref().to_path_buf());
+        Ok(config)
     }
 
     pub fn parse_by_str(str: &str) -> anyhow::Result<Self> {
         let value = serde_yaml::from_str::<Value>(str)?;
-        Ok(Config { value: Arc::new(value) })
+        Ok(Config { value: Arc::new(value), path: None })
+    }
+
+    /// Parse the file again for the values changed since starting, this config is left as is
+    pub fn reload(&self) -> anyhow::Result<Self> {
+        let path = self.path.as_ref().context("The config is not parsed from a file")?;
+        Self::parse(path)
     }
 
     pub fn get(&self, key: &str) -> anyhow::Result<Option<&Value>> {
@@ -21,12 +21,16 @@ where
 {
     type Entity = Creator;
 
-    async fn list(_executor: E) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn list(executor: E) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Creator, "SELECT * FROM creators")
+            .fetch_all(executor)
+            .await
     }
 
-    async fn page(_executor: E, _page: i64, _size: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Creator, "SELECT * FROM creators ORDER BY id LIMIT $1 OFFSET $2", size, page * size)
+            .fetch_all(executor)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Creator>> {
@@ -0,0 +1,151 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const STATUS_SENT: &str = "sent";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_DELIVERED: &str = "delivered";
+pub const STATUS_BOUNCED: &str = "bounced";
+pub const STATUS_COMPLAINED: &str = "complained";
+
+/// A sent email. The address is not stored, only its hash, see `service::email_delivery::recipient_hash`
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct EmailDelivery {
+    pub id: i64,
+    pub email_type: String,
+    pub recipient_hash: String,
+    pub provider: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub status: String,
+    pub status_detail: Option<String>,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct EmailDeliveryDao;
+
+pub trait IEmailDeliveryDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Returns the number of updated deliveries
+    fn update_status_by_message_id(executor: E, provider_message_id: &str, status: &str, status_detail: Option<&str>) -> impl Future<Output = Result<u64>> + Send;
+    /// The latest delivery of the type to the recipient
+    fn get_latest(executor: E, recipient_hash: &str, email_type: &str) -> impl Future<Output = Result<Option<EmailDelivery>>> + Send;
+    fn count_since(executor: E, recipient_hash: &str, email_type: &str, since: DateTime<Utc>) -> impl Future<Output = Result<i64>> + Send;
+}
+
+impl<'e, E> CrudDao<'e, E> for EmailDeliveryDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = EmailDelivery;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM email_deliveries ORDER BY id DESC")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM email_deliveries ORDER BY id DESC LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM email_deliveries WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE email_deliveries SET email_type = $1, recipient_hash = $2, provider = $3, provider_message_id = $4,
+                status = $5, status_detail = $6, create_time = $7, update_time = $8
+            WHERE id = $9",
+            value.email_type,
+            value.recipient_hash,
+            value.provider,
+            value.provider_message_id,
+            value.status,
+            value.status_detail,
+            value.create_time,
+            value.update_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO email_deliveries (email_type, recipient_hash, provider, provider_message_id, status, status_detail, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+            value.email_type,
+            value.recipient_hash,
+            value.provider,
+            value.provider_message_id,
+            value.status,
+            value.status_detail,
+            value.create_time,
+            value.update_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM email_deliveries WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'e, E> IEmailDeliveryDao<'e, E> for EmailDeliveryDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn update_status_by_message_id(executor: E, provider_message_id: &str, status: &str, status_detail: Option<&str>) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE email_deliveries SET status = $1, status_detail = $2, update_time = now() WHERE provider_message_id = $3",
+            status,
+            status_detail,
+            provider_message_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn get_latest(executor: E, recipient_hash: &str, email_type: &str) -> Result<Option<EmailDelivery>> {
+        sqlx::query_as!(
+            EmailDelivery,
+            "SELECT * FROM email_deliveries WHERE recipient_hash = $1 AND email_type = $2 ORDER BY create_time DESC LIMIT 1",
+            recipient_hash,
+            email_type
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn count_since(executor: E, recipient_hash: &str, email_type: &str, since: DateTime<Utc>) -> Result<i64> {
+        let count = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM email_deliveries WHERE recipient_hash = $1 AND email_type = $2 AND create_time >= $3"#,
+            recipient_hash,
+            email_type,
+            since
+        )
+        .fetch_one(executor)
+        .await?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,129 @@
+use sqlx::error::ErrorKind;
+use sqlx::{PgPool, PgTransaction};
+use std::time::Duration;
+use tracing::warn;
+
+/// SQLSTATE `serialization_failure`
+const SERIALIZATION_FAILURE: &str = "40001";
+/// SQLSTATE `deadlock_detected`
+const DEADLOCK_DETECTED: &str = "40P01";
+
+pub type DbResult<T> = Result<T, DbError>;
+
+/// A classified `sqlx::Error`, so that callers can tell conflicts from real failures.
+///
+/// DAOs still return `sqlx::Result`, convert it with `.map_err(DbError::from)` or `?` where the
+/// classification matters.
+#[derive(thiserror::Error, Debug)]
+pub enum DbError {
+    #[error("unique constraint {constraint:?} violated")]
+    UniqueViolation { constraint: Option<String> },
+    #[error("foreign key constraint {constraint:?} violated")]
+    ForeignKeyViolation { constraint: Option<String> },
+    #[error("check constraint {constraint:?} violated")]
+    CheckViolation { constraint: Option<String> },
+    /// Serialization failure or deadlock, the transaction could be retried
+    #[error("transaction conflicted with a concurrent transaction")]
+    SerializationFailure,
+    #[error("row not found")]
+    NotFound,
+    /// The row has been updated by someone else since it was read, see `update_by_id_checked` of DAOs
+    #[error("row version is stale")]
+    VersionConflict,
+    #[error(transparent)]
+    Other(sqlx::Error),
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::RowNotFound => DbError::NotFound,
+            sqlx::Error::Database(e) => {
+                let constraint = e.constraint().map(|x| x.to_string());
+                match e.kind() {
+                    ErrorKind::UniqueViolation => DbError::UniqueViolation { constraint },
+                    ErrorKind::ForeignKeyViolation => DbError::ForeignKeyViolation { constraint },
+                    ErrorKind::CheckViolation => DbError::CheckViolation { constraint },
+                    _ if matches!(e.code().as_deref(), Some(SERIALIZATION_FAILURE | DEADLOCK_DETECTED)) => DbError::SerializationFailure,
+                    _ => DbError::Other(err),
+                }
+            }
+            _ => DbError::Other(err),
+        }
+    }
+}
+
+impl DbError {
+    pub fn is_unique_violation(&self) -> bool {
+        matches!(self, DbError::UniqueViolation { .. })
+    }
+
+    /// Whether the error is a violation of the given unique constraint, e.g. `users_email_key`
+    pub fn is_unique_violation_of(&self, name: &str) -> bool {
+        matches!(self, DbError::UniqueViolation { constraint: Some(x) } if x == name)
+    }
+
+    /// Whether the operation could succeed by running it again from scratch
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DbError::SerializationFailure | DbError::VersionConflict)
+    }
+}
+
+/// Begin a transaction with `SERIALIZABLE` isolation level, use it with [retry_on_conflict].
+pub async fn begin_serializable(pool: &PgPool) -> sqlx::Result<PgTransaction<'static>> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE").execute(&mut *tx).await?;
+    Ok(tx)
+}
+
+/// Run the whole operation again when it fails with a serialization failure, deadlock or version conflict.
+///
+/// The closure must read the rows and begin/commit the transaction itself, so every attempt starts from scratch.
+pub async fn retry_on_conflict<T, F, Fut>(mut f: F) -> DbResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = DbResult<T>>,
+{
+    const MAX_ATTEMPTS: u32 = 3;
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(e) if e.is_retryable() && attempt < MAX_ATTEMPTS => {
+                warn!("{e}, retrying (attempt {attempt})");
+                let jitter = rand::random_range(0..20);
+                tokio::time::sleep(Duration::from_millis(20 * attempt as u64 + jitter)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::error::{retry_on_conflict, DbError};
+    use crate::db::test::get_test_pool;
+
+    #[tokio::test]
+    async fn test_classify_unique_violation() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let name = format!("t{}", rand::random_range(0..i32::MAX));
+        let insert = "INSERT INTO song_tags (name, is_active) VALUES ($1, true)";
+        sqlx::query(insert).bind(&name).execute(&mut *tx).await.unwrap();
+        let err = DbError::from(sqlx::query(insert).bind(&name).execute(&mut *tx).await.unwrap_err());
+        assert!(err.is_unique_violation_of("song_tags_name_key"), "{:?}", err);
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let mut attempts = 0;
+        let result: Result<(), DbError> = retry_on_conflict(|| {
+            attempts += 1;
+            async { Err(DbError::SerializationFailure) }
+        }).await;
+        assert!(matches!(result, Err(DbError::SerializationFailure)));
+        assert_eq!(3, attempts);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor};
+
+/// The starter set shown to new users
+pub const COLLECTION_ONBOARDING: &str = "onboarding";
+/// The picks of the next weekly selection, see [crate::service::weekly_selection]
+pub const COLLECTION_WEEKLY_PICKS: &str = "weekly_picks";
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct FeaturedSong {
+    pub id: i64,
+    pub collection: String,
+    pub song_id: i64,
+    /// Smaller first
+    pub position: i32,
+    pub note: Option<String>,
+    pub featured_by: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct FeaturedSongDao;
+
+pub trait IFeaturedSongDao<'e, E>: CrudDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn list_by_collection(executor: E, collection: &str) -> impl Future<Output = sqlx::Result<Vec<FeaturedSong>>> + Send;
+    /// Insert or move the song in the collection
+    fn upsert(executor: E, value: &FeaturedSong) -> impl Future<Output = sqlx::Result<()>> + Send;
+    /// Returns false if the song is not in the collection
+    fn remove(executor: E, collection: &str, song_id: i64) -> impl Future<Output = sqlx::Result<bool>> + Send;
+}
+
+impl<'e, E> CrudDao<'e, E> for FeaturedSongDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = FeaturedSong;
+
+    async fn list(executor: E) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(FeaturedSong, "SELECT * FROM featured_songs")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(FeaturedSong, "SELECT * FROM featured_songs ORDER BY id LIMIT $1 OFFSET $2", size, page * size)
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Self::Entity>> {
+        sqlx::query_as!(FeaturedSong, "SELECT * FROM featured_songs WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE featured_songs SET
+                collection = $1,
+                song_id = $2,
+                position = $3,
+                note = $4,
+                featured_by = $5,
+                create_time = $6
+            WHERE id = $7",
+            value.collection,
+            value.song_id,
+            value.position,
+            value.note,
+            value.featured_by,
+            value.create_time,
+            value.id,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> sqlx::Result<i64> {
+        sqlx::query!(
+            "INSERT INTO featured_songs (collection, song_id, position, note, featured_by, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            value.collection,
+            value.song_id,
+            value.position,
+            value.note,
+            value.featured_by,
+            value.create_time,
+        ).fetch_one(executor).await
+            .map(|x| x.id)
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> sqlx::Result<()> {
+        sqlx::query!("DELETE FROM featured_songs WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'e, E> IFeaturedSongDao<'e, E> for FeaturedSongDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_collection(executor: E, collection: &str) -> sqlx::Result<Vec<FeaturedSong>> {
+        sqlx::query_as!(
+            FeaturedSong,
+            "SELECT * FROM featured_songs WHERE collection = $1 ORDER BY position, id",
+            collection
+        ).fetch_all(executor).await
+    }
+
+    async fn upsert(executor: E, value: &FeaturedSong) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO featured_songs (collection, song_id, position, note, featured_by, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (collection, song_id) DO UPDATE SET
+                position = excluded.position,
+                note = excluded.note,
+                featured_by = excluded.featured_by",
+            value.collection,
+            value.song_id,
+            value.position,
+            value.note,
+            value.featured_by,
+            value.create_time,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn remove(executor: E, collection: &str, song_id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM featured_songs WHERE collection = $1 AND song_id = $2",
+            collection,
+            song_id
+        ).execute(executor).await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
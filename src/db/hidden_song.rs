@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A song hidden by the contributors, treated as not existing until unhidden, see [crate::service::song_report]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct HiddenSong {
+    pub song_id: i64,
+    pub reason: String,
+    pub hidden_by: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct HiddenSongDao;
+
+pub trait IHiddenSongDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Returns whether it was not hidden
+    fn insert(executor: E, value: &HiddenSong) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether it was hidden
+    fn delete(executor: E, song_id: i64) -> impl Future<Output = Result<bool>> + Send;
+    /// The hidden ones among the songs
+    fn list_hidden_ids(executor: E, song_ids: &[i64]) -> impl Future<Output = Result<Vec<i64>>> + Send;
+}
+
+impl<'e, E> IHiddenSongDao<'e, E> for HiddenSongDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn insert(executor: E, value: &HiddenSong) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO hidden_songs (song_id, reason, hidden_by, create_time) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (song_id) DO NOTHING",
+            value.song_id,
+            value.reason,
+            value.hidden_by,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(executor: E, song_id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM hidden_songs WHERE song_id = $1", song_id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_hidden_ids(executor: E, song_ids: &[i64]) -> Result<Vec<i64>> {
+        sqlx::query_scalar!("SELECT song_id FROM hidden_songs WHERE song_id = ANY($1)", song_ids)
+            .fetch_all(executor)
+            .await
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A version of the terms of service or the privacy policy, see [crate::service::legal]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LegalDocument {
+    pub id: i64,
+    /// [crate::service::legal::KIND_TERMS] or [crate::service::legal::KIND_PRIVACY]
+    pub kind: String,
+    pub version: i32,
+    pub title: String,
+    /// Markdown
+    pub content: String,
+    /// Whether the users must accept it again before logging in
+    pub mandatory: bool,
+    pub publish_time: DateTime<Utc>,
+    pub create_time: DateTime<Utc>,
+}
+
+/// The latest version of a kind accepted by a user
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserLegalAcceptance {
+    pub user_id: i64,
+    pub kind: String,
+    pub version: i32,
+    pub accept_time: DateTime<Utc>,
+}
+
+pub struct LegalDocumentDao;
+
+pub trait ILegalDocumentDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// The latest version of each kind published before `now`
+    fn list_latest(executor: E, now: DateTime<Utc>) -> impl Future<Output = Result<Vec<LegalDocument>>> + Send;
+    /// The latest mandatory version of each kind published before `now`
+    fn list_latest_mandatory(executor: E, now: DateTime<Utc>) -> impl Future<Output = Result<Vec<LegalDocument>>> + Send;
+    fn get_by_version(executor: E, kind: &str, version: i32) -> impl Future<Output = Result<Option<LegalDocument>>> + Send;
+    /// Insert as the next version of the kind ignoring `version`, returns the version
+    fn insert_next_version(executor: E, value: &LegalDocument) -> impl Future<Output = Result<i32>> + Send;
+    fn list_acceptances(executor: E, user_id: i64) -> impl Future<Output = Result<Vec<UserLegalAcceptance>>> + Send;
+    /// Record the accepted version, an older version than the accepted one is ignored
+    fn accept(executor: E, value: &UserLegalAcceptance) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<'e, E> ILegalDocumentDao<'e, E> for LegalDocumentDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_latest(executor: E, now: DateTime<Utc>) -> Result<Vec<LegalDocument>> {
+        sqlx::query_as!(
+            LegalDocument,
+            "SELECT DISTINCT ON (kind) * FROM legal_documents
+            WHERE publish_time <= $1
+            ORDER BY kind, version DESC",
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn list_latest_mandatory(executor: E, now: DateTime<Utc>) -> Result<Vec<LegalDocument>> {
+        sqlx::query_as!(
+            LegalDocument,
+            "SELECT DISTINCT ON (kind) * FROM legal_documents
+            WHERE publish_time <= $1 AND mandatory
+            ORDER BY kind, version DESC",
+            now
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_version(executor: E, kind: &str, version: i32) -> Result<Option<LegalDocument>> {
+        sqlx::query_as!(
+            LegalDocument,
+            "SELECT * FROM legal_documents WHERE kind = $1 AND version = $2",
+            kind,
+            version
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn insert_next_version(executor: E, value: &LegalDocument) -> Result<i32> {
+        sqlx::query_scalar!(
+            "INSERT INTO legal_documents (kind, version, title, content, mandatory, publish_time, create_time)
+            SELECT $1::VARCHAR, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5, $6 FROM legal_documents WHERE kind = $1
+            RETURNING version",
+            value.kind,
+            value.title,
+            value.content,
+            value.mandatory,
+            value.publish_time,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn list_acceptances(executor: E, user_id: i64) -> Result<Vec<UserLegalAcceptance>> {
+        sqlx::query_as!(
+            UserLegalAcceptance,
+            "SELECT * FROM user_legal_acceptances WHERE user_id = $1 ORDER BY kind",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn accept(executor: E, value: &UserLegalAcceptance) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_legal_acceptances (user_id, kind, version, accept_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, kind) DO UPDATE SET
+                version = EXCLUDED.version,
+                accept_time = EXCLUDED.accept_time
+            WHERE user_legal_acceptances.version < EXCLUDED.version",
+            value.user_id,
+            value.kind,
+            value.version,
+            value.accept_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const ENTITY_SONG: &str = "song";
+pub const ENTITY_PLAYLIST: &str = "playlist";
+
+/// A title of a song or playlist in another language, see [crate::service::localization]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LocalizedTitle {
+    pub entity_type: String,
+    pub entity_id: i64,
+    pub lang: String,
+    pub title: String,
+    pub subtitle: String,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct LocalizedTitleDao;
+
+pub trait ILocalizedTitleDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn list_by_entity_ids(executor: E, entity_type: &str, entity_ids: &[i64]) -> impl Future<Output = Result<Vec<LocalizedTitle>>> + Send;
+    fn insert_batch(executor: E, values: &[LocalizedTitle]) -> impl Future<Output = Result<()>> + Send;
+    fn delete_by_entity_id(executor: E, entity_type: &str, entity_id: i64) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<'e, E> ILocalizedTitleDao<'e, E> for LocalizedTitleDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_entity_ids(executor: E, entity_type: &str, entity_ids: &[i64]) -> Result<Vec<LocalizedTitle>> {
+        sqlx::query_as!(
+            LocalizedTitle,
+            "SELECT * FROM localized_titles WHERE entity_type = $1 AND entity_id = ANY($2) ORDER BY entity_id, lang",
+            entity_type,
+            entity_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn insert_batch(executor: E, values: &[LocalizedTitle]) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO localized_titles (entity_type, entity_id, lang, title, subtitle, update_time)
+            SELECT * FROM UNNEST($1::varchar[], $2::bigint[], $3::varchar[], $4::text[], $5::text[], $6::timestamptz[])",
+            &values.iter().map(|x| x.entity_type.clone()).collect::<Vec<_>>(),
+            &values.iter().map(|x| x.entity_id).collect::<Vec<_>>(),
+            &values.iter().map(|x| x.lang.clone()).collect::<Vec<_>>(),
+            &values.iter().map(|x| x.title.clone()).collect::<Vec<_>>(),
+            &values.iter().map(|x| x.subtitle.clone()).collect::<Vec<_>>(),
+            &values.iter().map(|x| x.update_time).collect::<Vec<_>>()
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_entity_id(executor: E, entity_type: &str, entity_id: i64) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM localized_titles WHERE entity_type = $1 AND entity_id = $2",
+            entity_type,
+            entity_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const SOURCE_POST: &str = "post";
+pub const SOURCE_REVIEW_COMMENT: &str = "review_comment";
+
+/// A user mentioned by `@name` in a post or a comment
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Mention {
+    pub id: i64,
+    pub source_type: String,
+    pub source_id: i64,
+    pub user_id: i64,
+    /// The name as written
+    pub name: String,
+    pub author_uid: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct MentionDao;
+
+pub trait IMentionDao<'e, E>: CrudDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn list_by_sources(executor: E, source_type: &str, source_ids: &[i64]) -> impl Future<Output = Result<Vec<Mention>>> + Send;
+    fn delete_by_source(executor: E, source_type: &str, source_id: i64) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<'e, E> CrudDao<'e, E> for MentionDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = Mention;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM mentions ORDER BY id")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM mentions ORDER BY id LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM mentions WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE mentions SET
+                source_type = $1,
+                source_id = $2,
+                user_id = $3,
+                name = $4,
+                author_uid = $5,
+                create_time = $6
+            WHERE id = $7",
+            value.source_type,
+            value.source_id,
+            value.user_id,
+            value.name,
+            value.author_uid,
+            value.create_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query!(
+            "INSERT INTO mentions (source_type, source_id, user_id, name, author_uid, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            value.source_type,
+            value.source_id,
+            value.user_id,
+            value.name,
+            value.author_uid,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+        .map(|x| x.id)
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM mentions WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'e, E> IMentionDao<'e, E> for MentionDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_sources(executor: E, source_type: &str, source_ids: &[i64]) -> Result<Vec<Mention>> {
+        if source_ids.is_empty() { return Ok(vec![]) }
+        sqlx::query_as!(
+            Mention,
+            "SELECT * FROM mentions WHERE source_type = $1 AND source_id = ANY($2) ORDER BY id",
+            source_type,
+            source_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn delete_by_source(executor: E, source_type: &str, source_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM mentions WHERE source_type = $1 AND source_id = $2", source_type, source_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use sqlx::PgExecutor;
 
+pub mod error;
 pub mod refresh_token;
 pub mod user;
 pub mod song;
@@ -8,11 +9,41 @@ pub mod playlist;
 pub mod song_publishing_review;
 pub mod song_publishing_review_comment;
 pub mod song_publishing_review_history;
+pub mod song_edit_log;
+pub mod storage_orphan_object;
+pub mod email_delivery;
+pub mod featured_song;
+pub mod mention;
+pub mod notification;
+pub mod search_stat;
 pub mod version;
 pub mod creator;
 pub mod post;
 pub mod user_play_history;
+pub mod user_preference;
 pub mod user_connection_accounts;
+pub mod user_oauth_identity;
+pub mod user_account_audit_log;
+pub mod user_storage_object;
+pub mod song_content_fingerprint;
+pub mod localized_title;
+pub mod review_guideline_snippet;
+pub mod user_shadow_ban;
+pub mod user_totp;
+pub mod user_follow;
+pub mod song_comment;
+pub mod user_release_channel;
+pub mod song_audio_rendition;
+pub mod user_role;
+pub mod song_share;
+pub mod legal_document;
+pub mod song_report;
+pub mod hidden_song;
+pub mod user_support_link;
+pub mod weekly_selection;
+pub mod user_legal_hold;
+pub mod retention;
+pub mod song_play_rollup;
 
 pub trait CrudDao<'e, E>
 where E: PgExecutor<'e> {
@@ -28,6 +59,51 @@ where E: PgExecutor<'e> {
 
 #[cfg(test)]
 mod test {
+    use crate::db::creator::CreatorDao;
+    use crate::db::email_delivery::{self, EmailDelivery, EmailDeliveryDao, IEmailDeliveryDao};
+    use crate::db::error::DbError;
+    use crate::db::featured_song::{FeaturedSong, FeaturedSongDao, IFeaturedSongDao};
+    use crate::db::localized_title;
+    use crate::db::mention::MentionDao;
+    use crate::db::notification::{INotificationDao, Notification, NotificationDao};
+    use crate::db::playlist::{FavoritePlaylist, IPlaylistDao, Playlist, PlaylistDao, PlaylistSong};
+    use crate::db::post::PostDao;
+    use crate::db::refresh_token::RefreshTokenDao;
+    use crate::db::review_guideline_snippet::{IReviewGuidelineSnippetDao, ReviewGuidelineSnippet, ReviewGuidelineSnippetDao, ReviewGuidelineSnippetVersion};
+    use crate::db::search_stat::{ISearchStatDao, SearchStatDao};
+    use crate::db::song::{ISongDao, Song, SongDao, SongLike, SongPlay};
+    use crate::db::song_publishing_review::{self, ISongPublishingReviewDao, SongPublishingReview, SongPublishingReviewDao};
+    use crate::db::song_publishing_review_comment::SongPublishingReviewCommentDao;
+    use crate::db::song_publishing_review_history::SongPublishingReviewHistoryDao;
+    use crate::db::song_edit_log::SongEditLogDao;
+    use crate::db::song_audio_rendition::{self, ISongAudioRenditionDao, SongAudioRendition, SongAudioRenditionDao};
+    use crate::db::song_tag::{ISongTagDao, SongTag, SongTagAlias, SongTagDao};
+    use crate::db::storage_orphan_object::{IStorageOrphanObjectDao, StorageOrphanObject, StorageOrphanObjectDao};
+    use crate::db::user::{IUserDao, User, UserDao};
+    use crate::db::user_account_audit_log::UserAccountAuditLogDao;
+    use crate::db::user_oauth_identity::{IUserOAuthIdentityDao, UserOAuthIdentity, UserOAuthIdentityDao};
+    use crate::db::user_preference::{IUserPreferenceDao, UserPreference, UserPreferenceDao};
+    use crate::db::legal_document::{ILegalDocumentDao, LegalDocument, LegalDocumentDao, UserLegalAcceptance};
+    use crate::db::song_share::{ISongShareDao, SongShareDao};
+    use crate::db::song_report::{self, ISongReportDao, SongReport, SongReportDao};
+    use crate::db::hidden_song::{HiddenSong, HiddenSongDao, IHiddenSongDao};
+    use crate::db::user_support_link::{IUserSupportLinkDao, UserSupportLink, UserSupportLinkDao};
+    use crate::db::weekly_selection::{IWeeklySelectionDao, WeeklySelection, WeeklySelectionDao};
+    use crate::db::user_legal_hold::{IUserLegalHoldDao, UserLegalHold, UserLegalHoldDao};
+    use crate::db::user_play_history::{IUserPlayHistory, UserPlayHistoryDao};
+    use crate::db::retention::{IRetentionDao, RetentionDao};
+    use crate::db::song_play_rollup::{ISongPlayRollupDao, SongPlayRollupDao};
+    use crate::db::user_role::{IUserRoleDao, UserRole, UserRoleDao};
+    use crate::db::user_shadow_ban::{self, IUserShadowBanDao, UserShadowBan, UserShadowBanDao};
+    use crate::db::user_totp::{IUserTotpDao, UserTotpDao, UserTotpSecret};
+    use crate::db::user_follow::{IUserFollowDao, UserFollow, UserFollowDao};
+    use crate::db::song_comment::{ISongCommentDao, SongComment, SongCommentDao};
+    use crate::db::user_storage_object::{self, IUserStorageObjectDao, StorageUsage, UserStorageObject, UserStorageObjectDao};
+    use crate::db::user_release_channel::{IUserReleaseChannelDao, UserReleaseChannel, UserReleaseChannelDao};
+    use crate::db::version::{self, Version, VersionDao};
+    use crate::db::CrudDao;
+    use crate::service::localization::{self, LocalizedTitleItem};
+    use chrono::{DateTime, Utc};
     use sqlx::PgPool;
 
     pub async fn get_test_pool() -> PgPool {
@@ -38,4 +114,1312 @@ mod test {
             .await
             .expect("Failed to connect to test database")
     }
+
+    /// A placeholder in a DAO compiles fine but panics in production, so we forbid them in `db`.
+    #[test]
+    fn test_no_placeholder_in_dao() {
+        let patterns = ["todo", "unimplemented"].map(|x| format!("{x}!("));
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/db");
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            for pattern in &patterns {
+                assert!(!content.contains(pattern.as_str()), "{} contains {}", path.display(), pattern);
+            }
+        }
+    }
+
+    /// Call the read and delete methods of a `CrudDao`, new DAOs should be added here.
+    macro_rules! exercise_crud_dao {
+        ($tx:ident, $($dao:ident),+ $(,)?) => {
+            $(
+                $dao::list(&mut *$tx).await.expect(concat!(stringify!($dao), "::list"));
+                let page = $dao::page(&mut *$tx, 0, 2).await.expect(concat!(stringify!($dao), "::page"));
+                assert!(page.len() <= 2, concat!(stringify!($dao), "::page exceeds the page size"));
+                $dao::get_by_id(&mut *$tx, -1).await.expect(concat!(stringify!($dao), "::get_by_id"));
+                $dao::delete_by_id(&mut *$tx, -1).await.expect(concat!(stringify!($dao), "::delete_by_id"));
+            )+
+        };
+    }
+
+    #[tokio::test]
+    async fn test_every_crud_dao() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        exercise_crud_dao!(
+            tx,
+            UserDao,
+            RefreshTokenDao,
+            SongDao,
+            SongTagDao,
+            PlaylistDao,
+            SongPublishingReviewDao,
+            SongPublishingReviewCommentDao,
+            SongPublishingReviewHistoryDao,
+            SongEditLogDao,
+            StorageOrphanObjectDao,
+            EmailDeliveryDao,
+            FeaturedSongDao,
+            MentionDao,
+            NotificationDao,
+            VersionDao,
+            CreatorDao,
+            PostDao,
+            UserAccountAuditLogDao,
+        );
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_page() {
+        let pool = get_test_pool().await;
+        let first = SongDao::page(&pool, 0, 2).await.unwrap();
+        let second = SongDao::page(&pool, 1, 2).await.unwrap();
+        let ids = first.iter().chain(second.iter()).map(|x| x.id).collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|x| x[0] > x[1]), "Songs should be ordered by id desc without overlap");
+    }
+
+    #[tokio::test]
+    async fn test_song_search_by_title() {
+        let pool = get_test_pool().await;
+        for sort in ["", "release_time_desc", "release_time_asc", "play_count_asc"] {
+            let ids = SongDao::search_by_title(&pool, "a", sort, 2, 0).await.unwrap();
+            assert!(ids.len() <= 2);
+        }
+        // The wildcards in the keyword are matched literally
+        let ids = SongDao::search_by_title(&pool, "%_\\", "", 20, 0).await.unwrap();
+        assert!(ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_song_recount_stats() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let song_id = SongDao::insert(&mut *tx, &Song {
+            id: 0,
+            display_id: format!("T{}", rand::random_range(1..1_000_000_000_000i64)),
+            title: "test".to_string(),
+            subtitle: String::new(),
+            description: String::new(),
+            artist: String::new(),
+            file_url: String::new(),
+            cover_art_url: String::new(),
+            lyrics: String::new(),
+            duration_seconds: 1,
+            uploader_uid: -1,
+            creation_type: 0,
+            play_count: 5,
+            like_count: 0,
+            is_private: false,
+            release_time: Utc::now(),
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+            explicit: None,
+            gain: None,
+            loudness_lufs: None,
+            true_peak: None,
+            version: 0,
+        }).await.unwrap();
+        SongDao::insert_likes(&mut *tx, &[SongLike {
+            song_id,
+            user_id: -1,
+            playback_position_secs: None,
+            create_time: Utc::now(),
+        }]).await.unwrap();
+
+        let drifts = SongDao::recount_stats(&mut *tx, Some(song_id)).await.unwrap();
+        assert_eq!(1, drifts.len());
+        assert_eq!((5, 0, 0, 1), (drifts[0].old_play_count, drifts[0].old_like_count, drifts[0].play_count, drifts[0].like_count));
+        // The updates don't reset the repaired counts
+        let mut song = SongDao::get_by_id(&mut *tx, song_id).await.unwrap().unwrap();
+        song.like_count = 0;
+        SongDao::update_by_id(&mut *tx, &song).await.unwrap();
+        assert!(SongDao::recount_stats(&mut *tx, Some(song_id)).await.unwrap().is_empty());
+
+        SongDao::add_play_counts(&mut *tx, &[(song_id, 3), (-1, 1)]).await.unwrap();
+        let song = SongDao::get_by_id(&mut *tx, song_id).await.unwrap().unwrap();
+        assert_eq!(3, song.play_count);
+
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        assert!(SongDao::insert_play_count_batch(&mut *tx, &batch_id).await.unwrap());
+        assert!(!SongDao::insert_play_count_batch(&mut *tx, &batch_id).await.unwrap());
+        SongDao::delete_play_count_batches_before(&mut *tx, Utc::now() + chrono::Days::new(1)).await.unwrap();
+        assert!(SongDao::insert_play_count_batch(&mut *tx, &batch_id).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upsert_user_preference() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        assert!(UserPreferenceDao::get_by_user_id(&mut *tx, user_id).await.unwrap().is_none());
+
+        let mut value = UserPreference {
+            user_id,
+            favorite_tag_ids: vec![1, 2],
+            blocked_tag_ids: vec![3],
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+        };
+        UserPreferenceDao::upsert(&mut *tx, &value).await.unwrap();
+        value.favorite_tag_ids = vec![];
+        UserPreferenceDao::upsert(&mut *tx, &value).await.unwrap();
+        let saved = UserPreferenceDao::get_by_user_id(&mut *tx, user_id).await.unwrap().unwrap();
+        assert!(saved.favorite_tag_ids.is_empty());
+        assert_eq!(vec![3], saved.blocked_tag_ids);
+
+        // No play history
+        assert!(SongTagDao::list_most_played_by_user(&mut *tx, user_id, 200, 5).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_featured_songs() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let collection = format!("test_{}", rand::random_range(1..i64::MAX));
+        for (song_id, position) in [(1, 2), (2, 1), (1, 0)] {
+            FeaturedSongDao::upsert(&mut *tx, &FeaturedSong {
+                id: 0,
+                collection: collection.clone(),
+                song_id,
+                position,
+                note: None,
+                featured_by: -1,
+                create_time: Utc::now(),
+            }).await.unwrap();
+        }
+        let songs = FeaturedSongDao::list_by_collection(&mut *tx, &collection).await.unwrap();
+        assert_eq!(vec![1, 2], songs.iter().map(|x| x.song_id).collect::<Vec<_>>());
+
+        assert!(FeaturedSongDao::remove(&mut *tx, &collection, 1).await.unwrap());
+        assert!(!FeaturedSongDao::remove(&mut *tx, &collection, 1).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_stats() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+        let query = format!("test_{}", rand::random_range(1..i64::MAX));
+        SearchStatDao::add_search(&mut *tx, date, &query, "relevance").await.unwrap();
+        SearchStatDao::add_search(&mut *tx, date, &query, "relevance").await.unwrap();
+        SearchStatDao::add_query_click(&mut *tx, date, &query, "relevance", 3, true).await.unwrap();
+        SearchStatDao::add_query_click(&mut *tx, date, &query, "relevance", 0, false).await.unwrap();
+        SearchStatDao::add_position_click(&mut *tx, date, "relevance", 3).await.unwrap();
+
+        let totals = SearchStatDao::list_query_totals(&mut *tx, date, i64::MAX).await.unwrap();
+        let total = totals.iter().find(|x| x.query == query).unwrap();
+        assert_eq!((2, 1, 2, 3), (total.searches, total.clicked_searches, total.clicks, total.click_position_sum));
+        let positions = SearchStatDao::list_position_totals(&mut *tx, date).await.unwrap();
+        assert!(positions.iter().any(|x| x.sort_by == "relevance" && x.position == 3 && x.clicks >= 1));
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_review_dashboard_counts() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let reviewer_uid = -rand::random_range(1..i64::MAX);
+        let now = Utc::now();
+        let review = SongPublishingReview {
+            id: 0,
+            user_id: reviewer_uid,
+            song_display_id: "JM-TEST-000".to_string(),
+            data: serde_json::json!({}),
+            submit_time: now,
+            update_time: now,
+            review_time: Some(now),
+            review_comment: None,
+            status: song_publishing_review::STATUS_APPROVED,
+            r#type: song_publishing_review::TYPE_CREATE,
+            comment: None,
+            reviewer_uid: Some(reviewer_uid),
+        };
+        SongPublishingReviewDao::insert(&mut *tx, &review).await.unwrap();
+
+        let reviewed = SongPublishingReviewDao::count_reviewed_by(&mut *tx, reviewer_uid, Some(now - chrono::Duration::days(1))).await.unwrap();
+        assert_eq!(1, reviewed.len());
+        assert_eq!((song_publishing_review::STATUS_APPROVED, 1), (reviewed[0].status, reviewed[0].count));
+        let reviewed = SongPublishingReviewDao::count_reviewed_by(&mut *tx, reviewer_uid, None).await.unwrap();
+        assert_eq!(1, reviewed.len());
+        assert!(SongPublishingReviewDao::count_reviewed_by(&mut *tx, reviewer_uid, Some(now + chrono::Duration::days(1))).await.unwrap().is_empty());
+        let statuses = SongPublishingReviewDao::count_group_by_status(&mut *tx).await.unwrap();
+        assert!(statuses.iter().any(|x| x.status == song_publishing_review::STATUS_APPROVED && x.count >= 1));
+        let days = SongPublishingReviewDao::count_submissions_by_day(&mut *tx, now - chrono::Duration::days(1)).await.unwrap();
+        assert!(days.iter().any(|x| x.date == now.date_naive() && x.count >= 1));
+        SongPublishingReviewDao::get_oldest_pending_submit_time(&mut *tx).await.unwrap();
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_insert_plays() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        let plays = (1..=2).map(|song_id| SongPlay {
+            id: 0,
+            song_id,
+            user_id: Some(user_id),
+            anonymous_uid: None,
+            create_time: Utc::now(),
+        }).collect::<Vec<_>>();
+        SongDao::insert_plays(&mut *tx, &plays).await.unwrap();
+
+        let inserted = SongDao::cursor_plays(&mut *tx, user_id, Utc::now(), 10).await.unwrap();
+        assert_eq!(2, inserted.len());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_playlist() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        let playlist_id = PlaylistDao::insert(&mut *tx, &Playlist {
+            id: 0,
+            name: "test".to_string(),
+            description: None,
+            user_id,
+            cover_url: None,
+            is_public: true,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+            use_song_cover: true,
+            version: 0,
+        }).await.unwrap();
+        let song = PlaylistSong { playlist_id, song_id: 1, sort_key: "V".to_string(), add_time: Utc::now() };
+        assert!(PlaylistDao::add_song(&mut *tx, &song).await.unwrap());
+        assert!(!PlaylistDao::add_song(&mut *tx, &song).await.unwrap());
+        assert_eq!(1, PlaylistDao::count_songs_of(&mut *tx, playlist_id).await.unwrap());
+        PlaylistDao::add_favorite(&mut *tx, &FavoritePlaylist { user_id, playlist_id, order_index: 0, add_time: Utc::now() }).await.unwrap();
+
+        PlaylistDao::delete_by_id(&mut *tx, playlist_id).await.unwrap();
+        assert!(PlaylistDao::get_by_id(&mut *tx, playlist_id).await.unwrap().is_none());
+        assert!(PlaylistDao::list_songs(&mut *tx, playlist_id).await.unwrap().is_empty());
+        assert!(PlaylistDao::get_favorite(&mut *tx, user_id, playlist_id).await.unwrap().is_none());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_checked_conflict() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let mut playlist = Playlist {
+            id: 0,
+            name: "test".to_string(),
+            description: None,
+            user_id: -1,
+            cover_url: None,
+            is_public: false,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+            use_song_cover: true,
+            version: 0,
+        };
+        playlist.id = PlaylistDao::insert(&mut *tx, &playlist).await.unwrap();
+
+        let stale = playlist.clone();
+        playlist.name = "renamed".to_string();
+        playlist.version = PlaylistDao::update_by_id_checked(&mut *tx, &playlist).await.unwrap();
+        assert_eq!(1, playlist.version);
+
+        let result = PlaylistDao::update_by_id_checked(&mut *tx, &stale).await;
+        assert!(matches!(result, Err(DbError::VersionConflict)));
+        assert_eq!("renamed", PlaylistDao::get_by_id(&mut *tx, playlist.id).await.unwrap().unwrap().name);
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_banned_ids() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let mut ids = vec![];
+        for is_banned in [false, true] {
+            let name = format!("t{}", rand::random_range(0..i32::MAX));
+            let id = UserDao::insert(&mut *tx, &User {
+                id: 0,
+                username: name.clone(),
+                email: format!("{name}@example.com"),
+                password_hash: String::new(),
+                avatar_url: None,
+                bio: None,
+                gender: None,
+                is_banned,
+                last_login_time: None,
+                create_time: Utc::now(),
+                update_time: Utc::now(),
+                version: 0,
+                preferred_language: None,
+            }).await.unwrap();
+            ids.push(id);
+        }
+        let banned = UserDao::list_banned_ids(&mut *tx, &ids).await.unwrap();
+        assert_eq!(vec![ids[1]], banned);
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_storage_orphan_object_upsert() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let key = format!("songs/{}.mp3", uuid::Uuid::new_v4());
+        let mut object = StorageOrphanObject {
+            id: 0,
+            object_key: key.clone(),
+            size: Some(1),
+            event_time: Utc::now(),
+            create_time: Utc::now(),
+        };
+        StorageOrphanObjectDao::upsert(&mut *tx, &object).await.unwrap();
+        object.size = Some(2);
+        StorageOrphanObjectDao::upsert(&mut *tx, &object).await.unwrap();
+
+        let flagged = StorageOrphanObjectDao::list(&mut *tx).await.unwrap()
+            .into_iter().filter(|x| x.object_key == key)
+            .collect::<Vec<_>>();
+        assert_eq!(1, flagged.len());
+        assert_eq!(Some(2), flagged[0].size);
+
+        assert!(StorageOrphanObjectDao::delete_by_key(&mut *tx, &key).await.unwrap());
+        assert!(!StorageOrphanObjectDao::delete_by_key(&mut *tx, &key).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_referenced_urls() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let name = format!("t{}", rand::random_range(0..i32::MAX));
+        let avatar_url = format!("https://example.com/images/{}.webp", uuid::Uuid::new_v4());
+        UserDao::insert(&mut *tx, &User {
+            id: 0,
+            username: name.clone(),
+            email: format!("{name}@example.com"),
+            password_hash: String::new(),
+            avatar_url: Some(avatar_url.clone()),
+            bio: None,
+            gender: None,
+            is_banned: false,
+            last_login_time: None,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+            version: 0,
+            preferred_language: None,
+        }).await.unwrap();
+
+        let unknown_url = format!("https://example.com/images/{}.webp", uuid::Uuid::new_v4());
+        let urls = [avatar_url.clone(), unknown_url];
+        assert_eq!(vec![avatar_url.clone()], StorageOrphanObjectDao::referenced_urls(&mut *tx, &urls).await.unwrap());
+        assert!(StorageOrphanObjectDao::referenced_urls(&mut *tx, &[]).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_email_delivery_status() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let hash = uuid::Uuid::new_v4().to_string();
+        let message_id = format!("<{}@example.com>", uuid::Uuid::new_v4());
+        EmailDeliveryDao::insert(&mut *tx, &EmailDelivery {
+            id: 0,
+            email_type: "verification_code".to_string(),
+            recipient_hash: hash.clone(),
+            provider: Some("smtp:example.com".to_string()),
+            provider_message_id: Some(message_id.clone()),
+            status: email_delivery::STATUS_SENT.to_string(),
+            status_detail: None,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+        }).await.unwrap();
+
+        let updated = EmailDeliveryDao::update_status_by_message_id(&mut *tx, &message_id, email_delivery::STATUS_BOUNCED, Some("550")).await.unwrap();
+        assert_eq!(1, updated);
+        let latest = EmailDeliveryDao::get_latest(&mut *tx, &hash, "verification_code").await.unwrap().unwrap();
+        assert_eq!(email_delivery::STATUS_BOUNCED, latest.status);
+        let count = EmailDeliveryDao::count_since(&mut *tx, &hash, "verification_code", Utc::now() - chrono::Duration::hours(1)).await.unwrap();
+        assert_eq!(1, count);
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_oauth_identity() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let provider_user_id = uuid::Uuid::new_v4().to_string();
+        let identity = UserOAuthIdentity {
+            id: 0,
+            user_id: -1,
+            provider: "github".to_string(),
+            provider_user_id: provider_user_id.clone(),
+            provider_user_name: "hachimi".to_string(),
+            provider_email: None,
+            create_time: Utc::now(),
+            last_login_time: None,
+        };
+        let id = UserOAuthIdentityDao::insert(&mut *tx, &identity).await.unwrap();
+        let found = UserOAuthIdentityDao::get_by_provider_user_id(&mut *tx, "github", &provider_user_id).await.unwrap().unwrap();
+        assert_eq!(-1, found.user_id);
+        assert!(found.last_login_time.is_none());
+        UserOAuthIdentityDao::update_last_login_time(&mut *tx, id, Utc::now()).await.unwrap();
+        let found = UserOAuthIdentityDao::get_by_provider_user_id(&mut *tx, "github", &provider_user_id).await.unwrap().unwrap();
+        assert!(found.last_login_time.is_some());
+        assert_eq!(1, UserOAuthIdentityDao::list_by_user_id_for_update(&mut *tx, -1).await.unwrap().len());
+
+        // The same account can't be linked to another user
+        let mut sp = sqlx::Acquire::begin(&mut *tx).await.unwrap();
+        assert!(UserOAuthIdentityDao::insert(&mut *sp, &UserOAuthIdentity { user_id: -2, ..identity.clone() }).await.is_err());
+        sp.rollback().await.unwrap();
+
+        assert!(UserOAuthIdentityDao::delete_by_user_id_and_provider(&mut *tx, -1, "github").await.unwrap());
+        assert!(!UserOAuthIdentityDao::delete_by_user_id_and_provider(&mut *tx, -1, "github").await.unwrap());
+        assert!(UserOAuthIdentityDao::list_by_user_id(&mut *tx, -1).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_storage_object() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let key = format!("images/cover/{}.webp", uuid::Uuid::new_v4());
+        let object = UserStorageObject {
+            id: 0,
+            user_id: -1,
+            object_key: key.clone(),
+            kind: user_storage_object::KIND_IMAGE.to_string(),
+            size: 100,
+            create_time: Utc::now(),
+        };
+        assert!(UserStorageObjectDao::insert_if_absent(&mut *tx, &object).await.unwrap());
+        // The same image uploaded again is not counted twice
+        assert!(!UserStorageObjectDao::insert_if_absent(&mut *tx, &object).await.unwrap());
+        assert!(UserStorageObjectDao::insert_if_absent(&mut *tx, &UserStorageObject {
+            object_key: format!("songs/{}.mp3", uuid::Uuid::new_v4()),
+            kind: user_storage_object::KIND_AUDIO.to_string(),
+            size: 1000,
+            ..object.clone()
+        }).await.unwrap());
+        assert!(UserStorageObjectDao::insert_if_absent(&mut *tx, &UserStorageObject { user_id: -2, ..object.clone() }).await.unwrap());
+        assert_eq!(
+            StorageUsage { audio_bytes: 1000, image_bytes: 100 },
+            UserStorageObjectDao::get_usage_by_user_id(&mut *tx, -1).await.unwrap()
+        );
+
+        // Released from all the owners
+        assert_eq!(2, UserStorageObjectDao::delete_by_key(&mut *tx, &key).await.unwrap());
+        assert_eq!(0, UserStorageObjectDao::get_usage_by_user_id(&mut *tx, -2).await.unwrap().total_bytes());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_localized_title() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let item = |lang: &str, title: &str| LocalizedTitleItem {
+            lang: lang.to_string(),
+            title: title.to_string(),
+            subtitle: String::new(),
+        };
+        localization::replace(&mut tx, localized_title::ENTITY_SONG, -1, &[item("ja", "ハチミ"), item("en", "Hachimi")]).await.unwrap();
+        localization::replace(&mut tx, localized_title::ENTITY_PLAYLIST, -1, &[item("en", "Playlist")]).await.unwrap();
+        let titles = localization::list_by_ids(&mut *tx, localized_title::ENTITY_SONG, &[-1, -2]).await.unwrap();
+        assert_eq!(vec![item("en", "Hachimi"), item("ja", "ハチミ")], titles[&-1]);
+        assert!(!titles.contains_key(&-2));
+
+        // Replaced as a whole, the other entity types are untouched
+        localization::replace(&mut tx, localized_title::ENTITY_SONG, -1, &[item("en", "Hachimi!")]).await.unwrap();
+        let titles = localization::list_by_ids(&mut *tx, localized_title::ENTITY_SONG, &[-1]).await.unwrap();
+        assert_eq!(vec![item("en", "Hachimi!")], titles[&-1]);
+        let titles = localization::list_by_ids(&mut *tx, localized_title::ENTITY_PLAYLIST, &[-1]).await.unwrap();
+        assert_eq!(1, titles[&-1].len());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_review_guideline_snippet() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let slug = format!("test-{}", uuid::Uuid::new_v4());
+        let mut snippet = ReviewGuidelineSnippet {
+            id: 0,
+            slug: slug.clone(),
+            title: "Low audio quality".to_string(),
+            content: "The audio is clipped".to_string(),
+            version: 1,
+            archived: false,
+            update_by: -1,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+        };
+        snippet.id = ReviewGuidelineSnippetDao::insert(&mut *tx, &snippet).await.unwrap().unwrap();
+        assert!(ReviewGuidelineSnippetDao::insert(&mut *tx, &snippet).await.unwrap().is_none());
+
+        // Only the next version is saved
+        snippet.version = 2;
+        snippet.archived = true;
+        assert!(ReviewGuidelineSnippetDao::update_checked(&mut *tx, &snippet).await.unwrap());
+        assert!(!ReviewGuidelineSnippetDao::update_checked(&mut *tx, &snippet).await.unwrap());
+        let saved = ReviewGuidelineSnippetDao::get_by_slug(&mut *tx, &slug).await.unwrap().unwrap();
+        assert_eq!(2, saved.version);
+        assert!(ReviewGuidelineSnippetDao::list(&mut *tx, false).await.unwrap().iter().all(|x| x.slug != slug));
+        assert!(ReviewGuidelineSnippetDao::list(&mut *tx, true).await.unwrap().iter().any(|x| x.slug == slug));
+
+        for version in [1, 2] {
+            ReviewGuidelineSnippetDao::insert_version(&mut *tx, &ReviewGuidelineSnippetVersion {
+                id: 0,
+                snippet_id: snippet.id,
+                version,
+                title: snippet.title.clone(),
+                content: snippet.content.clone(),
+                archived: version == 2,
+                editor_uid: -1,
+                create_time: Utc::now(),
+            }).await.unwrap();
+        }
+        let versions = ReviewGuidelineSnippetDao::list_versions(&mut *tx, snippet.id).await.unwrap();
+        assert_eq!(vec![2, 1], versions.iter().map(|x| x.version).collect::<Vec<_>>());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_shadow_ban() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        SongDao::insert_likes(&mut *tx, &[-1, -2].map(|user_id| SongLike {
+            song_id: -1,
+            user_id,
+            playback_position_secs: None,
+            create_time: Utc::now(),
+        })).await.unwrap();
+        assert_eq!(2, SongDao::count_likes(&mut *tx, -1).await.unwrap());
+
+        let ban = UserShadowBan {
+            user_id: -1,
+            features: vec![user_shadow_ban::FEATURE_PLAYS.to_string()],
+            reason: "spam".to_string(),
+            update_by: -3,
+            create_time: Utc::now(),
+            update_time: Utc::now(),
+        };
+        UserShadowBanDao::upsert(&mut *tx, &ban).await.unwrap();
+        // The likes are counted until they are shadow-banned as well
+        assert_eq!(2, SongDao::count_likes(&mut *tx, -1).await.unwrap());
+        UserShadowBanDao::upsert(&mut *tx, &UserShadowBan {
+            features: vec![user_shadow_ban::FEATURE_LIKES.to_string(), user_shadow_ban::FEATURE_PLAYS.to_string()],
+            ..ban.clone()
+        }).await.unwrap();
+        assert_eq!(1, SongDao::count_likes(&mut *tx, -1).await.unwrap());
+        assert_eq!(Some(&1), SongDao::count_likes_batch(&mut *tx, &[-1]).await.unwrap().get(&-1));
+        // The liker still sees their own like
+        assert!(SongDao::is_liked(&mut *tx, -1, -1).await.unwrap());
+
+        let saved = UserShadowBanDao::get_by_user_id(&mut *tx, -1).await.unwrap().unwrap();
+        assert_eq!(2, saved.features.len());
+        assert!(UserShadowBanDao::list(&mut *tx).await.unwrap().iter().any(|x| x.user_id == -1));
+        assert!(UserShadowBanDao::delete_by_user_id(&mut *tx, -1).await.unwrap());
+        assert!(!UserShadowBanDao::delete_by_user_id(&mut *tx, -1).await.unwrap());
+        assert_eq!(2, SongDao::count_likes(&mut *tx, -1).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_totp() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let secret = UserTotpSecret {
+            user_id: -1,
+            secret: "JBSWY3DPEHPK3PXP".to_string(),
+            confirm_time: None,
+            create_time: Utc::now(),
+        };
+        UserTotpDao::upsert(&mut *tx, &secret).await.unwrap();
+        assert!(UserTotpDao::confirm(&mut *tx, -1, Utc::now()).await.unwrap());
+        assert!(!UserTotpDao::confirm(&mut *tx, -1, Utc::now()).await.unwrap());
+        assert!(UserTotpDao::get_by_user_id(&mut *tx, -1).await.unwrap().unwrap().confirm_time.is_some());
+        // Re-enrolling resets the confirmation
+        UserTotpDao::upsert(&mut *tx, &secret).await.unwrap();
+        assert!(UserTotpDao::get_by_user_id(&mut *tx, -1).await.unwrap().unwrap().confirm_time.is_none());
+
+        UserTotpDao::insert_recovery_codes(&mut *tx, -1, &["a".to_string(), "b".to_string()]).await.unwrap();
+        assert_eq!(2, UserTotpDao::count_unused_recovery_codes(&mut *tx, -1).await.unwrap());
+        assert!(UserTotpDao::use_recovery_code(&mut *tx, -1, "a", Utc::now()).await.unwrap());
+        assert!(!UserTotpDao::use_recovery_code(&mut *tx, -1, "a", Utc::now()).await.unwrap());
+        assert!(!UserTotpDao::use_recovery_code(&mut *tx, -2, "b", Utc::now()).await.unwrap());
+        assert_eq!(1, UserTotpDao::count_unused_recovery_codes(&mut *tx, -1).await.unwrap());
+
+        UserTotpDao::delete_recovery_codes(&mut *tx, -1).await.unwrap();
+        assert_eq!(0, UserTotpDao::count_unused_recovery_codes(&mut *tx, -1).await.unwrap());
+        assert!(UserTotpDao::delete_by_user_id(&mut *tx, -1).await.unwrap());
+        assert!(!UserTotpDao::delete_by_user_id(&mut *tx, -1).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_follow() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        for follower_id in [-2, -3] {
+            let value = UserFollow { follower_id, followee_id: -1, create_time: Utc::now() };
+            assert!(UserFollowDao::insert(&mut *tx, &value).await.unwrap());
+            assert!(!UserFollowDao::insert(&mut *tx, &value).await.unwrap());
+        }
+        assert!(UserFollowDao::is_following(&mut *tx, -2, -1).await.unwrap());
+        assert!(!UserFollowDao::is_following(&mut *tx, -1, -2).await.unwrap());
+        assert_eq!(2, UserFollowDao::count_followers(&mut *tx, -1).await.unwrap());
+        assert_eq!(1, UserFollowDao::count_following(&mut *tx, -2).await.unwrap());
+        let counts = UserFollowDao::count_followers_batch(&mut *tx, &[-1, -2]).await.unwrap();
+        assert_eq!(Some(&2), counts.get(&-1));
+        assert_eq!(None, counts.get(&-2));
+
+        // Latest first
+        let followers = UserFollowDao::page_followers(&mut *tx, -1, 0, 1).await.unwrap();
+        assert_eq!(-3, followers[0].follower_id);
+        let followers = UserFollowDao::page_followers(&mut *tx, -1, 1, 1).await.unwrap();
+        assert_eq!(-2, followers[0].follower_id);
+        assert_eq!(-1, UserFollowDao::page_following(&mut *tx, -3, 0, 10).await.unwrap()[0].followee_id);
+
+        assert!(UserFollowDao::delete(&mut *tx, -2, -1).await.unwrap());
+        assert!(!UserFollowDao::delete(&mut *tx, -2, -1).await.unwrap());
+        assert_eq!(1, UserFollowDao::count_followers(&mut *tx, -1).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_comment() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let now = Utc::now();
+        let mut comment = SongComment {
+            id: 0,
+            song_id: -1,
+            user_id: -1,
+            parent_id: None,
+            reply_to_uid: None,
+            content: "hello".to_string(),
+            create_time: now,
+            update_time: now,
+            delete_time: None,
+        };
+        let root_id = SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+        comment.parent_id = Some(root_id);
+        let reply_id = SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+        comment.reply_to_uid = Some(-1);
+        SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+
+        assert_eq!(1, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, 0).await.unwrap());
+        assert_eq!(root_id, SongCommentDao::page_roots_by_song_id(&mut *tx, -1, 0, 0, 10).await.unwrap()[0].id);
+        assert_eq!(Some(&2), SongCommentDao::count_replies_batch(&mut *tx, &[root_id], 0).await.unwrap().get(&root_id));
+        assert_eq!(reply_id, SongCommentDao::page_replies(&mut *tx, root_id, 0, 0, 10).await.unwrap()[0].id);
+
+        // A reply of a shadow-banned author is only seen by themselves
+        comment.user_id = -2;
+        let hidden_reply_id = SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+        UserShadowBanDao::upsert(&mut *tx, &UserShadowBan {
+            user_id: -2,
+            features: vec![user_shadow_ban::FEATURE_COMMENTS.to_string()],
+            reason: String::new(),
+            update_by: -1,
+            create_time: now,
+            update_time: now,
+        }).await.unwrap();
+        assert_eq!(Some(&2), SongCommentDao::count_replies_batch(&mut *tx, &[root_id], 0).await.unwrap().get(&root_id));
+        assert_eq!(Some(&3), SongCommentDao::count_replies_batch(&mut *tx, &[root_id], -2).await.unwrap().get(&root_id));
+        let replies = SongCommentDao::page_replies(&mut *tx, root_id, 0, 0, 10).await.unwrap();
+        assert!(replies.iter().all(|x| x.id != hidden_reply_id));
+        let replies = SongCommentDao::page_replies(&mut *tx, root_id, -2, 0, 10).await.unwrap();
+        assert!(replies.iter().any(|x| x.id == hidden_reply_id));
+        SongCommentDao::soft_delete_by_id(&mut *tx, hidden_reply_id, now).await.unwrap();
+
+        // So is a root comment
+        comment.parent_id = None;
+        comment.reply_to_uid = None;
+        let hidden_root_id = SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+        assert_eq!(1, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, 0).await.unwrap());
+        assert_eq!(2, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, -2).await.unwrap());
+        assert_eq!(hidden_root_id, SongCommentDao::page_roots_by_song_id(&mut *tx, -1, -2, 0, 10).await.unwrap()[0].id);
+        SongCommentDao::soft_delete_by_id(&mut *tx, hidden_root_id, now).await.unwrap();
+
+        // The comments of a banned user are hidden from everyone
+        let name = format!("t{}", rand::random_range(0..i32::MAX));
+        comment.user_id = UserDao::insert(&mut *tx, &User {
+            id: 0,
+            username: name.clone(),
+            email: format!("{name}@example.com"),
+            password_hash: String::new(),
+            avatar_url: None,
+            bio: None,
+            gender: None,
+            is_banned: true,
+            last_login_time: None,
+            create_time: now,
+            update_time: now,
+            version: 0,
+            preferred_language: None,
+        }).await.unwrap();
+        let banned_root_id = SongCommentDao::insert(&mut *tx, &comment).await.unwrap();
+        assert_eq!(1, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, comment.user_id).await.unwrap());
+        let roots = SongCommentDao::page_roots_by_song_id(&mut *tx, -1, 0, 0, 10).await.unwrap();
+        assert!(roots.iter().all(|x| x.id != banned_root_id));
+        SongCommentDao::soft_delete_by_id(&mut *tx, banned_root_id, now).await.unwrap();
+
+        assert!(SongCommentDao::insert_report(&mut *tx, reply_id, -2, "spam").await.unwrap());
+        assert!(!SongCommentDao::insert_report(&mut *tx, reply_id, -2, "spam").await.unwrap());
+        assert_eq!(1, SongCommentDao::count_reports(&mut *tx, reply_id).await.unwrap());
+
+        // The deleted root comment is kept for the replies
+        assert!(SongCommentDao::soft_delete_by_id(&mut *tx, root_id, now).await.unwrap());
+        assert!(!SongCommentDao::soft_delete_by_id(&mut *tx, root_id, now).await.unwrap());
+        assert_eq!("", SongCommentDao::get_by_id(&mut *tx, root_id).await.unwrap().unwrap().content);
+        assert_eq!(1, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, 0).await.unwrap());
+        let purge_time = now + chrono::TimeDelta::seconds(1);
+        SongCommentDao::purge_deleted(&mut *tx, purge_time).await.unwrap();
+        assert!(SongCommentDao::get_by_id(&mut *tx, root_id).await.unwrap().is_some());
+
+        // Until all the replies are deleted and purged
+        let replies = SongCommentDao::page_replies(&mut *tx, root_id, 0, 0, 10).await.unwrap();
+        for x in &replies {
+            SongCommentDao::soft_delete_by_id(&mut *tx, x.id, now).await.unwrap();
+        }
+        assert_eq!(0, SongCommentDao::count_roots_by_song_id(&mut *tx, -1, 0).await.unwrap());
+        assert!(SongCommentDao::purge_deleted(&mut *tx, purge_time).await.unwrap() >= 2);
+        assert!(SongCommentDao::get_by_id(&mut *tx, reply_id).await.unwrap().is_none());
+        assert!(SongCommentDao::purge_deleted(&mut *tx, purge_time).await.unwrap() >= 1);
+        assert!(SongCommentDao::get_by_id(&mut *tx, root_id).await.unwrap().is_none());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_tag_alias() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let prefix = format!("alias_test_{}", rand::random::<u16>());
+        let mut ids = vec![];
+        for name in ["canonical", "variant"] {
+            let id = SongTagDao::insert(&mut *tx, &SongTag {
+                id: 0,
+                name: format!("{prefix}_{name}"),
+                description: None,
+                is_active: true,
+                create_time: Utc::now(),
+                update_time: Utc::now(),
+            }).await.unwrap();
+            ids.push(id);
+        }
+        for alias in ["variant", "other"] {
+            SongTagDao::insert_alias(&mut *tx, &SongTagAlias {
+                id: 0,
+                tag_id: ids[0],
+                alias: format!("{prefix}_{alias}"),
+                create_time: Utc::now(),
+            }).await.unwrap();
+        }
+
+        // The tag named by an alias is hidden, the canonical one matches by its aliases
+        let found = SongTagDao::search_by_prefix(&mut *tx, &prefix).await.unwrap();
+        assert_eq!(vec![ids[0]], found.iter().map(|x| x.id).collect::<Vec<_>>());
+        let found = SongTagDao::search_by_prefix(&mut *tx, &format!("{prefix}_oth")).await.unwrap();
+        assert_eq!(vec![ids[0]], found.iter().map(|x| x.id).collect::<Vec<_>>());
+
+        let aliases = SongTagDao::list_aliases_by_names(&mut *tx, &[format!("{prefix}_variant")]).await.unwrap();
+        assert_eq!(ids[0], aliases[0].tag_id);
+        assert!(SongTagDao::delete_alias(&mut *tx, aliases[0].id).await.unwrap());
+        assert!(!SongTagDao::delete_alias(&mut *tx, aliases[0].id).await.unwrap());
+        assert_eq!(2, SongTagDao::search_by_prefix(&mut *tx, &prefix).await.unwrap().len());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_version_channels() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let variant = format!("channel_test_{}", rand::random::<u16>());
+        let now = Utc::now();
+        for (number, channel) in [(1, version::CHANNEL_STABLE), (2, version::CHANNEL_BETA)] {
+            VersionDao::insert(&mut *tx, &Version {
+                id: 0,
+                version_name: format!("v{number}"),
+                version_number: number,
+                changelog: String::new(),
+                variant: variant.clone(),
+                channel: channel.to_string(),
+                url: String::new(),
+                release_time: now - chrono::TimeDelta::minutes(10 - number as i64),
+                create_time: now,
+                update_time: now,
+            }).await.unwrap();
+        }
+        let stable = VersionDao::get_latest_version(&mut *tx, &variant, version::included_channels("stable").unwrap(), now).await.unwrap();
+        assert_eq!(1, stable.unwrap().version_number);
+        let nightly = VersionDao::get_latest_version(&mut *tx, &variant, version::included_channels("nightly").unwrap(), now).await.unwrap();
+        assert_eq!(2, nightly.unwrap().version_number);
+        assert!(version::included_channels("alpha").is_none());
+        assert_eq!(1, VersionDao::count(&mut *tx, Some(&variant), Some(version::CHANNEL_BETA)).await.unwrap());
+
+        let user_id = -rand::random_range(1..i64::MAX);
+        assert!(UserReleaseChannelDao::get_by_user_id(&mut *tx, user_id).await.unwrap().is_none());
+        for channel in [version::CHANNEL_BETA, version::CHANNEL_STABLE] {
+            UserReleaseChannelDao::upsert(&mut *tx, &UserReleaseChannel {
+                user_id,
+                channel: channel.to_string(),
+                update_time: now,
+            }).await.unwrap();
+        }
+        assert_eq!(Some(version::CHANNEL_STABLE.to_string()), UserReleaseChannelDao::get_by_user_id(&mut *tx, user_id).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_audio_rendition() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let song_id = -rand::random_range(1..i64::MAX);
+        let rendition = |codec: &str, file_url: &str| SongAudioRendition {
+            id: 0,
+            song_id,
+            codec: codec.to_string(),
+            bitrate_kbps: 96,
+            file_url: file_url.to_string(),
+            size: 1,
+            source_url: "a.flac".to_string(),
+            create_time: Utc::now(),
+            true_peak: Some(-1.5),
+        };
+        let aac = SongAudioRenditionDao::upsert(&mut *tx, &rendition(song_audio_rendition::CODEC_AAC, "a.m4a")).await.unwrap();
+        SongAudioRenditionDao::upsert(&mut *tx, &rendition(song_audio_rendition::CODEC_OPUS, "a.opus")).await.unwrap();
+        // Replaced
+        assert_eq!(aac, SongAudioRenditionDao::upsert(&mut *tx, &rendition(song_audio_rendition::CODEC_AAC, "b.m4a")).await.unwrap());
+
+        let renditions = SongAudioRenditionDao::list_by_song_ids(&mut *tx, &[song_id]).await.unwrap();
+        assert_eq!(vec!["b.m4a", "a.opus"], renditions.iter().map(|x| x.file_url.as_str()).collect::<Vec<_>>());
+        assert_eq!(Some(-1.5), renditions[0].true_peak);
+        assert_eq!(vec!["a.opus".to_string()], SongAudioRenditionDao::delete_except(&mut *tx, song_id, &[aac]).await.unwrap());
+        assert_eq!(1, SongAudioRenditionDao::list_by_song_ids(&mut *tx, &[song_id]).await.unwrap().len());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_notification() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        let mut ids = vec![];
+        for r#type in ["follow", "comment", "mention"] {
+            ids.push(NotificationDao::insert(&mut *tx, &Notification {
+                id: 0,
+                user_id,
+                r#type: r#type.to_string(),
+                actor_uid: Some(-1),
+                data: serde_json::json!({}),
+                read_time: None,
+                create_time: Utc::now(),
+            }).await.unwrap());
+        }
+        // Latest first
+        let page = NotificationDao::page_by_user(&mut *tx, user_id, false, 0, 2).await.unwrap();
+        assert_eq!(vec!["mention", "comment"], page.iter().map(|x| x.r#type.as_str()).collect::<Vec<_>>());
+
+        // Others' notifications are not marked
+        assert_eq!(1, NotificationDao::mark_read(&mut *tx, user_id, &[ids[0], -1], Utc::now()).await.unwrap());
+        assert_eq!(0, NotificationDao::mark_read(&mut *tx, user_id + 1, &[ids[1]], Utc::now()).await.unwrap());
+        assert_eq!(3, NotificationDao::count_by_user(&mut *tx, user_id, false).await.unwrap());
+        assert_eq!(2, NotificationDao::count_by_user(&mut *tx, user_id, true).await.unwrap());
+        let unread = NotificationDao::page_by_user(&mut *tx, user_id, true, 0, 10).await.unwrap();
+        assert!(unread.iter().all(|x| x.id != ids[0]));
+
+        assert_eq!(2, NotificationDao::mark_all_read(&mut *tx, user_id, Utc::now()).await.unwrap());
+        assert_eq!(0, NotificationDao::count_by_user(&mut *tx, user_id, true).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_role() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        assert!(UserRoleDao::role_exists(&mut *tx, "contributor").await.unwrap());
+        assert!(!UserRoleDao::role_exists(&mut *tx, "nobody").await.unwrap());
+
+        let role = UserRole {
+            user_id,
+            role: "contributor".to_string(),
+            granted_by: Some(-1),
+            create_time: Utc::now(),
+        };
+        assert!(UserRoleDao::grant(&mut *tx, &role).await.unwrap());
+        assert!(!UserRoleDao::grant(&mut *tx, &role).await.unwrap());
+        assert_eq!(vec!["contributor"], UserRoleDao::list_by_user(&mut *tx, user_id).await.unwrap());
+        assert!(UserRoleDao::list_user_ids_by_role(&mut *tx, "contributor").await.unwrap().contains(&user_id));
+        // An unknown role can't be granted
+        assert!(UserRoleDao::grant(&mut *tx, &UserRole { role: "nobody".to_string(), ..role.clone() }).await.is_err());
+        tx.rollback().await.unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        UserRoleDao::grant(&mut *tx, &role).await.unwrap();
+        assert!(UserRoleDao::revoke(&mut *tx, user_id, "contributor").await.unwrap());
+        assert!(!UserRoleDao::revoke(&mut *tx, user_id, "contributor").await.unwrap());
+        assert!(UserRoleDao::list_by_user(&mut *tx, user_id).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_share() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let song_id = -rand::random_range(1..i64::MAX);
+        let today = Utc::now().date_naive();
+        SongShareDao::increment(&mut *tx, song_id, today, "link").await.unwrap();
+        SongShareDao::increment(&mut *tx, song_id, today, "link").await.unwrap();
+        SongShareDao::increment(&mut *tx, song_id, today - chrono::Days::new(1), "qq").await.unwrap();
+        let counts = SongShareDao::count_by_song_ids(&mut *tx, &[song_id, song_id + 1]).await.unwrap();
+        assert_eq!(Some(&3), counts.get(&song_id));
+        assert_eq!(None, counts.get(&(song_id + 1)));
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_legal_document() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let now = Utc::now();
+        let doc = LegalDocument {
+            id: 0,
+            kind: "terms".to_string(),
+            version: 0,
+            title: "Terms".to_string(),
+            content: "...".to_string(),
+            mandatory: true,
+            publish_time: now,
+            create_time: now,
+        };
+        let v1 = LegalDocumentDao::insert_next_version(&mut *tx, &doc).await.unwrap();
+        let v2 = LegalDocumentDao::insert_next_version(&mut *tx, &LegalDocument { mandatory: false, ..doc.clone() }).await.unwrap();
+        assert_eq!(v1 + 1, v2);
+        let latest = LegalDocumentDao::list_latest(&mut *tx, now).await.unwrap();
+        assert_eq!(Some(v2), latest.iter().find(|x| x.kind == "terms").map(|x| x.version));
+        let mandatory = LegalDocumentDao::list_latest_mandatory(&mut *tx, now).await.unwrap();
+        assert_eq!(Some(v1), mandatory.iter().find(|x| x.kind == "terms").map(|x| x.version));
+
+        let user_id = -rand::random_range(1..i64::MAX);
+        let acceptance = |version| UserLegalAcceptance { user_id, kind: "terms".to_string(), version, accept_time: now };
+        LegalDocumentDao::accept(&mut *tx, &acceptance(v2)).await.unwrap();
+        // An older version doesn't replace the newer one
+        LegalDocumentDao::accept(&mut *tx, &acceptance(v1)).await.unwrap();
+        let accepted = LegalDocumentDao::list_acceptances(&mut *tx, user_id).await.unwrap();
+        assert_eq!(vec![v2], accepted.iter().map(|x| x.version).collect::<Vec<_>>());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_report() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let now = Utc::now();
+        let song_id = -rand::random_range(1..i64::MAX);
+        let user_id = -rand::random_range(1..i64::MAX);
+        let report = SongReport {
+            id: 0,
+            song_id,
+            reporter_uid: user_id,
+            category: "copyright".to_string(),
+            reason: "".to_string(),
+            status: song_report::STATUS_PENDING,
+            resolution_note: None,
+            resolved_by: None,
+            resolve_time: None,
+            create_time: now,
+        };
+        let id = SongReportDao::insert(&mut *tx, &report).await.unwrap().unwrap();
+        // Reported again before handled
+        assert_eq!(None, SongReportDao::insert(&mut *tx, &report).await.unwrap());
+        assert_eq!(1, SongReportDao::count_by_reporter_since(&mut *tx, user_id, now - chrono::Duration::hours(1)).await.unwrap());
+
+        assert!(SongReportDao::resolve(&mut *tx, id, song_report::STATUS_DISMISSED, 1, Some("ok"), now).await.unwrap());
+        assert!(!SongReportDao::resolve(&mut *tx, id, song_report::STATUS_RESOLVED, 1, None, now).await.unwrap());
+        let resolved = SongReportDao::get_by_id(&mut *tx, id).await.unwrap().unwrap();
+        assert_eq!(song_report::STATUS_DISMISSED, resolved.status);
+        // Can be reported again after handled
+        assert!(SongReportDao::insert(&mut *tx, &report).await.unwrap().is_some());
+
+        let hidden = HiddenSong { song_id, reason: "".to_string(), hidden_by: 1, create_time: now };
+        assert!(HiddenSongDao::insert(&mut *tx, &hidden).await.unwrap());
+        assert!(!HiddenSongDao::insert(&mut *tx, &hidden).await.unwrap());
+        assert_eq!(vec![song_id], HiddenSongDao::list_hidden_ids(&mut *tx, &[song_id, song_id + 1]).await.unwrap());
+        assert!(HiddenSongDao::delete(&mut *tx, song_id).await.unwrap());
+        assert!(HiddenSongDao::list_hidden_ids(&mut *tx, &[song_id]).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_user_support_link() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX);
+        let link = |platform: &str, position| UserSupportLink {
+            user_id,
+            platform: platform.to_string(),
+            url: format!("https://{platform}.com"),
+            position,
+            create_time: Utc::now(),
+        };
+        UserSupportLinkDao::insert(&mut *tx, &link("patreon", 1)).await.unwrap();
+        UserSupportLinkDao::insert(&mut *tx, &link("afdian", 0)).await.unwrap();
+        let links = UserSupportLinkDao::list_by_user_ids(&mut *tx, &[user_id]).await.unwrap();
+        assert_eq!(vec!["afdian", "patreon"], links.iter().map(|x| x.platform.as_str()).collect::<Vec<_>>());
+
+        UserSupportLinkDao::delete_by_user(&mut *tx, user_id).await.unwrap();
+        assert!(UserSupportLinkDao::list_by_user_ids(&mut *tx, &[user_id]).await.unwrap().is_empty());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_weekly_selection() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        // Far in the future, so it's the latest
+        let week_start = chrono::NaiveDate::from_ymd_opt(9999, 1, 4).unwrap();
+        let selection = WeeklySelection { week_start, playlist_id: -1, create_time: Utc::now() };
+        assert!(WeeklySelectionDao::insert(&mut *tx, &selection).await.unwrap());
+        assert!(!WeeklySelectionDao::insert(&mut *tx, &WeeklySelection { playlist_id: -2, ..selection.clone() }).await.unwrap());
+        assert_eq!(Some(-1), WeeklySelectionDao::get_by_week(&mut *tx, week_start).await.unwrap().map(|x| x.playlist_id));
+        assert_eq!(Some(week_start), WeeklySelectionDao::get_latest(&mut *tx).await.unwrap().map(|x| x.week_start));
+        let page = WeeklySelectionDao::page(&mut *tx, 0, 1).await.unwrap();
+        assert_eq!(week_start, page[0].week_start);
+        assert!(WeeklySelectionDao::count(&mut *tx).await.unwrap() >= 1);
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retention_purge() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX / 2);
+        let held_user_id = user_id - 1;
+        let old = "2000-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for (uid, song_id) in [(user_id, 1), (user_id, 2), (user_id, 3), (held_user_id, 1)] {
+            sqlx::query("INSERT INTO user_play_history (user_id, song_id, create_time) VALUES ($1, $2, $3)")
+                .bind(uid).bind(song_id).bind(old)
+                .execute(&mut *tx).await.unwrap();
+        }
+        assert!(UserLegalHoldDao::insert(&mut *tx, &UserLegalHold {
+            user_id: held_user_id,
+            reason: "test".to_string(),
+            held_by: user_id,
+            create_time: Utc::now(),
+        }).await.unwrap());
+        assert_eq!(held_user_id, UserLegalHoldDao::list(&mut *tx).await.unwrap()[0].user_id);
+
+        let before = old + chrono::Duration::days(1);
+        assert_eq!(2, RetentionDao::purge_play_history(&mut *tx, before, 2).await.unwrap());
+        assert_eq!(1, RetentionDao::purge_play_history(&mut *tx, before, 2).await.unwrap());
+        // The held user is exempted
+        assert_eq!(0, RetentionDao::purge_play_history(&mut *tx, before, 2).await.unwrap());
+
+        assert!(UserLegalHoldDao::delete_by_user_id(&mut *tx, held_user_id).await.unwrap());
+        assert!(!UserLegalHoldDao::delete_by_user_id(&mut *tx, held_user_id).await.unwrap());
+        assert_eq!(1, RetentionDao::purge_play_history(&mut *tx, before, 2).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_play_history_cursor() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX / 2);
+        let time = "2000-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        // Played at the same time, so the song id tells them apart
+        for song_id in [1, 2, 3] {
+            sqlx::query("INSERT INTO user_play_history (user_id, song_id, create_time) VALUES ($1, $2, $3)")
+                .bind(user_id).bind(song_id).bind(time)
+                .execute(&mut *tx).await.unwrap();
+        }
+
+        let first = UserPlayHistoryDao::cursor_by_user_id(&mut *tx, user_id, None, 2).await.unwrap();
+        assert_eq!(vec![3, 2], first.iter().map(|x| x.song_id).collect::<Vec<_>>());
+        let last = first.last().unwrap();
+        let second = UserPlayHistoryDao::cursor_by_user_id(&mut *tx, user_id, Some((last.create_time, last.song_id)), 2).await.unwrap();
+        assert_eq!(vec![1], second.iter().map(|x| x.song_id).collect::<Vec<_>>());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retention_purge_song_plays() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let user_id = -rand::random_range(1..i64::MAX / 2);
+        let held_user_id = user_id - 1;
+        let song_id = -rand::random_range(1..i64::MAX);
+        // Older than any real play, so only these are purged
+        let old = "1990-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let plays = [Some(user_id), None, Some(held_user_id)]
+            .map(|user_id| SongPlay { id: 0, song_id, user_id, anonymous_uid: None, create_time: old });
+        SongDao::insert_plays(&mut *tx, &plays).await.unwrap();
+        SongDao::insert_plays(&mut *tx, &[SongPlay { create_time: Utc::now(), ..plays[0].clone() }]).await.unwrap();
+        UserLegalHoldDao::insert(&mut *tx, &UserLegalHold {
+            user_id: held_user_id,
+            reason: "test".to_string(),
+            held_by: user_id,
+            create_time: Utc::now(),
+        }).await.unwrap();
+
+        // The held user and the recent play are kept, and the purged plays are still counted
+        let before = old + chrono::Duration::days(1);
+        assert_eq!(2, RetentionDao::purge_song_plays(&mut *tx, before, 10).await.unwrap());
+        assert_eq!(0, RetentionDao::purge_song_plays(&mut *tx, before, 10).await.unwrap());
+        assert_eq!(4, SongDao::count_plays(&mut *tx, song_id).await.unwrap());
+
+        UserLegalHoldDao::delete_by_user_id(&mut *tx, held_user_id).await.unwrap();
+        assert_eq!(1, RetentionDao::purge_song_plays(&mut *tx, before, 10).await.unwrap());
+        assert_eq!(4, SongDao::count_plays(&mut *tx, song_id).await.unwrap());
+        assert_eq!(Some(&4), SongDao::count_plays_batch(&mut *tx, &[song_id]).await.unwrap().get(&song_id));
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_play_rollup() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        // Far in the future, so no other plays are rolled up
+        let day = chrono::NaiveDate::from_ymd_opt(9999, 1, 1).unwrap();
+        let time = day.and_hms_opt(23, 59, 0).unwrap().and_utc();
+        let song_id = -rand::random_range(1..i64::MAX);
+        let plays = [(song_id, time), (song_id, time), (song_id, time + chrono::Duration::minutes(2))]
+            .map(|(song_id, create_time)| SongPlay { id: 0, song_id, user_id: None, anonymous_uid: None, create_time });
+        SongDao::insert_plays(&mut *tx, &plays).await.unwrap();
+
+        assert_eq!(2, SongPlayRollupDao::insert_since(&mut *tx, day, user_shadow_ban::FEATURE_PLAYS).await.unwrap());
+        assert_eq!(Some(day.succ_opt().unwrap()), SongPlayRollupDao::get_latest_day(&mut *tx).await.unwrap());
+        let counts = sqlx::query_as::<_, (chrono::NaiveDate, i64)>(
+            "SELECT day, play_count FROM song_play_counts_daily WHERE song_id = $1 ORDER BY day"
+        ).bind(song_id).fetch_all(&mut *tx).await.unwrap();
+        assert_eq!(vec![(day, 2), (day.succ_opt().unwrap(), 1)], counts);
+        assert_eq!(2, SongPlayRollupDao::delete_since(&mut *tx, day).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_song_delete_with_contents() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let now = Utc::now();
+        let song_id = SongDao::insert(&mut *tx, &Song {
+            id: 0,
+            display_id: format!("T{}", rand::random_range(1..1_000_000_000_000i64)),
+            title: "test".to_string(),
+            subtitle: String::new(),
+            description: String::new(),
+            artist: String::new(),
+            file_url: String::new(),
+            cover_art_url: String::new(),
+            lyrics: String::new(),
+            duration_seconds: 1,
+            uploader_uid: -1,
+            creation_type: 0,
+            play_count: 0,
+            like_count: 0,
+            is_private: false,
+            release_time: now,
+            create_time: now,
+            update_time: now,
+            explicit: None,
+            gain: None,
+            loudness_lufs: None,
+            true_peak: None,
+            version: 0,
+        }).await.unwrap();
+        SongDao::insert_likes(&mut *tx, &[SongLike { song_id, user_id: -1, playback_position_secs: None, create_time: now }]).await.unwrap();
+        SongDao::insert_plays(&mut *tx, &[SongPlay { id: 0, song_id, user_id: None, anonymous_uid: None, create_time: now }]).await.unwrap();
+        SongAudioRenditionDao::upsert(&mut *tx, &SongAudioRendition {
+            id: 0,
+            song_id,
+            codec: song_audio_rendition::CODEC_AAC.to_string(),
+            bitrate_kbps: 128,
+            file_url: "a.m4a".to_string(),
+            size: 1,
+            source_url: String::new(),
+            create_time: now,
+            true_peak: None,
+        }).await.unwrap();
+
+        assert_eq!(vec!["a.m4a".to_string()], SongDao::delete_with_contents(&mut tx, song_id).await.unwrap());
+        assert!(SongDao::get_by_id(&mut *tx, song_id).await.unwrap().is_none());
+        assert!(!SongDao::is_liked(&mut *tx, song_id, -1).await.unwrap());
+        assert!(SongAudioRenditionDao::list_by_song_ids(&mut *tx, &[song_id]).await.unwrap().is_empty());
+        // The plays are kept for the stats
+        assert_eq!(1, SongDao::count_plays(&mut *tx, song_id).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_review_cursor_filtered() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let prefix = format!("T{}-", rand::random_range(1..1_000_000_000_000i64));
+        let now = Utc::now();
+        let review = |suffix: &str, submit_time: DateTime<Utc>, status: i32| SongPublishingReview {
+            id: 0,
+            user_id: -1,
+            song_display_id: format!("{prefix}{suffix}"),
+            data: serde_json::json!({}),
+            submit_time,
+            update_time: submit_time,
+            review_time: None,
+            review_comment: None,
+            status,
+            r#type: song_publishing_review::TYPE_CREATE,
+            comment: None,
+            reviewer_uid: None,
+        };
+        let earlier = now - chrono::Duration::hours(1);
+        let first = SongPublishingReviewDao::insert(&mut *tx, &review("1", earlier, song_publishing_review::STATUS_PENDING)).await.unwrap();
+        let second = SongPublishingReviewDao::insert(&mut *tx, &review("2", now, song_publishing_review::STATUS_PENDING)).await.unwrap();
+        let third = SongPublishingReviewDao::insert(&mut *tx, &review("3", now, song_publishing_review::STATUS_REJECTED)).await.unwrap();
+
+        let ids = |x: Vec<SongPublishingReview>| x.into_iter().map(|x| x.id).collect::<Vec<_>>();
+        let page = SongPublishingReviewDao::cursor_filtered(&mut *tx, None, Some(&prefix), None, 2).await.unwrap();
+        assert_eq!(vec![third, second], ids(page));
+        // The reviews submitted at the same time are paged by the id
+        let page = SongPublishingReviewDao::cursor_filtered(&mut *tx, None, Some(&prefix), Some((now, third)), 2).await.unwrap();
+        assert_eq!(vec![second, first], ids(page));
+        let page = SongPublishingReviewDao::cursor_filtered(&mut *tx, Some(song_publishing_review::STATUS_PENDING), Some(&prefix), None, 10).await.unwrap();
+        assert_eq!(vec![second, first], ids(page));
+        let page = SongPublishingReviewDao::cursor_filtered(&mut *tx, None, Some(&format!("{prefix}1")), None, 10).await.unwrap();
+        assert_eq!(vec![first], ids(page));
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reject_pending_modifies() {
+        let pool = get_test_pool().await;
+        let mut tx = pool.begin().await.unwrap();
+        let display_id = format!("T{}", rand::random_range(1..1_000_000_000_000i64));
+        let now = Utc::now();
+        let review = |r#type: i32, status: i32| SongPublishingReview {
+            id: 0,
+            user_id: -1,
+            song_display_id: display_id.clone(),
+            data: serde_json::json!({}),
+            submit_time: now,
+            update_time: now,
+            review_time: None,
+            review_comment: None,
+            status,
+            r#type,
+            comment: None,
+            reviewer_uid: None,
+        };
+        let created = SongPublishingReviewDao::insert(&mut *tx, &review(song_publishing_review::TYPE_CREATE, song_publishing_review::STATUS_APPROVED)).await.unwrap();
+        let pending = SongPublishingReviewDao::insert(&mut *tx, &review(song_publishing_review::TYPE_MODIFY, song_publishing_review::STATUS_PENDING)).await.unwrap();
+
+        assert_eq!(1, SongPublishingReviewDao::reject_pending_modifies(&mut *tx, &display_id, "Deleted", now).await.unwrap());
+        let rejected = SongPublishingReviewDao::get_by_id(&mut *tx, pending).await.unwrap().unwrap();
+        assert_eq!(song_publishing_review::STATUS_REJECTED, rejected.status);
+        assert_eq!(Some("Deleted".to_string()), rejected.review_comment);
+        assert!(rejected.review_time.is_some());
+        let approved = SongPublishingReviewDao::get_by_id(&mut *tx, created).await.unwrap().unwrap();
+        assert_eq!(song_publishing_review::STATUS_APPROVED, approved.status);
+        assert_eq!(0, SongPublishingReviewDao::reject_pending_modifies(&mut *tx, &display_id, "Deleted", now).await.unwrap());
+        tx.rollback().await.unwrap();
+    }
 }
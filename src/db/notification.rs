@@ -0,0 +1,164 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// An in-app notification
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: i64,
+    /// The recipient
+    pub user_id: i64,
+    pub r#type: String,
+    /// The user who caused the notification, `None` for the system
+    pub actor_uid: Option<i64>,
+    /// Depends on the type
+    pub data: serde_json::Value,
+    pub read_time: Option<DateTime<Utc>>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct NotificationDao;
+
+pub trait INotificationDao<'e, E>: CrudDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Latest first
+    fn page_by_user(executor: E, user_id: i64, unread_only: bool, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<Notification>>> + Send;
+    fn count_by_user(executor: E, user_id: i64, unread_only: bool) -> impl Future<Output = Result<i64>> + Send;
+    /// Mark the unread notifications of the user as read, returns the count marked.
+    /// The IDs of the other users are ignored.
+    fn mark_read(executor: E, user_id: i64, ids: &[i64], time: DateTime<Utc>) -> impl Future<Output = Result<u64>> + Send;
+    fn mark_all_read(executor: E, user_id: i64, time: DateTime<Utc>) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl<'e, E> CrudDao<'e, E> for NotificationDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = Notification;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM notifications ORDER BY id DESC")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM notifications ORDER BY id DESC LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM notifications WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE notifications SET
+                user_id = $1,
+                type = $2,
+                actor_uid = $3,
+                data = $4,
+                read_time = $5,
+                create_time = $6
+            WHERE id = $7",
+            value.user_id,
+            value.r#type,
+            value.actor_uid,
+            value.data,
+            value.read_time,
+            value.create_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query!(
+            "INSERT INTO notifications (user_id, type, actor_uid, data, read_time, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            value.user_id,
+            value.r#type,
+            value.actor_uid,
+            value.data,
+            value.read_time,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+        .map(|x| x.id)
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM notifications WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'e, E> INotificationDao<'e, E> for NotificationDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn page_by_user(executor: E, user_id: i64, unread_only: bool, page_index: i64, page_size: i64) -> Result<Vec<Notification>> {
+        sqlx::query_as!(
+            Notification,
+            "SELECT * FROM notifications
+            WHERE user_id = $1 AND (NOT $2 OR read_time IS NULL)
+            ORDER BY id DESC LIMIT $3 OFFSET $4",
+            user_id,
+            unread_only,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn count_by_user(executor: E, user_id: i64, unread_only: bool) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM notifications WHERE user_id = $1 AND (NOT $2 OR read_time IS NULL)"#,
+            user_id,
+            unread_only
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn mark_read(executor: E, user_id: i64, ids: &[i64], time: DateTime<Utc>) -> Result<u64> {
+        if ids.is_empty() { return Ok(0) }
+        let result = sqlx::query!(
+            "UPDATE notifications SET read_time = $1 WHERE user_id = $2 AND id = ANY($3) AND read_time IS NULL",
+            time,
+            user_id,
+            ids
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn mark_all_read(executor: E, user_id: i64, time: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE notifications SET read_time = $1 WHERE user_id = $2 AND read_time IS NULL",
+            time,
+            user_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
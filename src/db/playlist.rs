@@ -1,3 +1,4 @@
+use crate::db::error::{DbError, DbResult};
 use crate::db::CrudDao;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -14,13 +15,19 @@ pub struct Playlist {
     pub is_public: bool,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
+    /// Whether the cover is generated from the first songs, false once a custom cover is set
+    pub use_song_cover: bool,
+    /// Row version for optimistic locking
+    #[serde(default)]
+    pub version: i64,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct PlaylistSong {
     pub playlist_id: i64,
     pub song_id: i64,
-    pub order_index: i32,
+    /// Fractional sort key, see [crate::util::fractional_index]
+    pub sort_key: String,
     pub add_time: DateTime<Utc>,
 }
 
@@ -39,18 +46,30 @@ where
     E: PgExecutor<'e>,
 {
     fn remove_song(executor: E, playlist_id: i64, song_id: i64) -> impl Future<Output=sqlx::Result<()>> + Send;
-    fn add_song(executor: E, value: &PlaylistSong) -> impl Future<Output=sqlx::Result<()>> + Send;
+    /// Returns false if the song is already in the playlist, `(playlist_id, song_id)` is the primary key
+    fn add_song(executor: E, value: &PlaylistSong) -> impl Future<Output=sqlx::Result<bool>> + Send;
     fn list_songs(executor: E, playlist_id: i64) -> impl Future<Output=sqlx::Result<Vec<PlaylistSong>>> + Send;
+    /// The largest sort key of the playlist, to append after it
+    fn get_last_sort_key(executor: E, playlist_id: i64) -> impl Future<Output=sqlx::Result<Option<String>>> + Send;
+    fn update_song_sort_key(executor: E, playlist_id: i64, song_id: i64, sort_key: &str) -> impl Future<Output=sqlx::Result<()>> + Send;
+    /// The playlists with any sort key longer than `max_len`
+    fn list_ids_with_long_sort_keys(executor: E, max_len: i32, limit: i64) -> impl Future<Output=sqlx::Result<Vec<i64>>> + Send;
     fn count_songs(executor: E, playlist_ids: &[i64]) -> impl Future<Output=sqlx::Result<HashMap<i64, i64>>> + Send;
+    fn count_songs_of(executor: E, playlist_id: i64) -> impl Future<Output=sqlx::Result<i64>> + Send;
     fn list_by_user(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<Vec<Playlist>>> + Send;
     fn list_by_ids(executor: E, ids: &[i64]) -> impl Future<Output=sqlx::Result<Vec<Playlist>>> + Send;
     fn list_containing(executor: E, song_id: i64, user_id: i64) -> impl Future<Output=sqlx::Result<Vec<Playlist>>> + Send;
     fn count_by_user(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<i64>> + Send;
+    /// The public playlists of the user, the recently updated first. None if the user is banned
+    fn page_public_by_user(executor: E, user_id: i64, page_index: i64, page_size: i64) -> impl Future<Output=sqlx::Result<Vec<Playlist>>> + Send;
+    fn count_public_by_user(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<i64>> + Send;
     fn page_favorites(executor: E, user_id: i64, page_index: i64, page_size: i64) -> impl Future<Output=sqlx::Result<Vec<FavoritePlaylist>>> + Send;
     fn count_favorites(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<i64>> + Send;
     fn add_favorite(executor: E, value: &FavoritePlaylist) -> impl Future<Output=sqlx::Result<()>> + Send;
     fn get_favorite(executor: E, user_id: i64, playlist_id: i64) -> impl Future<Output=sqlx::Result<Option<FavoritePlaylist>>> + Send;
     fn remove_favorite(executor: E, user_id: i64, playlist_id: i64) -> impl Future<Output=sqlx::Result<()>> + Send;
+    /// Update only if the row version still equals `value.version`, returns the new version
+    fn update_by_id_checked(executor: E, value: &Playlist) -> impl Future<Output=DbResult<i64>> + Send;
 }
 
 impl<'e, E> CrudDao<'e, E> for PlaylistDao
@@ -59,12 +78,16 @@ where
 {
     type Entity = Playlist;
 
-    async fn list(_executor: E) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn list(executor: E) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM playlists")
+            .fetch_all(executor)
+            .await
     }
 
-    async fn page(_executor: E, _page: i64, _size: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM playlists ORDER BY id DESC LIMIT $1 OFFSET $2", size, page * size)
+            .fetch_all(executor)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Self::Entity>> {
@@ -82,8 +105,10 @@ where
                 cover_url = $4,
                 is_public = $5,
                 create_time = $6,
-                update_time = $7
-            WHERE id = $8",
+                update_time = $7,
+                use_song_cover = $8,
+                version = version + 1
+            WHERE id = $9",
             value.name,
             value.description,
             value.user_id,
@@ -91,6 +116,7 @@ where
             value.is_public,
             value.create_time,
             value.update_time,
+            value.use_song_cover,
             value.id,
         ).execute(executor).await?;
         Ok(())
@@ -105,21 +131,30 @@ where
                cover_url,
                is_public,
                create_time,
-               update_time
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+               update_time,
+               use_song_cover
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
             value.name,
             value.description,
             value.user_id,
             value.cover_url,
             value.is_public,
             value.create_time,
-            value.update_time
+            value.update_time,
+            value.use_song_cover,
         ).fetch_one(executor).await
             .map(|x| x.id)
     }
 
-    async fn delete_by_id(_executor: E, _id: i64) -> sqlx::Result<()> {
-        todo!()
+    /// Delete the playlist along with its songs and favorites in a single statement
+    async fn delete_by_id(executor: E, id: i64) -> sqlx::Result<()> {
+        sqlx::query!(
+            "WITH deleted_songs AS (DELETE FROM playlist_songs WHERE playlist_id = $1),
+                deleted_favorites AS (DELETE FROM favorite_playlists WHERE playlist_id = $1)
+            DELETE FROM playlists WHERE id = $1",
+            id
+        ).execute(executor).await?;
+        Ok(())
     }
 }
 
@@ -127,6 +162,34 @@ impl<'e, E> IPlaylistDao<'e, E> for PlaylistDao
 where
     E: PgExecutor<'e>,
 {
+    async fn update_by_id_checked(executor: E, value: &Playlist) -> DbResult<i64> {
+        sqlx::query_scalar!(
+            "UPDATE playlists SET
+                name = $1,
+                description = $2,
+                user_id = $3,
+                cover_url = $4,
+                is_public = $5,
+                create_time = $6,
+                update_time = $7,
+                use_song_cover = $8,
+                version = version + 1
+            WHERE id = $9 AND version = $10
+            RETURNING version",
+            value.name,
+            value.description,
+            value.user_id,
+            value.cover_url,
+            value.is_public,
+            value.create_time,
+            value.update_time,
+            value.use_song_cover,
+            value.id,
+            value.version,
+        ).fetch_optional(executor).await?
+            .ok_or(DbError::VersionConflict)
+    }
+
     async fn remove_song(executor: E, playlist_id: i64, song_id: i64) -> sqlx::Result<()> {
         sqlx::query!("DELETE FROM playlist_songs WHERE playlist_id = $1 AND song_id = $2", playlist_id, song_id)
             .execute(executor)
@@ -134,23 +197,55 @@ where
         Ok(())
     }
 
-    async fn add_song(executor: E, value: &PlaylistSong) -> sqlx::Result<()> {
-        sqlx::query!(
-            "INSERT INTO playlist_songs (playlist_id, song_id, order_index, add_time) VALUES ($1, $2, $3, $4)",
+    async fn add_song(executor: E, value: &PlaylistSong) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO playlist_songs (playlist_id, song_id, sort_key, add_time) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (playlist_id, song_id) DO NOTHING",
             value.playlist_id,
             value.song_id,
-            value.order_index,
+            value.sort_key,
             value.add_time,
         ).execute(executor)
             .await?;
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
     async fn list_songs(executor: E, playlist_id: i64) -> sqlx::Result<Vec<PlaylistSong>> {
-        sqlx::query_as!(PlaylistSong, "SELECT * FROM playlist_songs WHERE playlist_id = $1 ORDER BY order_index", playlist_id)
+        sqlx::query_as!(PlaylistSong, "SELECT * FROM playlist_songs WHERE playlist_id = $1 ORDER BY sort_key, song_id", playlist_id)
             .fetch_all(executor)
             .await
     }
 
+    async fn get_last_sort_key(executor: E, playlist_id: i64) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar!("SELECT max(sort_key) FROM playlist_songs WHERE playlist_id = $1", playlist_id)
+            .fetch_one(executor)
+            .await
+    }
+
+    async fn update_song_sort_key(executor: E, playlist_id: i64, song_id: i64, sort_key: &str) -> sqlx::Result<()> {
+        sqlx::query!(
+            "UPDATE playlist_songs SET sort_key = $1 WHERE playlist_id = $2 AND song_id = $3",
+            sort_key,
+            playlist_id,
+            song_id,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn list_ids_with_long_sort_keys(executor: E, max_len: i32, limit: i64) -> sqlx::Result<Vec<i64>> {
+        sqlx::query_scalar!(
+            "SELECT DISTINCT playlist_id FROM playlist_songs WHERE length(sort_key) > $1 LIMIT $2",
+            max_len,
+            limit,
+        ).fetch_all(executor).await
+    }
+
+    async fn count_songs_of(executor: E, playlist_id: i64) -> sqlx::Result<i64> {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM playlist_songs WHERE playlist_id = $1", playlist_id)
+            .fetch_one(executor)
+            .await
+            .map(|x| x.unwrap_or(0))
+    }
+
     async fn count_songs(executor: E, playlist_ids: &[i64]) -> sqlx::Result<HashMap<i64, i64>> {
         if playlist_ids.is_empty() { return Ok(HashMap::new()); }
 
@@ -192,6 +287,25 @@ WHERE ps.song_id = $1 AND p.user_id = $2;", song_id, user_id)
             .map(|x| x.count.unwrap_or(0))
     }
 
+    async fn page_public_by_user(executor: E, user_id: i64, page_index: i64, page_size: i64) -> sqlx::Result<Vec<Playlist>> {
+        let offset = page_index * page_size;
+        sqlx::query_as!(
+            Playlist,
+            "SELECT * FROM playlists WHERE user_id = $1 AND is_public
+                AND user_id NOT IN (SELECT id FROM users WHERE is_banned)
+            ORDER BY update_time DESC, id DESC LIMIT $2 OFFSET $3",
+            user_id, page_size, offset
+        ).fetch_all(executor).await
+    }
+
+    async fn count_public_by_user(executor: E, user_id: i64) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM playlists WHERE user_id = $1 AND is_public
+                AND user_id NOT IN (SELECT id FROM users WHERE is_banned)"#,
+            user_id
+        ).fetch_one(executor).await
+    }
+
     async fn page_favorites(executor: E, user_id: i64, page_index: i64, page_size: i64) -> sqlx::Result<Vec<FavoritePlaylist>> {
         let offset = page_index * page_size;
         sqlx::query_as!(
@@ -244,11 +358,12 @@ WHERE ps.song_id = $1 AND p.user_id = $2;", song_id, user_id)
 }
 
 impl<'e> PlaylistDao {
+    /// Write the sort keys of all the songs, used to normalize the keys
     pub async fn update_songs_orders(tx: &mut PgTransaction<'e>, values: &[PlaylistSong]) -> sqlx::Result<()> {
         for value in values {
             sqlx::query!(
-                "UPDATE playlist_songs SET order_index = $1 WHERE playlist_id = $2 AND song_id = $3",
-                value.order_index,
+                "UPDATE playlist_songs SET sort_key = $1 WHERE playlist_id = $2 AND song_id = $3",
+                value.sort_key,
                 value.playlist_id,
                 value.song_id,
             ).execute(&mut **tx).await?;
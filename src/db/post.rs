@@ -13,10 +13,24 @@ pub struct Post {
     pub cover_url: Option<String>,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
+    /// `Some` if it's deleted, the content is cleared and it's shown as a placeholder until purged
+    pub delete_time: Option<DateTime<Utc>>,
 }
 
 pub struct PostDao;
 
+pub trait IPostDao<'e, E>: CrudDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// The posts not deleted
+    fn count(executor: E) -> impl Future<Output = Result<i64>> + Send;
+    /// Clear the title, content and cover, and leave the tombstone. Returns false if it's deleted already.
+    fn soft_delete_by_id(executor: E, id: i64, time: DateTime<Utc>) -> impl Future<Output = Result<bool>> + Send;
+    /// Delete the tombstones deleted before the time, returns the number of the purged
+    fn purge_deleted(executor: E, before: DateTime<Utc>) -> impl Future<Output = Result<u64>> + Send;
+}
+
 impl<'e, E> CrudDao<'e, E> for PostDao
 where
     E: PgExecutor<'e>,
@@ -30,9 +44,9 @@ where
     }
 
     async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
-        Ok(sqlx::query_as!(Self::Entity, "SELECT * FROM posts ORDER BY create_time DESC LIMIT $1 OFFSET $2", page_size, page_index * page_size)
+        sqlx::query_as!(Self::Entity, "SELECT * FROM posts WHERE delete_time IS NULL ORDER BY create_time DESC LIMIT $1 OFFSET $2", page_size, page_index * page_size)
             .fetch_all(executor)
-            .await?)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
@@ -82,4 +96,34 @@ where
             .await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+impl<'e, E> IPostDao<'e, E> for PostDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn count(executor: E) -> Result<i64> {
+        sqlx::query_scalar!("SELECT COUNT(*) FROM posts WHERE delete_time IS NULL")
+            .fetch_one(executor)
+            .await
+            .map(|count| count.unwrap_or(0))
+    }
+
+    async fn soft_delete_by_id(executor: E, id: i64, time: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE posts SET title = '', content = '', cover_url = NULL, delete_time = $2, update_time = $2
+            WHERE id = $1 AND delete_time IS NULL",
+            id,
+            time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn purge_deleted(executor: E, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM posts WHERE delete_time < $1", before)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -16,6 +16,11 @@ pub struct RefreshToken {
     pub ip_address: Option<String>,
     pub is_revoked: bool,
     pub user_agent: Option<String>,
+    /// Set by the user to tell the devices apart
+    pub device_name: Option<String>,
+    /// The SHA-256 of the trusted device token, see [crate::service::device_trust]
+    pub trust_token_hash: Option<String>,
+    pub trust_time: Option<DateTime<Utc>>,
 }
 
 pub trait IRefreshTokenDao<'e, E>: CrudDao<'e, E> 
@@ -23,6 +28,13 @@ where E: PgExecutor<'e>{
     fn get_by_token_id(executor: E, token_id: &str) -> impl Future<Output = sqlx::Result<Option<RefreshToken>>> + Send;
     fn list_by_uid(executor: E, uid: i64) -> impl Future<Output = sqlx::Result<Vec<RefreshToken>>> + Send;
     fn delete_all_by_uid(executor: E, uid: i64) -> impl Future<Output = sqlx::Result<u64>> + Send;
+    /// Returns false if the device of the user is not found
+    fn rename(executor: E, id: i64, uid: i64, name: Option<&str>) -> impl Future<Output = sqlx::Result<bool>> + Send;
+    /// Set the trust of the device of the user to `(token_hash, trust_time)`, or clear it if `None`,
+    /// returns false if the device is not found
+    fn set_trust(executor: E, id: i64, uid: i64, trust: Option<(&str, DateTime<Utc>)>) -> impl Future<Output = sqlx::Result<bool>> + Send;
+    /// Get the unrevoked device of the user trusted after `min_trust_time` by the token
+    fn get_trusted(executor: E, uid: i64, token_hash: &str, min_trust_time: DateTime<Utc>) -> impl Future<Output = sqlx::Result<Option<RefreshToken>>> + Send;
 }
 
 pub struct RefreshTokenDao;
@@ -31,12 +43,21 @@ impl <'e, E> CrudDao<'e, E> for RefreshTokenDao
 where E: PgExecutor<'e> {
     type Entity = RefreshToken;
 
-    async fn list(_executor: E) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn list(executor: E) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM refresh_tokens")
+            .fetch_all(executor)
+            .await
     }
 
-    async fn page(_executor: E, _page: i64, _size: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM refresh_tokens ORDER BY id DESC LIMIT $1 OFFSET $2",
+            size,
+            page * size
+        )
+        .fetch_all(executor)
+        .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Self::Entity>> {
@@ -51,7 +72,7 @@ where E: PgExecutor<'e> {
 
     async fn update_by_id(executor: E, value: &Self::Entity) -> sqlx::Result<()> {
         sqlx::query!(
-            "UPDATE refresh_tokens SET user_id = $1, token_id = $2, token_value = $3, expires_time = $4, create_time = $5, last_used_time = $6, device_info = $7, ip_address = $8, is_revoked = $9, user_agent = $10 WHERE id = $11",
+            "UPDATE refresh_tokens SET user_id = $1, token_id = $2, token_value = $3, expires_time = $4, create_time = $5, last_used_time = $6, device_info = $7, ip_address = $8, is_revoked = $9, user_agent = $10, device_name = $11, trust_token_hash = $12, trust_time = $13 WHERE id = $14",
             value.user_id,
             value.token_id,
             value.token_value,
@@ -62,6 +83,9 @@ where E: PgExecutor<'e> {
             value.ip_address,
             value.is_revoked,
             value.user_agent,
+            value.device_name,
+            value.trust_token_hash,
+            value.trust_time,
             value.id
         ).execute(executor).await?;
         Ok(())
@@ -69,8 +93,8 @@ where E: PgExecutor<'e> {
 
     async fn insert(executor: E, value: &Self::Entity) -> sqlx::Result<i64> {
         let r = sqlx::query!(
-            "INSERT INTO refresh_tokens(user_id, token_id, token_value, expires_time, create_time, last_used_time, device_info, ip_address, is_revoked, user_agent)
-             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+            "INSERT INTO refresh_tokens(user_id, token_id, token_value, expires_time, create_time, last_used_time, device_info, ip_address, is_revoked, user_agent, device_name, trust_token_hash, trust_time)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id",
             value.user_id,
             value.token_id,
             value.token_value,
@@ -81,6 +105,9 @@ where E: PgExecutor<'e> {
             value.ip_address,
             value.is_revoked,
             value.user_agent,
+            value.device_name,
+            value.trust_token_hash,
+            value.trust_time,
         ).fetch_one(executor).await?;
         Ok(r.id)
     }
@@ -119,4 +146,28 @@ where E: PgExecutor<'e> {
             .await?.rows_affected();
         Ok(rows)
     }
+    async fn rename(executor: E, id: i64, uid: i64, name: Option<&str>) -> sqlx::Result<bool> {
+        let rows = sqlx::query!(
+            "UPDATE refresh_tokens SET device_name = $3 WHERE id = $1 AND user_id = $2",
+            id, uid, name
+        ).execute(executor).await?.rows_affected();
+        Ok(rows > 0)
+    }
+    async fn set_trust(executor: E, id: i64, uid: i64, trust: Option<(&str, DateTime<Utc>)>) -> sqlx::Result<bool> {
+        let rows = sqlx::query!(
+            "UPDATE refresh_tokens SET trust_token_hash = $3, trust_time = $4 WHERE id = $1 AND user_id = $2",
+            id, uid, trust.map(|x| x.0), trust.map(|x| x.1)
+        ).execute(executor).await?.rows_affected();
+        Ok(rows > 0)
+    }
+    async fn get_trusted(executor: E, uid: i64, token_hash: &str, min_trust_time: DateTime<Utc>) -> sqlx::Result<Option<RefreshToken>> {
+        sqlx::query_as!(
+            RefreshToken,
+            "SELECT * FROM refresh_tokens
+            WHERE user_id = $1 AND trust_token_hash = $2 AND trust_time > $3 AND NOT is_revoked",
+            uid, token_hash, min_trust_time
+        )
+        .fetch_optional(executor)
+        .await
+    }
 }
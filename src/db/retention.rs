@@ -0,0 +1,120 @@
+//! The batched purges of the expired data, see [crate::service::retention].
+//!
+//! Each purge deletes at most `limit` rows older than `before`, skipping the users on legal hold, and returns the
+//! number of deleted rows, so the caller repeats it until fewer rows are deleted.
+
+use crate::db::user_shadow_ban;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, Result};
+
+pub struct RetentionDao;
+
+pub trait IRetentionDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn purge_play_history(executor: E, before: DateTime<Utc>, limit: i64) -> impl Future<Output = Result<u64>> + Send;
+    /// The raw plays, the counted ones are added to `song_archived_play_counts` so the play counts are kept
+    fn purge_song_plays(executor: E, before: DateTime<Utc>, limit: i64) -> impl Future<Output = Result<u64>> + Send;
+    fn purge_notifications(executor: E, before: DateTime<Utc>, limit: i64) -> impl Future<Output = Result<u64>> + Send;
+    /// The tokens expired before `before`
+    fn purge_refresh_tokens(executor: E, before: DateTime<Utc>, limit: i64) -> impl Future<Output = Result<u64>> + Send;
+    fn purge_audit_logs(executor: E, before: DateTime<Utc>, limit: i64) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl<'e, E> IRetentionDao<'e, E> for RetentionDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn purge_play_history(executor: E, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM user_play_history WHERE (user_id, song_id) IN (
+                SELECT h.user_id, h.song_id FROM user_play_history h
+                WHERE h.create_time < $1
+                    AND NOT EXISTS (SELECT 1 FROM user_legal_holds l WHERE l.user_id = h.user_id)
+                LIMIT $2
+            )",
+            before,
+            limit
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_song_plays(executor: E, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        sqlx::query_scalar!(
+            r#"WITH deleted AS (
+                DELETE FROM song_plays WHERE id IN (
+                    SELECT p.id FROM song_plays p
+                    WHERE p.create_time < $1
+                        AND NOT EXISTS (SELECT 1 FROM user_legal_holds l WHERE l.user_id = p.user_id)
+                    LIMIT $2
+                )
+                RETURNING song_id, user_id
+            ), archived AS (
+                INSERT INTO song_archived_play_counts (song_id, play_count)
+                SELECT song_id, COUNT(*) FROM deleted d
+                WHERE NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = d.user_id AND $3 = ANY(b.features))
+                GROUP BY song_id
+                ON CONFLICT (song_id) DO UPDATE
+                    SET play_count = song_archived_play_counts.play_count + EXCLUDED.play_count
+            )
+            SELECT COUNT(*) AS "count!" FROM deleted"#,
+            before,
+            limit,
+            user_shadow_ban::FEATURE_PLAYS
+        )
+        .fetch_one(executor)
+        .await
+        .map(|x| x as u64)
+    }
+
+    async fn purge_notifications(executor: E, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM notifications WHERE id IN (
+                SELECT n.id FROM notifications n
+                WHERE n.create_time < $1
+                    AND NOT EXISTS (SELECT 1 FROM user_legal_holds l WHERE l.user_id = n.user_id)
+                LIMIT $2
+            )",
+            before,
+            limit
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_refresh_tokens(executor: E, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM refresh_tokens WHERE id IN (
+                SELECT t.id FROM refresh_tokens t
+                WHERE t.expires_time < $1
+                    AND NOT EXISTS (SELECT 1 FROM user_legal_holds l WHERE l.user_id = t.user_id)
+                LIMIT $2
+            )",
+            before,
+            limit
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_audit_logs(executor: E, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM user_account_audit_logs WHERE id IN (
+                SELECT a.id FROM user_account_audit_logs a
+                WHERE a.create_time < $1
+                    AND NOT EXISTS (SELECT 1 FROM user_legal_holds l WHERE l.user_id = a.user_id)
+                LIMIT $2
+            )",
+            before,
+            limit
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A piece of the review guidance, see [crate::service::review_guideline]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewGuidelineSnippet {
+    pub id: i64,
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    pub version: i32,
+    pub archived: bool,
+    pub update_by: i64,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+/// A snapshot of a snippet, saved on every edit including the first one
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewGuidelineSnippetVersion {
+    pub id: i64,
+    pub snippet_id: i64,
+    pub version: i32,
+    pub title: String,
+    pub content: String,
+    pub archived: bool,
+    pub editor_uid: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct ReviewGuidelineSnippetDao;
+
+pub trait IReviewGuidelineSnippetDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_slug(executor: E, slug: &str) -> impl Future<Output = Result<Option<ReviewGuidelineSnippet>>> + Send;
+    /// Ordered by the slug
+    fn list(executor: E, include_archived: bool) -> impl Future<Output = Result<Vec<ReviewGuidelineSnippet>>> + Send;
+    /// Returns `None` if the slug is already used
+    fn insert(executor: E, value: &ReviewGuidelineSnippet) -> impl Future<Output = Result<Option<i64>>> + Send;
+    /// Save the snippet with `value.version`, returns false if the saved version is not `value.version - 1`
+    fn update_checked(executor: E, value: &ReviewGuidelineSnippet) -> impl Future<Output = Result<bool>> + Send;
+    fn insert_version(executor: E, value: &ReviewGuidelineSnippetVersion) -> impl Future<Output = Result<()>> + Send;
+    /// Newest first
+    fn list_versions(executor: E, snippet_id: i64) -> impl Future<Output = Result<Vec<ReviewGuidelineSnippetVersion>>> + Send;
+}
+
+impl<'e, E> IReviewGuidelineSnippetDao<'e, E> for ReviewGuidelineSnippetDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_slug(executor: E, slug: &str) -> Result<Option<ReviewGuidelineSnippet>> {
+        sqlx::query_as!(
+            ReviewGuidelineSnippet,
+            "SELECT * FROM review_guideline_snippets WHERE slug = $1",
+            slug
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn list(executor: E, include_archived: bool) -> Result<Vec<ReviewGuidelineSnippet>> {
+        sqlx::query_as!(
+            ReviewGuidelineSnippet,
+            "SELECT * FROM review_guideline_snippets WHERE $1 OR NOT archived ORDER BY slug",
+            include_archived
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn insert(executor: E, value: &ReviewGuidelineSnippet) -> Result<Option<i64>> {
+        sqlx::query_scalar!(
+            "INSERT INTO review_guideline_snippets (slug, title, content, version, archived, update_by, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (slug) DO NOTHING
+            RETURNING id",
+            value.slug,
+            value.title,
+            value.content,
+            value.version,
+            value.archived,
+            value.update_by,
+            value.create_time,
+            value.update_time
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn update_checked(executor: E, value: &ReviewGuidelineSnippet) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE review_guideline_snippets SET
+                title = $1,
+                content = $2,
+                version = $3,
+                archived = $4,
+                update_by = $5,
+                update_time = $6
+            WHERE id = $7 AND version = $3 - 1",
+            value.title,
+            value.content,
+            value.version,
+            value.archived,
+            value.update_by,
+            value.update_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_version(executor: E, value: &ReviewGuidelineSnippetVersion) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO review_guideline_snippet_versions (snippet_id, version, title, content, archived, editor_uid, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            value.snippet_id,
+            value.version,
+            value.title,
+            value.content,
+            value.archived,
+            value.editor_uid,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_versions(executor: E, snippet_id: i64) -> Result<Vec<ReviewGuidelineSnippetVersion>> {
+        sqlx::query_as!(
+            ReviewGuidelineSnippetVersion,
+            "SELECT * FROM review_guideline_snippet_versions WHERE snippet_id = $1 ORDER BY version DESC",
+            snippet_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
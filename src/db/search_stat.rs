@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor};
+
+/// The counts of a query summed over the days
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct SearchQueryTotal {
+    pub query: String,
+    pub sort_by: String,
+    pub searches: i64,
+    pub clicked_searches: i64,
+    pub clicks: i64,
+    pub click_position_sum: i64,
+}
+
+/// The clicks at a position summed over the days
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct SearchPositionTotal {
+    pub sort_by: String,
+    pub position: i32,
+    pub clicks: i64,
+}
+
+pub struct SearchStatDao;
+
+pub trait ISearchStatDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn add_search(executor: E, date: NaiveDate, query: &str, sort_by: &str) -> impl Future<Output = sqlx::Result<()>> + Send;
+    /// `first_click`: whether it's the first clicked hit of the search
+    fn add_query_click(executor: E, date: NaiveDate, query: &str, sort_by: &str, position: i32, first_click: bool) -> impl Future<Output = sqlx::Result<()>> + Send;
+    fn add_position_click(executor: E, date: NaiveDate, sort_by: &str, position: i32) -> impl Future<Output = sqlx::Result<()>> + Send;
+    /// The most searched queries since the date
+    fn list_query_totals(executor: E, since: NaiveDate, limit: i64) -> impl Future<Output = sqlx::Result<Vec<SearchQueryTotal>>> + Send;
+    fn list_position_totals(executor: E, since: NaiveDate) -> impl Future<Output = sqlx::Result<Vec<SearchPositionTotal>>> + Send;
+}
+
+impl<'e, E> ISearchStatDao<'e, E> for SearchStatDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn add_search(executor: E, date: NaiveDate, query: &str, sort_by: &str) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO search_query_stats (date, query, sort_by, searches)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (date, query, sort_by) DO UPDATE SET searches = search_query_stats.searches + 1",
+            date,
+            query,
+            sort_by,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn add_query_click(executor: E, date: NaiveDate, query: &str, sort_by: &str, position: i32, first_click: bool) -> sqlx::Result<()> {
+        let clicked_searches = if first_click { 1i64 } else { 0 };
+        sqlx::query!(
+            "INSERT INTO search_query_stats (date, query, sort_by, clicked_searches, clicks, click_position_sum)
+            VALUES ($1, $2, $3, $4, 1, $5)
+            ON CONFLICT (date, query, sort_by) DO UPDATE SET
+                clicked_searches = search_query_stats.clicked_searches + excluded.clicked_searches,
+                clicks = search_query_stats.clicks + 1,
+                click_position_sum = search_query_stats.click_position_sum + excluded.click_position_sum",
+            date,
+            query,
+            sort_by,
+            clicked_searches,
+            position as i64,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn add_position_click(executor: E, date: NaiveDate, sort_by: &str, position: i32) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO search_click_position_stats (date, sort_by, position, clicks)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (date, sort_by, position) DO UPDATE SET clicks = search_click_position_stats.clicks + 1",
+            date,
+            sort_by,
+            position,
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn list_query_totals(executor: E, since: NaiveDate, limit: i64) -> sqlx::Result<Vec<SearchQueryTotal>> {
+        sqlx::query_as!(
+            SearchQueryTotal,
+            r#"SELECT query, sort_by,
+                SUM(searches)::BIGINT AS "searches!",
+                SUM(clicked_searches)::BIGINT AS "clicked_searches!",
+                SUM(clicks)::BIGINT AS "clicks!",
+                SUM(click_position_sum)::BIGINT AS "click_position_sum!"
+            FROM search_query_stats
+            WHERE date >= $1
+            GROUP BY query, sort_by
+            ORDER BY 3 DESC, query
+            LIMIT $2"#,
+            since,
+            limit,
+        ).fetch_all(executor).await
+    }
+
+    async fn list_position_totals(executor: E, since: NaiveDate) -> sqlx::Result<Vec<SearchPositionTotal>> {
+        sqlx::query_as!(
+            SearchPositionTotal,
+            r#"SELECT sort_by, position, SUM(clicks)::BIGINT AS "clicks!"
+            FROM search_click_position_stats
+            WHERE date >= $1
+            GROUP BY sort_by, position
+            ORDER BY sort_by, position"#,
+            since,
+        ).fetch_all(executor).await
+    }
+}
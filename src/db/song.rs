@@ -1,6 +1,9 @@
+use crate::db::error::{DbError, DbResult};
+use crate::db::user_shadow_ban;
 use crate::db::CrudDao;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgExecutor, PgTransaction};
 use std::collections::HashMap;
@@ -22,9 +25,13 @@ pub struct Song {
     pub uploader_uid: i64,
     pub creation_type: i32,
     /// Play count is deprecated because it should be got from play history
+    ///
+    /// Not written by the updates, only repaired by [ISongDao::recount_stats] so a stale read can't reset it.
     /// @deprecated since 20250925
     pub play_count: i64,
     /// Like count is deprecated because it should be got from like history
+    ///
+    /// Not written by the updates, see `play_count`.
     /// @deprecated since 20250925
     pub like_count: i64,
     pub is_private: bool,
@@ -35,6 +42,25 @@ pub struct Song {
     pub explicit: Option<bool>,
     // Since 251105
     pub gain: Option<f32>,
+    // Since 260505, the EBU R128 integrated loudness in LUFS
+    #[serde(default)]
+    pub loudness_lufs: Option<f32>,
+    // Since 260505, the true peak in dBTP
+    #[serde(default)]
+    pub true_peak: Option<f32>,
+    /// Row version for optimistic locking
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// The denormalized counts of a song before and after recounting
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongStatsDrift {
+    pub song_id: i64,
+    pub old_play_count: i64,
+    pub old_like_count: i64,
+    pub play_count: i64,
+    pub like_count: i64,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -48,13 +74,16 @@ pub struct SongOriginInfo {
     pub origin_url: Option<String>,
 }
 
-#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
 pub struct SongProductionCrew {
     pub id: i64,
     pub song_id: i64,
     pub role: String,
     pub uid: Option<i64>,
     pub person_name: Option<String>,
+    /// The credited user hasn't confirmed yet
+    #[serde(default)]
+    pub pending: bool,
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -94,13 +123,25 @@ where
     fn list_origin_info_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output=sqlx::Result<Vec<SongOriginInfo>>>;
     fn list_production_crew_by_song_id(executor: E, song_id: i64) -> impl Future<Output=sqlx::Result<Vec<SongProductionCrew>>>;
     fn list_production_crew_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output=sqlx::Result<Vec<SongProductionCrew>>>;
+    fn list_pending_production_crew_by_uid(executor: E, uid: i64) -> impl Future<Output=sqlx::Result<Vec<SongProductionCrew>>>;
+    /// Confirm the pending credits of the user on the song, returns the number of confirmed credits
+    fn confirm_production_crew(executor: E, song_id: i64, uid: i64) -> impl Future<Output=sqlx::Result<u64>>;
+    /// Unlink the user from the credits on the song and keep the names, returns the number of unlinked credits
+    fn unlink_production_crew(executor: E, song_id: i64, uid: i64) -> impl Future<Output=sqlx::Result<u64>>;
     fn list_external_link_by_song_id(executor: E, song_id: i64) -> impl Future<Output=sqlx::Result<Vec<SongExternalLink>>>;
     fn list_external_link_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output=sqlx::Result<Vec<SongExternalLink>>>;
     fn list_by_ids(executor: E, ids: &[i64]) -> impl Future<Output=sqlx::Result<Vec<Self::Entity>>>;
+    /// Keyset pagination ordered by id, for scanning all songs with bounded memory
+    fn list_ids_after(executor: E, after_id: i64, limit: i64) -> impl Future<Output=sqlx::Result<Vec<i64>>>;
     fn list_by_create_time_after(executor: E, create_time: DateTime<Utc>, limit: i64) -> impl Future<Output=sqlx::Result<Vec<Self::Entity>>>;
     fn list_by_create_time_before(executor: E, create_time: DateTime<Utc>, limit: i64) -> impl Future<Output=sqlx::Result<Vec<Self::Entity>>>;
     fn list_random(executor: E, limit: i64) -> impl Future<Output=sqlx::Result<Vec<i64>>>;
+    /// Case-insensitive substring match on the title, used when the search engine is unavailable.
+    ///
+    /// `sort` is one of `release_time_desc`, `release_time_asc`, `play_count_asc`, otherwise by play count descending.
+    fn search_by_title(executor: E, keyword: &str, sort: &str, limit: i64, offset: i64) -> impl Future<Output=sqlx::Result<Vec<i64>>>;
     fn page_by_user(executor: E, user_id: i64, page: i64, size: i64) -> impl Future<Output=sqlx::Result<Vec<Self::Entity>>>;
+    fn list_by_user(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<Vec<Self::Entity>>>;
     fn count_by_user(executor: E, user_id: i64) -> impl Future<Output=sqlx::Result<i64>>;
     fn count_likes(executor: E, song_id: i64) -> impl Future<Output=sqlx::Result<i64>>;
     fn count_likes_batch(executor: E, song_ids: &[i64]) -> impl Future<Output=sqlx::Result<HashMap<i64, i64>>>;
@@ -115,6 +156,19 @@ where
     fn cursor_plays(executor: E, user_id: i64, max_create_time: DateTime<Utc>, size: usize) -> impl Future<Output=sqlx::Result<Vec<SongPlay>>>;
     fn cursor_plays_distinct_latest(executor: E, user_id: i64, max_create_time: DateTime<Utc>, size: usize) -> impl Future<Output=sqlx::Result<Vec<SongPlay>>>;
     fn delete_play(executor: E, id: i64, user_id: i64) -> impl Future<Output=sqlx::Result<()>>;
+    /// Recompute `play_count` and `like_count` of the song, or all songs if `song_id` is `None`, from the plays
+    /// and likes. Returns the songs whose counts drifted, which are repaired without bumping the row version.
+    fn recount_stats(executor: E, song_id: Option<i64>) -> impl Future<Output=sqlx::Result<Vec<SongStatsDrift>>>;
+    /// Add the deltas of `(song_id, delta)` to `play_count` without bumping the row version
+    fn add_play_counts(executor: E, deltas: &[(i64, i64)]) -> impl Future<Output=sqlx::Result<()>>;
+    /// Record the batch of the play counts as added, returns false if it was already recorded
+    fn insert_play_count_batch(executor: E, batch_id: &str) -> impl Future<Output=sqlx::Result<bool>>;
+    fn delete_play_count_batches_before(executor: E, before: DateTime<Utc>) -> impl Future<Output=sqlx::Result<u64>>;
+    /// Record the batch of the play events as inserted, returns false if it was already recorded
+    fn insert_play_event_batch(executor: E, batch_id: &str) -> impl Future<Output=sqlx::Result<bool>>;
+    fn delete_play_event_batches_before(executor: E, before: DateTime<Utc>) -> impl Future<Output=sqlx::Result<u64>>;
+    /// Update only if the row version still equals `value.version`, returns the new version
+    fn update_by_id_checked(executor: E, value: &Song) -> impl Future<Output=DbResult<i64>>;
 }
 
 impl<'e, E> CrudDao<'e, E> for SongDao
@@ -129,8 +183,10 @@ where
             .await
     }
 
-    async fn page(_executor: E, _page: i64, _size: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Song, "SELECT * FROM songs ORDER BY id DESC LIMIT $1 OFFSET $2", size, page * size)
+            .fetch_all(executor)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Self::Entity>> {
@@ -153,14 +209,15 @@ where
                 duration_seconds = $9,
                 uploader_uid = $10,
                 creation_type = $11,
-                play_count = $12,
-                like_count = $13,
-                is_private = $14,
-                release_time = $15,
-                create_time = $16,
-                update_time = $17,
-                explicit = $18,
-                gain = $19
+                is_private = $12,
+                release_time = $13,
+                create_time = $14,
+                update_time = $15,
+                explicit = $16,
+                gain = $17,
+                loudness_lufs = $18,
+                true_peak = $19,
+                version = version + 1
             WHERE id = $20",
             value.display_id,
             value.title,
@@ -173,14 +230,14 @@ where
             value.duration_seconds,
             value.uploader_uid,
             value.creation_type,
-            value.play_count,
-            value.like_count,
             value.is_private,
             value.release_time,
             value.create_time,
             value.update_time,
             value.explicit,
             value.gain,
+            value.loudness_lufs,
+            value.true_peak,
             value.id
         )
             .execute(executor)
@@ -209,8 +266,10 @@ where
                 create_time,
                 update_time,
                 explicit,
-                gain
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19) RETURNING id",
+                gain,
+                loudness_lufs,
+                true_peak
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21) RETURNING id",
             value.display_id,
             value.title,
             value.subtitle,
@@ -229,7 +288,9 @@ where
             value.create_time,
             value.update_time,
             value.explicit,
-            value.gain
+            value.gain,
+            value.loudness_lufs,
+            value.true_peak
         ).fetch_one(executor).await.map(|x| x.id)
     }
 
@@ -245,6 +306,135 @@ impl<'e, E> ISongDao<'e, E> for SongDao
 where
     E: PgExecutor<'e>,
 {
+    async fn update_by_id_checked(executor: E, value: &Song) -> DbResult<i64> {
+        sqlx::query_scalar!(
+            "UPDATE songs SET
+                display_id = $1,
+                title = $2,
+                subtitle = $3,
+                description = $4,
+                artist = $5,
+                file_url = $6,
+                cover_art_url = $7,
+                lyrics = $8,
+                duration_seconds = $9,
+                uploader_uid = $10,
+                creation_type = $11,
+                is_private = $12,
+                release_time = $13,
+                create_time = $14,
+                update_time = $15,
+                explicit = $16,
+                gain = $17,
+                loudness_lufs = $18,
+                true_peak = $19,
+                version = version + 1
+            WHERE id = $20 AND version = $21
+            RETURNING version",
+            value.display_id,
+            value.title,
+            value.subtitle,
+            value.description,
+            value.artist,
+            value.file_url,
+            value.cover_art_url,
+            value.lyrics,
+            value.duration_seconds,
+            value.uploader_uid,
+            value.creation_type,
+            value.is_private,
+            value.release_time,
+            value.create_time,
+            value.update_time,
+            value.explicit,
+            value.gain,
+            value.loudness_lufs,
+            value.true_peak,
+            value.id,
+            value.version
+        ).fetch_optional(executor).await?
+            .ok_or(DbError::VersionConflict)
+    }
+
+    async fn add_play_counts(executor: E, deltas: &[(i64, i64)]) -> sqlx::Result<()> {
+        let (ids, deltas): (Vec<i64>, Vec<i64>) = deltas.iter().copied().unzip();
+        sqlx::query!(
+            "UPDATE songs s SET play_count = s.play_count + d.delta
+            FROM UNNEST($1::bigint[], $2::bigint[]) AS d(id, delta)
+            WHERE s.id = d.id",
+            &ids[..], &deltas[..]
+        ).execute(executor).await?;
+        Ok(())
+    }
+
+    async fn insert_play_count_batch(executor: E, batch_id: &str) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO song_play_count_batches (batch_id) VALUES ($1) ON CONFLICT DO NOTHING",
+            batch_id
+        ).execute(executor).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_play_count_batches_before(executor: E, before: DateTime<Utc>) -> sqlx::Result<u64> {
+        let result = sqlx::query!("DELETE FROM song_play_count_batches WHERE create_time < $1", before)
+            .execute(executor).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn insert_play_event_batch(executor: E, batch_id: &str) -> sqlx::Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO song_play_event_batches (batch_id) VALUES ($1) ON CONFLICT DO NOTHING",
+            batch_id
+        ).execute(executor).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_play_event_batches_before(executor: E, before: DateTime<Utc>) -> sqlx::Result<u64> {
+        let result = sqlx::query!("DELETE FROM song_play_event_batches WHERE create_time < $1", before)
+            .execute(executor).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn recount_stats(executor: E, song_id: Option<i64>) -> sqlx::Result<Vec<SongStatsDrift>> {
+        sqlx::query_as!(
+            SongStatsDrift,
+            r#"WITH plays AS (
+                SELECT song_id, SUM(count) AS count FROM (
+                    SELECT song_id, COUNT(*) AS count FROM song_plays sp
+                    WHERE ($1::BIGINT IS NULL OR song_id = $1)
+                        AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sp.user_id AND $2 = ANY(b.features))
+                    GROUP BY song_id
+                    UNION ALL
+                    SELECT song_id, play_count FROM song_archived_play_counts
+                    WHERE $1::BIGINT IS NULL OR song_id = $1
+                ) x
+                GROUP BY song_id
+            ), likes AS (
+                SELECT song_id, COUNT(*) AS count FROM song_likes sl
+                WHERE ($1::BIGINT IS NULL OR song_id = $1)
+                    AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sl.user_id AND $3 = ANY(b.features))
+                GROUP BY song_id
+            ), counts AS (
+                SELECT s.id, s.play_count AS old_play_count, s.like_count AS old_like_count,
+                    COALESCE(p.count, 0)::BIGINT AS play_count, COALESCE(l.count, 0) AS like_count
+                FROM songs s
+                LEFT JOIN plays p ON p.song_id = s.id
+                LEFT JOIN likes l ON l.song_id = s.id
+                WHERE $1::BIGINT IS NULL OR s.id = $1
+            )
+            UPDATE songs s SET play_count = c.play_count, like_count = c.like_count
+            FROM counts c
+            WHERE s.id = c.id AND (s.play_count <> c.play_count OR s.like_count <> c.like_count)
+            RETURNING s.id AS "song_id!", c.old_play_count AS "old_play_count!", c.old_like_count AS "old_like_count!",
+                s.play_count AS "play_count!", s.like_count AS "like_count!""#,
+            song_id,
+            user_shadow_ban::FEATURE_PLAYS,
+            user_shadow_ban::FEATURE_LIKES
+        )
+            .fetch_all(executor)
+            .await
+    }
+
     async fn get_by_display_id(executor: E, display_id: &str) -> sqlx::Result<Option<Song>> {
         sqlx::query_as!(
             Song,
@@ -285,6 +475,29 @@ where
             .fetch_all(executor).await
     }
 
+    async fn list_pending_production_crew_by_uid(executor: E, uid: i64) -> sqlx::Result<Vec<SongProductionCrew>> {
+        sqlx::query_as!(SongProductionCrew, "SELECT * FROM song_production_crew WHERE uid = $1 AND pending ORDER BY id DESC", uid)
+            .fetch_all(executor).await
+    }
+
+    async fn confirm_production_crew(executor: E, song_id: i64, uid: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE song_production_crew SET pending = FALSE WHERE song_id = $1 AND uid = $2 AND pending",
+            song_id,
+            uid
+        ).execute(executor).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn unlink_production_crew(executor: E, song_id: i64, uid: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE song_production_crew SET uid = NULL, pending = FALSE WHERE song_id = $1 AND uid = $2",
+            song_id,
+            uid
+        ).execute(executor).await?;
+        Ok(result.rows_affected())
+    }
+
     async fn list_external_link_by_song_id(executor: E, song_id: i64) -> sqlx::Result<Vec<SongExternalLink>> {
         sqlx::query_as!(SongExternalLink, "SELECT * FROM song_external_links WHERE song_id = $1", song_id)
             .fetch_all(executor).await
@@ -304,13 +517,23 @@ where
         ).fetch_all(executor).await
     }
 
+    async fn list_ids_after(executor: E, after_id: i64, limit: i64) -> sqlx::Result<Vec<i64>> {
+        sqlx::query_scalar!("SELECT id FROM songs WHERE id > $1 ORDER BY id LIMIT $2", after_id, limit)
+            .fetch_all(executor)
+            .await
+    }
+
     async fn list_by_create_time_after(executor: E, create_time: DateTime<Utc>, limit: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        sqlx::query_as!(Song, "SELECT * FROM songs WHERE create_time > $1 ORDER BY create_time ASC LIMIT $2", create_time, limit)
+        sqlx::query_as!(Song, "SELECT * FROM songs
+            WHERE create_time > $1 AND uploader_uid NOT IN (SELECT id FROM users WHERE is_banned)
+            ORDER BY create_time ASC LIMIT $2", create_time, limit)
             .fetch_all(executor).await
     }
 
     async fn list_by_create_time_before(executor: E, create_time: DateTime<Utc>, limit: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        sqlx::query_as!(Song, "SELECT * FROM songs WHERE create_time < $1 ORDER BY create_time DESC LIMIT $2", create_time, limit)
+        sqlx::query_as!(Song, "SELECT * FROM songs
+            WHERE create_time < $1 AND uploader_uid NOT IN (SELECT id FROM users WHERE is_banned)
+            ORDER BY create_time DESC LIMIT $2", create_time, limit)
             .fetch_all(executor).await
     }
 
@@ -321,6 +544,25 @@ where
         Ok(rows.into_iter().map(|x| x.id).collect_vec())
     }
 
+    async fn search_by_title(executor: E, keyword: &str, sort: &str, limit: i64, offset: i64) -> sqlx::Result<Vec<i64>> {
+        let pattern = format!("%{}%", keyword.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let rows = sqlx::query!(
+            "SELECT id FROM songs
+            WHERE title ILIKE $1 AND uploader_uid NOT IN (SELECT id FROM users WHERE is_banned)
+            ORDER BY
+                CASE WHEN $2 = 'release_time_desc' THEN release_time END DESC,
+                CASE WHEN $2 = 'release_time_asc' THEN release_time END ASC,
+                CASE WHEN $2 = 'play_count_asc' THEN play_count END ASC,
+                play_count DESC, id DESC
+            LIMIT $3 OFFSET $4",
+            pattern,
+            sort,
+            limit,
+            offset
+        ).fetch_all(executor).await?;
+        Ok(rows.into_iter().map(|x| x.id).collect_vec())
+    }
+
     async fn page_by_user(executor: E, user_id: i64, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
         sqlx::query_as!(
             Song,
@@ -331,6 +573,11 @@ where
         ).fetch_all(executor).await
     }
 
+    async fn list_by_user(executor: E, user_id: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Song, "SELECT * FROM songs WHERE uploader_uid = $1", user_id)
+            .fetch_all(executor).await
+    }
+
     async fn count_by_user(executor: E, user_id: i64) -> sqlx::Result<i64> {
         sqlx::query!(
             "SELECT COUNT(*) FROM songs WHERE uploader_uid = $1",
@@ -339,14 +586,25 @@ where
     }
 
     async fn count_likes(executor: E, song_id: i64) -> sqlx::Result<i64> {
-        sqlx::query!("SELECT COUNT(1) FROM song_likes WHERE song_id = $1", song_id)
+        sqlx::query!(
+            "SELECT COUNT(1) FROM song_likes sl WHERE song_id = $1
+                AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sl.user_id AND $2 = ANY(b.features))",
+            song_id,
+            user_shadow_ban::FEATURE_LIKES
+        )
             .fetch_one(executor)
             .await.map(|x| x.count).map(|x| x.unwrap_or(0))
     }
 
     async fn count_likes_batch(executor: E, song_ids: &[i64]) -> sqlx::Result<HashMap<i64, i64>> {
         if song_ids.is_empty() { return Ok(HashMap::new()); }
-        let result = sqlx::query!("SELECT song_id, COUNT(*) FROM song_likes WHERE song_id = ANY($1) GROUP BY song_id", song_ids)
+        let result = sqlx::query!(
+            "SELECT song_id, COUNT(*) FROM song_likes sl WHERE song_id = ANY($1)
+                AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sl.user_id AND $2 = ANY(b.features))
+            GROUP BY song_id",
+            song_ids,
+            user_shadow_ban::FEATURE_LIKES
+        )
             .fetch_all(executor)
             .await?
             .into_iter()
@@ -364,14 +622,31 @@ where
     }
 
     async fn count_plays(executor: E, song_id: i64) -> sqlx::Result<i64> {
-        sqlx::query!("SELECT COUNT(1) FROM song_plays WHERE song_id = $1", song_id)
+        sqlx::query!(
+            "SELECT COUNT(1) + COALESCE((SELECT play_count FROM song_archived_play_counts WHERE song_id = $1), 0) AS count
+            FROM song_plays sp WHERE song_id = $1
+                AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sp.user_id AND $2 = ANY(b.features))",
+            song_id,
+            user_shadow_ban::FEATURE_PLAYS
+        )
             .fetch_one(executor)
             .await.map(|x| x.count).map(|x| x.unwrap_or(0))
     }
 
     async fn count_plays_batch(executor: E, song_ids: &[i64]) -> sqlx::Result<HashMap<i64, i64>> {
         if song_ids.is_empty() { return Ok(HashMap::new()); }
-        let result = sqlx::query!("SELECT song_id, COUNT(*) FROM song_plays WHERE song_id = ANY($1) GROUP BY song_id", song_ids)
+        let result = sqlx::query!(
+            r#"SELECT song_id AS "song_id!", SUM(count)::BIGINT AS count FROM (
+                SELECT song_id, COUNT(*) AS count FROM song_plays sp WHERE song_id = ANY($1)
+                    AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sp.user_id AND $2 = ANY(b.features))
+                GROUP BY song_id
+                UNION ALL
+                SELECT song_id, play_count FROM song_archived_play_counts WHERE song_id = ANY($1)
+            ) x
+            GROUP BY song_id"#,
+            song_ids,
+            user_shadow_ban::FEATURE_PLAYS
+        )
             .fetch_all(executor)
             .await?
             .into_iter()
@@ -476,12 +751,14 @@ impl<'e> SongDao {
                     song_id,
                     role,
                     uid,
-                    person_name
-                ) VALUES ($1, $2, $3, $4)",
+                    person_name,
+                    pending
+                ) VALUES ($1, $2, $3, $4, $5)",
                 song_id,
                 x.role,
                 x.uid,
-                x.person_name
+                x.person_name,
+                x.pending
             ).execute(&mut **executor).await?;
         }
         Ok(())
@@ -531,6 +808,33 @@ impl<'e> SongDao {
         Ok(())
     }
 
+    /// Delete the song with its contents and the references to it, returns the file URLs of the deleted renditions.
+    ///
+    /// The plays, the shares, the edit logs and the reports are kept for the stats and the moderation history,
+    /// the comments are soft deleted.
+    pub(crate) async fn delete_with_contents(executor: &mut PgTransaction<'e>, song_id: i64) -> sqlx::Result<Vec<String>> {
+        sqlx::query!("DELETE FROM song_tag_refs WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM song_production_crew WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM song_origin_info WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM song_external_links WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM song_likes WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM playlist_songs WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM featured_songs WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM hidden_songs WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM song_content_fingerprints WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!("DELETE FROM user_play_history WHERE song_id = $1", song_id).execute(&mut **executor).await?;
+        sqlx::query!(
+            "UPDATE song_comments SET delete_time = now() WHERE song_id = $1 AND delete_time IS NULL",
+            song_id
+        ).execute(&mut **executor).await?;
+        let rendition_urls = sqlx::query_scalar!(
+            "DELETE FROM song_audio_renditions WHERE song_id = $1 RETURNING file_url",
+            song_id
+        ).fetch_all(&mut **executor).await?;
+        sqlx::query!("DELETE FROM songs WHERE id = $1", song_id).execute(&mut **executor).await?;
+        Ok(rendition_urls)
+    }
+
     pub async fn update_song_external_links(executor: &mut PgTransaction<'e>, song_id: i64, values: &[SongExternalLink]) -> sqlx::Result<()> {
         sqlx::query!("DELETE FROM song_external_links WHERE song_id = $1", song_id).execute(&mut **executor).await?;
         sqlx::query!("INSERT INTO song_external_links (song_id, platform, url) SELECT * FROM UNNEST($1::bigint[], $2::text[], $3::text[])",
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const CODEC_AAC: &str = "aac";
+pub const CODEC_OPUS: &str = "opus";
+
+/// A lower-bitrate rendition of the audio of a song, see [crate::service::transcode]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongAudioRendition {
+    pub id: i64,
+    pub song_id: i64,
+    /// [CODEC_AAC] or [CODEC_OPUS]
+    pub codec: String,
+    pub bitrate_kbps: i32,
+    pub file_url: String,
+    pub size: i64,
+    /// The `file_url` of the song it's transcoded from
+    pub source_url: String,
+    pub create_time: DateTime<Utc>,
+    /// Measured on the rendition itself in dBTP, `None` if it's not measured
+    pub true_peak: Option<f32>,
+}
+
+pub struct SongAudioRenditionDao;
+
+pub trait ISongAudioRenditionDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Ordered by the codec and bitrate
+    fn list_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output = Result<Vec<SongAudioRendition>>> + Send;
+    /// Insert or replace the rendition of the same codec and bitrate, returns the ID
+    fn upsert(executor: E, value: &SongAudioRendition) -> impl Future<Output = Result<i64>> + Send;
+    /// Delete the renditions of the song but the kept ones, returns the file URLs of the deleted
+    fn delete_except(executor: E, song_id: i64, kept_ids: &[i64]) -> impl Future<Output = Result<Vec<String>>> + Send;
+}
+
+impl<'e, E> ISongAudioRenditionDao<'e, E> for SongAudioRenditionDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_song_ids(executor: E, song_ids: &[i64]) -> Result<Vec<SongAudioRendition>> {
+        sqlx::query_as!(
+            SongAudioRendition,
+            "SELECT * FROM song_audio_renditions WHERE song_id = ANY($1) ORDER BY song_id, codec, bitrate_kbps",
+            song_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn upsert(executor: E, value: &SongAudioRendition) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO song_audio_renditions (song_id, codec, bitrate_kbps, file_url, size, source_url, create_time, true_peak)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (song_id, codec, bitrate_kbps) DO UPDATE SET
+                file_url = excluded.file_url,
+                size = excluded.size,
+                source_url = excluded.source_url,
+                create_time = excluded.create_time,
+                true_peak = excluded.true_peak
+            RETURNING id",
+            value.song_id,
+            value.codec,
+            value.bitrate_kbps,
+            value.file_url,
+            value.size,
+            value.source_url,
+            value.create_time,
+            value.true_peak
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_except(executor: E, song_id: i64, kept_ids: &[i64]) -> Result<Vec<String>> {
+        sqlx::query_scalar!(
+            "DELETE FROM song_audio_renditions WHERE song_id = $1 AND id <> ALL($2) RETURNING file_url",
+            song_id,
+            kept_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,201 @@
+use crate::db::user_shadow_ban;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+use std::collections::HashMap;
+
+/// A comment of a song, the replies are flattened into the thread of the root comment
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongComment {
+    pub id: i64,
+    pub song_id: i64,
+    pub user_id: i64,
+    /// The root comment of the thread, `None` for the root comments
+    pub parent_id: Option<i64>,
+    /// The author of the replied comment, `None` if replying to the root comment
+    pub reply_to_uid: Option<i64>,
+    pub content: String,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+    /// `Some` if it's deleted, the content is cleared and it's shown as a placeholder until purged
+    pub delete_time: Option<DateTime<Utc>>,
+}
+
+pub struct SongCommentDao;
+
+pub trait ISongCommentDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_id(executor: E, id: i64) -> impl Future<Output = Result<Option<SongComment>>> + Send;
+    fn insert(executor: E, value: &SongComment) -> impl Future<Output = Result<i64>> + Send;
+    /// Clear the content and leave the tombstone, the replies are kept. Returns false if it's deleted already.
+    fn soft_delete_by_id(executor: E, id: i64, time: DateTime<Utc>) -> impl Future<Output = Result<bool>> + Send;
+    /// Delete the tombstones deleted before the time, a root comment is purged after all its replies are.
+    /// Returns the number of the purged.
+    fn purge_deleted(executor: E, before: DateTime<Utc>) -> impl Future<Output = Result<u64>> + Send;
+    /// The root comments of the song seen by the viewer, latest first. The deleted ones are only kept if any reply
+    /// is not deleted. The comments of the shadow-banned authors are only seen by themselves.
+    fn page_roots_by_song_id(executor: E, song_id: i64, viewer_uid: i64, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<SongComment>>> + Send;
+    fn count_roots_by_song_id(executor: E, song_id: i64, viewer_uid: i64) -> impl Future<Output = Result<i64>> + Send;
+    /// The replies of the root comment seen by the viewer including the deleted ones, oldest first
+    fn page_replies(executor: E, parent_id: i64, viewer_uid: i64, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<SongComment>>> + Send;
+    /// The replies seen by the viewer including the deleted ones, the comments without replies are absent
+    fn count_replies_batch(executor: E, parent_ids: &[i64], viewer_uid: i64) -> impl Future<Output = Result<HashMap<i64, i64>>> + Send;
+    /// Returns false if the user reported it already
+    fn insert_report(executor: E, comment_id: i64, reporter_uid: i64, reason: &str) -> impl Future<Output = Result<bool>> + Send;
+    fn count_reports(executor: E, comment_id: i64) -> impl Future<Output = Result<i64>> + Send;
+}
+
+impl<'e, E> ISongCommentDao<'e, E> for SongCommentDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<SongComment>> {
+        sqlx::query_as!(SongComment, "SELECT * FROM song_comments WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn insert(executor: E, value: &SongComment) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO song_comments (song_id, user_id, parent_id, reply_to_uid, content, create_time, update_time, delete_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+            value.song_id,
+            value.user_id,
+            value.parent_id,
+            value.reply_to_uid,
+            value.content,
+            value.create_time,
+            value.update_time,
+            value.delete_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn soft_delete_by_id(executor: E, id: i64, time: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE song_comments SET content = '', delete_time = $2, update_time = $2 WHERE id = $1 AND delete_time IS NULL",
+            id,
+            time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn purge_deleted(executor: E, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM song_comments c
+            WHERE c.delete_time < $1 AND NOT EXISTS (SELECT 1 FROM song_comments r WHERE r.parent_id = c.id)",
+            before
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn page_roots_by_song_id(executor: E, song_id: i64, viewer_uid: i64, page_index: i64, page_size: i64) -> Result<Vec<SongComment>> {
+        sqlx::query_as!(
+            SongComment,
+            "SELECT * FROM song_comments c
+            WHERE song_id = $1 AND parent_id IS NULL
+                AND c.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                AND (c.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = c.user_id AND $3 = ANY(b.features)))
+                AND (delete_time IS NULL OR EXISTS (
+                    SELECT 1 FROM song_comments r WHERE r.parent_id = c.id AND r.delete_time IS NULL
+                        AND r.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                        AND (r.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = r.user_id AND $3 = ANY(b.features)))
+                ))
+            ORDER BY create_time DESC, id DESC LIMIT $4 OFFSET $5",
+            song_id,
+            viewer_uid,
+            user_shadow_ban::FEATURE_COMMENTS,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn count_roots_by_song_id(executor: E, song_id: i64, viewer_uid: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM song_comments c
+            WHERE song_id = $1 AND parent_id IS NULL
+                AND c.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                AND (c.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = c.user_id AND $3 = ANY(b.features)))
+                AND (delete_time IS NULL OR EXISTS (
+                    SELECT 1 FROM song_comments r WHERE r.parent_id = c.id AND r.delete_time IS NULL
+                        AND r.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                        AND (r.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = r.user_id AND $3 = ANY(b.features)))
+                ))"#,
+            song_id,
+            viewer_uid,
+            user_shadow_ban::FEATURE_COMMENTS
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn page_replies(executor: E, parent_id: i64, viewer_uid: i64, page_index: i64, page_size: i64) -> Result<Vec<SongComment>> {
+        sqlx::query_as!(
+            SongComment,
+            "SELECT * FROM song_comments c
+            WHERE parent_id = $1
+                AND c.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                AND (c.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = c.user_id AND $3 = ANY(b.features)))
+            ORDER BY create_time ASC, id ASC LIMIT $4 OFFSET $5",
+            parent_id,
+            viewer_uid,
+            user_shadow_ban::FEATURE_COMMENTS,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn count_replies_batch(executor: E, parent_ids: &[i64], viewer_uid: i64) -> Result<HashMap<i64, i64>> {
+        if parent_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let result = sqlx::query!(
+            r#"SELECT parent_id AS "parent_id!", COUNT(*) AS "count!" FROM song_comments c
+            WHERE parent_id = ANY($1)
+                AND c.user_id NOT IN (SELECT id FROM users WHERE is_banned)
+                AND (c.user_id = $2 OR NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = c.user_id AND $3 = ANY(b.features)))
+            GROUP BY parent_id"#,
+            parent_ids,
+            viewer_uid,
+            user_shadow_ban::FEATURE_COMMENTS
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|x| (x.parent_id, x.count))
+        .collect();
+        Ok(result)
+    }
+
+    async fn insert_report(executor: E, comment_id: i64, reporter_uid: i64, reason: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO song_comment_reports (comment_id, reporter_uid, reason) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            comment_id,
+            reporter_uid,
+            reason
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_reports(executor: E, comment_id: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM song_comment_reports WHERE comment_id = $1"#,
+            comment_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
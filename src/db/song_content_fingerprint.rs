@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// The SimHash of the title and lyrics of a song, see [crate::service::near_duplicate]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongContentFingerprint {
+    pub song_id: i64,
+    /// `None` if the content is too short to be compared
+    pub simhash: Option<i64>,
+    pub band0: Option<i32>,
+    pub band1: Option<i32>,
+    pub band2: Option<i32>,
+    pub band3: Option<i32>,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct SongContentFingerprintDao;
+
+pub trait ISongContentFingerprintDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn upsert(executor: E, value: &SongContentFingerprint) -> impl Future<Output = Result<()>> + Send;
+    /// The fingerprints sharing any of the bands
+    fn list_by_bands(executor: E, bands: [i32; 4], limit: i64) -> impl Future<Output = Result<Vec<SongContentFingerprint>>> + Send;
+    /// The songs not fingerprinted yet, e.g. published before the fingerprints are introduced
+    fn list_song_ids_without_fingerprint(executor: E, limit: i64) -> impl Future<Output = Result<Vec<i64>>> + Send;
+}
+
+impl<'e, E> ISongContentFingerprintDao<'e, E> for SongContentFingerprintDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn upsert(executor: E, value: &SongContentFingerprint) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO song_content_fingerprints (song_id, simhash, band0, band1, band2, band3, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (song_id) DO UPDATE SET
+                simhash = excluded.simhash,
+                band0 = excluded.band0,
+                band1 = excluded.band1,
+                band2 = excluded.band2,
+                band3 = excluded.band3,
+                update_time = excluded.update_time",
+            value.song_id,
+            value.simhash,
+            value.band0,
+            value.band1,
+            value.band2,
+            value.band3,
+            value.update_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_by_bands(executor: E, bands: [i32; 4], limit: i64) -> Result<Vec<SongContentFingerprint>> {
+        sqlx::query_as!(
+            SongContentFingerprint,
+            "SELECT * FROM song_content_fingerprints
+            WHERE band0 = $1 OR band1 = $2 OR band2 = $3 OR band3 = $4
+            LIMIT $5",
+            bands[0],
+            bands[1],
+            bands[2],
+            bands[3],
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn list_song_ids_without_fingerprint(executor: E, limit: i64) -> Result<Vec<i64>> {
+        sqlx::query_scalar!(
+            "SELECT s.id FROM songs s
+            LEFT JOIN song_content_fingerprints f ON f.song_id = s.id
+            WHERE f.song_id IS NULL
+            ORDER BY s.id
+            LIMIT $1",
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,89 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// Audit record of a metadata edit of a published song made outside the review process
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongEditLog {
+    pub id: i64,
+    pub song_id: i64,
+    pub editor_uid: i64,
+    pub note: Option<String>,
+    pub before_data: Value,
+    pub after_data: Value,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct SongEditLogDao;
+
+impl<'e, E> CrudDao<'e, E> for SongEditLogDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = SongEditLog;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM song_edit_logs ORDER BY create_time DESC, id DESC",
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM song_edit_logs ORDER BY create_time DESC, id DESC LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM song_edit_logs WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE song_edit_logs SET song_id = $1, editor_uid = $2, note = $3, before_data = $4, after_data = $5, create_time = $6 WHERE id = $7",
+            value.song_id,
+            value.editor_uid,
+            value.note,
+            value.before_data,
+            value.after_data,
+            value.create_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO song_edit_logs (song_id, editor_uid, note, before_data, after_data, create_time) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            value.song_id,
+            value.editor_uid,
+            value.note,
+            value.before_data,
+            value.after_data,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM song_edit_logs WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::NaiveDate;
+use sqlx::{PgExecutor, Result};
+
+/// The plays of the songs counted per day, see [crate::service::song_play::rollup_daily_plays]
+pub struct SongPlayRollupDao;
+
+pub trait ISongPlayRollupDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// The latest day rolled up, `None` if nothing is rolled up yet
+    fn get_latest_day(executor: E) -> impl Future<Output = Result<Option<NaiveDate>>> + Send;
+    fn delete_since(executor: E, since: NaiveDate) -> impl Future<Output = Result<u64>> + Send;
+    /// Count the plays since the day into the rollups, the plays of the users shadow banned from plays are not
+    /// counted. Returns the rows inserted
+    fn insert_since(executor: E, since: NaiveDate, shadow_ban_feature: &str) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl<'e, E> ISongPlayRollupDao<'e, E> for SongPlayRollupDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_latest_day(executor: E) -> Result<Option<NaiveDate>> {
+        sqlx::query_scalar!("SELECT MAX(day) FROM song_play_counts_daily")
+            .fetch_one(executor)
+            .await
+    }
+
+    async fn delete_since(executor: E, since: NaiveDate) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM song_play_counts_daily WHERE day >= $1", since)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn insert_since(executor: E, since: NaiveDate, shadow_ban_feature: &str) -> Result<u64> {
+        let result = sqlx::query!(
+            "INSERT INTO song_play_counts_daily (song_id, day, play_count)
+            SELECT sp.song_id, (sp.create_time AT TIME ZONE 'UTC')::DATE, COUNT(*)
+            FROM song_plays sp
+            WHERE sp.create_time >= $1::DATE::TIMESTAMP AT TIME ZONE 'UTC'
+                AND NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sp.user_id AND $2 = ANY(b.features))
+            GROUP BY 1, 2",
+            since,
+            shadow_ban_feature
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::db::CrudDao;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{query, query_as, FromRow, PgExecutor};
@@ -21,6 +22,23 @@ pub struct SongPublishingReview {
     pub r#type: i32,
     /// @since 251114
     pub comment: Option<String>,
+    /// The contributor who approved or rejected it
+    /// @since 260428
+    pub reviewer_uid: Option<i64>,
+}
+
+/// The number of reviews in a status
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StatusCount {
+    pub status: i32,
+    pub count: i64,
+}
+
+/// The number of reviews submitted in a day, in UTC
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct DailyCount {
+    pub date: NaiveDate,
+    pub count: i64,
 }
 
 pub const STATUS_PENDING: i32 = 0;
@@ -41,7 +59,25 @@ where
     fn count_by_user(executor: E, user_id: i64) -> impl Future<Output = sqlx::Result<i64>> + Send;
     fn count_by_user_and_status(executor: E, user_id: i64, status: i32) -> impl Future<Output = sqlx::Result<i64>> + Send;
     fn list_by_jmid(executor: E, jmid: &str) -> impl Future<Output = sqlx::Result<Vec<Self::Entity>>> + Send;
+    /// The reviews submitted before `before` (the submit time and the id), newest first.
+    ///
+    /// The reviews are filtered by the status and the prefix of the display id if present.
+    fn cursor_filtered(
+        executor: E,
+        status: Option<i32>,
+        display_id_prefix: Option<&str>,
+        before: Option<(DateTime<Utc>, i64)>,
+        limit: i64,
+    ) -> impl Future<Output = sqlx::Result<Vec<Self::Entity>>> + Send;
     fn swap_jmid(executor: E, old_jmid: &str, new_jmid: &str) -> impl Future<Output = sqlx::Result<u64>> + Send;
+    /// Reject the pending modifications of the song without a reviewer, returns the count of them
+    fn reject_pending_modifies(executor: E, song_display_id: &str, comment: &str, time: DateTime<Utc>) -> impl Future<Output = sqlx::Result<u64>> + Send;
+    fn count_group_by_status(executor: E) -> impl Future<Output = sqlx::Result<Vec<StatusCount>>> + Send;
+    fn get_oldest_pending_submit_time(executor: E) -> impl Future<Output = sqlx::Result<Option<DateTime<Utc>>>> + Send;
+    /// Only the days with submissions are returned
+    fn count_submissions_by_day(executor: E, since: DateTime<Utc>) -> impl Future<Output = sqlx::Result<Vec<DailyCount>>> + Send;
+    /// The reviews approved or rejected by the contributor since the time, of all time if `None`
+    fn count_reviewed_by(executor: E, reviewer_uid: i64, since: Option<DateTime<Utc>>) -> impl Future<Output = sqlx::Result<Vec<StatusCount>>> + Send;
 }
 
 impl<'e, E> CrudDao<'e, E> for SongPublishingReviewDao
@@ -77,8 +113,9 @@ where
                 review_comment = $7,
                 status = $8,
                 type = $9,
-                comment = $10
-            WHERE id = $11",
+                comment = $10,
+                reviewer_uid = $11
+            WHERE id = $12",
             value.user_id,
             value.song_display_id,
             value.data,
@@ -89,16 +126,17 @@ where
             value.status,
             value.r#type,
             value.comment,
+            value.reviewer_uid,
             value.id,
         ).execute(executor).await?;
         Ok(())
     }
 
     async fn insert(executor: E, value: &Self::Entity) -> sqlx::Result<i64> {
-        query!("INSERT INTO song_publishing_review (user_id, song_display_id, data, submit_time, update_time, review_time, review_comment, status, type, comment)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        query!("INSERT INTO song_publishing_review (user_id, song_display_id, data, submit_time, update_time, review_time, review_comment, status, type, comment, reviewer_uid)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
                 RETURNING id",
-            value.user_id, value.song_display_id, value.data, value.submit_time, value.update_time, value.review_time, value.review_comment, value.status, value.r#type, value.comment
+            value.user_id, value.song_display_id, value.data, value.submit_time, value.update_time, value.review_time, value.review_comment, value.status, value.r#type, value.comment, value.reviewer_uid
         ).fetch_one(executor).await.map(|r| r.id)
     }
 
@@ -146,8 +184,85 @@ where
         ).fetch_all(executor).await
     }
 
+    async fn cursor_filtered(
+        executor: E,
+        status: Option<i32>,
+        display_id_prefix: Option<&str>,
+        before: Option<(DateTime<Utc>, i64)>,
+        limit: i64,
+    ) -> sqlx::Result<Vec<Self::Entity>> {
+        let (before_time, before_id) = before.unzip();
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM song_publishing_review
+            WHERE ($1::int IS NULL OR status = $1)
+                AND ($2::text IS NULL OR starts_with(song_display_id, $2))
+                AND ($3::timestamptz IS NULL OR (submit_time, id) < ($3, $4))
+            ORDER BY submit_time DESC, id DESC
+            LIMIT $5",
+            status,
+            display_id_prefix,
+            before_time,
+            before_id,
+            limit
+        ).fetch_all(executor).await
+    }
+
     async fn swap_jmid(executor: E, old_jmid: &str, new_jmid: &str) -> sqlx::Result<u64> {
         let r= query!("UPDATE song_publishing_review SET song_display_id = $1 WHERE song_display_id = $2", new_jmid, old_jmid).execute(executor).await?;
         Ok(r.rows_affected())
     }
+
+    async fn reject_pending_modifies(executor: E, song_display_id: &str, comment: &str, time: DateTime<Utc>) -> sqlx::Result<u64> {
+        let r = query!(
+            "UPDATE song_publishing_review
+            SET status = $1, review_comment = $2, review_time = $3, update_time = $3, reviewer_uid = NULL
+            WHERE song_display_id = $4 AND type = $5 AND status = $6",
+            STATUS_REJECTED,
+            comment,
+            time,
+            song_display_id,
+            TYPE_MODIFY,
+            STATUS_PENDING
+        ).execute(executor).await?;
+        Ok(r.rows_affected())
+    }
+
+    async fn count_group_by_status(executor: E) -> sqlx::Result<Vec<StatusCount>> {
+        query_as!(
+            StatusCount,
+            r#"SELECT status, COUNT(*) AS "count!" FROM song_publishing_review GROUP BY status ORDER BY status"#
+        ).fetch_all(executor).await
+    }
+
+    async fn get_oldest_pending_submit_time(executor: E) -> sqlx::Result<Option<DateTime<Utc>>> {
+        query!("SELECT MIN(submit_time) FROM song_publishing_review WHERE status = $1", STATUS_PENDING)
+            .fetch_one(executor).await
+            .map(|r| r.min)
+    }
+
+    async fn count_submissions_by_day(executor: E, since: DateTime<Utc>) -> sqlx::Result<Vec<DailyCount>> {
+        query_as!(
+            DailyCount,
+            r#"SELECT (submit_time AT TIME ZONE 'UTC')::DATE AS "date!", COUNT(*) AS "count!"
+            FROM song_publishing_review
+            WHERE submit_time >= $1
+            GROUP BY 1
+            ORDER BY 1"#,
+            since
+        ).fetch_all(executor).await
+    }
+
+    async fn count_reviewed_by(executor: E, reviewer_uid: i64, since: Option<DateTime<Utc>>) -> sqlx::Result<Vec<StatusCount>> {
+        query_as!(
+            StatusCount,
+            r#"SELECT status, COUNT(*) AS "count!"
+            FROM song_publishing_review
+            WHERE reviewer_uid = $1 AND ($2::timestamptz IS NULL OR review_time >= $2)
+            GROUP BY status
+            ORDER BY status"#,
+            reviewer_uid,
+            since
+        ).fetch_all(executor).await
+    }
 }
\ No newline at end of file
@@ -28,6 +28,10 @@ where
 
     fn count_by_review_id(executor: E, review_id: i64)
         -> impl Future<Output = Result<i64>> + Send;
+
+    /// Oldest first
+    fn list_by_review_ids(executor: E, review_ids: &[i64])
+        -> impl Future<Output = Result<Vec<Self::Entity>>> + Send;
 }
 
 impl<'e, E> CrudDao<'e, E> for SongPublishingReviewCommentDao
@@ -129,6 +133,14 @@ where
         .await
         .map(|count| count.unwrap_or(0))
     }
-}
-
 
+    async fn list_by_review_ids(executor: E, review_ids: &[i64]) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM song_publishing_review_comment WHERE review_id = ANY($1) ORDER BY create_time ASC, id ASC",
+            review_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
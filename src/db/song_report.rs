@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const STATUS_PENDING: i16 = 0;
+/// Handled by the contributors, e.g. the song was hidden or edited
+pub const STATUS_RESOLVED: i16 = 1;
+pub const STATUS_DISMISSED: i16 = 2;
+
+/// A report of a song by a user, see [crate::service::song_report]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SongReport {
+    pub id: i64,
+    pub song_id: i64,
+    pub reporter_uid: i64,
+    pub category: String,
+    pub reason: String,
+    /// [STATUS_PENDING], [STATUS_RESOLVED] or [STATUS_DISMISSED]
+    pub status: i16,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<i64>,
+    pub resolve_time: Option<DateTime<Utc>>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct SongReportDao;
+
+pub trait ISongReportDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_id(executor: E, id: i64) -> impl Future<Output = Result<Option<SongReport>>> + Send;
+    /// Returns `None` if the reporter has a pending report of the song already
+    fn insert(executor: E, value: &SongReport) -> impl Future<Output = Result<Option<i64>>> + Send;
+    fn count_by_reporter_since(executor: E, reporter_uid: i64, since: DateTime<Utc>) -> impl Future<Output = Result<i64>> + Send;
+    /// Of the status, or all if `None`, latest first
+    fn page_by_status(executor: E, status: Option<i16>, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<SongReport>>> + Send;
+    fn count_by_status(executor: E, status: Option<i16>) -> impl Future<Output = Result<i64>> + Send;
+    /// Set the status of a pending report, returns whether it was pending
+    fn resolve(
+        executor: E,
+        id: i64,
+        status: i16,
+        resolved_by: i64,
+        note: Option<&str>,
+        time: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool>> + Send;
+}
+
+impl<'e, E> ISongReportDao<'e, E> for SongReportDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<SongReport>> {
+        sqlx::query_as!(SongReport, "SELECT * FROM song_reports WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn insert(executor: E, value: &SongReport) -> Result<Option<i64>> {
+        sqlx::query_scalar!(
+            "INSERT INTO song_reports (song_id, reporter_uid, category, reason, status, create_time)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (song_id, reporter_uid) WHERE status = 0 DO NOTHING
+            RETURNING id",
+            value.song_id,
+            value.reporter_uid,
+            value.category,
+            value.reason,
+            value.status,
+            value.create_time
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn count_by_reporter_since(executor: E, reporter_uid: i64, since: DateTime<Utc>) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM song_reports WHERE reporter_uid = $1 AND create_time >= $2"#,
+            reporter_uid,
+            since
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn page_by_status(executor: E, status: Option<i16>, page_index: i64, page_size: i64) -> Result<Vec<SongReport>> {
+        sqlx::query_as!(
+            SongReport,
+            "SELECT * FROM song_reports
+            WHERE $1::SMALLINT IS NULL OR status = $1
+            ORDER BY create_time DESC, id DESC
+            LIMIT $2 OFFSET $3",
+            status,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn count_by_status(executor: E, status: Option<i16>) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM song_reports WHERE $1::SMALLINT IS NULL OR status = $1"#,
+            status
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn resolve(
+        executor: E,
+        id: i64,
+        status: i16,
+        resolved_by: i64,
+        note: Option<&str>,
+        time: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE song_reports SET status = $2, resolved_by = $3, resolution_note = $4, resolve_time = $5
+            WHERE id = $1 AND status = 0",
+            id,
+            status,
+            resolved_by,
+            note,
+            time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::NaiveDate;
+use sqlx::{PgExecutor, Result};
+use std::collections::HashMap;
+
+/// The shares of the songs counted per day and platform, see [crate::service::song_share]
+pub struct SongShareDao;
+
+pub trait ISongShareDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Count a share of the song on the day
+    fn increment(executor: E, song_id: i64, day: NaiveDate, platform: &str) -> impl Future<Output = Result<()>> + Send;
+    /// The total shares of the songs, the songs never shared are absent
+    fn count_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output = Result<HashMap<i64, i64>>> + Send;
+}
+
+impl<'e, E> ISongShareDao<'e, E> for SongShareDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn increment(executor: E, song_id: i64, day: NaiveDate, platform: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO song_share_rollups (song_id, day, platform, share_count)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (song_id, day, platform) DO UPDATE SET share_count = song_share_rollups.share_count + 1",
+            song_id,
+            day,
+            platform
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn count_by_song_ids(executor: E, song_ids: &[i64]) -> Result<HashMap<i64, i64>> {
+        let rows = sqlx::query!(
+            r#"SELECT song_id, SUM(share_count)::BIGINT AS "share_count!"
+            FROM song_share_rollups
+            WHERE song_id = ANY($1)
+            GROUP BY song_id"#,
+            song_ids
+        )
+        .fetch_all(executor)
+        .await?;
+        Ok(rows.into_iter().map(|x| (x.song_id, x.share_count)).collect())
+    }
+}
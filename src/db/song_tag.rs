@@ -1,6 +1,7 @@
 use crate::db::CrudDao;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgExecutor};
 use std::collections::HashMap;
@@ -15,6 +16,15 @@ pub struct SongTag {
     pub update_time: DateTime<Utc>,
 }
 
+/// Another spelling of the canonical tag, the tag named by the alias is resolved to the canonical one
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct SongTagAlias {
+    pub id: i64,
+    pub tag_id: i64,
+    pub alias: String,
+    pub create_time: DateTime<Utc>,
+}
+
 pub struct SongTagDao;
 
 pub trait ISongTagDao<'e, E>: CrudDao<'e, E>
@@ -23,6 +33,15 @@ where E: PgExecutor<'e> {
     fn list_by_song_ids(executor: E, song_ids: &[i64]) -> impl Future<Output = sqlx::Result<HashMap<i64, Vec<i64>>>> + Send;
     fn get_by_name(executor: E, name: &str) -> impl Future<Output = sqlx::Result<Option<SongTag>>> + Send;
     fn search_by_prefix(executor: E, prefix: &str) -> impl Future<Output = sqlx::Result<Vec<SongTag>>> + Send;
+    /// The active tags used by the most songs
+    fn list_popular(executor: E, limit: i64) -> impl Future<Output = sqlx::Result<Vec<SongTag>>> + Send;
+    /// The active tags most common in the latest `history_size` songs played by the user
+    fn list_most_played_by_user(executor: E, user_id: i64, history_size: i64, limit: i64) -> impl Future<Output = sqlx::Result<Vec<i64>>> + Send;
+    fn list_aliases(executor: E) -> impl Future<Output = sqlx::Result<Vec<SongTagAlias>>> + Send;
+    fn list_aliases_by_names(executor: E, aliases: &[String]) -> impl Future<Output = sqlx::Result<Vec<SongTagAlias>>> + Send;
+    fn insert_alias(executor: E, value: &SongTagAlias) -> impl Future<Output = sqlx::Result<i64>> + Send;
+    /// Returns whether the alias existed
+    fn delete_alias(executor: E, id: i64) -> impl Future<Output = sqlx::Result<bool>> + Send;
 }
 
 impl <'e, E> CrudDao<'e, E> for SongTagDao
@@ -35,8 +54,10 @@ where E: PgExecutor<'e> {
             .await
     }
 
-    async fn page(_executor: E, _page: i64, _size: i64) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn page(executor: E, page: i64, size: i64) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM song_tags ORDER BY id LIMIT $1 OFFSET $2", size, page * size)
+            .fetch_all(executor)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> sqlx::Result<Option<Self::Entity>> {
@@ -120,12 +141,79 @@ where E: PgExecutor<'e> {
             .await
     }
 
+    async fn list_popular(executor: E, limit: i64) -> sqlx::Result<Vec<SongTag>> {
+        sqlx::query_as!(
+            SongTag,
+            "SELECT t.* FROM song_tags t
+                JOIN song_tag_refs r ON r.tag_id = t.id
+            WHERE t.is_active
+            GROUP BY t.id
+            ORDER BY COUNT(*) DESC, t.id
+            LIMIT $1",
+            limit
+        ).fetch_all(executor).await
+    }
+
+    async fn list_most_played_by_user(executor: E, user_id: i64, history_size: i64, limit: i64) -> sqlx::Result<Vec<i64>> {
+        sqlx::query_scalar!(
+            "SELECT r.tag_id FROM
+                (SELECT song_id FROM user_play_history WHERE user_id = $1 ORDER BY create_time DESC LIMIT $2) h
+                JOIN song_tag_refs r ON r.song_id = h.song_id
+                JOIN song_tags t ON t.id = r.tag_id AND t.is_active
+            GROUP BY r.tag_id
+            ORDER BY COUNT(*) DESC, r.tag_id
+            LIMIT $3",
+            user_id,
+            history_size,
+            limit,
+        ).fetch_all(executor).await
+    }
+
+    /// Matches the names and the aliases, the tags named by an alias are replaced by the canonical ones
     async fn search_by_prefix(executor: E, prefix: &str) -> sqlx::Result<Vec<SongTag>> {
         let mut escaped = prefix.replace("%", "\\%")
             .replace("_", "\\_");
         escaped.push_str("%");
-        sqlx::query_as!(SongTag, "SELECT * FROM song_tags WHERE name LIKE $1 LIMIT 20", escaped)
+        sqlx::query_as!(
+            SongTag,
+            "SELECT * FROM song_tags t
+            WHERE (t.name LIKE $1 OR EXISTS (SELECT 1 FROM song_tag_aliases a WHERE a.tag_id = t.id AND a.alias LIKE $1))
+                AND NOT EXISTS (SELECT 1 FROM song_tag_aliases a WHERE a.alias = t.name)
+            LIMIT 20",
+            escaped
+        )
             .fetch_all(executor)
             .await
     }
+
+    async fn list_aliases(executor: E) -> sqlx::Result<Vec<SongTagAlias>> {
+        sqlx::query_as!(SongTagAlias, "SELECT * FROM song_tag_aliases ORDER BY tag_id, id")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn list_aliases_by_names(executor: E, aliases: &[String]) -> sqlx::Result<Vec<SongTagAlias>> {
+        if aliases.is_empty() { return Ok(vec![]) }
+        sqlx::query_as!(SongTagAlias, "SELECT * FROM song_tag_aliases WHERE alias = ANY($1)", aliases)
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn insert_alias(executor: E, value: &SongTagAlias) -> sqlx::Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO song_tag_aliases (tag_id, alias, create_time) VALUES ($1, $2, $3) RETURNING id",
+            value.tag_id,
+            value.alias,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_alias(executor: E, id: i64) -> sqlx::Result<bool> {
+        let result = sqlx::query!("DELETE FROM song_tag_aliases WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
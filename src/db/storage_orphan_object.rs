@@ -0,0 +1,143 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// An object found in the bucket but not referenced by any record, to be collected by the GC.
+///
+/// Freshly uploaded files are only referenced by the temp data in Redis until they are published,
+/// so an object should be collected only after it stays unreferenced for a while.
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct StorageOrphanObject {
+    pub id: i64,
+    pub object_key: String,
+    pub size: Option<i64>,
+    pub event_time: DateTime<Utc>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct StorageOrphanObjectDao;
+
+pub trait IStorageOrphanObjectDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Insert the object, or refresh its size and event time if it has been flagged
+    fn upsert(executor: E, value: &StorageOrphanObject) -> impl Future<Output = Result<()>> + Send;
+    /// Returns whether a flagged object is removed
+    fn delete_by_key(executor: E, object_key: &str) -> impl Future<Output = Result<bool>> + Send;
+    /// The public URLs of `urls` referenced by songs, renditions, users, playlists, posts, or pending reviews,
+    /// checked in one query
+    fn referenced_urls(executor: E, urls: &[String]) -> impl Future<Output = Result<Vec<String>>> + Send;
+}
+
+impl<'e, E> CrudDao<'e, E> for StorageOrphanObjectDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = StorageOrphanObject;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM storage_orphan_objects ORDER BY event_time, id",
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM storage_orphan_objects ORDER BY event_time, id LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM storage_orphan_objects WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE storage_orphan_objects SET object_key = $1, size = $2, event_time = $3, create_time = $4 WHERE id = $5",
+            value.object_key,
+            value.size,
+            value.event_time,
+            value.create_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO storage_orphan_objects (object_key, size, event_time, create_time) VALUES ($1, $2, $3, $4) RETURNING id",
+            value.object_key,
+            value.size,
+            value.event_time,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM storage_orphan_objects WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
+
+impl<'e, E> IStorageOrphanObjectDao<'e, E> for StorageOrphanObjectDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn upsert(executor: E, value: &StorageOrphanObject) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO storage_orphan_objects (object_key, size, event_time, create_time) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (object_key) DO UPDATE SET size = EXCLUDED.size, event_time = EXCLUDED.event_time",
+            value.object_key,
+            value.size,
+            value.event_time,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_key(executor: E, object_key: &str) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM storage_orphan_objects WHERE object_key = $1", object_key)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn referenced_urls(executor: E, urls: &[String]) -> Result<Vec<String>> {
+        // The pending reviews are read once for all the URLs
+        sqlx::query_scalar!(
+            r#"WITH pending AS MATERIALIZED (
+                SELECT coalesce(string_agg(data::text, ' '), '') AS data FROM song_publishing_review WHERE status = 0
+            )
+            SELECT u.url AS "url!" FROM unnest($1::text[]) AS u(url), pending p
+            WHERE EXISTS (SELECT 1 FROM songs WHERE file_url = u.url OR cover_art_url = u.url)
+                OR EXISTS (SELECT 1 FROM song_audio_renditions WHERE file_url = u.url)
+                OR EXISTS (SELECT 1 FROM users WHERE avatar_url = u.url)
+                OR EXISTS (SELECT 1 FROM playlists WHERE cover_url = u.url)
+                OR EXISTS (SELECT 1 FROM posts WHERE cover_url = u.url)
+                OR strpos(p.data, u.url) > 0"#,
+            urls
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -1,3 +1,4 @@
+use crate::db::error::{DbError, DbResult};
 use crate::db::CrudDao;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -16,6 +17,12 @@ pub struct User {
     pub last_login_time: Option<DateTime<Utc>>,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
+    /// Row version for optimistic locking
+    #[serde(default)]
+    pub version: i64,
+    /// The language tag of the emails, `None` to follow the default
+    #[serde(default)]
+    pub preferred_language: Option<String>,
 }
 
 pub struct UserDao;
@@ -23,8 +30,17 @@ pub struct UserDao;
 pub trait IUserDao<'e, E>: CrudDao<'e, E>
 where E: PgExecutor<'e> {
     fn list_by_ids(executor: E, ids: &[i64]) -> impl Future<Output = Result<Vec<User>>>;
+    /// Keyset pagination ordered by id, for scanning all users with bounded memory
+    fn list_after_id(executor: E, after_id: i64, limit: i64) -> impl Future<Output = Result<Vec<User>>>;
     fn get_by_email(executor: E, email: &str) -> impl Future<Output = Result<Option<User>>>;
     fn get_by_username(executor: E, username: &str) -> impl Future<Output = Result<Option<User>>>;
+    fn list_by_usernames(executor: E, usernames: &[String]) -> impl Future<Output = Result<Vec<User>>>;
+    fn list_by_emails(executor: E, emails: &[String]) -> impl Future<Output = Result<Vec<User>>>;
+    /// Return the ids of banned users among `ids`
+    fn list_banned_ids(executor: E, ids: &[i64]) -> impl Future<Output = Result<Vec<i64>>>;
+    /// Update only if the row version still equals `value.version`, returns the new version
+    fn update_by_id_checked(executor: E, value: &User) -> impl Future<Output = DbResult<i64>>;
+    fn set_preferred_language(executor: E, id: i64, language: Option<&str>) -> impl Future<Output = Result<()>>;
 }
 
 impl <'e, E> CrudDao<'e, E> for UserDao
@@ -38,9 +54,9 @@ where E: PgExecutor<'e> {
     }
 
     async fn page(executor: E, page: i64, size: i64) -> Result<Vec<User>> {
-        Ok(sqlx::query_as!(User, "SELECT * FROM users LIMIT $1 OFFSET $2", size, (page - 1) * size)
+        sqlx::query_as!(User, "SELECT * FROM users ORDER BY id LIMIT $1 OFFSET $2", size, page * size)
             .fetch_all(executor)
-            .await?)
+            .await
     }
 
     async fn get_by_id(executor: E, id: i64) -> Result<Option<User>> {
@@ -51,7 +67,7 @@ where E: PgExecutor<'e> {
 
     async fn insert(executor: E, value: &User) -> Result<i64> {
         let result = sqlx::query!(
-            "INSERT INTO users(username, email, password_hash, avatar_url, bio, gender, is_banned, last_login_time, create_time, update_time) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING id",
+            "INSERT INTO users(username, email, password_hash, avatar_url, bio, gender, is_banned, last_login_time, create_time, update_time, preferred_language) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id",
             value.username,
             value.email,
             value.password_hash,
@@ -62,6 +78,7 @@ where E: PgExecutor<'e> {
             value.last_login_time,
             value.create_time,
             value.update_time,
+            value.preferred_language,
         ).fetch_one(executor).await?;
 
         Ok(result.id)
@@ -69,7 +86,7 @@ where E: PgExecutor<'e> {
 
     async fn update_by_id(executor: E, value: &User) -> Result<()> {
         sqlx::query!(
-            "UPDATE users SET username = $1, email = $2, password_hash = $3, avatar_url = $4, bio = $5, gender = $6, is_banned = $7, last_login_time = $8, create_time = $9, update_time = $10 WHERE id = $11",
+            "UPDATE users SET username = $1, email = $2, password_hash = $3, avatar_url = $4, bio = $5, gender = $6, is_banned = $7, last_login_time = $8, create_time = $9, update_time = $10, version = version + 1 WHERE id = $11",
             value.username,
             value.email,
             value.password_hash,
@@ -95,12 +112,44 @@ where E: PgExecutor<'e> {
 
 impl <'e, E> IUserDao<'e, E> for UserDao 
 where E: PgExecutor<'e> {
+    async fn update_by_id_checked(executor: E, value: &User) -> DbResult<i64> {
+        sqlx::query_scalar!(
+            "UPDATE users SET username = $1, email = $2, password_hash = $3, avatar_url = $4, bio = $5, gender = $6, is_banned = $7, last_login_time = $8, create_time = $9, update_time = $10, version = version + 1 WHERE id = $11 AND version = $12 RETURNING version",
+            value.username,
+            value.email,
+            value.password_hash,
+            value.avatar_url,
+            value.bio,
+            value.gender,
+            value.is_banned,
+            value.last_login_time,
+            value.create_time,
+            value.update_time,
+            value.id,
+            value.version
+        ).fetch_optional(executor).await?
+            .ok_or(DbError::VersionConflict)
+    }
+
+    async fn set_preferred_language(executor: E, id: i64, language: Option<&str>) -> Result<()> {
+        sqlx::query!("UPDATE users SET preferred_language = $1 WHERE id = $2", language, id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
     async fn list_by_ids(executor: E, ids: &[i64]) -> Result<Vec<User>> {
         if ids.is_empty() { return Ok(vec![]) }
         sqlx::query_as!(User, "SELECT * FROM users WHERE id = ANY($1)", ids)
             .fetch_all(executor)
             .await
     }
+
+    async fn list_after_id(executor: E, after_id: i64, limit: i64) -> Result<Vec<User>> {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id > $1 ORDER BY id LIMIT $2", after_id, limit)
+            .fetch_all(executor)
+            .await
+    }
     async fn get_by_email(executor: E, email: &str) -> Result<Option<User>> {
         sqlx::query_as!(User, "SELECT * FROM users WHERE email = $1", email)
             .fetch_optional(executor)
@@ -112,4 +161,25 @@ where E: PgExecutor<'e> {
             .fetch_optional(executor)
             .await
     }
+
+    async fn list_by_usernames(executor: E, usernames: &[String]) -> Result<Vec<User>> {
+        if usernames.is_empty() { return Ok(vec![]) }
+        sqlx::query_as!(User, "SELECT * FROM users WHERE username = ANY($1)", usernames)
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn list_by_emails(executor: E, emails: &[String]) -> Result<Vec<User>> {
+        if emails.is_empty() { return Ok(vec![]) }
+        sqlx::query_as!(User, "SELECT * FROM users WHERE email = ANY($1)", emails)
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn list_banned_ids(executor: E, ids: &[i64]) -> Result<Vec<i64>> {
+        if ids.is_empty() { return Ok(vec![]) }
+        sqlx::query_scalar!("SELECT id FROM users WHERE id = ANY($1) AND is_banned", ids)
+            .fetch_all(executor)
+            .await
+    }
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+use crate::db::CrudDao;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const ACTION_OAUTH_LINK: &str = "oauth_link";
+pub const ACTION_OAUTH_UNLINK: &str = "oauth_unlink";
+pub const ACTION_TOTP_ENABLE: &str = "totp_enable";
+pub const ACTION_TOTP_DISABLE: &str = "totp_disable";
+
+/// Audit record of a security related change of a user account, such as the login methods
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserAccountAuditLog {
+    pub id: i64,
+    pub user_id: i64,
+    pub action: String,
+    pub detail: Value,
+    pub ip: Option<String>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserAccountAuditLogDao;
+
+pub trait IUserAccountAuditLogDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn list_by_user_id(executor: E, user_id: i64, limit: i64) -> impl Future<Output = Result<Vec<UserAccountAuditLog>>> + Send;
+}
+
+impl<'e, E> IUserAccountAuditLogDao<'e, E> for UserAccountAuditLogDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_user_id(executor: E, user_id: i64, limit: i64) -> Result<Vec<UserAccountAuditLog>> {
+        sqlx::query_as!(
+            UserAccountAuditLog,
+            "SELECT * FROM user_account_audit_logs WHERE user_id = $1 ORDER BY create_time DESC, id DESC LIMIT $2",
+            user_id,
+            limit
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
+
+impl<'e, E> CrudDao<'e, E> for UserAccountAuditLogDao
+where
+    E: PgExecutor<'e>,
+{
+    type Entity = UserAccountAuditLog;
+
+    async fn list(executor: E) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM user_account_audit_logs ORDER BY create_time DESC, id DESC",
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<Self::Entity>> {
+        sqlx::query_as!(
+            Self::Entity,
+            "SELECT * FROM user_account_audit_logs ORDER BY create_time DESC, id DESC LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_id(executor: E, id: i64) -> Result<Option<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM user_account_audit_logs WHERE id = $1", id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn update_by_id(executor: E, value: &Self::Entity) -> Result<()> {
+        sqlx::query!(
+            "UPDATE user_account_audit_logs SET user_id = $1, action = $2, detail = $3, ip = $4, create_time = $5 WHERE id = $6",
+            value.user_id,
+            value.action,
+            value.detail,
+            value.ip,
+            value.create_time,
+            value.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert(executor: E, value: &Self::Entity) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO user_account_audit_logs (user_id, action, detail, ip, create_time) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+            value.user_id,
+            value.action,
+            value.detail,
+            value.ip,
+            value.create_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_by_id(executor: E, id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM user_account_audit_logs WHERE id = $1", id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserFollow {
+    pub follower_id: i64,
+    pub followee_id: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserFollowDao;
+
+pub trait IUserFollowDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Returns false if it's followed already
+    fn insert(executor: E, value: &UserFollow) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether it was followed
+    fn delete(executor: E, follower_id: i64, followee_id: i64) -> impl Future<Output = Result<bool>> + Send;
+    fn is_following(executor: E, follower_id: i64, followee_id: i64) -> impl Future<Output = Result<bool>> + Send;
+    fn count_followers(executor: E, user_id: i64) -> impl Future<Output = Result<i64>> + Send;
+    /// The users without followers are absent
+    fn count_followers_batch(executor: E, user_ids: &[i64]) -> impl Future<Output = Result<HashMap<i64, i64>>> + Send;
+    fn count_following(executor: E, user_id: i64) -> impl Future<Output = Result<i64>> + Send;
+    /// Latest followed first
+    fn page_followers(executor: E, user_id: i64, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<UserFollow>>> + Send;
+    /// Latest followed first
+    fn page_following(executor: E, user_id: i64, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<UserFollow>>> + Send;
+}
+
+impl<'e, E> IUserFollowDao<'e, E> for UserFollowDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn insert(executor: E, value: &UserFollow) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO user_follows (follower_id, followee_id, create_time) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            value.follower_id,
+            value.followee_id,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(executor: E, follower_id: i64, followee_id: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM user_follows WHERE follower_id = $1 AND followee_id = $2",
+            follower_id,
+            followee_id
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn is_following(executor: E, follower_id: i64, followee_id: i64) -> Result<bool> {
+        sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM user_follows WHERE follower_id = $1 AND followee_id = $2) AS "exists!""#,
+            follower_id,
+            followee_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn count_followers(executor: E, user_id: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_follows WHERE followee_id = $1"#,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn count_followers_batch(executor: E, user_ids: &[i64]) -> Result<HashMap<i64, i64>> {
+        if user_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let result = sqlx::query!(
+            r#"SELECT followee_id, COUNT(*) AS "count!" FROM user_follows WHERE followee_id = ANY($1) GROUP BY followee_id"#,
+            user_ids
+        )
+        .fetch_all(executor)
+        .await?
+        .into_iter()
+        .map(|x| (x.followee_id, x.count))
+        .collect();
+        Ok(result)
+    }
+
+    async fn count_following(executor: E, user_id: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_follows WHERE follower_id = $1"#,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn page_followers(executor: E, user_id: i64, page_index: i64, page_size: i64) -> Result<Vec<UserFollow>> {
+        sqlx::query_as!(
+            UserFollow,
+            "SELECT * FROM user_follows WHERE followee_id = $1 ORDER BY create_time DESC, follower_id DESC LIMIT $2 OFFSET $3",
+            user_id,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn page_following(executor: E, user_id: i64, page_index: i64, page_size: i64) -> Result<Vec<UserFollow>> {
+        sqlx::query_as!(
+            UserFollow,
+            "SELECT * FROM user_follows WHERE follower_id = $1 ORDER BY create_time DESC, followee_id DESC LIMIT $2 OFFSET $3",
+            user_id,
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A user whose data is kept regardless of the retention, see [crate::service::retention]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct UserLegalHold {
+    pub user_id: i64,
+    pub reason: String,
+    pub held_by: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserLegalHoldDao;
+
+pub trait IUserLegalHoldDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Latest first
+    fn list(executor: E) -> impl Future<Output = Result<Vec<UserLegalHold>>> + Send;
+    /// Returns whether the user was not held
+    fn insert(executor: E, value: &UserLegalHold) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether the user was held
+    fn delete_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<bool>> + Send;
+}
+
+impl<'e, E> IUserLegalHoldDao<'e, E> for UserLegalHoldDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list(executor: E) -> Result<Vec<UserLegalHold>> {
+        sqlx::query_as!(UserLegalHold, "SELECT * FROM user_legal_holds ORDER BY create_time DESC")
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn insert(executor: E, value: &UserLegalHold) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO user_legal_holds (user_id, reason, held_by, create_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id) DO NOTHING",
+            value.user_id,
+            value.reason,
+            value.held_by,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_user_id(executor: E, user_id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM user_legal_holds WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
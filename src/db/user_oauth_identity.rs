@@ -0,0 +1,106 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// An external account linked for logging in, one per provider for each user
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserOAuthIdentity {
+    pub id: i64,
+    pub user_id: i64,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub provider_user_name: String,
+    pub provider_email: Option<String>,
+    pub create_time: DateTime<Utc>,
+    pub last_login_time: Option<DateTime<Utc>>,
+}
+
+pub struct UserOAuthIdentityDao;
+
+pub trait IUserOAuthIdentityDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn insert(executor: E, value: &UserOAuthIdentity) -> impl Future<Output = Result<i64>> + Send;
+    fn list_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<Vec<UserOAuthIdentity>>> + Send;
+    /// Lock the identities of the user until the transaction ends, so the concurrent unlinks are serialized
+    fn list_by_user_id_for_update(executor: E, user_id: i64) -> impl Future<Output = Result<Vec<UserOAuthIdentity>>> + Send;
+    fn get_by_provider_user_id(executor: E, provider: &str, provider_user_id: &str) -> impl Future<Output = Result<Option<UserOAuthIdentity>>> + Send;
+    fn update_last_login_time(executor: E, id: i64, time: DateTime<Utc>) -> impl Future<Output = Result<()>> + Send;
+    /// Returns whether the identity existed
+    fn delete_by_user_id_and_provider(executor: E, user_id: i64, provider: &str) -> impl Future<Output = Result<bool>> + Send;
+}
+
+impl<'e, E> IUserOAuthIdentityDao<'e, E> for UserOAuthIdentityDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn insert(executor: E, value: &UserOAuthIdentity) -> Result<i64> {
+        sqlx::query_scalar!(
+            "INSERT INTO user_oauth_identities (user_id, provider, provider_user_id, provider_user_name, provider_email, create_time, last_login_time)
+            VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            value.user_id,
+            value.provider,
+            value.provider_user_id,
+            value.provider_user_name,
+            value.provider_email,
+            value.create_time,
+            value.last_login_time
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn list_by_user_id(executor: E, user_id: i64) -> Result<Vec<UserOAuthIdentity>> {
+        sqlx::query_as!(
+            UserOAuthIdentity,
+            "SELECT * FROM user_oauth_identities WHERE user_id = $1 ORDER BY id",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn list_by_user_id_for_update(executor: E, user_id: i64) -> Result<Vec<UserOAuthIdentity>> {
+        sqlx::query_as!(
+            UserOAuthIdentity,
+            "SELECT * FROM user_oauth_identities WHERE user_id = $1 ORDER BY id FOR UPDATE",
+            user_id
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn get_by_provider_user_id(executor: E, provider: &str, provider_user_id: &str) -> Result<Option<UserOAuthIdentity>> {
+        sqlx::query_as!(
+            UserOAuthIdentity,
+            "SELECT * FROM user_oauth_identities WHERE provider = $1 AND provider_user_id = $2",
+            provider,
+            provider_user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn update_last_login_time(executor: E, id: i64, time: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE user_oauth_identities SET last_login_time = $1 WHERE id = $2",
+            time,
+            id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_user_id_and_provider(executor: E, user_id: i64, provider: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM user_oauth_identities WHERE user_id = $1 AND provider = $2",
+            user_id,
+            provider
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
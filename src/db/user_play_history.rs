@@ -14,7 +14,8 @@ pub struct UserPlayHistoryDao;
 pub trait IUserPlayHistory<'e, E>
 where
     E: PgExecutor<'e>, {
-    fn cursor_by_user_id(executor: E, user_id: i64, before_time: DateTime<Utc>, size: usize) -> impl Future<Output = sqlx::Result<Vec<UserPlayHistory>>>;
+    /// Newest first, `before` is the `(create_time, song_id)` of the last item of the previous page
+    fn cursor_by_user_id(executor: E, user_id: i64, before: Option<(DateTime<Utc>, i64)>, size: usize) -> impl Future<Output = sqlx::Result<Vec<UserPlayHistory>>>;
 }
 
 pub trait IUserPlayHistoryExt<'e> {
@@ -24,12 +25,17 @@ pub trait IUserPlayHistoryExt<'e> {
 impl<'e, E> IUserPlayHistory<'e, E> for UserPlayHistoryDao
 where
     E: PgExecutor<'e> {
-    async fn cursor_by_user_id(executor: E, user_id: i64, before_time: DateTime<Utc>, size: usize) -> sqlx::Result<Vec<UserPlayHistory>> {
+    async fn cursor_by_user_id(executor: E, user_id: i64, before: Option<(DateTime<Utc>, i64)>, size: usize) -> sqlx::Result<Vec<UserPlayHistory>> {
+        let (before_time, before_song_id) = before.unzip();
         sqlx::query_as!(
             UserPlayHistory,
-            "SELECT * FROM user_play_history WHERE user_id = $1 AND create_time < $2 ORDER BY create_time DESC LIMIT $3",
+            "SELECT * FROM user_play_history
+            WHERE user_id = $1 AND ($2::timestamptz IS NULL OR (create_time, song_id) < ($2, $3))
+            ORDER BY create_time DESC, song_id DESC
+            LIMIT $4",
             user_id,
             before_time,
+            before_song_id,
             size as i64
         ).fetch_all(executor).await
     }
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor};
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserPreference {
+    pub user_id: i64,
+    pub favorite_tag_ids: Vec<i64>,
+    pub blocked_tag_ids: Vec<i64>,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct UserPreferenceDao;
+
+pub trait IUserPreferenceDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_user_id(executor: E, user_id: i64) -> impl Future<Output = sqlx::Result<Option<UserPreference>>> + Send;
+    /// Insert the preferences, or replace the tags if the user already has them
+    fn upsert(executor: E, value: &UserPreference) -> impl Future<Output = sqlx::Result<()>> + Send;
+}
+
+impl<'e, E> IUserPreferenceDao<'e, E> for UserPreferenceDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_user_id(executor: E, user_id: i64) -> sqlx::Result<Option<UserPreference>> {
+        sqlx::query_as!(UserPreference, "SELECT * FROM user_preferences WHERE user_id = $1", user_id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn upsert(executor: E, value: &UserPreference) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_preferences (user_id, favorite_tag_ids, blocked_tag_ids, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id) DO UPDATE SET
+                favorite_tag_ids = excluded.favorite_tag_ids,
+                blocked_tag_ids = excluded.blocked_tag_ids,
+                update_time = excluded.update_time",
+            value.user_id,
+            &value.favorite_tag_ids,
+            &value.blocked_tag_ids,
+            value.create_time,
+            value.update_time,
+        ).execute(executor).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor};
+
+/// The release channel opted in by a user, see [crate::db::version::CHANNELS]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserReleaseChannel {
+    pub user_id: i64,
+    pub channel: String,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct UserReleaseChannelDao;
+
+pub trait IUserReleaseChannelDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// `None` if the user is on the stable channel
+    fn get_by_user_id(executor: E, user_id: i64) -> impl Future<Output = sqlx::Result<Option<String>>> + Send;
+    fn upsert(executor: E, value: &UserReleaseChannel) -> impl Future<Output = sqlx::Result<()>> + Send;
+}
+
+impl<'e, E> IUserReleaseChannelDao<'e, E> for UserReleaseChannelDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_user_id(executor: E, user_id: i64) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar!("SELECT channel FROM user_release_channels WHERE user_id = $1", user_id)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn upsert(executor: E, value: &UserReleaseChannel) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_release_channels (user_id, channel, update_time)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE SET
+                channel = excluded.channel,
+                update_time = excluded.update_time",
+            value.user_id,
+            value.channel,
+            value.update_time,
+        ).execute(executor).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A role granted to a user, see [crate::service::contributor]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserRole {
+    pub user_id: i64,
+    pub role: String,
+    /// `None` if granted from the config
+    pub granted_by: Option<i64>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserRoleDao;
+
+pub trait IUserRoleDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn role_exists(executor: E, role: &str) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether the user didn't have the role
+    fn grant(executor: E, value: &UserRole) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether the user had the role
+    fn revoke(executor: E, user_id: i64, role: &str) -> impl Future<Output = Result<bool>> + Send;
+    fn list_by_user(executor: E, user_id: i64) -> impl Future<Output = Result<Vec<String>>> + Send;
+    /// Earliest granted first
+    fn list_user_ids_by_role(executor: E, role: &str) -> impl Future<Output = Result<Vec<i64>>> + Send;
+    /// The emails of the users having the role, earliest granted first
+    fn list_emails_by_role(executor: E, role: &str) -> impl Future<Output = Result<Vec<String>>> + Send;
+}
+
+impl<'e, E> IUserRoleDao<'e, E> for UserRoleDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn role_exists(executor: E, role: &str) -> Result<bool> {
+        let result = sqlx::query!("SELECT EXISTS(SELECT 1 FROM roles WHERE name = $1)", role)
+            .fetch_one(executor)
+            .await?;
+        Ok(result.exists.unwrap_or(false))
+    }
+
+    async fn grant(executor: E, value: &UserRole) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO user_roles (user_id, role, granted_by, create_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id, role) DO NOTHING",
+            value.user_id,
+            value.role,
+            value.granted_by,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke(executor: E, user_id: i64, role: &str) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM user_roles WHERE user_id = $1 AND role = $2", user_id, role)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_by_user(executor: E, user_id: i64) -> Result<Vec<String>> {
+        sqlx::query_scalar!("SELECT role FROM user_roles WHERE user_id = $1 ORDER BY role", user_id)
+            .fetch_all(executor)
+            .await
+    }
+
+    async fn list_user_ids_by_role(executor: E, role: &str) -> Result<Vec<i64>> {
+        sqlx::query_scalar!(
+            "SELECT user_id FROM user_roles WHERE role = $1 ORDER BY create_time, user_id",
+            role
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn list_emails_by_role(executor: E, role: &str) -> Result<Vec<String>> {
+        sqlx::query_scalar!(
+            "SELECT u.email FROM user_roles r JOIN users u ON u.id = r.user_id
+            WHERE r.role = $1
+            ORDER BY r.create_time, r.user_id",
+            role
+        )
+        .fetch_all(executor)
+        .await
+    }
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// The likes of the user are not counted
+pub const FEATURE_LIKES: &str = "likes";
+/// The plays of the user are not counted
+pub const FEATURE_PLAYS: &str = "plays";
+/// The comments of the user are only shown to themselves
+pub const FEATURE_COMMENTS: &str = "comments";
+
+/// A shadow-banned user, see [crate::service::shadow_ban]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
+pub struct UserShadowBan {
+    pub user_id: i64,
+    pub features: Vec<String>,
+    pub reason: String,
+    pub update_by: i64,
+    pub create_time: DateTime<Utc>,
+    pub update_time: DateTime<Utc>,
+}
+
+pub struct UserShadowBanDao;
+
+pub trait IUserShadowBanDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<Option<UserShadowBan>>> + Send;
+    /// Latest updated first
+    fn list(executor: E) -> impl Future<Output = Result<Vec<UserShadowBan>>> + Send;
+    /// Insert or replace the features and reason, the `create_time` of the existing one is kept
+    fn upsert(executor: E, value: &UserShadowBan) -> impl Future<Output = Result<()>> + Send;
+    /// Returns whether the user was shadow-banned
+    fn delete_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<bool>> + Send;
+}
+
+impl<'e, E> IUserShadowBanDao<'e, E> for UserShadowBanDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_user_id(executor: E, user_id: i64) -> Result<Option<UserShadowBan>> {
+        sqlx::query_as!(
+            UserShadowBan,
+            "SELECT * FROM user_shadow_bans WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn list(executor: E) -> Result<Vec<UserShadowBan>> {
+        sqlx::query_as!(
+            UserShadowBan,
+            "SELECT * FROM user_shadow_bans ORDER BY update_time DESC"
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn upsert(executor: E, value: &UserShadowBan) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_shadow_bans (user_id, features, reason, update_by, create_time, update_time)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_id) DO UPDATE SET
+                features = EXCLUDED.features,
+                reason = EXCLUDED.reason,
+                update_by = EXCLUDED.update_by,
+                update_time = EXCLUDED.update_time",
+            value.user_id,
+            &value.features,
+            value.reason,
+            value.update_by,
+            value.create_time,
+            value.update_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_user_id(executor: E, user_id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM user_shadow_bans WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
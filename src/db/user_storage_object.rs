@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+pub const KIND_AUDIO: &str = "audio";
+pub const KIND_IMAGE: &str = "image";
+
+/// An uploaded object accounted to the user's storage usage
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserStorageObject {
+    pub id: i64,
+    pub user_id: i64,
+    pub object_key: String,
+    pub kind: String,
+    pub size: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+/// The total bytes of the objects of a user by the kind
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    pub audio_bytes: i64,
+    pub image_bytes: i64,
+}
+
+impl StorageUsage {
+    pub fn total_bytes(&self) -> i64 {
+        self.audio_bytes + self.image_bytes
+    }
+}
+
+pub struct UserStorageObjectDao;
+
+pub trait IUserStorageObjectDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Returns false if the user already has the object
+    fn insert_if_absent(executor: E, value: &UserStorageObject) -> impl Future<Output = Result<bool>> + Send;
+    fn get_usage_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<StorageUsage>> + Send;
+    /// Remove the object from all the owners, returns the count of the removed records
+    fn delete_by_key(executor: E, object_key: &str) -> impl Future<Output = Result<u64>> + Send;
+}
+
+impl<'e, E> IUserStorageObjectDao<'e, E> for UserStorageObjectDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn insert_if_absent(executor: E, value: &UserStorageObject) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO user_storage_objects (user_id, object_key, kind, size, create_time)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_id, object_key) DO NOTHING",
+            value.user_id,
+            value.object_key,
+            value.kind,
+            value.size,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_usage_by_user_id(executor: E, user_id: i64) -> Result<StorageUsage> {
+        sqlx::query_as!(
+            StorageUsage,
+            r#"SELECT
+                COALESCE(SUM(size) FILTER (WHERE kind = 'audio'), 0)::BIGINT AS "audio_bytes!",
+                COALESCE(SUM(size) FILTER (WHERE kind = 'image'), 0)::BIGINT AS "image_bytes!"
+            FROM user_storage_objects WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+
+    async fn delete_by_key(executor: E, object_key: &str) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM user_storage_objects WHERE object_key = $1", object_key)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// A link to support the user on a donation platform, see [crate::service::support_link]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserSupportLink {
+    pub user_id: i64,
+    pub platform: String,
+    pub url: String,
+    pub position: i32,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserSupportLinkDao;
+
+pub trait IUserSupportLinkDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    /// Ordered by the user and the position
+    fn list_by_user_ids(executor: E, user_ids: &[i64]) -> impl Future<Output = Result<Vec<UserSupportLink>>> + Send;
+    fn insert(executor: E, value: &UserSupportLink) -> impl Future<Output = Result<()>> + Send;
+    fn delete_by_user(executor: E, user_id: i64) -> impl Future<Output = Result<()>> + Send;
+}
+
+impl<'e, E> IUserSupportLinkDao<'e, E> for UserSupportLinkDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn list_by_user_ids(executor: E, user_ids: &[i64]) -> Result<Vec<UserSupportLink>> {
+        sqlx::query_as!(
+            UserSupportLink,
+            "SELECT * FROM user_support_links WHERE user_id = ANY($1) ORDER BY user_id, position",
+            user_ids
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn insert(executor: E, value: &UserSupportLink) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_support_links (user_id, platform, url, position, create_time)
+            VALUES ($1, $2, $3, $4, $5)",
+            value.user_id,
+            value.platform,
+            value.url,
+            value.position,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_by_user(executor: E, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM user_support_links WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+}
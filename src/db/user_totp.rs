@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// The TOTP secret of a user, see [crate::service::totp]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct UserTotpSecret {
+    pub user_id: i64,
+    pub secret: String,
+    /// `None` until it's confirmed, the 2FA is enabled after that
+    pub confirm_time: Option<DateTime<Utc>>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct UserTotpDao;
+
+pub trait IUserTotpDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<Option<UserTotpSecret>>> + Send;
+    /// Insert or replace the secret of the user, the replaced one becomes unconfirmed
+    fn upsert(executor: E, value: &UserTotpSecret) -> impl Future<Output = Result<()>> + Send;
+    /// Returns false if there is no unconfirmed secret
+    fn confirm(executor: E, user_id: i64, time: DateTime<Utc>) -> impl Future<Output = Result<bool>> + Send;
+    /// Returns whether the user had a secret
+    fn delete_by_user_id(executor: E, user_id: i64) -> impl Future<Output = Result<bool>> + Send;
+    fn insert_recovery_codes(executor: E, user_id: i64, code_hashes: &[String]) -> impl Future<Output = Result<()>> + Send;
+    fn delete_recovery_codes(executor: E, user_id: i64) -> impl Future<Output = Result<()>> + Send;
+    /// Mark the unused code as used, returns false if it doesn't exist or is used
+    fn use_recovery_code(executor: E, user_id: i64, code_hash: &str, time: DateTime<Utc>) -> impl Future<Output = Result<bool>> + Send;
+    fn count_unused_recovery_codes(executor: E, user_id: i64) -> impl Future<Output = Result<i64>> + Send;
+}
+
+impl<'e, E> IUserTotpDao<'e, E> for UserTotpDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_by_user_id(executor: E, user_id: i64) -> Result<Option<UserTotpSecret>> {
+        sqlx::query_as!(
+            UserTotpSecret,
+            "SELECT * FROM user_totp_secrets WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(executor)
+        .await
+    }
+
+    async fn upsert(executor: E, value: &UserTotpSecret) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_totp_secrets (user_id, secret, confirm_time, create_time)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (user_id) DO UPDATE SET
+                secret = EXCLUDED.secret,
+                confirm_time = EXCLUDED.confirm_time,
+                create_time = EXCLUDED.create_time",
+            value.user_id,
+            value.secret,
+            value.confirm_time,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn confirm(executor: E, user_id: i64, time: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE user_totp_secrets SET confirm_time = $2 WHERE user_id = $1 AND confirm_time IS NULL",
+            user_id,
+            time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_by_user_id(executor: E, user_id: i64) -> Result<bool> {
+        let result = sqlx::query!("DELETE FROM user_totp_secrets WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn insert_recovery_codes(executor: E, user_id: i64, code_hashes: &[String]) -> Result<()> {
+        sqlx::query!(
+            "INSERT INTO user_recovery_codes (user_id, code_hash) SELECT $1, * FROM UNNEST($2::text[])",
+            user_id,
+            code_hashes
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_recovery_codes(executor: E, user_id: i64) -> Result<()> {
+        sqlx::query!("DELETE FROM user_recovery_codes WHERE user_id = $1", user_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    async fn use_recovery_code(executor: E, user_id: i64, code_hash: &str, time: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query!(
+            "UPDATE user_recovery_codes SET use_time = $3 WHERE user_id = $1 AND code_hash = $2 AND use_time IS NULL",
+            user_id,
+            code_hash,
+            time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_unused_recovery_codes(executor: E, user_id: i64) -> Result<i64> {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_recovery_codes WHERE user_id = $1 AND use_time IS NULL"#,
+            user_id
+        )
+        .fetch_one(executor)
+        .await
+    }
+}
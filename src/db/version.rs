@@ -3,6 +3,19 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::PgExecutor;
 
+pub const CHANNEL_STABLE: &str = "stable";
+pub const CHANNEL_BETA: &str = "beta";
+pub const CHANNEL_NIGHTLY: &str = "nightly";
+/// From the most stable one
+pub const CHANNELS: [&str; 3] = [CHANNEL_STABLE, CHANNEL_BETA, CHANNEL_NIGHTLY];
+
+/// The channels whose releases are offered to the clients of the channel, `None` if the channel is invalid.
+///
+/// The clients also get the releases of the more stable channels, so a beta client is updated by a newer stable release.
+pub fn included_channels(channel: &str) -> Option<&'static [&'static str]> {
+    CHANNELS.iter().position(|x| *x == channel).map(|i| &CHANNELS[..=i])
+}
+
 #[derive(sqlx::FromRow)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Version {
@@ -11,6 +24,8 @@ pub struct Version {
     pub version_number: i32,
     pub changelog: String,
     pub variant: String,
+    /// See [CHANNELS]
+    pub channel: String,
     pub url: String,
     pub release_time: DateTime<Utc>,
     pub create_time: DateTime<Utc>,
@@ -25,8 +40,10 @@ where
 {
     type Entity = Version;
 
-    async fn list(_executor: E) -> sqlx::Result<Vec<Self::Entity>> {
-        todo!()
+    async fn list(executor: E) -> sqlx::Result<Vec<Self::Entity>> {
+        sqlx::query_as!(Self::Entity, "SELECT * FROM version ORDER BY release_time DESC")
+            .fetch_all(executor)
+            .await
     }
 
     async fn page(executor: E, page_index: i64, page_size: i64) -> sqlx::Result<Vec<Self::Entity>> {
@@ -49,8 +66,9 @@ where
                 variant = $4,
                 url = $5,
                 release_time = $6,
-                update_time = $7
-            WHERE id = $8",
+                update_time = $7,
+                channel = $8
+            WHERE id = $9",
             value.version_name, 
             value.version_number, 
             value.changelog, 
@@ -58,6 +76,7 @@ where
             value.url, 
             value.release_time, 
             value.update_time, 
+            value.channel,
             value.id
         ).execute(executor).await?;
         Ok(())
@@ -72,8 +91,9 @@ where
                 url,
                 release_time,
                 create_time,
-                update_time
-            ) VALUES($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+                update_time,
+                channel
+            ) VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
             value.version_name,
             value.version_number,
             value.changelog,
@@ -81,7 +101,8 @@ where
             value.url,
             value.release_time,
             value.create_time,
-            value.update_time
+            value.update_time,
+            value.channel
         ).fetch_one(executor).await.map(|x| x.id)
     }
 
@@ -92,28 +113,44 @@ where
 }
 
 impl<'e> VersionDao {
-    pub async fn get_latest_version(executor: impl PgExecutor<'e>, variant: &str, end_time: DateTime<Utc>) -> sqlx::Result<Option<Version>> {
-        sqlx::query_as!(Version, "SELECT * FROM version WHERE variant = $1 AND release_time <= $2 ORDER BY release_time DESC LIMIT 1", variant, end_time)
-            .fetch_optional(executor)
-            .await
+    /// The latest version released in any of the channels
+    pub async fn get_latest_version(executor: impl PgExecutor<'e>, variant: &str, channels: &[&str], end_time: DateTime<Utc>) -> sqlx::Result<Option<Version>> {
+        let channels = channels.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        sqlx::query_as!(
+            Version,
+            "SELECT * FROM version WHERE variant = $1 AND channel = ANY($2) AND release_time <= $3 ORDER BY release_time DESC LIMIT 1",
+            variant, &channels, end_time
+        )
+        .fetch_optional(executor)
+        .await
     }
 
-    pub async fn count(executor: impl PgExecutor<'e>, variant: Option<&str>) -> sqlx::Result<i64> {
+    pub async fn count(executor: impl PgExecutor<'e>, variant: Option<&str>, channel: Option<&str>) -> sqlx::Result<i64> {
         if let Some(variant) = variant {
-            sqlx::query!("SELECT COUNT(*) FROM version WHERE variant = $1", variant)
+            sqlx::query!("SELECT COUNT(*) FROM version WHERE variant = $1 AND ($2::text IS NULL OR channel = $2)", variant, channel)
                 .fetch_one(executor)
                 .await
                 .map(|x| x.count.unwrap_or(0))
         } else {
-            sqlx::query!("SELECT COUNT(*) FROM version")
+            sqlx::query!("SELECT COUNT(*) FROM version WHERE $1::text IS NULL OR channel = $1", channel)
                 .fetch_one(executor)
                 .await
                 .map(|x| x.count.unwrap_or(0))
         }
     }
 
-    pub async fn page_by_variant(executor: impl PgExecutor<'e>, variant: &str, page_index: i64, page_size: i64) -> sqlx::Result<Vec<Version>> {
-        sqlx::query_as!(Version, "SELECT * FROM version WHERE variant = $1 ORDER BY release_time DESC LIMIT $2 OFFSET $3", variant, page_size, page_index * page_size)
+    pub async fn page_by_variant(executor: impl PgExecutor<'e>, variant: &str, channel: Option<&str>, page_index: i64, page_size: i64) -> sqlx::Result<Vec<Version>> {
+        sqlx::query_as!(
+            Version,
+            "SELECT * FROM version WHERE variant = $1 AND ($2::text IS NULL OR channel = $2) ORDER BY release_time DESC LIMIT $3 OFFSET $4",
+            variant, channel, page_size, page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn page_by_channel(executor: impl PgExecutor<'e>, channel: &str, page_index: i64, page_size: i64) -> sqlx::Result<Vec<Version>> {
+        sqlx::query_as!(Version, "SELECT * FROM version WHERE channel = $1 ORDER BY release_time DESC LIMIT $2 OFFSET $3", channel, page_size, page_index * page_size)
             .fetch_all(executor)
             .await
     }
@@ -0,0 +1,75 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgExecutor, Result};
+
+/// The official playlist of a week, see [crate::service::weekly_selection]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WeeklySelection {
+    /// The Monday of the week
+    pub week_start: NaiveDate,
+    pub playlist_id: i64,
+    pub create_time: DateTime<Utc>,
+}
+
+pub struct WeeklySelectionDao;
+
+pub trait IWeeklySelectionDao<'e, E>
+where
+    E: PgExecutor<'e>,
+{
+    fn get_latest(executor: E) -> impl Future<Output = Result<Option<WeeklySelection>>> + Send;
+    fn get_by_week(executor: E, week_start: NaiveDate) -> impl Future<Output = Result<Option<WeeklySelection>>> + Send;
+    /// Returns false if the week is published already
+    fn insert(executor: E, value: &WeeklySelection) -> impl Future<Output = Result<bool>> + Send;
+    /// Latest first
+    fn page(executor: E, page_index: i64, page_size: i64) -> impl Future<Output = Result<Vec<WeeklySelection>>> + Send;
+    fn count(executor: E) -> impl Future<Output = Result<i64>> + Send;
+}
+
+impl<'e, E> IWeeklySelectionDao<'e, E> for WeeklySelectionDao
+where
+    E: PgExecutor<'e>,
+{
+    async fn get_latest(executor: E) -> Result<Option<WeeklySelection>> {
+        sqlx::query_as!(WeeklySelection, "SELECT * FROM weekly_selections ORDER BY week_start DESC LIMIT 1")
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn get_by_week(executor: E, week_start: NaiveDate) -> Result<Option<WeeklySelection>> {
+        sqlx::query_as!(WeeklySelection, "SELECT * FROM weekly_selections WHERE week_start = $1", week_start)
+            .fetch_optional(executor)
+            .await
+    }
+
+    async fn insert(executor: E, value: &WeeklySelection) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT INTO weekly_selections (week_start, playlist_id, create_time)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (week_start) DO NOTHING",
+            value.week_start,
+            value.playlist_id,
+            value.create_time
+        )
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn page(executor: E, page_index: i64, page_size: i64) -> Result<Vec<WeeklySelection>> {
+        sqlx::query_as!(
+            WeeklySelection,
+            "SELECT * FROM weekly_selections ORDER BY week_start DESC LIMIT $1 OFFSET $2",
+            page_size,
+            page_index * page_size
+        )
+        .fetch_all(executor)
+        .await
+    }
+
+    async fn count(executor: E) -> Result<i64> {
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM weekly_selections"#)
+            .fetch_one(executor)
+            .await
+    }
+}
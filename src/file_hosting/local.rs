@@ -0,0 +1,349 @@
+use crate::file_hosting::{CompletedPart, DownloadedObject, FileHost, ObjectHead, UploadOptions, UploadResult};
+use anyhow::{bail, Context};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, SeekFrom};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::info;
+
+/// The `storage.local` section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalStorageCfg {
+    #[serde(default = "default_root")]
+    pub root: PathBuf,
+    #[serde(default = "default_public_base_url")]
+    pub public_base_url: String,
+}
+
+fn default_root() -> PathBuf { PathBuf::from("./data/files") }
+
+fn default_public_base_url() -> String { String::from("http://localhost:8080/files") }
+
+impl Default for LocalStorageCfg {
+    fn default() -> Self {
+        LocalStorageCfg {
+            root: default_root(),
+            public_base_url: default_public_base_url(),
+        }
+    }
+}
+
+/// Store files in a local directory, for development and tests without cloud credentials.
+///
+/// Only the bytes are stored, the content type is derived from the extension when downloading.
+pub struct LocalFileHost {
+    root: PathBuf,
+    public_base_url: String,
+}
+
+impl LocalFileHost {
+    pub async fn new(cfg: LocalStorageCfg) -> anyhow::Result<Self> {
+        tokio::fs::create_dir_all(&cfg.root).await
+            .with_context(|| format!("Failed to create storage root {}", cfg.root.display()))?;
+        info!("Using local storage at {}", cfg.root.display());
+        Ok(LocalFileHost {
+            root: cfg.root,
+            public_base_url: cfg.public_base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Map the key to a path under the root, returns `None` if the key escapes the root
+    fn resolve(&self, key: &str) -> Option<PathBuf> {
+        let path = Path::new(key);
+        let is_normal = path.components().all(|x| matches!(x, Component::Normal(_)));
+        if key.is_empty() || !is_normal {
+            return None;
+        }
+        Some(self.root.join(path))
+    }
+
+    /// The directory of the parts of a multipart upload, the ID is a UUID generated here
+    fn multipart_dir(&self, upload_id: &str) -> anyhow::Result<PathBuf> {
+        let upload_id = uuid::Uuid::parse_str(upload_id).with_context(|| format!("Invalid upload ID {upload_id}"))?;
+        Ok(self.root.join(".multipart").join(upload_id.to_string()))
+    }
+}
+
+impl FileHost for LocalFileHost {
+    fn upload<'a>(
+        &'a self,
+        bytes: Bytes,
+        key: &'a str,
+        _options: &'a UploadOptions,
+    ) -> BoxFuture<'a, anyhow::Result<UploadResult>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                bail!("Invalid key {key}")
+            };
+            info!("Saving file {} to {}. Total: {} bytes", key, path.display(), bytes.len());
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, &bytes).await
+                .with_context(|| format!("Failed to upload {}", key))?;
+            Ok(UploadResult { key: key.to_string(), public_url: self.public_url(key), size: bytes.len() })
+        }.boxed()
+    }
+
+    fn download<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<DownloadedObject>>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                return Ok(None);
+            };
+            let bytes = match tokio::fs::read(&path).await {
+                Ok(x) => x,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to download {}", key)),
+            };
+            Ok(Some(DownloadedObject {
+                bytes: bytes.into(),
+                content_type: guess_content_type(key).map(|x| x.to_string()),
+            }))
+        }.boxed()
+    }
+
+    fn download_range<'a>(&'a self, key: &'a str, range: Range<u64>) -> BoxFuture<'a, anyhow::Result<Option<Bytes>>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                return Ok(None);
+            };
+            let mut file = match tokio::fs::File::open(&path).await {
+                Ok(x) => x,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to download {}", key)),
+            };
+            file.seek(SeekFrom::Start(range.start)).await?;
+            let mut bytes = vec![];
+            file.take(range.end.saturating_sub(range.start)).read_to_end(&mut bytes).await
+                .with_context(|| format!("Failed to download {}", key))?;
+            Ok(Some(bytes.into()))
+        }.boxed()
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<ObjectHead>>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                return Ok(None);
+            };
+            let metadata = match tokio::fs::metadata(&path).await {
+                Ok(x) => x,
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to head {}", key)),
+            };
+            Ok(Some(ObjectHead {
+                size: metadata.len(),
+                content_type: guess_content_type(key).map(|x| x.to_string()),
+                sha256: None,
+            }))
+        }.boxed()
+    }
+
+    fn create_multipart_upload<'a>(&'a self, key: &'a str, _options: &'a UploadOptions) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            if self.resolve(key).is_none() {
+                bail!("Invalid key {key}")
+            }
+            let upload_id = uuid::Uuid::new_v4().to_string();
+            tokio::fs::create_dir_all(self.multipart_dir(&upload_id)?).await?;
+            Ok(upload_id)
+        }.boxed()
+    }
+
+    fn upload_part<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        part_number: i32,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<CompletedPart>> {
+        async move {
+            let dir = self.multipart_dir(upload_id)?;
+            if !tokio::fs::try_exists(&dir).await? {
+                bail!("Multipart upload {upload_id} of {key} is not found")
+            }
+            tokio::fs::write(dir.join(part_number.to_string()), &bytes).await
+                .with_context(|| format!("Failed to upload part {} of {}", part_number, key))?;
+            Ok(CompletedPart { part_number, etag: hex::encode(openssl::sha::sha256(&bytes)) })
+        }.boxed()
+    }
+
+    fn complete_multipart_upload<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        parts: &'a [CompletedPart],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                bail!("Invalid key {key}")
+            };
+            let dir = self.multipart_dir(upload_id)?;
+            let mut parts = parts.to_vec();
+            parts.sort_by_key(|x| x.part_number);
+
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let mut file = tokio::fs::File::create(&path).await
+                .with_context(|| format!("Failed to complete multipart upload of {}", key))?;
+            for part in parts {
+                let bytes = tokio::fs::read(dir.join(part.part_number.to_string())).await
+                    .with_context(|| format!("Part {} of {} is not found", part.part_number, key))?;
+                // Like S3, the parts must be the uploaded ones
+                if hex::encode(openssl::sha::sha256(&bytes)) != part.etag {
+                    drop(file);
+                    tokio::fs::remove_file(&path).await?;
+                    bail!("Part {} of {} doesn't match the ETag", part.part_number, key)
+                }
+                file.write_all(&bytes).await?;
+            }
+            file.flush().await?;
+            tokio::fs::remove_dir_all(&dir).await?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn abort_multipart_upload<'a>(&'a self, _key: &'a str, upload_id: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            match tokio::fs::remove_dir_all(self.multipart_dir(upload_id)?).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        }.boxed()
+    }
+
+    fn rename<'a>(&'a self, old_key: &'a str, new_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let (Some(old_path), Some(new_path)) = (self.resolve(old_key), self.resolve(new_key)) else {
+                bail!("Invalid key {old_key} or {new_key}")
+            };
+            if let Some(parent) = new_path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::copy(&old_path, &new_path).await
+                .with_context(|| format!("Failed to rename {}", old_key))?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let Some(path) = self.resolve(key) else {
+                bail!("Invalid key {key}")
+            };
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e).with_context(|| format!("Failed to delete {}", key)),
+            }
+        }.boxed()
+    }
+
+    fn check(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            let metadata = tokio::fs::metadata(&self.root).await
+                .with_context(|| format!("Failed to access storage root {}", self.root.display()))?;
+            if !metadata.is_dir() {
+                bail!("Storage root {} is not a directory", self.root.display())
+            }
+            Ok(())
+        }.boxed()
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, key)
+    }
+}
+
+fn guess_content_type(key: &str) -> Option<&'static str> {
+    let ext = Path::new(key).extension()?.to_str()?.to_ascii_lowercase();
+    let content_type = match ext.as_str() {
+        "webp" => "image/webp",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "mp3" => "audio/mpeg",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "ogg" | "opus" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "wav" => "audio/wav",
+        "json" => "application/json",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_hosting::PresignUpload;
+
+    #[tokio::test]
+    async fn test_local_file_host() {
+        let root = std::env::temp_dir().join(format!("hachimi-storage-{}", uuid::Uuid::new_v4()));
+        let host = LocalFileHost::new(LocalStorageCfg {
+            root: root.clone(),
+            public_base_url: "http://localhost:8080/files/".to_string(),
+        }).await.unwrap();
+
+        let result = host.upload(Bytes::from_static(b"hello"), "images/cover/a.webp", &UploadOptions::default()).await.unwrap();
+        assert_eq!(result.public_url, "http://localhost:8080/files/images/cover/a.webp");
+
+        let object = host.download("images/cover/a.webp").await.unwrap().unwrap();
+        assert_eq!(object.bytes, Bytes::from_static(b"hello"));
+        assert_eq!(object.content_type.as_deref(), Some("image/webp"));
+
+        assert_eq!(Bytes::from_static(b"ell"), host.download_range("images/cover/a.webp", 1..4).await.unwrap().unwrap());
+        assert_eq!(Bytes::from_static(b"lo"), host.download_range("images/cover/a.webp", 3..100).await.unwrap().unwrap());
+        assert_eq!(5, host.head("images/cover/a.webp").await.unwrap().unwrap().size);
+        assert!(host.head("images/cover/missing.webp").await.unwrap().is_none());
+        // Not supported
+        let upload = PresignUpload { size: 5, sha256: String::new(), expires_in: std::time::Duration::from_secs(60) };
+        assert!(host.presign_upload("images/cover/c.webp", &UploadOptions::default(), &upload).await.unwrap().is_none());
+
+        let upload_id = host.create_multipart_upload("songs/a.flac", &UploadOptions::default()).await.unwrap();
+        let second = host.upload_part("songs/a.flac", &upload_id, 2, Bytes::from_static(b"world")).await.unwrap();
+        let first = host.upload_part("songs/a.flac", &upload_id, 1, Bytes::from_static(b"hi ")).await.unwrap();
+        // A stale part is rejected, and the part can be uploaded again
+        let first = CompletedPart { etag: String::new(), ..first };
+        assert!(host.complete_multipart_upload("songs/a.flac", &upload_id, &[first.clone(), second.clone()]).await.is_err());
+        let first = host.upload_part("songs/a.flac", &upload_id, 1, Bytes::from_static(b"hello ")).await.unwrap();
+        host.complete_multipart_upload("songs/a.flac", &upload_id, &[second, first]).await.unwrap();
+        assert_eq!(Bytes::from_static(b"hello world"), host.download("songs/a.flac").await.unwrap().unwrap().bytes);
+        assert!(host.upload_part("songs/a.flac", &upload_id, 3, Bytes::new()).await.is_err());
+        assert!(host.upload_part("songs/a.flac", "../../x", 1, Bytes::new()).await.is_err());
+
+        let upload_id = host.create_multipart_upload("songs/b.flac", &UploadOptions::default()).await.unwrap();
+        host.upload_part("songs/b.flac", &upload_id, 1, Bytes::from_static(b"hello")).await.unwrap();
+        host.abort_multipart_upload("songs/b.flac", &upload_id).await.unwrap();
+        assert!(host.upload_part("songs/b.flac", &upload_id, 2, Bytes::new()).await.is_err());
+
+        host.rename("images/cover/a.webp", "images/cover/b.webp").await.unwrap();
+        assert!(host.download("images/cover/b.webp").await.unwrap().is_some());
+        assert!(host.download("images/cover/missing.webp").await.unwrap().is_none());
+
+        host.delete("images/cover/b.webp").await.unwrap();
+        assert!(host.download("images/cover/b.webp").await.unwrap().is_none());
+        host.delete("images/cover/missing.webp").await.unwrap();
+        assert!(host.delete("../secret").await.is_err());
+
+        let keys = ["images/cover/a.webp", "../secret", "songs/a.flac", "songs/missing.flac"].map(String::from);
+        // The invalid key fails, but the rest are deleted
+        assert!(host.delete_many(&keys).await.is_err());
+        assert!(host.download("images/cover/a.webp").await.unwrap().is_none());
+        assert!(host.download("songs/a.flac").await.unwrap().is_none());
+        host.delete_many(&keys[2..]).await.unwrap();
+
+        // Keys must stay under the root
+        assert!(host.download("../secret").await.unwrap().is_none());
+        assert!(host.download("/etc/passwd").await.unwrap().is_none());
+        assert!(host.upload(Bytes::new(), "images/../../x", &UploadOptions::default()).await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
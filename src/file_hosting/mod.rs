@@ -1,58 +1,250 @@
-use anyhow::Context;
-use aws_sdk_s3::operation::put_object::PutObjectOutput;
-use aws_sdk_s3::primitives::ByteStream;
+pub mod local;
+pub mod s3;
+
+use crate::config::Config;
 use bytes::Bytes;
-use tracing::info;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// Object storage for the uploaded files, see [s3::S3FileHost] and [local::LocalFileHost]
+pub trait FileHost: Send + Sync {
+    fn upload<'a>(
+        &'a self,
+        bytes: Bytes,
+        key: &'a str,
+        options: &'a UploadOptions,
+    ) -> BoxFuture<'a, anyhow::Result<UploadResult>>;
+
+    /// Download an object, returns `None` if the key does not exist
+    fn download<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<DownloadedObject>>>;
+
+    /// Download the bytes in the range of an object, which may be shorter if the object is.
+    /// Returns `None` if the key does not exist.
+    fn download_range<'a>(&'a self, key: &'a str, range: Range<u64>) -> BoxFuture<'a, anyhow::Result<Option<Bytes>>>;
+
+    /// Get the size and checksum of an object without downloading it, returns `None` if the key does not exist
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<ObjectHead>>>;
+
+    /// Sign a request for the client to upload the object directly, which is only accepted with the exact size
+    /// and SHA-256 in `upload`. Returns `None` if the backend doesn't support it.
+    fn presign_upload<'a>(
+        &'a self,
+        _key: &'a str,
+        _options: &'a UploadOptions,
+        _upload: &'a PresignUpload,
+    ) -> BoxFuture<'a, anyhow::Result<Option<PresignedRequest>>> {
+        async { Ok(None) }.boxed()
+    }
+
+    /// Start uploading an object in parts, returns the ID of the upload
+    fn create_multipart_upload<'a>(&'a self, key: &'a str, options: &'a UploadOptions) -> BoxFuture<'a, anyhow::Result<String>>;
+
+    /// Upload a part, numbered from 1. Every part but the last must be at least [MULTIPART_MIN_PART_BYTES],
+    /// and uploading the same number again replaces the part.
+    fn upload_part<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        part_number: i32,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<CompletedPart>>;
+
+    /// Assemble the parts into the object in the order of their numbers
+    fn complete_multipart_upload<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        parts: &'a [CompletedPart],
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Discard the uploaded parts
+    fn abort_multipart_upload<'a>(&'a self, key: &'a str, upload_id: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Copy the object to the new key, the old object is kept
+    fn rename<'a>(&'a self, old_key: &'a str, new_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
 
-pub struct FileHost {
-    bucket_name: String,
-    client: aws_sdk_s3::Client,
-    public_domain: String,
+    /// Delete the object, deleting a missing key succeeds as well
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Delete the objects, the missing keys are skipped. The rest are still deleted if some fail
+    fn delete_many<'a>(&'a self, keys: &'a [String]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let mut failed = Vec::new();
+            for key in keys {
+                if let Err(e) = self.delete(key).await {
+                    failed.push(format!("{key}: {e:#}"));
+                }
+            }
+            if !failed.is_empty() {
+                anyhow::bail!("Failed to delete {} objects: {}", failed.len(), failed.join("; "))
+            }
+            Ok(())
+        }.boxed()
+    }
+
+    /// Check whether the storage is reachable, for the health check
+    fn check(&self) -> BoxFuture<'_, anyhow::Result<()>>;
+
+    /// The URL saved in the database for the object
+    fn public_url(&self, key: &str) -> String;
+
+    /// The key of an object by its public URL, `None` if the URL is not hosted here
+    fn key_of(&self, url: &str) -> Option<String> {
+        url.strip_prefix(&self.public_url("")).map(|x| x.to_string())
+    }
 }
 
-impl FileHost {
-    pub fn new(bucket_name: String, public_domain: String, client: aws_sdk_s3::Client) -> Self {
-        FileHost {
-            bucket_name,
-            public_domain,
-            client,
+/// Cache for a year, for content-addressed files that never change under the same key
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Cache for a day, since audio may be replaced or taken down
+pub const AUDIO_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// The minimum size of the parts but the last of a multipart upload, required by S3
+pub const MULTIPART_MIN_PART_BYTES: usize = 5 * 1024 * 1024;
+
+/// The bytes read at a time by [download_to_file]
+const DOWNLOAD_RANGE_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Download the object of `size` bytes to the file in ranges, without buffering it in memory.
+/// Returns `false` if the key does not exist.
+pub async fn download_to_file(file_host: &dyn FileHost, key: &str, size: u64, path: &Path) -> anyhow::Result<bool> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut start = 0;
+    while start < size {
+        let end = (start + DOWNLOAD_RANGE_BYTES).min(size);
+        let Some(bytes) = file_host.download_range(key, start..end).await? else {
+            return Ok(false);
+        };
+        if bytes.len() as u64 != end - start {
+            anyhow::bail!("The object {} is shorter than {} bytes", key, size)
         }
+        file.write_all(&bytes).await?;
+        start = end;
     }
+    file.flush().await?;
+    Ok(true)
+}
+
+/// Headers stored along with the object and served to browsers and CDNs
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    pub content_type: Option<String>,
+    pub cache_control: Option<String>,
+    pub content_disposition: Option<String>,
+}
 
-    pub async fn upload(&self, bytes: Bytes, key: &str) -> anyhow::Result<UploadResult> {
-        info!("Uploading file {} to r2. Total: {} bytes", key, bytes.len());
-        let body = ByteStream::from(bytes);
-        let result = self
-            .client
-            .put_object()
-            .bucket(self.bucket_name.clone())
-            .body(body)
-            .key(key)
-            .send()
-            .await
-            .with_context(|| format!("Failed to upload {}", key))?;
-        let url = format!("https://{}/{}", self.public_domain, key);
-        info!("Uploaded to {}", url);
-        Ok(UploadResult {
-            output: result,
-            public_url: url,
-        })
+impl UploadOptions {
+    /// Images are stored by their hash, see [crate::service::image::process_and_upload]
+    pub fn hashed_image(content_type: &str) -> Self {
+        UploadOptions {
+            content_type: Some(content_type.to_string()),
+            cache_control: Some(IMMUTABLE_CACHE_CONTROL.to_string()),
+            content_disposition: None,
+        }
     }
 
-    pub async fn rename(&self, old_key: &str, new_key: &str) -> anyhow::Result<()> {
-        self.client
-            .copy_object()
-            .bucket(self.bucket_name.clone())
-            .copy_source(format!("/{}/{}", self.bucket_name, old_key))
-            .key(new_key)
-            .send()
-            .await
-            .with_context(|| format!("Failed to rename {}", old_key))?;
-        Ok(())
+    pub fn audio(content_type: &str) -> Self {
+        UploadOptions {
+            content_type: Some(content_type.to_string()),
+            cache_control: Some(AUDIO_CACHE_CONTROL.to_string()),
+            content_disposition: Some("inline".to_string()),
+        }
     }
 }
 
 pub struct UploadResult {
-    pub output: PutObjectOutput,
+    pub key: String,
     pub public_url: String,
+    /// The stored bytes
+    pub size: usize,
+}
+
+pub struct DownloadedObject {
+    pub bytes: Bytes,
+    pub content_type: Option<String>,
+}
+
+pub struct ObjectHead {
+    pub size: u64,
+    pub content_type: Option<String>,
+    /// The hex of the SHA-256 of the bytes, if the backend stored it
+    pub sha256: Option<String>,
+}
+
+/// The object the client is going to upload, see [FileHost::presign_upload]
+pub struct PresignUpload {
+    pub size: u64,
+    /// The hex of the SHA-256 of the bytes
+    pub sha256: String,
+    pub expires_in: Duration,
+}
+
+/// An uploaded part of a multipart upload, see [FileHost::upload_part]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedPart {
+    pub part_number: i32,
+    pub etag: String,
+}
+
+/// A signed request for the client to send as is
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PresignedRequest {
+    pub method: String,
+    pub url: String,
+    /// Must be sent along with the request
+    pub headers: Vec<(String, String)>,
+}
+
+/// Optional `storage` section of the config file, S3 is used if it's absent.
+///
+/// ```yaml
+/// storage:
+///   backend: local # s3 | local
+///   local:
+///     root: ./data/files
+///     public_base_url: http://localhost:8080/files
+/// ```
+///
+/// The `s3` backend reads the `s3` section. Files of the `local` backend are served via `/files` in debug builds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageCfg {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    pub local: Option<local::LocalStorageCfg>,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    #[default]
+    S3,
+    Local,
+}
+
+impl StorageCfg {
+    /// Load the `storage` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("storage")?.is_some() {
+            config.get_and_parse("storage")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Create the file host of the configured backend
+pub async fn from_config(config: &Config) -> anyhow::Result<Arc<dyn FileHost>> {
+    let cfg = StorageCfg::load(config)?;
+    let file_host: Arc<dyn FileHost> = match cfg.backend {
+        StorageBackend::S3 => Arc::new(s3::S3FileHost::from_config(config)?),
+        StorageBackend::Local => Arc::new(local::LocalFileHost::new(cfg.local.unwrap_or_default()).await?),
+    };
+    Ok(file_host)
 }
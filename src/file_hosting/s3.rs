@@ -0,0 +1,349 @@
+use crate::config::Config;
+use crate::file_hosting::{CompletedPart, DownloadedObject, FileHost, ObjectHead, PresignUpload, PresignedRequest, UploadOptions, UploadResult};
+use anyhow::{anyhow, Context};
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{ChecksumMode, CompletedMultipartUpload, Delete, ObjectIdentifier};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use tracing::info;
+
+/// The `s3` section of the config file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct S3Config {
+    pub bucket_name: String,
+    pub endpoint_url: String,
+    pub public_domain: String,
+    pub access_key_id: String,
+    pub access_key_secret: String,
+}
+
+pub struct S3FileHost {
+    bucket_name: String,
+    client: aws_sdk_s3::Client,
+    public_domain: String,
+}
+
+impl S3FileHost {
+    pub fn new(bucket_name: String, public_domain: String, client: aws_sdk_s3::Client) -> Self {
+        S3FileHost {
+            bucket_name,
+            public_domain,
+            client,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let cfg: S3Config = config.get_and_parse("s3")?;
+
+        // Configure the client
+        let config = aws_sdk_s3::Config::builder()
+            .endpoint_url(cfg.endpoint_url)
+            .credentials_provider(aws_sdk_s3::config::Credentials::new(
+                cfg.access_key_id,
+                cfg.access_key_secret,
+                None, // session token is not used with R2
+                None,
+                "R2",
+            ))
+            .region(Region::new("auto"))
+            .behavior_version_latest()
+            .build();
+
+        let client = aws_sdk_s3::Client::from_conf(config);
+        Ok(S3FileHost::new(
+            cfg.bucket_name,
+            cfg.public_domain,
+            client,
+        ))
+    }
+}
+
+impl FileHost for S3FileHost {
+    fn upload<'a>(
+        &'a self,
+        bytes: Bytes,
+        key: &'a str,
+        options: &'a UploadOptions,
+    ) -> BoxFuture<'a, anyhow::Result<UploadResult>> {
+        async move {
+            let size = bytes.len();
+            info!("Uploading file {} to r2. Total: {} bytes", key, size);
+            let body = ByteStream::from(bytes);
+            self.client
+                .put_object()
+                .bucket(self.bucket_name.clone())
+                .body(body)
+                .key(key)
+                .set_content_type(options.content_type.clone())
+                .set_cache_control(options.cache_control.clone())
+                .set_content_disposition(options.content_disposition.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload {}", key))?;
+            let url = self.public_url(key);
+            info!("Uploaded to {}", url);
+            Ok(UploadResult { key: key.to_string(), public_url: url, size })
+        }.boxed()
+    }
+
+    fn download<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<DownloadedObject>>> {
+        async move {
+            let result = self
+                .client
+                .get_object()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .send()
+                .await;
+            let output = match result {
+                Ok(x) => x,
+                Err(e) if e.as_service_error().is_some_and(|x| x.is_no_such_key()) => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to download {}", key)),
+            };
+            let content_type = output.content_type;
+            let bytes = output.body.collect().await
+                .with_context(|| format!("Failed to read body of {}", key))?
+                .into_bytes();
+            Ok(Some(DownloadedObject { bytes, content_type }))
+        }.boxed()
+    }
+
+    fn download_range<'a>(&'a self, key: &'a str, range: Range<u64>) -> BoxFuture<'a, anyhow::Result<Option<Bytes>>> {
+        async move {
+            if range.is_empty() {
+                return Ok(Some(Bytes::new()));
+            }
+            let result = self
+                .client
+                .get_object()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                // Inclusive
+                .range(format!("bytes={}-{}", range.start, range.end - 1))
+                .send()
+                .await;
+            let output = match result {
+                Ok(x) => x,
+                Err(e) if e.as_service_error().is_some_and(|x| x.is_no_such_key()) => return Ok(None),
+                // The range starts after the end of the object
+                Err(e) if e.raw_response().is_some_and(|x| x.status().as_u16() == 416) => return Ok(Some(Bytes::new())),
+                Err(e) => return Err(e).with_context(|| format!("Failed to download {}", key)),
+            };
+            let bytes = output.body.collect().await
+                .with_context(|| format!("Failed to read body of {}", key))?
+                .into_bytes();
+            Ok(Some(bytes))
+        }.boxed()
+    }
+
+    fn head<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<Option<ObjectHead>>> {
+        async move {
+            let result = self
+                .client
+                .head_object()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .checksum_mode(ChecksumMode::Enabled)
+                .send()
+                .await;
+            let output = match result {
+                Ok(x) => x,
+                Err(e) if e.as_service_error().is_some_and(|x| x.is_not_found()) => return Ok(None),
+                Err(e) => return Err(e).with_context(|| format!("Failed to head {}", key)),
+            };
+            let sha256 = output.checksum_sha256()
+                .and_then(|x| openssl::base64::decode_block(x).ok())
+                .map(hex::encode);
+            Ok(Some(ObjectHead {
+                size: output.content_length().unwrap_or(0).max(0) as u64,
+                content_type: output.content_type,
+                sha256,
+            }))
+        }.boxed()
+    }
+
+    fn presign_upload<'a>(
+        &'a self,
+        key: &'a str,
+        options: &'a UploadOptions,
+        upload: &'a PresignUpload,
+    ) -> BoxFuture<'a, anyhow::Result<Option<PresignedRequest>>> {
+        async move {
+            let sha256 = hex::decode(&upload.sha256).context("Invalid SHA-256")?;
+            // The checksum is signed, so the storage rejects any other bytes
+            let request = self.client
+                .put_object()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .content_length(upload.size as i64)
+                .checksum_sha256(openssl::base64::encode_block(&sha256))
+                .set_content_type(options.content_type.clone())
+                .set_cache_control(options.cache_control.clone())
+                .set_content_disposition(options.content_disposition.clone())
+                .presigned(PresigningConfig::expires_in(upload.expires_in)?)
+                .await
+                .with_context(|| format!("Failed to presign {}", key))?;
+            Ok(Some(PresignedRequest {
+                method: request.method().to_string(),
+                url: request.uri().to_string(),
+                headers: request.headers().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }))
+        }.boxed()
+    }
+
+    fn create_multipart_upload<'a>(&'a self, key: &'a str, options: &'a UploadOptions) -> BoxFuture<'a, anyhow::Result<String>> {
+        async move {
+            let output = self.client
+                .create_multipart_upload()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .set_content_type(options.content_type.clone())
+                .set_cache_control(options.cache_control.clone())
+                .set_content_disposition(options.content_disposition.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to create multipart upload of {}", key))?;
+            let upload_id = output.upload_id.ok_or_else(|| anyhow!("No upload ID for {}", key))?;
+            info!("Created multipart upload {} of {}", upload_id, key);
+            Ok(upload_id)
+        }.boxed()
+    }
+
+    fn upload_part<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        part_number: i32,
+        bytes: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<CompletedPart>> {
+        async move {
+            let output = self.client
+                .upload_part()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(bytes))
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload part {} of {}", part_number, key))?;
+            let etag = output.e_tag.ok_or_else(|| anyhow!("No ETag for part {} of {}", part_number, key))?;
+            Ok(CompletedPart { part_number, etag })
+        }.boxed()
+    }
+
+    fn complete_multipart_upload<'a>(
+        &'a self,
+        key: &'a str,
+        upload_id: &'a str,
+        parts: &'a [CompletedPart],
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            let mut parts = parts.to_vec();
+            parts.sort_by_key(|x| x.part_number);
+            let parts = parts.into_iter()
+                .map(|x| aws_sdk_s3::types::CompletedPart::builder().part_number(x.part_number).e_tag(x.etag).build())
+                .collect();
+            self.client
+                .complete_multipart_upload()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .upload_id(upload_id)
+                .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+                .send()
+                .await
+                .with_context(|| format!("Failed to complete multipart upload of {}", key))?;
+            info!("Completed multipart upload of {}", key);
+            Ok(())
+        }.boxed()
+    }
+
+    fn abort_multipart_upload<'a>(&'a self, key: &'a str, upload_id: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .abort_multipart_upload()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .upload_id(upload_id)
+                .send()
+                .await
+                .with_context(|| format!("Failed to abort multipart upload of {}", key))?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn rename<'a>(&'a self, old_key: &'a str, new_key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .copy_object()
+                .bucket(self.bucket_name.clone())
+                .copy_source(format!("/{}/{}", self.bucket_name, old_key))
+                .key(new_key)
+                .send()
+                .await
+                .with_context(|| format!("Failed to rename {}", old_key))?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            self.client
+                .delete_object()
+                .bucket(self.bucket_name.clone())
+                .key(key)
+                .send()
+                .await
+                .with_context(|| format!("Failed to delete {}", key))?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn delete_many<'a>(&'a self, keys: &'a [String]) -> BoxFuture<'a, anyhow::Result<()>> {
+        async move {
+            // At most 1000 keys in a request
+            for chunk in keys.chunks(1000) {
+                let objects = chunk.iter()
+                    .map(|x| ObjectIdentifier::builder().key(x).build())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let output = self.client
+                    .delete_objects()
+                    .bucket(self.bucket_name.clone())
+                    .delete(Delete::builder().set_objects(Some(objects)).quiet(true).build()?)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to delete {} objects", chunk.len()))?;
+                // The failed keys are reported in the response instead of failing the request
+                let errors = output.errors();
+                if !errors.is_empty() {
+                    let errors = errors.iter()
+                        .map(|x| format!("{}: {}", x.key().unwrap_or_default(), x.message().unwrap_or_default()))
+                        .collect::<Vec<_>>();
+                    return Err(anyhow!("Failed to delete {} objects: {}", errors.len(), errors.join("; ")));
+                }
+            }
+            Ok(())
+        }.boxed()
+    }
+
+    fn check(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        async move {
+            self.client
+                .head_bucket()
+                .bucket(self.bucket_name.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to head bucket {}", self.bucket_name))?;
+            Ok(())
+        }.boxed()
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("https://{}/{}", self.public_domain, key)
+    }
+}
@@ -1,22 +1,21 @@
 extern crate hachimi_world_server as app;
 
 use app::config::Config;
-use app::file_hosting::FileHost;
-use app::util::gracefully_shutdown;
+use app::file_hosting;
+use app::util::{gracefully_shutdown, redis_health};
 use app::util::redlock::RedLock;
 use app::web::state::AppState;
+use app::web::limits::LimitsCfg;
 use app::web::ServerCfg;
-use app::{search, web};
+use app::{search, service, web};
 use async_backtrace::framed;
-use aws_sdk_s3 as s3;
-use aws_sdk_s3::config::Region;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::sync::Arc;
 #[cfg(not(target_env = "msvc"))]
 use tikv_jemallocator::Jemalloc;
 use tokio::join;
-use tracing::{info, info_span, Instrument};
+use tracing::{error, info, info_span, Instrument};
 
 #[cfg(not(target_env = "msvc"))]
 #[global_allocator]
@@ -36,13 +35,20 @@ async fn main() -> anyhow::Result<()> {
     let config = Config::parse(&std::env::var("CONFIG_PATH").unwrap_or_else(|_| String::from("config.yaml")))?;
 
     let server_cfg = config.get_and_parse::<ServerCfg>("server")?;
+    let limits = LimitsCfg::load(&config)?;
+    service::test_mode::init(&config)?;
     let sql_pool = get_database_pool(config.clone()).await?;
     let all = async {
-        tokio::join!(
+        let (redis_conn, file_host) = tokio::join!(
             get_redis_pool(config.clone()),
-            get_file_host(config.clone()),
-            get_meilisearch_client(config.clone(), &sql_pool)
-        )
+            file_hosting::from_config(&config),
+        );
+        // The full indexing checkpoints in Redis
+        let meilisearch_client = match &redis_conn {
+            Ok(x) => get_meilisearch_client(config.clone(), &sql_pool, x.clone()).await,
+            Err(_) => Err(anyhow::anyhow!("Redis is unavailable")),
+        };
+        (redis_conn, file_host, meilisearch_client)
     };
 
     let state = tokio::select! {
@@ -52,9 +58,10 @@ async fn main() -> anyhow::Result<()> {
                 redis_conn: redis_conn.clone(),
                 config: Arc::new(config),
                 sql_pool: sql_pool,
-                file_host: Arc::new(file_host?),
+                file_host: file_host?,
                 meilisearch: Arc::new(meilisearch_client?),
-                red_lock: RedLock::new(redis_conn)?
+                red_lock: RedLock::new(redis_conn)?,
+                limits: Arc::new(limits),
             }
         }
         _ = cancel_token.cancelled() => {
@@ -63,6 +70,143 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    service::contributor::bootstrap(&state).await?;
+
+    tokio::spawn(
+        redis_health::run_watchdog(state.redis_conn.clone(), cancel_token.clone())
+            .instrument(info_span!("redis_watchdog"))
+    );
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::jobs::run(state, cancel_token).await {
+                error!("Job workers failed: {:?}", e);
+            }
+        }.instrument(info_span!("job_workers"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::cache_warming::run(state, cancel_token).await {
+                error!("Cache warming failed: {:?}", e);
+            }
+        }.instrument(info_span!("cache_warming"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::playlist::run_sort_key_normalization(state, cancel_token).await {
+                error!("Playlist sort key normalization failed: {:?}", e);
+            }
+        }.instrument(info_span!("playlist_sort_key_normalization"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::song_stats::run_verification(state, cancel_token).await {
+                error!("Song stats verification failed: {:?}", e);
+            }
+        }.instrument(info_span!("song_stats_verification"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::song_play::run_flush(state, cancel_token).await {
+                error!("Play count flushing failed: {:?}", e);
+            }
+        }.instrument(info_span!("play_count_flush"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::song_play::run_play_events_flush(state, cancel_token).await {
+                error!("Play events flushing failed: {:?}", e);
+            }
+        }.instrument(info_span!("play_events_flush"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::song_play::run_rollup(state, cancel_token).await {
+                error!("Play rollup failed: {:?}", e);
+            }
+        }.instrument(info_span!("song_play_rollup"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::near_duplicate::run_backfill(state, cancel_token).await {
+                error!("Song fingerprint backfill failed: {:?}", e);
+            }
+        }.instrument(info_span!("song_fingerprint_backfill"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::tombstone::run_purge(state, cancel_token).await {
+                error!("Tombstone purging failed: {:?}", e);
+            }
+        }.instrument(info_span!("tombstone_purge"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::weekly_selection::run_publish(state, cancel_token).await {
+                error!("Weekly selection publishing failed: {:?}", e);
+            }
+        }.instrument(info_span!("weekly_selection_publish"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::retention::run_purge(state, cancel_token).await {
+                error!("Retention purge failed: {:?}", e);
+            }
+        }.instrument(info_span!("retention_purge"))
+    });
+
+    tokio::spawn({
+        let state = state.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::upload_cleanup::run_cleanup(state, cancel_token).await {
+                error!("Temp upload cleanup failed: {:?}", e);
+            }
+        }.instrument(info_span!("upload_cleanup"))
+    });
+
+    tokio::spawn({
+        let redis_client = get_redis_client(&state.config.get_and_parse::<RedisConfig>("redis")?)?;
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = service::events::run_subscriber(redis_client, cancel_token).await {
+                error!("Event subscriber failed: {:?}", e);
+            }
+        }.instrument(info_span!("event_subscriber"))
+    });
+
     // Initialize auth service
 
     info!("Starting web server at {}", server_cfg.listen);
@@ -118,80 +262,53 @@ struct RedisConfig {
     pub database: Option<u16>,
 }
 
+fn get_redis_client(config: &RedisConfig) -> anyhow::Result<redis::Client> {
+    // redis://[<username>][:<password>@]<hostname>[:<port>][/[<db>][?protocol=<protocol>]]
+    let url = format!(
+        "redis://{username}{password}{address}{database}",
+        username = config.username.clone().unwrap_or_default(),
+        password = config.password.as_ref().map_or(String::new(), |p| format!(
+            ":{p}@",
+            p = urlencoding::encode(p)
+        )),
+        address = config.address,
+        database = config.database.map_or(String::new(), |d| format!("/{d}"))
+    );
+    Ok(redis::Client::open(url)?)
+}
+
 async fn get_redis_pool(config: Config) -> anyhow::Result<redis::aio::ConnectionManager> {
     let span = info_span!("redis");
     async {
-        // redis://[<username>][:<password>@]<hostname>[:<port>][/[<db>][?protocol=<protocol>]]
         let config = config.get_and_parse::<RedisConfig>("redis")?;
-
-        let url = format!(
-            "redis://{username}{password}{address}{database}",
-            username = config.username.map_or(String::new(), |u| u),
-            password = config.password.map_or(String::new(), |p| format!(
-                ":{p}@",
-                p = urlencoding::encode(&p)
-            )),
-            address = config.address,
-            database = config.database.map_or(String::new(), |d| format!("/{d}"))
-        );
         info!("Connecting to redis at {}", config.address);
-        let redis = redis::Client::open(url)?;
+        let redis = get_redis_client(&config)?;
         let redis_conn = redis.get_connection_manager().await?;
         info!("Redis connected");
         Ok(redis_conn)
     }.instrument(span).await
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-struct S3Config {
-    pub bucket_name: String,
-    pub endpoint_url: String,
-    pub public_domain: String,
-    pub access_key_id: String,
-    pub access_key_secret: String,
-}
-
-async fn get_file_host(config: Config) -> anyhow::Result<FileHost> {
-    let cfg: S3Config = config.get_and_parse("s3")?;
-
-    // Configure the client
-    let config = s3::Config::builder()
-        .endpoint_url(cfg.endpoint_url)
-        .credentials_provider(aws_sdk_s3::config::Credentials::new(
-            cfg.access_key_id,
-            cfg.access_key_secret,
-            None, // session token is not used with R2
-            None,
-            "R2",
-        ))
-        .region(Region::new("auto"))
-        .behavior_version_latest()
-        .build();
-
-    let client = s3::Client::from_conf(config);
-    Ok(FileHost::new(
-        cfg.bucket_name,
-        cfg.public_domain,
-        client,
-    ))
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct MeiliCfg {
     pub host: String,
     pub api_key: String,
+    /// Only log the index settings to migrate instead of applying them
+    #[serde(default)]
+    pub settings_dry_run: bool,
 }
 
-async fn get_meilisearch_client(config: Config, pool: &PgPool) -> anyhow::Result<meilisearch_sdk::client::Client> {
+async fn get_meilisearch_client(config: Config, pool: &PgPool, redis: redis::aio::ConnectionManager) -> anyhow::Result<meilisearch_sdk::client::Client> {
     let cfg: MeiliCfg = config.get_and_parse("meilisearch")?;
-    let client = meilisearch_sdk::client::Client::new(cfg.host, Some(cfg.api_key))?;
+    let client = meilisearch_sdk::client::Client::new(cfg.host.clone(), Some(cfg.api_key.clone()))?;
+    search::settings::initialize_tokenization(search::settings::TokenizationCfg::load(&config)?)?;
     let span = info_span!("search");
     async {
         info!("Setting up search index");
         let (a, b, c) = join!(
-            search::song::setup_search_index(&client, pool),
-            search::user::setup_search_index(&client, pool),
-            search::playlist::setup_search_index(&client, pool)
+            search::song::setup_search_index(&client, pool, redis.clone(), cfg.settings_dry_run),
+            search::user::setup_search_index(&client, pool, redis.clone(), cfg.settings_dry_run),
+            search::playlist::setup_search_index(&client, pool, redis, cfg.settings_dry_run)
         );
         a.or(b).or(c)
     }.instrument(span).await?;
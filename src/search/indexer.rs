@@ -0,0 +1,221 @@
+//! Full indexing into a new index swapped in when finished, streaming from Postgres with keyset pagination.
+//!
+//! The rows are read in chunks sized to the observed latency, and the progress is checkpointed in Redis,
+//! so a restart resumes into the same new index instead of starting over.
+
+use crate::util::redis_health;
+use meilisearch_sdk::client::{Client, SwapIndexes};
+use meilisearch_sdk::indexes::Index;
+use redis::aio::ConnectionManager;
+use redis::AsyncTypedCommands;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const MIN_CHUNK_SIZE: usize = 64;
+const MAX_CHUNK_SIZE: usize = 4096;
+const INITIAL_CHUNK_SIZE: usize = 512;
+/// The chunks are resized to take about this long to load and index
+const TARGET_CHUNK_DURATION: Duration = Duration::from_secs(5);
+/// The failed chunk is retried with the backoff doubled from the initial one, and then the indexing fails
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Long enough for a restart, the abandoned index is left behind after that
+const CHECKPOINT_TTL_SECS: u64 = 24 * 3600;
+
+/// The progress of a full indexing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The new index being filled
+    pub index_name: String,
+    /// The rows with the id greater than it are not indexed yet
+    pub last_id: i64,
+    pub indexed_count: u64,
+}
+
+/// A chunk loaded from the database
+pub struct Chunk<D> {
+    pub documents: Vec<D>,
+    /// The id of the last row read, `None` if there is no more rows.
+    ///
+    /// Some rows may be skipped as documents, so it's not always the id of the last document.
+    pub last_id: Option<i64>,
+}
+
+/// Adapts the chunk size to the time the chunks take, halving on failures
+#[derive(Debug, Clone)]
+pub struct ChunkSizer {
+    size: usize,
+}
+
+impl Default for ChunkSizer {
+    fn default() -> Self {
+        ChunkSizer { size: INITIAL_CHUNK_SIZE }
+    }
+}
+
+impl ChunkSizer {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn on_success(&mut self, elapsed: Duration) {
+        if elapsed < TARGET_CHUNK_DURATION / 2 {
+            self.size = (self.size * 2).min(MAX_CHUNK_SIZE);
+        } else if elapsed > TARGET_CHUNK_DURATION {
+            self.size = (self.size / 2).max(MIN_CHUNK_SIZE);
+        }
+    }
+
+    pub fn on_failure(&mut self) {
+        self.size = (self.size / 2).max(MIN_CHUNK_SIZE);
+    }
+}
+
+pub async fn has_checkpoint(redis: &ConnectionManager, target: &str) -> bool {
+    matches!(redis_health::cached(load_checkpoint(redis.clone(), target)).await, Some(Some(_)))
+}
+
+/// Fully index the documents into a new index and swap it with `target`.
+///
+/// * `create_index` - Set up the new index with the given name
+/// * `load_chunk` - Load the rows with the id greater than the given one, at most the given size
+pub async fn fully_index<D, C, CF, L, LF>(
+    client: &Client,
+    redis: ConnectionManager,
+    target: &str,
+    create_index: C,
+    load_chunk: L,
+) -> anyhow::Result<()>
+where
+    D: Serialize + Send + Sync,
+    C: FnOnce(String) -> CF,
+    CF: Future<Output = anyhow::Result<Index>>,
+    L: Fn(i64, i64) -> LF,
+    LF: Future<Output = anyhow::Result<Chunk<D>>>,
+{
+    let resumed = match redis_health::cached(load_checkpoint(redis.clone(), target)).await.flatten() {
+        // The new index may have been deleted manually
+        Some(x) if client.get_index(&x.index_name).await.is_ok() => Some(x),
+        _ => None,
+    };
+    let (index, mut checkpoint) = match resumed {
+        Some(x) => {
+            info!("Resuming indexing {} into {} after id {}", target, x.index_name, x.last_id);
+            (client.index(&x.index_name), x)
+        }
+        None => {
+            let index_name = format!("{}_{}", target, chrono::Utc::now().format("%Y%m%d%H%M%S"));
+            let index = create_index(index_name.clone()).await?;
+            (index, Checkpoint { index_name, last_id: i64::MIN, indexed_count: 0 })
+        }
+    };
+
+    let mut sizer = ChunkSizer::default();
+    loop {
+        let mut attempt = 0;
+        let start = Instant::now();
+        let last_id = loop {
+            attempt += 1;
+            match index_chunk(client, &index, &load_chunk, checkpoint.last_id, sizer.size()).await {
+                Ok(x) => break x,
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    let backoff = INITIAL_BACKOFF * 2u32.pow(attempt - 1);
+                    warn!("Failed to index the chunk of {} after id {}, retrying in {:?}: {:?}", target, checkpoint.last_id, backoff, e);
+                    sizer.on_failure();
+                    tokio::time::sleep(backoff).await;
+                }
+                // The checkpoint is kept, so the next run resumes from here
+                Err(e) => return Err(e),
+            }
+        };
+        let Some((last_id, count)) = last_id else { break };
+        sizer.on_success(start.elapsed());
+        checkpoint.last_id = last_id;
+        checkpoint.indexed_count += count as u64;
+        info!("Indexed {} documents of {} until id {}, next chunk size {}", checkpoint.indexed_count, target, last_id, sizer.size());
+        redis_health::cached(save_checkpoint(redis.clone(), target, &checkpoint)).await;
+    }
+
+    info!("Indexed all documents of {}, swapping indexes", target);
+    // Cleared before swapping, resuming after the swap would swap the old documents back
+    redis_health::cached(delete_checkpoint(redis, target)).await;
+    client.swap_indexes([&SwapIndexes {
+        indexes: (target.to_string(), checkpoint.index_name.clone()),
+        rename: None,
+    }]).await?
+        .wait_for_completion(client, None, None)
+        .await?;
+    // The old documents are in the new index after swapping
+    index.delete().await?;
+    info!("Swapped indexes of {} successfully", target);
+    Ok(())
+}
+
+/// Returns the last id and the number of the documents, `None` if there is no more rows
+async fn index_chunk<D, L, LF>(
+    client: &Client,
+    index: &Index,
+    load_chunk: &L,
+    after_id: i64,
+    size: usize,
+) -> anyhow::Result<Option<(i64, usize)>>
+where
+    D: Serialize + Send + Sync,
+    L: Fn(i64, i64) -> LF,
+    LF: Future<Output = anyhow::Result<Chunk<D>>>,
+{
+    let chunk = load_chunk(after_id, size as i64).await?;
+    let Some(last_id) = chunk.last_id else { return Ok(None) };
+    if !chunk.documents.is_empty() {
+        index.add_documents(&chunk.documents, Some("id")).await?
+            .wait_for_completion(client, None, None)
+            .await?;
+    }
+    Ok(Some((last_id, chunk.documents.len())))
+}
+
+fn checkpoint_key(target: &str) -> String {
+    format!("search:full_index_checkpoint:{}", target)
+}
+
+async fn load_checkpoint(mut redis: ConnectionManager, target: &str) -> anyhow::Result<Option<Checkpoint>> {
+    let value = redis.get(checkpoint_key(target)).await?;
+    Ok(value.and_then(|x| serde_json::from_str(&x).ok()))
+}
+
+async fn save_checkpoint(mut redis: ConnectionManager, target: &str, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    redis.set_ex(checkpoint_key(target), serde_json::to_string(checkpoint)?, CHECKPOINT_TTL_SECS).await?;
+    Ok(())
+}
+
+async fn delete_checkpoint(mut redis: ConnectionManager, target: &str) -> anyhow::Result<()> {
+    redis.del(checkpoint_key(target)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_sizer() {
+        let mut sizer = ChunkSizer::default();
+        sizer.on_success(Duration::from_millis(100));
+        assert_eq!(INITIAL_CHUNK_SIZE * 2, sizer.size());
+        // Within the target
+        sizer.on_success(Duration::from_secs(4));
+        assert_eq!(INITIAL_CHUNK_SIZE * 2, sizer.size());
+        sizer.on_success(Duration::from_secs(10));
+        assert_eq!(INITIAL_CHUNK_SIZE, sizer.size());
+
+        for _ in 0..20 {
+            sizer.on_failure();
+        }
+        assert_eq!(MIN_CHUNK_SIZE, sizer.size());
+        for _ in 0..20 {
+            sizer.on_success(Duration::ZERO);
+        }
+        assert_eq!(MAX_CHUNK_SIZE, sizer.size());
+    }
+}
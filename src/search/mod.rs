@@ -1,3 +1,57 @@
+use crate::util::circuit_breaker::CircuitBreaker;
+use meilisearch_sdk::errors::{Error, ErrorType};
+use std::time::Duration;
+
 pub mod song;
 pub mod user;
 pub mod playlist;
+pub mod indexer;
+pub mod settings;
+
+/// Guards the search calls to MeiliSearch, so a slow instance degrades the search instead of stalling the requests
+static SEARCH_BREAKER: CircuitBreaker = CircuitBreaker::new("meilisearch", 5, Duration::from_secs(30));
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Run a search call with a timeout through the circuit breaker, `None` if MeiliSearch is unavailable
+/// or the request is rejected
+pub async fn guarded<T>(operation: impl Future<Output = Result<T, Error>>) -> Option<T> {
+    SEARCH_BREAKER.call_classified(SEARCH_TIMEOUT, operation, is_unavailable).await
+}
+
+/// Whether the error means MeiliSearch is unavailable. The invalid requests, e.g. a bad filter from the client,
+/// are not counted, otherwise anyone could open the circuit and disable the search for everyone.
+fn is_unavailable(error: &Error) -> bool {
+    !matches!(error, Error::Meilisearch(x) if x.error_type == ErrorType::InvalidRequest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use meilisearch_sdk::errors::{ErrorCode, MeilisearchError};
+
+    fn meilisearch_error(error_code: ErrorCode, error_type: ErrorType) -> Error {
+        Error::Meilisearch(MeilisearchError {
+            error_message: String::new(),
+            error_code,
+            error_type,
+            error_link: String::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_invalid_filter_keeps_breaker_closed() {
+        let breaker = CircuitBreaker::new("test", 5, Duration::from_secs(30));
+        for _ in 0..10 {
+            let error = meilisearch_error(ErrorCode::InvalidSearchFilter, ErrorType::InvalidRequest);
+            let result = breaker.call_classified(SEARCH_TIMEOUT, async { Err::<(), _>(error) }, is_unavailable).await;
+            assert!(result.is_none());
+        }
+        assert!(!breaker.is_open());
+
+        for _ in 0..5 {
+            let error = meilisearch_error(ErrorCode::Unknown, ErrorType::Internal);
+            breaker.call_classified(SEARCH_TIMEOUT, async { Err::<(), _>(error) }, is_unavailable).await;
+        }
+        assert!(breaker.is_open());
+    }
+}
@@ -1,10 +1,13 @@
 use crate::db::playlist::{IPlaylistDao, PlaylistDao};
+use crate::db::user::{IUserDao, UserDao};
+use crate::search::settings::{IndexSettings, TypoTolerance};
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use meilisearch_sdk::client::{Client, SwapIndexes};
 use meilisearch_sdk::errors::{Error, ErrorCode};
 use meilisearch_sdk::indexes::Index;
 use metrics::counter;
+use redis::aio::ConnectionManager;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use tracing::{error, info, info_span, Instrument};
@@ -112,7 +115,24 @@ pub async fn search_playlists(
     })
 }
 
-pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(), meilisearch_sdk::errors::Error> {
+pub const SETTINGS: IndexSettings = IndexSettings {
+    index: "playlists",
+    version: 1,
+    // Search text only should come from these fields.
+    searchable: &["title", "description"],
+    // Only public playlists should be searchable.
+    filterable: &["user_id"],
+    sortable: &["create_time", "update_time"],
+    ranking_rules: &[],
+    typo_tolerance: TypoTolerance::DEFAULT,
+};
+
+pub async fn setup_search_index(
+    client: &Client,
+    pg_pool: &PgPool,
+    redis: ConnectionManager,
+    dry_run: bool,
+) -> Result<(), meilisearch_sdk::errors::Error> {
     let exists = match client.get_index("playlists").await {
         Ok(_) => true,
         Err(Error::Meilisearch(err)) => {
@@ -128,6 +148,7 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
     if !exists {
         info!("Setting up playlists index");
         setup_search_index_with_name(client, "playlists").await?;
+        SETTINGS.save_applied_version(redis).await;
 
         // Startup indexing
         tokio::spawn({
@@ -143,6 +164,8 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
             }
             .instrument(info_span!("full_index_playlists"))
         });
+    } else {
+        SETTINGS.migrate(&client.index("playlists"), redis, dry_run).await?;
     }
 
     Ok(())
@@ -150,15 +173,7 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
 
 async fn setup_search_index_with_name(client: &Client, index_name: &str) -> Result<Index, meilisearch_sdk::errors::Error> {
     let index = client.index(index_name);
-
-    // Search text only should come from these fields.
-    index.set_searchable_attributes(["title", "description"]).await?;
-
-    // Only public playlists should be searchable.
-    index.set_filterable_attributes(["user_id"]).await?;
-
-    index.set_sortable_attributes(["create_time", "update_time"]).await?;
-
+    SETTINGS.apply(&index).await?;
     Ok(index)
 }
 
@@ -214,8 +229,11 @@ async fn fully_index_playlists(
 
 async fn get_documents_batch(pool: &PgPool, playlist_ids: &[i64]) -> anyhow::Result<Vec<PlaylistDocument>> {
     let rows = PlaylistDao::list_by_ids(pool, playlist_ids).await?;
+    // Playlists of banned users are not indexed
+    let user_ids = rows.iter().map(|x| x.user_id).unique().collect_vec();
+    let banned_ids = UserDao::list_banned_ids(pool, &user_ids).await?;
     let docs = rows.into_iter()
-        .filter(|x| x.is_public)
+        .filter(|x| x.is_public && !banned_ids.contains(&x.user_id))
         .map(|x| PlaylistDocument {
             id: x.id,
             user_id: x.user_id,
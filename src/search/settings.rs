@@ -0,0 +1,497 @@
+//! The settings of the search indexes, declared per index and migrated at startup.
+//!
+//! The settings were only applied when an index was created, so the attributes added later never reached the
+//! existing deployments. Now the declared settings are diffed against the actual ones on every startup, and the
+//! changed settings are applied. Bump the `version` along with any change.
+//!
+//! The version last applied to each index is kept in Redis. An index migrated by a newer version is left as is,
+//! so rolling back a deployment doesn't revert the settings the newer one relies on.
+//!
+//! The tokenization is tuned by the optional `search_tokenization` section of the config file instead,
+//! see [TokenizationCfg].
+
+use crate::config::Config;
+use crate::util::redis_health;
+use meilisearch_sdk::errors::Error;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::settings::{LocalizedAttributes, MinWordSizeForTypos, TypoToleranceSettings};
+use redis::aio::ConnectionManager;
+use redis::AsyncTypedCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{LazyLock, OnceLock};
+use tracing::{info, warn};
+
+static TOKENIZATION: OnceLock<TokenizationCfg> = OnceLock::new();
+static DEFAULT_TOKENIZATION: LazyLock<TokenizationCfg> = LazyLock::new(TokenizationCfg::default);
+
+/// Optional `search_tokenization` section of the config file, for the mostly Chinese and Japanese titles.
+///
+/// ```yaml
+/// search_tokenization:
+///   indexes: [songs, playlists]
+///   locales: [cmn, jpn]
+///   stop_words: [的, の]
+///   separator_tokens: ["·", "・"]
+///   non_separator_tokens: []
+///   synonyms:
+///     哈基米: [哈吉米, 蛤基米]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TokenizationCfg {
+    /// The indexes to tune, the others are reset to the defaults of MeiliSearch
+    pub indexes: Vec<String>,
+    /// ISO 639-3 codes, so the short titles are not misdetected as another language.
+    /// Requires MeiliSearch 1.10 or later.
+    pub locales: Vec<String>,
+    pub stop_words: Vec<String>,
+    pub separator_tokens: Vec<String>,
+    pub non_separator_tokens: Vec<String>,
+    /// Each word and its variants are synonyms of each other, merged into the tag synonyms of the songs index
+    pub synonyms: HashMap<String, Vec<String>>,
+}
+
+impl Default for TokenizationCfg {
+    fn default() -> Self {
+        TokenizationCfg {
+            indexes: vec!["songs".to_string(), "playlists".to_string()],
+            locales: vec![],
+            stop_words: vec![],
+            separator_tokens: vec![],
+            non_separator_tokens: vec![],
+            synonyms: HashMap::new(),
+        }
+    }
+}
+
+impl TokenizationCfg {
+    /// Load the `search_tokenization` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("search_tokenization")?.is_some() {
+            config.get_and_parse("search_tokenization")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// The configured synonyms, where every form maps to all the others
+    pub fn expand_synonyms(&self) -> HashMap<String, Vec<String>> {
+        let mut synonyms: HashMap<String, Vec<String>> = HashMap::new();
+        for (word, variants) in &self.synonyms {
+            let forms = std::iter::once(word).chain(variants).collect::<Vec<_>>();
+            for form in &forms {
+                let others = synonyms.entry(form.to_string()).or_default();
+                for x in forms.iter().filter(|x| *x != form) {
+                    if !others.contains(x) {
+                        others.push(x.to_string());
+                    }
+                }
+            }
+        }
+        synonyms
+    }
+}
+
+/// Should be called once before setting up the indexes, the default config is used without it
+pub fn initialize_tokenization(cfg: TokenizationCfg) -> anyhow::Result<()> {
+    info!("Tuning the tokenization of the search indexes {:?}", cfg.indexes);
+    if TOKENIZATION.set(cfg).is_err() {
+        anyhow::bail!("Search tokenization is already initialized");
+    }
+    Ok(())
+}
+
+pub fn tokenization() -> &'static TokenizationCfg {
+    TOKENIZATION.get().unwrap_or(&DEFAULT_TOKENIZATION)
+}
+
+/// The declared settings of an index
+#[derive(Debug)]
+pub struct IndexSettings {
+    /// The name of the index, without the timestamp suffix of a full indexing
+    pub index: &'static str,
+    pub version: u32,
+    /// In the order of relevance
+    pub searchable: &'static [&'static str],
+    pub filterable: &'static [&'static str],
+    pub sortable: &'static [&'static str],
+    /// Empty for the defaults of MeiliSearch
+    pub ranking_rules: &'static [&'static str],
+    pub typo_tolerance: TypoTolerance,
+}
+
+/// The default ranking rules of MeiliSearch
+pub const DEFAULT_RANKING_RULES: [&str; 6] = ["words", "typo", "proximity", "attribute", "sort", "exactness"];
+
+/// The typo tolerance of an index, always enabled
+#[derive(Debug, Clone, Copy)]
+pub struct TypoTolerance {
+    /// The minimum characters of a word to accept one typo
+    pub one_typo: u8,
+    /// The minimum characters of a word to accept two typos
+    pub two_typos: u8,
+    /// The attributes matched exactly
+    pub disable_on_attributes: &'static [&'static str],
+}
+
+impl TypoTolerance {
+    /// The defaults of MeiliSearch
+    pub const DEFAULT: TypoTolerance = TypoTolerance { one_typo: 5, two_typos: 9, disable_on_attributes: &[] };
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Setting {
+    Searchable,
+    Filterable,
+    Sortable,
+    RankingRules,
+    /// `[one_typo, two_typos]`
+    TypoMinWordSize,
+    TypoDisabledAttributes,
+    StopWords,
+    SeparatorTokens,
+    NonSeparatorTokens,
+    Locales,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub setting: Setting,
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+/// The settings managed here, either declared or read from an index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ManagedSettings {
+    searchable: Vec<String>,
+    filterable: Vec<String>,
+    sortable: Vec<String>,
+    ranking_rules: Vec<String>,
+    typo_min_word_size: Vec<String>,
+    typo_disabled_attributes: Vec<String>,
+    stop_words: Vec<String>,
+    separator_tokens: Vec<String>,
+    non_separator_tokens: Vec<String>,
+    locales: Vec<String>,
+}
+
+impl ManagedSettings {
+    fn get(&self, setting: Setting) -> &Vec<String> {
+        match setting {
+            Setting::Searchable => &self.searchable,
+            Setting::Filterable => &self.filterable,
+            Setting::Sortable => &self.sortable,
+            Setting::RankingRules => &self.ranking_rules,
+            Setting::TypoMinWordSize => &self.typo_min_word_size,
+            Setting::TypoDisabledAttributes => &self.typo_disabled_attributes,
+            Setting::StopWords => &self.stop_words,
+            Setting::SeparatorTokens => &self.separator_tokens,
+            Setting::NonSeparatorTokens => &self.non_separator_tokens,
+            Setting::Locales => &self.locales,
+        }
+    }
+}
+
+impl IndexSettings {
+    fn declared(&self, tokenization: &TokenizationCfg) -> ManagedSettings {
+        fn to_vec(xs: &[&str]) -> Vec<String> {
+            xs.iter().map(|x| x.to_string()).collect()
+        }
+        let ranking_rules = if self.ranking_rules.is_empty() { &DEFAULT_RANKING_RULES[..] } else { self.ranking_rules };
+        let mut settings = ManagedSettings {
+            searchable: to_vec(self.searchable),
+            filterable: to_vec(self.filterable),
+            sortable: to_vec(self.sortable),
+            ranking_rules: to_vec(ranking_rules),
+            typo_min_word_size: vec![self.typo_tolerance.one_typo.to_string(), self.typo_tolerance.two_typos.to_string()],
+            typo_disabled_attributes: to_vec(self.typo_tolerance.disable_on_attributes),
+            ..Default::default()
+        };
+        if tokenization.indexes.iter().any(|x| x == self.index) {
+            settings.stop_words = tokenization.stop_words.clone();
+            settings.separator_tokens = tokenization.separator_tokens.clone();
+            settings.non_separator_tokens = tokenization.non_separator_tokens.clone();
+            settings.locales = tokenization.locales.clone();
+        }
+        settings
+    }
+
+    /// Apply all the settings, for a newly created index
+    pub async fn apply(&self, index: &Index) -> Result<(), Error> {
+        let declared = self.declared(tokenization());
+        index.set_searchable_attributes(&declared.searchable).await?;
+        index.set_filterable_attributes(&declared.filterable).await?;
+        index.set_sortable_attributes(&declared.sortable).await?;
+        index.set_ranking_rules(&declared.ranking_rules).await?;
+        set(index, Setting::TypoMinWordSize, &declared).await?;
+        // The defaults of a new index are empty
+        for setting in [Setting::StopWords, Setting::SeparatorTokens, Setting::NonSeparatorTokens, Setting::Locales] {
+            if !declared.get(setting).is_empty() {
+                set(index, setting, &declared).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the index is left as is, since it has been migrated by a newer version
+    fn is_applied_by_newer(&self, applied_version: Option<u32>) -> bool {
+        applied_version.is_some_and(|x| x > self.version)
+    }
+
+    /// The version last applied to the index, `None` if unknown
+    async fn applied_version(&self, mut redis: ConnectionManager) -> anyhow::Result<Option<u32>> {
+        let value = redis.get(version_key(self.index)).await?;
+        Ok(value.and_then(|x| x.parse().ok()))
+    }
+
+    /// Record the version as applied, after the index is created or migrated
+    pub async fn save_applied_version(&self, mut redis: ConnectionManager) {
+        let key = version_key(self.index);
+        redis_health::cached(redis.set(&key, self.version)).await;
+    }
+
+    /// The settings to change to match the declared ones
+    fn diff(&self, actual: &ManagedSettings, tokenization: &TokenizationCfg) -> Vec<SettingChange> {
+        fn same_set(a: &[String], b: &[String]) -> bool {
+            a.iter().collect::<BTreeSet<_>>() == b.iter().collect::<BTreeSet<_>>()
+        }
+
+        let declared = self.declared(tokenization);
+        let settings = [
+            Setting::Searchable,
+            Setting::Filterable,
+            Setting::Sortable,
+            Setting::RankingRules,
+            Setting::TypoMinWordSize,
+            Setting::TypoDisabledAttributes,
+            Setting::StopWords,
+            Setting::SeparatorTokens,
+            Setting::NonSeparatorTokens,
+            Setting::Locales,
+        ];
+        settings.into_iter()
+            .filter(|x| {
+                let (from, to) = (actual.get(*x), declared.get(*x));
+                // The order of the searchable attributes and the ranking rules matters to the ranking,
+                // so does the order of the minimum word sizes
+                let ordered = matches!(x, Setting::Searchable | Setting::RankingRules | Setting::TypoMinWordSize);
+                if ordered { from != to } else { !same_set(from, to) }
+            })
+            .map(|x| SettingChange { setting: x, from: actual.get(x).clone(), to: declared.get(x).clone() })
+            .collect()
+    }
+
+    /// Apply the settings differing from the declared ones, or only log them in the dry run
+    pub async fn migrate(&self, index: &Index, redis: ConnectionManager, dry_run: bool) -> Result<Vec<SettingChange>, Error> {
+        let applied_version = redis_health::cached(self.applied_version(redis.clone())).await.flatten();
+        if self.is_applied_by_newer(applied_version) {
+            warn!(index = index.uid, version = self.version, applied_version, "The settings are migrated by a newer version, skipped");
+            return Ok(vec![]);
+        }
+
+        let tokenization = tokenization();
+        let locales = if tokenization.locales.is_empty() {
+            // Not read unless configured, since the older MeiliSearch doesn't support it
+            vec![]
+        } else {
+            let attributes = index.get_localized_attributes().await?.unwrap_or_default();
+            match attributes.as_slice() {
+                [] => vec![],
+                [x] if x.attribute_patterns == ["*"] => x.locales.clone(),
+                // Customized elsewhere, replaced with the configured ones
+                _ => vec![format!("{:?}", attributes)],
+            }
+        };
+        let typo = index.get_typo_tolerance().await?;
+        let min_word_size = typo.min_word_size_for_typos.unwrap_or_default();
+        let actual = ManagedSettings {
+            searchable: index.get_searchable_attributes().await?,
+            filterable: index.get_filterable_attributes().await?,
+            sortable: index.get_sortable_attributes().await?,
+            ranking_rules: index.get_ranking_rules().await?,
+            typo_min_word_size: vec![
+                min_word_size.one_typo.unwrap_or(TypoTolerance::DEFAULT.one_typo).to_string(),
+                min_word_size.two_typos.unwrap_or(TypoTolerance::DEFAULT.two_typos).to_string(),
+            ],
+            typo_disabled_attributes: typo.disable_on_attributes.unwrap_or_default(),
+            stop_words: index.get_stop_words().await?,
+            separator_tokens: index.get_separator_tokens().await?,
+            non_separator_tokens: index.get_non_separator_tokens().await?,
+            locales,
+        };
+        let changes = self.diff(&actual, tokenization);
+        let declared = self.declared(tokenization);
+        for change in &changes {
+            if dry_run {
+                info!(index = index.uid, version = self.version, applied_version, "[dry run] Would change {:?}", change);
+                continue;
+            }
+            info!(index = index.uid, version = self.version, applied_version, "Changing {:?}", change);
+            set(index, change.setting, &declared).await?;
+        }
+        if !dry_run && applied_version != Some(self.version) {
+            self.save_applied_version(redis).await;
+        }
+        Ok(changes)
+    }
+}
+
+fn version_key(index: &str) -> String {
+    format!("search:settings_version:{}", index)
+}
+
+/// Set a setting to the declared value
+async fn set(index: &Index, setting: Setting, declared: &ManagedSettings) -> Result<(), Error> {
+    let value = declared.get(setting);
+    match setting {
+        Setting::Searchable => index.set_searchable_attributes(value).await?,
+        Setting::Filterable => index.set_filterable_attributes(value).await?,
+        Setting::Sortable => index.set_sortable_attributes(value).await?,
+        Setting::RankingRules => index.set_ranking_rules(value).await?,
+        // The typo tolerance is set as a whole, the unset fields would be reset
+        Setting::TypoMinWordSize | Setting::TypoDisabledAttributes => {
+            let size = |x: &String| x.parse::<u8>().ok();
+            let min_word_size = &declared.typo_min_word_size;
+            index.set_typo_tolerance(&TypoToleranceSettings {
+                enabled: Some(true),
+                disable_on_attributes: Some(declared.typo_disabled_attributes.clone()),
+                disable_on_words: None,
+                min_word_size_for_typos: Some(MinWordSizeForTypos {
+                    one_typo: min_word_size.first().and_then(size),
+                    two_typos: min_word_size.get(1).and_then(size),
+                }),
+                disable_on_numbers: None,
+            }).await?
+        }
+        Setting::StopWords => index.set_stop_words(value).await?,
+        Setting::SeparatorTokens => index.set_separator_tokens(value).await?,
+        Setting::NonSeparatorTokens => index.set_non_separator_tokens(value).await?,
+        Setting::Locales => {
+            let attributes = if value.is_empty() {
+                vec![]
+            } else {
+                vec![LocalizedAttributes { locales: value.clone(), attribute_patterns: vec!["*".to_string()] }]
+            };
+            index.set_localized_attributes(&attributes).await?
+        }
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: IndexSettings = IndexSettings {
+        index: "songs",
+        version: 2,
+        searchable: &["title", "tags"],
+        filterable: &["tags", "uploader_uid"],
+        sortable: &["play_count"],
+        ranking_rules: &[],
+        typo_tolerance: TypoTolerance::DEFAULT,
+    };
+
+    fn strings(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|x| x.to_string()).collect()
+    }
+
+    /// The actual settings with the default ranking rules and typo tolerance
+    fn actual(searchable: &[&str], filterable: &[&str], sortable: &[&str]) -> ManagedSettings {
+        ManagedSettings {
+            searchable: strings(searchable),
+            filterable: strings(filterable),
+            sortable: strings(sortable),
+            ranking_rules: strings(&DEFAULT_RANKING_RULES),
+            typo_min_word_size: strings(&["5", "9"]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let cfg = TokenizationCfg::default();
+        // Up to date, the filterable and sortable attributes are returned sorted
+        assert!(SETTINGS.diff(&actual(&["title", "tags"], &["tags", "uploader_uid"], &["play_count"]), &cfg).is_empty());
+        assert!(SETTINGS.diff(&actual(&["title", "tags"], &["uploader_uid", "tags"], &["play_count"]), &cfg).is_empty());
+
+        // An attribute added after the index is created
+        let changes = SETTINGS.diff(&actual(&["title", "tags"], &["tags"], &["play_count"]), &cfg);
+        assert_eq!(vec![SettingChange { setting: Setting::Filterable, from: strings(&["tags"]), to: strings(&["tags", "uploader_uid"]) }], changes);
+
+        // Reordered or defaulted searchable attributes
+        let changes = SETTINGS.diff(&actual(&["tags", "title"], &["tags", "uploader_uid"], &[]), &cfg);
+        assert_eq!(vec![Setting::Searchable, Setting::Sortable], changes.iter().map(|x| x.setting).collect::<Vec<_>>());
+        assert_eq!(1, SETTINGS.diff(&actual(&["*"], &["tags", "uploader_uid"], &["play_count"]), &cfg).len());
+    }
+
+    #[test]
+    fn test_is_applied_by_newer() {
+        assert!(!SETTINGS.is_applied_by_newer(None));
+        assert!(!SETTINGS.is_applied_by_newer(Some(1)));
+        assert!(!SETTINGS.is_applied_by_newer(Some(2)));
+        assert!(SETTINGS.is_applied_by_newer(Some(3)));
+    }
+
+    #[test]
+    fn test_diff_tokenization() {
+        let cfg: TokenizationCfg = serde_yaml::from_str("
+            locales: [cmn, jpn]
+            stop_words: [的]
+            separator_tokens: ['·']
+        ").unwrap();
+        assert_eq!(strings(&["songs", "playlists"]), cfg.indexes);
+        let up_to_date = actual(&["title", "tags"], &["tags", "uploader_uid"], &["play_count"]);
+        let changes = SETTINGS.diff(&up_to_date, &cfg);
+        assert_eq!(vec![Setting::StopWords, Setting::SeparatorTokens, Setting::Locales], changes.iter().map(|x| x.setting).collect::<Vec<_>>());
+
+        let tuned = ManagedSettings {
+            stop_words: strings(&["的"]),
+            separator_tokens: strings(&["·"]),
+            locales: strings(&["jpn", "cmn"]),
+            ..up_to_date.clone()
+        };
+        assert!(SETTINGS.diff(&tuned, &cfg).is_empty());
+        // Reset if the index is no longer tuned
+        let users = IndexSettings { index: "users", ..SETTINGS };
+        assert_eq!(3, users.diff(&tuned, &cfg).len());
+        assert!(users.diff(&up_to_date, &cfg).is_empty());
+    }
+
+    #[test]
+    fn test_expand_synonyms() {
+        let cfg: TokenizationCfg = serde_yaml::from_str("synonyms: { 哈基米: [哈吉米, 蛤基米] }").unwrap();
+        let synonyms = cfg.expand_synonyms();
+        assert_eq!(3, synonyms.len());
+        assert_eq!(strings(&["哈吉米", "蛤基米"]), synonyms["哈基米"]);
+        assert_eq!(strings(&["哈基米", "蛤基米"]), synonyms["哈吉米"]);
+    }
+
+    #[test]
+    fn test_diff_ranking_and_typo() {
+        let cfg = TokenizationCfg::default();
+        let tuned = IndexSettings {
+            ranking_rules: &["words", "typo", "proximity", "attribute", "sort", "exactness", "play_count:desc"],
+            typo_tolerance: TypoTolerance { one_typo: 4, two_typos: 8, disable_on_attributes: &["tags"] },
+            ..SETTINGS
+        };
+        let defaults = actual(&["title", "tags"], &["tags", "uploader_uid"], &["play_count"]);
+        let changes = tuned.diff(&defaults, &cfg);
+        assert_eq!(
+            vec![Setting::RankingRules, Setting::TypoMinWordSize, Setting::TypoDisabledAttributes],
+            changes.iter().map(|x| x.setting).collect::<Vec<_>>()
+        );
+
+        let up_to_date = ManagedSettings {
+            ranking_rules: strings(tuned.ranking_rules),
+            typo_min_word_size: strings(&["4", "8"]),
+            typo_disabled_attributes: strings(&["tags"]),
+            ..defaults.clone()
+        };
+        assert!(tuned.diff(&up_to_date, &cfg).is_empty());
+        // Reset to the defaults if no longer tuned
+        assert_eq!(3, SETTINGS.diff(&up_to_date, &cfg).len());
+    }
+}
@@ -1,10 +1,17 @@
+use crate::db::hidden_song::{HiddenSongDao, IHiddenSongDao};
 use crate::db::song::{ISongDao, SongDao};
-use crate::db::CrudDao;
+use crate::db::song_tag::{ISongTagDao, SongTagAlias, SongTagDao};
+use crate::search::settings::{self, IndexSettings, TypoTolerance};
+use crate::search::indexer::{self, Chunk};
+use crate::db::user::{IUserDao, UserDao};
 use itertools::Itertools;
-use meilisearch_sdk::client::{Client, SwapIndexes};
+use meilisearch_sdk::client::Client;
 use meilisearch_sdk::errors::{Error, ErrorCode};
 use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
 use metrics::counter;
+use redis::aio::ConnectionManager;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::{query, PgPool};
 use std::collections::HashMap;
@@ -61,7 +68,9 @@ pub struct SearchQuery {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub filter: Option<String>,
-    pub sort_method: Option<SearchSortMethod>
+    pub sort_method: Option<SearchSortMethod>,
+    /// The names of the tags whose songs are left out, e.g. the blocked tags of the user
+    pub exclude_tags: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -81,6 +90,15 @@ impl SearchSortMethod {
             SearchSortMethod::PlayCountAsc => "play_count:asc",
         }
     }
+
+    fn to_fallback_sort(&self) -> &'static str {
+        match self {
+            SearchSortMethod::ReleaseTimeDesc => "release_time_desc",
+            SearchSortMethod::ReleaseTimeAsc => "release_time_asc",
+            SearchSortMethod::PlayCountDesc => "play_count_desc",
+            SearchSortMethod::PlayCountAsc => "play_count_asc",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,7 +134,8 @@ pub async fn search_songs(
 
     search_request.with_sort(&sort);
 
-    if let Some(ref filter) = query.filter {
+    let filter = combine_filters(query.filter.as_deref(), exclude_tags_filter(&query.exclude_tags).as_deref());
+    if let Some(ref filter) = filter {
         search_request.with_filter(filter);
     }
 
@@ -134,7 +153,107 @@ pub async fn search_songs(
     })
 }
 
-pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(), meilisearch_sdk::errors::Error> {
+/// The filter leaving out the songs with any of the tags, `None` if there are no tags
+fn exclude_tags_filter(tags: &[String]) -> Option<String> {
+    if tags.is_empty() {
+        return None;
+    }
+    let tags = tags.iter()
+        .map(|x| format!("\"{}\"", x.replace('\\', "\\\\").replace('"', "\\\"")))
+        .join(", ");
+    Some(format!("tags NOT IN [{}]", tags))
+}
+
+fn combine_filters(a: Option<&str>, b: Option<&str>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(format!("({}) AND ({})", a, b)),
+        (a, b) => a.or(b).map(|x| x.to_string()),
+    }
+}
+
+/// The least of a song for the suggestions while typing
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SongSuggestion {
+    pub id: i64,
+    pub display_id: String,
+    pub title: String,
+    pub artist: String,
+}
+
+/// Suggest the songs matching the partial query, only the fields of [SongSuggestion] are retrieved
+pub async fn suggest_songs(
+    client: &Client,
+    q: &str,
+    limit: usize,
+) -> Result<Vec<SongSuggestion>, meilisearch_sdk::errors::Error> {
+    let index = client.index("songs");
+    let result = index.search()
+        .with_query(q)
+        .with_limit(limit)
+        .with_attributes_to_retrieve(Selectors::Some(&["id", "display_id", "title", "artist"]))
+        .execute::<SongSuggestion>()
+        .await?;
+    Ok(result.hits.into_iter().map(|x| x.result).collect())
+}
+
+/// Search with MeiliSearch, or match the title in Postgres if MeiliSearch is unavailable.
+///
+/// Only the basic queries can fall back, returns `None` for the queries with filters. The excluded tags are
+/// dropped from the page in the fallback, which has no total hits anyway.
+pub async fn search_songs_or_fallback(
+    client: &Client,
+    pool: &PgPool,
+    query: &SearchQuery,
+) -> anyhow::Result<Option<SearchResult>> {
+    if let Some(result) = crate::search::guarded(search_songs(client, query)).await {
+        return Ok(Some(result));
+    }
+    if query.filter.is_some() || query.q.trim().is_empty() {
+        return Ok(None);
+    }
+
+    counter!("search_fallback_count", "index" => "songs").increment(1);
+    let start = std::time::Instant::now();
+    let limit = query.limit.unwrap_or(20);
+    let offset = query.offset.unwrap_or(0);
+    let sort = query.sort_method.as_ref().map(|x| x.to_fallback_sort()).unwrap_or_default();
+    let ids = SongDao::search_by_title(pool, query.q.trim(), sort, limit as i64, offset as i64).await?;
+    let hits = get_documents_batch(pool, &ids).await?
+        .into_iter()
+        .filter(|x| !x.tags.iter().any(|t| query.exclude_tags.contains(t)))
+        .collect();
+
+    Ok(Some(SearchResult {
+        hits,
+        query: query.q.clone(),
+        processing_time_ms: start.elapsed().as_millis() as u64,
+        hits_info: SearchResultHitsInfo {
+            total_hits: None,
+            limit,
+            offset,
+        },
+    }))
+}
+
+pub const SETTINGS: IndexSettings = IndexSettings {
+    index: "songs",
+    version: 2,
+    searchable: &["title", "subtitle", "artist", "origins", "origin_artists", "tags", "crew"],
+    filterable: &["tags", "creation_type", "uploader_uid", "release_time"],
+    sortable: &["play_count", "like_count", "release_time"],
+    // The popular songs go first among the equally relevant ones, mostly for the suggestions of a few characters
+    ranking_rules: &["words", "typo", "proximity", "attribute", "sort", "exactness", "play_count:desc"],
+    // The titles are short, so a typo is accepted earlier than the defaults. The tags are matched exactly,
+    // their variants are covered by the synonyms
+    typo_tolerance: TypoTolerance { one_typo: 4, two_typos: 8, disable_on_attributes: &["tags"] },
+};
+
+pub async fn setup_search_index(
+    client: &Client,
+    pg_pool: &PgPool,
+    redis: ConnectionManager,
+    dry_run: bool,
+) -> Result<(), meilisearch_sdk::errors::Error> {
     let exists = match client.get_index("songs").await {
         Ok(_) => { true }
         Err(Error::Meilisearch(err)) => {
@@ -150,13 +269,22 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
     if !exists {
         info!("Setting up songs index");
         setup_search_index_with_name(client, "songs").await?;
-
-        // Startup indexing
+        SETTINGS.save_applied_version(redis.clone()).await;
+    } else {
+        let index = client.index("songs");
+        SETTINGS.migrate(&index, redis.clone(), dry_run).await?;
+        // The configured synonyms may be changed
+        if !dry_run && let Err(e) = sync_tag_synonyms(&index, pg_pool).await {
+            warn!("Failed to sync the tag synonyms: {:?}", e);
+        }
+    }
+    // Index on the first startup, or resume the indexing interrupted by a restart
+    if !exists || indexer::has_checkpoint(&redis, "songs").await {
         tokio::spawn({
             let client = client.clone();
             let pool = pg_pool.clone();
             async move {
-                match fully_index_songs(&client, &pool).await {
+                match fully_index_songs(&client, &pool, redis).await {
                     Ok(_) => {}
                     Err(err) => {
                         error!("Failed to index songs: {:?}", err);
@@ -171,80 +299,80 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
 
 async fn setup_search_index_with_name(client: &Client, index_name: &str) -> Result<Index, meilisearch_sdk::errors::Error> {
     let index = client.index(index_name);
+    SETTINGS.apply(&index).await?;
+    Ok(index)
+}
 
-    // Set searchable attributes
-    index.set_searchable_attributes([
-        "title",
-        "subtitle",
-        "artist",
-        "origins",
-        "origin_artists",
-        "tags",
-        "crew",
-    ]).await?;
-
-    // Set filterable attributes
-    index.set_filterable_attributes([
-        "tags",
-        "creation_type",
-        "uploader_uid",
-        "release_time"
-    ]).await?;
-
-    // Set sortable attributes
-    index.set_sortable_attributes([
-        "play_count",
-        "like_count",
-        "release_time"
-    ]).await?;
+/// Feed the tag aliases and the configured synonyms to the synonyms of the index,
+/// so searching either spelling matches the songs
+pub async fn sync_tag_synonyms(index: &Index, pool: &PgPool) -> anyhow::Result<()> {
+    let aliases = SongTagDao::list_aliases(pool).await?;
+    let tag_ids = aliases.iter().map(|x| x.tag_id).unique().collect_vec();
+    let tag_names: HashMap<i64, String> = SongTagDao::list_by_ids(pool, &tag_ids).await?
+        .into_iter()
+        .map(|x| (x.id, x.name))
+        .collect();
+    let mut synonyms = build_tag_synonyms(&tag_names, &aliases);
+    let tokenization = settings::tokenization();
+    if tokenization.indexes.iter().any(|x| x == SETTINGS.index) {
+        merge_synonyms(&mut synonyms, tokenization.expand_synonyms());
+    }
+    if index.get_synonyms().await? != synonyms {
+        index.set_synonyms(&synonyms).await?;
+    }
+    Ok(())
+}
 
-    Ok(index)
+fn merge_synonyms(synonyms: &mut HashMap<String, Vec<String>>, other: HashMap<String, Vec<String>>) {
+    for (form, others) in other {
+        let existing = synonyms.entry(form).or_default();
+        for x in others {
+            if !existing.contains(&x) {
+                existing.push(x);
+            }
+        }
+    }
+}
+
+/// Every spelling of a tag is a synonym of all the others
+fn build_tag_synonyms(tag_names: &HashMap<i64, String>, aliases: &[SongTagAlias]) -> HashMap<String, Vec<String>> {
+    let mut synonyms = HashMap::new();
+    for (tag_id, aliases) in aliases.iter().into_group_map_by(|x| x.tag_id) {
+        let Some(name) = tag_names.get(&tag_id) else { continue };
+        let forms = std::iter::once(name.clone())
+            .chain(aliases.into_iter().map(|x| x.alias.clone()))
+            .collect_vec();
+        for form in &forms {
+            let others = forms.iter().filter(|x| *x != form).cloned().collect_vec();
+            synonyms.insert(form.clone(), others);
+        }
+    }
+    synonyms
 }
 
 // Schedule to execute fully indexing task
 async fn fully_index_songs(
     client: &Client,
     pool: &PgPool,
+    redis: ConnectionManager,
 ) -> anyhow::Result<()> {
     counter!("full_index_song_count").increment(1);
-
-    // 1. Take all songs from the database (it's best to get a snapshot)
-    // 2. Catch-up new changes
-    // 3. Indexing
-    // 4. Replace it with the new index
-    let time = chrono::Utc::now();
-    let new_index_name = format!("songs_{}", time.format("%Y%m%d%H%M%S"));
-
-    // How much RAM is it required to do this job?
-    let songs = SongDao::list(pool).await?;
-
-    let new_index = setup_search_index_with_name(client, &new_index_name).await?;
-
-    let chunks = songs.chunks(1024).collect::<Vec<_>>();
-
-    for (index, chunk) in chunks.iter().enumerate() {
-        info!("indexing chunk {} of {}", index, chunks.len());
-
-        let ids: Vec<_> = chunk.iter().map(|x| x.id).collect();
-        let documents = get_documents_batch(pool, &ids).await?;
-        info!("sync chunk {} to MeiliSearch: {:?}", index, documents.len());
-        let _ = new_index.add_documents(&documents, Some("id")).await?
-            .wait_for_completion(&client, None, None)
-            .await?;
-        info!("sync chunk {index} successfully");
-    }
-
-    info!("sync all chunk successfully, swapping indexes");
-    let _ = client.swap_indexes([&SwapIndexes {
-        indexes: ("songs".to_string(), new_index_name),
-        rename: None,
-    }]).await?
-        .wait_for_completion(&client, None, None)
-        .await?;
-    info!("swapping indexes successfully");
-    new_index.delete().await?;
+    indexer::fully_index(
+        client,
+        redis,
+        "songs",
+        |name| async move {
+            let index = setup_search_index_with_name(client, &name).await?;
+            sync_tag_synonyms(&index, pool).await?;
+            Ok(index)
+        },
+        |after_id, limit| async move {
+            let ids = SongDao::list_ids_after(pool, after_id, limit).await?;
+            let documents = get_documents_batch(pool, &ids).await?;
+            Ok(Chunk { documents, last_id: ids.last().copied() })
+        },
+    ).await?;
     counter!("full_index_song_success_count").increment(1);
-
     Ok(())
 }
 
@@ -253,9 +381,16 @@ pub async fn get_documents_batch(
     song_ids: &[i64]
 ) -> anyhow::Result<Vec<SongDocument>> {
     let mut documents: Vec<SongDocument> = vec![];
-    let songs: HashMap<i64, _> = SongDao::list_by_ids(pool, &song_ids).await?.into_iter()
+    let mut songs: HashMap<i64, _> = SongDao::list_by_ids(pool, song_ids).await?.into_iter()
         .map(|x| (x.id, x))
         .collect();
+    // Songs of banned users are not indexed
+    let uploader_ids = songs.values().map(|x| x.uploader_uid).unique().collect_vec();
+    let banned_ids = UserDao::list_banned_ids(pool, &uploader_ids).await?;
+    songs.retain(|_, x| !banned_ids.contains(&x.uploader_uid));
+    // Nor the hidden songs
+    let hidden_ids = HiddenSongDao::list_hidden_ids(pool, song_ids).await?;
+    songs.retain(|id, _| !hidden_ids.contains(id));
     let mut crews: HashMap<i64, _> = query!(
             "SELECT song_id AS \"song_id!\", u.username internal_username, c.uid, c.person_name external_username, c.role FROM song_production_crew c
                LEFT JOIN users u ON u.id = c.uid
@@ -292,7 +427,7 @@ pub async fn get_documents_batch(
         let song_info = if let Some(x) = songs.get(&id) {
             x
         } else {
-            warn!("Song not found for id: {}", id);
+            warn!("Song not found or hidden for id: {}", id);
             continue;
         };
 
@@ -341,4 +476,41 @@ pub async fn get_documents_batch(
         documents.push(doc)
     }
     Ok(documents)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_exclude_tags_filter() {
+        assert_eq!(None, exclude_tags_filter(&[]));
+        let tags = ["a".to_string(), r#"b"c\"#.to_string()];
+        assert_eq!(r#"tags NOT IN ["a", "b\"c\\"]"#, exclude_tags_filter(&tags).unwrap());
+        assert_eq!(Some("(x = 1) AND (y = 2)".to_string()), combine_filters(Some("x = 1"), Some("y = 2")));
+        assert_eq!(Some("y = 2".to_string()), combine_filters(None, Some("y = 2")));
+        assert_eq!(None, combine_filters(None, None));
+    }
+
+    fn alias(tag_id: i64, alias: &str) -> SongTagAlias {
+        SongTagAlias { id: 0, tag_id, alias: alias.to_string(), create_time: Utc::now() }
+    }
+
+    #[test]
+    fn test_build_tag_synonyms() {
+        let tag_names = HashMap::from([(1, "R&B".to_string()), (2, "摇滚".to_string())]);
+        let aliases = [alias(1, "RnB"), alias(1, "rhythm and blues"), alias(2, "rock"), alias(3, "orphan")];
+        let synonyms = build_tag_synonyms(&tag_names, &aliases);
+        assert_eq!(vec!["RnB", "rhythm and blues"], synonyms["R&B"]);
+        assert_eq!(vec!["R&B", "rhythm and blues"], synonyms["RnB"]);
+        assert_eq!(vec!["摇滚"], synonyms["rock"]);
+        assert!(!synonyms.contains_key("orphan"));
+
+        let mut synonyms = synonyms;
+        merge_synonyms(&mut synonyms, HashMap::from([
+            ("rock".to_string(), vec!["摇滚".to_string(), "摇滚乐".to_string()]),
+        ]));
+        assert_eq!(vec!["摇滚", "摇滚乐"], synonyms["rock"]);
+    }
+}
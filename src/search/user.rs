@@ -1,13 +1,16 @@
-use meilisearch_sdk::client::{Client, SwapIndexes};
+use meilisearch_sdk::client::Client;
 use meilisearch_sdk::errors::{Error, ErrorCode};
 use meilisearch_sdk::indexes::Index;
 use metrics::counter;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use tracing::{error, info, info_span, Instrument};
-use crate::db::CrudDao;
-use crate::db::user::UserDao;
+use crate::db::user::{IUserDao, User, UserDao};
+use crate::db::user_follow::{IUserFollowDao, UserFollowDao};
+use crate::search::settings::{IndexSettings, TypoTolerance};
+use crate::search::indexer::{self, Chunk};
 use crate::search::song::SearchResultHitsInfo;
+use redis::aio::ConnectionManager;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserDocument {
@@ -71,7 +74,40 @@ pub async fn update_user_document(client: &Client, document: UserDocument) -> an
     Ok(())
 }
 
-pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(), meilisearch_sdk::errors::Error> {
+/// Update the document of the user with the current follower count, banned users should be deleted instead
+pub async fn sync_user_document(client: &Client, pool: &PgPool, user: &User) -> anyhow::Result<()> {
+    let follower_count = UserFollowDao::count_followers(pool, user.id).await?;
+    update_user_document(client, UserDocument {
+        id: user.id,
+        avatar_url: user.avatar_url.clone(),
+        name: user.username.clone(),
+        follower_count,
+    }).await
+}
+
+pub async fn delete_user_document(client: &Client, user_id: i64) -> anyhow::Result<()> {
+    client.index("users")
+        .delete_document(user_id)
+        .await?;
+    Ok(())
+}
+
+pub const SETTINGS: IndexSettings = IndexSettings {
+    index: "users",
+    version: 1,
+    searchable: &["name"],
+    filterable: &[],
+    sortable: &["follower_count"],
+    ranking_rules: &[],
+    typo_tolerance: TypoTolerance::DEFAULT,
+};
+
+pub async fn setup_search_index(
+    client: &Client,
+    pg_pool: &PgPool,
+    redis: ConnectionManager,
+    dry_run: bool,
+) -> Result<(), meilisearch_sdk::errors::Error> {
     let exists = match client.get_index("users").await {
         Ok(_) => { true }
         Err(Error::Meilisearch(err)) => {
@@ -87,13 +123,17 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
     if !exists {
         info!("Setting up users index");
         setup_search_index_with_name(client, "users").await?;
-
-        // Startup indexing
+        SETTINGS.save_applied_version(redis.clone()).await;
+    } else {
+        SETTINGS.migrate(&client.index("users"), redis.clone(), dry_run).await?;
+    }
+    // Index on the first startup, or resume the indexing interrupted by a restart
+    if !exists || indexer::has_checkpoint(&redis, "users").await {
         tokio::spawn({
             let client = client.clone();
             let pool = pg_pool.clone();
             async move {
-                match fully_index_users(&client, &pool).await {
+                match fully_index_users(&client, &pool, redis).await {
                     Ok(_) => {}
                     Err(err) => {
                         error!("Failed to index users: {:?}", err);
@@ -108,52 +148,37 @@ pub async fn setup_search_index(client: &Client, pg_pool: &PgPool) -> Result<(),
 
 async fn setup_search_index_with_name(client: &Client, index_name: &str) -> Result<Index, meilisearch_sdk::errors::Error> {
     let index = client.index(index_name);
-
-    // Set searchable attributes
-    index.set_searchable_attributes(["name"]).await?;
-    // Set sortable attributes
-    index.set_sortable_attributes(["follower_count"]).await?;
+    SETTINGS.apply(&index).await?;
     Ok(index)
 }
 
 async fn fully_index_users(
     client: &Client,
     pool: &PgPool,
+    redis: ConnectionManager,
 ) -> anyhow::Result<()> {
     counter!("full_index_user_count").increment(1);
-    let users = UserDao::list(pool).await?;
-    let time = chrono::Utc::now();
-    let new_index_name = format!("users_{}", time.format("%Y%m%d%H%M%S"));
-    let new_index = setup_search_index_with_name(client, &new_index_name).await?;
-    let chunks = users.chunks(1024).collect::<Vec<_>>();
-
-    for (index, chunk) in chunks.iter().enumerate() {
-        info!("indexing chunk {} of {}", index, chunks.len());
-
-        let documents = chunk.iter().map(|x| UserDocument {
-            id: x.id,
-            name: x.username.clone(),
-            avatar_url: x.avatar_url.clone(),
-            follower_count: 0, // TODO: Count follower count
-        }).collect::<Vec<_>>();
-
-        info!("syncing chunk {} to MeiliSearch: {:?}", index, documents.len());
-        let _ = new_index.add_documents(&documents, Some("id")).await?
-            .wait_for_completion(&client, None, None)
-            .await?;
-        info!("sync chunk {index} successfully");
-    }
-
-    info!("sync all chunk successfully, swapping indexes");
-
-    let _ = client.swap_indexes([&SwapIndexes {
-        indexes: ("users".to_string(), new_index_name),
-        rename: None,
-    }]).await?
-        .wait_for_completion(&client, None, None);
-    info!("swapping indexes successfully");
-    new_index.delete().await?;
-
+    indexer::fully_index(
+        client,
+        redis,
+        "users",
+        |name| async move { Ok(setup_search_index_with_name(client, &name).await?) },
+        |after_id, limit| async move {
+            let users = UserDao::list_after_id(pool, after_id, limit).await?;
+            let last_id = users.last().map(|x| x.id);
+            // Banned users are not indexed
+            let users = users.into_iter().filter(|x| !x.is_banned).collect::<Vec<_>>();
+            let ids = users.iter().map(|x| x.id).collect::<Vec<_>>();
+            let follower_counts = UserFollowDao::count_followers_batch(pool, &ids).await?;
+            let documents = users.into_iter().map(|x| UserDocument {
+                follower_count: follower_counts.get(&x.id).copied().unwrap_or(0),
+                id: x.id,
+                name: x.username,
+                avatar_url: x.avatar_url,
+            }).collect();
+            Ok(Chunk { documents, last_id })
+        },
+    ).await?;
     counter!("full_index_user_success_count").increment(1);
     Ok(())
-}
\ No newline at end of file
+}
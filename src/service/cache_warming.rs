@@ -0,0 +1,108 @@
+use crate::config::Config;
+use crate::service::recommend_v2;
+use crate::service::recommend_v2::ANONYMOUS_RECOMMEND_GROUPS;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// The windows requested by clients by default, see `/song/recent_v2` and `/song/hot/weekly`
+const RECENT_LIMIT: i32 = 50;
+const HOT_DAY_DELTA: i64 = 7;
+const HOT_LIMIT: i64 = 50;
+/// Refreshed before the cache expires, the interval must be less than [recommend_v2::RECENT_CACHE_TTL_SECS]
+pub const RECENT_JOB: Job = Job {
+    name: "warm_recent_cache",
+    schedule: "*/4 * * * *",
+    max_jitter: Duration::from_secs(10),
+};
+/// Refreshed before the cache expires, the interval must be less than [recommend_v2::HOT_CACHE_TTL_SECS]
+pub const HOT_JOB: Job = Job {
+    name: "warm_hot_cache",
+    schedule: "*/30 * * * *",
+    max_jitter: Duration::from_secs(60),
+};
+
+/// Optional `cache_warming` section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheWarmingCfg {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool { true }
+
+impl Default for CacheWarmingCfg {
+    fn default() -> Self {
+        CacheWarmingCfg { enabled: default_enabled() }
+    }
+}
+
+impl CacheWarmingCfg {
+    /// Load the `cache_warming` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("cache_warming")?.is_some() {
+            config.get_and_parse("cache_warming")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Keep the front-page caches warm until cancelled.
+///
+/// Recent and hot songs are recomputed shortly before their caches expire, by one instance of the fleet per scheduled time.
+/// The daily recommendations of anonymous groups are only filled when missing, so they stay the same within a day.
+pub async fn run(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = CacheWarmingCfg::load(&state.config)?;
+    if !cfg.enabled {
+        return Ok(());
+    }
+    info!("Cache warming started");
+
+    let scheduler = Scheduler::from_state(&state)?;
+    let recent = scheduler.spawn(
+        &RECENT_JOB,
+        cancel_token.clone(),
+        {
+            let state = state.clone();
+            move || {
+                let state = state.clone();
+                async move {
+                    warm_recent(&state).await?;
+                    warm_recommend_anonymous(&state).await
+                }
+            }
+        },
+    )?;
+    let hot = scheduler.spawn(
+        &HOT_JOB,
+        cancel_token.clone(),
+        move || {
+            let state = state.clone();
+            async move { warm_hot(&state).await }
+        },
+    )?;
+
+    recent.await?;
+    hot.await?;
+    info!("Cache warming stopped");
+    Ok(())
+}
+
+async fn warm_recent(state: &AppState) -> anyhow::Result<()> {
+    recommend_v2::refresh_recent_songs(state.redis_conn.clone(), &state.sql_pool, None, RECENT_LIMIT, false).await
+}
+
+async fn warm_hot(state: &AppState) -> anyhow::Result<()> {
+    recommend_v2::refresh_hot_songs(&state.redis_conn, &state.sql_pool, HOT_DAY_DELTA, HOT_LIMIT).await
+}
+
+async fn warm_recommend_anonymous(state: &AppState) -> anyhow::Result<()> {
+    for group in 1..=ANONYMOUS_RECOMMEND_GROUPS {
+        recommend_v2::get_recommend(-group, state.red_lock.clone(), state.redis_conn.clone(), &state.sql_pool).await?;
+    }
+    Ok(())
+}
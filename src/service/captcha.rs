@@ -1,5 +1,6 @@
 use redis::AsyncCommands;
 use serde_json::json;
+use crate::service::test_mode;
 use crate::web::routes::auth::TurnstileCfg;
 
 const STATUS_INIT: &str = "0";
@@ -8,7 +9,9 @@ const STATUS_FAILURE: &str = "2";
 
 pub async fn generate_new_captcha(redis: &mut redis::aio::ConnectionManager) -> anyhow::Result<String> {
     let key = uuid::Uuid::new_v4().to_string();
-    let _: () = redis.set_ex(build_captcha_redis_key(&key), STATUS_INIT, 300).await?;
+    // The captchas are passed already in the test mode
+    let status = if test_mode::is_enabled() { STATUS_SUCCESS } else { STATUS_INIT };
+    let _: () = redis.set_ex(build_captcha_redis_key(&key), status, 300).await?;
     Ok(key)
 }
 
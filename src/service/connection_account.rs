@@ -1,5 +1,6 @@
 use crate::db::user_connection_accounts::{IUserConnectionAccountDao, UserConnectionAccount, UserConnectionAccountDao};
 use crate::util::bilibili;
+use crate::util::redis_health;
 use crate::util::redlock::RedLock;
 use anyhow::{anyhow, bail, Context};
 use chrono::Utc;
@@ -20,7 +21,7 @@ pub struct ConnectionAccount {
 }
 
 pub async fn list_connections(sql: &PgPool, mut redis: ConnectionManager, uid: i64, public: bool) -> anyhow::Result<Vec<ConnectionAccount>> {
-    let cache = get_connections_from_cache(&mut redis, uid, public).await?;
+    let cache = redis_health::cached(get_connections_from_cache(&mut redis, uid, public)).await.flatten();
     if let Some(cached) = cache {
         return Ok(cached);
     }
@@ -38,7 +39,7 @@ pub async fn list_connections(sql: &PgPool, mut redis: ConnectionManager, uid: i
         public: c.public,
     }).collect_vec();
 
-    save_connections_to_cache(&mut redis, uid, public, &mapped).await?;
+    redis_health::cached(save_connections_to_cache(&mut redis, uid, public, &mapped)).await;
     Ok(mapped)
 }
 
@@ -1,48 +1,138 @@
 use crate::common;
 use crate::config::Config;
 use crate::db::user::{IUserDao, UserDao};
+use crate::db::user_role::{IUserRoleDao, UserRole, UserRoleDao};
+use crate::db::CrudDao;
 use crate::util::redlock::RedLock;
+use crate::web::jwt::Claims;
 use crate::web::result::{CommonError, WebError};
 use crate::web::state::AppState;
 use anyhow::bail;
+use chrono::Utc;
 use metrics::counter;
 use redis::aio::ConnectionManager;
 use redis::AsyncTypedCommands;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::collections::HashSet;
-use std::time::Duration;
-use tracing::warn;
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub const ROLE_CONTRIBUTOR: &str = "contributor";
+
+const CONTRIBUTORS_KEY: &str = "contributors";
+const CONTRIBUTORS_LOCK: &str = "lock:contributors";
+/// Invalidated on every change through [grant_role] and [revoke_role], expires in case the table is edited directly
+const CONTRIBUTORS_CACHE_TTL_SECS: u64 = 3600;
+/// The contributors of the last rebuild, to find the users whose roles changed
+const LAST_CONTRIBUTORS_KEY: &str = "contributors:last";
+/// The demotions take effect on the other instances after it at most
+const ROLE_EPOCH_CACHE_TTL: Duration = Duration::from_secs(60);
+const ROLE_EPOCH_CACHE_CAPACITY: usize = 10000;
+
+static ROLE_EPOCH_CACHE: LazyLock<Mutex<HashMap<i64, (i64, Instant)>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn role_epoch_key(uid: i64) -> String {
+    format!("role_epoch:{uid}")
+}
+
+/// Optional `community` section of the config file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CommunityCfg {
+    /// The emails granted the contributor role at the startup while nobody has it, see [bootstrap]
+    #[serde(default)]
     pub contributors: Vec<String>,
 }
 
+impl CommunityCfg {
+    /// Load the `community` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("community")?.is_some() {
+            config.get_and_parse("community")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
 pub async fn ensure_contributor(
     state: &AppState,
-    uid: i64,
+    claims: &Claims,
 ) -> Result<(), WebError<CommonError>> {
-    let config = state.config.clone();
-    let pool = &state.sql_pool;
-    let redis = state.redis_conn.clone();
-    let is_contributor = check_contributor(&config, redis, &state.red_lock, pool, uid).await?;
-
-    if is_contributor {
+    if is_contributor(state, claims).await? {
         Ok(())
     } else {
         Err(common!("permission_denied", "You are not a contributor"))
     }
 }
 
+/// Whether the token holder is a contributor, by the roles in the token while its role epoch is current,
+/// so most requests skip looking up the contributors
+pub async fn is_contributor(state: &AppState, claims: &Claims) -> anyhow::Result<bool> {
+    if let Some(epoch) = claims.role_epoch
+        && epoch == cached_role_epoch(state.redis_conn.clone(), claims.uid()).await?
+    {
+        counter!("check_contributor_claims_hit_count").increment(1);
+        return Ok(claims.roles.iter().any(|x| x == ROLE_CONTRIBUTOR));
+    }
+    check_contributor(state.redis_conn.clone(), &state.red_lock, &state.sql_pool, claims.uid()).await
+}
+
+/// The roles to embed in the access token, and the role epoch they're derived at
+pub async fn current_roles(state: &AppState, uid: i64) -> anyhow::Result<(Vec<String>, i64)> {
+    // Read before the roles, so a change during the lookup makes the token stale instead of missed
+    let epoch = role_epoch(state.redis_conn.clone(), uid).await?;
+    let roles = UserRoleDao::list_by_user(&state.sql_pool, uid).await?;
+    Ok((roles, epoch))
+}
+
+async fn role_epoch(mut redis: ConnectionManager, uid: i64) -> anyhow::Result<i64> {
+    let value = redis.get(role_epoch_key(uid)).await?;
+    Ok(value.and_then(|x| x.parse().ok()).unwrap_or(0))
+}
+
+async fn cached_role_epoch(redis: ConnectionManager, uid: i64) -> anyhow::Result<i64> {
+    let now = Instant::now();
+    if let Some((epoch, expire)) = ROLE_EPOCH_CACHE.lock().unwrap().get(&uid) && *expire > now {
+        return Ok(*epoch);
+    }
+    let epoch = role_epoch(redis, uid).await?;
+    let mut cache = ROLE_EPOCH_CACHE.lock().unwrap();
+    if cache.len() >= ROLE_EPOCH_CACHE_CAPACITY {
+        cache.retain(|_, (_, expire)| *expire > now);
+        if cache.len() >= ROLE_EPOCH_CACHE_CAPACITY {
+            cache.clear();
+        }
+    }
+    cache.insert(uid, (epoch, now + ROLE_EPOCH_CACHE_TTL));
+    Ok(epoch)
+}
+
+/// Make the roles in the issued access tokens of the users stale, they're looked up again until the tokens are refreshed
+pub async fn bump_role_epochs(mut redis: ConnectionManager, uids: &[i64]) -> anyhow::Result<()> {
+    if uids.is_empty() {
+        return Ok(());
+    }
+    let mut pipe = redis::pipe();
+    for uid in uids {
+        pipe.incr(role_epoch_key(*uid), 1).ignore();
+    }
+    let _: () = pipe.query_async(&mut redis).await?;
+    let mut cache = ROLE_EPOCH_CACHE.lock().unwrap();
+    for uid in uids {
+        cache.remove(uid);
+    }
+    Ok(())
+}
+
 pub async fn check_contributor(
-    config: &Config,
     mut redis: ConnectionManager,
     red_lock: &RedLock,
     pool: &PgPool,
     uid: i64,
 ) -> anyhow::Result<bool> {
-    let contributors = redis.get("contributors").await?;
+    let contributors = redis.get(CONTRIBUTORS_KEY).await?;
     if let Some(contributors) = contributors {
         counter!("check_contributor_cache_hit_count").increment(1);
         let contributor_uids: Vec<i64> = serde_json::from_str(&contributors)?;
@@ -50,39 +140,117 @@ pub async fn check_contributor(
     } else {
         counter!("check_contributor_cache_miss_count").increment(1);
 
-        let lock = red_lock.lock_with_timeout("lock:contributors", Duration::from_secs(30)).await?;
+        let lock = red_lock.lock_with_timeout(CONTRIBUTORS_LOCK, Duration::from_secs(30)).await?;
         if lock.is_none() {
             counter!("check_contributor_lock_timeout_count").increment(1);
             bail!("Can't get lock")
         }
 
         // Check cache again
-        let contributors = redis.get("contributors").await?;
+        let contributors = redis.get(CONTRIBUTORS_KEY).await?;
         if let Some(contributors) = contributors {
             let contributor_uids: Vec<i64> = serde_json::from_str(&contributors)?;
-            if contributor_uids.contains(&uid) {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+            Ok(contributor_uids.contains(&uid))
         } else {
             // Get from source of truth
-            // TODO: Get from github repository
-            let cfg: CommunityCfg = config.get_and_parse("community")?;
-            let mut contributor_uids = HashSet::new();
-            for email in cfg.contributors {
-                if let Some(user) = UserDao::get_by_email(pool, &email).await? {
-                    contributor_uids.insert(user.id);
-                } else {
-                    warn!("Contributor {} was configured but not found in database", email);
-                }
-            }
-            redis.set("contributors", serde_json::to_string(&contributor_uids)?).await?;
-            if contributor_uids.contains(&uid) {
-                Ok(true)
-            } else {
-                Ok(false)
-            }
+            let contributor_uids: HashSet<i64> = UserRoleDao::list_user_ids_by_role(pool, ROLE_CONTRIBUTOR).await?
+                .into_iter()
+                .collect();
+            redis.set_ex(CONTRIBUTORS_KEY, serde_json::to_string(&contributor_uids)?, CONTRIBUTORS_CACHE_TTL_SECS).await?;
+
+            // The added and removed contributors get the roles in their tokens updated
+            let last: HashSet<i64> = match redis.get(LAST_CONTRIBUTORS_KEY).await? {
+                Some(x) => serde_json::from_str(&x)?,
+                None => HashSet::new(),
+            };
+            let changed: Vec<i64> = last.symmetric_difference(&contributor_uids).copied().collect();
+            bump_role_epochs(redis.clone(), &changed).await?;
+            redis.set(LAST_CONTRIBUTORS_KEY, serde_json::to_string(&contributor_uids)?).await?;
+            Ok(contributor_uids.contains(&uid))
+        }
+    }
+}
+
+/// The emails of the contributors, earliest granted first
+pub async fn contributor_emails(pool: &PgPool) -> sqlx::Result<Vec<String>> {
+    UserRoleDao::list_emails_by_role(pool, ROLE_CONTRIBUTOR).await
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RoleError {
+    #[error("Role {0} not found")]
+    RoleNotFound(String),
+    #[error("User {0} not found")]
+    UserNotFound(i64),
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Grant the role to the user, returns whether the user didn't have it
+pub async fn grant_role(state: &AppState, uid: i64, role: &str, granted_by: Option<i64>) -> Result<bool, RoleError> {
+    if !UserRoleDao::role_exists(&state.sql_pool, role).await? {
+        return Err(RoleError::RoleNotFound(role.to_string()));
+    }
+    if UserDao::get_by_id(&state.sql_pool, uid).await?.is_none() {
+        return Err(RoleError::UserNotFound(uid));
+    }
+    let granted = UserRoleDao::grant(&state.sql_pool, &UserRole {
+        user_id: uid,
+        role: role.to_string(),
+        granted_by,
+        create_time: Utc::now(),
+    }).await?;
+    if granted {
+        info!("Granted role {} to user {} by {:?}", role, uid, granted_by);
+        on_roles_changed(state, uid).await?;
+    }
+    Ok(granted)
+}
+
+/// Revoke the role from the user, returns whether the user had it
+pub async fn revoke_role(state: &AppState, uid: i64, role: &str) -> Result<bool, RoleError> {
+    if !UserRoleDao::role_exists(&state.sql_pool, role).await? {
+        return Err(RoleError::RoleNotFound(role.to_string()));
+    }
+    let revoked = UserRoleDao::revoke(&state.sql_pool, uid, role).await?;
+    if revoked {
+        info!("Revoked role {} from user {}", role, uid);
+        on_roles_changed(state, uid).await?;
+    }
+    Ok(revoked)
+}
+
+/// Drop the cached contributors and make the roles in the tokens of the user stale.
+///
+/// Done under the rebuilding lock, so a rebuild reading the roles before the change can't cache them after it.
+async fn on_roles_changed(state: &AppState, uid: i64) -> anyhow::Result<()> {
+    let mut redis = state.redis_conn.clone();
+    let lock = state.red_lock.lock_with_timeout(CONTRIBUTORS_LOCK, Duration::from_secs(30)).await?;
+    if lock.is_none() {
+        bail!("Can't get lock")
+    }
+    redis.del(CONTRIBUTORS_KEY).await?;
+    drop(lock);
+    bump_role_epochs(redis, &[uid]).await
+}
+
+/// Grant the contributor role to the configured contributors if nobody has it yet, so the first contributors
+/// can be set up before the API is usable. Later changes of the config are ignored, use the role API instead.
+pub async fn bootstrap(state: &AppState) -> anyhow::Result<()> {
+    let cfg = CommunityCfg::load(&state.config)?;
+    if cfg.contributors.is_empty()
+        || !UserRoleDao::list_user_ids_by_role(&state.sql_pool, ROLE_CONTRIBUTOR).await?.is_empty()
+    {
+        return Ok(());
+    }
+    for email in cfg.contributors {
+        if let Some(user) = UserDao::get_by_email(&state.sql_pool, &email).await? {
+            grant_role(state, user.id, ROLE_CONTRIBUTOR, None).await?;
+        } else {
+            warn!("Contributor {} was configured but not found in database", email);
         }
     }
-}
\ No newline at end of file
+    Ok(())
+}
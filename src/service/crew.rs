@@ -0,0 +1,100 @@
+use crate::config::Config;
+use crate::db::song::SongProductionCrew;
+use crate::db::user::{IUserDao, UserDao};
+use crate::service;
+use crate::service::email_delivery;
+use crate::service::mailer::{self, EmailConfig};
+use crate::service::notification_templates::NotificationTemplate;
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+/// Mark the credits of the registered users as pending, except the uploader and the users who already
+/// confirmed on the previous version of the song.
+///
+/// Returns the users to invite.
+pub fn mark_pending(
+    existing: &[SongProductionCrew],
+    uploader_uid: i64,
+    crew: &mut [SongProductionCrew],
+) -> Vec<i64> {
+    let confirmed: HashSet<i64> = existing.iter()
+        .filter(|x| !x.pending)
+        .filter_map(|x| x.uid)
+        .collect();
+    let pending_before: HashSet<i64> = existing.iter()
+        .filter(|x| x.pending)
+        .filter_map(|x| x.uid)
+        .collect();
+    for member in crew.iter_mut() {
+        member.pending = member.uid.is_some_and(|x| x != uploader_uid && !confirmed.contains(&x));
+    }
+    // The users still pending from the previous version were invited already
+    crew.iter()
+        .filter(|x| x.pending)
+        .filter_map(|x| x.uid)
+        .filter(|x| !pending_before.contains(x))
+        .unique()
+        .collect()
+}
+
+/// Mail the invitations to confirm the credits
+pub async fn send_invitations(
+    config: &Config,
+    pool: &PgPool,
+    song_display_id: &str,
+    song_title: &str,
+    uploader_name: &str,
+    uids: &[i64],
+) -> anyhow::Result<()> {
+    if uids.is_empty() {
+        return Ok(());
+    }
+    let email_cfg: EmailConfig = config.get_and_parse("email")?;
+    for user in UserDao::list_by_ids(pool, uids).await? {
+        email_delivery::track(
+            pool,
+            email_delivery::TYPE_CREW_INVITATION,
+            &user.email,
+            mailer::send_template(&email_cfg, service::user::email_lang(&user), &user.email, &NotificationTemplate::CrewInvitation {
+                user_name: user.username.clone(),
+                song_display_id: song_display_id.to_string(),
+                song_title: song_title.to_string(),
+                uploader_name: uploader_name.to_string(),
+            }),
+        ).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(uid: Option<i64>, pending: bool) -> SongProductionCrew {
+        SongProductionCrew {
+            id: 0,
+            song_id: 1,
+            role: "composer".to_string(),
+            uid,
+            person_name: Some("name".to_string()),
+            pending,
+        }
+    }
+
+    #[test]
+    fn test_mark_pending() {
+        let existing = vec![member(Some(2), false), member(Some(3), true)];
+        let mut crew = vec![
+            member(Some(1), false),
+            member(Some(2), false),
+            member(Some(3), false),
+            member(Some(4), false),
+            member(Some(4), false),
+            member(None, false),
+        ];
+        let invited = mark_pending(&existing, 1, &mut crew);
+        assert_eq!(vec![false, false, true, true, true, false], crew.iter().map(|x| x.pending).collect_vec());
+        assert_eq!(vec![4], invited);
+    }
+}
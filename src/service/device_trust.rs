@@ -0,0 +1,55 @@
+//! Trusting a logged in device, so it can log in again without the captcha and the 2FA.
+//!
+//! Trusting a device issues a random token kept by the device, and only its SHA-256 is stored on the refresh
+//! token row of the device. The login with the token moves the trust to the new row, and the trust is gone
+//! when the device is logged out or [TRUST_TTL] passes.
+
+use crate::db::refresh_token::{IRefreshTokenDao, RefreshToken, RefreshTokenDao};
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::PgExecutor;
+
+/// The device has to be trusted again after it
+pub const TRUST_TTL: TimeDelta = TimeDelta::days(90);
+pub const MAX_NAME_CHARS: usize = 64;
+
+pub fn generate_token() -> String {
+    hex::encode(rand::random::<[u8; 32]>())
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(openssl::sha::sha256(token.trim().as_bytes()))
+}
+
+/// Get the trusted device of the user by the token, `None` if it's invalid, expired or logged out
+pub async fn get_trusted_device<'e>(
+    executor: impl PgExecutor<'e>,
+    uid: i64,
+    token: &str,
+    now: DateTime<Utc>,
+) -> sqlx::Result<Option<RefreshToken>> {
+    RefreshTokenDao::get_trusted(executor, uid, &hash_token(token), now - TRUST_TTL).await
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    name.trim().chars().count() <= MAX_NAME_CHARS && !name.trim().chars().any(char::is_control)
+}
+
+/// The trimmed name, `None` to clear it
+pub fn normalize_name(name: &str) -> Option<&str> {
+    Some(name.trim()).filter(|x| !x.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(Some("My phone"), normalize_name("  My phone "));
+        assert_eq!(None, normalize_name("   "));
+        assert!(is_valid_name(&format!(" {} ", "x".repeat(MAX_NAME_CHARS))));
+        assert!(!is_valid_name(&"x".repeat(MAX_NAME_CHARS + 1)));
+        assert!(!is_valid_name("a\nb"));
+        assert_eq!(hash_token("abc"), hash_token(" abc\n"));
+    }
+}
@@ -0,0 +1,253 @@
+use crate::config::Config;
+use crate::db::email_delivery::{self, EmailDelivery, EmailDeliveryDao, IEmailDeliveryDao};
+use crate::db::CrudDao;
+use crate::service::mailer::transport::DeliveryReceipt;
+use crate::util::redis_health;
+use chrono::Utc;
+use metrics::counter;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+pub const TYPE_VERIFICATION_CODE: &str = "verification_code";
+pub const TYPE_REVIEW_RESULT: &str = "review_result";
+pub const TYPE_REVIEW_UPDATE: &str = "review_update";
+pub const TYPE_CREW_INVITATION: &str = "crew_invitation";
+pub const TYPE_MAGIC_LINK: &str = "magic_link";
+
+/// Longer than any retry of a review
+const REVIEW_RESULT_DEDUP_TTL_SECS: u64 = 7 * 24 * 3600;
+
+/// The address is only stored as a hash
+pub fn recipient_hash(email: &str) -> String {
+    hex::encode(openssl::sha::sha256(email.trim().to_lowercase().as_bytes()))
+}
+
+/// Await the sending and record the delivery, the error of the sending is returned as is.
+///
+/// Failing to record is only logged, since the mail may have been sent.
+pub async fn track(
+    pool: &PgPool,
+    email_type: &str,
+    to: &str,
+    send: impl Future<Output = anyhow::Result<Option<DeliveryReceipt>>>,
+) -> anyhow::Result<()> {
+    let result = send.await;
+    let (provider, message_id, status, detail) = match &result {
+        // Sending is disabled
+        Ok(None) => return Ok(()),
+        Ok(Some(receipt)) => (Some(receipt.provider.clone()), receipt.message_id.clone(), email_delivery::STATUS_SENT, None),
+        Err(e) => (None, None, email_delivery::STATUS_FAILED, Some(e.to_string())),
+    };
+    let now = Utc::now();
+    let delivery = EmailDelivery {
+        id: 0,
+        email_type: email_type.to_string(),
+        recipient_hash: recipient_hash(to),
+        provider,
+        provider_message_id: message_id,
+        status: status.to_string(),
+        status_detail: detail,
+        create_time: now,
+        update_time: now,
+    };
+    if let Err(e) = EmailDeliveryDao::insert(pool, &delivery).await {
+        warn!("Failed to record email delivery: {:?}", e);
+    }
+    result.map(|_| ())
+}
+
+fn review_result_key(review_id: i64, status: i32) -> String {
+    format!("email:review_result:{}:{}", review_id, status)
+}
+
+/// Like [track], but the result email of a review in a status is sent only once, so the retried approvals and
+/// rejections don't send it again. The claim is released if the sending fails, so the retry can send it.
+///
+/// It's sent anyway if Redis is unavailable, a duplicate is better than a missing result.
+pub async fn track_review_result(
+    pool: &PgPool,
+    mut redis: ConnectionManager,
+    review_id: i64,
+    status: i32,
+    to: &str,
+    send: impl Future<Output = anyhow::Result<Option<DeliveryReceipt>>>,
+) -> anyhow::Result<()> {
+    let key = review_result_key(review_id, status);
+    let claimed: Option<bool> = redis_health::cached(redis.set_options(
+        &key, 1,
+        SetOptions::default().conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(REVIEW_RESULT_DEDUP_TTL_SECS)),
+    )).await;
+    if claimed == Some(false) {
+        info!(review_id, status, "Suppressed the duplicated review result email");
+        counter!("email_duplicate_suppressed_count", "type" => TYPE_REVIEW_RESULT).increment(1);
+        return Ok(());
+    }
+
+    let result = track(pool, TYPE_REVIEW_RESULT, to, send).await;
+    if result.is_err() && claimed == Some(true) {
+        redis_health::cached(redis.del::<_, ()>(&key)).await;
+    }
+    result
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SendBlock {
+    /// The last code bounced, the address is probably mistyped
+    Bounced,
+    /// The user marked our mail as spam, sending again hurts the reputation
+    Complained,
+}
+
+/// Check whether a verification code can be sent to the address.
+///
+/// The codes per hour are limited by [crate::service::verification_code::hourly_limited] instead.
+pub async fn check_suppressed(pool: &PgPool, email: &str) -> anyhow::Result<Option<SendBlock>> {
+    let hash = recipient_hash(email);
+    let Some(latest) = EmailDeliveryDao::get_latest(pool, &hash, TYPE_VERIFICATION_CODE).await? else {
+        return Ok(None);
+    };
+    Ok(match latest.status.as_str() {
+        email_delivery::STATUS_BOUNCED => Some(SendBlock::Bounced),
+        email_delivery::STATUS_COMPLAINED => Some(SendBlock::Complained),
+        _ => None,
+    })
+}
+
+/// Optional `email_webhook` section of the config file, the webhook is disabled if it's absent.
+///
+/// ```yaml
+/// email_webhook:
+///   token: 12345678
+/// ```
+///
+/// The provider should call `/email/webhook` with the `Authorization: Bearer {token}` header. It's not accepted in
+/// the query, which ends up in the access logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailWebhookCfg {
+    pub token: String,
+}
+
+impl EmailWebhookCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("email_webhook")?.is_some() {
+            Ok(Some(config.get_and_parse("email_webhook")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn verify(&self, token: &str) -> bool {
+        self.token.len() == token.len() && openssl::memcmp::eq(self.token.as_bytes(), token.as_bytes())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryEvent {
+    pub message_id: String,
+    pub status: &'static str,
+    pub detail: Option<String>,
+}
+
+/// Accepts the SendGrid event webhook, and a generic format for the relays of the SMTP providers
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EventBatch {
+    Sendgrid(Vec<SendgridEvent>),
+    Generic(Vec<GenericEvent>),
+}
+
+#[derive(Deserialize)]
+struct SendgridEvent {
+    event: String,
+    sg_message_id: String,
+    #[serde(rename = "type")]
+    bounce_type: Option<String>,
+    reason: Option<String>,
+}
+
+/// `{"message_id": "<...>", "status": "bounced", "detail": "550 No such user"}`
+#[derive(Deserialize)]
+struct GenericEvent {
+    message_id: String,
+    status: String,
+    detail: Option<String>,
+}
+
+/// Parse the events in the body, the events other than deliveries, bounces and complaints are skipped
+pub fn parse_events(body: &[u8]) -> anyhow::Result<Vec<DeliveryEvent>> {
+    let batch: EventBatch = serde_json::from_slice(body)?;
+    let events = match batch {
+        EventBatch::Sendgrid(events) => events.into_iter().filter_map(|x| {
+            let status = match (x.event.as_str(), x.bounce_type.as_deref()) {
+                ("delivered", _) => email_delivery::STATUS_DELIVERED,
+                // Blocked is a temporary rejection
+                ("bounce", Some("blocked")) | ("dropped", _) => email_delivery::STATUS_FAILED,
+                ("bounce", _) => email_delivery::STATUS_BOUNCED,
+                ("spamreport", _) => email_delivery::STATUS_COMPLAINED,
+                _ => return None,
+            };
+            // The `sg_message_id` is the `X-Message-Id` of the send API with a filter suffix
+            let message_id = x.sg_message_id.split(".filter").next().unwrap_or_default().to_string();
+            Some(DeliveryEvent { message_id, status, detail: x.reason })
+        }).collect(),
+        EventBatch::Generic(events) => events.into_iter().filter_map(|x| {
+            let status = match x.status.as_str() {
+                "delivered" => email_delivery::STATUS_DELIVERED,
+                "bounced" => email_delivery::STATUS_BOUNCED,
+                "complained" => email_delivery::STATUS_COMPLAINED,
+                "failed" => email_delivery::STATUS_FAILED,
+                _ => return None,
+            };
+            Some(DeliveryEvent { message_id: x.message_id, status, detail: x.detail })
+        }).collect(),
+    };
+    Ok(events)
+}
+
+/// Update the deliveries by the events, returns the number of updated deliveries
+pub async fn ingest(pool: &PgPool, events: &[DeliveryEvent]) -> anyhow::Result<u64> {
+    let mut updated = 0;
+    for event in events {
+        counter!("email_delivery_event_count", "status" => event.status).increment(1);
+        updated += EmailDeliveryDao::update_status_by_message_id(pool, &event.message_id, event.status, event.detail.as_deref()).await?;
+    }
+    info!("Ingested {} email delivery events, {} deliveries updated", events.len(), updated);
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let sendgrid = br#"[
+            {"email":"a@example.com","event":"bounce","type":"bounce","reason":"550 No such user","sg_message_id":"abc.filter0001.1.2"},
+            {"email":"a@example.com","event":"bounce","type":"blocked","sg_message_id":"def.filter0001.1.2"},
+            {"email":"a@example.com","event":"open","sg_message_id":"ghi.filter0001.1.2"},
+            {"email":"a@example.com","event":"spamreport","sg_message_id":"jkl"}
+        ]"#;
+        let events = parse_events(sendgrid).unwrap();
+        assert_eq!(3, events.len());
+        assert_eq!(DeliveryEvent {
+            message_id: "abc".to_string(),
+            status: email_delivery::STATUS_BOUNCED,
+            detail: Some("550 No such user".to_string()),
+        }, events[0]);
+        assert_eq!(email_delivery::STATUS_FAILED, events[1].status);
+        assert_eq!(email_delivery::STATUS_COMPLAINED, events[2].status);
+
+        let generic = br#"[{"message_id":"<x@example.com>","status":"bounced"}]"#;
+        let events = parse_events(generic).unwrap();
+        assert_eq!("<x@example.com>", events[0].message_id);
+    }
+
+    #[test]
+    fn test_recipient_hash() {
+        assert_eq!(recipient_hash("A@Example.com "), recipient_hash("a@example.com"));
+    }
+}
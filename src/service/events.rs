@@ -0,0 +1,226 @@
+//! The events pushed to the online users, see [crate::web::routes::events].
+//!
+//! The events are published to a Redis channel, so the users connected to any instance receive them. Each instance
+//! keeps a single subscription to the channel in [run_subscriber], and fans the events out to the connections of
+//! the recipient only, at most [MAX_CONNECTIONS_PER_USER] of them. The events are not persisted, the clients should
+//! reload the lists after reconnecting.
+//!
+//! Since `EventSource` can't send the authorization header, the connection is authorized by a [EventsTicket]
+//! instead, created with the access token right before connecting.
+
+use crate::util::redis_health;
+use futures::StreamExt;
+use metrics::counter;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+const EVENTS_CHANNEL: &str = "events";
+/// The events of a user buffered for the slow connections, the older ones are dropped for them
+const BUFFER_SIZE: usize = 64;
+/// The connections of a user on an instance, e.g. the tabs of the browser
+pub const MAX_CONNECTIONS_PER_USER: usize = 8;
+/// How long a ticket is valid before connecting
+pub const TICKET_TTL_SECS: u64 = 60;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// `data`: `{"id": 1, "type": "follow", "actor_uid": 2, "data": {}, "create_time": "..."}`, the created notification,
+/// see [crate::service::notification]
+pub const EVENT_NOTIFICATION: &str = "notification";
+/// `data`: `{"review_id": 1, "review_type": 0, "song_display_id": "JM-AAA-001", "status": 1}`
+pub const EVENT_REVIEW_STATUS: &str = "review_status";
+/// `data`: `{"follower_uid": 1, "followed": true}`, false if unfollowed
+pub const EVENT_FOLLOWER: &str = "follower";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserEvent {
+    /// The recipient
+    pub uid: i64,
+    pub event: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Default)]
+struct Hub {
+    /// The users connected to this instance, removed once all their connections are closed
+    senders: Mutex<HashMap<i64, broadcast::Sender<Arc<UserEvent>>>>,
+    /// Cancelled on shutdown to end the connections
+    cancel_token: CancellationToken,
+}
+
+static HUB: LazyLock<Hub> = LazyLock::new(Hub::default);
+
+impl Hub {
+    fn subscribe(&'static self, uid: i64) -> Option<Subscription> {
+        let mut senders = self.senders.lock().unwrap();
+        let sender = senders.entry(uid).or_insert_with(|| broadcast::channel(BUFFER_SIZE).0);
+        if sender.receiver_count() >= MAX_CONNECTIONS_PER_USER {
+            return None;
+        }
+        Some(Subscription {
+            hub: self,
+            uid,
+            receiver: sender.subscribe(),
+            cancel_token: self.cancel_token.clone(),
+        })
+    }
+
+    fn dispatch(&self, event: UserEvent) {
+        if let Some(sender) = self.senders.lock().unwrap().get(&event.uid) {
+            // The connections may be closed meanwhile
+            _ = sender.send(Arc::new(event));
+        }
+    }
+}
+
+/// A connection receiving the events of a user, counted until it's dropped
+pub struct Subscription {
+    hub: &'static Hub,
+    uid: i64,
+    pub receiver: broadcast::Receiver<Arc<UserEvent>>,
+    /// Cancelled on shutdown
+    pub cancel_token: CancellationToken,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut senders = self.hub.senders.lock().unwrap();
+        // The receiver of this connection is not dropped yet
+        if senders.get(&self.uid).is_some_and(|x| x.receiver_count() <= 1) {
+            senders.remove(&self.uid);
+        }
+    }
+}
+
+/// Receive the events of the user on this instance, `None` if they have [MAX_CONNECTIONS_PER_USER] already
+pub fn subscribe(uid: i64) -> Option<Subscription> {
+    HUB.subscribe(uid)
+}
+
+/// Send the event to the connections of the recipient on this instance
+pub(crate) fn dispatch(event: UserEvent) {
+    HUB.dispatch(event)
+}
+
+/// Authorizes a connection once, see [create_ticket]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventsTicket {
+    pub uid: i64,
+    /// The unix seconds when the access token creating it expires, so does the connection
+    pub expire_at: i64,
+}
+
+/// Create a ticket valid for [TICKET_TTL_SECS]
+pub async fn create_ticket(redis: &mut ConnectionManager, value: &EventsTicket) -> anyhow::Result<String> {
+    let ticket = hex::encode(rand::random::<[u8; 16]>());
+    let _: () = redis.set_ex(get_ticket_key(&ticket), serde_json::to_string(value)?, TICKET_TTL_SECS).await?;
+    Ok(ticket)
+}
+
+/// Returns `None` if it's used or expired
+pub async fn consume_ticket(redis: &mut ConnectionManager, ticket: &str) -> anyhow::Result<Option<EventsTicket>> {
+    let saved: Option<String> = redis.get_del(get_ticket_key(ticket)).await?;
+    Ok(saved.map(|x| serde_json::from_str(&x)).transpose()?)
+}
+
+fn get_ticket_key(ticket: &str) -> String {
+    format!("events:ticket:{ticket}")
+}
+
+/// Push the event to the user wherever they're connected.
+///
+/// It's best-effort, failing to publish is only logged since the clients reload the lists anyway.
+pub async fn publish(redis: &mut ConnectionManager, uid: i64, event: &str, data: serde_json::Value) {
+    let event = UserEvent { uid, event: event.to_string(), data };
+    let payload = match serde_json::to_string(&event) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("Failed to serialize the {} event: {:?}", event.event, e);
+            return;
+        }
+    };
+    let published: Option<()> = redis_health::cached(redis.publish(EVENTS_CHANNEL, payload)).await;
+    if published.is_none() {
+        counter!("event_publish_failed_count", "event" => event.event).increment(1);
+    }
+}
+
+/// Relay the events from Redis to the connections on this instance until cancelled, reconnecting on errors
+pub async fn run_subscriber(client: redis::Client, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    loop {
+        tokio::select! {
+            result = relay(&client) => {
+                warn!("The event subscription is lost, reconnecting: {:?}", result);
+            }
+            _ = cancel_token.cancelled() => break,
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+            _ = cancel_token.cancelled() => break,
+        }
+    }
+    HUB.cancel_token.cancel();
+    info!("Event subscriber stopped");
+    Ok(())
+}
+
+/// Returns when the subscription ends
+async fn relay(client: &redis::Client) -> anyhow::Result<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(EVENTS_CHANNEL).await?;
+    info!("Subscribed to the events");
+    let mut messages = pubsub.into_on_message();
+    while let Some(msg) = messages.next().await {
+        let event = msg.get_payload::<String>().map_err(anyhow::Error::from)
+            .and_then(|x| serde_json::from_str::<UserEvent>(&x).map_err(anyhow::Error::from));
+        match event {
+            Ok(event) => dispatch(event),
+            Err(e) => warn!("Failed to decode the event: {:?}", e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_fan_out_by_user() {
+        let hub: &'static Hub = Box::leak(Box::default());
+        let event = |uid: i64| UserEvent { uid, event: EVENT_FOLLOWER.to_string(), data: json!(uid) };
+        let mut first = hub.subscribe(1).unwrap();
+        let mut second = hub.subscribe(2).unwrap();
+        // Nobody is connected
+        hub.dispatch(event(3));
+        hub.dispatch(event(2));
+        hub.dispatch(event(1));
+        assert_eq!(1, first.receiver.recv().await.unwrap().uid);
+        assert_eq!(2, second.receiver.recv().await.unwrap().uid);
+        assert!(first.receiver.try_recv().is_err());
+        assert!(!hub.senders.lock().unwrap().contains_key(&3));
+
+        drop(second);
+        assert!(!hub.senders.lock().unwrap().contains_key(&2));
+        assert!(hub.senders.lock().unwrap().contains_key(&1));
+    }
+
+    #[test]
+    fn test_max_connections() {
+        let hub: &'static Hub = Box::leak(Box::default());
+        let mut connections = (0..MAX_CONNECTIONS_PER_USER).map(|_| hub.subscribe(1).unwrap()).collect::<Vec<_>>();
+        assert!(hub.subscribe(1).is_none());
+        // Another user is not affected
+        assert!(hub.subscribe(2).is_some());
+
+        connections.pop();
+        assert!(hub.subscribe(1).is_some());
+    }
+}
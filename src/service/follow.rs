@@ -0,0 +1,72 @@
+use crate::db::user::UserDao;
+use crate::db::CrudDao;
+use crate::db::user_follow::{IUserFollowDao, UserFollow, UserFollowDao};
+use crate::search;
+use crate::service::{events, notification, user};
+use crate::web::state::AppState;
+use chrono::Utc;
+use serde_json::json;
+use tracing::warn;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FollowError {
+    #[error("You can't follow yourself")]
+    FollowSelf,
+    #[error("User {0} not found")]
+    UserNotFound(i64),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Follow the user, returns false if it's followed already.
+///
+/// Banned users can't be followed, but the existing followers are kept for the unbanning.
+pub async fn follow(state: &AppState, follower_id: i64, followee_id: i64) -> Result<bool, FollowError> {
+    if follower_id == followee_id {
+        return Err(FollowError::FollowSelf);
+    }
+    match UserDao::get_by_id(&state.sql_pool, followee_id).await? {
+        Some(x) if !x.is_banned => {}
+        _ => return Err(FollowError::UserNotFound(followee_id)),
+    }
+    let inserted = UserFollowDao::insert(&state.sql_pool, &UserFollow {
+        follower_id,
+        followee_id,
+        create_time: Utc::now(),
+    }).await?;
+    if inserted {
+        on_follower_changed(state, followee_id, follower_id, true).await;
+        notification::notify(state, &[followee_id], notification::TYPE_FOLLOW, Some(follower_id), json!({})).await;
+    }
+    Ok(inserted)
+}
+
+/// Returns false if it's not followed
+pub async fn unfollow(state: &AppState, follower_id: i64, followee_id: i64) -> sqlx::Result<bool> {
+    let deleted = UserFollowDao::delete(&state.sql_pool, follower_id, followee_id).await?;
+    if deleted {
+        on_follower_changed(state, followee_id, follower_id, false).await;
+    }
+    Ok(deleted)
+}
+
+/// Refresh the follower count in the profile cache and the search index, the failures are only logged
+/// since the follow itself is saved, and the count is corrected by the next update or full indexing.
+async fn on_follower_changed(state: &AppState, uid: i64, follower_uid: i64, followed: bool) {
+    let data = json!({ "follower_uid": follower_uid, "followed": followed });
+    events::publish(&mut state.redis_conn.clone(), uid, events::EVENT_FOLLOWER, data).await;
+    if let Err(e) = user::evict_profile_cache(state.redis_conn.clone(), uid).await {
+        warn!("Failed to evict the profile cache of user {}: {:?}", uid, e);
+    }
+    let result = async {
+        if let Some(user) = UserDao::get_by_id(&state.sql_pool, uid).await?
+            && !user.is_banned
+        {
+            search::user::sync_user_document(&state.meilisearch, &state.sql_pool, &user).await?;
+        }
+        anyhow::Ok(())
+    }.await;
+    if let Err(e) = result {
+        warn!("Failed to sync the search document of user {}: {:?}", uid, e);
+    }
+}
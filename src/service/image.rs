@@ -0,0 +1,433 @@
+use crate::common;
+use crate::config::Config;
+use crate::file_hosting::{FileHost, UploadOptions, UploadResult};
+use crate::service::upload;
+use crate::web::limits::LimitsCfg;
+use crate::web::result::{CommonError, WebError};
+use anyhow::anyhow;
+use bytes::Bytes;
+use image::codecs::gif::GifDecoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{PngDecoder, PngEncoder};
+use image::codecs::webp::WebPDecoder;
+use image::imageops::{self, FilterType};
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits, RgbaImage};
+use metrics::histogram;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::time::Instant;
+use tracing::info;
+
+/// The max dimension of a source image, to avoid decompression bombs.
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+/// Optional `image` section of the config file.
+///
+/// ```yaml
+/// image:
+///   output_format: webp # webp | jpeg | png
+///   animated: first_frame # first_frame | reject
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageCfg {
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    #[serde(default)]
+    pub animated: AnimationPolicy,
+}
+
+impl ImageCfg {
+    /// Load the `image` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("image")?.is_some() {
+            config.get_and_parse("image")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Webp,
+    Jpeg,
+    Png,
+}
+
+impl OutputFormat {
+    pub fn ext(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Png => "png",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+        }
+    }
+}
+
+/// How to deal with animated GIF/WebP/APNG images
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationPolicy {
+    /// Only keep the first frame
+    #[default]
+    FirstFrame,
+    /// Reject animated images with `ProcessError::Animated`
+    Reject,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum ResizeType {
+    Crop, Fit, Exact
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageProcessOptions {
+    /// Max size of the source image in bytes, the `image_max_bytes` advertised by `/bootstrap` for the uploads
+    pub max_size: usize,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub resize_type: ResizeType,
+    pub quality: f32,
+    pub format: OutputFormat,
+    pub animated: AnimationPolicy,
+}
+
+impl ImageProcessOptions {
+    pub fn avatar(cfg: &ImageCfg, limits: &LimitsCfg) -> Self {
+        Self::new(cfg, limits, 256, ResizeType::Crop, 80f32)
+    }
+
+    pub fn song_cover(cfg: &ImageCfg, limits: &LimitsCfg) -> Self {
+        Self::new(cfg, limits, 1024, ResizeType::Fit, 90f32)
+    }
+
+    pub fn playlist_cover(cfg: &ImageCfg, limits: &LimitsCfg) -> Self {
+        Self::new(cfg, limits, 512, ResizeType::Crop, 80f32)
+    }
+
+    pub fn post_image(cfg: &ImageCfg, limits: &LimitsCfg) -> Self {
+        Self::new(cfg, limits, 512, ResizeType::Fit, 85f32)
+    }
+
+    fn new(cfg: &ImageCfg, limits: &LimitsCfg, max_dimension: u32, resize_type: ResizeType, quality: f32) -> Self {
+        ImageProcessOptions {
+            max_size: limits.image_max_bytes,
+            max_width: max_dimension,
+            max_height: max_dimension,
+            resize_type,
+            quality,
+            format: cfg.output_format,
+            animated: cfg.animated,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProcessError {
+    #[error("the image is larger than {max_size} bytes")]
+    TooLarge { max_size: usize },
+    #[error("invalid image")]
+    InvalidImage,
+    #[error("the image format is unsupported")]
+    UnsupportedFormat,
+    #[error("animated images are not allowed")]
+    Animated,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl ProcessError {
+    pub fn into_web_error(self) -> WebError<CommonError> {
+        match self {
+            ProcessError::TooLarge { max_size } => common!("image_too_large", "Image size must be less than {}MB", max_size / 1024 / 1024),
+            ProcessError::InvalidImage | ProcessError::UnsupportedFormat => common!("invalid_image", "The image is not supported"),
+            ProcessError::Animated => common!("animated_image_not_allowed", "Animated images are not allowed"),
+            ProcessError::Other(e) => WebError::Internal(e),
+        }
+    }
+}
+
+pub struct ProcessedImage {
+    pub data: Vec<u8>,
+    pub format: OutputFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decode, normalize and re-encode an uploaded image.
+///
+/// Only the pixels are carried over to the output, so EXIF (including GPS), XMP and ICC metadata are
+/// always stripped. The EXIF orientation is applied to the pixels before it gets dropped.
+pub fn process(bytes: &[u8], options: &ImageProcessOptions) -> Result<ProcessedImage, ProcessError> {
+    let start = Instant::now();
+    let image = decode(bytes, options)?;
+
+    let resized = resize(image, options.max_width, options.max_height, options.resize_type);
+    let (width, height) = (resized.width(), resized.height());
+    let data = encode(resized, options.format, options.quality)?;
+
+    info!("Image processing took {:?}, size from {} to {}", start.elapsed(), bytes.len(), data.len());
+    histogram!("image_process_duration_secs").record(start.elapsed().as_secs_f64());
+
+    Ok(ProcessedImage {
+        data,
+        format: options.format,
+        width,
+        height,
+    })
+}
+
+/// Compose a 2x2 collage of the first four images, or crop the first one if there are fewer than four.
+///
+/// The size of the collage is `options.max_width`.
+pub fn compose_collage(sources: &[Bytes], options: &ImageProcessOptions) -> Result<ProcessedImage, ProcessError> {
+    let size = options.max_width;
+    let image = if sources.len() < 4 {
+        let first = sources.first().ok_or(ProcessError::InvalidImage)?;
+        decode(first, options)?.resize_to_fill(size, size, FilterType::Lanczos3)
+    } else {
+        let tile = size / 2;
+        let mut canvas = RgbaImage::new(tile * 2, tile * 2);
+        for (i, source) in sources.iter().take(4).enumerate() {
+            let image = decode(source, options)?.resize_to_fill(tile, tile, FilterType::Lanczos3);
+            let (x, y) = ((i % 2) as u32 * tile, (i / 2) as u32 * tile);
+            imageops::overlay(&mut canvas, &image, x as i64, y as i64);
+        }
+        DynamicImage::ImageRgba8(canvas)
+    };
+    let (width, height) = (image.width(), image.height());
+    let data = encode(image, options.format, options.quality)?;
+    Ok(ProcessedImage {
+        data,
+        format: options.format,
+        width,
+        height,
+    })
+}
+
+fn decode(bytes: &[u8], options: &ImageProcessOptions) -> Result<DynamicImage, ProcessError> {
+    if bytes.len() > options.max_size {
+        return Err(ProcessError::TooLarge { max_size: options.max_size });
+    }
+
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|_| ProcessError::InvalidImage)?;
+    let format = reader.format().ok_or(ProcessError::InvalidImage)?;
+    match format {
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP | ImageFormat::Gif | ImageFormat::Avif => {}
+        _ => return Err(ProcessError::UnsupportedFormat),
+    }
+
+    if options.animated == AnimationPolicy::Reject && is_animated(format, bytes) {
+        return Err(ProcessError::Animated);
+    }
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    reader.limits(limits);
+
+    // Animated images are decoded as their first frame
+    let mut decoder = reader.into_decoder().map_err(|_| ProcessError::InvalidImage)?;
+    let orientation = decoder.orientation().map_err(|_| ProcessError::InvalidImage)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|_| ProcessError::InvalidImage)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Process the image and upload it to `images/{dir}/{sha1}.{ext}`.
+///
+/// The processing runs on the blocking thread pool since it is CPU-bound.
+pub async fn process_and_upload(
+    file_host: &dyn FileHost,
+    dir: &str,
+    bytes: Bytes,
+    options: &ImageProcessOptions,
+) -> Result<UploadResult, ProcessError> {
+    let options_cloned = options.clone();
+    let processed = tokio::task::spawn_blocking(move || process(&bytes, &options_cloned))
+        .await
+        .map_err(|e| anyhow!(e))??;
+    Ok(upload_processed(file_host, dir, processed).await?)
+}
+
+/// Upload a processed image to `images/{dir}/{sha1}.{ext}`
+pub async fn upload_processed(
+    file_host: &dyn FileHost,
+    dir: &str,
+    processed: ProcessedImage,
+) -> anyhow::Result<UploadResult> {
+    let sha1 = openssl::sha::sha1(&processed.data);
+    let filename = format!("images/{}/{}.{}", dir, hex::encode(sha1), processed.format.ext());
+    let upload_options = UploadOptions::hashed_image(processed.format.mime_type());
+    let result = upload::store(file_host, dir, processed.data.into(), &filename, &upload_options).await?;
+    Ok(result)
+}
+
+fn is_animated(format: ImageFormat, bytes: &[u8]) -> bool {
+    match format {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes))
+            .map(|x| x.into_frames().take(2).count() > 1)
+            .unwrap_or(false),
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(bytes))
+            .map(|x| x.has_animation())
+            .unwrap_or(false),
+        ImageFormat::Png => PngDecoder::new(Cursor::new(bytes))
+            .and_then(|x| x.is_apng())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+fn resize(image: DynamicImage, w: u32, h: u32, resize_type: ResizeType) -> DynamicImage {
+    if image.width() <= w && image.height() <= h {
+        return image;
+    }
+    match resize_type {
+        ResizeType::Crop => image.resize_to_fill(w, h, FilterType::Lanczos3),
+        ResizeType::Fit => image.resize(w, h, FilterType::Lanczos3),
+        ResizeType::Exact => image.resize_exact(w, h, FilterType::Lanczos3),
+    }
+}
+
+fn encode(image: DynamicImage, format: OutputFormat, quality: f32) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    match format {
+        OutputFormat::Webp => {
+            // The webp encoder only supports rgb8/rgba8
+            let image = match image {
+                x @ DynamicImage::ImageRgb8(_) => x,
+                x @ DynamicImage::ImageRgba8(_) => x,
+                x if x.color().has_alpha() => DynamicImage::ImageRgba8(x.into_rgba8()),
+                x => DynamicImage::ImageRgb8(x.into_rgb8()),
+            };
+            let encoder = webp::Encoder::from_image(&image).map_err(|_| anyhow!("Failed to encode image to webp"))?;
+            buf = encoder.encode(quality).to_vec();
+        }
+        OutputFormat::Jpeg => {
+            // Jpeg doesn't support alpha channel
+            let image = DynamicImage::ImageRgb8(image.into_rgb8());
+            image.write_with_encoder(JpegEncoder::new_with_quality(&mut buf, quality.clamp(1f32, 100f32) as u8))?;
+        }
+        OutputFormat::Png => {
+            image.write_with_encoder(PngEncoder::new(&mut buf))?;
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Frame, RgbaImage};
+
+    fn options(animated: AnimationPolicy) -> ImageProcessOptions {
+        ImageProcessOptions::song_cover(&ImageCfg { output_format: OutputFormat::Webp, animated }, &LimitsCfg::default())
+    }
+
+    fn animated_gif() -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut buf);
+            let frames = (0..2).map(|i| Frame::new(RgbaImage::from_pixel(32, 32, [i * 100, 0, 0, 255].into())));
+            encoder.encode_frames(frames).unwrap();
+        }
+        buf
+    }
+
+    /// A PNG of random pixels, which can't be compressed much
+    fn noise_png(size: u32) -> Vec<u8> {
+        let mut pixels = vec![0u8; (size * size * 3) as usize];
+        rand::fill(&mut pixels[..]);
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(image::RgbImage::from_raw(size, size, pixels).unwrap())
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_resize_and_formats() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(image::RgbImage::new(2048, 1024))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+
+        for format in [OutputFormat::Webp, OutputFormat::Jpeg, OutputFormat::Png] {
+            let opts = ImageProcessOptions::song_cover(&ImageCfg { output_format: format, animated: AnimationPolicy::Reject }, &LimitsCfg::default());
+            let result = process(&png, &opts).unwrap();
+            assert_eq!((1024, 512), (result.width, result.height));
+            let guessed = image::guess_format(&result.data).unwrap();
+            assert_eq!(format.mime_type(), guessed.to_mime_type());
+        }
+    }
+
+    #[test]
+    fn test_animated_images() {
+        let gif = animated_gif();
+        assert!(matches!(process(&gif, &options(AnimationPolicy::Reject)), Err(ProcessError::Animated)));
+
+        let result = process(&gif, &options(AnimationPolicy::FirstFrame)).unwrap();
+        assert_eq!((32, 32), (result.width, result.height));
+    }
+
+    #[test]
+    fn test_compose_collage() {
+        let covers = (0..4u8).map(|i| {
+            let mut png = Vec::new();
+            DynamicImage::ImageRgb8(image::RgbImage::from_pixel(300, 200, [i * 60, 0, 0].into()))
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .unwrap();
+            Bytes::from(png)
+        }).collect::<Vec<_>>();
+        let opts = ImageProcessOptions::playlist_cover(&ImageCfg { output_format: OutputFormat::Png, animated: AnimationPolicy::FirstFrame }, &LimitsCfg::default());
+
+        let result = compose_collage(&covers, &opts).unwrap();
+        assert_eq!((512, 512), (result.width, result.height));
+        let image = image::load_from_memory(&result.data).unwrap().into_rgba8();
+        assert_eq!(180, image.get_pixel(300, 300)[0]);
+
+        let result = compose_collage(&covers[..1], &opts).unwrap();
+        assert_eq!((512, 512), (result.width, result.height));
+        assert!(compose_collage(&[], &opts).is_err());
+    }
+
+    #[test]
+    fn test_invalid_images() {
+        assert!(matches!(process(b"not an image", &options(AnimationPolicy::Reject)), Err(ProcessError::InvalidImage)));
+
+        let mut opts = options(AnimationPolicy::Reject);
+        opts.max_size = 4;
+        assert!(matches!(process(&animated_gif(), &opts), Err(ProcessError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn test_size_limit_from_limits_cfg() {
+        // Larger than the 8MB once hardcoded for the covers, smaller than the default `image_max_bytes`
+        let png = noise_png(1728);
+        let limits = LimitsCfg::default();
+        assert!(png.len() > 8 * 1024 * 1024 && png.len() < limits.image_max_bytes);
+
+        let cfg = ImageCfg::default();
+        for opts in [ImageProcessOptions::avatar(&cfg, &limits), ImageProcessOptions::song_cover(&cfg, &limits)] {
+            assert_eq!(limits.image_max_bytes, opts.max_size);
+            assert!(process(&png, &opts).is_ok());
+        }
+
+        let limits = LimitsCfg { image_max_bytes: 8 * 1024 * 1024, ..LimitsCfg::default() };
+        let opts = ImageProcessOptions::post_image(&cfg, &limits);
+        assert!(matches!(process(&png, &opts), Err(ProcessError::TooLarge { .. })));
+    }
+}
@@ -0,0 +1,329 @@
+//! The queue of the background jobs, run by the workers of every instance.
+//!
+//! The jobs are the members of the sorted set `jobs:queue`, scored by the time they are due in milliseconds.
+//! A worker claims the first due job by leasing it, which moves its score to the end of the lease, renews the lease
+//! while running it, and removes it when it's done. A failed job is rescheduled with a backoff, and moved to the `jobs:dead` list after
+//! [MAX_ATTEMPTS]. The job of a crashed worker is claimed again when the lease expires, so the jobs are run
+//! at least once and must be safe to retry.
+
+use crate::config::Config;
+use crate::search;
+use crate::service::email_delivery;
+use crate::service::mailer::{self, EmailConfig};
+use crate::service::notification_templates::NotificationTemplate;
+use crate::service::transcode;
+use crate::util::redis_health;
+use crate::web::i18n::Lang;
+use crate::web::state::AppState;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, Script};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+const QUEUE_KEY: &str = "jobs:queue";
+const DEAD_KEY: &str = "jobs:dead";
+/// Only the latest dead jobs are kept for inspection
+const MAX_DEAD_JOBS: isize = 1000;
+pub const MAX_ATTEMPTS: u32 = 8;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+/// Renewed every [LEASE_RENEWAL] while the job runs, so a long job like transcoding is not claimed again meanwhile
+const LEASE: Duration = Duration::from_secs(120);
+const LEASE_RENEWAL: Duration = Duration::from_secs(30);
+/// How long an idle worker waits before polling again
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+const CLAIM_SCRIPT: &str = r"
+local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, 1)
+if #due == 0 then
+    return false
+end
+redis.call('ZADD', KEYS[1], ARGV[2], due[1])
+return due[1]
+";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackgroundJob {
+    /// Add or replace the search documents of the songs
+    IndexSongs { song_ids: Vec<i64> },
+    /// Send the email and track the delivery, see [email_delivery::track]
+    SendEmail {
+        email_type: String,
+        to: String,
+        template: NotificationTemplate,
+        /// Absent in the jobs enqueued before the emails were localized
+        #[serde(default)]
+        lang: Lang,
+    },
+    /// Send the result email of a review in a status once, see [email_delivery::track_review_result]
+    SendReviewResult {
+        review_id: i64,
+        status: i32,
+        to: String,
+        template: NotificationTemplate,
+        #[serde(default)]
+        lang: Lang,
+    },
+    /// Generate the missing or stale renditions of the song, see [transcode::transcode_song]
+    TranscodeSong { song_id: i64 },
+}
+
+impl BackgroundJob {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BackgroundJob::IndexSongs { .. } => "index_songs",
+            BackgroundJob::SendEmail { .. } => "send_email",
+            BackgroundJob::SendReviewResult { .. } => "send_review_result",
+            BackgroundJob::TranscodeSong { .. } => "transcode_song",
+        }
+    }
+
+    async fn run(&self, state: &AppState) -> anyhow::Result<()> {
+        match self {
+            BackgroundJob::IndexSongs { song_ids } => {
+                search::song::add_or_replace_document(&state.meilisearch, &state.sql_pool, song_ids).await?;
+            }
+            BackgroundJob::SendEmail { email_type, to, template, lang } => {
+                let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
+                email_delivery::track(
+                    &state.sql_pool,
+                    email_type,
+                    to,
+                    mailer::send_template(&email_cfg, *lang, to, template),
+                ).await?;
+            }
+            BackgroundJob::SendReviewResult { review_id, status, to, template, lang } => {
+                let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
+                email_delivery::track_review_result(
+                    &state.sql_pool,
+                    state.redis_conn.clone(),
+                    *review_id,
+                    *status,
+                    to,
+                    mailer::send_template(&email_cfg, *lang, to, template),
+                ).await?;
+            }
+            BackgroundJob::TranscodeSong { song_id } => {
+                transcode::transcode_song(state, *song_id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A job in the queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Envelope {
+    /// Tells apart the same jobs enqueued more than once
+    id: String,
+    /// The failed attempts so far
+    attempt: u32,
+    enqueue_time: DateTime<Utc>,
+    job: BackgroundJob,
+}
+
+/// Optional `jobs` section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobsCfg {
+    /// The workers of each instance
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+}
+
+fn default_workers() -> usize { 2 }
+
+impl Default for JobsCfg {
+    fn default() -> Self {
+        JobsCfg { workers: default_workers() }
+    }
+}
+
+impl JobsCfg {
+    /// Load the `jobs` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("jobs")?.is_some() {
+            config.get_and_parse("jobs")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// Enqueue the job to run as soon as a worker is free.
+///
+/// If Redis is unavailable, the job is run once in place instead, without retrying.
+pub async fn enqueue(state: &AppState, job: BackgroundJob) {
+    let envelope = Envelope {
+        id: uuid::Uuid::new_v4().to_string(),
+        attempt: 0,
+        enqueue_time: Utc::now(),
+        job,
+    };
+    let pushed = redis_health::cached(push(state.redis_conn.clone(), &envelope, Utc::now())).await;
+    if pushed.is_none() {
+        warn!(job = envelope.job.name(), "Failed to enqueue the job, running it in place");
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = envelope.job.run(&state).await {
+                error!(job = envelope.job.name(), "Job failed: {:?}", e);
+            }
+        });
+    }
+}
+
+async fn push(mut redis: ConnectionManager, envelope: &Envelope, due_time: DateTime<Utc>) -> anyhow::Result<()> {
+    let _: () = redis.zadd(QUEUE_KEY, serde_json::to_string(envelope)?, due_time.timestamp_millis()).await?;
+    Ok(())
+}
+
+/// The delay before retrying after the failed attempts
+pub fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(MAX_BACKOFF)
+}
+
+/// Run the workers of this instance until cancelled
+pub async fn run(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = JobsCfg::load(&state.config)?;
+    info!("Starting {} job workers", cfg.workers);
+    let workers = (0..cfg.workers)
+        .map(|_| tokio::spawn(run_worker(state.clone(), cancel_token.clone())))
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.await?;
+    }
+    info!("Job workers stopped");
+    Ok(())
+}
+
+async fn run_worker(state: AppState, cancel_token: CancellationToken) {
+    let mut redis = state.redis_conn.clone();
+    let script = Script::new(CLAIM_SCRIPT);
+    while !cancel_token.is_cancelled() {
+        let now = Utc::now();
+        let claimed: anyhow::Result<Option<String>> = script
+            .key(QUEUE_KEY)
+            .arg(now.timestamp_millis())
+            .arg((now + LEASE).timestamp_millis())
+            .invoke_async(&mut redis)
+            .await
+            .map_err(Into::into);
+        let wait = match claimed {
+            // The running job is finished before stopping
+            Ok(Some(member)) => {
+                if let Err(e) = process(&state, &mut redis, &member).await {
+                    warn!("Failed to settle the job, it will be run again after the lease: {:?}", e);
+                }
+                continue;
+            }
+            Ok(None) => POLL_INTERVAL,
+            Err(e) => {
+                warn!("Failed to claim a job: {:?}", e);
+                ERROR_BACKOFF
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = cancel_token.cancelled() => {}
+        }
+    }
+}
+
+/// Run the claimed job, and remove, reschedule or bury it by the result
+async fn process(state: &AppState, redis: &mut ConnectionManager, member: &str) -> anyhow::Result<()> {
+    let mut envelope: Envelope = match serde_json::from_str(member) {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Burying the unreadable job {}: {:?}", member, e);
+            return bury(redis, member, member).await;
+        }
+    };
+    let name = envelope.job.name();
+    let Err(e) = run_leased(state, &envelope.job, member).await else {
+        counter!("background_job_count", "type" => name, "result" => "ok").increment(1);
+        let _: () = redis.zrem(QUEUE_KEY, member).await?;
+        return Ok(());
+    };
+
+    envelope.attempt += 1;
+    if envelope.attempt >= MAX_ATTEMPTS {
+        error!(job = name, id = envelope.id, "Job failed after {} attempts, burying it: {:?}", envelope.attempt, e);
+        counter!("background_job_count", "type" => name, "result" => "dead").increment(1);
+        return bury(redis, member, &serde_json::to_string(&envelope)?).await;
+    }
+    let delay = backoff(envelope.attempt);
+    warn!(job = name, id = envelope.id, "Job failed, retrying in {:?}: {:?}", delay, e);
+    counter!("background_job_count", "type" => name, "result" => "retry").increment(1);
+    let due_time = Utc::now() + delay;
+    let _: () = redis::pipe()
+        .atomic()
+        .zrem(QUEUE_KEY, member).ignore()
+        .zadd(QUEUE_KEY, serde_json::to_string(&envelope)?, due_time.timestamp_millis()).ignore()
+        .query_async(redis)
+        .await?;
+    Ok(())
+}
+
+/// Run the job, renewing its lease until it's done
+async fn run_leased(state: &AppState, job: &BackgroundJob, member: &str) -> anyhow::Result<()> {
+    let mut redis = state.redis_conn.clone();
+    let run = job.run(state);
+    tokio::pin!(run);
+    let mut renewal = tokio::time::interval_at(tokio::time::Instant::now() + LEASE_RENEWAL, LEASE_RENEWAL);
+    loop {
+        tokio::select! {
+            result = &mut run => return result,
+            _ = renewal.tick() => {
+                // Only updated if it's still in the queue
+                let renewed: redis::RedisResult<()> = redis::cmd("ZADD")
+                    .arg(QUEUE_KEY)
+                    .arg("XX")
+                    .arg((Utc::now() + LEASE).timestamp_millis())
+                    .arg(member)
+                    .query_async(&mut redis)
+                    .await;
+                if let Err(e) = renewed {
+                    warn!(job = job.name(), "Failed to renew the lease, the job may be run again: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+async fn bury(redis: &mut ConnectionManager, member: &str, dead: &str) -> anyhow::Result<()> {
+    let _: () = redis::pipe()
+        .atomic()
+        .zrem(QUEUE_KEY, member).ignore()
+        .lpush(DEAD_KEY, dead).ignore()
+        .ltrim(DEAD_KEY, 0, MAX_DEAD_JOBS - 1).ignore()
+        .query_async(redis)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(Duration::from_secs(10), backoff(1));
+        assert_eq!(Duration::from_secs(20), backoff(2));
+        assert_eq!(Duration::from_secs(640), backoff(7));
+        assert_eq!(MAX_BACKOFF, backoff(20));
+        assert_eq!(MAX_BACKOFF, backoff(u32::MAX));
+    }
+
+    #[test]
+    fn test_job_format() {
+        let job = BackgroundJob::IndexSongs { song_ids: vec![1, 2] };
+        let value = serde_json::to_value(&job).unwrap();
+        assert_eq!(serde_json::json!({"type": "index_songs", "song_ids": [1, 2]}), value);
+        assert_eq!(job, serde_json::from_value(value).unwrap());
+    }
+}
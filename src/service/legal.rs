@@ -0,0 +1,135 @@
+//! The versions of the terms of service and the privacy policy accepted by the users.
+//!
+//! Publishing a mandatory version makes the users who accepted an older one fail to log in or refresh the tokens
+//! with `legal_acceptance_required`, listing the versions to accept in the detail, until they retry with them in
+//! `accepted_legal` or accept them by `/legal/accept`. It's checked by every login path when issuing the tokens.
+
+use crate::db::legal_document::{ILegalDocumentDao, LegalDocument, LegalDocumentDao, UserLegalAcceptance};
+use crate::web::result::{CommonError, WebError};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+pub const KIND_TERMS: &str = "terms";
+pub const KIND_PRIVACY: &str = "privacy";
+pub const KINDS: [&str; 2] = [KIND_TERMS, KIND_PRIVACY];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LegalAcceptance {
+    pub kind: String,
+    pub version: i32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcceptError {
+    #[error("Version {version} of {kind} is not published")]
+    InvalidVersion { kind: String, version: i32 },
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// The mandatory versions not covered by the accepted ones
+pub fn missing(mandatory: &[LegalDocument], accepted: &[LegalAcceptance]) -> Vec<LegalAcceptance> {
+    mandatory.iter()
+        .filter(|doc| !accepted.iter().any(|x| x.kind == doc.kind && x.version >= doc.version))
+        .map(|doc| LegalAcceptance { kind: doc.kind.clone(), version: doc.version })
+        .collect()
+}
+
+/// The latest mandatory versions the user has to accept
+pub async fn pending_for_user(pool: &PgPool, uid: i64) -> sqlx::Result<Vec<LegalAcceptance>> {
+    let mandatory = LegalDocumentDao::list_latest_mandatory(pool, Utc::now()).await?;
+    if mandatory.is_empty() {
+        return Ok(vec![]);
+    }
+    let accepted = LegalDocumentDao::list_acceptances(pool, uid).await?
+        .into_iter()
+        .map(|x| LegalAcceptance { kind: x.kind, version: x.version })
+        .collect::<Vec<_>>();
+    Ok(missing(&mandatory, &accepted))
+}
+
+/// The latest mandatory versions not covered by the ones accepted by a registering user
+pub async fn pending_for_registration(pool: &PgPool, accepted: &[LegalAcceptance]) -> sqlx::Result<Vec<LegalAcceptance>> {
+    let mandatory = LegalDocumentDao::list_latest_mandatory(pool, Utc::now()).await?;
+    Ok(missing(&mandatory, accepted))
+}
+
+/// Check the versions are published, without recording them
+pub async fn validate(pool: &PgPool, items: &[LegalAcceptance]) -> Result<(), AcceptError> {
+    let now = Utc::now();
+    for item in items {
+        let published = LegalDocumentDao::get_by_version(pool, &item.kind, item.version).await?
+            .is_some_and(|x| x.publish_time <= now);
+        if !published {
+            return Err(AcceptError::InvalidVersion { kind: item.kind.clone(), version: item.version });
+        }
+    }
+    Ok(())
+}
+
+/// Record the versions accepted by the user, all or nothing if any of them is not published
+pub async fn accept(pool: &PgPool, uid: i64, items: &[LegalAcceptance]) -> Result<(), AcceptError> {
+    validate(pool, items).await?;
+    let now = Utc::now();
+    for item in items {
+        LegalDocumentDao::accept(pool, &UserLegalAcceptance {
+            user_id: uid,
+            kind: item.kind.clone(),
+            version: item.version,
+            accept_time: now,
+        }).await?;
+    }
+    Ok(())
+}
+
+/// The `legal_acceptance_required` error, with the versions to accept in the detail
+pub fn acceptance_required(pending: &[LegalAcceptance]) -> WebError<CommonError> {
+    WebError::common_with_detail(
+        "legal_acceptance_required",
+        "Please accept the latest terms before continuing",
+        serde_json::json!({ "pending": pending }),
+    )
+}
+
+/// Map the [AcceptError] to the `invalid_legal_version` error
+pub fn map_accept_error(e: AcceptError) -> WebError<CommonError> {
+    match e {
+        e @ AcceptError::InvalidVersion { .. } => WebError::common("invalid_legal_version", &e.to_string()),
+        AcceptError::Sqlx(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing() {
+        let doc = |kind: &str, version| LegalDocument {
+            id: 0,
+            kind: kind.to_string(),
+            version,
+            title: String::new(),
+            content: String::new(),
+            mandatory: true,
+            publish_time: Utc::now(),
+            create_time: Utc::now(),
+        };
+        let accepted = |kind: &str, version| LegalAcceptance { kind: kind.to_string(), version };
+        let mandatory = [doc(KIND_TERMS, 2), doc(KIND_PRIVACY, 1)];
+
+        assert_eq!(2, missing(&mandatory, &[]).len());
+        // A newer version covers the older ones
+        assert_eq!(
+            vec![accepted(KIND_PRIVACY, 1)],
+            missing(&mandatory, &[accepted(KIND_TERMS, 3)])
+        );
+        assert_eq!(
+            vec![accepted(KIND_TERMS, 2)],
+            missing(&mandatory, &[accepted(KIND_TERMS, 1), accepted(KIND_PRIVACY, 1)])
+        );
+        assert!(missing(&mandatory, &[accepted(KIND_TERMS, 2), accepted(KIND_PRIVACY, 1)]).is_empty());
+    }
+}
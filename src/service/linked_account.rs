@@ -0,0 +1,102 @@
+//! The external identities (OAuth) linked to the users for logging in.
+//!
+//! A user can always log in by one of the methods: the password, or any linked identity. The users registered by
+//! an identity have an empty password hash until they set one, so the last identity of them can't be unlinked.
+//! Every link and unlink is recorded in the account audit logs.
+
+use crate::db::user::{User, UserDao};
+use crate::db::user_account_audit_log::{self, UserAccountAuditLog, UserAccountAuditLogDao};
+use crate::db::user_oauth_identity::{IUserOAuthIdentityDao, UserOAuthIdentity, UserOAuthIdentityDao};
+use crate::db::CrudDao;
+use chrono::Utc;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+
+/// Whether the user can log in by the password
+pub fn has_password(user: &User) -> bool {
+    !user.password_hash.is_empty()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LinkError {
+    #[error("The user already linked an account of {0}")]
+    ProviderAlreadyLinked(String),
+    #[error("The account is linked to another user")]
+    LinkedToOther,
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Link the identity to the user, the `id` and `user_id` of the identity are ignored
+pub async fn link(pool: &PgPool, uid: i64, identity: &UserOAuthIdentity, ip: Option<&str>) -> Result<i64, LinkError> {
+    let mut tx = pool.begin().await?;
+    let id = link_in(&mut tx, uid, identity, ip).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// [link] in the transaction, e.g. the one registering the user
+pub async fn link_in(tx: &mut PgConnection, uid: i64, identity: &UserOAuthIdentity, ip: Option<&str>) -> Result<i64, LinkError> {
+    if let Some(x) = UserOAuthIdentityDao::get_by_provider_user_id(&mut *tx, &identity.provider, &identity.provider_user_id).await? {
+        return Err(if x.user_id == uid {
+            LinkError::ProviderAlreadyLinked(x.provider)
+        } else {
+            LinkError::LinkedToOther
+        });
+    }
+    let existing = UserOAuthIdentityDao::list_by_user_id_for_update(&mut *tx, uid).await?;
+    if existing.iter().any(|x| x.provider == identity.provider) {
+        return Err(LinkError::ProviderAlreadyLinked(identity.provider.clone()));
+    }
+
+    let id = UserOAuthIdentityDao::insert(&mut *tx, &UserOAuthIdentity {
+        id: 0,
+        user_id: uid,
+        ..identity.clone()
+    }).await?;
+    audit(tx, uid, user_account_audit_log::ACTION_OAUTH_LINK, identity, ip).await?;
+    Ok(id)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum UnlinkError {
+    #[error("No account of {0} is linked")]
+    NotLinked(String),
+    #[error("Can't unlink the last login method, set a password first")]
+    LastLoginMethod,
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+pub async fn unlink(pool: &PgPool, uid: i64, provider: &str, ip: Option<&str>) -> Result<(), UnlinkError> {
+    let mut tx = pool.begin().await?;
+    let identities = UserOAuthIdentityDao::list_by_user_id_for_update(&mut *tx, uid).await?;
+    let Some(identity) = identities.iter().find(|x| x.provider == provider) else {
+        return Err(UnlinkError::NotLinked(provider.to_string()));
+    };
+    let user = UserDao::get_by_id(&mut *tx, uid).await?.ok_or(sqlx::Error::RowNotFound)?;
+    if identities.len() <= 1 && !has_password(&user) {
+        return Err(UnlinkError::LastLoginMethod);
+    }
+
+    UserOAuthIdentityDao::delete_by_user_id_and_provider(&mut *tx, uid, provider).await?;
+    audit(&mut tx, uid, user_account_audit_log::ACTION_OAUTH_UNLINK, identity, ip).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn audit(conn: &mut PgConnection, uid: i64, action: &str, identity: &UserOAuthIdentity, ip: Option<&str>) -> sqlx::Result<()> {
+    UserAccountAuditLogDao::insert(&mut *conn, &UserAccountAuditLog {
+        id: 0,
+        user_id: uid,
+        action: action.to_string(),
+        detail: json!({
+            "provider": identity.provider,
+            "provider_user_id": identity.provider_user_id,
+            "provider_user_name": identity.provider_user_name,
+        }),
+        ip: ip.map(|x| x.to_string()),
+        create_time: Utc::now(),
+    }).await?;
+    Ok(())
+}
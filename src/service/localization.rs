@@ -0,0 +1,168 @@
+//! The titles of the songs and playlists in the other languages, for the international audience.
+//!
+//! The uploaders and the playlist owners may submit a title (and a subtitle) per language tag. The detail and
+//! search responses take the title of the language most preferred by the `Accept-Language` of the request, matched
+//! exactly first and then by the primary subtag, and fallback to the original title. The original titles are
+//! assumed to be Chinese, so the languages less preferred than Chinese are never taken.
+
+use crate::common;
+use crate::db::localized_title::{ILocalizedTitleDao, LocalizedTitle, LocalizedTitleDao};
+use crate::util::IsBlank;
+use crate::web::i18n;
+use crate::web::result::{CommonError, WebError};
+use chrono::Utc;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgTransaction};
+use std::collections::HashMap;
+
+pub const MAX_ITEMS: usize = 16;
+pub const SONG_TITLE_MAX_CHARS: usize = 100;
+pub const SONG_SUBTITLE_MAX_CHARS: usize = 200;
+const LANG_TAG_MAX_CHARS: usize = 16;
+/// The primary subtag of the language of the original titles
+const ORIGINAL_LANG: &str = "zh";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LocalizedTitleItem {
+    /// BCP 47 language tag, e.g. `en`, `ja`, `zh-TW`
+    pub lang: String,
+    pub title: String,
+    /// The description for the playlists
+    #[serde(default)]
+    pub subtitle: String,
+}
+
+fn primary_subtag(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or_default()
+}
+
+/// A simplified BCP 47 check, a 2-3 letters primary subtag and the optional alphanumeric subtags
+pub fn is_valid_lang_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    tag.len() <= LANG_TAG_MAX_CHARS
+        && (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|x| (2..=8).contains(&x.len()) && x.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Check the submitted titles, the languages must be unique
+pub fn validate(
+    items: &[LocalizedTitleItem],
+    title_max_chars: usize,
+    subtitle_max_chars: usize,
+) -> Result<(), WebError<CommonError>> {
+    if items.len() > MAX_ITEMS {
+        return Err(common!("invalid_localized_titles", "Too many localized titles, at most {}", MAX_ITEMS));
+    }
+    for x in items {
+        if !is_valid_lang_tag(&x.lang) {
+            return Err(common!("invalid_localized_titles", "Invalid language tag: {}", x.lang));
+        }
+        if x.title.is_blank() || x.title.chars().count() > title_max_chars {
+            return Err(common!("invalid_localized_titles", "Invalid title of {}", x.lang));
+        }
+        if x.subtitle.chars().count() > subtitle_max_chars {
+            return Err(common!("invalid_localized_titles", "The subtitle of {} is too long", x.lang));
+        }
+    }
+    if !items.iter().map(|x| x.lang.to_ascii_lowercase()).all_unique() {
+        return Err(common!("invalid_localized_titles", "Duplicated languages"));
+    }
+    Ok(())
+}
+
+/// The title of the most preferred language in `accepted`, `None` if the original title should be taken
+pub fn select<'a>(items: &'a [LocalizedTitleItem], accepted: &[String]) -> Option<&'a LocalizedTitleItem> {
+    for tag in accepted {
+        if let Some(x) = items.iter().find(|x| x.lang.eq_ignore_ascii_case(tag)) {
+            return Some(x);
+        }
+        let primary = primary_subtag(tag);
+        if primary.eq_ignore_ascii_case(ORIGINAL_LANG) {
+            return None;
+        }
+        if let Some(x) = items.iter().find(|x| primary_subtag(&x.lang).eq_ignore_ascii_case(primary)) {
+            return Some(x);
+        }
+    }
+    None
+}
+
+/// [select] by the `Accept-Language` of the current request
+pub fn select_for_request(items: &[LocalizedTitleItem]) -> Option<&LocalizedTitleItem> {
+    if items.is_empty() {
+        return None;
+    }
+    select(items, &i18n::accepted_languages())
+}
+
+/// The titles of the songs or playlists, see [crate::db::localized_title] for the entity types
+pub async fn list_by_ids<'e>(
+    executor: impl PgExecutor<'e>,
+    entity_type: &str,
+    entity_ids: &[i64],
+) -> sqlx::Result<HashMap<i64, Vec<LocalizedTitleItem>>> {
+    let rows = LocalizedTitleDao::list_by_entity_ids(executor, entity_type, entity_ids).await?;
+    Ok(rows.into_iter()
+        .map(|x| (x.entity_id, LocalizedTitleItem { lang: x.lang, title: x.title, subtitle: x.subtitle }))
+        .into_group_map())
+}
+
+/// Replace all the titles of the song or playlist
+pub async fn replace(
+    tx: &mut PgTransaction<'_>,
+    entity_type: &str,
+    entity_id: i64,
+    items: &[LocalizedTitleItem],
+) -> sqlx::Result<()> {
+    LocalizedTitleDao::delete_by_entity_id(&mut **tx, entity_type, entity_id).await?;
+    let now = Utc::now();
+    let values = items.iter().map(|x| LocalizedTitle {
+        entity_type: entity_type.to_string(),
+        entity_id,
+        lang: x.lang.clone(),
+        title: x.title.clone(),
+        subtitle: x.subtitle.clone(),
+        update_time: now,
+    }).collect_vec();
+    LocalizedTitleDao::insert_batch(&mut **tx, &values).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(lang: &str, title: &str) -> LocalizedTitleItem {
+        LocalizedTitleItem { lang: lang.to_string(), title: title.to_string(), subtitle: String::new() }
+    }
+
+    fn tags(value: &str) -> Vec<String> {
+        i18n::parse_accept_language(value)
+    }
+
+    #[test]
+    fn test_select() {
+        let items = [item("en", "Hachimi"), item("ja", "ハチミ"), item("zh-TW", "哈基米（繁）")];
+        assert_eq!("Hachimi", select(&items, &tags("en-US,en;q=0.9")).unwrap().title);
+        assert_eq!("ハチミ", select(&items, &tags("fr, ja-JP;q=0.8")).unwrap().title);
+        // The exact match wins over the original
+        assert_eq!("哈基米（繁）", select(&items, &tags("zh-TW, en")).unwrap().title);
+        // The languages after Chinese are ignored
+        assert!(select(&items, &tags("zh-CN, en;q=0.5")).is_none());
+        assert!(select(&items, &tags("fr")).is_none());
+        assert!(select(&items, &[]).is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[item("en", "Hachimi"), item("zh-Hant-TW", "哈基米")], 10, 10).is_ok());
+        assert!(validate(&[item("en", "Hachimi"), item("EN", "Hachimi")], 10, 10).is_err());
+        assert!(validate(&[item("english", "Hachimi")], 10, 10).is_err());
+        assert!(validate(&[item("en-", "Hachimi")], 10, 10).is_err());
+        assert!(validate(&[item("en", " ")], 10, 10).is_err());
+        assert!(validate(&[item("en", "Hachimi Hachimi")], 10, 10).is_err());
+    }
+}
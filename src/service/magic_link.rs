@@ -0,0 +1,124 @@
+//! Logging in by a link sent to the email, for the casual users who don't want the password and captcha.
+//!
+//! The link carries a token signed like the other JWTs, whose `jti` is kept in Redis until it's used or expired,
+//! so each link can only be used once.
+
+use crate::config::Config;
+use crate::db::email_delivery::{EmailDeliveryDao, IEmailDeliveryDao};
+use crate::service::email_delivery;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+pub const TOKEN_TTL_SECS: i64 = 15 * 60;
+/// The link is revoked after this many wrong 2FA codes entered with it
+pub const MAX_FAILED_CODES: i64 = 3;
+/// The interval between the requests for the same address
+const REQUEST_INTERVAL_SECS: u64 = 60;
+
+/// The links sent per hour, on top of the 60 seconds interval of an address.
+/// It's configured by `rate_limits.magic_link`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MagicLinkLimit {
+    /// To an address
+    pub per_email_per_hour: i64,
+}
+
+impl Default for MagicLinkLimit {
+    fn default() -> Self {
+        MagicLinkLimit { per_email_per_hour: 5 }
+    }
+}
+
+/// Optional `magic_link` section of the config file, the magic link login is disabled if it's absent.
+///
+/// ```yaml
+/// magic_link:
+///   # The page calls `/auth/login/magic_link/verify` with the token
+///   url: https://hachimi.world/login/magic_link?token={token}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MagicLinkCfg {
+    pub url: String,
+}
+
+impl MagicLinkCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("magic_link")?.is_some() {
+            Ok(Some(config.get_and_parse("magic_link")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn link(&self, token: &str) -> String {
+        self.url.replace("{token}", &urlencoding::encode(token))
+    }
+}
+
+/// Whether another link can't be sent to the address yet
+pub async fn is_limited(pool: &PgPool, redis: &mut ConnectionManager, email: &str, limit: &MagicLinkLimit) -> anyhow::Result<bool> {
+    let absent: bool = redis.set_options(
+        format!("magic_link:limited:{}", email.trim().to_lowercase()),
+        0,
+        SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(REQUEST_INTERVAL_SECS)),
+    ).await?;
+    if !absent {
+        return Ok(true);
+    }
+    let since = Utc::now() - chrono::Duration::hours(1);
+    let count = EmailDeliveryDao::count_since(pool, &email_delivery::recipient_hash(email), email_delivery::TYPE_MAGIC_LINK, since).await?;
+    Ok(count >= limit.per_email_per_hour)
+}
+
+/// Keep the `jti` of the issued token for [TOKEN_TTL_SECS]
+pub async fn save_token(redis: &mut ConnectionManager, jti: &str, uid: i64) -> anyhow::Result<()> {
+    let _: () = redis.set_ex(get_token_key(jti), uid, TOKEN_TTL_SECS as u64).await?;
+    Ok(())
+}
+
+/// Consume the token, returns false if it's used, expired or not issued for the user
+pub async fn consume_token(redis: &mut ConnectionManager, jti: &str, uid: i64) -> anyhow::Result<bool> {
+    let saved: Option<i64> = redis.get_del(get_token_key(jti)).await?;
+    Ok(saved == Some(uid))
+}
+
+/// Whether the token is issued for the user and not used yet, without consuming it
+pub async fn is_token_valid(redis: &mut ConnectionManager, jti: &str, uid: i64) -> anyhow::Result<bool> {
+    let saved: Option<i64> = redis.get(get_token_key(jti)).await?;
+    Ok(saved == Some(uid))
+}
+
+/// Count a wrong 2FA code entered with the link, the link is revoked after [MAX_FAILED_CODES]
+pub async fn record_failed_code(redis: &mut ConnectionManager, jti: &str) -> anyhow::Result<()> {
+    let key = format!("magic_link:failed:{jti}");
+    let (failures,): (i64,) = redis::pipe()
+        .incr(&key, 1)
+        .expire(&key, TOKEN_TTL_SECS).ignore()
+        .query_async(redis)
+        .await?;
+    if failures >= MAX_FAILED_CODES {
+        let _: () = redis.del(get_token_key(jti)).await?;
+    }
+    Ok(())
+}
+
+fn get_token_key(jti: &str) -> String {
+    format!("magic_link:token:{jti}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link() {
+        let cfg = MagicLinkCfg { url: "https://example.com/login?token={token}".to_string() };
+        assert_eq!("https://example.com/login?token=a.b%2Bc", cfg.link("a.b+c"));
+    }
+}
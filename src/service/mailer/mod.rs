@@ -1,9 +1,32 @@
-use lettre::message::header::{ContentTransferEncoding, ContentType};
-use lettre::message::{Mailbox, MultiPart, SinglePart};
-use lettre::{SmtpTransport, Transport};
-use lettre::transport::smtp::authentication::Credentials;
+use crate::service::mailer::transport::{DeliveryReceipt, Mail, MailTransportCfg};
+use crate::service::notification_templates::NotificationTemplate;
+use crate::service::test_mode;
+use crate::web::i18n::Lang;
+use askama::Template;
 use serde::{Deserialize, Serialize};
+use tracing::info;
 
+pub mod transport;
+
+/// The `email` section of the config file.
+///
+/// The SMTP server configured by `host` is the primary transport, and the `fallbacks` are tried in order
+/// if it rejects the mail or times out.
+///
+/// ```yaml
+/// email:
+///   host: email.example.com
+///   username: noreply@example.com
+///   password: 12345678
+///   no_reply_email: noreply@example.com
+///   fallbacks:
+///     - type: smtp
+///       host: backup.example.com
+///       username: noreply@example.com
+///       password: 12345678
+///     - type: sendgrid
+///       api_key: SG.xxx
+/// ```
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmailConfig {
     #[serde(default)]
@@ -12,150 +35,230 @@ pub struct EmailConfig {
     pub username: String,
     pub password: String,
     pub no_reply_email: String,
+    #[serde(default)]
+    pub fallbacks: Vec<MailTransportCfg>,
+}
+
+impl EmailConfig {
+    pub fn transports(&self) -> Vec<MailTransportCfg> {
+        let primary = MailTransportCfg::Smtp {
+            host: self.host.clone(),
+            username: self.username.clone(),
+            password: self.password.clone(),
+        };
+        std::iter::once(primary).chain(self.fallbacks.iter().cloned()).collect()
+    }
+
+    fn mail(&self, lang: Lang, to: &str, subject: &str, plain: String, html: String) -> Mail {
+        Mail {
+            from_name: Chrome::of(lang).sender_name.to_string(),
+            from_email: self.no_reply_email.clone(),
+            to: to.to_string(),
+            subject: subject.to_string(),
+            plain,
+            html,
+        }
+    }
+}
+
+/// The localized text around the content, shared by the HTML templates in `templates/`
+struct Chrome {
+    html_lang: &'static str,
+    sender_name: &'static str,
+    footer: &'static str,
+}
+
+impl Chrome {
+    fn of(lang: Lang) -> Chrome {
+        match lang {
+            Lang::ZhCn => Chrome { html_lang: "zh", sender_name: "基米天堂", footer: "此为系统邮件，请勿回复。" },
+            Lang::En => Chrome { html_lang: "en", sender_name: "Hachimi World", footer: "This is a system email, please do not reply." },
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "verification_code.html")]
+struct VerificationCodeHtml<'a> {
+    chrome: Chrome,
+    title: &'a str,
+    code: &'a str,
+    /// Following the code
+    lines: &'a [&'a str],
 }
 
-const EMAIL_TEMPLATE: &str = include_str!("templates/code_mail_template_zh.html");
-const EMAIL_PLAIN_TEMPLATE: &str = include_str!("templates/code_mail_template_zh.txt");
-const EMAIL_NOTIFICATION_TEMPLATE: &str = include_str!("templates/general_notification_zh.html");
+#[derive(Template)]
+#[template(path = "notification.html")]
+struct NotificationHtml<'a> {
+    chrome: Chrome,
+    title: &'a str,
+    /// Separated by a blank line
+    blocks: &'a [Block<'a>],
+}
+
+enum Block<'a> {
+    /// The line breaks are kept
+    Text(&'a str),
+    Link(&'a str),
+}
+
+/// Returns the subject, the plain text and the HTML
+pub fn render_verification_code(lang: Lang, code: &str) -> anyhow::Result<(String, String, String)> {
+    let (subject, lines) = match lang {
+        Lang::ZhCn => ("请查收你的邮箱验证码", ["是您的验证码，5 分钟内有效。", "如非本人操作，请忽略此邮件。"]),
+        Lang::En => ("Your verification code", ["is your verification code, valid for 5 minutes.", "If you didn't request it, please ignore this email."]),
+    };
+    let plain = format!("{code} {}\n{}", lines[0], lines[1]);
+    let html = VerificationCodeHtml { chrome: Chrome::of(lang), title: subject, code, lines: &lines }.render()?;
+    Ok((subject.to_string(), plain, html))
+}
 
 pub async fn send_verification_code(
     cfg: &EmailConfig,
+    lang: Lang,
     to: &str,
     code: &str,
-) -> anyhow::Result<()> {
-    if cfg.disabled { return Ok(()) }
-
-    let html_content = EMAIL_TEMPLATE.replace("{{VERIFICATION_CODE}}", code);
-    let plain_content = EMAIL_PLAIN_TEMPLATE.replace("{{VERIFICATION_CODE}}", code);
-
-    let email_msg = lettre::Message::builder()
-        .from(Mailbox::new(
-            Some("基米天堂".to_string()),
-            cfg.no_reply_email.parse()?,
-        ))
-        .to(Mailbox::new(None, to.parse()?))
-        .subject("请查收你的邮箱验证码")
-        .multipart(MultiPart::alternative()
-            .singlepart(SinglePart::plain(plain_content))
-            .singlepart(SinglePart::builder()
-                .header(ContentType::TEXT_HTML)
-                .header(ContentTransferEncoding::Base64)
-                .body(html_content)
-            )
-        )?;
-
-    let creds = Credentials::new(cfg.username.clone(), cfg.password.clone());
-
-    let mailer = SmtpTransport::relay(cfg.host.as_str())?
-        .credentials(creds)
-        .build();
-    mailer.send(&email_msg)?;
-    Ok(())
+) -> anyhow::Result<Option<DeliveryReceipt>> {
+    let (subject, plain, html) = render_verification_code(lang, code)?;
+    let mail = cfg.mail(lang, to, &subject, plain, html);
+    send(cfg, &mail).await
+}
+
+/// The HTML of the plain notification content, escaped and with the line breaks kept
+pub fn render_notification_html(lang: Lang, subject: &str, content: &str) -> anyhow::Result<String> {
+    Ok(NotificationHtml { chrome: Chrome::of(lang), title: subject, blocks: &[Block::Text(content)] }.render()?)
 }
 
 pub async fn send_notification(
     cfg: &EmailConfig,
+    lang: Lang,
     to: &str,
     subject: &str,
     content: &str,
-) -> anyhow::Result<()> {
-    if cfg.disabled { return Ok(()) }
-
-    let html_content = EMAIL_NOTIFICATION_TEMPLATE.replace("{{CONTENT}}", &askama_escape::escape(content, askama_escape::Html).to_string().replace("\n", "<br>"));
-    let email_msg = lettre::Message::builder()
-        .from(Mailbox::new(
-            Some("基米天堂".to_string()),
-            cfg.no_reply_email.parse()?,
-        ))
-        .to(Mailbox::new(None, to.parse()?))
-        .subject(subject)
-        .multipart(MultiPart::alternative()
-            .singlepart(SinglePart::plain(content.to_string()))
-            .singlepart(SinglePart::builder()
-                .header(ContentType::TEXT_HTML)
-                .header(ContentTransferEncoding::Base64)
-                .body(html_content)
-            )
-        )?;
-
-    let creds = Credentials::new(cfg.username.clone(), cfg.password.clone());
-
-    let mailer = SmtpTransport::relay(cfg.host.as_str())?
-        .credentials(creds)
-        .build();
-    mailer.send(&email_msg)?;
-    Ok(())
-}
-
-pub async fn send_review_approved_notification(
-    cfg: &EmailConfig,
-    to: &str,
-    song_display_id: &str,
-    song_title: &str,
-    user_name: &str,
-    comment: Option<&str>
-) -> anyhow::Result<()> {
-    let content = format!(
-        "亲爱的 {user_name}：\n\n您提交的作品《{song_title}》({song_display_id}) 已通过审核。感谢您的投稿！{}",
-        comment.map(|c| format!("\n\n审核留言：{c}")).unwrap_or_default()
-    );
-    send_notification(cfg, to, "您提交的作品已通过审核", &content).await
-}
-
-pub async fn send_review_rejected_notification(
-    cfg: &EmailConfig,
-    to: &str,
-    song_display_id: &str,
-    song_title: &str,
-    user_name: &str,
-    comment: &str
-) -> anyhow::Result<()> {
-    let content = format!(
-        "亲爱的 {user_name}：\n\n很抱歉，您提交的作品《{song_title}》({song_display_id}) 已被退回。\n\n审核留言：{comment}"
-    );
-    send_notification(cfg, to, "您提交的作品已被退回", &content).await
-}
-
-pub async fn send_review_modify_approved_notification(
+) -> anyhow::Result<Option<DeliveryReceipt>> {
+    let html = render_notification_html(lang, subject, content)?;
+    let mail = cfg.mail(lang, to, subject, content.to_string(), html);
+    send(cfg, &mail).await
+}
+
+/// Returns the subject, the plain text and the HTML. The link is clickable in the HTML, and kept as is in the plain text.
+pub fn render_magic_link(lang: Lang, link: &str, ttl_minutes: i64) -> anyhow::Result<(String, String, String)> {
+    let (subject, head, tail) = match lang {
+        Lang::ZhCn => (
+            "基米天堂登录链接",
+            format!("点击下面的链接登录基米天堂，{ttl_minutes} 分钟内有效，且只能使用一次："),
+            "如非本人操作，请忽略此邮件，你的账号仍然安全。",
+        ),
+        Lang::En => (
+            "Your Hachimi World login link",
+            format!("Click the link below to log in to Hachimi World. It's valid for {ttl_minutes} minutes and can only be used once:"),
+            "If you didn't request it, please ignore this email, your account is still safe.",
+        ),
+    };
+    let plain = format!("{head}\n\n{link}\n\n{tail}");
+    let blocks = [Block::Text(&head), Block::Link(link), Block::Text(tail)];
+    let html = NotificationHtml { chrome: Chrome::of(lang), title: subject, blocks: &blocks }.render()?;
+    Ok((subject.to_string(), plain, html))
+}
+
+pub async fn send_magic_link(
     cfg: &EmailConfig,
+    lang: Lang,
     to: &str,
-    song_display_id: &str,
-    user_name: &str,
-    comment: Option<&str>
-) -> anyhow::Result<()> {
-    let content = format!(
-        "亲爱的 {user_name}：\n\n您的作品编辑请求 ({song_display_id}) 已通过。{}",
-        comment.map(|c| format!("\n\n审核留言：{c}")).unwrap_or_default()
-    );
-    send_notification(cfg, to, "您的作品编辑请求已通过", &content).await
-}
-
-pub async fn send_review_modify_rejected_notification(
+    link: &str,
+    ttl_minutes: i64,
+) -> anyhow::Result<Option<DeliveryReceipt>> {
+    let (subject, plain, html) = render_magic_link(lang, link, ttl_minutes)?;
+    let mail = cfg.mail(lang, to, &subject, plain, html);
+    send(cfg, &mail).await
+}
+
+/// Deliver the mail, returns `None` if sending is disabled. The mail is only captured in the test mode.
+async fn send(cfg: &EmailConfig, mail: &Mail) -> anyhow::Result<Option<DeliveryReceipt>> {
+    if test_mode::is_enabled() {
+        test_mode::capture(mail);
+        return Ok(Some(DeliveryReceipt { provider: test_mode::PROVIDER.to_string(), message_id: None }));
+    }
+    if cfg.disabled { return Ok(None) }
+    transport::deliver(&cfg.transports(), mail).await.map(Some)
+}
+
+/// Render the template and send it, the template name is logged for tracing the moderation emails
+pub async fn send_template(
     cfg: &EmailConfig,
+    lang: Lang,
     to: &str,
-    song_display_id: &str,
-    user_name: &str,
-    comment: &str
-) -> anyhow::Result<()> {
-    let content = format!(
-        "亲爱的 {user_name}：\n\n很抱歉，您的作品编辑请求 ({song_display_id}) 未通过。\n\n审核留言：{comment}"
-    );
-    send_notification(cfg, to, "您的作品编辑请求未通过", &content).await
+    template: &NotificationTemplate,
+) -> anyhow::Result<Option<DeliveryReceipt>> {
+    let rendered = template.render(lang);
+    info!(template = template.name(), to = mask_address(to), ?lang, "Sending notification email");
+    send_notification(cfg, lang, to, &rendered.subject, &rendered.content).await
 }
 
+/// Keep the first character and the domain of the address for the logs, e.g. `m***@example.com`
+fn mask_address(address: &str) -> String {
+    match address.split_once('@') {
+        Some((local, domain)) => format!("{}***@{}", local.chars().next().unwrap_or_default(), domain),
+        None => "***".to_string(),
+    }
+}
 
 #[cfg(test)]
 mod test {
     use std::fs;
-    use crate::service::mailer::{send_review_approved_notification, send_review_rejected_notification, send_verification_code, EmailConfig};
+    use crate::service::mailer::{mask_address, render_magic_link, render_notification_html, render_verification_code, send_template, send_verification_code, EmailConfig};
+    use crate::service::notification_templates::NotificationTemplate;
+    use crate::web::i18n::Lang;
 
     #[tokio::test]
     async fn test() {
         let content = fs::read_to_string("config.yaml").unwrap();
         let value = serde_yaml::from_str::<serde_yaml::Value>(content.as_str()).unwrap();
         let cfg: EmailConfig = serde_yaml::from_value(value["email"].clone()).unwrap();
-        send_verification_code(&cfg, "mail@example.com", "114514").await.unwrap();
-        send_review_approved_notification(&cfg, "mail@example.com", "JM-1111", "哈基哈基2", "我不是神人", Some("非常好听")).await.unwrap();
-        send_review_rejected_notification(&cfg, "mail@example.com", "JM-1111", "哈基哈基", "我不是神人", "请修改标题").await.unwrap();
+        send_verification_code(&cfg, Lang::ZhCn, "mail@example.com", "114514").await.unwrap();
+        send_template(&cfg, Lang::ZhCn, "mail@example.com", &NotificationTemplate::sample("review_approved").unwrap()).await.unwrap();
+        send_template(&cfg, Lang::En, "mail@example.com", &NotificationTemplate::sample("review_rejected").unwrap()).await.unwrap();
+    }
+
+    #[test]
+    fn test_render() {
+        let (subject, plain, html) = render_verification_code(Lang::En, "114514").unwrap();
+        assert_eq!("Your verification code", subject);
+        assert!(plain.starts_with("114514 is your verification code"));
+        assert!(html.contains("lang=en") && html.contains("114514") && html.contains("valid for 5 minutes"));
+
+        let html = render_notification_html(Lang::ZhCn, "标题", "<b>第一行</b>\n\n第二行").unwrap();
+        assert!(html.contains("lang=zh") && html.contains("<title>标题</title>"));
+        assert!(html.contains("&#60;b&#62;第一行&#60;/b&#62;<br><br>第二行") || html.contains("&lt;b&gt;第一行&lt;/b&gt;<br><br>第二行"));
+        assert!(html.contains("此为系统邮件"));
+
+        let (_, plain, html) = render_magic_link(Lang::ZhCn, "https://example.com/?a=1&b=2", 15).unwrap();
+        assert!(plain.contains("15 分钟内有效") && plain.contains("\n\nhttps://example.com/?a=1&b=2\n\n"));
+        assert!(html.contains("<a href=\"https://example.com/?a=1&#38;b=2\">") || html.contains("<a href=\"https://example.com/?a=1&amp;b=2\">"));
+    }
+
+    #[test]
+    fn test_mask_address() {
+        assert_eq!("m***@example.com", mask_address("mail@example.com"));
+        assert_eq!("***", mask_address("not an address"));
+    }
+
+    #[test]
+    fn test_parse_fallbacks() {
+        let cfg: EmailConfig = serde_yaml::from_str(r#"
+            host: primary.example.com
+            username: u
+            password: p
+            no_reply_email: noreply@example.com
+            fallbacks:
+              - type: smtp
+                host: backup.example.com
+                username: u
+                password: p
+              - type: sendgrid
+                api_key: key
+        "#).unwrap();
+        let providers = cfg.transports().iter().map(|x| x.provider()).collect::<Vec<_>>();
+        assert_eq!(vec!["smtp:primary.example.com", "smtp:backup.example.com", "sendgrid"], providers);
     }
 }
\ No newline at end of file
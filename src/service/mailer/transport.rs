@@ -0,0 +1,151 @@
+use lettre::message::header::{ContentTransferEncoding, ContentType};
+use lettre::message::{Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use metrics::{counter, histogram};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A mail ready to be delivered by any transport
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub from_name: String,
+    pub from_email: String,
+    pub to: String,
+    pub subject: String,
+    pub plain: String,
+    pub html: String,
+}
+
+/// Which transport accepted the mail
+#[derive(Debug, Clone)]
+pub struct DeliveryReceipt {
+    pub provider: String,
+    /// The `Message-ID` header for SMTP, or the id assigned by the HTTP API provider.
+    /// Used to match the bounce and complaint events.
+    pub message_id: Option<String>,
+}
+
+/// A fallback transport in the `email.fallbacks` list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MailTransportCfg {
+    Smtp {
+        host: String,
+        username: String,
+        password: String,
+    },
+    /// The SendGrid v3 mail send API
+    Sendgrid {
+        api_key: String,
+        #[serde(default = "default_sendgrid_endpoint")]
+        endpoint: String,
+    },
+}
+
+fn default_sendgrid_endpoint() -> String {
+    "https://api.sendgrid.com/v3/mail/send".to_string()
+}
+
+impl MailTransportCfg {
+    /// The `provider` label of the delivery metrics
+    pub fn provider(&self) -> String {
+        match self {
+            MailTransportCfg::Smtp { host, .. } => format!("smtp:{host}"),
+            MailTransportCfg::Sendgrid { .. } => "sendgrid".to_string(),
+        }
+    }
+
+    /// Returns the message id if known
+    pub async fn send(&self, mail: &Mail) -> anyhow::Result<Option<String>> {
+        match self {
+            MailTransportCfg::Smtp { host, username, password } => {
+                send_smtp(host, username, password, mail).await
+            }
+            MailTransportCfg::Sendgrid { api_key, endpoint } => {
+                send_sendgrid(api_key, endpoint, mail).await
+            }
+        }
+    }
+}
+
+async fn send_smtp(host: &str, username: &str, password: &str, mail: &Mail) -> anyhow::Result<Option<String>> {
+    let domain = mail.from_email.rsplit('@').next().unwrap_or("localhost");
+    let message_id = format!("<{}@{}>", uuid::Uuid::new_v4(), domain);
+    let email_msg = lettre::Message::builder()
+        .message_id(Some(message_id.clone()))
+        .from(Mailbox::new(Some(mail.from_name.clone()), mail.from_email.parse()?))
+        .to(Mailbox::new(None, mail.to.parse()?))
+        .subject(&mail.subject)
+        .multipart(MultiPart::alternative()
+            .singlepart(SinglePart::plain(mail.plain.clone()))
+            .singlepart(SinglePart::builder()
+                .header(ContentType::TEXT_HTML)
+                .header(ContentTransferEncoding::Base64)
+                .body(mail.html.clone())
+            )
+        )?;
+
+    let creds = Credentials::new(username.to_string(), password.to_string());
+    let mailer = SmtpTransport::relay(host)?
+        .credentials(creds)
+        .timeout(Some(SMTP_TIMEOUT))
+        .build();
+    // The SMTP transport is blocking
+    tokio::task::spawn_blocking(move || mailer.send(&email_msg)).await??;
+    Ok(Some(message_id))
+}
+
+async fn send_sendgrid(api_key: &str, endpoint: &str, mail: &Mail) -> anyhow::Result<Option<String>> {
+    let body = serde_json::json!({
+        "personalizations": [{ "to": [{ "email": mail.to }] }],
+        "from": { "email": mail.from_email, "name": mail.from_name },
+        "subject": mail.subject,
+        "content": [
+            { "type": "text/plain", "value": mail.plain },
+            { "type": "text/html", "value": mail.html },
+        ],
+    });
+    let resp = reqwest::Client::new()
+        .post(endpoint)
+        .bearer_auth(api_key)
+        .timeout(HTTP_TIMEOUT)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+    let message_id = resp.headers().get("X-Message-Id")
+        .and_then(|x| x.to_str().ok())
+        .map(|x| x.to_string());
+    Ok(message_id)
+}
+
+/// Try the transports in order until one of them accepts the mail
+pub async fn deliver(transports: &[MailTransportCfg], mail: &Mail) -> anyhow::Result<DeliveryReceipt> {
+    let mut last_error = None;
+    for (index, transport) in transports.iter().enumerate() {
+        let provider = transport.provider();
+        let start = Instant::now();
+        let result = transport.send(mail).await;
+        histogram!("email_send_duration_seconds", "provider" => provider.clone()).record(start.elapsed().as_secs_f64());
+        match result {
+            Ok(message_id) => {
+                counter!("email_send_count", "provider" => provider.clone(), "result" => "ok").increment(1);
+                if index > 0 {
+                    counter!("email_failover_count").increment(1);
+                }
+                return Ok(DeliveryReceipt { provider, message_id });
+            }
+            Err(e) => {
+                warn!("Failed to send email with {provider}: {:?}", e);
+                counter!("email_send_count", "provider" => provider, "result" => "error").increment(1);
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No mail transport is configured")))
+}
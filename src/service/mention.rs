@@ -0,0 +1,135 @@
+use crate::db::mention::{IMentionDao, Mention, MentionDao};
+use crate::db::user::{IUserDao, UserDao};
+use crate::db::CrudDao;
+use crate::service::notification;
+use crate::web::state::AppState;
+use chrono::Utc;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use tracing::warn;
+
+/// At most this many users can be mentioned in one post or comment
+pub const MAX_MENTIONS: usize = 20;
+/// Longer names are not treated as mentions
+const MAX_NAME_CHARS: usize = 32;
+
+/// `@` at the start or after a non-word character, so email addresses are not matched.
+/// The name ends at a whitespace or a punctuation.
+static MENTION_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:^|[^\w@])@([^\s@,.!?;:()\[\]{}<>"'，。！？；：、（）【】「」《》]+)"#).unwrap()
+});
+
+/// A mentioned user, `name` is the name as written so the clients can find `@{name}` in the content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct MentionEntity {
+    pub uid: i64,
+    pub name: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum MentionError {
+    #[error("At most {MAX_MENTIONS} users can be mentioned")]
+    TooManyMentions,
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// The distinct mentioned names in the order they appear
+pub fn parse(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    MENTION_REGEX.captures_iter(content)
+        .map(|x| x[1].to_string())
+        .filter(|x| x.chars().count() <= MAX_NAME_CHARS)
+        .filter(|x| seen.insert(x.clone()))
+        .collect()
+}
+
+/// Parse the mentions of the content, the names of no user are plain text and skipped
+pub async fn resolve(pool: &PgPool, content: &str) -> Result<Vec<MentionEntity>, MentionError> {
+    let names = parse(content);
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    let users: HashMap<String, i64> = UserDao::list_by_usernames(pool, &names).await?
+        .into_iter()
+        .map(|x| (x.username, x.id))
+        .collect();
+    let mut mentions = Vec::with_capacity(names.len());
+    for name in names {
+        let Some(&uid) = users.get(&name) else { continue };
+        // Different names may never map to the same user, but keep one anyway
+        if mentions.iter().all(|x: &MentionEntity| x.uid != uid) {
+            mentions.push(MentionEntity { uid, name });
+        }
+    }
+    if mentions.len() > MAX_MENTIONS {
+        return Err(MentionError::TooManyMentions);
+    }
+    Ok(mentions)
+}
+
+/// Replace the mentions of the source, returns the newly mentioned users except the author
+pub async fn save(
+    conn: &mut PgConnection,
+    source_type: &str,
+    source_id: i64,
+    author_uid: i64,
+    mentions: &[MentionEntity],
+) -> sqlx::Result<Vec<i64>> {
+    let existing = MentionDao::list_by_sources(&mut *conn, source_type, &[source_id]).await?;
+    for x in existing.iter().filter(|x| mentions.iter().all(|m| m.uid != x.user_id)) {
+        MentionDao::delete_by_id(&mut *conn, x.id).await?;
+    }
+    let mut added = Vec::new();
+    for x in mentions.iter().filter(|m| existing.iter().all(|x| x.user_id != m.uid)) {
+        MentionDao::insert(&mut *conn, &Mention {
+            id: 0,
+            source_type: source_type.to_string(),
+            source_id,
+            user_id: x.uid,
+            name: x.name.clone(),
+            author_uid,
+            create_time: Utc::now(),
+        }).await?;
+        if x.uid != author_uid {
+            added.push(x.uid);
+        }
+    }
+    Ok(added)
+}
+
+/// The saved mentions of the sources
+pub async fn list_by_sources(pool: &PgPool, source_type: &str, source_ids: &[i64]) -> sqlx::Result<HashMap<i64, Vec<MentionEntity>>> {
+    let mut result: HashMap<i64, Vec<MentionEntity>> = HashMap::new();
+    for x in MentionDao::list_by_sources(pool, source_type, source_ids).await? {
+        result.entry(x.source_id).or_default().push(MentionEntity { uid: x.user_id, name: x.name });
+    }
+    Ok(result)
+}
+
+/// Notify the mentioned users, failing to notify is only logged
+pub async fn notify(state: &AppState, source_type: &str, source_id: i64, author_uid: i64, user_ids: &[i64]) {
+    let data = json!({ "source_type": source_type, "source_id": source_id });
+    if let Err(e) = notification::emit(state, user_ids, notification::TYPE_MENTION, Some(author_uid), data).await {
+        warn!("Failed to notify the mentions of {source_type} {source_id}: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(vec!["神人00000001", "bob"], parse("@神人00000001 你好，@bob。再次 @bob"));
+        assert_eq!(vec!["a", "b", "c"], parse("(@a)@b？ @c@d"));
+        assert!(parse("mail me at someone@example.com").is_empty());
+        assert!(parse("@ alone").is_empty());
+        assert!(parse(&format!("@{}", "x".repeat(MAX_NAME_CHARS + 1))).is_empty());
+    }
+}
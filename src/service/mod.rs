@@ -3,6 +3,7 @@ pub mod mailer;
 pub mod song_like;
 pub mod captcha;
 pub mod upload;
+pub mod image;
 pub mod song;
 pub mod recommend_v2;
 pub mod song_play;
@@ -12,3 +13,41 @@ pub mod user;
 pub mod playlist;
 pub mod contributor;
 pub mod connection_account;
+pub mod moderation;
+pub mod cache_warming;
+pub mod storage_events;
+pub mod email_delivery;
+pub mod preference;
+pub mod song_exclusion;
+pub mod crew;
+pub mod mention;
+pub mod notification;
+pub mod search_feedback;
+pub mod test_mode;
+pub mod notification_templates;
+pub mod linked_account;
+pub mod magic_link;
+pub mod qr_login;
+pub mod storage_quota;
+pub mod song_stats;
+pub mod near_duplicate;
+pub mod localization;
+pub mod review_guideline;
+pub mod oauth;
+pub mod shadow_ban;
+pub mod totp;
+pub mod follow;
+pub mod tag_alias;
+pub mod jobs;
+pub mod device_trust;
+pub mod transcode;
+pub mod tombstone;
+pub mod play_queue;
+pub mod song_share;
+pub mod legal;
+pub mod song_report;
+pub mod support_link;
+pub mod weekly_selection;
+pub mod retention;
+pub mod upload_cleanup;
+pub mod events;
@@ -0,0 +1,85 @@
+use crate::db::error::{retry_on_conflict, DbError};
+use crate::db::playlist::{IPlaylistDao, PlaylistDao};
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::user::{IUserDao, User, UserDao};
+use crate::db::CrudDao;
+use crate::search;
+use crate::service::{recommend_v2, song, user};
+use crate::web::routes;
+use crate::web::state::AppState;
+use chrono::Utc;
+use itertools::Itertools;
+use tracing::info;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SetBannedError {
+    #[error("User {uid} not found")]
+    UserNotFound { uid: i64 },
+    #[error(transparent)]
+    Db(#[from] DbError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Ban or unban the user, then apply the change to everywhere their content is read from.
+///
+/// Setting the current status again re-applies it, which repairs a previously failed apply.
+pub async fn set_banned(state: &AppState, uid: i64, banned: bool) -> Result<(), SetBannedError> {
+    let user = retry_on_conflict(|| async {
+        let mut user = UserDao::get_by_id(&state.sql_pool, uid).await?
+            .ok_or(DbError::NotFound)?;
+        if user.is_banned != banned {
+            user.is_banned = banned;
+            user.update_time = Utc::now();
+            user.version = UserDao::update_by_id_checked(&state.sql_pool, &user).await?;
+        }
+        Ok(user)
+    }).await.map_err(|e| match e {
+        DbError::NotFound => SetBannedError::UserNotFound { uid },
+        e => e.into(),
+    })?;
+
+    on_ban_changed(state, &user).await?;
+    Ok(())
+}
+
+/// Handle the change of `is_banned`.
+///
+/// The read paths check the ban status themselves, this drops everything cached before the change and
+/// removes or re-adds the content in the search indexes.
+async fn on_ban_changed(state: &AppState, user: &User) -> anyhow::Result<()> {
+    let songs = SongDao::list_by_user(&state.sql_pool, user.id).await?;
+    let song_ids = songs.iter().map(|x| x.id).collect_vec();
+    let playlist_ids = PlaylistDao::list_by_user(&state.sql_pool, user.id).await?
+        .into_iter().map(|x| x.id)
+        .collect_vec();
+    info!(
+        "Applying ban status {} of user {} to {} songs and {} playlists",
+        user.is_banned, user.id, song_ids.len(), playlist_ids.len()
+    );
+
+    user::evict_profile_cache(state.redis_conn.clone(), user.id).await?;
+    song::evict_detail_cache(state.redis_conn.clone(), &songs).await?;
+    routes::song::evict_page_by_user_cache(state.redis_conn.clone(), user.id).await?;
+    recommend_v2::evict_list_caches(state.redis_conn.clone()).await?;
+    recommend_v2::evict_recommend_caches(state.redis_conn.clone()).await?;
+
+    if user.is_banned {
+        if !song_ids.is_empty() {
+            search::song::delete_song_document(&state.meilisearch, &song_ids).await?;
+        }
+        if !playlist_ids.is_empty() {
+            search::playlist::delete_playlist_document(&state.meilisearch, &playlist_ids).await?;
+        }
+        search::user::delete_user_document(&state.meilisearch, user.id).await?;
+    } else {
+        if !song_ids.is_empty() {
+            search::song::add_or_replace_document(&state.meilisearch, &state.sql_pool, &song_ids).await?;
+        }
+        if !playlist_ids.is_empty() {
+            search::playlist::add_or_replace_document(&state.meilisearch, &state.sql_pool, &playlist_ids).await?;
+        }
+        search::user::sync_user_document(&state.meilisearch, &state.sql_pool, user).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! Finding the published songs whose title and lyrics are nearly the same as a submission, to spot re-uploads.
+//!
+//! The content is normalized (lowercased, only letters and digits, without the LRC tags) and split into
+//! character shingles, which works for Chinese and Japanese lyrics without segmentation. Its 64-bit SimHash is
+//! stored per song, and two songs within [MAX_DISTANCE] bits are considered duplicates. The hash is split into
+//! 4 bands of 16 bits for looking up, by the pigeonhole principle the duplicates share at least one band.
+
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::song_content_fingerprint::{ISongContentFingerprintDao, SongContentFingerprint, SongContentFingerprintDao};
+use crate::db::CrudDao;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use chrono::Utc;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// The max Hamming distance between the hashes of the duplicates, must be less than the number of bands
+pub const MAX_DISTANCE: u32 = 3;
+/// The shorter content is too common to be compared, e.g. a title without lyrics
+const MIN_CONTENT_CHARS: usize = 32;
+const SHINGLE_CHARS: usize = 3;
+/// The candidates sharing a band, the common bands of the unrelated songs are cut off
+const MAX_CANDIDATES: i64 = 200;
+const BACKFILL_BATCH_SIZE: i64 = 500;
+
+pub const BACKFILL_JOB: Job = Job {
+    name: "backfill_song_fingerprints",
+    schedule: "30 4 * * *",
+    max_jitter: Duration::from_secs(300),
+};
+
+fn normalize(text: &str) -> Vec<char> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            // The LRC timestamps and tags, e.g. `[00:12.34]`
+            '[' => in_tag = true,
+            ']' => in_tag = false,
+            '\n' => in_tag = false,
+            _ if !in_tag && c.is_alphanumeric() => result.extend(c.to_lowercase()),
+            _ => {}
+        }
+    }
+    result
+}
+
+/// The SimHash of the title and lyrics, `None` if the content is too short
+pub fn simhash(title: &str, lyrics: &str) -> Option<u64> {
+    let chars = normalize(&format!("{title}\n{lyrics}"));
+    if chars.len() < MIN_CONTENT_CHARS {
+        return None;
+    }
+    let mut weights = [0i32; 64];
+    for shingle in chars.windows(SHINGLE_CHARS) {
+        let shingle = shingle.iter().collect::<String>();
+        let digest = openssl::sha::sha256(shingle.as_bytes());
+        let hash = u64::from_le_bytes(digest[..8].try_into().unwrap());
+        for (i, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> i & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    Some(weights.iter().enumerate().fold(0, |acc, (i, weight)| if *weight > 0 { acc | 1 << i } else { acc }))
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+fn bands(hash: u64) -> [i32; 4] {
+    [0, 1, 2, 3].map(|i| (hash >> (i * 16) & 0xFFFF) as i32)
+}
+
+fn fingerprint_of(song_id: i64, title: &str, lyrics: &str) -> SongContentFingerprint {
+    let hash = simhash(title, lyrics);
+    let bands = hash.map(bands);
+    SongContentFingerprint {
+        song_id,
+        simhash: hash.map(|x| x as i64),
+        band0: bands.map(|x| x[0]),
+        band1: bands.map(|x| x[1]),
+        band2: bands.map(|x| x[2]),
+        band3: bands.map(|x| x[3]),
+        update_time: Utc::now(),
+    }
+}
+
+/// Save the fingerprint of a published song, should be called whenever the title or lyrics are changed
+pub async fn save_fingerprint<'e>(executor: impl PgExecutor<'e>, song_id: i64, title: &str, lyrics: &str) -> sqlx::Result<()> {
+    SongContentFingerprintDao::upsert(executor, &fingerprint_of(song_id, title, lyrics)).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SimilarSong {
+    pub song_id: i64,
+    pub display_id: String,
+    pub title: String,
+    /// The differing bits of the 64-bit hashes, 0 means the same content
+    pub distance: u32,
+}
+
+/// The published songs nearly the same as the content, nearest first
+pub async fn find_similar(
+    pool: &PgPool,
+    title: &str,
+    lyrics: &str,
+    exclude_song_id: Option<i64>,
+) -> anyhow::Result<Vec<SimilarSong>> {
+    let Some(hash) = simhash(title, lyrics) else {
+        return Ok(vec![]);
+    };
+    let candidates = SongContentFingerprintDao::list_by_bands(pool, bands(hash), MAX_CANDIDATES).await?;
+    let matched = candidates.into_iter()
+        .filter(|x| Some(x.song_id) != exclude_song_id)
+        .filter_map(|x| Some((x.song_id, distance(hash, x.simhash? as u64))))
+        .filter(|(_, d)| *d <= MAX_DISTANCE)
+        .collect::<Vec<_>>();
+    if matched.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let ids = matched.iter().map(|(id, _)| *id).collect_vec();
+    let songs = SongDao::list_by_ids(pool, &ids).await?;
+    let result = matched.into_iter()
+        .filter_map(|(id, distance)| {
+            songs.iter().find(|x| x.id == id).map(|x| SimilarSong {
+                song_id: x.id,
+                display_id: x.display_id.clone(),
+                title: x.title.clone(),
+                distance,
+            })
+        })
+        .sorted_by_key(|x| (x.distance, x.song_id))
+        .collect();
+    Ok(result)
+}
+
+/// Fingerprint the songs without fingerprints, returns the number of the fingerprinted songs
+pub async fn backfill(pool: &PgPool) -> anyhow::Result<usize> {
+    let mut count = 0;
+    loop {
+        let ids = SongContentFingerprintDao::list_song_ids_without_fingerprint(pool, BACKFILL_BATCH_SIZE).await?;
+        if ids.is_empty() {
+            break;
+        }
+        for id in &ids {
+            if let Some(song) = SongDao::get_by_id(pool, *id).await? {
+                save_fingerprint(pool, song.id, &song.title, &song.lyrics).await?;
+            }
+        }
+        count += ids.len();
+    }
+    Ok(count)
+}
+
+/// Fingerprint the missing songs daily until cancelled
+pub async fn run_backfill(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &BACKFILL_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            async move {
+                let count = backfill(&pool).await?;
+                if count > 0 {
+                    info!("Fingerprinted {count} songs");
+                }
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LYRICS: &str = "[00:01.00]哈基米哈基米哈基米南北绿豆\n[00:05.00]阿西噶阿西 哈呀库那路多\n[00:09.00]曼波曼波 哦马吉利曼波";
+
+    #[test]
+    fn test_simhash() {
+        let hash = simhash("哈基米", LYRICS).unwrap();
+        // The timestamps, punctuations and cases are ignored
+        let retimed = LYRICS.replace("00:0", "00:1").replace(' ', "，");
+        assert_eq!(hash, simhash("哈基米!", &retimed).unwrap());
+        // Changing a few characters moves it a bit, still much nearer than the unrelated content (about 32 bits)
+        let edited = LYRICS.replace("绿豆", "红豆");
+        assert!(distance(hash, simhash("哈基米", &edited).unwrap()) < 16);
+        // Unrelated lyrics are far away
+        let other = simhash("Another song", "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod").unwrap();
+        assert!(distance(hash, other) > MAX_DISTANCE);
+        // Too short
+        assert!(simhash("哈基米", "").is_none());
+    }
+
+    #[test]
+    fn test_bands() {
+        let hash = 0x0123_4567_89AB_CDEF;
+        assert_eq!([0xCDEF, 0x89AB, 0x4567, 0x0123], bands(hash));
+        // Within the max distance, at least one band is shared
+        let near = hash ^ (1 << 3) ^ (1 << 20) ^ (1 << 40);
+        assert!(bands(hash).iter().zip(bands(near)).any(|(a, b)| *a == b));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::db::notification::{Notification, NotificationDao};
+use crate::db::CrudDao;
+use crate::service::events;
+use crate::web::state::AppState;
+use chrono::Utc;
+use serde_json::json;
+use tracing::warn;
+
+/// `data`: `{"source_type": "post", "source_id": 1}`
+pub const TYPE_MENTION: &str = "mention";
+/// `data`: `{"review_id": 1, "review_type": 0, "song_display_id": "JM-AAA-001", "comment": "..."}`
+pub const TYPE_REVIEW_APPROVED: &str = "review_approved";
+/// `data`: the same as [TYPE_REVIEW_APPROVED]
+pub const TYPE_REVIEW_REJECTED: &str = "review_rejected";
+/// `data`: `{}`, the actor is the new follower
+pub const TYPE_FOLLOW: &str = "follow";
+/// `data`: `{"song_id": 1, "comment_id": 2, "parent_id": null}`, to the uploader for a root comment,
+/// or to the replied user for a reply
+pub const TYPE_COMMENT: &str = "comment";
+
+/// Create a notification of the type for each recipient, and push it to the online ones
+pub async fn emit(
+    state: &AppState,
+    user_ids: &[i64],
+    r#type: &str,
+    actor_uid: Option<i64>,
+    data: serde_json::Value,
+) -> sqlx::Result<()> {
+    let now = Utc::now();
+    let mut redis = state.redis_conn.clone();
+    for user_id in user_ids {
+        let id = NotificationDao::insert(&state.sql_pool, &Notification {
+            id: 0,
+            user_id: *user_id,
+            r#type: r#type.to_string(),
+            actor_uid,
+            data: data.clone(),
+            read_time: None,
+            create_time: now,
+        }).await?;
+        let event = json!({ "id": id, "type": r#type, "actor_uid": actor_uid, "data": data, "create_time": now });
+        events::publish(&mut redis, *user_id, events::EVENT_NOTIFICATION, event).await;
+    }
+    Ok(())
+}
+
+/// [emit] except to the actor, failing to notify is only logged since the action itself is done
+pub async fn notify(
+    state: &AppState,
+    user_ids: &[i64],
+    r#type: &str,
+    actor_uid: Option<i64>,
+    data: serde_json::Value,
+) {
+    let user_ids = user_ids.iter().copied().filter(|x| Some(*x) != actor_uid).collect::<Vec<_>>();
+    if user_ids.is_empty() {
+        return;
+    }
+    if let Err(e) = emit(state, &user_ids, r#type, actor_uid, data).await {
+        warn!("Failed to emit {type} notifications to {:?}: {:?}", user_ids, e);
+    }
+}
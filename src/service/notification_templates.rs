@@ -0,0 +1,347 @@
+//! The notification emails, each kind is a typed template rendered to the subject and the plain content.
+//!
+//! The HTML is wrapped by [crate::service::mailer::render_notification_html] when sending.
+//! Both are localized, the language is picked from the recipient's preference.
+
+use crate::web::i18n::Lang;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "template", rename_all = "snake_case")]
+pub enum NotificationTemplate {
+    /// To the uploader, the new song is published
+    ReviewApproved {
+        user_name: String,
+        song_display_id: String,
+        song_title: String,
+        comment: Option<String>,
+    },
+    /// To the uploader, the new song is sent back
+    ReviewRejected {
+        user_name: String,
+        song_display_id: String,
+        song_title: String,
+        comment: String,
+    },
+    /// To the uploader, the modification of a published song is applied
+    ReviewModifyApproved {
+        user_name: String,
+        song_display_id: String,
+        comment: Option<String>,
+    },
+    /// To the uploader, the modification of a published song is sent back
+    ReviewModifyRejected {
+        user_name: String,
+        song_display_id: String,
+        comment: String,
+    },
+    /// To a registered user credited in the production crew
+    CrewInvitation {
+        user_name: String,
+        song_display_id: String,
+        song_title: String,
+        uploader_name: String,
+    },
+    /// To the uploader and the contributors, someone commented on the review
+    ReviewComment {
+        actor_name: String,
+        song_display_id: String,
+        content: String,
+    },
+    /// To the contributors, the uploader updated the review
+    ReviewModified {
+        actor_name: String,
+        song_display_id: String,
+        note: Option<String>,
+    },
+    /// To the maintainer, a new review is submitted
+    ReviewPending {
+        song_title: String,
+        author: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderedNotification {
+    pub subject: String,
+    /// The plain text, the line breaks are kept in the HTML
+    pub content: String,
+}
+
+pub const TEMPLATE_NAMES: &[&str] = &[
+    "review_approved",
+    "review_rejected",
+    "review_modify_approved",
+    "review_modify_rejected",
+    "crew_invitation",
+    "review_comment",
+    "review_modified",
+    "review_pending",
+];
+
+fn format_comment(lang: Lang, label: &str, comment: Option<&str>) -> String {
+    let colon = match lang {
+        Lang::ZhCn => "：",
+        Lang::En => ": ",
+    };
+    comment.map(|x| format!("\n\n{label}{colon}{x}")).unwrap_or_default()
+}
+
+impl NotificationTemplate {
+    /// The snake case name, the same as the `template` tag in JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationTemplate::ReviewApproved { .. } => "review_approved",
+            NotificationTemplate::ReviewRejected { .. } => "review_rejected",
+            NotificationTemplate::ReviewModifyApproved { .. } => "review_modify_approved",
+            NotificationTemplate::ReviewModifyRejected { .. } => "review_modify_rejected",
+            NotificationTemplate::CrewInvitation { .. } => "crew_invitation",
+            NotificationTemplate::ReviewComment { .. } => "review_comment",
+            NotificationTemplate::ReviewModified { .. } => "review_modified",
+            NotificationTemplate::ReviewPending { .. } => "review_pending",
+        }
+    }
+
+    pub fn render(&self, lang: Lang) -> RenderedNotification {
+        let (subject, content) = match lang {
+            Lang::ZhCn => self.render_zh(),
+            Lang::En => self.render_en(),
+        };
+        RenderedNotification { subject, content }
+    }
+
+    fn render_zh(&self) -> (String, String) {
+        let lang = Lang::ZhCn;
+        match self {
+            NotificationTemplate::ReviewApproved { user_name, song_display_id, song_title, comment } => (
+                "您提交的作品已通过审核".to_string(),
+                format!(
+                    "亲爱的 {user_name}：\n\n您提交的作品《{song_title}》({song_display_id}) 已通过审核。感谢您的投稿！{}",
+                    format_comment(lang, "审核留言", comment.as_deref()),
+                ),
+            ),
+            NotificationTemplate::ReviewRejected { user_name, song_display_id, song_title, comment } => (
+                "您提交的作品已被退回".to_string(),
+                format!("亲爱的 {user_name}：\n\n很抱歉，您提交的作品《{song_title}》({song_display_id}) 已被退回。\n\n审核留言：{comment}"),
+            ),
+            NotificationTemplate::ReviewModifyApproved { user_name, song_display_id, comment } => (
+                "您的作品编辑请求已通过".to_string(),
+                format!(
+                    "亲爱的 {user_name}：\n\n您的作品编辑请求 ({song_display_id}) 已通过。{}",
+                    format_comment(lang, "审核留言", comment.as_deref()),
+                ),
+            ),
+            NotificationTemplate::ReviewModifyRejected { user_name, song_display_id, comment } => (
+                "您的作品编辑请求未通过".to_string(),
+                format!("亲爱的 {user_name}：\n\n很抱歉，您的作品编辑请求 ({song_display_id}) 未通过。\n\n审核留言：{comment}"),
+            ),
+            NotificationTemplate::CrewInvitation { user_name, song_display_id, song_title, uploader_name } => (
+                "您被署名为作品的制作人员".to_string(),
+                format!("亲爱的 {user_name}：\n\n{uploader_name} 在作品《{song_title}》({song_display_id}) 的制作人员中署名了您。请在客户端中确认或拒绝该署名，确认前署名将显示为待确认。"),
+            ),
+            NotificationTemplate::ReviewComment { actor_name, song_display_id, content } => (
+                format!("稿件评论更新：{song_display_id}"),
+                format!("{actor_name} 在稿件 {song_display_id} 下发表了新评论：\n\n{content}"),
+            ),
+            NotificationTemplate::ReviewModified { actor_name, song_display_id, note } => (
+                format!("稿件已更新：{song_display_id}"),
+                format!("{actor_name} 更新了稿件 {song_display_id}。{}", format_comment(lang, "备注", note.as_deref())),
+            ),
+            NotificationTemplate::ReviewPending { song_title, author } => (
+                "有新的稿件待审核".to_string(),
+                format!("{song_title} - {author}"),
+            ),
+        }
+    }
+
+    fn render_en(&self) -> (String, String) {
+        let lang = Lang::En;
+        match self {
+            NotificationTemplate::ReviewApproved { user_name, song_display_id, song_title, comment } => (
+                "Your submission has been approved".to_string(),
+                format!(
+                    "Dear {user_name},\n\nYour submission \"{song_title}\" ({song_display_id}) has been approved. Thanks for sharing!{}",
+                    format_comment(lang, "Reviewer's comment", comment.as_deref()),
+                ),
+            ),
+            NotificationTemplate::ReviewRejected { user_name, song_display_id, song_title, comment } => (
+                "Your submission has been sent back".to_string(),
+                format!("Dear {user_name},\n\nSorry, your submission \"{song_title}\" ({song_display_id}) has been sent back.\n\nReviewer's comment: {comment}"),
+            ),
+            NotificationTemplate::ReviewModifyApproved { user_name, song_display_id, comment } => (
+                "Your edit request has been approved".to_string(),
+                format!(
+                    "Dear {user_name},\n\nYour edit request ({song_display_id}) has been approved.{}",
+                    format_comment(lang, "Reviewer's comment", comment.as_deref()),
+                ),
+            ),
+            NotificationTemplate::ReviewModifyRejected { user_name, song_display_id, comment } => (
+                "Your edit request has been declined".to_string(),
+                format!("Dear {user_name},\n\nSorry, your edit request ({song_display_id}) has been declined.\n\nReviewer's comment: {comment}"),
+            ),
+            NotificationTemplate::CrewInvitation { user_name, song_display_id, song_title, uploader_name } => (
+                "You are credited in a production crew".to_string(),
+                format!("Dear {user_name},\n\n{uploader_name} credited you in the production crew of \"{song_title}\" ({song_display_id}). Please confirm or decline it in the app, the credit is shown as pending until confirmed."),
+            ),
+            NotificationTemplate::ReviewComment { actor_name, song_display_id, content } => (
+                format!("New comment on the review: {song_display_id}"),
+                format!("{actor_name} commented on the review {song_display_id}:\n\n{content}"),
+            ),
+            NotificationTemplate::ReviewModified { actor_name, song_display_id, note } => (
+                format!("Review updated: {song_display_id}"),
+                format!("{actor_name} updated the review {song_display_id}.{}", format_comment(lang, "Note", note.as_deref())),
+            ),
+            NotificationTemplate::ReviewPending { song_title, author } => (
+                "A new submission is pending review".to_string(),
+                format!("{song_title} - {author}"),
+            ),
+        }
+    }
+
+    /// The template filled with the sample data for previewing, `None` if the name is unknown
+    pub fn sample(name: &str) -> Option<Self> {
+        let user_name = "哈基米".to_string();
+        let song_display_id = "JM-ABCD-001".to_string();
+        let song_title = "南北绿豆".to_string();
+        let template = match name {
+            "review_approved" => NotificationTemplate::ReviewApproved {
+                user_name,
+                song_display_id,
+                song_title,
+                comment: Some("非常好听".to_string()),
+            },
+            "review_rejected" => NotificationTemplate::ReviewRejected {
+                user_name,
+                song_display_id,
+                song_title,
+                comment: "请补充原作信息".to_string(),
+            },
+            "review_modify_approved" => NotificationTemplate::ReviewModifyApproved {
+                user_name,
+                song_display_id,
+                comment: None,
+            },
+            "review_modify_rejected" => NotificationTemplate::ReviewModifyRejected {
+                user_name,
+                song_display_id,
+                comment: "封面不符合规范".to_string(),
+            },
+            "crew_invitation" => NotificationTemplate::CrewInvitation {
+                user_name,
+                song_display_id,
+                song_title,
+                uploader_name: "曼波".to_string(),
+            },
+            "review_comment" => NotificationTemplate::ReviewComment {
+                actor_name: user_name,
+                song_display_id,
+                content: "标题是否需要修改？".to_string(),
+            },
+            "review_modified" => NotificationTemplate::ReviewModified {
+                actor_name: user_name,
+                song_display_id,
+                note: Some("已修改标题".to_string()),
+            },
+            "review_pending" => NotificationTemplate::ReviewPending {
+                song_title,
+                author: user_name,
+            },
+            _ => return None,
+        };
+        Some(template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(name: &str) -> RenderedNotification {
+        NotificationTemplate::sample(name).unwrap().render(Lang::ZhCn)
+    }
+
+    #[test]
+    fn test_samples() {
+        for name in TEMPLATE_NAMES {
+            let template = NotificationTemplate::sample(name).unwrap();
+            assert_eq!(*name, template.name());
+            let json = serde_json::to_value(&template).unwrap();
+            assert_eq!(*name, json["template"]);
+        }
+        assert!(NotificationTemplate::sample("unknown").is_none());
+    }
+
+    #[test]
+    fn test_review_approved() {
+        let x = render("review_approved");
+        assert_eq!("您提交的作品已通过审核", x.subject);
+        assert!(x.content.contains("《南北绿豆》(JM-ABCD-001) 已通过审核"));
+        assert!(x.content.ends_with("审核留言：非常好听"));
+    }
+
+    #[test]
+    fn test_review_rejected() {
+        let x = render("review_rejected");
+        assert_eq!("您提交的作品已被退回", x.subject);
+        assert!(x.content.contains("《南北绿豆》(JM-ABCD-001) 已被退回"));
+        assert!(x.content.ends_with("审核留言：请补充原作信息"));
+    }
+
+    #[test]
+    fn test_review_modify_approved() {
+        let x = render("review_modify_approved");
+        assert_eq!("您的作品编辑请求已通过", x.subject);
+        // No comment section without the comment
+        assert!(x.content.ends_with("(JM-ABCD-001) 已通过。"));
+    }
+
+    #[test]
+    fn test_review_modify_rejected() {
+        let x = render("review_modify_rejected");
+        assert_eq!("您的作品编辑请求未通过", x.subject);
+        assert!(x.content.ends_with("审核留言：封面不符合规范"));
+    }
+
+    #[test]
+    fn test_crew_invitation() {
+        let x = render("crew_invitation");
+        assert_eq!("您被署名为作品的制作人员", x.subject);
+        assert!(x.content.contains("曼波 在作品《南北绿豆》(JM-ABCD-001) 的制作人员中署名了您"));
+    }
+
+    #[test]
+    fn test_review_comment() {
+        let x = render("review_comment");
+        assert_eq!("稿件评论更新：JM-ABCD-001", x.subject);
+        assert_eq!("哈基米 在稿件 JM-ABCD-001 下发表了新评论：\n\n标题是否需要修改？", x.content);
+    }
+
+    #[test]
+    fn test_review_modified() {
+        let x = render("review_modified");
+        assert_eq!("稿件已更新：JM-ABCD-001", x.subject);
+        assert_eq!("哈基米 更新了稿件 JM-ABCD-001。\n\n备注：已修改标题", x.content);
+    }
+
+    #[test]
+    fn test_review_pending() {
+        let x = render("review_pending");
+        assert_eq!("有新的稿件待审核", x.subject);
+        assert_eq!("南北绿豆 - 哈基米", x.content);
+    }
+
+    #[test]
+    fn test_render_en() {
+        let x = NotificationTemplate::sample("review_approved").unwrap().render(Lang::En);
+        assert_eq!("Your submission has been approved", x.subject);
+        assert!(x.content.contains("\"南北绿豆\" (JM-ABCD-001) has been approved"));
+        assert!(x.content.ends_with("Reviewer's comment: 非常好听"));
+
+        // Every template has both languages
+        for name in TEMPLATE_NAMES {
+            let template = NotificationTemplate::sample(name).unwrap();
+            assert_ne!(template.render(Lang::ZhCn).subject, template.render(Lang::En).subject);
+        }
+    }
+}
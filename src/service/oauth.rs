@@ -0,0 +1,425 @@
+//! Logging in by the GitHub and Google accounts (OAuth 2.0 authorization code flow).
+//!
+//! The client gets the authorize URL with a random `state` kept in Redis for [STATE_TTL_SECS], the provider
+//! redirects the browser to the configured page with the `code` and `state`, and the page posts them back to
+//! exchange the code. The `state` is single-use and bound to the provider, and to the user if it's for linking.
+//!
+//! An identity already linked logs in its user. Otherwise, the user with the same verified email gets the identity
+//! linked, or a new user without password is registered by it.
+//!
+//! The code can't be exchanged again, so if the user enabled the 2FA, the callback returns a ticket of the
+//! [PendingLogin] instead, and the login is finished by posting the ticket with the 2FA code.
+
+use crate::config::Config;
+use crate::db::error::DbError;
+use crate::db::user::{IUserDao, User, UserDao};
+use crate::db::user_oauth_identity::{IUserOAuthIdentityDao, UserOAuthIdentity, UserOAuthIdentityDao};
+use crate::db::CrudDao;
+use crate::service::linked_account::{self, LinkError};
+use crate::web::i18n;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use reqwest::header;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub const STATE_TTL_SECS: u64 = 600;
+const USER_AGENT: &str = "hachimi-world-server";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Optional `oauth` section of the config file, a provider is disabled if it's absent.
+///
+/// ```yaml
+/// oauth:
+///   github:
+///     client_id: abcdef
+///     client_secret: abcdef
+///     # The page calls `/auth/oauth/callback` with the `code` and `state` in its query
+///     redirect_url: https://hachimi.world/login/oauth/github
+///   google:
+///     client_id: abcdef.apps.googleusercontent.com
+///     client_secret: abcdef
+///     redirect_url: https://hachimi.world/login/oauth/google
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthCfg {
+    pub github: Option<OAuthProviderCfg>,
+    pub google: Option<OAuthProviderCfg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthProviderCfg {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+}
+
+impl OAuthCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("oauth")?.is_some() {
+            Ok(Some(config.get_and_parse("oauth")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn provider(&self, provider: Provider) -> Option<&OAuthProviderCfg> {
+        match provider {
+            Provider::Github => self.github.as_ref(),
+            Provider::Google => self.google.as_ref(),
+        }
+    }
+}
+
+/// The provider of the name if it's configured
+pub fn load_provider(config: &Config, name: &str) -> anyhow::Result<Option<(Provider, OAuthProviderCfg)>> {
+    let Some(provider) = Provider::parse(name) else {
+        return Ok(None);
+    };
+    let cfg = OAuthCfg::load(config)?.and_then(|x| x.provider(provider).cloned());
+    Ok(cfg.map(|x| (provider, x)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Github,
+    Google,
+}
+
+impl Provider {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "github" => Some(Self::Github),
+            "google" => Some(Self::Google),
+            _ => None,
+        }
+    }
+
+    /// Saved as the `provider` of the identities
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Github => "github",
+            Self::Google => "google",
+        }
+    }
+
+    fn authorize_endpoint(&self) -> &'static str {
+        match self {
+            Self::Github => "https://github.com/login/oauth/authorize",
+            Self::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    fn token_endpoint(&self) -> &'static str {
+        match self {
+            Self::Github => "https://github.com/login/oauth/access_token",
+            Self::Google => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    fn scopes(&self) -> &'static str {
+        match self {
+            Self::Github => "read:user user:email",
+            Self::Google => "openid email profile",
+        }
+    }
+}
+
+/// The profile of the provider account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    /// Only the verified email, it's trusted for linking the existing user
+    pub email: Option<String>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OAuthError {
+    #[error("The authorization is invalid or expired, please try again")]
+    InvalidCode,
+    #[error("Failed to request the provider: {0}")]
+    ProviderApi(#[from] reqwest::Error),
+    #[error(transparent)]
+    Link(#[from] LinkError),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub fn authorize_url(provider: Provider, cfg: &OAuthProviderCfg, state: &str) -> String {
+    let query = serde_urlencoded::to_string([
+        ("client_id", cfg.client_id.as_str()),
+        ("redirect_uri", cfg.redirect_url.as_str()),
+        ("response_type", "code"),
+        ("scope", provider.scopes()),
+        ("state", state),
+    ]).unwrap();
+    format!("{}?{}", provider.authorize_endpoint(), query)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthState {
+    pub provider: Provider,
+    /// The user linking the account, `None` for logging in
+    pub uid: Option<i64>,
+}
+
+/// Create a `state` for the authorize URL
+pub async fn create_state(redis: &mut ConnectionManager, value: &OAuthState) -> anyhow::Result<String> {
+    let state = hex::encode(rand::random::<[u8; 16]>());
+    let _: () = redis.set_ex(get_state_key(&state), serde_json::to_string(value)?, STATE_TTL_SECS).await?;
+    Ok(state)
+}
+
+/// Consume the `state`, returns `None` if it's used or expired
+pub async fn consume_state(redis: &mut ConnectionManager, state: &str) -> anyhow::Result<Option<OAuthState>> {
+    let saved: Option<String> = redis.get_del(get_state_key(state)).await?;
+    Ok(saved.map(|x| serde_json::from_str(&x)).transpose()?)
+}
+
+fn get_state_key(state: &str) -> String {
+    format!("oauth:state:{state}")
+}
+
+/// The login signed in by the provider and waiting for the second factor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub uid: i64,
+    pub first_access: bool,
+}
+
+/// Create a ticket of the login valid for [STATE_TTL_SECS]
+pub async fn create_pending_login(redis: &mut ConnectionManager, value: &PendingLogin) -> anyhow::Result<String> {
+    let ticket = hex::encode(rand::random::<[u8; 16]>());
+    let _: () = redis.set_ex(get_pending_login_key(&ticket), serde_json::to_string(value)?, STATE_TTL_SECS).await?;
+    Ok(ticket)
+}
+
+/// Returns `None` if it's used or expired. It's kept, so the user can retry after a wrong code
+pub async fn get_pending_login(redis: &mut ConnectionManager, ticket: &str) -> anyhow::Result<Option<PendingLogin>> {
+    let saved: Option<String> = redis.get(get_pending_login_key(ticket)).await?;
+    Ok(saved.map(|x| serde_json::from_str(&x)).transpose()?)
+}
+
+/// Returns false if it's already consumed
+pub async fn consume_pending_login(redis: &mut ConnectionManager, ticket: &str) -> anyhow::Result<bool> {
+    let deleted: u64 = redis.del(get_pending_login_key(ticket)).await?;
+    Ok(deleted > 0)
+}
+
+fn get_pending_login_key(ticket: &str) -> String {
+    format!("oauth:pending_login:{ticket}")
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResp {
+    access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubUser {
+    id: i64,
+    login: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleUserInfo {
+    sub: String,
+    name: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+fn github_profile(user: GithubUser, emails: Vec<GithubEmail>) -> Profile {
+    Profile {
+        id: user.id.to_string(),
+        name: user.login,
+        email: emails.into_iter().find(|x| x.primary && x.verified).map(|x| x.email),
+    }
+}
+
+fn google_profile(info: GoogleUserInfo) -> Profile {
+    Profile {
+        name: info.name.or_else(|| info.email.clone()).unwrap_or_else(|| info.sub.clone()),
+        email: info.email.filter(|_| info.email_verified),
+        id: info.sub,
+    }
+}
+
+fn client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder().user_agent(USER_AGENT).timeout(REQUEST_TIMEOUT).build()
+}
+
+async fn get_json<T: DeserializeOwned>(client: &reqwest::Client, url: &str, access_token: &str) -> reqwest::Result<T> {
+    client.get(url)
+        .bearer_auth(access_token)
+        .header(header::ACCEPT, "application/json")
+        .send().await?
+        .error_for_status()?
+        .json().await
+}
+
+/// Exchange the code for the profile of the provider account
+pub async fn fetch_profile(provider: Provider, cfg: &OAuthProviderCfg, code: &str) -> Result<Profile, OAuthError> {
+    let client = client()?;
+    let form = serde_urlencoded::to_string([
+        ("client_id", cfg.client_id.as_str()),
+        ("client_secret", cfg.client_secret.as_str()),
+        ("code", code),
+        ("redirect_uri", cfg.redirect_url.as_str()),
+        ("grant_type", "authorization_code"),
+    ]).map_err(anyhow::Error::from)?;
+    let resp = client.post(provider.token_endpoint())
+        .header(header::ACCEPT, "application/json")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(form)
+        .send().await?;
+    // Both providers reject an invalid code, GitHub by an `error` field with 200 and Google by 400
+    if resp.status().is_client_error() {
+        return Err(OAuthError::InvalidCode);
+    }
+    let Some(access_token) = resp.error_for_status()?.json::<TokenResp>().await?.access_token else {
+        return Err(OAuthError::InvalidCode);
+    };
+
+    let profile = match provider {
+        Provider::Github => {
+            let user = get_json(&client, "https://api.github.com/user", &access_token).await?;
+            let emails = get_json(&client, "https://api.github.com/user/emails", &access_token).await?;
+            github_profile(user, emails)
+        }
+        Provider::Google => {
+            google_profile(get_json(&client, "https://openidconnect.googleapis.com/v1/userinfo", &access_token).await?)
+        }
+    };
+    Ok(profile)
+}
+
+fn identity_of(provider: Provider, profile: &Profile) -> UserOAuthIdentity {
+    let now = Utc::now();
+    UserOAuthIdentity {
+        id: 0,
+        user_id: 0,
+        provider: provider.name().to_string(),
+        provider_user_id: profile.id.clone(),
+        provider_user_name: profile.name.clone(),
+        provider_email: profile.email.clone(),
+        create_time: now,
+        last_login_time: Some(now),
+    }
+}
+
+/// Link the provider account to the user
+pub async fn link(pool: &PgPool, uid: i64, provider: Provider, profile: &Profile, ip: Option<&str>) -> Result<i64, LinkError> {
+    linked_account::link(pool, uid, &UserOAuthIdentity { last_login_time: None, ..identity_of(provider, profile) }, ip).await
+}
+
+pub struct SignIn {
+    pub user: User,
+    /// The user is registered by this login
+    pub first_access: bool,
+}
+
+/// Find or register the user of the provider account, `username` is taken for the new user
+pub async fn sign_in(
+    pool: &PgPool,
+    provider: Provider,
+    profile: &Profile,
+    username: String,
+    ip: Option<&str>,
+) -> Result<SignIn, OAuthError> {
+    if let Some(identity) = UserOAuthIdentityDao::get_by_provider_user_id(pool, provider.name(), &profile.id).await? {
+        UserOAuthIdentityDao::update_last_login_time(pool, identity.id, Utc::now()).await?;
+        let user = UserDao::get_by_id(pool, identity.user_id).await?.ok_or(sqlx::Error::RowNotFound)?;
+        return Ok(SignIn { user, first_access: false });
+    }
+
+    let identity = identity_of(provider, profile);
+    if let Some(email) = &profile.email && let Some(user) = UserDao::get_by_email(pool, email).await? {
+        linked_account::link(pool, user.id, &identity, ip).await?;
+        return Ok(SignIn { user, first_access: false });
+    }
+
+    let now = Utc::now();
+    let mut user = User {
+        id: 0,
+        username,
+        // The email is required by the users, the placeholder is never verified or sent to
+        email: profile.email.clone().unwrap_or_else(|| format!("{}.{}@oauth.invalid", provider.name(), profile.id)),
+        // No password until the user sets one, see [linked_account::has_password]
+        password_hash: String::new(),
+        avatar_url: None,
+        bio: None,
+        gender: None,
+        is_banned: false,
+        last_login_time: None,
+        create_time: now,
+        update_time: now,
+        version: 0,
+        preferred_language: Some(i18n::current_lang().tag().to_string()),
+    };
+    let mut tx = pool.begin().await?;
+    user.id = match UserDao::insert(&mut *tx, &user).await.map_err(DbError::from) {
+        Ok(x) => x,
+        // Registered by the same account concurrently
+        Err(e) if e.is_unique_violation_of("users_email_key") => return Err(LinkError::LinkedToOther.into()),
+        Err(e) => return Err(anyhow::Error::from(e).into()),
+    };
+    linked_account::link_in(&mut tx, user.id, &identity, ip).await?;
+    tx.commit().await?;
+    Ok(SignIn { user, first_access: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorize_url() {
+        let cfg = OAuthProviderCfg {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: "https://example.com/login/oauth/github?a=1".to_string(),
+        };
+        assert_eq!(
+            "https://github.com/login/oauth/authorize?client_id=id&redirect_uri=https%3A%2F%2Fexample.com%2Flogin%2Foauth%2Fgithub%3Fa%3D1&response_type=code&scope=read%3Auser+user%3Aemail&state=abc",
+            authorize_url(Provider::Github, &cfg, "abc"),
+        );
+    }
+
+    #[test]
+    fn test_profile() {
+        let user = GithubUser { id: 42, login: "hachimi".to_string() };
+        let emails = vec![
+            GithubEmail { email: "a@example.com".to_string(), primary: false, verified: true },
+            GithubEmail { email: "b@example.com".to_string(), primary: true, verified: true },
+        ];
+        assert_eq!(Profile {
+            id: "42".to_string(),
+            name: "hachimi".to_string(),
+            email: Some("b@example.com".to_string()),
+        }, github_profile(user, emails));
+
+        // The unverified email is dropped
+        let info: GoogleUserInfo = serde_json::from_str(r#"{"sub": "1", "email": "c@example.com", "email_verified": false}"#).unwrap();
+        assert_eq!(Profile {
+            id: "1".to_string(),
+            name: "c@example.com".to_string(),
+            email: None,
+        }, google_profile(info));
+    }
+}
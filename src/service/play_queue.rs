@@ -0,0 +1,90 @@
+//! The play queue of a user synced across the devices, kept in Redis only since it's disposable.
+//!
+//! Every save increases the revision, and is rejected unless it's based on the current revision,
+//! so a device holding a stale queue can't overwrite the one saved by another device.
+
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionManager;
+use redis::Script;
+use serde::{Deserialize, Serialize};
+
+/// At most this many songs in the queue
+pub const MAX_QUEUE_SONGS: usize = 1000;
+/// The queue is forgotten if not saved for a week
+const QUEUE_TTL_SECS: i64 = 7 * 24 * 3600;
+
+/// Save the queue if the revision is still `ARGV[1]`, returns `{saved, revision}`
+const SAVE_SCRIPT: &str = r"
+local current = tonumber(redis.call('HGET', KEYS[1], 'revision') or '0')
+if current ~= tonumber(ARGV[1]) then
+    return {0, current}
+end
+redis.call('HSET', KEYS[1], 'revision', current + 1, 'data', ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[3])
+return {1, current + 1}
+";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayQueue {
+    pub song_ids: Vec<i64>,
+    /// The index of the playing song in `song_ids`
+    pub current_index: usize,
+    /// The progress of the playing song
+    pub position_ms: u64,
+    /// The device saving the queue, for display only
+    pub device: Option<String>,
+    pub update_time: DateTime<Utc>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SaveQueueError {
+    #[error("At most {MAX_QUEUE_SONGS} songs can be queued")]
+    TooManySongs,
+    #[error("The current index is out of the queue")]
+    InvalidIndex,
+    #[error("The queue has been saved by another device, the current revision is {0}")]
+    Conflict(i64),
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+}
+
+fn queue_key(uid: i64) -> String {
+    format!("player:queue:{}", uid)
+}
+
+/// The saved queue with its revision, or revision 0 if there is none
+pub async fn get(mut redis: ConnectionManager, uid: i64) -> anyhow::Result<(i64, Option<PlayQueue>)> {
+    let (revision, data): (Option<i64>, Option<String>) = redis::cmd("HMGET")
+        .arg(queue_key(uid))
+        .arg("revision")
+        .arg("data")
+        .query_async(&mut redis)
+        .await?;
+    let queue = data.map(|x| serde_json::from_str(&x)).transpose()?;
+    Ok((revision.unwrap_or(0), queue))
+}
+
+/// Save the queue based on the revision, returns the new revision
+pub async fn save(mut redis: ConnectionManager, uid: i64, base_revision: i64, queue: &PlayQueue) -> Result<i64, SaveQueueError> {
+    if queue.song_ids.len() > MAX_QUEUE_SONGS {
+        return Err(SaveQueueError::TooManySongs);
+    }
+    // An empty queue is saved with the index 0
+    if queue.current_index >= queue.song_ids.len().max(1) {
+        return Err(SaveQueueError::InvalidIndex);
+    }
+    let (saved, revision): (bool, i64) = Script::new(SAVE_SCRIPT)
+        .key(queue_key(uid))
+        .arg(base_revision)
+        .arg(serde_json::to_string(queue)?)
+        .arg(QUEUE_TTL_SECS)
+        .invoke_async(&mut redis)
+        .await?;
+    if !saved {
+        return Err(SaveQueueError::Conflict(revision));
+    }
+    Ok(revision)
+}
+
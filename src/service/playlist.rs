@@ -1,16 +1,53 @@
+use crate::config::Config;
+use crate::db::error::{begin_serializable, retry_on_conflict};
 use crate::db::playlist::{IPlaylistDao, Playlist, PlaylistDao, PlaylistSong};
+use crate::db::song::{ISongDao, Song, SongDao};
+use crate::db::localized_title;
 use crate::db::CrudDao;
 use crate::service::playlist::GetDetailError::{CreatorUserNotFound, NotFound, NotOwner};
-use crate::service::{song, user};
+use crate::service::image::{ImageCfg, ImageProcessOptions};
+use crate::service::{image, localization, song, user};
+use crate::util::{fractional_index, IsBlank};
+use crate::util::scheduler::{Job, Scheduler};
 use crate::web::routes::playlist::{DetailResp, PlaylistItem, SongItem};
 use crate::web::state::AppState;
 use axum::extract::State;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use redis::aio::ConnectionManager;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Optional `playlist` section of the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistCfg {
+    #[serde(default = "default_max_songs")]
+    pub max_songs: i64,
+}
+
+fn default_max_songs() -> i64 { 1000 }
+
+impl Default for PlaylistCfg {
+    fn default() -> Self {
+        PlaylistCfg { max_songs: default_max_songs() }
+    }
+}
+
+impl PlaylistCfg {
+    /// Load the `playlist` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("playlist")?.is_some() {
+            config.get_and_parse("playlist")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
 
 #[derive(thiserror::Error, Debug)]
 pub enum GetDetailError {
@@ -41,17 +78,27 @@ pub async fn get_detail(state: &State<AppState>, uid: Option<i64>, playlist_id:
 
     let playlist_songs = PlaylistDao::list_songs(&state.sql_pool, playlist.id).await?;
     let song_ids = playlist_songs.iter().map(|song| song.song_id).collect_vec();
-    let playlist_songs_map: HashMap<i64, PlaylistSong> = playlist_songs.into_iter().map(|x| (x.song_id, x)).collect();
+    // The order index is the position in the playlist
+    let playlist_songs_map: HashMap<i64, (usize, PlaylistSong)> = playlist_songs.into_iter()
+        .enumerate()
+        .map(|(i, x)| (x.song_id, (i, x)))
+        .collect();
 
     let songs = song::get_public_detail_with_cache(state.redis_conn.clone(), &state.sql_pool, &song_ids).await?;
     let creator_user = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &[playlist.user_id]).await?
         .remove(&playlist.user_id)
         .ok_or_else(|| CreatorUserNotFound { playlist_id })?; // This should never happen
 
+    // Playlists of banned users are hidden from others
+    if creator_user.is_banned && uid != Some(playlist.user_id) {
+        return Err(NotFound { playlist_id });
+    }
+
     let mut result = Vec::<SongItem>::new();
 
-    for (_, song) in songs {
-        if let Some(ps) = playlist_songs_map.get(&song.id) {
+    for (_, mut song) in songs {
+        song.localize();
+        if let Some((order_index, ps)) = playlist_songs_map.get(&song.id) {
             let item = SongItem {
                 song_id: song.id,
                 song_display_id: song.display_id,
@@ -61,32 +108,145 @@ pub async fn get_detail(state: &State<AppState>, uid: Option<i64>, playlist_id:
                 uploader_name: song.uploader_name,
                 uploader_uid: song.uploader_uid,
                 duration_seconds: song.duration_seconds,
-                order_index: ps.order_index,
+                order_index: *order_index as i32,
                 add_time: ps.add_time,
             };
             result.push(item);
         }
     }
 
+    let localized_titles = localization::list_by_ids(&state.sql_pool, localized_title::ENTITY_PLAYLIST, &[playlist.id]).await?
+        .remove(&playlist.id)
+        .unwrap_or_default();
+    let mut playlist_info = PlaylistItem {
+        id: playlist.id,
+        name: playlist.name,
+        cover_url: playlist.cover_url,
+        description: playlist.description,
+        create_time: playlist.create_time,
+        is_public: playlist.is_public,
+        songs_count: result.len() as i64,
+        update_time: playlist.update_time,
+        localized_titles,
+        name_lang: None,
+        default_name: None,
+        default_description: None,
+    };
+    playlist_info.localize();
+
     let resp = DetailResp {
-        playlist_info: PlaylistItem {
-            id: playlist.id,
-            name: playlist.name,
-            cover_url: playlist.cover_url,
-            description: playlist.description,
-            create_time: playlist.create_time,
-            is_public: playlist.is_public,
-            songs_count: result.len() as i64,
-            update_time: playlist.update_time,
-        },
+        playlist_info,
         creator_profile: creator_user,
         songs: result,
     };
     Ok(resp)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// M3U8 with the stream URLs
+    M3u,
+    /// [PlaylistManifest]
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "m3u" | "m3u8" => Some(ExportFormat::M3u),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::M3u => "audio/x-mpegurl; charset=utf-8",
+            ExportFormat::Json => "application/json; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::M3u => "m3u8",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// The exported JSON of a playlist, the songs are identified by the display ids
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistManifest {
+    pub name: String,
+    pub description: Option<String>,
+    pub creator_name: String,
+    pub exported_at: DateTime<Utc>,
+    pub songs: Vec<PlaylistManifestSong>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistManifestSong {
+    pub display_id: String,
+    pub title: String,
+    pub uploader_name: String,
+}
+
+/// Export the playlist in the playlist order, with the same visibility rules as [get_detail]
+pub async fn export(
+    state: &State<AppState>,
+    uid: Option<i64>,
+    playlist_id: i64,
+    format: ExportFormat,
+) -> Result<String, GetDetailError> {
+    let mut detail = get_detail(state, uid, playlist_id).await?;
+    detail.songs.sort_by_key(|x| x.order_index);
+    match format {
+        ExportFormat::M3u => {
+            let song_ids = detail.songs.iter().map(|x| x.song_id).collect_vec();
+            let audio_urls = song::get_public_detail_with_cache(state.redis_conn.clone(), &state.sql_pool, &song_ids).await?
+                .into_iter()
+                .map(|(id, x)| (id, x.audio_url))
+                .collect();
+            Ok(render_m3u(&detail, &audio_urls))
+        }
+        ExportFormat::Json => {
+            let manifest = PlaylistManifest {
+                name: detail.playlist_info.name,
+                description: detail.playlist_info.description,
+                creator_name: detail.creator_profile.username,
+                exported_at: Utc::now(),
+                songs: detail.songs.into_iter()
+                    .map(|x| PlaylistManifestSong {
+                        display_id: x.song_display_id,
+                        title: x.title,
+                        uploader_name: x.uploader_name,
+                    })
+                    .collect(),
+            };
+            Ok(serde_json::to_string_pretty(&manifest).map_err(anyhow::Error::from)?)
+        }
+    }
+}
+
+fn render_m3u(detail: &DetailResp, audio_urls: &HashMap<i64, String>) -> String {
+    // A line break in the names would start a new entry
+    let single_line = |x: &str| x.replace(['\r', '\n'], " ");
+    let mut content = format!("#EXTM3U\n#PLAYLIST:{}\n", single_line(&detail.playlist_info.name));
+    for song in &detail.songs {
+        let Some(url) = audio_urls.get(&song.song_id) else { continue };
+        content.push_str(&format!(
+            "#EXTINF:{},{} - {}\n{}\n",
+            song.duration_seconds,
+            single_line(&song.uploader_name),
+            single_line(&song.title),
+            url,
+        ));
+    }
+    content
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PlaylistMetadata {
     pub id: i64,
     pub user_id: i64,
@@ -98,8 +258,13 @@ pub struct PlaylistMetadata {
     pub songs_count: i64,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
+    /// The language of the `name` if it's localized, see [PlaylistItem::name_lang].
+    /// @since 260430
+    #[serde(default)]
+    pub name_lang: Option<String>,
 }
 
+/// The metadata of the playlists, the names are localized for the current request
 pub async fn list_playlist_metadata(
     redis: ConnectionManager,
     sql_pool: &PgPool,
@@ -116,23 +281,211 @@ pub async fn list_playlist_metadata(
     let counts = PlaylistDao::count_songs(sql_pool, &playlist_ids).await?;
     let playlists: HashMap<i64, Playlist> = rows.into_iter().map(|p| (p.id, p)).collect();
     let users = user::get_public_profile(redis, sql_pool, &user_ids).await?;
+    let localized_titles = localization::list_by_ids(sql_pool, localized_title::ENTITY_PLAYLIST, playlist_ids).await?;
 
     let result: HashMap<i64, _> = playlist_ids
         .into_iter()
         .filter_map(|id| playlists.get(&id))
-        .map(|p| PlaylistMetadata {
-            id: p.id,
-            name: p.name.clone(),
-            description: p.description.clone(),
-            cover_url: p.cover_url.clone(),
-            user_id: p.user_id,
-            user_name: users.get(&p.user_id).map_or("Unknown User".to_string(), |u| u.username.clone()),
-            create_time: p.create_time,
-            update_time: p.update_time,
-            songs_count: counts.get(&p.id).cloned().unwrap_or(0),
-            user_avatar_url: users.get(&p.user_id).and_then(|u| u.avatar_url.clone()),
+        // Playlists of banned users are hidden
+        .filter(|p| !users.get(&p.user_id).is_some_and(|u| u.is_banned))
+        .map(|p| {
+            let localized = localized_titles.get(&p.id).and_then(|x| localization::select_for_request(x));
+            PlaylistMetadata {
+                id: p.id,
+                name: localized.map_or_else(|| p.name.clone(), |x| x.title.clone()),
+                description: localized.filter(|x| !x.subtitle.is_blank())
+                    .map_or_else(|| p.description.clone(), |x| Some(x.subtitle.clone())),
+                cover_url: p.cover_url.clone(),
+                user_id: p.user_id,
+                user_name: users.get(&p.user_id).map_or("Unknown User".to_string(), |u| u.username.clone()),
+                create_time: p.create_time,
+                update_time: p.update_time,
+                songs_count: counts.get(&p.id).cloned().unwrap_or(0),
+                user_avatar_url: users.get(&p.user_id).and_then(|u| u.avatar_url.clone()),
+                name_lang: localized.map(|x| x.lang.clone()),
+            }
         })
         .map(|x| (x.id, x))
         .collect();
     Ok(result)
-}
\ No newline at end of file
+}
+/// Reassign evenly spaced sort keys in the current order
+pub fn normalize_sort_keys(songs: &mut [PlaylistSong]) {
+    let keys = fractional_index::normalized_keys(songs.len());
+    for (song, key) in songs.iter_mut().zip(keys) {
+        song.sort_key = key;
+    }
+}
+
+/// Normalize the playlists whose sort keys grew too long after repeated moves to the same place,
+/// returns the number of normalized playlists
+pub async fn normalize_long_sort_keys(pool: &PgPool) -> anyhow::Result<usize> {
+    let playlist_ids = PlaylistDao::list_ids_with_long_sort_keys(pool, fractional_index::NORMALIZE_THRESHOLD as i32, 100).await?;
+    for playlist_id in &playlist_ids {
+        retry_on_conflict(|| async {
+            let mut tx = begin_serializable(pool).await?;
+            let mut songs = PlaylistDao::list_songs(&mut *tx, *playlist_id).await?;
+            normalize_sort_keys(&mut songs);
+            PlaylistDao::update_songs_orders(&mut tx, &songs).await?;
+            tx.commit().await?;
+            Ok(())
+        }).await?;
+    }
+    Ok(playlist_ids.len())
+}
+
+pub const SORT_KEY_NORMALIZATION_JOB: Job = Job {
+    name: "normalize_playlist_sort_keys",
+    schedule: "15 * * * *",
+    max_jitter: Duration::from_secs(60),
+};
+
+/// Normalize the long sort keys hourly until cancelled
+pub async fn run_sort_key_normalization(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &SORT_KEY_NORMALIZATION_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            async move {
+                let count = normalize_long_sort_keys(&pool).await?;
+                if count > 0 {
+                    info!("Normalized the sort keys of {count} playlists");
+                }
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+/// The number of songs whose covers make up the generated cover
+pub const COVER_SONGS: usize = 4;
+
+/// Regenerate the cover from the covers of the first songs, unless the playlist has a custom cover
+pub async fn refresh_song_cover(state: &AppState, playlist_id: i64) -> anyhow::Result<()> {
+    let Some(playlist) = PlaylistDao::get_by_id(&state.sql_pool, playlist_id).await? else {
+        return Ok(());
+    };
+    if !playlist.use_song_cover {
+        return Ok(());
+    }
+
+    let song_ids = PlaylistDao::list_songs(&state.sql_pool, playlist_id).await?
+        .into_iter()
+        .take(COVER_SONGS)
+        .map(|x| x.song_id)
+        .collect_vec();
+    let songs: HashMap<i64, Song> = SongDao::list_by_ids(&state.sql_pool, &song_ids).await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+    let mut covers = Vec::new();
+    for song in song_ids.iter().filter_map(|x| songs.get(x)) {
+        let Some(key) = state.file_host.key_of(&song.cover_art_url) else { continue };
+        if let Some(object) = state.file_host.download(&key).await? {
+            covers.push(object.bytes);
+        }
+    }
+
+    let cover_url = if covers.is_empty() {
+        None
+    } else {
+        let options = ImageProcessOptions::playlist_cover(&ImageCfg::load(&state.config)?, &state.limits);
+        let processed = tokio::task::spawn_blocking(move || image::compose_collage(&covers, &options)).await??;
+        Some(image::upload_processed(state.file_host.as_ref(), "playlist", processed).await?.public_url)
+    };
+    if cover_url == playlist.cover_url {
+        return Ok(());
+    }
+
+    // The cover replaced, or the new one if it's not applied
+    let unused_url = retry_on_conflict(|| async {
+        let Some(mut playlist) = PlaylistDao::get_by_id(&state.sql_pool, playlist_id).await? else {
+            return Ok(cover_url.clone());
+        };
+        // A custom cover may be set meanwhile
+        if !playlist.use_song_cover {
+            return Ok(cover_url.clone());
+        }
+        let old_url = std::mem::replace(&mut playlist.cover_url, cover_url.clone());
+        PlaylistDao::update_by_id_checked(&state.sql_pool, &playlist).await?;
+        Ok(old_url)
+    }).await?;
+    song::delete_unreferenced_files(state, unused_url).await?;
+    Ok(())
+}
+
+/// Refresh the generated cover in the background, one at a time per playlist so the last refresh sees the latest
+/// songs and wins
+pub fn spawn_refresh_song_cover(state: &AppState, playlist_id: i64) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let lock = state.red_lock.lock_with_timeout(&format!("playlist:cover:{playlist_id}"), Duration::from_secs(60)).await;
+        let _guard = match lock {
+            Ok(Some(guard)) => guard,
+            Ok(None) => {
+                warn!("Skipped refreshing the cover of playlist {playlist_id} since another refresh takes too long");
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to lock the cover of playlist {playlist_id}: {:?}", e);
+                return;
+            }
+        };
+        if let Err(e) = refresh_song_cover(&state, playlist_id).await {
+            warn!("Failed to refresh the cover of playlist {playlist_id}: {:?}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::routes::user::PublicUserProfile;
+
+    fn song_item(song_id: i64, title: &str) -> SongItem {
+        SongItem {
+            song_id,
+            song_display_id: format!("JM-{song_id}"),
+            title: title.to_string(),
+            subtitle: String::new(),
+            cover_url: String::new(),
+            uploader_name: "神人".to_string(),
+            uploader_uid: 1,
+            duration_seconds: 60,
+            order_index: 0,
+            add_time: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_render_m3u() {
+        let detail = DetailResp {
+            playlist_info: PlaylistItem {
+                id: 1,
+                name: "哈基米\nplaylist".to_string(),
+                cover_url: None,
+                description: None,
+                create_time: Utc::now(),
+                is_public: true,
+                songs_count: 2,
+                update_time: Utc::now(),
+                localized_titles: vec![],
+                name_lang: None,
+                default_name: None,
+                default_description: None,
+            },
+            songs: vec![song_item(1, "a"), song_item(2, "b")],
+            creator_profile: PublicUserProfile::tombstone(1),
+        };
+        // The songs without an audio URL are skipped
+        let audio_urls = HashMap::from([(1, "https://example.com/1.mp3".to_string())]);
+        assert_eq!(
+            "#EXTM3U\n#PLAYLIST:哈基米 playlist\n#EXTINF:60,神人 - a\nhttps://example.com/1.mp3\n",
+            render_m3u(&detail, &audio_urls)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use crate::db::song_tag::{ISongTagDao, SongTagDao};
+use crate::db::user_preference::{IUserPreferenceDao, UserPreference, UserPreferenceDao};
+use chrono::Utc;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+
+/// At most this many favorite or blocked tags can be set
+pub const MAX_TAGS: usize = 20;
+/// The number of favorite tags inferred for the users who never set them
+const INFERRED_FAVORITE_TAGS: i64 = 5;
+/// Infer from the latest this many played songs
+const INFERRED_HISTORY_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagPreferences {
+    pub favorite_tag_ids: Vec<i64>,
+    pub blocked_tag_ids: Vec<i64>,
+    /// Inferred from the play history since the user never set them
+    pub inferred: bool,
+}
+
+impl TagPreferences {
+    pub fn is_empty(&self) -> bool {
+        self.favorite_tag_ids.is_empty() && self.blocked_tag_ids.is_empty()
+    }
+
+    pub fn is_blocked(&self, tag_ids: &[i64]) -> bool {
+        tag_ids.iter().any(|x| self.blocked_tag_ids.contains(x))
+    }
+
+    /// The number of favorite tags among the tags
+    pub fn favorite_score(&self, tag_ids: &[i64]) -> usize {
+        tag_ids.iter().filter(|x| self.favorite_tag_ids.contains(x)).count()
+    }
+
+    /// Drop the items with blocked tags, and move the items with more favorite tags ahead.
+    ///
+    /// Items with the same score keep their order.
+    pub fn rank<T>(&self, items: Vec<T>, tag_ids: impl Fn(&T) -> Vec<i64>) -> Vec<T> {
+        let mut scored = items.into_iter()
+            .filter_map(|x| {
+                let tags = tag_ids(&x);
+                (!self.is_blocked(&tags)).then(|| (self.favorite_score(&tags), x))
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|x| std::cmp::Reverse(x.0));
+        scored.into_iter().map(|x| x.1).collect()
+    }
+
+    /// Pick `limit` random items preferring the favorite tags, the result is shuffled
+    pub fn pick<T>(&self, mut items: Vec<T>, limit: usize, tag_ids: impl Fn(&T) -> Vec<i64>) -> Vec<T> {
+        items.shuffle(&mut rand::rng());
+        let mut picked = self.rank(items, tag_ids);
+        picked.truncate(limit);
+        picked.shuffle(&mut rand::rng());
+        picked
+    }
+}
+
+/// The preferences set by the user, or inferred from the play history if the user never set them
+pub async fn get_effective(pool: &PgPool, user_id: i64) -> anyhow::Result<TagPreferences> {
+    if let Some(x) = UserPreferenceDao::get_by_user_id(pool, user_id).await? {
+        return Ok(TagPreferences {
+            favorite_tag_ids: x.favorite_tag_ids,
+            blocked_tag_ids: x.blocked_tag_ids,
+            inferred: false,
+        });
+    }
+    let favorite_tag_ids = SongTagDao::list_most_played_by_user(pool, user_id, INFERRED_HISTORY_SIZE, INFERRED_FAVORITE_TAGS).await?;
+    Ok(TagPreferences {
+        favorite_tag_ids,
+        blocked_tag_ids: vec![],
+        inferred: true,
+    })
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SavePreferencesError {
+    #[error("At most {MAX_TAGS} tags can be set")]
+    TooManyTags,
+    #[error("Tag {0} not found")]
+    TagNotFound(i64),
+    #[error("Tag {0} is both favorite and blocked")]
+    Conflict(i64),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+pub async fn save(
+    pool: &PgPool,
+    user_id: i64,
+    favorite_tag_ids: &[i64],
+    blocked_tag_ids: &[i64],
+) -> Result<(), SavePreferencesError> {
+    let favorite = dedup(favorite_tag_ids);
+    let blocked = dedup(blocked_tag_ids);
+    if favorite.len() > MAX_TAGS || blocked.len() > MAX_TAGS {
+        return Err(SavePreferencesError::TooManyTags);
+    }
+    if let Some(x) = favorite.iter().find(|x| blocked.contains(x)) {
+        return Err(SavePreferencesError::Conflict(*x));
+    }
+
+    let all = favorite.iter().chain(blocked.iter()).copied().collect::<Vec<_>>();
+    let found: HashSet<i64> = SongTagDao::list_by_ids(pool, &all).await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    if let Some(x) = all.iter().find(|x| !found.contains(x)) {
+        return Err(SavePreferencesError::TagNotFound(*x));
+    }
+
+    let now = Utc::now();
+    UserPreferenceDao::upsert(pool, &UserPreference {
+        user_id,
+        favorite_tag_ids: favorite,
+        blocked_tag_ids: blocked,
+        create_time: now,
+        update_time: now,
+    }).await?;
+    Ok(())
+}
+
+/// Set the favorite tags picked in the onboarding, the picked tags are unblocked and the other blocked tags are kept
+pub async fn seed_favorites(pool: &PgPool, user_id: i64, tag_ids: &[i64]) -> Result<(), SavePreferencesError> {
+    let blocked = UserPreferenceDao::get_by_user_id(pool, user_id).await?
+        .map(|x| x.blocked_tag_ids)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|x| !tag_ids.contains(x))
+        .collect::<Vec<_>>();
+    save(pool, user_id, tag_ids, &blocked).await
+}
+
+/// Remove the duplicates and keep the order
+fn dedup(ids: &[i64]) -> Vec<i64> {
+    let mut seen = HashSet::new();
+    ids.iter().copied().filter(|x| seen.insert(*x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let prefs = TagPreferences {
+            favorite_tag_ids: vec![1, 2],
+            blocked_tag_ids: vec![9],
+            inferred: false,
+        };
+        let items = vec![(10, vec![3]), (11, vec![1]), (12, vec![1, 9]), (13, vec![1, 2]), (14, vec![])];
+        let ranked = prefs.rank(items.clone(), |x| x.1.clone()).into_iter().map(|x| x.0).collect::<Vec<_>>();
+        assert_eq!(vec![13, 11, 10, 14], ranked);
+
+        let picked = prefs.pick(items, 2, |x| x.1.clone()).into_iter().map(|x| x.0).collect::<HashSet<_>>();
+        assert_eq!(HashSet::from([13, 11]), picked);
+    }
+
+    #[test]
+    fn test_dedup() {
+        assert_eq!(vec![3, 1, 2], dedup(&[3, 1, 3, 2, 1]));
+    }
+}
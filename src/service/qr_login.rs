@@ -0,0 +1,119 @@
+//! Logging in the TV and desktop clients by scanning a QR code with the logged in mobile app.
+//!
+//! The desktop creates a session and displays its `code`, the mobile app scans the code to show where the
+//! desktop is and approves it after the user confirmed, then the desktop
+//! polls with the `poll_token` only known by itself and receives the tokens. The session lives in Redis for
+//! [SESSION_TTL_SECS], and is deleted once the tokens are issued.
+
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+
+pub const SESSION_TTL_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct QrLoginSession {
+    poll_token: String,
+    device_info: String,
+    ip: String,
+    create_time: DateTime<Utc>,
+}
+
+/// The desktop being logged in, shown on the mobile app before approving, so a code sent by someone else
+/// can be told apart from the scanned one
+#[derive(Debug, Clone)]
+pub struct PendingSession {
+    pub device_info: String,
+    pub ip: String,
+    pub create_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreatedSession {
+    pub code: String,
+    pub poll_token: String,
+}
+
+pub async fn create(redis: &mut ConnectionManager, device_info: &str, ip: &str) -> anyhow::Result<CreatedSession> {
+    let code = uuid::Uuid::new_v4().simple().to_string();
+    let poll_token = hex::encode(rand::random::<[u8; 32]>());
+    let session = QrLoginSession {
+        poll_token: poll_token.clone(),
+        device_info: device_info.to_string(),
+        ip: ip.to_string(),
+        create_time: Utc::now(),
+    };
+    let _: () = redis.set_ex(get_session_key(&code), serde_json::to_string(&session)?, SESSION_TTL_SECS).await?;
+    Ok(CreatedSession { code, poll_token })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApproveError {
+    #[error("The QR code is expired")]
+    Expired,
+    #[error("The QR code is already approved")]
+    AlreadyApproved,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Look up the session being scanned, returns `None` if it's expired
+pub async fn scan(redis: &mut ConnectionManager, code: &str) -> anyhow::Result<Option<PendingSession>> {
+    let session = get_session(redis, code).await?;
+    Ok(session.map(|x| PendingSession { device_info: x.device_info, ip: x.ip, create_time: x.create_time }))
+}
+
+/// Approve the session for the user, returns the device info of the desktop
+pub async fn approve(redis: &mut ConnectionManager, code: &str, uid: i64) -> Result<String, ApproveError> {
+    let Some(session) = get_session(redis, code).await? else {
+        return Err(ApproveError::Expired);
+    };
+    let absent: bool = redis.set_options(
+        get_approved_key(code),
+        uid,
+        SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(SESSION_TTL_SECS)),
+    ).await.map_err(anyhow::Error::from)?;
+    if !absent {
+        return Err(ApproveError::AlreadyApproved);
+    }
+    Ok(session.device_info)
+}
+
+pub enum PollResult {
+    Expired,
+    Pending,
+    /// The session is consumed, the tokens should be issued to the user
+    Approved { uid: i64, device_info: String },
+}
+
+pub async fn poll(redis: &mut ConnectionManager, code: &str, poll_token: &str) -> anyhow::Result<PollResult> {
+    let Some(session) = get_session(redis, code).await? else {
+        return Ok(PollResult::Expired);
+    };
+    if session.poll_token.len() != poll_token.len() || !openssl::memcmp::eq(session.poll_token.as_bytes(), poll_token.as_bytes()) {
+        return Ok(PollResult::Expired);
+    }
+    // Only one of the concurrent polls gets the uid
+    let uid: Option<i64> = redis.get_del(get_approved_key(code)).await?;
+    let Some(uid) = uid else {
+        return Ok(PollResult::Pending);
+    };
+    let _: () = redis.del(get_session_key(code)).await?;
+    Ok(PollResult::Approved { uid, device_info: session.device_info })
+}
+
+async fn get_session(redis: &mut ConnectionManager, code: &str) -> anyhow::Result<Option<QrLoginSession>> {
+    let value: Option<String> = redis.get(get_session_key(code)).await?;
+    Ok(value.map(|x| serde_json::from_str(&x)).transpose()?)
+}
+
+fn get_session_key(code: &str) -> String {
+    format!("qr_login:session:{code}")
+}
+
+fn get_approved_key(code: &str) -> String {
+    format!("qr_login:approved:{code}")
+}
@@ -1,21 +1,44 @@
 use crate::db::song::{ISongDao, SongDao};
-use crate::service::song;
+use crate::service::preference::{self, TagPreferences};
 use crate::service::song::PublicSongDetail;
+use crate::service::tag_recommend::{self, SongScorer};
+use crate::service::{song, song_exclusion, song_share};
 use crate::util;
+use crate::util::redis_health;
 use crate::util::redlock::RedLock;
 use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use futures::TryStreamExt;
 use itertools::Itertools;
 use metrics::histogram;
-use rand::prelude::SliceRandom;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use redis::aio::ConnectionManager;
 use redis::{AsyncIter, AsyncTypedCommands};
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Pool, Postgres};
+use std::collections::HashSet;
 use std::ops::Sub;
 use std::time::{Duration, Instant};
 use tracing::warn;
 
+pub const RECENT_CACHE_TTL_SECS: u64 = 300;
+pub const HOT_CACHE_TTL_SECS: u64 = 3600;
+/// Anonymous users are divided into groups by IP, each group shares the same recommendations
+pub const ANONYMOUS_RECOMMEND_GROUPS: i64 = 32;
+const RECOMMEND_SIZE: usize = 30;
+/// Sample this many times of the songs to pick from for the users with preferences
+const RECOMMEND_SAMPLE_FACTOR: usize = 3;
+/// At most this many more songs are sampled to make up for the excluded ones
+const MAX_EXCLUDED_SAMPLE: usize = 100;
+/// The top tags of the user to personalize the recommendations with
+const PERSONALIZED_TAGS: i64 = 20;
+/// The tags are counted from the plays in this many days, and all the likes
+const PERSONALIZED_HISTORY_DAYS: i64 = 90;
+/// The random songs mixed into the personalized recommendations, apart from the top ranked ones
+const PERSONALIZED_EXPLORE_SIZE: usize = 6;
+/// At most this much random score is added to each song, see [SongScorer::score]
+const PERSONALIZED_SCORE_JITTER: f64 = 0.1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecentSongRedisCache {
     pub songs: Vec<PublicSongDetail>,
@@ -28,7 +51,11 @@ pub async fn get_recent_songs(
     pool: &PgPool,
     cursor: Option<DateTime<Utc>>, limit: i32, after: bool,
 ) -> anyhow::Result<Vec<PublicSongDetail>> {
-    let cache = get_from_cache(redis.clone(), cursor, limit, after).await?;
+    let cache = match redis_health::cached(get_from_cache(redis.clone(), cursor, limit, after)).await {
+        Some(x) => x,
+        // Redis is unavailable
+        None => return get_recent_from_db(redis, pool, cursor, limit, after).await,
+    };
 
     match cache {
         Some(cache) => {
@@ -54,6 +81,16 @@ pub async fn get_recent_songs(
     }
 }
 
+/// Recompute the recent songs and overwrite the cache, so readers never see it expired
+pub async fn refresh_recent_songs(
+    redis: ConnectionManager,
+    pool: &PgPool,
+    cursor: Option<DateTime<Utc>>, limit: i32, after: bool,
+) -> anyhow::Result<()> {
+    let songs = get_recent_from_db(redis.clone(), pool, cursor, limit, after).await?;
+    save_cache(redis, &songs, cursor, limit, after).await
+}
+
 async fn get_from_cache(mut redis: ConnectionManager, cursor: Option<DateTime<Utc>>, limit: i32, after: bool) -> anyhow::Result<Option<Vec<PublicSongDetail>>> {
     let cache: Option<String> = redis.get(build_recent_redis_key(cursor, limit, after)).await?;
     match cache {
@@ -78,8 +115,7 @@ async fn save_cache(mut redis: ConnectionManager, songs: &[PublicSongDetail], cu
     let cache = RecentSongRedisCache { songs: songs.to_vec(), create_time: Utc::now() };
     let value = serde_json::to_string(&cache)?;
 
-    // Cache for 5 minutes
-    let _: () = redis.set_ex(build_recent_redis_key(cursor, limit, after), value, 300).await?;
+    let _: () = redis.set_ex(build_recent_redis_key(cursor, limit, after), value, RECENT_CACHE_TTL_SECS).await?;
     Ok(())
 }
 
@@ -129,6 +165,26 @@ pub async fn notify_update(song_id: i64, mut redis: ConnectionManager) -> anyhow
     Ok(())
 }
 
+/// Delete the cached recent and hot song lists, so they are rebuilt with the latest song details
+pub async fn evict_list_caches(redis: ConnectionManager) -> anyhow::Result<()> {
+    delete_matching(redis.clone(), "songs:recent_v2:*").await?;
+    delete_matching(redis, "songs:hot:*").await
+}
+
+/// Delete the daily recommendations of all users, they will be re-rolled on the next request
+pub async fn evict_recommend_caches(redis: ConnectionManager) -> anyhow::Result<()> {
+    delete_matching(redis, "songs:recommend:*").await
+}
+
+async fn delete_matching(mut redis: ConnectionManager, pattern: &str) -> anyhow::Result<()> {
+    let keys: AsyncIter<String> = redis.scan_match(pattern).await?;
+    let keys = keys.try_collect::<Vec<_>>().await?;
+    if !keys.is_empty() {
+        redis.del(&keys).await?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecommendRedisCache {
     pub songs: Vec<PublicSongDetail>,
@@ -142,24 +198,48 @@ pub async fn get_recommend_anonymous(
     pool: &PgPool,
 ) -> anyhow::Result<Vec<PublicSongDetail>> {
     let anonymous_uid = util::convert_ip_to_anonymous_uid(&ip)?;
-    // 32 groups
-    let hash = anonymous_uid % 32 + 1;
+    let hash = anonymous_uid % ANONYMOUS_RECOMMEND_GROUPS + 1;
 
     get_recommend(-hash, lock, redis, pool).await
 }
 
-/// Return random 30 songs for a user in one day
+/// The day of the recommendations, they refresh at 06:00+8
+fn recommend_date() -> NaiveDate {
+    Utc::now().with_timezone(&chrono_tz::Asia::Shanghai).sub(TimeDelta::hours(6)).date_naive()
+}
+
+fn build_recommend_redis_key(user_id: i64, date: &NaiveDate) -> String {
+    format!("songs:recommend:{}:{}", user_id, date)
+}
+
+/// Delete today's recommendations of the user, e.g. after the preferences change
+pub async fn evict_user_recommend(mut redis: ConnectionManager, user_id: i64) -> anyhow::Result<()> {
+    redis.del(build_recommend_redis_key(user_id, &recommend_date())).await?;
+    Ok(())
+}
+
+/// Return 30 songs for a user in one day, personalized by the tags of the songs played and liked by the user,
+/// or random for the users without them, respecting the preferences of the user.
+///
+/// The recently played songs and the songs the user is not interested in are excluded,
+/// and the songs marked as not interested later in the day are removed from the cached ones.
 pub async fn get_recommend(
     user_id: i64,
     lock: RedLock,
     redis: ConnectionManager,
     pool: &PgPool,
 ) -> anyhow::Result<Vec<PublicSongDetail>> {
-    // Refresh at 06:00+8
-    let date = Utc::now().with_timezone(&chrono_tz::Asia::Shanghai).sub(TimeDelta::hours(6)).date_naive();
-    let cache = get_from_cache_recommend(redis.clone(), user_id, &date).await?;
+    let date = recommend_date();
+    let cache = match redis_health::cached(get_from_cache_recommend(redis.clone(), user_id, &date)).await {
+        Some(x) => x,
+        // Redis is unavailable, the recommendations are rebuilt on every request until it recovers
+        None => {
+            let prefs = get_preferences(pool, user_id).await;
+            return get_from_db_recommend(redis, pool, user_id, &prefs, &HashSet::new()).await;
+        }
+    };
     match cache {
-        Some(cache) => Ok(cache),
+        Some(cache) => Ok(without_disliked(redis, user_id, cache).await),
         None => {
             let guard = lock.lock_with_timeout(
                 &format!("lock:songs:recommend:{}", user_id),
@@ -168,11 +248,12 @@ pub async fn get_recommend(
 
             let cache = get_from_cache_recommend(redis.clone(), user_id, &date).await?;
             if let Some(cache) = cache {
-                return Ok(cache);
+                return Ok(without_disliked(redis, user_id, cache).await);
             }
 
-            let mut songs = get_from_db_recommend(redis.clone(), pool).await?;
-            songs.shuffle(&mut rand::rng());
+            let prefs = get_preferences(pool, user_id).await;
+            let excluded = song_exclusion::list_excluded(redis.clone(), user_id).await;
+            let songs = get_from_db_recommend(redis.clone(), pool, user_id, &prefs, &excluded).await?;
 
             save_cache_recommend(redis, user_id, &songs, &date).await?;
             drop(guard);
@@ -181,12 +262,28 @@ pub async fn get_recommend(
     }
 }
 
+async fn without_disliked(redis: ConnectionManager, user_id: i64, songs: Vec<PublicSongDetail>) -> Vec<PublicSongDetail> {
+    let disliked = song_exclusion::list_disliked(redis, user_id).await;
+    songs.into_iter().filter(|x| !disliked.contains(&x.id)).collect()
+}
+
+/// Anonymous users have no preferences, the recommendations don't fail if the preferences can't be loaded
+async fn get_preferences(pool: &PgPool, user_id: i64) -> TagPreferences {
+    if user_id <= 0 {
+        return TagPreferences::default();
+    }
+    preference::get_effective(pool, user_id).await.unwrap_or_else(|e| {
+        warn!("Failed to get the preferences of user {user_id}: {:?}", e);
+        TagPreferences::default()
+    })
+}
+
 async fn get_from_cache_recommend(
     mut redis: ConnectionManager,
     user_id: i64,
     date: &NaiveDate,
 ) -> anyhow::Result<Option<Vec<PublicSongDetail>>> {
-    let cache: Option<String> = redis.get(format!("songs:recommend:{}:{}", user_id, date)).await?;
+    let cache: Option<String> = redis.get(build_recommend_redis_key(user_id, date)).await?;
     match cache {
         Some(cache) => match serde_json::from_str::<RecommendRedisCache>(&cache) {
             Ok(x) => Ok(Some(x.songs)),
@@ -213,31 +310,114 @@ async fn save_cache_recommend(
 
     // Cache for 1 day
     let _: () = redis
-        .set_ex(format!("songs:recommend:{}:{}", user_id, date.to_string()), value, 86400)
+        .set_ex(build_recommend_redis_key(user_id, date), value, 86400)
         .await?;
     Ok(())
 }
 
+/// Personalize the recommendations by the tags of the user, or pick from random songs for the cold-start and
+/// the anonymous users. The personalization falls back to the random songs if it fails
 async fn get_from_db_recommend(
     redis: ConnectionManager,
     pool: &PgPool,
+    user_id: i64,
+    prefs: &TagPreferences,
+    excluded: &HashSet<i64>,
 ) -> anyhow::Result<Vec<PublicSongDetail>> {
+    if user_id > 0 {
+        let start = Instant::now();
+        match get_from_db_recommend_personalized(redis.clone(), pool, user_id, prefs, excluded).await {
+            Ok(Some(songs)) => {
+                histogram!("recommend_personalized_get_from_db_duration_seconds").record(start.elapsed().as_secs_f64());
+                return Ok(songs);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to personalize the recommendations of user {user_id}, fallback to random: {:?}", e),
+        }
+    }
+
     let start = Instant::now();
-    let random_song_ids: Vec<i64> = SongDao::list_random(pool, 30).await?;
+    let sample_size = if prefs.is_empty() { RECOMMEND_SIZE } else { RECOMMEND_SIZE * RECOMMEND_SAMPLE_FACTOR }
+        + excluded.len().min(MAX_EXCLUDED_SAMPLE);
+    let random_song_ids: Vec<i64> = SongDao::list_random(pool, sample_size as i64).await?
+        .into_iter()
+        .filter(|x| !excluded.contains(x))
+        .collect();
 
     let songs = song::get_public_detail_with_cache(redis.clone(), pool, &random_song_ids).await?
-        .into_iter()
-        .map(|(_, mut data)| {
-            data.description = data.description.chars().take(128).collect();
-            data.lyrics.clear();
-            data.audio_url.clear();
-            data
-        })
+        .into_values()
+        .map(trim_recommend_detail)
         .collect::<Vec<_>>();
+    let songs = prefs.pick(songs, RECOMMEND_SIZE, |x| x.tags.iter().map(|t| t.id).collect());
     histogram!("recommend_random_get_from_db_duration_seconds").record(start.elapsed().as_secs_f64());
     Ok(songs)
 }
 
+/// Rank the songs with the top played and liked tags of the user by [SongScorer], and mix in a few random ones
+/// to explore. The result is shuffled. Returns `None` for a cold-start user without any tags
+async fn get_from_db_recommend_personalized(
+    redis: ConnectionManager,
+    pool: &PgPool,
+    user_id: i64,
+    prefs: &TagPreferences,
+    excluded: &HashSet<i64>,
+) -> anyhow::Result<Option<Vec<PublicSongDetail>>> {
+    let now = Utc::now();
+    let tag_scores = tag_recommend::recommend_tags(pool, user_id, PERSONALIZED_TAGS, now - TimeDelta::days(PERSONALIZED_HISTORY_DAYS)).await?
+        .into_iter()
+        .map(|(tag, score)| (tag.id, score))
+        .collect_vec();
+    if tag_scores.is_empty() && prefs.favorite_tag_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let tag_ids = tag_scores.iter().map(|x| x.0).chain(prefs.favorite_tag_ids.iter().copied()).unique().collect_vec();
+    let sample_size = RECOMMEND_SIZE * RECOMMEND_SAMPLE_FACTOR + excluded.len().min(MAX_EXCLUDED_SAMPLE);
+    let by_tags = tag_recommend::list_random_songs_by_tags(pool, &tag_ids, sample_size as i64).await?;
+    let random = SongDao::list_random(pool, (RECOMMEND_SIZE + excluded.len().min(MAX_EXCLUDED_SAMPLE)) as i64).await?;
+    let candidate_ids = by_tags.into_iter().chain(random)
+        .unique()
+        .filter(|x| !excluded.contains(x))
+        .collect_vec();
+
+    let candidates = song::get_public_detail_with_cache(redis, pool, &candidate_ids).await?
+        .into_values()
+        .map(trim_recommend_detail)
+        .filter(|x| !prefs.is_blocked(&x.tags.iter().map(|t| t.id).collect_vec()))
+        .collect_vec();
+    let max_play_count = candidates.iter().map(|x| x.play_count).max().unwrap_or(0);
+    let scorer = SongScorer::new(&tag_scores, &prefs.favorite_tag_ids, max_play_count, now);
+
+    // A little randomness, so the recommendations of the next day differ even if the history doesn't change
+    let mut rng = rand::rng();
+    let mut scored = candidates.into_iter()
+        .map(|x| {
+            let tag_ids = x.tags.iter().map(|t| t.id).collect_vec();
+            let score = scorer.score(&tag_ids, x.play_count, x.release_time) + rng.random_range(0.0..PERSONALIZED_SCORE_JITTER);
+            (score, x)
+        })
+        .collect_vec();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let ranked_size = RECOMMEND_SIZE.saturating_sub(PERSONALIZED_EXPLORE_SIZE);
+    let mut rest = scored.split_off(ranked_size.min(scored.len()));
+    rest.shuffle(&mut rng);
+    let mut songs = scored.into_iter().chain(rest)
+        .take(RECOMMEND_SIZE)
+        .map(|x| x.1)
+        .collect_vec();
+    songs.shuffle(&mut rng);
+    Ok(Some(songs))
+}
+
+/// Only the summary of the song is needed in the list
+fn trim_recommend_detail(mut data: PublicSongDetail) -> PublicSongDetail {
+    data.description = data.description.chars().take(128).collect();
+    data.lyrics.clear();
+    data.audio_url.clear();
+    data
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HotWeeklyRedisCache {
     pub songs: Vec<PublicSongDetail>,
@@ -245,7 +425,11 @@ pub struct HotWeeklyRedisCache {
 }
 
 pub async fn get_hot_songs(redis: &ConnectionManager, pool: &Pool<Postgres>, day_delta: i64, limit: i64) -> anyhow::Result<Vec<PublicSongDetail>> {
-    let cache = get_from_cache_hot(redis.clone(), day_delta, limit).await?;
+    let cache = match redis_health::cached(get_from_cache_hot(redis.clone(), day_delta, limit)).await {
+        Some(x) => x,
+        // Redis is unavailable
+        None => return get_from_db_hot_weekly(redis, pool, day_delta, limit).await,
+    };
     if let Some(cache) = cache {
         return Ok(cache);
     }
@@ -255,6 +439,12 @@ pub async fn get_hot_songs(redis: &ConnectionManager, pool: &Pool<Postgres>, day
     Ok(songs)
 }
 
+/// Recompute the hot songs and overwrite the cache, so readers never see it expired
+pub async fn refresh_hot_songs(redis: &ConnectionManager, pool: &Pool<Postgres>, day_delta: i64, limit: i64) -> anyhow::Result<()> {
+    let songs = get_from_db_hot_weekly(redis, pool, day_delta, limit).await?;
+    save_cache_hot(redis.clone(), &songs, day_delta, limit).await
+}
+
 async fn get_from_cache_hot(mut redis: ConnectionManager, day_delta: i64, limit: i64) -> anyhow::Result<Option<Vec<PublicSongDetail>>> {
     let cache: Option<String> = redis.get(format!("songs:hot:{}:{}", day_delta, limit)).await?;
     match cache {
@@ -276,22 +466,36 @@ async fn save_cache_hot(mut redis: ConnectionManager, songs: &[PublicSongDetail]
     };
     let value = serde_json::to_string(&cache)?;
 
-    // Cache for 1 hour
-    let _: () = redis.set_ex(format!("songs:hot:{}:{}", day_delta, limit), value, 3600).await?;
+    let _: () = redis.set_ex(format!("songs:hot:{}:{}", day_delta, limit), value, HOT_CACHE_TTL_SECS).await?;
     Ok(())
 }
 
 async fn get_from_db_hot_weekly(redis: &ConnectionManager, pool: &Pool<Postgres>, day_delta: i64, limit: i64) -> anyhow::Result<Vec<PublicSongDetail>> {
-    let time_ago = Utc::now().sub(TimeDelta::days(day_delta));
-    let result = sqlx::query!("
-        SELECT s.title, sp.song_id, count(*) AS play_count
-        FROM song_plays sp
-                 JOIN songs s ON sp.song_id = s.id
-        WHERE sp.create_time > $1
-        GROUP BY sp.song_id, s.title
-        ORDER BY play_count DESC
+    let since = Utc::now().sub(TimeDelta::days(day_delta)).date_naive();
+    // The plays are read from the daily rollups, where the plays of the shadow banned users are excluded already,
+    // see [crate::service::song_play::rollup_daily_plays].
+    // The shares weigh a little on top of the plays, see [song_share::SHARE_WEIGHT]
+    let result = sqlx::query!(r#"
+        WITH plays AS (
+            SELECT song_id, sum(play_count)::BIGINT AS play_count
+            FROM song_play_counts_daily
+            WHERE day >= $1
+            GROUP BY song_id
+        ), shares AS (
+            SELECT song_id, sum(share_count) AS share_count
+            FROM song_share_rollups
+            WHERE day >= $1
+            GROUP BY song_id
+        )
+        SELECT p.song_id, p.play_count + coalesce(sh.share_count, 0)::BIGINT * $3 AS "score!"
+        FROM plays p
+                 JOIN songs s ON p.song_id = s.id
+                 JOIN users u ON s.uploader_uid = u.id
+                 LEFT JOIN shares sh ON sh.song_id = p.song_id
+        WHERE NOT u.is_banned
+        ORDER BY 2 DESC
         LIMIT $2
-    ", time_ago, limit).fetch_all(pool).await?;
+    "#, since, limit, song_share::SHARE_WEIGHT).fetch_all(pool).await?;
     
     let song_ids = &result.iter().map(|x| x.song_id).collect::<Vec<_>>();
     let songs = song::get_public_detail_with_cache(redis.clone(), pool, song_ids).await?
@@ -0,0 +1,173 @@
+//! The retention policies purging the expired data of each [DataClass] daily by [PURGE_JOB].
+//!
+//! Only the classes configured in the `retention` section are purged. The rows are deleted in batches to keep the
+//! transactions and the locks short, the deleted counts are reported by the `retention_purged_count` metric.
+//! The data of the users on legal hold is never purged, see `/admin/user/legal_hold`.
+//!
+//! The play history is the per-user history in `user_play_history`, and the song plays are the raw plays in
+//! `song_plays`. The purged plays are archived as counts per song, so the play counts recounted by
+//! [crate::service::song_stats] are kept, but the shadow bans changed later don't apply to them.
+
+use crate::config::Config;
+use crate::db::retention::{IRetentionDao, RetentionDao};
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataClass {
+    /// The history of the songs played by the users, by the last play time
+    PlayHistory,
+    /// The raw plays of the songs, by the play time
+    SongPlays,
+    /// By the create time, read or not
+    Notifications,
+    /// By the expiry time
+    RefreshTokens,
+    /// The account audit logs, by the create time
+    AuditLogs,
+}
+
+impl DataClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataClass::PlayHistory => "play_history",
+            DataClass::SongPlays => "song_plays",
+            DataClass::Notifications => "notifications",
+            DataClass::RefreshTokens => "refresh_tokens",
+            DataClass::AuditLogs => "audit_logs",
+        }
+    }
+}
+
+/// Optional `retention` section of the config file, nothing is purged if it's absent
+///
+/// ```yaml
+/// retention:
+///   batch_size: 1000
+///   policies:
+///     play_history:
+///       retention_days: 365
+///     refresh_tokens:
+///       retention_days: 90
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionCfg {
+    /// The rows deleted in a statement
+    #[serde(default = "default_batch_size")]
+    pub batch_size: i64,
+    #[serde(default)]
+    pub policies: BTreeMap<DataClass, PolicyCfg>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyCfg {
+    /// The data older than this is purged
+    pub retention_days: u32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_batch_size() -> i64 { 1000 }
+
+fn default_enabled() -> bool { true }
+
+impl Default for RetentionCfg {
+    fn default() -> Self {
+        RetentionCfg { batch_size: default_batch_size(), policies: BTreeMap::new() }
+    }
+}
+
+impl RetentionCfg {
+    /// Load the `retention` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("retention")?.is_some() {
+            config.get_and_parse("retention")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+pub const PURGE_JOB: Job = Job {
+    name: "purge_retained_data",
+    schedule: "0 5 * * *",
+    max_jitter: Duration::from_secs(600),
+};
+
+/// Purge the data of the class older than `before` batch by batch, returns the deleted rows
+pub async fn purge_class(pool: &PgPool, class: DataClass, before: DateTime<Utc>, batch_size: i64) -> sqlx::Result<u64> {
+    let batch_size = batch_size.max(1);
+    let mut total = 0;
+    loop {
+        let deleted = match class {
+            DataClass::PlayHistory => RetentionDao::purge_play_history(pool, before, batch_size).await?,
+            DataClass::SongPlays => RetentionDao::purge_song_plays(pool, before, batch_size).await?,
+            DataClass::Notifications => RetentionDao::purge_notifications(pool, before, batch_size).await?,
+            DataClass::RefreshTokens => RetentionDao::purge_refresh_tokens(pool, before, batch_size).await?,
+            DataClass::AuditLogs => RetentionDao::purge_audit_logs(pool, before, batch_size).await?,
+        };
+        counter!("retention_purged_count", "class" => class.as_str()).increment(deleted);
+        total += deleted;
+        if deleted < batch_size as u64 {
+            return Ok(total);
+        }
+    }
+}
+
+/// Purge the expired data of all the enabled policies
+pub async fn purge(pool: &PgPool, cfg: &RetentionCfg) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for (class, policy) in &cfg.policies {
+        if !policy.enabled {
+            continue;
+        }
+        let before = now - chrono::Days::new(policy.retention_days as u64);
+        let deleted = purge_class(pool, *class, before, cfg.batch_size).await?;
+        info!("Purged {} rows of {} before {}", deleted, class.as_str(), before);
+    }
+    Ok(())
+}
+
+/// Purge the expired data daily until cancelled
+pub async fn run_purge(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = RetentionCfg::load(&state.config)?;
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &PURGE_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            let cfg = cfg.clone();
+            async move { purge(&pool, &cfg).await }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cfg() {
+        let cfg: RetentionCfg = serde_yaml::from_str(
+            "policies:\n  play_history:\n    retention_days: 365\n  refresh_tokens:\n    retention_days: 90\n    enabled: false\n",
+        ).unwrap();
+        assert_eq!(1000, cfg.batch_size);
+        assert_eq!(2, cfg.policies.len());
+        assert!(cfg.policies[&DataClass::PlayHistory].enabled);
+        assert_eq!(90, cfg.policies[&DataClass::RefreshTokens].retention_days);
+        assert!(!cfg.policies[&DataClass::RefreshTokens].enabled);
+        assert!(serde_yaml::from_str::<RetentionCfg>("policies:\n  songs:\n    retention_days: 1\n").is_err());
+    }
+}
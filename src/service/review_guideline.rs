@@ -0,0 +1,112 @@
+//! The review guideline snippets, the canned guidance and rejection reasons inserted into the review comments.
+//!
+//! The admins edit the snippets by their slugs. Every edit increases the version and is kept as a snapshot, so the
+//! wording of an old comment can be traced back. The archived snippets are hidden from the reviewers.
+
+use crate::db::review_guideline_snippet::{IReviewGuidelineSnippetDao, ReviewGuidelineSnippet, ReviewGuidelineSnippetDao, ReviewGuidelineSnippetVersion};
+use chrono::Utc;
+use sqlx::{PgPool, PgTransaction};
+
+pub const SLUG_MAX_CHARS: usize = 64;
+pub const TITLE_MAX_CHARS: usize = 100;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SaveSnippetError {
+    #[error("Snippet {slug} already exists")]
+    SlugExists { slug: String },
+    #[error("Snippet {slug} not found")]
+    NotFound { slug: String },
+    #[error("Snippet {slug} is not at version {version}")]
+    VersionConflict { slug: String, version: i32 },
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// The editable fields of a snippet
+#[derive(Debug, Clone)]
+pub struct SnippetEdit {
+    pub title: String,
+    pub content: String,
+    pub archived: bool,
+}
+
+/// Lowercase letters, digits and dashes, e.g. `low-audio-quality`
+pub fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug.len() <= SLUG_MAX_CHARS
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+async fn save_version(tx: &mut PgTransaction<'_>, snippet: &ReviewGuidelineSnippet) -> sqlx::Result<()> {
+    ReviewGuidelineSnippetDao::insert_version(&mut **tx, &ReviewGuidelineSnippetVersion {
+        id: 0,
+        snippet_id: snippet.id,
+        version: snippet.version,
+        title: snippet.title.clone(),
+        content: snippet.content.clone(),
+        archived: snippet.archived,
+        editor_uid: snippet.update_by,
+        create_time: snippet.update_time,
+    }).await
+}
+
+/// Create the snippet at version 1
+pub async fn create(pool: &PgPool, uid: i64, slug: &str, edit: SnippetEdit) -> Result<ReviewGuidelineSnippet, SaveSnippetError> {
+    let now = Utc::now();
+    let mut snippet = ReviewGuidelineSnippet {
+        id: 0,
+        slug: slug.to_string(),
+        title: edit.title,
+        content: edit.content,
+        version: 1,
+        archived: edit.archived,
+        update_by: uid,
+        create_time: now,
+        update_time: now,
+    };
+    let mut tx = pool.begin().await?;
+    snippet.id = ReviewGuidelineSnippetDao::insert(&mut *tx, &snippet).await?
+        .ok_or_else(|| SaveSnippetError::SlugExists { slug: slug.to_string() })?;
+    save_version(&mut tx, &snippet).await?;
+    tx.commit().await?;
+    Ok(snippet)
+}
+
+/// Update the snippet if it's still at `version`, so the concurrent edits don't overwrite each other
+pub async fn update(pool: &PgPool, uid: i64, slug: &str, version: i32, edit: SnippetEdit) -> Result<ReviewGuidelineSnippet, SaveSnippetError> {
+    let mut snippet = ReviewGuidelineSnippetDao::get_by_slug(pool, slug).await?
+        .ok_or_else(|| SaveSnippetError::NotFound { slug: slug.to_string() })?;
+    let conflict = || SaveSnippetError::VersionConflict { slug: slug.to_string(), version };
+    if snippet.version != version {
+        return Err(conflict());
+    }
+    snippet.title = edit.title;
+    snippet.content = edit.content;
+    snippet.archived = edit.archived;
+    snippet.version = version + 1;
+    snippet.update_by = uid;
+    snippet.update_time = Utc::now();
+
+    let mut tx = pool.begin().await?;
+    if !ReviewGuidelineSnippetDao::update_checked(&mut *tx, &snippet).await? {
+        return Err(conflict());
+    }
+    save_version(&mut tx, &snippet).await?;
+    tx.commit().await?;
+    Ok(snippet)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_slug() {
+        assert!(is_valid_slug("low-audio-quality"));
+        assert!(is_valid_slug("rule-3"));
+        assert!(!is_valid_slug(""));
+        assert!(!is_valid_slug("Low Quality"));
+        assert!(!is_valid_slug("音质"));
+        assert!(!is_valid_slug(&"a".repeat(SLUG_MAX_CHARS + 1)));
+    }
+}
@@ -0,0 +1,126 @@
+//! Click-through feedback of the song search.
+//!
+//! Each search gets a query id, and the search (the normalized query, the sort method and the hits) is kept in
+//! Redis for an hour. The clicks reported with the id are counted into the daily rollups, once per hit.
+
+use crate::db::search_stat::{ISearchStatDao, SearchStatDao};
+use crate::util::redis_health;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::warn;
+
+/// The clicks are accepted within an hour after the search
+const SESSION_TTL_SECS: u64 = 3600;
+/// Longer queries are truncated in the rollups
+const MAX_QUERY_CHARS: usize = 128;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SearchSession {
+    query: String,
+    sort_by: String,
+    /// The offset of the first hit
+    offset: usize,
+    hit_ids: Vec<i64>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FeedbackError {
+    #[error("The search is expired or not found")]
+    SearchNotFound,
+    #[error("The song is not a hit of the search")]
+    NotAHit,
+    #[error(transparent)]
+    Redis(#[from] redis::RedisError),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+fn session_key(query_id: &str) -> String {
+    format!("search:session:{}", query_id)
+}
+
+fn clicks_key(query_id: &str) -> String {
+    format!("search:session:{}:clicks", query_id)
+}
+
+pub fn normalize_query(q: &str) -> String {
+    q.trim().to_lowercase().chars().take(MAX_QUERY_CHARS).collect()
+}
+
+/// Keep the search and count it in the background, returns the query id.
+///
+/// Returns `None` if Redis is unavailable, the clients should not report the clicks then.
+pub async fn record_search(
+    mut redis: ConnectionManager,
+    pool: &PgPool,
+    q: &str,
+    sort_by: &str,
+    offset: usize,
+    hit_ids: Vec<i64>,
+) -> Option<String> {
+    let query_id = uuid::Uuid::new_v4().to_string();
+    let session = SearchSession {
+        query: normalize_query(q),
+        sort_by: sort_by.to_string(),
+        offset,
+        hit_ids,
+    };
+    let value = serde_json::to_string(&session).unwrap();
+    redis_health::cached(redis.set_ex::<_, _, ()>(session_key(&query_id), value, SESSION_TTL_SECS)).await?;
+
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let date = Utc::now().date_naive();
+        if let Err(e) = SearchStatDao::add_search(&pool, date, &session.query, &session.sort_by).await {
+            warn!("Failed to count the search: {:?}", e);
+        }
+    });
+    Some(query_id)
+}
+
+/// Count a clicked hit of the search, the repeated clicks on the same hit are ignored
+pub async fn record_click(
+    mut redis: ConnectionManager,
+    pool: &PgPool,
+    query_id: &str,
+    song_id: i64,
+) -> Result<(), FeedbackError> {
+    let value: Option<String> = redis.get(session_key(query_id)).await?;
+    let session: SearchSession = value.and_then(|x| serde_json::from_str(&x).ok())
+        .ok_or(FeedbackError::SearchNotFound)?;
+    let index = session.hit_ids.iter().position(|x| *x == song_id)
+        .ok_or(FeedbackError::NotAHit)?;
+    let position = (session.offset + index) as i32;
+
+    let key = clicks_key(query_id);
+    let (added, clicked): (i64, i64) = redis::pipe()
+        .atomic()
+        .sadd(&key, song_id)
+        .scard(&key)
+        .expire(&key, SESSION_TTL_SECS as i64).ignore()
+        .query_async(&mut redis).await?;
+    if added == 0 {
+        return Ok(());
+    }
+
+    let date = Utc::now().date_naive();
+    let mut tx = pool.begin().await?;
+    SearchStatDao::add_query_click(&mut *tx, date, &session.query, &session.sort_by, position, clicked == 1).await?;
+    SearchStatDao::add_position_click(&mut *tx, date, &session.sort_by, position).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!("hachimi 哈基米", normalize_query("  HachiMi 哈基米 "));
+        assert_eq!(MAX_QUERY_CHARS, normalize_query(&"基".repeat(200)).chars().count());
+    }
+}
@@ -0,0 +1,60 @@
+//! Shadow-banning the spam accounts, to slow them down without alerting them.
+//!
+//! The interactions of the shadow-banned features are still accepted and shown to the user themselves, but
+//! excluded from the public counts, rankings and comment listings read by the others. The banned features are chosen per user,
+//! see [crate::db::user_shadow_ban] for them. The counts are cached for minutes, so a change takes effect after
+//! the caches expire.
+
+use crate::db::user_shadow_ban::{self, IUserShadowBanDao, UserShadowBan, UserShadowBanDao};
+use chrono::Utc;
+use itertools::Itertools;
+use sqlx::PgExecutor;
+
+pub const FEATURES: [&str; 3] = [user_shadow_ban::FEATURE_LIKES, user_shadow_ban::FEATURE_PLAYS, user_shadow_ban::FEATURE_COMMENTS];
+
+/// Whether the features are known, the duplicates are allowed
+pub fn is_valid_features(features: &[String]) -> bool {
+    features.iter().all(|x| FEATURES.contains(&x.as_str()))
+}
+
+/// Set the shadow-banned features of the user, the user is unbanned if `features` is empty
+pub async fn set<'e>(
+    executor: impl PgExecutor<'e>,
+    operator_uid: i64,
+    uid: i64,
+    features: &[String],
+    reason: &str,
+) -> sqlx::Result<()> {
+    if features.is_empty() {
+        UserShadowBanDao::delete_by_user_id(executor, uid).await?;
+        return Ok(());
+    }
+    let now = Utc::now();
+    UserShadowBanDao::upsert(executor, &UserShadowBan {
+        user_id: uid,
+        features: features.iter().unique().sorted().cloned().collect(),
+        reason: reason.to_string(),
+        update_by: operator_uid,
+        create_time: now,
+        update_time: now,
+    }).await
+}
+
+/// Whether the feature of the user is shadow-banned
+pub async fn is_banned<'e>(executor: impl PgExecutor<'e>, uid: i64, feature: &str) -> sqlx::Result<bool> {
+    let ban = UserShadowBanDao::get_by_user_id(executor, uid).await?;
+    Ok(ban.is_some_and(|x| x.features.iter().any(|f| f == feature)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_features() {
+        assert!(is_valid_features(&["likes".to_string(), "plays".to_string(), "likes".to_string()]));
+        assert!(is_valid_features(&[]));
+        assert!(is_valid_features(&["comments".to_string()]));
+        assert!(!is_valid_features(&["posts".to_string()]));
+    }
+}
@@ -1,14 +1,28 @@
+use crate::db::hidden_song::{HiddenSongDao, IHiddenSongDao};
 use crate::db::song::{ISongDao, Song, SongDao, SongOriginInfo, SongProductionCrew};
+use crate::db::song_publishing_review::{ISongPublishingReviewDao, SongPublishingReviewDao};
+use crate::db::song_audio_rendition::{ISongAudioRenditionDao, SongAudioRendition, SongAudioRenditionDao};
+use crate::db::song_share::{ISongShareDao, SongShareDao};
 use crate::db::song_tag::{ISongTagDao, SongTagDao};
 use crate::db::user::{IUserDao, UserDao};
+use crate::db::localized_title::{self, ILocalizedTitleDao, LocalizedTitleDao};
+use crate::db::storage_orphan_object::{IStorageOrphanObjectDao, StorageOrphanObjectDao};
+use crate::db::user_storage_object::{IUserStorageObjectDao, UserStorageObjectDao};
 use crate::db::CrudDao;
-use crate::service::song_like;
+use crate::service::localization::LocalizedTitleItem;
+use crate::service::support_link::SupportLink;
+use crate::service::{localization, recommend_v2, song_like, support_link, upload_cleanup};
+use crate::search;
+use crate::util::{redis_health, IsBlank};
+use crate::web::routes;
 use crate::web::routes::song::TagItem;
+use crate::web::state::AppState;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use rand::Rng;
 use redis::aio::ConnectionManager;
-use redis::{AsyncTypedCommands, MSetOptions, SetExpiry};
+use redis::{AsyncTypedCommands, ExistenceCheck, MSetOptions, SetExpiry, SetOptions};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::HashMap;
@@ -16,7 +30,7 @@ use std::sync::Arc;
 use std::time::Instant;
 use tracing::{debug, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PublicSongDetail {
     pub id: i64,
     pub display_id: String,
@@ -43,10 +57,94 @@ pub struct PublicSongDetail {
     /// @since 251105
     pub gain: Option<f32>,
     /// @since 251105
-    pub explicit: Option<bool>
+    pub explicit: Option<bool>,
+    /// The EBU R128 integrated loudness in LUFS, to normalize the volume across the platforms unlike `gain`.
+    /// `None` for the songs not measured yet.
+    /// @since 260505
+    #[serde(default)]
+    pub loudness_lufs: Option<f32>,
+    /// The true peak in dBTP, the limit of the gain applied without clipping
+    /// @since 260505
+    #[serde(default)]
+    pub true_peak: Option<f32>,
+    /// The titles in the other languages, the `title` and `subtitle` are replaced by the one preferred by
+    /// `Accept-Language` in the detail and search responses.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Vec<LocalizedTitleItem>,
+    /// The language of the `title` if it's localized, `None` for the original title.
+    /// @since 260430
+    #[serde(default)]
+    pub title_lang: Option<String>,
+    /// The original title and subtitle if they are replaced.
+    /// @since 260430
+    #[serde(default)]
+    pub default_title: Option<String>,
+    /// @since 260430
+    #[serde(default)]
+    pub default_subtitle: Option<String>,
+    /// The lower-bitrate renditions of `audio_url` for streaming, empty until they are transcoded.
+    /// @since 260505
+    #[serde(default)]
+    pub audio_renditions: Vec<AudioRendition>,
+    /// The times shared to the other platforms, see [crate::service::song_share]
+    /// @since 260505
+    #[serde(default)]
+    pub share_count: i64,
+    /// The links to support the uploader, see [crate::service::support_link]
+    /// @since 260505
+    #[serde(default)]
+    pub uploader_support_links: Vec<SupportLink>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AudioRendition {
+    /// `aac` in M4A or `opus` in Ogg
+    pub codec: String,
+    pub bitrate_kbps: i32,
+    pub url: String,
+    pub size: i64,
+    /// The true peak in dBTP of the rendition itself, usually above the `true_peak` of the song after the lossy
+    /// encoding, so the gain applied to the rendition should be limited by it instead
+    /// @since 260505
+    #[serde(default)]
+    pub true_peak: Option<f32>,
+}
+
+impl AudioRendition {
+    /// The renditions transcoded from the current audio of the song, the stale ones are left out
+    fn list_of(song: &Song, renditions: Vec<SongAudioRendition>) -> Vec<Self> {
+        renditions.into_iter()
+            .filter(|x| x.source_url == song.file_url)
+            .map(|x| AudioRendition {
+                codec: x.codec,
+                bitrate_kbps: x.bitrate_kbps,
+                url: x.file_url,
+                size: x.size,
+                true_peak: x.true_peak,
+            })
+            .collect()
+    }
+}
+
+impl PublicSongDetail {
+    /// Take the title preferred by the current request, see [localization::select_for_request]
+    pub fn localize(&mut self) {
+        if self.title_lang.is_some() {
+            return;
+        }
+        if let Some(x) = localization::select_for_request(&self.localized_titles) {
+            self.default_title = Some(std::mem::replace(&mut self.title, x.title.clone()));
+            // Keep the original subtitle if it's not localized
+            if !x.subtitle.is_blank() {
+                self.default_subtitle = Some(std::mem::replace(&mut self.subtitle, x.subtitle.clone()));
+            }
+            self.title_lang = Some(x.lang.clone());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreationTypeInfo {
     // If `song_id` is Some, the rest fields could be None
     pub song_display_id: Option<String>,
@@ -56,7 +154,7 @@ pub struct CreationTypeInfo {
     pub origin_type: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ExternalLink {
     pub platform: String,
     pub url: String,
@@ -80,7 +178,7 @@ pub async fn get_public_detail_with_cache_by_display_id(
     song_display_id: &str,
 ) -> Result<Option<PublicSongDetail>, anyhow::Error> {
     let cache_key_display_id = format!("song:detail:{}", song_display_id);
-    let cache: Option<String> = redis.get(&cache_key_display_id).await?;
+    let cache = redis_health::cached(redis.get(&cache_key_display_id)).await.flatten();
 
     if let Some(cache) = cache {
         if cache == "null" {
@@ -96,18 +194,25 @@ pub async fn get_public_detail_with_cache_by_display_id(
         Some(data) => {
             // Set cache both for id and display_id
             let cache_key = format!("song:detail:{}", data.id);
-            let _: () = redis.set_ex(cache_key, serde_json::to_string(&data)?, 30 * 60).await?;
-            let _: () = redis.set_ex(cache_key_display_id, serde_json::to_string(&data)?, 30 * 60).await?;
+            let value = serde_json::to_string(&data)?;
+            redis_health::cached(redis.set_ex(cache_key, &value, 30 * 60)).await;
+            redis_health::cached(redis.set_ex(cache_key_display_id, &value, 30 * 60)).await;
             Ok(Some(data))
         }
         None => {
             // Not exists to forbid cache-through
-            let _: () = redis.set_ex(cache_key_display_id, "null", 30 * 60).await?;
+            redis_health::cached(redis.set_ex(cache_key_display_id, "null", 30 * 60)).await;
             Ok(None)
         }
     }
 }
 
+/// Whether the song can be played and liked, the hidden songs and the songs of the banned users are treated as
+/// not existing the same as [get_public_detail_with_cache]
+pub async fn is_public(redis: ConnectionManager, sql_pool: &PgPool, song_id: i64) -> anyhow::Result<bool> {
+    Ok(get_public_detail_with_cache(redis, sql_pool, &[song_id]).await?.contains_key(&song_id))
+}
+
 pub async fn get_public_detail_with_cache(
     mut redis: ConnectionManager,
     sql_pool: &PgPool,
@@ -118,7 +223,9 @@ pub async fn get_public_detail_with_cache(
     let cache_keys = song_id_list.iter().map(|id| format!("song:detail:{}", id))
         .collect::<Vec<_>>();
 
-    let cache: Vec<Option<String>> = redis.mget(&cache_keys).await?;
+    // Treat all as missed if Redis is unavailable
+    let cache: Vec<Option<String>> = redis_health::cached(redis.mget(&cache_keys)).await
+        .unwrap_or_else(|| vec![None; cache_keys.len()]);
     let mut cached: HashMap<i64, PublicSongDetail> = HashMap::with_capacity(song_id_list.len());
 
     let mut missed_ids: Vec<i64> = vec![];
@@ -175,7 +282,8 @@ pub async fn get_public_detail_with_cache(
         .into_iter().flatten().collect::<Vec<_>>();
 
     if !cache_to_save_items.is_empty() {
-        redis.mset_ex(&cache_to_save_items, MSetOptions::default().with_expiration(SetExpiry::EX(rand::random_range(30 * 60..40 * 60)))).await?;
+        let options = MSetOptions::default().with_expiration(SetExpiry::EX(rand::random_range(30 * 60..40 * 60)));
+        redis_health::cached(redis.mset_ex(&cache_to_save_items, options)).await;
     }
 
     // Assemble the cached and fetch
@@ -243,6 +351,107 @@ pub async fn get_public_detail_with_cache_legacy(
     Ok(result)
 }
 
+/// Delete the cached details of the songs, both keyed by id and display id
+pub async fn evict_detail_cache(mut redis: ConnectionManager, songs: &[Song]) -> anyhow::Result<()> {
+    if songs.is_empty() {
+        return Ok(());
+    }
+    let keys = songs.iter()
+        .flat_map(|x| [format!("song:detail:{}", x.id), format!("song:detail:{}", x.display_id)])
+        .collect_vec();
+    redis.del(&keys).await?;
+    Ok(())
+}
+
+/// The review comment of the pending modifications rejected by [delete_song]
+pub const DELETED_SONG_REVIEW_COMMENT: &str = "The song is deleted";
+
+/// Delete the song with its contents, then the files no longer referenced from the storage.
+///
+/// The pending modifications of the song are rejected along with it. Returns the count of the deleted files,
+/// see [SongDao::delete_with_contents] for what is kept.
+pub async fn delete_song(state: &AppState, song: &Song) -> anyhow::Result<usize> {
+    let mut tx = state.sql_pool.begin().await?;
+    let rendition_urls = SongDao::delete_with_contents(&mut tx, song.id).await?;
+    LocalizedTitleDao::delete_by_entity_id(&mut *tx, localized_title::ENTITY_SONG, song.id).await?;
+    SongPublishingReviewDao::reject_pending_modifies(&mut *tx, &song.display_id, DELETED_SONG_REVIEW_COMMENT, Utc::now()).await?;
+    tx.commit().await?;
+
+    // The song is deleted already, so the caches are left to expire if they fail to be evicted
+    let evictions = [
+        ("detail cache", evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(song)).await),
+        ("user page cache", routes::song::evict_page_by_user_cache(state.redis_conn.clone(), song.uploader_uid).await),
+        ("list caches", recommend_v2::evict_list_caches(state.redis_conn.clone()).await),
+        ("recommend caches", recommend_v2::evict_recommend_caches(state.redis_conn.clone()).await),
+        ("search document", search::song::delete_song_document(&state.meilisearch, &[song.id]).await.map_err(Into::into)),
+    ];
+    for (name, result) in evictions {
+        if let Err(e) = result {
+            warn!(song_id = song.id, "Failed to evict the {} of the deleted song: {:?}", name, e);
+        }
+    }
+
+    let urls = [song.file_url.clone(), song.cover_art_url.clone()].into_iter().chain(rendition_urls);
+    delete_unreferenced_files(state, urls).await
+}
+
+/// Delete the hosted files no record references anymore and release them from the storage usage of the uploaders,
+/// returns the count of the deleted files.
+///
+/// The files failed to delete are handed over to the cleanup of the temp uploads to be retried, see
+/// [upload_cleanup::cleanup_expired].
+pub async fn delete_unreferenced_files(
+    state: &AppState,
+    urls: impl IntoIterator<Item = String>,
+) -> anyhow::Result<usize> {
+    let urls = urls.into_iter()
+        .unique()
+        .filter(|x| state.file_host.key_of(x).is_some())
+        .collect_vec();
+    let referenced = StorageOrphanObjectDao::referenced_urls(&state.sql_pool, &urls).await?;
+    let keys = urls.iter()
+        .filter(|x| !referenced.contains(x))
+        .filter_map(|x| state.file_host.key_of(x))
+        .collect_vec();
+    if keys.is_empty() {
+        return Ok(0);
+    }
+    if let Err(e) = state.file_host.delete_many(&keys).await {
+        warn!("Failed to delete {} files, retrying later: {:?}", keys.len(), e);
+        let mut redis = state.redis_conn.clone();
+        for key in &keys {
+            upload_cleanup::track_temp(&mut redis, key).await;
+        }
+        return Ok(0);
+    }
+    for key in &keys {
+        UserStorageObjectDao::delete_by_key(&state.sql_pool, key).await?;
+    }
+    Ok(keys.len())
+}
+
+/// Patch the like count of the cached detail in place, keeping the TTL.
+///
+/// Keys that are not cached stay absent, they will be assembled with the latest count when read.
+pub async fn update_cached_like_count(
+    mut redis: ConnectionManager,
+    song_id: i64,
+    like_count: i64,
+) -> anyhow::Result<()> {
+    let cache: Option<String> = redis.get(format!("song:detail:{}", song_id)).await?;
+    let Some(mut data) = cache.and_then(|x| serde_json::from_str::<PublicSongDetail>(&x).ok()) else {
+        return Ok(());
+    };
+    data.like_count = like_count;
+    let value = serde_json::to_string(&data)?;
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::XX)
+        .with_expiration(SetExpiry::KEEPTTL);
+    redis.set_options(format!("song:detail:{}", song_id), &value, options.clone()).await?;
+    redis.set_options(format!("song:detail:{}", data.display_id), &value, options).await?;
+    Ok(())
+}
+
 async fn get_from_db_by_display_id(
     redis: &ConnectionManager,
     sql_pool: &PgPool,
@@ -281,7 +490,15 @@ async fn get_from_db_by_ids(
         return Ok(vec![]);
     }
     
-    let songs = SongDao::list_by_ids(sql_pool, song_ids).await?;
+    let mut songs = SongDao::list_by_ids(sql_pool, song_ids).await?;
+
+    // Songs of banned users are treated as not existing
+    let uploader_ids = songs.iter().map(|x| x.uploader_uid).unique().collect_vec();
+    let banned_ids = UserDao::list_banned_ids(sql_pool, &uploader_ids).await?;
+    songs.retain(|x| !banned_ids.contains(&x.uploader_uid));
+    // So are the hidden songs
+    let hidden_ids = HiddenSongDao::list_hidden_ids(sql_pool, song_ids).await?;
+    songs.retain(|x| !hidden_ids.contains(&x.id));
 
     assemble_from_db_batch(sql_pool, &songs).await
 }
@@ -361,8 +578,16 @@ async fn assemble_from_db_batch(
     });
 
     let like_counts_map_fut = SongDao::count_likes_batch(sql_pool, &song_ids);
-
-    let (a, b, c, d, e, f, g) = tokio::join!(a, b, c, d, play_counts_map_fut, f, like_counts_map_fut);
+    let localized_titles_fut = localization::list_by_ids(sql_pool, localized_title::ENTITY_SONG, &song_ids);
+    let renditions_fut = SongAudioRenditionDao::list_by_song_ids(sql_pool, &song_ids);
+    let share_counts_fut = SongShareDao::count_by_song_ids(sql_pool, &song_ids);
+    let uploader_ids = songs.iter().map(|x| x.uploader_uid).unique().collect_vec();
+    let support_links_fut = support_link::list_by_user_ids(sql_pool, &uploader_ids);
+
+    let (a, b, c, d, e, f, g, h, i, j, k) = tokio::join!(
+        a, b, c, d, play_counts_map_fut, f, like_counts_map_fut, localized_titles_fut, renditions_fut, share_counts_fut,
+        support_links_fut
+    );
     let (
         (mut tag_id_map, _tag_ids, tags_ref),
         uploader_users_map,
@@ -370,8 +595,13 @@ async fn assemble_from_db_batch(
         mut external_links_map,
         mut play_counts_map,
         mut production_crew,
-        mut like_counts_map
-    ) = (a??, b??, c??, d??, e?, f??, g?);
+        mut like_counts_map,
+        mut localized_titles,
+        renditions,
+        share_counts_map,
+        support_links_map,
+    ) = (a??, b??, c??, d??, e?, f??, g?, h?, i?, j?, k?);
+    let mut renditions_map = renditions.into_iter().into_group_map_by(|x| x.song_id);
 
 
     let result = songs.into_iter().map(|song| {
@@ -423,6 +653,15 @@ async fn assemble_from_db_batch(
             release_time: song.release_time,
             gain: song.gain,
             explicit: song.explicit,
+            loudness_lufs: song.loudness_lufs,
+            true_peak: song.true_peak,
+            localized_titles: localized_titles.remove(&song.id).unwrap_or_default(),
+            title_lang: None,
+            default_title: None,
+            default_subtitle: None,
+            audio_renditions: AudioRendition::list_of(song, renditions_map.remove(&song.id).unwrap_or_default()),
+            share_count: share_counts_map.get(&song.id).copied().unwrap_or(0),
+            uploader_support_links: support_links_map.get(&song.uploader_uid).cloned().unwrap_or_default(),
         };
         data
     }).collect_vec();
@@ -444,8 +683,15 @@ async fn assemble_from_db(
         }
     ).collect();
 
-    let uploader_name = UserDao::get_by_id(sql_pool, song.uploader_uid).await?
-        .map(|x| x.username).unwrap_or_else(|| "Invalid".to_string());
+    let uploader = UserDao::get_by_id(sql_pool, song.uploader_uid).await?;
+    if uploader.as_ref().is_some_and(|x| x.is_banned) {
+        // Songs of banned users are treated as not existing
+        return Ok(None)
+    }
+    if !HiddenSongDao::list_hidden_ids(sql_pool, &[song.id]).await?.is_empty() {
+        return Ok(None)
+    }
+    let uploader_name = uploader.map(|x| x.username).unwrap_or_else(|| "Invalid".to_string());
 
     let origin_infos = SongDao::list_origin_info_by_song_id(sql_pool, song.id).await?;
     let mut id_display_map = HashMap::new();
@@ -500,6 +746,21 @@ async fn assemble_from_db(
         release_time: song.release_time,
         gain: song.gain,
         explicit: song.explicit,
+        loudness_lufs: song.loudness_lufs,
+        true_peak: song.true_peak,
+        localized_titles: localization::list_by_ids(sql_pool, localized_title::ENTITY_SONG, &[song.id]).await?
+            .remove(&song.id)
+            .unwrap_or_default(),
+        title_lang: None,
+        default_title: None,
+        default_subtitle: None,
+        audio_renditions: AudioRendition::list_of(&song, SongAudioRenditionDao::list_by_song_ids(sql_pool, &[song.id]).await?),
+        share_count: SongShareDao::count_by_song_ids(sql_pool, &[song.id]).await?
+            .remove(&song.id)
+            .unwrap_or(0),
+        uploader_support_links: support_link::list_by_user_ids(sql_pool, &[song.uploader_uid]).await?
+            .remove(&song.uploader_uid)
+            .unwrap_or_default(),
     };
 
     Ok(Some(data))
@@ -0,0 +1,75 @@
+//! The songs excluded from the recommendations of a user.
+//!
+//! Each user has two sorted sets in Redis, the member is the song id and the score is the unix time when the
+//! exclusion expires, so a song can be recommended again after a while.
+
+use crate::util::redis_health;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use std::collections::HashSet;
+
+/// Recently played songs are excluded for two weeks
+const PLAYED_TTL_SECS: i64 = 14 * 86400;
+/// Songs marked as not interested are excluded for four weeks
+const DISLIKED_TTL_SECS: i64 = 28 * 86400;
+/// Only the latest this many played songs are kept
+const MAX_PLAYED: isize = 500;
+
+fn played_key(user_id: i64) -> String {
+    format!("songs:excluded:played:{}", user_id)
+}
+
+fn disliked_key(user_id: i64) -> String {
+    format!("songs:excluded:disliked:{}", user_id)
+}
+
+async fn add(redis: &mut ConnectionManager, key: &str, song_id: i64, ttl_secs: i64, max: Option<isize>) -> anyhow::Result<()> {
+    let expire_at = Utc::now().timestamp() + ttl_secs;
+    let mut pipe = redis::pipe();
+    pipe.atomic()
+        // Never shorten an existing exclusion
+        .cmd("ZADD").arg(key).arg("GT").arg(expire_at).arg(song_id).ignore()
+        .expire(key, ttl_secs).ignore();
+    if let Some(max) = max {
+        pipe.zremrangebyrank(key, 0, -(max + 1)).ignore();
+    }
+    let _: () = pipe.query_async(redis).await?;
+    Ok(())
+}
+
+/// Exclude a played song, ignored if Redis is unavailable
+pub async fn record_played(mut redis: ConnectionManager, user_id: i64, song_id: i64) {
+    redis_health::cached(add(&mut redis, &played_key(user_id), song_id, PLAYED_TTL_SECS, Some(MAX_PLAYED))).await;
+}
+
+/// Exclude a song the user is not interested in
+pub async fn mark_not_interested(mut redis: ConnectionManager, user_id: i64, song_id: i64) -> anyhow::Result<()> {
+    add(&mut redis, &disliked_key(user_id), song_id, DISLIKED_TTL_SECS, None).await
+}
+
+async fn list(redis: &mut ConnectionManager, keys: &[String]) -> anyhow::Result<HashSet<i64>> {
+    let now = Utc::now().timestamp();
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.zrembyscore(key, "-inf", now).ignore()
+            .zrangebyscore(key, format!("({now}"), "+inf");
+    }
+    let result: Vec<Vec<i64>> = pipe.query_async(redis).await?;
+    Ok(result.into_iter().flatten().collect())
+}
+
+/// The songs the user is not interested in, empty for anonymous users or if Redis is unavailable
+pub async fn list_disliked(mut redis: ConnectionManager, user_id: i64) -> HashSet<i64> {
+    if user_id <= 0 {
+        return HashSet::new();
+    }
+    redis_health::cached(list(&mut redis, &[disliked_key(user_id)])).await.unwrap_or_default()
+}
+
+/// The recently played songs and the songs the user is not interested in
+pub async fn list_excluded(mut redis: ConnectionManager, user_id: i64) -> HashSet<i64> {
+    if user_id <= 0 {
+        return HashSet::new();
+    }
+    redis_health::cached(list(&mut redis, &[played_key(user_id), disliked_key(user_id)])).await.unwrap_or_default()
+}
@@ -1,4 +1,8 @@
+use crate::db::error::DbError;
 use crate::db::song::{ISongDao, SongDao, SongLike};
+use crate::db::user_shadow_ban;
+use crate::service::{shadow_ban, song};
+use crate::util::redis_health;
 use chrono::Utc;
 use itertools::Itertools;
 use redis::aio::ConnectionManager;
@@ -19,7 +23,8 @@ pub async fn get_song_likes_batch(
     let likes_cache = get_likes_cache_batch(&mut redis, song_ids).await?;
 
     let missed_ids = likes_cache.iter()
-        .filter_map(|(id, cache)| cache.as_ref().map(|_| *id).or(Some(*id)))
+        .filter(|(_, cache)| cache.is_none())
+        .map(|(id, _)| *id)
         .collect::<Vec<_>>();
     if missed_ids.is_empty() {
         let filtered = likes_cache.into_iter()
@@ -46,29 +51,48 @@ pub async fn get_song_likes(
     song_id: i64
 ) -> anyhow::Result<i64> {
     let mut redis = redis_conn.clone();
-    let likes_cache = get_likes_cache(&mut redis, song_id).await?;
-    if let Some(x) = likes_cache {
-        return Ok(x)
+    match redis_health::cached(get_likes_cache(&mut redis, song_id)).await {
+        Some(Some(x)) => return Ok(x),
+        Some(None) => {}
+        // Redis is unavailable
+        None => return Ok(SongDao::count_likes(sql_pool, song_id).await?),
     }
 
     let likes_db = SongDao::count_likes(sql_pool, song_id).await?;
-    set_likes_cache(&mut redis, song_id, likes_db).await?;
+    redis_health::cached(set_likes_cache(&mut redis, song_id, likes_db)).await;
     Ok(likes_db)
 }
 
+/// Like the song, returns the like count after liking.
+///
+/// The like of a shadow-banned user is not counted, but it's added to the returned count, so they don't notice it.
 pub async fn like(
     redis_conn: &ConnectionManager,
     sql_pool: &PgPool,
     uid: i64, song_id: i64,
     playback_position_secs: Option<i32>
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i64> {
+    let likes = like_and_count(redis_conn, sql_pool, uid, song_id, playback_position_secs).await?;
+    if shadow_ban::is_banned(sql_pool, uid, user_shadow_ban::FEATURE_LIKES).await? {
+        Ok(likes + 1)
+    } else {
+        Ok(likes)
+    }
+}
+
+async fn like_and_count(
+    redis_conn: &ConnectionManager,
+    sql_pool: &PgPool,
+    uid: i64, song_id: i64,
+    playback_position_secs: Option<i32>
+) -> anyhow::Result<i64> {
     let mut redis = redis_conn.clone();
 
     let cache_is_liked = get_cache_is_liked(&mut redis, uid, song_id).await?;
     match cache_is_liked {
         Some(x) => {
             if x {
-                return Ok(())
+                return get_song_likes(redis_conn, sql_pool, song_id).await
             }
         }
         None => {
@@ -76,20 +100,28 @@ pub async fn like(
             set_cache_is_liked(&mut redis, uid, song_id, db_is_liked).await?;
 
             if db_is_liked {
-                return Ok(())
+                return get_song_likes(redis_conn, sql_pool, song_id).await
             }
         }
     }
 
-    SongDao::insert_likes(sql_pool, &[SongLike {
+    let result = SongDao::insert_likes(sql_pool, &[SongLike {
         song_id,
         user_id: uid,
         playback_position_secs,
         create_time: Utc::now(),
-    }]).await?;
+    }]).await.map_err(DbError::from);
+    match result {
+        Ok(_) => {}
+        // Liked by a concurrent request, the counter has been refreshed by that one
+        Err(e) if e.is_unique_violation() => {
+            set_cache_is_liked(&mut redis, uid, song_id, true).await?;
+            return get_song_likes(redis_conn, sql_pool, song_id).await
+        }
+        Err(e) => Err(e)?,
+    }
     set_cache_is_liked(&mut redis, uid, song_id, true).await?;
-    incr_likes_cache(&mut redis, song_id, 1).await?;
-    Ok(())
+    refresh_likes(&mut redis, sql_pool, song_id).await
 }
 
 pub async fn is_liked(
@@ -108,18 +140,19 @@ pub async fn is_liked(
     Ok(db_is_liked)
 }
 
+/// Unlike the song, returns the like count after unliking.
 pub async fn unlike(
     redis_conn: &ConnectionManager,
     sql_pool: &PgPool,
     uid: i64, song_id: i64,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i64> {
     let mut redis = redis_conn.clone();
 
     let cache_is_liked = get_cache_is_liked(&mut redis, uid, song_id).await?;
     match cache_is_liked {
         Some(x) => {
             if !x {
-                return Ok(())
+                return get_song_likes(redis_conn, sql_pool, song_id).await
             }
         }
         None => {
@@ -127,15 +160,23 @@ pub async fn unlike(
             set_cache_is_liked(&mut redis, uid, song_id, db_is_liked).await?;
 
             if !db_is_liked {
-                return Ok(())
+                return get_song_likes(redis_conn, sql_pool, song_id).await
             }
         }
     }
 
     SongDao::delete_like(sql_pool, song_id, uid).await?;
     set_cache_is_liked(&mut redis, uid, song_id, false).await?;
-    incr_likes_cache(&mut redis, song_id, -1).await?;
-    Ok(())
+    refresh_likes(&mut redis, sql_pool, song_id).await
+}
+
+/// Recount the likes and write the count through to the likes cache and the cached song detail,
+/// so the user reads their own write instead of a stale count.
+async fn refresh_likes(redis: &mut ConnectionManager, sql_pool: &PgPool, song_id: i64) -> anyhow::Result<i64> {
+    let likes = SongDao::count_likes(sql_pool, song_id).await?;
+    set_likes_cache(redis, song_id, likes).await?;
+    song::update_cached_like_count(redis.clone(), song_id, likes).await?;
+    Ok(likes)
 }
 
 pub async fn page_by_user(
@@ -178,11 +219,6 @@ async fn set_likes_cache_batch(redis: &mut ConnectionManager, values: &[(i64, i6
     Ok(())
 }
 
-async fn incr_likes_cache(redis: &mut ConnectionManager, song_id: i64, delta: i32) -> anyhow::Result<()> {
-    let _: () = redis.incr(format!("song:likes:{}", song_id), delta).await?;
-    Ok(())
-}
-
 async fn get_cache_is_liked(redis: &mut ConnectionManager, uid: i64, song_id: i64) -> anyhow::Result<Option<bool>> {
     Ok(redis.get(format!("song:liked:{}:{}", uid, song_id)).await?)
 }
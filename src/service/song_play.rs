@@ -1,10 +1,22 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use chrono::{NaiveDate, Utc};
+use itertools::Itertools;
+use metrics::{counter, gauge, histogram};
 use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
+use redis::{AsyncCommands, ExistenceCheck, Script, SetExpiry, SetOptions};
 use sqlx::PgPool;
-use crate::db::song::{ISongDao, SongDao};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+use crate::db::song::{ISongDao, SongDao, SongPlay};
+use crate::db::song_play_rollup::{ISongPlayRollupDao, SongPlayRollupDao};
+use crate::db::user_shadow_ban::FEATURE_PLAYS;
 use crate::service::errors::ServiceResult;
+use crate::service::shadow_ban;
+use crate::util::redis_health;
 use crate::util::redlock::RedLock;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
 
 pub async fn get_play_count(
     redis: &mut ConnectionManager,
@@ -12,9 +24,11 @@ pub async fn get_play_count(
     sql_pool: &PgPool,
     song_id: i64
 ) -> ServiceResult<i64, ()> {
-    let cache = get_plays_cache(redis, song_id).await?;
-    if let Some(x) = cache {
-        return Ok(x)
+    match redis_health::cached(get_plays_cache(redis, song_id)).await {
+        Some(Some(x)) => return Ok(x),
+        Some(None) => {}
+        // Redis is unavailable, skip the lock as well
+        None => return Ok(SongDao::count_plays(sql_pool, song_id).await?),
     }
 
     let guard = red_lock.lock_with_timeout("lock:song_plays", Duration::from_secs(10)).await?;
@@ -24,7 +38,7 @@ pub async fn get_play_count(
     }
 
     let likes_db = SongDao::count_plays(sql_pool, song_id).await?;
-    set_plays_cache(redis, song_id, likes_db).await?;
+    redis_health::cached(set_plays_cache(redis, song_id, likes_db)).await;
     drop(guard);
     Ok(likes_db)
 }
@@ -34,6 +48,402 @@ async fn get_plays_cache(redis: &mut ConnectionManager, song_id: i64) -> anyhow:
 }
 
 async fn set_plays_cache(redis: &mut ConnectionManager, song_id: i64, value: i64) -> anyhow::Result<()> {
-    let _: () = redis.set_ex(format!("song:likes:{}", song_id), value, 300).await?;
+    let _: () = redis.set_ex(format!("song:plays:{}", song_id), value, 300).await?;
     Ok(())
-}
\ No newline at end of file
+}
+/// Plays recorded since the last flush, a hash of song id to count
+const PENDING_KEY: &str = "song:play_count_pending";
+/// The pending plays being flushed, kept until they are written so a failed flush is retried
+const FLUSHING_KEY: &str = "song:play_count_flushing";
+/// The id of the flushing plays, recorded in `song_play_count_batches` when they are added
+const FLUSHING_BATCH_KEY: &str = "song:play_count_flushing_batch";
+/// Held during a flush, so the flushing plays are never added twice by the concurrent flushes
+const FLUSH_LOCK: &str = "lock:play_count_flush";
+const FLUSH_LOCK_TTL: Duration = Duration::from_secs(600);
+/// How long the added batches are kept, much longer than a batch can be left in Redis by a failed flush
+const FLUSHED_BATCH_RETENTION: chrono::Days = chrono::Days::new(7);
+
+/// Take the pending plays as the flushing ones with `ARGV[1]` as the batch id unless a failed flush left some.
+/// Returns the batch id and the flushing plays, or nil if there are none.
+/// `KEYS[1]`: [PENDING_KEY], `KEYS[2]`: [FLUSHING_KEY], `KEYS[3]`: [FLUSHING_BATCH_KEY]
+const CLAIM_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[2]) == 0 then
+    if redis.call('EXISTS', KEYS[1]) == 0 then
+        return false
+    end
+    redis.call('RENAME', KEYS[1], KEYS[2])
+    redis.call('SET', KEYS[3], ARGV[1])
+end
+local batch = redis.call('GET', KEYS[3])
+if not batch then
+    redis.call('SET', KEYS[3], ARGV[1])
+    batch = ARGV[1]
+end
+return {batch, redis.call('HGETALL', KEYS[2])}
+";
+
+pub const FLUSH_JOB: Job = Job {
+    name: "flush_play_counts",
+    // Every 5 minutes
+    schedule: "*/5 * * * *",
+    max_jitter: Duration::from_secs(30),
+};
+
+/// Returns true if the play of the song by the user (or anonymous uid) is in the 60 seconds cooldown
+pub async fn cooldown(
+    user_id: i64,
+    song_id: i64,
+    redis: &mut ConnectionManager
+) -> anyhow::Result<bool> {
+    let cooldown_key = format!("play:touch_cooldown:{}:{}", user_id, song_id);
+    let cooldown_absent: Option<bool> = redis_health::cached(redis.set_options(
+        cooldown_key, 0,
+        SetOptions::default().conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(60)) // CD for 60 secs
+    )).await;
+    // Skip the cooldown if Redis is unavailable, a few duplicated plays are acceptable
+    Ok(cooldown_absent.is_some_and(|x| !x))
+}
+
+/// The anonymous plays of a song counted from an IP per [ANONYMOUS_IP_WINDOW_SECS]
+const ANONYMOUS_PLAYS_PER_IP: i64 = 30;
+const ANONYMOUS_IP_WINDOW_SECS: u64 = 3600;
+
+/// Returns true if the IP played the song anonymously too many times in the window.
+///
+/// The fingerprints are made up by the clients, so the cooldown by the anonymous uid alone can be bypassed by
+/// rotating them. Unlike the cooldown, this is keyed by the IP only.
+pub async fn anonymous_ip_limited(
+    ip: &str,
+    song_id: i64,
+    redis: &mut ConnectionManager,
+) -> anyhow::Result<bool> {
+    let key = format!("play:anonymous_ip:{}:{}", ip, song_id);
+    // The expiration is set with the counter created, so a counter never outlives the window
+    let count: Option<(i64,)> = redis_health::cached(
+        redis::pipe()
+            .atomic()
+            .set_options(&key, 0, SetOptions::default().conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::EX(ANONYMOUS_IP_WINDOW_SECS))).ignore()
+            .incr(&key, 1)
+            .query_async(redis)
+    ).await;
+    let count = count.map(|(x,)| x);
+    // Skip the limit if Redis is unavailable, the same as the cooldown
+    Ok(count.is_some_and(|x| x > ANONYMOUS_PLAYS_PER_IP))
+}
+
+/// Count a recorded play into `songs.play_count` at the next flush.
+///
+/// The plays of the users shadow banned from plays are not counted, the same as [crate::service::song_stats].
+/// The plays missed while Redis is unavailable are repaired by the weekly verification.
+pub async fn add_pending_play(
+    mut redis: ConnectionManager,
+    sql_pool: &PgPool,
+    user_id: Option<i64>,
+    song_id: i64,
+) -> anyhow::Result<()> {
+    if let Some(uid) = user_id && shadow_ban::is_banned(sql_pool, uid, FEATURE_PLAYS).await? {
+        return Ok(());
+    }
+    let _: Option<i64> = redis_health::cached(redis.hincr(PENDING_KEY, song_id, 1)).await;
+    Ok(())
+}
+
+/// Add the pending plays to `songs.play_count`, returns the number of the songs updated.
+///
+/// Skipped if another flush is running. The plays added meanwhile go to a new pending hash, and the ones left by a
+/// failed flush are retried before taking the new ones.
+pub async fn flush_pending_plays(redis: &mut ConnectionManager, red_lock: &RedLock, sql_pool: &PgPool) -> anyhow::Result<usize> {
+    let Some(guard) = red_lock.try_lock_with_ttl(FLUSH_LOCK, FLUSH_LOCK_TTL).await? else {
+        return Ok(0);
+    };
+    let songs = flush_pending_plays_locked(redis, sql_pool).await?;
+    drop(guard);
+    Ok(songs)
+}
+
+/// Flush with [FLUSH_LOCK] held by the caller.
+///
+/// The batch is recorded in the same transaction, so it's not added again if removing it from Redis fails.
+async fn flush_pending_plays_locked(redis: &mut ConnectionManager, sql_pool: &PgPool) -> anyhow::Result<usize> {
+    let claimed: Option<(String, HashMap<i64, i64>)> = Script::new(CLAIM_SCRIPT)
+        .key(PENDING_KEY)
+        .key(FLUSHING_KEY)
+        .key(FLUSHING_BATCH_KEY)
+        .arg(uuid::Uuid::new_v4().to_string())
+        .invoke_async(redis)
+        .await?;
+    let Some((batch_id, counts)) = claimed else {
+        return Ok(0);
+    };
+    let deltas = counts.into_iter().filter(|(_, x)| *x > 0).collect_vec();
+    let mut tx = sql_pool.begin().await?;
+    let added = SongDao::insert_play_count_batch(&mut *tx, &batch_id).await?;
+    if added {
+        SongDao::add_play_counts(&mut *tx, &deltas).await?;
+    } else {
+        warn!("The play counts batch {} was already added, dropping it", batch_id);
+    }
+    SongDao::delete_play_count_batches_before(&mut *tx, Utc::now() - FLUSHED_BATCH_RETENTION).await?;
+    tx.commit().await?;
+    let _: () = redis.del(&[FLUSHING_KEY, FLUSHING_BATCH_KEY]).await?;
+    Ok(if added { deltas.len() } else { 0 })
+}
+
+/// Run `f` with the pending plays added to `songs.play_count` and no flush running, so the plays counted from
+/// `song_plays` by `f` are not added again by the next flush.
+///
+/// The buffered play events are inserted first, since a play is pending as soon as its event is buffered.
+pub async fn with_pending_plays_flushed<T, F, Fut>(
+    redis: &mut ConnectionManager,
+    red_lock: &RedLock,
+    sql_pool: &PgPool,
+    f: F,
+) -> anyhow::Result<T>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let Some(guard) = red_lock.lock_with_ttl_timeout(FLUSH_LOCK, FLUSH_LOCK_TTL, Duration::from_secs(60)).await? else {
+        anyhow::bail!("Timed out waiting for the play counts flush");
+    };
+    let Some(events_guard) = red_lock.lock_with_ttl_timeout(PLAY_EVENTS_FLUSH_LOCK, PLAY_EVENTS_FLUSH_LOCK_TTL, Duration::from_secs(60)).await? else {
+        anyhow::bail!("Timed out waiting for the play events flush");
+    };
+    while flush_play_events_locked(redis, sql_pool).await? == PLAY_EVENTS_BATCH_SIZE {}
+    drop(events_guard);
+    flush_pending_plays_locked(redis, sql_pool).await?;
+    let result = f().await;
+    drop(guard);
+    result
+}
+
+/// Flush the pending plays every 5 minutes until cancelled
+pub async fn run_flush(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &FLUSH_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            let mut redis = state.redis_conn.clone();
+            let red_lock = state.red_lock.clone();
+            async move {
+                let songs = flush_pending_plays(&mut redis, &red_lock, &pool).await?;
+                debug!("Flushed the play counts of {} songs", songs);
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+/// The play events waiting to be inserted into `song_plays`, a list of [SongPlay] in JSON
+const PLAY_EVENTS_KEY: &str = "song:play_events";
+/// The batch of the play events being inserted, kept until they are inserted so a failed flush is retried
+const PLAY_EVENTS_PROCESSING_KEY: &str = "song:play_events:processing";
+/// The id of the processing batch, recorded in `song_play_event_batches` when it's inserted
+const PLAY_EVENTS_PROCESSING_BATCH_KEY: &str = "song:play_events:processing_batch";
+/// Held during a flush, so the concurrent flushes don't claim the same batch. The batch id keeps it inserted once
+/// if a flush outlasts the lock.
+const PLAY_EVENTS_FLUSH_LOCK: &str = "lock:play_events_flush";
+const PLAY_EVENTS_FLUSH_LOCK_TTL: Duration = Duration::from_secs(60);
+/// The play events inserted at most by a flush
+const PLAY_EVENTS_BATCH_SIZE: usize = 1000;
+
+/// Move a batch of at most `ARGV[1]` events to the processing list with `ARGV[2]` as the batch id unless a failed
+/// flush left some. Returns the batch id and the processing events, or nil if there are none.
+/// `KEYS[1]`: [PLAY_EVENTS_KEY], `KEYS[2]`: [PLAY_EVENTS_PROCESSING_KEY], `KEYS[3]`: [PLAY_EVENTS_PROCESSING_BATCH_KEY]
+const CLAIM_PLAY_EVENTS_SCRIPT: &str = r"
+if redis.call('EXISTS', KEYS[2]) == 0 then
+    local batch = redis.call('LRANGE', KEYS[1], 0, tonumber(ARGV[1]) - 1)
+    if #batch == 0 then
+        return false
+    end
+    redis.call('LTRIM', KEYS[1], #batch, -1)
+    redis.call('RPUSH', KEYS[2], unpack(batch))
+    redis.call('SET', KEYS[3], ARGV[2])
+end
+local batch = redis.call('GET', KEYS[3])
+if not batch then
+    redis.call('SET', KEYS[3], ARGV[2])
+    batch = ARGV[2]
+end
+return {batch, redis.call('LRANGE', KEYS[2], 0, -1)}
+";
+const PLAY_EVENTS_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Buffer the play to be inserted into `song_plays` by [run_play_events_flush].
+///
+/// If Redis is unavailable, it's inserted into the database directly instead.
+pub async fn record_play(
+    redis: ConnectionManager,
+    sql_pool: &PgPool,
+    play: SongPlay,
+) -> anyhow::Result<()> {
+    let data = serde_json::to_string(&play)?;
+    let pushed: Option<i64> = redis_health::cached(redis.clone().rpush(PLAY_EVENTS_KEY, data)).await;
+    if pushed.is_none() {
+        SongDao::insert_plays(sql_pool, &[play]).await?;
+    }
+    Ok(())
+}
+
+/// Insert a batch of the buffered plays, returns the number of the play events claimed, including the unreadable
+/// ones dropped.
+///
+/// Skipped if the flusher of another instance is running. The batch is moved to a processing list atomically and
+/// removed after it's inserted, so a failed or interrupted flush is retried by the next one.
+pub async fn flush_play_events(redis: &mut ConnectionManager, red_lock: &RedLock, sql_pool: &PgPool) -> anyhow::Result<usize> {
+    let Some(guard) = red_lock.try_lock_with_ttl(PLAY_EVENTS_FLUSH_LOCK, PLAY_EVENTS_FLUSH_LOCK_TTL).await? else {
+        return Ok(0);
+    };
+    let plays = flush_play_events_locked(redis, sql_pool).await?;
+    drop(guard);
+    Ok(plays)
+}
+
+/// Flush with [PLAY_EVENTS_FLUSH_LOCK] held by the caller.
+///
+/// The batch is recorded in the same transaction, so it's not inserted again by a flush claiming it after the lock
+/// expired, or if removing it from Redis fails.
+async fn flush_play_events_locked(redis: &mut ConnectionManager, sql_pool: &PgPool) -> anyhow::Result<usize> {
+    let claimed: Option<(String, Vec<String>)> = Script::new(CLAIM_PLAY_EVENTS_SCRIPT)
+        .key(PLAY_EVENTS_KEY)
+        .key(PLAY_EVENTS_PROCESSING_KEY)
+        .key(PLAY_EVENTS_PROCESSING_BATCH_KEY)
+        .arg(PLAY_EVENTS_BATCH_SIZE)
+        .arg(uuid::Uuid::new_v4().to_string())
+        .invoke_async(redis)
+        .await?;
+    let Some((batch_id, batch)) = claimed else {
+        return Ok(0);
+    };
+    let plays = batch.iter()
+        .filter_map(|x| match serde_json::from_str::<SongPlay>(x) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                warn!("Dropping the unreadable play event {}: {:?}", x, e);
+                None
+            }
+        })
+        .collect_vec();
+
+    let start = Instant::now();
+    let mut tx = sql_pool.begin().await?;
+    let added = SongDao::insert_play_event_batch(&mut *tx, &batch_id).await?;
+    if added {
+        SongDao::insert_plays(&mut *tx, &plays).await?;
+    } else {
+        warn!("The play events batch {} was already inserted, dropping it", batch_id);
+    }
+    SongDao::delete_play_event_batches_before(&mut *tx, Utc::now() - FLUSHED_BATCH_RETENTION).await?;
+    tx.commit().await?;
+    let _: () = redis.del(&[PLAY_EVENTS_PROCESSING_KEY, PLAY_EVENTS_PROCESSING_BATCH_KEY]).await?;
+    histogram!("play_events_flush_duration_seconds").record(start.elapsed().as_secs_f64());
+    if added {
+        counter!("play_events_flushed_count").increment(plays.len() as u64);
+    }
+    Ok(batch.len())
+}
+
+/// Flush the buffered plays every few seconds until cancelled, and all the remaining ones after that
+pub async fn run_play_events_flush(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let mut redis = state.redis_conn.clone();
+    info!("Starting the play events flusher");
+    loop {
+        // Keep flushing while the batches are full
+        loop {
+            match flush_play_events(&mut redis, &state.red_lock, &state.sql_pool).await {
+                Ok(x) if x == PLAY_EVENTS_BATCH_SIZE => continue,
+                Ok(_) => break,
+                Err(e) => {
+                    error!("Failed to flush the play events: {:?}", e);
+                    break;
+                }
+            }
+        }
+        if let Ok(depth) = redis.llen::<_, i64>(PLAY_EVENTS_KEY).await {
+            gauge!("play_events_queue_depth").set(depth as f64);
+        }
+        if cancel_token.is_cancelled() {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(PLAY_EVENTS_FLUSH_INTERVAL) => {}
+            _ = cancel_token.cancelled() => {}
+        }
+    }
+    info!("Play events flusher stopped");
+    Ok(())
+}
+
+pub const ROLLUP_JOB: Job = Job {
+    name: "rollup_song_plays",
+    // Every 10 minutes
+    schedule: "*/10 * * * *",
+    max_jitter: Duration::from_secs(60),
+};
+
+/// The days rolled up at the first run, enough for the hot songs
+const ROLLUP_BACKFILL_DAYS: u64 = 30;
+
+/// The first day to roll up again after the latest one.
+///
+/// The latest day is not complete yet, and the day before may still receive the plays buffered around the midnight.
+pub fn rollup_since(latest_day: Option<NaiveDate>, today: NaiveDate) -> NaiveDate {
+    match latest_day {
+        Some(x) => x.pred_opt().unwrap_or(x).min(today),
+        None => today - chrono::Days::new(ROLLUP_BACKFILL_DAYS),
+    }
+}
+
+/// Recount the daily plays in `song_play_counts_daily` since [rollup_since], returns the rows rolled up.
+///
+/// The plays of the users shadow banned from plays are not counted, but the days rolled up before the shadow ban
+/// are kept as they are.
+pub async fn rollup_daily_plays(sql_pool: &PgPool) -> anyhow::Result<u64> {
+    let latest_day = SongPlayRollupDao::get_latest_day(sql_pool).await?;
+    let since = rollup_since(latest_day, Utc::now().date_naive());
+    let start = Instant::now();
+    let mut tx = sql_pool.begin().await?;
+    SongPlayRollupDao::delete_since(&mut *tx, since).await?;
+    let rows = SongPlayRollupDao::insert_since(&mut *tx, since, FEATURE_PLAYS).await?;
+    tx.commit().await?;
+    histogram!("song_play_rollup_duration_seconds").record(start.elapsed().as_secs_f64());
+    Ok(rows)
+}
+
+/// Roll up the daily plays every 10 minutes until cancelled
+pub async fn run_rollup(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &ROLLUP_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            async move {
+                let rows = rollup_daily_plays(&pool).await?;
+                debug!("Rolled up {} daily play counts", rows);
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollup_since() {
+        let today = NaiveDate::from_ymd_opt(2026, 5, 8).unwrap();
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 4, 8).unwrap(), rollup_since(None, today));
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 5, 7).unwrap(), rollup_since(Some(today), today));
+        assert_eq!(NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(), rollup_since(Some(NaiveDate::from_ymd_opt(2026, 5, 2).unwrap()), today));
+    }
+}
@@ -0,0 +1,112 @@
+//! The reports of the infringing or miscategorized songs, handled by the contributors.
+//!
+//! A contributor can hide a reported song while looking into it, the hidden songs are treated as not existing
+//! the same as the songs of the banned users, until they're unhidden.
+
+use crate::db::hidden_song::{HiddenSong, HiddenSongDao, IHiddenSongDao};
+use crate::db::song::{Song, SongDao};
+use crate::db::song_report::{ISongReportDao, SongReport, SongReportDao, STATUS_PENDING};
+use crate::db::CrudDao;
+use crate::search;
+use crate::service::{recommend_v2, song};
+use crate::web::routes;
+use crate::web::state::AppState;
+use chrono::Utc;
+use tracing::info;
+
+/// The song infringes the copyright of the reporter or others
+pub const CATEGORY_COPYRIGHT: &str = "copyright";
+/// The song is explicit but not marked so
+pub const CATEGORY_EXPLICIT_CONTENT: &str = "explicit_content";
+/// The creation type or the origin songs are wrong
+pub const CATEGORY_WRONG_ORIGIN_INFO: &str = "wrong_origin_info";
+pub const CATEGORIES: [&str; 3] = [CATEGORY_COPYRIGHT, CATEGORY_EXPLICIT_CONTENT, CATEGORY_WRONG_ORIGIN_INFO];
+
+pub const REASON_MAX_CHARS: usize = 1000;
+/// The reports a user can submit in an hour
+pub const MAX_REPORTS_PER_HOUR: i64 = 10;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("Unknown category {0}")]
+    InvalidCategory(String),
+    #[error("Reason must be {REASON_MAX_CHARS} characters or less")]
+    InvalidReason,
+    #[error("At most {MAX_REPORTS_PER_HOUR} reports can be submitted in an hour")]
+    TooManyReports,
+    #[error("Song {0} not found")]
+    SongNotFound(i64),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Report the song, returns `None` if the user has reported it and it's not handled yet
+pub async fn report(
+    state: &AppState,
+    uid: i64,
+    song_id: i64,
+    category: &str,
+    reason: &str,
+) -> Result<Option<i64>, ReportError> {
+    if !CATEGORIES.contains(&category) {
+        return Err(ReportError::InvalidCategory(category.to_string()));
+    }
+    if reason.chars().count() > REASON_MAX_CHARS {
+        return Err(ReportError::InvalidReason);
+    }
+    let since = Utc::now() - chrono::Duration::hours(1);
+    if SongReportDao::count_by_reporter_since(&state.sql_pool, uid, since).await? >= MAX_REPORTS_PER_HOUR {
+        return Err(ReportError::TooManyReports);
+    }
+    // The hidden songs can still be reported, they're probably being looked into
+    if SongDao::get_by_id(&state.sql_pool, song_id).await?.is_none() {
+        return Err(ReportError::SongNotFound(song_id));
+    }
+    let id = SongReportDao::insert(&state.sql_pool, &SongReport {
+        id: 0,
+        song_id,
+        reporter_uid: uid,
+        category: category.to_string(),
+        reason: reason.trim().to_string(),
+        status: STATUS_PENDING,
+        resolution_note: None,
+        resolved_by: None,
+        resolve_time: None,
+        create_time: Utc::now(),
+    }).await?;
+    Ok(id)
+}
+
+/// Hide or unhide the song, then apply it to everywhere the song is read from.
+///
+/// Returns whether the status changed, setting the current status again re-applies it anyway.
+pub async fn set_hidden(
+    state: &AppState,
+    song: &Song,
+    hidden: bool,
+    operator_uid: i64,
+    reason: &str,
+) -> anyhow::Result<bool> {
+    let changed = if hidden {
+        HiddenSongDao::insert(&state.sql_pool, &HiddenSong {
+            song_id: song.id,
+            reason: reason.to_string(),
+            hidden_by: operator_uid,
+            create_time: Utc::now(),
+        }).await?
+    } else {
+        HiddenSongDao::delete(&state.sql_pool, song.id).await?
+    };
+    info!("User {} set song {} hidden: {}, changed: {}", operator_uid, song.id, hidden, changed);
+
+    song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(song)).await?;
+    routes::song::evict_page_by_user_cache(state.redis_conn.clone(), song.uploader_uid).await?;
+    recommend_v2::evict_list_caches(state.redis_conn.clone()).await?;
+    recommend_v2::evict_recommend_caches(state.redis_conn.clone()).await?;
+    if hidden {
+        search::song::delete_song_document(&state.meilisearch, &[song.id]).await?;
+    } else {
+        search::song::add_or_replace_document(&state.meilisearch, &state.sql_pool, &[song.id]).await?;
+    }
+    Ok(changed)
+}
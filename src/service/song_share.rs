@@ -0,0 +1,113 @@
+//! The shares of the songs to the other platforms, reported by the clients when the share sheet completes.
+//!
+//! They're counted per day and platform only, and weigh a little in the hot songs, see [SHARE_WEIGHT].
+
+use crate::db::song_share::{ISongShareDao, SongShareDao};
+use crate::db::user_shadow_ban::FEATURE_PLAYS;
+use crate::service::shadow_ban;
+use crate::util::redis_health;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// A share counts as this many plays in the hot songs
+pub const SHARE_WEIGHT: i64 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SharePlatform {
+    /// Copied the link
+    Link,
+    Qq,
+    Wechat,
+    Weibo,
+    Bilibili,
+    Twitter,
+    Other,
+}
+
+impl SharePlatform {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharePlatform::Link => "link",
+            SharePlatform::Qq => "qq",
+            SharePlatform::Wechat => "wechat",
+            SharePlatform::Weibo => "weibo",
+            SharePlatform::Bilibili => "bilibili",
+            SharePlatform::Twitter => "twitter",
+            SharePlatform::Other => "other",
+        }
+    }
+}
+
+/// Returns true if the share of the song by the user (or anonymous uid) is in the 60 seconds cooldown,
+/// the same as [crate::service::song_play::cooldown] regardless of the platform
+pub async fn cooldown(
+    user_id: i64,
+    song_id: i64,
+    redis: &mut ConnectionManager,
+) -> anyhow::Result<bool> {
+    let cooldown_key = format!("share:cooldown:{}:{}", user_id, song_id);
+    let cooldown_absent: Option<bool> = redis_health::cached(redis.set_options(
+        cooldown_key, 0,
+        SetOptions::default().conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(60))
+    )).await;
+    // Skip the cooldown if Redis is unavailable, a few duplicated shares are acceptable
+    Ok(cooldown_absent.is_some_and(|x| !x))
+}
+
+/// The anonymous shares of a song counted from an IP per [ANONYMOUS_IP_WINDOW_SECS]
+const ANONYMOUS_SHARES_PER_IP: i64 = 10;
+const ANONYMOUS_IP_WINDOW_SECS: u64 = 3600;
+
+/// Returns true if the IP shared the song anonymously too many times in the window,
+/// the same as [crate::service::song_play::anonymous_ip_limited] since the fingerprints can be rotated
+pub async fn anonymous_ip_limited(
+    ip: &str,
+    song_id: i64,
+    redis: &mut ConnectionManager,
+) -> anyhow::Result<bool> {
+    let key = format!("share:anonymous_ip:{}:{}", ip, song_id);
+    let count: Option<(i64,)> = redis_health::cached(
+        redis::pipe()
+            .atomic()
+            .set_options(&key, 0, SetOptions::default().conditional_set(ExistenceCheck::NX)
+                .with_expiration(SetExpiry::EX(ANONYMOUS_IP_WINDOW_SECS))).ignore()
+            .incr(&key, 1)
+            .query_async(redis)
+    ).await;
+    Ok(count.is_some_and(|(x,)| x > ANONYMOUS_SHARES_PER_IP))
+}
+
+/// Count the share into the rollup of today.
+///
+/// The shares of the users shadow banned from plays are not counted, since they feed the hot songs as well.
+pub async fn record_share(
+    sql_pool: &PgPool,
+    user_id: Option<i64>,
+    song_id: i64,
+    platform: SharePlatform,
+) -> anyhow::Result<()> {
+    if let Some(uid) = user_id && shadow_ban::is_banned(sql_pool, uid, FEATURE_PLAYS).await? {
+        return Ok(());
+    }
+    SongShareDao::increment(sql_pool, song_id, Utc::now().date_naive(), platform.as_str()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_str() {
+        for platform in [SharePlatform::Link, SharePlatform::Qq, SharePlatform::Wechat, SharePlatform::Other] {
+            let json = serde_json::to_string(&platform).unwrap();
+            assert_eq!(format!("\"{}\"", platform.as_str()), json);
+        }
+    }
+}
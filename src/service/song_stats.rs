@@ -0,0 +1,106 @@
+//! Verification of the play and like counts denormalized in `songs`.
+//!
+//! The counts are read from `song_plays` and `song_likes`, but the columns are still used for sorting the
+//! fallback search, so they are recounted weekly and repaired if they drifted. A song can also be recounted
+//! by the admins at any time.
+
+use crate::db::song::{ISongDao, SongDao, SongStatsDrift};
+use crate::service::song_play;
+use crate::util::redlock::RedLock;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use metrics::{counter, gauge};
+use redis::aio::ConnectionManager;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+pub const VERIFICATION_JOB: Job = Job {
+    name: "verify_song_stats",
+    // Weekly at Monday 04:00
+    schedule: "0 4 * * 1",
+    max_jitter: Duration::from_secs(300),
+};
+
+/// The sum of the absolute drifts of the repaired songs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DriftSummary {
+    pub songs: usize,
+    pub play_count_drift: i64,
+    pub like_count_drift: i64,
+}
+
+pub fn summarize(drifts: &[SongStatsDrift]) -> DriftSummary {
+    DriftSummary {
+        songs: drifts.len(),
+        play_count_drift: drifts.iter().map(|x| (x.play_count - x.old_play_count).abs()).sum(),
+        like_count_drift: drifts.iter().map(|x| (x.like_count - x.old_like_count).abs()).sum(),
+    }
+}
+
+/// Recount one song, or all songs if `song_id` is `None`, and record the drift.
+///
+/// The pending plays are flushed first and no flush runs meanwhile, since they are already in `song_plays`.
+pub async fn recount(
+    redis: &mut ConnectionManager,
+    red_lock: &RedLock,
+    pool: &PgPool,
+    song_id: Option<i64>,
+) -> anyhow::Result<Vec<SongStatsDrift>> {
+    let drifts = song_play::with_pending_plays_flushed(redis, red_lock, pool, || async {
+        Ok(SongDao::recount_stats(pool, song_id).await?)
+    }).await?;
+    let summary = summarize(&drifts);
+    let scope = if song_id.is_some() { "song" } else { "all" };
+    counter!("song_stats_repaired_count", "scope" => scope).increment(summary.songs as u64);
+    if song_id.is_none() {
+        gauge!("song_stats_drifted_songs").set(summary.songs as f64);
+        gauge!("song_stats_drift", "counter" => "play").set(summary.play_count_drift as f64);
+        gauge!("song_stats_drift", "counter" => "like").set(summary.like_count_drift as f64);
+    }
+    if summary.songs > 0 {
+        warn!("Repaired the stats of {} songs, drift: {:?}", summary.songs, summary);
+    }
+    Ok(drifts)
+}
+
+/// Verify the counts of all songs weekly until cancelled
+pub async fn run_verification(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &VERIFICATION_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            let mut redis = state.redis_conn.clone();
+            let red_lock = state.red_lock.clone();
+            async move {
+                let drifts = recount(&mut redis, &red_lock, &pool, None).await?;
+                info!("Verified the song stats, {} songs drifted", drifts.len());
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let drift = |old_play_count, play_count, old_like_count, like_count| SongStatsDrift {
+            song_id: 1,
+            old_play_count,
+            old_like_count,
+            play_count,
+            like_count,
+        };
+        let summary = summarize(&[drift(10, 15, 3, 3), drift(20, 0, 5, 4)]);
+        assert_eq!(DriftSummary { songs: 2, play_count_drift: 25, like_count_drift: 1 }, summary);
+        assert_eq!(DriftSummary::default(), summarize(&[]));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::config::Config;
+use crate::db::storage_orphan_object::{IStorageOrphanObjectDao, StorageOrphanObject, StorageOrphanObjectDao};
+use crate::db::user_storage_object::{IUserStorageObjectDao, UserStorageObjectDao};
+use crate::file_hosting::FileHost;
+use chrono::{DateTime, Utc};
+use metrics::counter;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Optional `storage_webhook` section of the config file, the webhook is disabled if it's absent.
+///
+/// ```yaml
+/// storage_webhook:
+///   secret: 12345678
+///   tolerance_secs: 300
+/// ```
+///
+/// The sender signs every request with the headers:
+/// - `X-Storage-Timestamp`: unix seconds
+/// - `X-Storage-Signature`: hex of `HMAC-SHA256(secret, "{timestamp}.{body}")`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageWebhookCfg {
+    pub secret: String,
+    #[serde(default = "default_tolerance_secs")]
+    pub tolerance_secs: i64,
+}
+
+fn default_tolerance_secs() -> i64 { 300 }
+
+impl StorageWebhookCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("storage_webhook")?.is_some() {
+            Ok(Some(config.get_and_parse("storage_webhook")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn signature(&self, timestamp: i64, body: &[u8]) -> anyhow::Result<String> {
+        let pkey = PKey::hmac(self.secret.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+        signer.update(format!("{timestamp}.").as_bytes())?;
+        signer.update(body)?;
+        Ok(hex::encode(signer.sign_to_vec()?))
+    }
+
+    /// Check the signature and reject requests outside the tolerance to prevent replays
+    pub fn verify(&self, timestamp: i64, signature: &str, body: &[u8], now: i64) -> bool {
+        if (now - timestamp).abs() > self.tolerance_secs {
+            return false;
+        }
+        match self.signature(timestamp, body) {
+            Ok(expected) => expected.len() == signature.len()
+                && openssl::memcmp::eq(expected.as_bytes(), signature.to_ascii_lowercase().as_bytes()),
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StorageEventKind {
+    Created,
+    Deleted,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageEvent {
+    pub kind: StorageEventKind,
+    pub key: String,
+    pub size: Option<i64>,
+    pub event_time: DateTime<Utc>,
+}
+
+/// Accepts both the R2 event notification messages forwarded as a JSON array and the S3 notification format
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EventBatch {
+    S3 {
+        #[serde(rename = "Records")]
+        records: Vec<S3Record>,
+    },
+    R2(Vec<R2Message>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct R2Message {
+    action: String,
+    object: EventObject,
+    event_time: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct S3Record {
+    event_name: String,
+    event_time: DateTime<Utc>,
+    s3: S3Entity,
+}
+
+#[derive(Deserialize)]
+struct S3Entity {
+    object: EventObject,
+}
+
+#[derive(Deserialize)]
+struct EventObject {
+    key: String,
+    size: Option<i64>,
+}
+
+/// Parse the events in the body, the actions other than creations and deletions are skipped
+pub fn parse_events(body: &[u8]) -> anyhow::Result<Vec<StorageEvent>> {
+    let batch: EventBatch = serde_json::from_slice(body)?;
+    let events = match batch {
+        EventBatch::R2(messages) => messages.into_iter().filter_map(|x| {
+            let kind = match x.action.as_str() {
+                "PutObject" | "CopyObject" | "CompleteMultipartUpload" => StorageEventKind::Created,
+                "DeleteObject" | "LifecycleDeletion" => StorageEventKind::Deleted,
+                _ => return None,
+            };
+            Some(StorageEvent { kind, key: x.object.key, size: x.object.size, event_time: x.event_time })
+        }).collect(),
+        EventBatch::S3 { records } => records.into_iter().filter_map(|x| {
+            let kind = if x.event_name.starts_with("ObjectCreated:") {
+                StorageEventKind::Created
+            } else if x.event_name.starts_with("ObjectRemoved:") {
+                StorageEventKind::Deleted
+            } else {
+                return None;
+            };
+            // S3 keys are form-urlencoded
+            let key = urlencoding::decode(&x.s3.object.key.replace('+', " ")).ok()?.into_owned();
+            Some(StorageEvent { kind, key, size: x.s3.object.size, event_time: x.event_time })
+        }).collect(),
+    };
+    Ok(events)
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReconcileSummary {
+    /// Created objects not referenced by any record
+    pub flagged: usize,
+    /// Deleted objects removed from the flagged list
+    pub cleared: usize,
+    /// Deleted objects that are still referenced
+    pub dangling: usize,
+    /// Deleted objects released from the storage usage of the uploaders
+    pub released: usize,
+}
+
+/// Compare the events with the objects referenced in the database.
+///
+/// Unknown objects are flagged in `storage_orphan_objects` for the GC, and deleted objects are unflagged and
+/// released from the storage usage.
+pub async fn reconcile(
+    file_host: &dyn FileHost,
+    pool: &PgPool,
+    events: &[StorageEvent],
+) -> anyhow::Result<ReconcileSummary> {
+    let mut summary = ReconcileSummary::default();
+    let urls = events.iter().map(|x| file_host.public_url(&x.key)).collect::<Vec<_>>();
+    let referenced_urls = StorageOrphanObjectDao::referenced_urls(pool, &urls).await?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    for (event, url) in events.iter().zip(&urls) {
+        let kind = match event.kind {
+            StorageEventKind::Created => "created",
+            StorageEventKind::Deleted => "deleted",
+        };
+        counter!("storage_event_count", "kind" => kind).increment(1);
+
+        let referenced = referenced_urls.contains(url);
+        match event.kind {
+            StorageEventKind::Created => {
+                if !referenced {
+                    StorageOrphanObjectDao::upsert(pool, &StorageOrphanObject {
+                        id: 0,
+                        object_key: event.key.clone(),
+                        size: event.size,
+                        event_time: event.event_time,
+                        create_time: Utc::now(),
+                    }).await?;
+                    summary.flagged += 1;
+                }
+            }
+            StorageEventKind::Deleted => {
+                if StorageOrphanObjectDao::delete_by_key(pool, &event.key).await? {
+                    summary.cleared += 1;
+                }
+                if UserStorageObjectDao::delete_by_key(pool, &event.key).await? > 0 {
+                    summary.released += 1;
+                }
+                if referenced {
+                    warn!("Object {} is deleted but still referenced", event.key);
+                    counter!("storage_dangling_reference_count").increment(1);
+                    summary.dangling += 1;
+                }
+            }
+        }
+    }
+    info!("Reconciled {} storage events: {:?}", events.len(), summary);
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_events() {
+        let r2 = br#"[
+            {"account":"a","action":"PutObject","bucket":"b","object":{"key":"songs/a.mp3","size":3,"eTag":"e"},"eventTime":"2026-04-18T09:00:00Z"},
+            {"account":"a","action":"DeleteObject","bucket":"b","object":{"key":"songs/b.mp3"},"eventTime":"2026-04-18T09:00:01Z"},
+            {"account":"a","action":"AbortMultipartUpload","bucket":"b","object":{"key":"songs/c.mp3"},"eventTime":"2026-04-18T09:00:02Z"}
+        ]"#;
+        let events = parse_events(r2).unwrap();
+        assert_eq!(2, events.len());
+        assert_eq!(StorageEventKind::Created, events[0].kind);
+        assert_eq!(Some(3), events[0].size);
+        assert_eq!(StorageEventKind::Deleted, events[1].kind);
+
+        let s3 = br#"{"Records":[
+            {"eventName":"ObjectCreated:Put","eventTime":"2026-04-18T09:00:00Z","s3":{"object":{"key":"images/cover/a+b%2B.webp","size":5}}}
+        ]}"#;
+        let events = parse_events(s3).unwrap();
+        assert_eq!("images/cover/a b+.webp", events[0].key);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let cfg = StorageWebhookCfg { secret: "secret".to_string(), tolerance_secs: 300 };
+        let body = b"[]";
+        let sig = cfg.signature(1000, body).unwrap();
+        assert!(cfg.verify(1000, &sig, body, 1100));
+        assert!(cfg.verify(1000, &sig.to_uppercase(), body, 1100));
+        assert!(!cfg.verify(1000, &sig, b"[ ]", 1100));
+        assert!(!cfg.verify(1001, &sig, body, 1100));
+        assert!(!cfg.verify(1000, &sig, body, 1400));
+    }
+}
@@ -0,0 +1,177 @@
+//! The storage quota of the uploads.
+//!
+//! Every uploaded audio and image is accounted to the uploader in `user_storage_objects`, and removed when the
+//! object is deleted from the bucket (see [crate::service::storage_events::reconcile]). The uploads are rejected
+//! once the usage would exceed the quota of the user's tier. The check is not atomic with the upload, so the
+//! concurrent uploads may exceed the quota by a few files.
+
+use crate::config::Config;
+use crate::db::user_storage_object::{IUserStorageObjectDao, StorageUsage, UserStorageObject, UserStorageObjectDao};
+use crate::file_hosting::UploadResult;
+use crate::web::result::{CommonError, WebError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Optional `storage_quota` section of the config file, the storage is unlimited if it's absent.
+///
+/// ```yaml
+/// storage_quota:
+///   default_tier: basic
+///   tiers:
+///     - name: basic
+///       quota_bytes: 1073741824
+///     - name: contributor
+///       quota_bytes: 10737418240
+///   # uid: tier
+///   user_tiers:
+///     100001: contributor
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageQuotaCfg {
+    pub default_tier: String,
+    pub tiers: Vec<QuotaTier>,
+    #[serde(default)]
+    pub user_tiers: HashMap<i64, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaTier {
+    pub name: String,
+    pub quota_bytes: i64,
+}
+
+impl StorageQuotaCfg {
+    /// Load the `storage_quota` config, the default tier must exist since a missing tier would mean unlimited
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("storage_quota")?.is_some() {
+            let cfg: Self = config.get_and_parse("storage_quota")?;
+            cfg.validate()?;
+            Ok(Some(cfg))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.tier(&self.default_tier).is_none() {
+            anyhow::bail!("The default tier `{}` of `storage_quota` is not in the tiers", self.default_tier);
+        }
+        for (uid, name) in &self.user_tiers {
+            if self.tier(name).is_none() {
+                warn!("The tier `{name}` of user {uid} in `storage_quota` is not in the tiers, fallback to the default tier");
+            }
+        }
+        Ok(())
+    }
+
+    fn tier(&self, name: &str) -> Option<&QuotaTier> {
+        self.tiers.iter().find(|x| x.name == name)
+    }
+
+    /// The tier of the user, or the default tier if the configured one doesn't exist.
+    /// `None` only if the default tier doesn't exist either, which is rejected by [Self::load].
+    pub fn tier_of(&self, uid: i64) -> Option<&QuotaTier> {
+        self.user_tiers.get(&uid)
+            .and_then(|name| self.tier(name))
+            .or_else(|| self.tier(&self.default_tier))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuotaStatus {
+    pub usage: StorageUsage,
+    pub tier: Option<String>,
+    /// `None` if unlimited
+    pub quota_bytes: Option<i64>,
+}
+
+impl QuotaStatus {
+    pub fn exceeds(&self, size: i64) -> bool {
+        self.quota_bytes.is_some_and(|quota| self.usage.total_bytes() + size > quota)
+    }
+}
+
+pub async fn get_status(config: &Config, pool: &PgPool, uid: i64) -> anyhow::Result<QuotaStatus> {
+    let usage = UserStorageObjectDao::get_usage_by_user_id(pool, uid).await?;
+    let cfg = StorageQuotaCfg::load(config)?;
+    let tier = cfg.as_ref().and_then(|x| x.tier_of(uid));
+    Ok(QuotaStatus {
+        usage,
+        tier: tier.map(|x| x.name.clone()),
+        quota_bytes: tier.map(|x| x.quota_bytes),
+    })
+}
+
+/// Reject the upload of `size` bytes with `storage_quota_exceeded` if it doesn't fit in the quota,
+/// the detail carries the usage for the clients to display.
+pub async fn ensure_quota(config: &Config, pool: &PgPool, uid: i64, size: usize) -> Result<(), WebError<CommonError>> {
+    let status = get_status(config, pool, uid).await?;
+    if status.exceeds(size as i64) {
+        return Err(WebError::common_with_detail(
+            "storage_quota_exceeded",
+            "The storage quota is exceeded",
+            json!({
+                "used_bytes": status.usage.total_bytes(),
+                "audio_bytes": status.usage.audio_bytes,
+                "image_bytes": status.usage.image_bytes,
+                "quota_bytes": status.quota_bytes,
+                "tier": status.tier,
+                "requested_bytes": size,
+            }),
+        ));
+    }
+    Ok(())
+}
+
+/// Account the uploaded object to the user, see [crate::db::user_storage_object] for the kinds
+pub async fn track(pool: &PgPool, uid: i64, kind: &str, result: &UploadResult) -> anyhow::Result<()> {
+    UserStorageObjectDao::insert_if_absent(pool, &UserStorageObject {
+        id: 0,
+        user_id: uid,
+        object_key: result.key.clone(),
+        kind: kind.to_string(),
+        size: result.size as i64,
+        create_time: Utc::now(),
+    }).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tier_and_exceeds() {
+        let cfg: StorageQuotaCfg = serde_yaml::from_str(r#"
+            default_tier: basic
+            tiers:
+              - name: basic
+                quota_bytes: 100
+              - name: contributor
+                quota_bytes: 1000
+            user_tiers:
+              2: contributor
+              3: missing
+        "#).unwrap();
+        assert_eq!("basic", cfg.tier_of(1).unwrap().name);
+        assert_eq!("contributor", cfg.tier_of(2).unwrap().name);
+        // The missing tier is not unlimited
+        assert_eq!("basic", cfg.tier_of(3).unwrap().name);
+        assert!(cfg.validate().is_ok());
+        let missing_default = StorageQuotaCfg { default_tier: "missing".to_string(), ..cfg };
+        assert!(missing_default.validate().is_err());
+
+        let status = QuotaStatus {
+            usage: StorageUsage { audio_bytes: 60, image_bytes: 30 },
+            tier: Some("basic".to_string()),
+            quota_bytes: Some(100),
+        };
+        assert!(!status.exceeds(10));
+        assert!(status.exceeds(11));
+        assert!(!QuotaStatus { quota_bytes: None, ..status }.exceeds(i64::MAX / 2));
+    }
+}
@@ -0,0 +1,160 @@
+//! The links to support the creators on the donation platforms.
+//!
+//! Only the whitelisted platforms are accepted, the same as the external links of the songs by
+//! [crate::util::validate_platforms], and they're shown in the public profile and the uploader of the song detail.
+
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::user_support_link::{IUserSupportLinkDao, UserSupportLink, UserSupportLinkDao};
+use crate::service::{song, user};
+use crate::web::routes;
+use crate::web::state::AppState;
+use chrono::Utc;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::warn;
+use url::Url;
+
+pub const MAX_LINKS: usize = 5;
+pub const URL_MAX_CHARS: usize = 500;
+
+/// The supported platforms and their hosts, the subdomains of the hosts are accepted as well
+const PLATFORM_HOSTS: [(&str, &[&str]); 5] = [
+    ("afdian", &["afdian.com", "afdian.net", "ifdian.net"]),
+    ("patreon", &["patreon.com"]),
+    ("kofi", &["ko-fi.com"]),
+    ("paypal", &["paypal.me", "paypal.com"]),
+    ("github_sponsors", &["github.com"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SupportLink {
+    /// `afdian`, `patreon`, `kofi`, `paypal` or `github_sponsors`
+    pub platform: String,
+    pub url: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SupportLinkError {
+    #[error("At most {MAX_LINKS} support links are allowed")]
+    TooManyLinks,
+    #[error("Unsupported platform {0}")]
+    UnsupportedPlatform(String),
+    #[error("Invalid url for {0}")]
+    InvalidUrl(String),
+    #[error("Duplicate platform {0}")]
+    DuplicatePlatform(String),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Check the url is an HTTPS link to a host of the platform
+pub fn validate(link: &SupportLink) -> Result<(), SupportLinkError> {
+    let Some((_, hosts)) = PLATFORM_HOSTS.iter().find(|(platform, _)| *platform == link.platform) else {
+        return Err(SupportLinkError::UnsupportedPlatform(link.platform.clone()));
+    };
+    let invalid_url = || SupportLinkError::InvalidUrl(link.platform.clone());
+    if link.url.chars().count() > URL_MAX_CHARS {
+        return Err(invalid_url());
+    }
+    let url = Url::parse(&link.url).map_err(|_| invalid_url())?;
+    if url.scheme() != "https" {
+        return Err(invalid_url());
+    }
+    let host = url.host_str().ok_or_else(invalid_url)?;
+    let matched = hosts.iter().any(|x| host == *x || host.ends_with(&format!(".{x}")));
+    if !matched {
+        return Err(invalid_url());
+    }
+    // Any page is on github.com, only the sponsors page is a support link
+    if link.platform == "github_sponsors" && !is_github_sponsors_path(url.path()) {
+        return Err(invalid_url());
+    }
+    Ok(())
+}
+
+/// `/sponsors/<login>`, optionally ending with a slash
+fn is_github_sponsors_path(path: &str) -> bool {
+    let path = path.strip_suffix('/').unwrap_or(path);
+    let Some(login) = path.strip_prefix("/sponsors/") else {
+        return false;
+    };
+    !login.is_empty() && login.chars().all(|x| x.is_ascii_alphanumeric() || x == '-')
+}
+
+/// The support links of the users, in the order set by them
+pub async fn list_by_user_ids(pool: &PgPool, user_ids: &[i64]) -> sqlx::Result<HashMap<i64, Vec<SupportLink>>> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let links = UserSupportLinkDao::list_by_user_ids(pool, user_ids).await?
+        .into_iter()
+        .map(|x| (x.user_id, SupportLink { platform: x.platform, url: x.url }))
+        .into_group_map();
+    Ok(links)
+}
+
+/// Replace all the support links of the user
+pub async fn set_links(state: &AppState, uid: i64, links: &[SupportLink]) -> Result<(), SupportLinkError> {
+    if links.len() > MAX_LINKS {
+        return Err(SupportLinkError::TooManyLinks);
+    }
+    for (i, link) in links.iter().enumerate() {
+        validate(link)?;
+        if links[..i].iter().any(|x| x.platform == link.platform) {
+            return Err(SupportLinkError::DuplicatePlatform(link.platform.clone()));
+        }
+    }
+
+    let now = Utc::now();
+    let mut tx = state.sql_pool.begin().await?;
+    UserSupportLinkDao::delete_by_user(&mut *tx, uid).await?;
+    for (position, link) in links.iter().enumerate() {
+        UserSupportLinkDao::insert(&mut *tx, &UserSupportLink {
+            user_id: uid,
+            platform: link.platform.clone(),
+            url: link.url.clone(),
+            position: position as i32,
+            create_time: now,
+        }).await?;
+    }
+    tx.commit().await?;
+
+    // The links are cached in the profile and the details of the songs, they catch up when the caches expire
+    // if the eviction fails
+    if let Err(e) = evict_caches(state, uid).await {
+        warn!("Failed to evict the caches after setting the support links of user {}: {:?}", uid, e);
+    }
+    Ok(())
+}
+
+async fn evict_caches(state: &AppState, uid: i64) -> anyhow::Result<()> {
+    user::evict_profile_cache(state.redis_conn.clone(), uid).await?;
+    let songs = SongDao::list_by_user(&state.sql_pool, uid).await?;
+    song::evict_detail_cache(state.redis_conn.clone(), &songs).await?;
+    routes::song::evict_page_by_user_cache(state.redis_conn.clone(), uid).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let link = |platform: &str, url: &str| SupportLink { platform: platform.to_string(), url: url.to_string() };
+        assert!(validate(&link("afdian", "https://afdian.com/a/hachimi")).is_ok());
+        assert!(validate(&link("patreon", "https://www.patreon.com/hachimi")).is_ok());
+        assert!(validate(&link("github_sponsors", "https://github.com/sponsors/hachimi")).is_ok());
+        assert!(validate(&link("github_sponsors", "https://github.com/sponsors/hachimi-world/")).is_ok());
+        assert!(matches!(validate(&link("github_sponsors", "https://github.com/hachimi")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("github_sponsors", "https://github.com/sponsors/")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("github_sponsors", "https://github.com/sponsors/a/b")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("afdian", "http://afdian.com/a/hachimi")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("afdian", "https://notafdian.com/a/hachimi")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("patreon", "not a url")), Err(SupportLinkError::InvalidUrl(_))));
+        assert!(matches!(validate(&link("unknown", "https://afdian.com")), Err(SupportLinkError::UnsupportedPlatform(_))));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::db::error::DbError;
+use crate::db::song_tag::{ISongTagDao, SongTag, SongTagAlias, SongTagDao};
+use crate::db::CrudDao;
+use crate::search;
+use crate::web::state::AppState;
+use chrono::Utc;
+use itertools::Itertools;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use tracing::warn;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AliasError {
+    #[error("Tag {0} not found")]
+    TagNotFound(i64),
+    #[error("The alias can't be the name of the tag itself")]
+    SameAsName,
+    #[error("The alias {0} already exists")]
+    AliasExists(String),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+/// Replace the tags named by an alias with their canonical tags, the duplicates are removed keeping the order
+pub async fn resolve_tags(pool: &PgPool, tags: Vec<SongTag>) -> sqlx::Result<Vec<SongTag>> {
+    let names = tags.iter().map(|x| x.name.clone()).collect_vec();
+    let aliases: HashMap<String, i64> = SongTagDao::list_aliases_by_names(pool, &names).await?
+        .into_iter()
+        .map(|x| (x.alias, x.tag_id))
+        .collect();
+    if aliases.is_empty() {
+        return Ok(tags);
+    }
+    let canonical_ids = aliases.values().copied().unique().collect_vec();
+    let canonical_tags: HashMap<i64, SongTag> = SongTagDao::list_by_ids(pool, &canonical_ids).await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+    let resolved = tags.into_iter()
+        .map(|x| aliases.get(&x.name)
+            .and_then(|id| canonical_tags.get(id).cloned())
+            .unwrap_or(x))
+        .unique_by(|x| x.id)
+        .collect_vec();
+    Ok(resolved)
+}
+
+/// Add an alias of the tag. The alias may be the name of another tag, which is resolved to this tag since then.
+pub async fn add_alias(state: &AppState, tag_id: i64, alias: &str) -> Result<i64, AliasError> {
+    let Some(tag) = SongTagDao::get_by_id(&state.sql_pool, tag_id).await? else {
+        return Err(AliasError::TagNotFound(tag_id));
+    };
+    if tag.name == alias {
+        return Err(AliasError::SameAsName);
+    }
+    let result = SongTagDao::insert_alias(&state.sql_pool, &SongTagAlias {
+        id: 0,
+        tag_id,
+        alias: alias.to_string(),
+        create_time: Utc::now(),
+    }).await.map_err(DbError::from);
+    let id = match result {
+        Ok(id) => id,
+        Err(e) if e.is_unique_violation() => return Err(AliasError::AliasExists(alias.to_string())),
+        Err(e) => return Err(e.into()),
+    };
+    sync_synonyms(state).await;
+    Ok(id)
+}
+
+/// Returns whether the alias existed
+pub async fn delete_alias(state: &AppState, id: i64) -> sqlx::Result<bool> {
+    let deleted = SongTagDao::delete_alias(&state.sql_pool, id).await?;
+    if deleted {
+        sync_synonyms(state).await;
+    }
+    Ok(deleted)
+}
+
+/// The aliases are saved already, the synonyms are synced again by the next full indexing if it fails
+async fn sync_synonyms(state: &AppState) {
+    let index = state.meilisearch.index("songs");
+    if let Err(e) = search::song::sync_tag_synonyms(&index, &state.sql_pool).await {
+        warn!("Failed to sync the tag synonyms: {:?}", e);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::db::song_tag::{ISongTagDao, SongTag, SongTagDao};
+use crate::db::user_shadow_ban;
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use sqlx::PgPool;
+use std::collections::HashMap;
 
 #[derive(Debug, Clone)]
 pub struct RecommendTagQuery {
@@ -135,7 +137,8 @@ pub async fn get_hot_tags(
         r#"
         WITH latest_plays AS (
             SELECT id, song_id
-            FROM song_plays
+            FROM song_plays sp
+            WHERE NOT EXISTS (SELECT 1 FROM user_shadow_bans b WHERE b.user_id = sp.user_id AND $3 = ANY(b.features))
             ORDER BY create_time DESC
             LIMIT $1
         )
@@ -145,7 +148,8 @@ pub async fn get_hot_tags(
         ORDER BY cnt DESC LIMIT $2;
         "#,
         history_limit,
-        tag_limit
+        tag_limit,
+        user_shadow_ban::FEATURE_PLAYS
     ).fetch_all(pool).await?;
 
     let tag_ids = play_rows.iter().map(|r| r.tag_id).collect_vec();
@@ -155,4 +159,107 @@ pub async fn get_hot_tags(
         .filter_map(|r| tag_map.get(&r.tag_id).cloned().map(|t| (t, r.cnt.unwrap_or(0))))
         .collect_vec();
     Ok(result)
-}
\ No newline at end of file
+}
+
+/// Random songs with any of the tags, the candidates of the personalized recommendations
+pub async fn list_random_songs_by_tags(
+    pool: &PgPool,
+    tag_ids: &[i64],
+    limit: i64,
+) -> anyhow::Result<Vec<i64>> {
+    if tag_ids.is_empty() {
+        return Ok(vec![]);
+    }
+    let song_ids = sqlx::query_scalar!(
+        r#"
+        SELECT song_id FROM (
+            SELECT DISTINCT song_id FROM song_tag_refs WHERE tag_id = ANY($1)
+        ) t
+        ORDER BY random()
+        LIMIT $2
+        "#,
+        tag_ids,
+        limit,
+    ).fetch_all(pool).await?;
+    Ok(song_ids)
+}
+
+/// The weight of the tag overlap in [SongScorer::score]
+const TAG_WEIGHT: f64 = 0.6;
+/// The weight of the play count
+const POPULARITY_WEIGHT: f64 = 0.25;
+/// The weight of the release time
+const RECENCY_WEIGHT: f64 = 0.15;
+/// The recency score halves every this many days since the release
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Score the candidate songs by the overlap with the top tags of a user, mixed with the popularity and the recency.
+///
+/// Each part is normalized to `0..=1`, so the score is `0..=1` as well.
+#[derive(Debug, Clone)]
+pub struct SongScorer {
+    /// tag_id -> weight relative to the top tag, in `0..=1`
+    tag_weights: HashMap<i64, f64>,
+    max_play_count: i64,
+    now: DateTime<Utc>,
+}
+
+impl SongScorer {
+    /// `tag_scores` are the scores of [recommend_tags], the favorite tags of the user count as the top tag
+    pub fn new(tag_scores: &[(i64, i64)], favorite_tag_ids: &[i64], max_play_count: i64, now: DateTime<Utc>) -> Self {
+        let max_score = tag_scores.iter().map(|x| x.1).max().unwrap_or(0).max(1) as f64;
+        let mut tag_weights = tag_scores.iter()
+            .map(|(tag_id, score)| (*tag_id, *score as f64 / max_score))
+            .collect::<HashMap<_, _>>();
+        for tag_id in favorite_tag_ids {
+            tag_weights.insert(*tag_id, 1.0);
+        }
+        SongScorer { tag_weights, max_play_count, now }
+    }
+
+    /// No tags to personalize with, e.g. a user who never played or liked any songs
+    pub fn is_cold_start(&self) -> bool {
+        self.tag_weights.is_empty()
+    }
+
+    pub fn score(&self, tag_ids: &[i64], play_count: i64, release_time: DateTime<Utc>) -> f64 {
+        let tag = tag_ids.iter()
+            .filter_map(|x| self.tag_weights.get(x))
+            .sum::<f64>()
+            .min(1.0);
+        let popularity = if self.max_play_count > 0 {
+            (play_count.max(0) as f64).ln_1p() / (self.max_play_count as f64).ln_1p()
+        } else {
+            0.0
+        };
+        let age_days = (self.now - release_time).num_hours().max(0) as f64 / 24.0;
+        let recency = 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        TAG_WEIGHT * tag + POPULARITY_WEIGHT * popularity.min(1.0) + RECENCY_WEIGHT * recency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_song_scorer() {
+        let now = Utc::now();
+        let scorer = SongScorer::new(&[(1, 10), (2, 5)], &[3], 1000, now);
+        assert!(!scorer.is_cold_start());
+        assert!(SongScorer::new(&[], &[], 1000, now).is_cold_start());
+
+        // The overlap with the top tags weighs the most
+        let top = scorer.score(&[1], 0, now - chrono::Days::new(365));
+        let unrelated = scorer.score(&[4], 1000, now);
+        assert!(top > unrelated);
+        assert!(scorer.score(&[1], 0, now) > scorer.score(&[2], 0, now));
+        // A favorite tag counts as the top tag
+        assert_eq!(scorer.score(&[1], 0, now), scorer.score(&[3], 0, now));
+        // The popularity and the recency break the ties
+        assert!(scorer.score(&[2], 100, now) > scorer.score(&[2], 10, now));
+        assert!(scorer.score(&[2], 10, now) > scorer.score(&[2], 10, now - chrono::Days::new(30)));
+        let max = scorer.score(&[1, 2, 3], 1000, now);
+        assert!((max - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::config::Config;
+use crate::service::mailer::transport::Mail;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tracing::warn;
+
+/// The provider recorded for the captured emails
+pub const PROVIDER: &str = "test_mode";
+/// The oldest emails are dropped beyond this
+const MAX_CAPTURED_EMAILS: usize = 1000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CAPTURED_EMAILS: LazyLock<Mutex<VecDeque<CapturedEmail>>> = LazyLock::new(|| Mutex::new(VecDeque::new()));
+
+/// Optional `test_mode` section of the config file, only for the integration tests.
+///
+/// When enabled, the outgoing emails are captured in memory instead of sent and can be read from
+/// `/test/emails`, the generated captchas are passed already, the routes are not rate limited, and the weekly
+/// selection can be published by `/test/weekly_selection/publish`.
+/// It's refused unless the server is built with the `test-mode` feature.
+///
+/// ```yaml
+/// test_mode:
+///   enabled: true
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestModeCfg {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CapturedEmail {
+    pub to: String,
+    pub subject: String,
+    pub plain: String,
+    pub create_time: DateTime<Utc>,
+}
+
+/// Enable the test mode if configured, must be called before building the router
+pub fn init(config: &Config) -> anyhow::Result<()> {
+    let cfg: TestModeCfg = if config.get("test_mode")?.is_some() {
+        config.get_and_parse("test_mode")?
+    } else {
+        TestModeCfg::default()
+    };
+    if !cfg.enabled {
+        return Ok(());
+    }
+    if !cfg!(feature = "test-mode") {
+        anyhow::bail!("test_mode is only available in the builds with the `test-mode` feature");
+    }
+    warn!("Test mode is enabled, emails are captured instead of sent and captchas are passed");
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    cfg!(feature = "test-mode") && ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn capture(mail: &Mail) {
+    let mut emails = CAPTURED_EMAILS.lock().unwrap();
+    if emails.len() >= MAX_CAPTURED_EMAILS {
+        emails.pop_front();
+    }
+    emails.push_back(CapturedEmail {
+        to: mail.to.clone(),
+        subject: mail.subject.clone(),
+        plain: mail.plain.clone(),
+        create_time: Utc::now(),
+    });
+}
+
+/// The captured emails to the address, latest first
+pub fn list_captured(to: &str) -> Vec<CapturedEmail> {
+    CAPTURED_EMAILS.lock().unwrap()
+        .iter()
+        .rev()
+        .filter(|x| x.to.eq_ignore_ascii_case(to))
+        .cloned()
+        .collect()
+}
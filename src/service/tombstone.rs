@@ -0,0 +1,78 @@
+//! The deleted comments and posts are kept as tombstones, shown as [DELETED_PLACEHOLDER] so the reply chains
+//! stay intact, and purged after the retention by [PURGE_JOB].
+
+use crate::config::Config;
+use crate::db::post::{IPostDao, PostDao};
+use crate::db::song_comment::{ISongCommentDao, SongCommentDao};
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Shown in place of the content of a deleted comment or post
+pub const DELETED_PLACEHOLDER: &str = "[deleted]";
+
+/// Optional `tombstone` section of the config file
+///
+/// ```yaml
+/// tombstone:
+///   retention_days: 30
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TombstoneCfg {
+    /// How long the tombstones are kept before purged
+    #[serde(default = "default_retention_days")]
+    pub retention_days: i64,
+}
+
+fn default_retention_days() -> i64 { 30 }
+
+impl Default for TombstoneCfg {
+    fn default() -> Self {
+        TombstoneCfg { retention_days: default_retention_days() }
+    }
+}
+
+impl TombstoneCfg {
+    /// Load the `tombstone` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("tombstone")?.is_some() {
+            config.get_and_parse("tombstone")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+pub const PURGE_JOB: Job = Job {
+    name: "purge_tombstones",
+    schedule: "30 4 * * *",
+    max_jitter: Duration::from_secs(300),
+};
+
+/// Purge the expired tombstones daily until cancelled
+pub async fn run_purge(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = TombstoneCfg::load(&state.config)?;
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &PURGE_JOB,
+        cancel_token,
+        move || {
+            let pool = state.sql_pool.clone();
+            let before = Utc::now() - chrono::Duration::days(cfg.retention_days);
+            async move {
+                let comments = SongCommentDao::purge_deleted(&pool, before).await?;
+                let posts = PostDao::purge_deleted(&pool, before).await?;
+                if comments > 0 || posts > 0 {
+                    info!("Purged {comments} deleted comments and {posts} deleted posts");
+                }
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
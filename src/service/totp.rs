@@ -0,0 +1,345 @@
+//! The TOTP (RFC 6238) second factor for logging in by the password or the magic link.
+//!
+//! The enrolled secret is only enforced after a code of it is confirmed, so a user leaving in the middle of the
+//! enrollment won't be locked out. The confirmation also issues the recovery codes, each of them can be used once
+//! in place of a TOTP code. A TOTP code is accepted once, and the wrong codes are limited per user.
+
+use crate::db::user_account_audit_log::{self, UserAccountAuditLog, UserAccountAuditLogDao};
+use crate::db::user_totp::{IUserTotpDao, UserTotpDao, UserTotpSecret};
+use crate::db::CrudDao;
+use anyhow::bail;
+use chrono::Utc;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rand::Rng;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+
+pub const ISSUER: &str = "Hachimi World";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const STEP_SECS: i64 = 30;
+const DIGITS: usize = 6;
+/// The codes of the adjacent steps are accepted as well, for the clock drift of the phones
+const WINDOW_STEPS: i64 = 1;
+const SECRET_BYTES: usize = 20;
+/// At most this many wrong codes are allowed in [FAILURE_WINDOW_SECS]
+const MAX_FAILURES: i64 = 5;
+const FAILURE_WINDOW_SECS: i64 = 15 * 60;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+/// Without the look-alike characters, as the recovery codes are usually written down
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TotpError {
+    #[error("The 2FA code is required")]
+    Required,
+    #[error("The 2FA is already enabled")]
+    AlreadyEnabled,
+    #[error("The 2FA is not enabled")]
+    NotEnabled,
+    #[error("No authenticator is being enrolled, please enroll again")]
+    NotEnrolled,
+    #[error("The 2FA code is incorrect or used")]
+    InvalidCode,
+    #[error("Too many incorrect 2FA codes, please try again later")]
+    TooManyFailures,
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Clone)]
+pub struct Enrollment {
+    /// Base32, for entering into the authenticator manually
+    pub secret: String,
+    /// The `otpauth://` URI displayed as the QR code
+    pub provisioning_uri: String,
+}
+
+/// Whether the user has to enter a 2FA code when logging in
+pub async fn is_enabled(pool: &PgPool, uid: i64) -> sqlx::Result<bool> {
+    Ok(UserTotpDao::get_by_user_id(pool, uid).await?.is_some_and(|x| x.confirm_time.is_some()))
+}
+
+/// Generate a new secret for the user, replacing the unconfirmed one. `account` is displayed in the authenticator.
+pub async fn enroll(pool: &PgPool, uid: i64, account: &str) -> Result<Enrollment, TotpError> {
+    if is_enabled(pool, uid).await? {
+        return Err(TotpError::AlreadyEnabled);
+    }
+    let secret = base32_encode(&rand::random::<[u8; SECRET_BYTES]>());
+    UserTotpDao::upsert(pool, &UserTotpSecret {
+        user_id: uid,
+        secret: secret.clone(),
+        confirm_time: None,
+        create_time: Utc::now(),
+    }).await?;
+    Ok(Enrollment {
+        provisioning_uri: provisioning_uri(account, &secret),
+        secret,
+    })
+}
+
+/// Enable the 2FA by a code of the enrolled secret, returns the new recovery codes which are only shown once
+pub async fn confirm(
+    pool: &PgPool,
+    redis: &mut ConnectionManager,
+    uid: i64,
+    code: &str,
+    ip: Option<&str>,
+) -> Result<Vec<String>, TotpError> {
+    let Some(secret) = UserTotpDao::get_by_user_id(pool, uid).await? else {
+        return Err(TotpError::NotEnrolled);
+    };
+    if secret.confirm_time.is_some() {
+        return Err(TotpError::AlreadyEnabled);
+    }
+    verify_code(pool, redis, &secret, code, false).await?;
+
+    let recovery_codes = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect::<Vec<_>>();
+    let hashes = recovery_codes.iter().map(|x| hash_recovery_code(x)).collect::<Vec<_>>();
+    let mut tx = pool.begin().await?;
+    if !UserTotpDao::confirm(&mut *tx, uid, Utc::now()).await? {
+        // Confirmed or re-enrolled concurrently
+        return Err(TotpError::NotEnrolled);
+    }
+    UserTotpDao::delete_recovery_codes(&mut *tx, uid).await?;
+    UserTotpDao::insert_recovery_codes(&mut *tx, uid, &hashes).await?;
+    audit(&mut tx, uid, user_account_audit_log::ACTION_TOTP_ENABLE, ip).await?;
+    tx.commit().await?;
+    Ok(recovery_codes)
+}
+
+/// Check the 2FA of the user who passed the first factor, a recovery code is accepted in place of the TOTP code
+pub async fn verify_login(
+    pool: &PgPool,
+    redis: &mut ConnectionManager,
+    uid: i64,
+    code: Option<&str>,
+) -> Result<(), TotpError> {
+    let Some(secret) = UserTotpDao::get_by_user_id(pool, uid).await?.filter(|x| x.confirm_time.is_some()) else {
+        return Ok(());
+    };
+    let Some(code) = code else {
+        return Err(TotpError::Required);
+    };
+    verify_code(pool, redis, &secret, code, true).await
+}
+
+/// Disable the 2FA by a TOTP or recovery code, the secret and recovery codes are deleted
+pub async fn disable(
+    pool: &PgPool,
+    redis: &mut ConnectionManager,
+    uid: i64,
+    code: &str,
+    ip: Option<&str>,
+) -> Result<(), TotpError> {
+    let Some(secret) = UserTotpDao::get_by_user_id(pool, uid).await?.filter(|x| x.confirm_time.is_some()) else {
+        return Err(TotpError::NotEnabled);
+    };
+    verify_code(pool, redis, &secret, code, true).await?;
+
+    let mut tx = pool.begin().await?;
+    UserTotpDao::delete_by_user_id(&mut *tx, uid).await?;
+    UserTotpDao::delete_recovery_codes(&mut *tx, uid).await?;
+    audit(&mut tx, uid, user_account_audit_log::ACTION_TOTP_DISABLE, ip).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn verify_code(
+    pool: &PgPool,
+    redis: &mut ConnectionManager,
+    secret: &UserTotpSecret,
+    code: &str,
+    allow_recovery_code: bool,
+) -> Result<(), TotpError> {
+    let uid = secret.user_id;
+    let failures_key = format!("totp:failures:{uid}");
+    let failures: Option<i64> = redis.get(&failures_key).await.map_err(anyhow::Error::from)?;
+    if failures.unwrap_or_default() >= MAX_FAILURES {
+        return Err(TotpError::TooManyFailures);
+    }
+
+    let code = code.trim();
+    let accepted = if code.len() == DIGITS && code.bytes().all(|x| x.is_ascii_digit()) {
+        match find_step(&base32_decode(&secret.secret)?, code, Utc::now().timestamp())? {
+            // A code can't be replayed within its window
+            Some(step) => redis.set_options(
+                format!("totp:used:{uid}:{step}"),
+                1,
+                SetOptions::default()
+                    .conditional_set(ExistenceCheck::NX)
+                    .with_expiration(SetExpiry::EX((STEP_SECS * (WINDOW_STEPS * 2 + 1)) as u64)),
+            ).await.map_err(anyhow::Error::from)?,
+            None => false,
+        }
+    } else if allow_recovery_code {
+        UserTotpDao::use_recovery_code(pool, uid, &hash_recovery_code(code), Utc::now()).await?
+    } else {
+        false
+    };
+
+    if !accepted {
+        let _: () = redis::pipe()
+            .incr(&failures_key, 1).ignore()
+            .expire(&failures_key, FAILURE_WINDOW_SECS).ignore()
+            .query_async(redis)
+            .await
+            .map_err(anyhow::Error::from)?;
+        return Err(TotpError::InvalidCode);
+    }
+    Ok(())
+}
+
+async fn audit(conn: &mut PgConnection, uid: i64, action: &str, ip: Option<&str>) -> sqlx::Result<()> {
+    UserAccountAuditLogDao::insert(&mut *conn, &UserAccountAuditLog {
+        id: 0,
+        user_id: uid,
+        action: action.to_string(),
+        detail: json!({}),
+        ip: ip.map(|x| x.to_string()),
+        create_time: Utc::now(),
+    }).await?;
+    Ok(())
+}
+
+pub fn provisioning_uri(account: &str, secret: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={secret}&issuer={}&algorithm=SHA1&digits={DIGITS}&period={STEP_SECS}",
+        urlencoding::encode(ISSUER),
+        urlencoding::encode(account),
+        urlencoding::encode(ISSUER),
+    )
+}
+
+/// The code of the time step, i.e. `unix_time / 30`
+pub fn code_at(secret: &[u8], step: i64) -> anyhow::Result<String> {
+    let pkey = PKey::hmac(secret)?;
+    let mut signer = Signer::new(MessageDigest::sha1(), &pkey)?;
+    signer.update(&step.to_be_bytes())?;
+    let hmac = signer.sign_to_vec()?;
+    // The dynamic truncation of RFC 4226
+    let offset = (hmac[hmac.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes(hmac[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+    Ok(format!("{:0width$}", value % 10u32.pow(DIGITS as u32), width = DIGITS))
+}
+
+/// The step of the code around the unix time, if it matches
+fn find_step(secret: &[u8], code: &str, unix_time: i64) -> anyhow::Result<Option<i64>> {
+    let current = unix_time / STEP_SECS;
+    for step in current - WINDOW_STEPS..=current + WINDOW_STEPS {
+        if openssl::memcmp::eq(code_at(secret, step)?.as_bytes(), code.as_bytes()) {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+fn generate_recovery_code() -> String {
+    let mut rng = rand::rng();
+    let chars = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.random_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect::<String>();
+    format!("{}-{}", &chars[..5], &chars[5..])
+}
+
+/// Hash the code ignoring the case, spaces and dashes
+fn hash_recovery_code(code: &str) -> String {
+    let normalized = code.chars()
+        .filter(|x| x.is_ascii_alphanumeric())
+        .map(|x| x.to_ascii_lowercase())
+        .collect::<String>();
+    hex::encode(openssl::sha::sha256(normalized.as_bytes()))
+}
+
+/// RFC 4648 base32 without padding
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(5) * 8);
+    for chunk in data.chunks(5) {
+        let mut buf = [0u8; 5];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let bits = u64::from_be_bytes([0, 0, 0, buf[0], buf[1], buf[2], buf[3], buf[4]]);
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            result.push(BASE32_ALPHABET[(bits >> (35 - i * 5) & 0x1f) as usize] as char);
+        }
+    }
+    result
+}
+
+/// Decode base32 ignoring the case, spaces and padding
+pub fn base32_decode(value: &str) -> anyhow::Result<Vec<u8>> {
+    let mut result = Vec::with_capacity(value.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in value.bytes().filter(|x| *x != b'=' && !x.is_ascii_whitespace()) {
+        let Some(index) = BASE32_ALPHABET.iter().position(|x| *x == c.to_ascii_uppercase()) else {
+            bail!("Invalid base32 character: {}", c as char);
+        };
+        buffer = (buffer << 5) | index as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base32() {
+        assert_eq!("", base32_encode(b""));
+        assert_eq!("MY", base32_encode(b"f"));
+        assert_eq!("MZXW6YTBOI", base32_encode(b"foobar"));
+        assert_eq!(b"foobar".to_vec(), base32_decode("MZXW6YTBOI======").unwrap());
+        assert_eq!(b"foobar".to_vec(), base32_decode("mzxw 6ytb oi").unwrap());
+        assert!(base32_decode("MZ1").is_err());
+
+        let secret = rand::random::<[u8; SECRET_BYTES]>();
+        assert_eq!(secret.to_vec(), base32_decode(&base32_encode(&secret)).unwrap());
+    }
+
+    #[test]
+    fn test_code_at() {
+        // The SHA-1 test vectors of RFC 6238, truncated to 6 digits
+        let secret = b"12345678901234567890";
+        assert_eq!("287082", code_at(secret, 59 / STEP_SECS).unwrap());
+        assert_eq!("081804", code_at(secret, 1111111109 / STEP_SECS).unwrap());
+        assert_eq!("005924", code_at(secret, 1234567890 / STEP_SECS).unwrap());
+    }
+
+    #[test]
+    fn test_find_step() {
+        let secret = b"12345678901234567890";
+        let step = 1111111109 / STEP_SECS;
+        assert_eq!(Some(step), find_step(secret, "081804", 1111111109).unwrap());
+        assert_eq!(Some(step), find_step(secret, "081804", 1111111109 + STEP_SECS).unwrap());
+        assert_eq!(None, find_step(secret, "081804", 1111111109 + STEP_SECS * 2).unwrap());
+        assert_eq!(None, find_step(secret, "000000", 1111111109).unwrap());
+    }
+
+    #[test]
+    fn test_recovery_code() {
+        let code = generate_recovery_code();
+        assert_eq!(11, code.len());
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&code.to_uppercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code(&generate_recovery_code()));
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        assert_eq!(
+            "otpauth://totp/Hachimi%20World:a%40b.com?secret=ABC&issuer=Hachimi%20World&algorithm=SHA1&digits=6&period=30",
+            provisioning_uri("a@b.com", "ABC"),
+        );
+    }
+}
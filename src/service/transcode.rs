@@ -0,0 +1,277 @@
+//! Transcode the approved songs into lower-bitrate renditions for streaming, see [SongAudioRendition].
+//!
+//! The audio is transcoded by `ffmpeg` in the job [BackgroundJob::TranscodeSong], enqueued when a review is
+//! approved. The renditions are tagged with the audio they are transcoded from, so the job is skipped if the audio
+//! is not replaced, and the renditions of a replaced audio are hidden until they are transcoded again.
+//! The files of the replaced and no longer configured renditions are deleted once they are unreferenced.
+//!
+//! The true peak of each rendition is measured by `ffmpeg` as well, since the lossy encoding overshoots the peak
+//! of the source audio, so the `true_peak` of the song would clip the rendition if the gain is limited by it.
+//!
+//! [BackgroundJob::TranscodeSong]: crate::service::jobs::BackgroundJob::TranscodeSong
+
+use crate::config::Config;
+use crate::db::song::SongDao;
+use crate::db::song_audio_rendition::{self, ISongAudioRenditionDao, SongAudioRendition, SongAudioRenditionDao};
+use crate::db::CrudDao;
+use crate::file_hosting::UploadOptions;
+use crate::service;
+use crate::web::state::AppState;
+use anyhow::{bail, Context};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Optional `transcode` section of the config file, the renditions are not generated if it's absent.
+///
+/// ```yaml
+/// transcode:
+///   ffmpeg_path: ffmpeg
+///   timeout_secs: 120
+///   renditions:
+///     - codec: aac
+///       bitrate_kbps: 128
+///     - codec: opus
+///       bitrate_kbps: 64
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscodeCfg {
+    #[serde(default = "default_ffmpeg_path")]
+    pub ffmpeg_path: String,
+    /// Of each rendition, the jobs are leased for 5 minutes
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_renditions")]
+    pub renditions: Vec<RenditionCfg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenditionCfg {
+    /// `aac` or `opus`
+    pub codec: String,
+    pub bitrate_kbps: i32,
+}
+
+fn default_ffmpeg_path() -> String { String::from("ffmpeg") }
+
+fn default_timeout_secs() -> u64 { 120 }
+
+fn default_renditions() -> Vec<RenditionCfg> {
+    vec![
+        RenditionCfg { codec: song_audio_rendition::CODEC_AAC.to_string(), bitrate_kbps: 128 },
+        RenditionCfg { codec: song_audio_rendition::CODEC_OPUS.to_string(), bitrate_kbps: 64 },
+    ]
+}
+
+impl TranscodeCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("transcode")?.is_some() {
+            Ok(Some(config.get_and_parse("transcode")?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl RenditionCfg {
+    /// The file extension, the encoder and the content type
+    fn format(&self) -> anyhow::Result<(&'static str, &'static str, &'static str)> {
+        match self.codec.as_str() {
+            song_audio_rendition::CODEC_AAC => Ok(("m4a", "aac", "audio/mp4")),
+            song_audio_rendition::CODEC_OPUS => Ok(("opus", "libopus", "audio/ogg")),
+            _ => bail!("Unsupported codec {}", self.codec),
+        }
+    }
+
+    /// The arguments of `ffmpeg` to transcode the input to the output, without the cover and metadata
+    fn ffmpeg_args(&self, input: &Path, output: &Path) -> anyhow::Result<Vec<String>> {
+        let (_, encoder, _) = self.format()?;
+        let mut args = vec![
+            "-nostdin".to_string(), "-y".to_string(), "-loglevel".to_string(), "error".to_string(),
+            "-i".to_string(), input.display().to_string(),
+            "-vn".to_string(), "-map_metadata".to_string(), "-1".to_string(),
+            "-c:a".to_string(), encoder.to_string(),
+            "-b:a".to_string(), format!("{}k", self.bitrate_kbps),
+        ];
+        if self.codec == song_audio_rendition::CODEC_AAC {
+            // Playable before downloaded completely
+            args.extend(["-movflags".to_string(), "+faststart".to_string()]);
+        }
+        args.push(output.display().to_string());
+        Ok(args)
+    }
+}
+
+/// The arguments of `ffmpeg` to measure the true peak of the input, printed in the summary of the `ebur128` filter
+fn true_peak_args(input: &Path) -> Vec<String> {
+    vec![
+        "-nostdin".to_string(), "-hide_banner".to_string(), "-nostats".to_string(),
+        "-i".to_string(), input.display().to_string(),
+        "-map".to_string(), "0:a:0".to_string(),
+        "-af".to_string(), "ebur128=peak=true".to_string(),
+        "-f".to_string(), "null".to_string(), "-".to_string(),
+    ]
+}
+
+/// The `Peak` in the `True peak` section of the summary of the `ebur128` filter, `None` for silence
+fn parse_true_peak(output: &str) -> Option<f32> {
+    let (_, summary) = output.rsplit_once("True peak:")?;
+    let peak = summary.lines().find_map(|x| x.trim().strip_prefix("Peak:"))?;
+    let peak = peak.trim().trim_end_matches("dBFS").trim().parse::<f32>().ok()?;
+    peak.is_finite().then_some(peak)
+}
+
+/// Generate the configured renditions of the song if they are missing or stale, safe to run again
+pub async fn transcode_song(state: &AppState, song_id: i64) -> anyhow::Result<()> {
+    let Some(cfg) = TranscodeCfg::load(&state.config)? else {
+        return Ok(());
+    };
+    let Some(song) = SongDao::get_by_id(&state.sql_pool, song_id).await? else {
+        return Ok(());
+    };
+    let existing = SongAudioRenditionDao::list_by_song_ids(&state.sql_pool, &[song_id]).await?;
+    let find_done = |x: &RenditionCfg| existing.iter()
+        .find(|y| y.codec == x.codec && y.bitrate_kbps == x.bitrate_kbps && y.source_url == song.file_url);
+    if existing.len() == cfg.renditions.len() && cfg.renditions.iter().all(|x| find_done(x).is_some()) {
+        return Ok(());
+    }
+
+    let Some(source_key) = state.file_host.key_of(&song.file_url) else {
+        warn!(song_id, "The audio {} is not hosted here, skip transcoding", song.file_url);
+        return Ok(());
+    };
+    let Some(source) = state.file_host.download(&source_key).await? else {
+        bail!("The audio {} of song {} is not found", source_key, song_id)
+    };
+
+    let work_dir = std::env::temp_dir().join(format!("hachimi-transcode-{}", uuid::Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+    let result = async {
+        let input = work_dir.join("input");
+        tokio::fs::write(&input, &source.bytes).await?;
+        let mut kept_ids = vec![];
+        // The files of the renditions transcoded from a replaced audio, overwritten by the upserts
+        let mut replaced = vec![];
+        for rendition in &cfg.renditions {
+            if let Some(x) = find_done(rendition) {
+                kept_ids.push(x.id);
+                continue;
+            }
+            let (ext, _, content_type) = rendition.format()?;
+            let output = work_dir.join(format!("output.{}", ext));
+            let name = format!("{} {}k", rendition.codec, rendition.bitrate_kbps);
+            run_ffmpeg(&cfg, rendition.ffmpeg_args(&input, &output)?, &name).await?;
+            // The rendition is still usable without it
+            let true_peak = match run_ffmpeg(&cfg, true_peak_args(&output), &name).await {
+                Ok(x) => parse_true_peak(&String::from_utf8_lossy(&x.stderr)),
+                Err(e) => {
+                    warn!(song_id, "Failed to measure the true peak of {}: {:?}", name, e);
+                    None
+                }
+            };
+
+            let bytes = tokio::fs::read(&output).await?;
+            let key = format!("songs/renditions/{}-{}k.{}", uuid::Uuid::new_v4(), rendition.bitrate_kbps, ext);
+            let uploaded = service::upload::store(
+                state.file_host.as_ref(),
+                "rendition",
+                bytes.into(),
+                &key,
+                &UploadOptions::audio(content_type),
+            ).await?;
+            let id = SongAudioRenditionDao::upsert(&state.sql_pool, &SongAudioRendition {
+                id: 0,
+                song_id,
+                codec: rendition.codec.clone(),
+                bitrate_kbps: rendition.bitrate_kbps,
+                file_url: uploaded.public_url,
+                size: uploaded.size as i64,
+                source_url: song.file_url.clone(),
+                create_time: Utc::now(),
+                true_peak,
+            }).await?;
+            kept_ids.push(id);
+            replaced.extend(existing.iter()
+                .filter(|x| x.codec == rendition.codec && x.bitrate_kbps == rendition.bitrate_kbps)
+                .map(|x| x.file_url.clone()));
+        }
+        // The renditions no longer configured
+        let deleted = SongAudioRenditionDao::delete_except(&state.sql_pool, song_id, &kept_ids).await?;
+        let deleted_files = service::song::delete_unreferenced_files(state, deleted.iter().chain(&replaced).cloned()).await?;
+        info!(
+            song_id,
+            "Transcoded {} renditions, replaced {} and deleted {}, {} files deleted",
+            cfg.renditions.len(), replaced.len(), deleted.len(), deleted_files,
+        );
+        anyhow::Ok(())
+    }.await;
+    if let Err(e) = tokio::fs::remove_dir_all(&work_dir).await {
+        warn!("Failed to remove {}: {:?}", work_dir.display(), e);
+    }
+    result?;
+
+    service::song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(&song)).await?;
+    Ok(())
+}
+
+/// Run `ffmpeg` on the rendition `name`, returns the output if it succeeds
+async fn run_ffmpeg(cfg: &TranscodeCfg, args: Vec<String>, name: &str) -> anyhow::Result<Output> {
+    let child = tokio::process::Command::new(&cfg.ffmpeg_path)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", cfg.ffmpeg_path))?;
+    let result = tokio::time::timeout(Duration::from_secs(cfg.timeout_secs), child.wait_with_output()).await
+        .with_context(|| format!("Running ffmpeg on {} timed out", name))??;
+    if !result.status.success() {
+        bail!("Failed to run ffmpeg on {}: {}", name, String::from_utf8_lossy(&result.stderr).trim())
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffmpeg_args() {
+        let cfg: TranscodeCfg = serde_yaml::from_str("timeout_secs: 60").unwrap();
+        assert_eq!(default_renditions(), cfg.renditions);
+
+        let args = cfg.renditions[0].ffmpeg_args(Path::new("/tmp/input"), Path::new("/tmp/output.m4a")).unwrap();
+        assert_eq!("-i /tmp/input -vn -map_metadata -1 -c:a aac -b:a 128k -movflags +faststart /tmp/output.m4a", args[4..].join(" "));
+        let args = cfg.renditions[1].ffmpeg_args(Path::new("/tmp/input"), Path::new("/tmp/output.opus")).unwrap();
+        assert_eq!("-c:a libopus -b:a 64k /tmp/output.opus", args[9..].join(" "));
+
+        let mp3 = RenditionCfg { codec: "mp3".to_string(), bitrate_kbps: 128 };
+        assert!(mp3.ffmpeg_args(Path::new("a"), Path::new("b")).is_err());
+    }
+
+    #[test]
+    fn test_parse_true_peak() {
+        let output = "[Parsed_ebur128_0 @ 0x5581] Summary:
+
+  Integrated loudness:
+    I:         -14.2 LUFS
+    Threshold: -24.6 LUFS
+
+  Loudness range:
+    LRA:         5.1 LU
+    Threshold: -34.7 LUFS
+    LRA low:   -18.9 LUFS
+    LRA high:  -13.8 LUFS
+
+  True peak:
+    Peak:        0.4 dBFS
+";
+        assert_eq!(Some(0.4), parse_true_peak(output));
+        assert_eq!(None, parse_true_peak(&output.replace("0.4 dBFS", "-inf dBFS")));
+        assert_eq!(None, parse_true_peak("Invalid data found when processing input"));
+    }
+}
@@ -1,19 +1,20 @@
+use crate::file_hosting::{FileHost, UploadOptions, UploadResult};
+use crate::db::user_storage_object;
+use crate::service::image::{self, ImageProcessOptions};
+use crate::service::{storage_quota, upload_cleanup};
 use crate::service::upload::ValidationError::{InvalidImage, UnsupportedFormat};
+use crate::web::multipart::{self, FieldSpec};
 use crate::web::result::{CommonError, WebError};
 use crate::web::state::AppState;
-use crate::common;
-use anyhow::{anyhow, Context};
 use axum::extract::{Multipart, State};
 use bytes::Bytes;
-use image::imageops::FilterType;
-use image::{DynamicImage, ImageFormat, ImageReader};
-use metrics::histogram;
+use ::image::{ImageFormat, ImageReader};
+use metrics::{counter, histogram};
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use std::io::Cursor;
 use std::time::Instant;
-use tracing::info;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ValidationError {
@@ -44,51 +45,70 @@ pub async fn validate_image_and_get_ext<'a>(bytes: Bytes) -> Result<&'a str, Val
     Ok(format_ext)
 }
 
-#[derive(Debug, Copy, Clone)]
-pub enum ResizeType {
-    Crop, Fit, Exact
+/// The metrics of an upload request, labeled by the `kind` of the upload:
+///
+/// - `upload_size_bytes`: the size of the received file
+/// - `upload_rejected_count`: the rejected uploads by the `reason`, which is the error code (e.g. `field_too_large`, `invalid_image`)
+/// - `upload_duration_seconds`: from receiving to the end of the request, by the `result` of `ok`, `rejected` or `error`
+///
+/// The duration is recorded on drop, so the early returns are counted as `error` unless they are passed through
+/// [UploadMetrics::check] or [UploadMetrics::reject].
+pub struct UploadMetrics {
+    kind: String,
+    start: Instant,
+    result: &'static str,
 }
 
-pub fn scale_down_to_webp(
-    w: u32,
-    h: u32,
-    bytes: Bytes,
-    resize_type: ResizeType,
-    quality: f32
-) -> anyhow::Result<Vec<u8>> {
-    let start = Instant::now();
-    let len = bytes.len();
-    let image = ImageReader::new(Cursor::new(bytes))
-        .with_guessed_format()?
-        .decode()?;
-    // Convert to rgb8 if needed because the webp encoder only supports rgb8/rgba8
-    let image = match image {
-        x @ DynamicImage::ImageRgb8(_) => x,
-        x @ DynamicImage::ImageRgba8(_) => x,
-        x => {
-            info!("Converting image to rgb8");
-            DynamicImage::ImageRgb8(x.into_rgb8())
-        }
-    };
-    // Resize image
-    let resized = if image.width() > w || image.height() > h {
-        match resize_type {
-            ResizeType::Crop => image.resize_to_fill(w, h, FilterType::Lanczos3),
-            ResizeType::Fit => image.resize(w, h, FilterType::Lanczos3),
-            ResizeType::Exact => image.resize(w, h, FilterType::Lanczos3)
+impl UploadMetrics {
+    pub fn start(kind: &str) -> Self {
+        UploadMetrics { kind: kind.to_string(), start: Instant::now(), result: "error" }
+    }
+
+    pub fn received(&self, size: usize) {
+        histogram!("upload_size_bytes", "kind" => self.kind.clone()).record(size as f64);
+    }
+
+    /// Record the business error as the rejection reason
+    pub fn reject(&mut self, err: WebError<CommonError>) -> WebError<CommonError> {
+        if let WebError::Business(x) = &err {
+            counter!("upload_rejected_count", "kind" => self.kind.clone(), "reason" => x.code.clone()).increment(1);
+            self.result = "rejected";
         }
-    } else {
-        image
-    };
+        err
+    }
+
+    /// Record the rejection reason if it's a business error, for the `?` operator
+    pub fn check<T>(&mut self, result: Result<T, WebError<CommonError>>) -> Result<T, WebError<CommonError>> {
+        result.map_err(|e| self.reject(e))
+    }
 
-    let webp_encoder = webp::Encoder::from_image(&resized).map_err(|_| anyhow!("Failed to encode image to webp"))?;
-    let webp = webp_encoder.encode(quality);
+    pub fn succeed(&mut self) {
+        self.result = "ok";
+    }
+}
 
-    info!("Image scale down to webp took {:?}, size from {} to {}", start.elapsed(), len, webp.len());
-    histogram!("image_scale_down_to_webp_duration_secs").record(start.elapsed().as_secs_f64());
-    Ok(webp.to_vec())
+impl Drop for UploadMetrics {
+    fn drop(&mut self) {
+        histogram!("upload_duration_seconds", "kind" => self.kind.clone(), "result" => self.result)
+            .record(self.start.elapsed().as_secs_f64());
+    }
 }
 
+/// Upload to the file host and record the latency as `upload_storage_duration_seconds`
+pub async fn store(
+    file_host: &dyn FileHost,
+    kind: &str,
+    bytes: Bytes,
+    key: &str,
+    options: &UploadOptions,
+) -> anyhow::Result<UploadResult> {
+    let start = Instant::now();
+    let result = file_host.upload(bytes, key, options).await;
+    let label = if result.is_ok() { "ok" } else { "error" };
+    histogram!("upload_storage_duration_seconds", "kind" => kind.to_string(), "result" => label)
+        .record(start.elapsed().as_secs_f64());
+    result
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadedImageTempData {
@@ -98,72 +118,38 @@ pub struct UploadedImageTempData {
     pub format: String
 }
 
-#[derive(Debug)]
-pub struct ImageProcessOptions {
-    pub max_width: u32,
-    pub max_height: u32,
-    pub resize_type: ResizeType,
-    pub quality: f32,
-}
-
-impl ImageProcessOptions {
-    pub fn default_cover() -> Self {
-        ImageProcessOptions {
-            max_width: 1024,
-            max_height: 1024,
-            resize_type: ResizeType::Fit,
-            quality: 90f32,
-        }
-    }
-
-    pub fn default_avatar() -> Self {
-        ImageProcessOptions {
-            max_width: 256,
-            max_height: 256,
-            resize_type: ResizeType::Crop,
-            quality: 80f32,
-        }
-    }
-}
-
 pub async fn upload_cover_image_as_temp_id(
     module_type: &str,
+    uid: i64,
     mut state: State<AppState>,
-    mut multipart: Multipart,
-    max_size: usize,
+    multipart: Multipart,
     options: ImageProcessOptions,
 ) -> Result<String, WebError<CommonError>> {
-    let data_field = multipart
-        .next_field()
-        .await?
-        .with_context(|| "No data field found")?;
-    let bytes = data_field.bytes().await?;
-
-    // Validate image
-    if bytes.len() > max_size {
-        Err(common!("image_too_large", "Image size too large"))?;
-    }
-
-    let webp = scale_down_to_webp(options.max_width, options.max_height, bytes.clone(), options.resize_type, options.quality)
-        .map_err(|_| common!("invalid_image", "The image is not supported"))?;
-
-    // Upload image
-    let sha1 = openssl::sha::sha1(&webp);
-    let filename = format!("images/{}/{}.webp", module_type, hex::encode(sha1));
-    let result = state.file_host.upload(webp.into(), &filename).await?;
+    let mut upload_metrics = UploadMetrics::start(module_type);
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[FieldSpec::image("file", state.limits.image_max_bytes)]).await)?;
+    let bytes = upload_metrics.check(fields.take_required("file"))?.bytes;
+    let size = bytes.len();
+    upload_metrics.received(size);
+    upload_metrics.check(storage_quota::ensure_quota(&state.config, &state.sql_pool, uid, size).await)?;
+
+    let result = upload_metrics.check(image::process_and_upload(state.file_host.as_ref(), module_type, bytes, &options).await
+        .map_err(|e| e.into_web_error()))?;
+    storage_quota::track(&state.sql_pool, uid, user_storage_object::KIND_IMAGE, &result).await?;
     let temp_id = uuid::Uuid::new_v4().to_string();
     let temp_data = UploadedImageTempData {
         module_type: module_type.to_string(),
         url: result.public_url,
-        size: bytes.len(),
-        format: "webp".to_string(),
+        size,
+        format: options.format.ext().to_string(),
     };
     let temp_data_json = serde_json::to_string(&temp_data)?;
 
     let _: () = state.redis_conn
-        .set_ex(build_image_temp_key(module_type, &temp_id), temp_data_json, 3600)
+        .set_ex(build_image_temp_key(module_type, &temp_id), temp_data_json, upload_cleanup::TEMP_DATA_TTL_SECS)
         .await?;
+    upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
 
+    upload_metrics.succeed();
     Ok(temp_id)
 }
 
@@ -186,15 +172,3 @@ fn build_image_temp_key(module_type: &str, temp_id: &str) -> String {
     let key = format!("upload:image:{}:{}", module_type, temp_id);
     key
 }
-
-#[cfg(test)]
-mod tests {
-    use crate::service::upload::{scale_down_to_webp, ResizeType};
-    use std::fs;
-
-    #[test]
-    fn test_scale_down() {
-        let bytes = fs::read(".local/test_res/test_rgb48be.png").unwrap();
-        let webp = scale_down_to_webp(1920, 1920, bytes.into(), ResizeType::Fit, 95f32).unwrap();
-    }
-}
\ No newline at end of file
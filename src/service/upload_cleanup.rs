@@ -0,0 +1,218 @@
+//! The cleanup of the uploaded files never published.
+//!
+//! The uploaded audio and cover files are only referenced by the temp data in Redis until they are published,
+//! which expires in [TEMP_DATA_TTL_SECS]. Their object keys are tracked in a sorted set by the expiry time by
+//! [track_temp], and [CLEANUP_JOB] deletes the ones still unreferenced some time after they expire, releasing them
+//! from the storage usage of the uploaders as well.
+//!
+//! The multipart uploads in progress are tracked by [track_temp_multipart] the same way, and aborted if they are
+//! abandoned, since the storage keeps their parts otherwise.
+
+use crate::config::Config;
+use crate::db::storage_orphan_object::{IStorageOrphanObjectDao, StorageOrphanObjectDao};
+use crate::db::user_storage_object::{IUserStorageObjectDao, UserStorageObjectDao};
+use crate::file_hosting::FileHost;
+use crate::util::redis_health;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use chrono::Utc;
+use metrics::counter;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// The object keys of the temp uploads, scored by the unix seconds when their temp data expires
+const TEMP_OBJECTS_KEY: &str = "upload:temp_objects";
+/// The multipart uploads in progress as the JSON of [TempMultipart], scored like [TEMP_OBJECTS_KEY]
+const TEMP_MULTIPARTS_KEY: &str = "upload:temp_multiparts";
+/// How long the temp data of an upload lives in Redis
+pub const TEMP_DATA_TTL_SECS: u64 = 3600;
+
+/// Optional `upload_cleanup` section of the config file, the absent fields take the defaults.
+///
+/// ```yaml
+/// upload_cleanup:
+///   grace_secs: 86400
+///   batch_size: 100
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadCleanupCfg {
+    /// The objects are kept for this long after their temp data expires, in case a publishing is in progress
+    pub grace_secs: u64,
+    /// The objects checked at most in a run
+    pub batch_size: isize,
+}
+
+impl Default for UploadCleanupCfg {
+    fn default() -> Self {
+        UploadCleanupCfg { grace_secs: 86400, batch_size: 100 }
+    }
+}
+
+impl UploadCleanupCfg {
+    /// Load the `upload_cleanup` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("upload_cleanup")?.is_some() {
+            config.get_and_parse("upload_cleanup")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+pub const CLEANUP_JOB: Job = Job {
+    name: "cleanup_temp_uploads",
+    // Hourly at :40
+    schedule: "40 * * * *",
+    max_jitter: Duration::from_secs(120),
+};
+
+/// Track the object of a temp upload to be cleaned up if it's never published.
+///
+/// Tracking the same key again postpones its cleanup. It's skipped if Redis is unavailable, then the object is
+/// only collected by the storage events, see [crate::service::storage_events].
+pub async fn track_temp(redis: &mut ConnectionManager, object_key: &str) {
+    track_temp_for(redis, object_key, TEMP_DATA_TTL_SECS).await
+}
+
+/// Like [track_temp], for the object referenced by something living for `ttl_secs` instead of the temp data,
+/// e.g. a presigned upload
+pub async fn track_temp_for(redis: &mut ConnectionManager, object_key: &str, ttl_secs: u64) {
+    let expire_at = Utc::now().timestamp() + ttl_secs as i64;
+    let _: Option<()> = redis_health::cached(redis.zadd(TEMP_OBJECTS_KEY, object_key, expire_at)).await;
+}
+
+/// A multipart upload of the storage, see [track_temp_multipart]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct TempMultipart {
+    key: String,
+    upload_id: String,
+}
+
+impl TempMultipart {
+    fn member(object_key: &str, upload_id: &str) -> String {
+        let value = TempMultipart { key: object_key.to_string(), upload_id: upload_id.to_string() };
+        serde_json::to_string(&value).unwrap_or_default()
+    }
+}
+
+/// Track the multipart upload to be aborted if it's not completed in `ttl_secs`.
+///
+/// Tracking it again postpones the abortion, it should be untracked by [untrack_temp_multipart] once it's completed
+/// or aborted. It's skipped if Redis is unavailable like [track_temp].
+pub async fn track_temp_multipart(redis: &mut ConnectionManager, object_key: &str, upload_id: &str, ttl_secs: u64) {
+    let expire_at = Utc::now().timestamp() + ttl_secs as i64;
+    let member = TempMultipart::member(object_key, upload_id);
+    let _: Option<()> = redis_health::cached(redis.zadd(TEMP_MULTIPARTS_KEY, member, expire_at)).await;
+}
+
+pub async fn untrack_temp_multipart(redis: &mut ConnectionManager, object_key: &str, upload_id: &str) {
+    let member = TempMultipart::member(object_key, upload_id);
+    let _: Option<()> = redis_health::cached(redis.zrem(TEMP_MULTIPARTS_KEY, member)).await;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanupSummary {
+    /// Unreferenced and deleted from the storage
+    pub deleted: usize,
+    /// Referenced by the published songs or the other records, so kept
+    pub kept: usize,
+    /// Abandoned multipart uploads aborted
+    pub aborted: usize,
+}
+
+/// Delete the expired temp objects not referenced by any record and abort the expired multipart uploads,
+/// at most `cfg.batch_size` of each
+pub async fn cleanup_expired(
+    file_host: &dyn FileHost,
+    pool: &PgPool,
+    redis: &mut ConnectionManager,
+    cfg: &UploadCleanupCfg,
+) -> anyhow::Result<CleanupSummary> {
+    let before = Utc::now().timestamp() - cfg.grace_secs as i64;
+    let mut summary = CleanupSummary::default();
+
+    let members: Vec<String> = redis.zrangebyscore_limit(TEMP_MULTIPARTS_KEY, "-inf", before, 0, cfg.batch_size).await?;
+    for member in members {
+        // Claimed by another run meanwhile
+        let removed: i64 = redis.zrem(TEMP_MULTIPARTS_KEY, &member).await?;
+        let Some(upload) = serde_json::from_str::<TempMultipart>(&member).ok().filter(|_| removed > 0) else {
+            continue;
+        };
+        // Not retried, it fails again if the upload is gone already
+        if let Err(e) = file_host.abort_multipart_upload(&upload.key, &upload.upload_id).await {
+            warn!("Failed to abort the multipart upload of {}: {:?}", upload.key, e);
+            continue;
+        }
+        counter!("upload_cleanup_aborted_count").increment(1);
+        summary.aborted += 1;
+    }
+
+    let expired: Vec<String> = redis.zrangebyscore_limit(TEMP_OBJECTS_KEY, "-inf", before, 0, cfg.batch_size).await?;
+    let mut keys = Vec::with_capacity(expired.len());
+    for key in expired {
+        // Claimed by another run meanwhile
+        let removed: i64 = redis.zrem(TEMP_OBJECTS_KEY, &key).await?;
+        if removed > 0 {
+            keys.push(key);
+        }
+    }
+    let urls = keys.iter().map(|x| file_host.public_url(x)).collect::<Vec<_>>();
+    let referenced = StorageOrphanObjectDao::referenced_urls(pool, &urls).await?.into_iter().collect::<HashSet<_>>();
+    for (key, url) in keys.into_iter().zip(urls) {
+        if referenced.contains(&url) {
+            summary.kept += 1;
+            continue;
+        }
+        if let Err(e) = file_host.delete(&key).await {
+            warn!("Failed to delete the temp object {}: {:?}", key, e);
+            // Retried in the next run
+            let _: () = redis.zadd(TEMP_OBJECTS_KEY, &key, before).await?;
+            continue;
+        }
+        UserStorageObjectDao::delete_by_key(pool, &key).await?;
+        StorageOrphanObjectDao::delete_by_key(pool, &key).await?;
+        counter!("upload_cleanup_deleted_count").increment(1);
+        summary.deleted += 1;
+    }
+    Ok(summary)
+}
+
+/// Clean up the expired temp uploads hourly until cancelled
+pub async fn run_cleanup(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = UploadCleanupCfg::load(&state.config)?;
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &CLEANUP_JOB,
+        cancel_token,
+        move || {
+            let state = state.clone();
+            let cfg = cfg.clone();
+            async move {
+                let mut redis = state.redis_conn.clone();
+                let summary = cleanup_expired(state.file_host.as_ref(), &state.sql_pool, &mut redis, &cfg).await?;
+                info!("Cleaned up the temp uploads: {:?}", summary);
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cfg() {
+        let cfg: UploadCleanupCfg = serde_yaml::from_str("grace_secs: 3600").unwrap();
+        assert_eq!(UploadCleanupCfg { grace_secs: 3600, ..UploadCleanupCfg::default() }, cfg);
+    }
+}
@@ -1,6 +1,11 @@
-use crate::db::user::{IUserDao, UserDao};
+use crate::db::user::{IUserDao, User, UserDao};
+use crate::db::CrudDao;
+use crate::db::user_follow::{IUserFollowDao, UserFollowDao};
 use crate::service::connection_account;
+use crate::service::support_link;
 use crate::service::connection_account::ConnectionAccount;
+use crate::util::redis_health;
+use crate::web::i18n::Lang;
 use crate::web::routes::user::{ConnectedAccountItem, PublicUserProfile};
 use itertools::Itertools;
 use redis::aio::ConnectionManager;
@@ -18,7 +23,8 @@ pub async fn get_public_profile(
     }
     
     let unique_uids = user_ids.iter().copied().unique().collect_vec();
-    let mut cached_profiles = get_from_cache(redis.clone(), &unique_uids).await?;
+    let mut cached_profiles = redis_health::cached(get_from_cache(redis.clone(), &unique_uids)).await
+        .unwrap_or_default();
 
     let missed_ids = unique_uids.into_iter().filter(|uid| !cached_profiles.contains_key(uid)).collect_vec();
     if missed_ids.is_empty() {
@@ -26,6 +32,8 @@ pub async fn get_public_profile(
     }
 
     let users = UserDao::list_by_ids(sql_pool, &missed_ids).await?;
+    let follower_counts = UserFollowDao::count_followers_batch(sql_pool, &missed_ids).await?;
+    let mut support_links = support_link::list_by_user_ids(sql_pool, &missed_ids).await?;
 
     // parallel get connections for each user and fill in the profile, but for now just return empty connections
     let connections: HashMap<i64, Vec<ConnectionAccount>> = futures::future::join_all(users.iter().map(|u| {
@@ -39,27 +47,38 @@ pub async fn get_public_profile(
         .collect();
 
     let profiles: HashMap<_, _> = users.into_iter()
-        .map(|u| PublicUserProfile {
-            uid: u.id,
-            username: u.username,
-            avatar_url: u.avatar_url,
-            bio: u.bio,
-            gender: u.gender,
-            is_banned: u.is_banned,
-            connected_accounts: connections.get(&u.id).cloned().unwrap_or_default().into_iter().map(|c| ConnectedAccountItem {
-                r#type: c.r#type,
-                id: c.id,
-                name: c.name
-            }).collect_vec(),
+        .map(|u| if u.is_banned {
+            PublicUserProfile::tombstone(u.id)
+        } else {
+            PublicUserProfile {
+                uid: u.id,
+                username: u.username,
+                avatar_url: u.avatar_url,
+                bio: u.bio,
+                gender: u.gender,
+                is_banned: u.is_banned,
+                connected_accounts: connections.get(&u.id).cloned().unwrap_or_default().into_iter().map(|c| ConnectedAccountItem {
+                    r#type: c.r#type,
+                    id: c.id,
+                    name: c.name
+                }).collect_vec(),
+                follower_count: follower_counts.get(&u.id).copied().unwrap_or(0),
+                support_links: support_links.remove(&u.id).unwrap_or_default(),
+            }
         })
         .into_iter()
         .map(|x| (x.uid, x))
         .collect();
-    save_to_cache(redis, &profiles).await?;
+    redis_health::cached(save_to_cache(redis, &profiles)).await;
     cached_profiles.extend(profiles);
     Ok(cached_profiles)
 }
 
+pub async fn evict_profile_cache(mut redis: ConnectionManager, uid: i64) -> anyhow::Result<()> {
+    redis.del(gen_cache_key(uid)).await?;
+    Ok(())
+}
+
 fn gen_cache_key(uid: i64) -> String {
     format!("user_profile:uid={}", uid)
 }
@@ -95,4 +114,51 @@ async fn save_to_cache(mut redis: ConnectionManager, profiles: &HashMap<i64, Pub
         redis.mset_ex(&cache_key_value_pairs, MSetOptions::default().with_expiration(SetExpiry::EX(3000))).await?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// The language of the emails to the user, the default if the user never chose one
+pub fn email_lang(user: &User) -> Lang {
+    user.preferred_language.as_deref().and_then(Lang::from_tag).unwrap_or_default()
+}
+
+const PREFERRED_LANG_CACHE_TTL_SECS: u64 = 3600;
+
+fn gen_preferred_lang_cache_key(user_id: i64) -> String {
+    format!("user:preferred_lang:{user_id}")
+}
+
+/// The language saved by the user, `None` if the user never chose one.
+///
+/// Cached since it's looked up on every authenticated request, see [crate::web::i18n::negotiate_language].
+pub async fn preferred_lang(mut redis: ConnectionManager, pool: &PgPool, user_id: i64) -> anyhow::Result<Option<Lang>> {
+    let key = gen_preferred_lang_cache_key(user_id);
+    // An empty tag is cached for the users without a saved language
+    if let Some(Some(tag)) = redis_health::cached(redis.get(&key)).await {
+        return Ok(Lang::from_tag(&tag));
+    }
+    let lang = UserDao::get_by_id(pool, user_id).await?
+        .and_then(|x| x.preferred_language)
+        .and_then(|x| Lang::from_tag(&x));
+    let tag = lang.map(|x| x.tag()).unwrap_or_default();
+    redis_health::cached(redis.set_ex(&key, tag, PREFERRED_LANG_CACHE_TTL_SECS)).await;
+    Ok(lang)
+}
+
+pub async fn evict_preferred_lang(mut redis: ConnectionManager, user_id: i64) {
+    redis_health::cached(redis.del(gen_preferred_lang_cache_key(user_id))).await;
+}
+
+/// Same as [email_lang] but by the address, the default for the addresses not registered
+pub async fn email_lang_by_address(pool: &PgPool, email: &str) -> sqlx::Result<Lang> {
+    Ok(UserDao::get_by_email(pool, email).await?.map(|x| email_lang(&x)).unwrap_or_default())
+}
+
+/// Same as [email_lang_by_address] for all the addresses in one query, keyed by the address
+pub async fn email_langs_by_addresses(pool: &PgPool, emails: &[String]) -> sqlx::Result<HashMap<String, Lang>> {
+    let users = UserDao::list_by_emails(pool, emails).await?;
+    let mut result: HashMap<String, Lang> = users.into_iter().map(|x| (x.email.clone(), email_lang(&x))).collect();
+    for email in emails {
+        result.entry(email.clone()).or_default();
+    }
+    Ok(result)
+}
@@ -1,6 +1,25 @@
+use crate::service::test_mode;
 use rand::Rng;
 use redis::aio::ConnectionManager;
 use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+
+/// The codes sent per hour across the instances, on top of the 60 seconds interval of an address.
+/// It's configured by `rate_limits.email_code`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailCodeLimit {
+    /// To an address
+    pub per_email_per_hour: i64,
+    /// From an IP, to any addresses
+    pub per_ip_per_hour: i64,
+}
+
+impl Default for EmailCodeLimit {
+    fn default() -> Self {
+        EmailCodeLimit { per_email_per_hour: 10, per_ip_per_hour: 30 }
+    }
+}
 
 /// Automatically:
 /// - increase the retry counter when error
@@ -39,6 +58,28 @@ pub async fn set_limit_nx(conn: &mut ConnectionManager, email: &str) -> anyhow::
     Ok(limit_absent)
 }
 
+/// Count a code requested to the address from the IP, returns true if either is over the limit of the hour.
+///
+/// Nothing is limited in the test mode, since the integration tests register many users from the same IP.
+pub async fn hourly_limited(conn: &mut ConnectionManager, email: &str, ip: &str, limit: &EmailCodeLimit) -> anyhow::Result<bool> {
+    if test_mode::is_enabled() {
+        return Ok(false);
+    }
+    let email_key = format!("email_code:hourly:email:{}", email.trim().to_lowercase());
+    let ip_key = format!("email_code:hourly:ip:{}", ip);
+    // The expiration is set with the counter created, so a counter never outlives the hour
+    let window = || SetOptions::default().conditional_set(ExistenceCheck::NX).with_expiration(SetExpiry::EX(3600));
+    let (email_count, ip_count): (i64, i64) = redis::pipe()
+        .atomic()
+        .set_options(&email_key, 0, window()).ignore()
+        .incr(&email_key, 1)
+        .set_options(&ip_key, 0, window()).ignore()
+        .incr(&ip_key, 1)
+        .query_async(conn)
+        .await?;
+    Ok(email_count > limit.per_email_per_hour || ip_count > limit.per_ip_per_hour)
+}
+
 pub async fn set_code(conn: &mut ConnectionManager, email: &str, code: &str) -> anyhow::Result<()> {
     let key = get_verify_code_key(email);
     let _: () = conn.set_ex(key, code, 300).await?;
@@ -0,0 +1,198 @@
+//! The official weekly selection, a public playlist of the official account published every Monday by [PUBLISH_JOB].
+//!
+//! The songs picked by the contributors into the [COLLECTION_WEEKLY_PICKS] collection by `/admin/featured/add` go
+//! first, then the hot songs of the past week fill the rest. The picks are cleared once published, and the
+//! playlists of the previous weeks are kept as the archive.
+
+use crate::config::Config;
+use crate::db::featured_song::{FeaturedSongDao, IFeaturedSongDao, COLLECTION_WEEKLY_PICKS};
+use crate::db::playlist::{IPlaylistDao, Playlist, PlaylistDao, PlaylistSong};
+use crate::db::user::UserDao;
+use crate::db::weekly_selection::{IWeeklySelectionDao, WeeklySelection, WeeklySelectionDao};
+use crate::db::CrudDao;
+use crate::search;
+use crate::service::{playlist, recommend_v2, song};
+use crate::util::fractional_index;
+use crate::util::scheduler::{Job, Scheduler};
+use crate::web::state::AppState;
+use chrono::{Datelike, NaiveDate, Utc};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Optional `weekly_selection` section of the config file, nothing is published without `official_uid`
+///
+/// ```yaml
+/// weekly_selection:
+///   official_uid: 100000
+///   name: 每周精选
+///   size: 30
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeeklySelectionCfg {
+    /// The account publishing the playlists
+    #[serde(default)]
+    pub official_uid: Option<i64>,
+    /// The name of the playlists, followed by the date of the week
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// The songs in a playlist, including the picks
+    #[serde(default = "default_size")]
+    pub size: usize,
+}
+
+fn default_name() -> String { "Weekly Selection".to_string() }
+
+fn default_size() -> usize { 30 }
+
+impl Default for WeeklySelectionCfg {
+    fn default() -> Self {
+        WeeklySelectionCfg { official_uid: None, name: default_name(), size: default_size() }
+    }
+}
+
+impl WeeklySelectionCfg {
+    /// Load the `weekly_selection` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("weekly_selection")?.is_some() {
+            config.get_and_parse("weekly_selection")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+pub const PUBLISH_JOB: Job = Job {
+    name: "publish_weekly_selection",
+    schedule: "0 10 * * 1",
+    max_jitter: Duration::from_secs(60),
+};
+
+/// The Monday of the week of the date
+pub fn week_start_of(date: NaiveDate) -> NaiveDate {
+    date - chrono::Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+/// The picks in order, then the hot songs not picked, up to `size`
+pub fn assemble(picks: &[i64], hot: &[i64], size: usize) -> Vec<i64> {
+    picks.iter().chain(hot)
+        .copied()
+        .unique()
+        .take(size)
+        .collect()
+}
+
+/// Publish the selection of the current week, returns the playlist id, or `None` if it's published already
+pub async fn publish(state: &AppState, cfg: &WeeklySelectionCfg) -> anyhow::Result<Option<i64>> {
+    publish_week(state, cfg, week_start_of(Utc::now().date_naive())).await
+}
+
+/// Publish the selection of the week starting on the Monday, see [publish]
+pub async fn publish_week(state: &AppState, cfg: &WeeklySelectionCfg, week_start: NaiveDate) -> anyhow::Result<Option<i64>> {
+    let Some(official_uid) = cfg.official_uid else {
+        warn!("Skipped the weekly selection since `weekly_selection.official_uid` is not configured");
+        return Ok(None);
+    };
+    if UserDao::get_by_id(&state.sql_pool, official_uid).await?.is_none() {
+        anyhow::bail!("The official account {official_uid} of the weekly selection is not found");
+    }
+    let now = Utc::now();
+    // The job runs at least once
+    if WeeklySelectionDao::get_by_week(&state.sql_pool, week_start).await?.is_some() {
+        return Ok(None);
+    }
+
+    let picks = FeaturedSongDao::list_by_collection(&state.sql_pool, COLLECTION_WEEKLY_PICKS).await?;
+    let hot = recommend_v2::get_hot_songs(&state.redis_conn, &state.sql_pool, 7, cfg.size as i64).await?;
+    let candidates = assemble(
+        &picks.iter().map(|x| x.song_id).collect_vec(),
+        &hot.iter().map(|x| x.id).collect_vec(),
+        // Some picks may be deleted or hidden meanwhile
+        cfg.size + picks.len(),
+    );
+    // Only the songs visible now
+    let visible = song::get_public_detail_with_cache(state.redis_conn.clone(), &state.sql_pool, &candidates).await?;
+    let song_ids = candidates.into_iter().filter(|x| visible.contains_key(x)).take(cfg.size).collect_vec();
+    if song_ids.is_empty() {
+        warn!("Skipped the weekly selection of {week_start} since there are no songs");
+        return Ok(None);
+    }
+
+    let mut tx = state.sql_pool.begin().await?;
+    let playlist_id = PlaylistDao::insert(&mut *tx, &Playlist {
+        id: 0,
+        name: format!("{} {}", cfg.name, week_start),
+        description: None,
+        user_id: official_uid,
+        cover_url: None,
+        is_public: true,
+        create_time: now,
+        update_time: now,
+        use_song_cover: true,
+        version: 0,
+    }).await?;
+    for (song_id, sort_key) in song_ids.iter().zip(fractional_index::normalized_keys(song_ids.len())) {
+        PlaylistDao::add_song(&mut *tx, &PlaylistSong {
+            playlist_id,
+            song_id: *song_id,
+            sort_key,
+            add_time: now,
+        }).await?;
+    }
+    if !WeeklySelectionDao::insert(&mut *tx, &WeeklySelection { week_start, playlist_id, create_time: now }).await? {
+        // Published by another instance meanwhile
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    for pick in &picks {
+        FeaturedSongDao::remove(&mut *tx, COLLECTION_WEEKLY_PICKS, pick.song_id).await?;
+    }
+    tx.commit().await?;
+    info!("Published the weekly selection of {week_start} with {} songs and {} picks", song_ids.len(), picks.len());
+
+    playlist::spawn_refresh_song_cover(state, playlist_id);
+    search::playlist::add_or_replace_document(&state.meilisearch, &state.sql_pool, &[playlist_id]).await?;
+    Ok(Some(playlist_id))
+}
+
+/// Publish the selection weekly until cancelled
+pub async fn run_publish(state: AppState, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    let cfg = WeeklySelectionCfg::load(&state.config)?;
+    let scheduler = Scheduler::from_state(&state)?;
+    let handle = scheduler.spawn(
+        &PUBLISH_JOB,
+        cancel_token,
+        move || {
+            let state = state.clone();
+            let cfg = cfg.clone();
+            async move {
+                publish(&state, &cfg).await?;
+                Ok(())
+            }
+        },
+    )?;
+    handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_week_start_of() {
+        let monday = NaiveDate::from_ymd_opt(2026, 5, 4).unwrap();
+        assert_eq!(monday, week_start_of(monday));
+        assert_eq!(monday, week_start_of(NaiveDate::from_ymd_opt(2026, 5, 10).unwrap()));
+        assert_eq!(monday + chrono::Days::new(7), week_start_of(NaiveDate::from_ymd_opt(2026, 5, 11).unwrap()));
+    }
+
+    #[test]
+    fn test_assemble() {
+        assert_eq!(vec![3, 1, 2, 4], assemble(&[3, 1], &[1, 2, 3, 4, 5], 4));
+        assert_eq!(vec![3, 1], assemble(&[3, 1], &[], 4));
+        assert_eq!(vec![1], assemble(&[1, 2], &[3], 1));
+    }
+}
@@ -0,0 +1,175 @@
+use metrics::{counter, gauge};
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// A circuit breaker for the external services without a health check.
+///
+/// The circuit opens after `failure_threshold` consecutive failures, and the calls are rejected immediately
+/// until `open_duration` passes. After that, a single trial call is let through (half-open),
+/// which closes the circuit on success, or reopens it on failure.
+///
+/// See [crate::util::redis_health] for Redis, which is recovered by a ping watchdog instead.
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    open_duration: Duration,
+    consecutive_failures: AtomicU32,
+    /// Unix millis until which the circuit stays open, 0 if closed
+    open_until: AtomicI64,
+}
+
+impl CircuitBreaker {
+    pub const fn new(name: &'static str, failure_threshold: u32, open_duration: Duration) -> Self {
+        CircuitBreaker {
+            name,
+            failure_threshold,
+            open_duration,
+            consecutive_failures: AtomicU32::new(0),
+            open_until: AtomicI64::new(0),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open_until.load(Ordering::Relaxed) != 0
+    }
+
+    /// Whether a call should be made now, only one caller gets the trial call when half-open
+    fn allow(&self, now: i64) -> bool {
+        let open_until = self.open_until.load(Ordering::Relaxed);
+        if open_until == 0 {
+            return true;
+        }
+        if now < open_until {
+            return false;
+        }
+        // Push the deadline forward so the concurrent callers are still rejected during the trial
+        let next = now + self.open_duration.as_millis() as i64;
+        self.open_until.compare_exchange(open_until, next, Ordering::Relaxed, Ordering::Relaxed).is_ok()
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        if self.open_until.swap(0, Ordering::Relaxed) != 0 {
+            info!("{} recovered, closing the circuit", self.name);
+            gauge!("circuit_breaker_open", "name" => self.name).set(0);
+        }
+    }
+
+    fn record_failure(&self, now: i64) {
+        counter!("circuit_breaker_error_count", "name" => self.name).increment(1);
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.failure_threshold {
+            let next = now + self.open_duration.as_millis() as i64;
+            if self.open_until.swap(next, Ordering::Relaxed) == 0 {
+                error!("{} failed {failures} times in a row, opening the circuit", self.name);
+                counter!("circuit_breaker_trip_count", "name" => self.name).increment(1);
+                gauge!("circuit_breaker_open", "name" => self.name).set(1);
+            }
+        }
+    }
+
+    /// Run the operation with a timeout through the circuit breaker.
+    ///
+    /// Returns `None` if the circuit is open, or the operation failed or timed out,
+    /// and the caller should use its fallback.
+    pub async fn call<T, E: Debug>(
+        &self,
+        timeout: Duration,
+        operation: impl Future<Output = Result<T, E>>,
+    ) -> Option<T> {
+        self.call_classified(timeout, operation, |_| true).await
+    }
+
+    /// Same as [Self::call], but only the errors for which `is_failure` returns true count as failures.
+    ///
+    /// The other errors, e.g. the invalid requests of the clients, prove the service is up, so they neither open
+    /// nor close the circuit.
+    pub async fn call_classified<T, E: Debug>(
+        &self,
+        timeout: Duration,
+        operation: impl Future<Output = Result<T, E>>,
+        is_failure: impl FnOnce(&E) -> bool,
+    ) -> Option<T> {
+        if !self.allow(chrono::Utc::now().timestamp_millis()) {
+            counter!("circuit_breaker_reject_count", "name" => self.name).increment(1);
+            return None;
+        }
+        match tokio::time::timeout(timeout, operation).await {
+            Ok(Ok(x)) => {
+                self.record_success();
+                Some(x)
+            }
+            Ok(Err(e)) if !is_failure(&e) => {
+                warn!("{} call rejected: {:?}", self.name, e);
+                None
+            }
+            Ok(Err(e)) => {
+                warn!("{} call failed: {:?}", self.name, e);
+                self.record_failure(chrono::Utc::now().timestamp_millis());
+                None
+            }
+            Err(_) => {
+                warn!("{} call timed out after {:?}", self.name, timeout);
+                self.record_failure(chrono::Utc::now().timestamp_millis());
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_half_open() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::from_secs(10));
+        assert!(breaker.allow(0));
+        breaker.record_failure(0);
+        assert!(!breaker.is_open());
+        breaker.record_failure(1000);
+        assert!(breaker.is_open());
+        assert!(!breaker.allow(5000));
+
+        // Only one trial call after the open duration
+        assert!(breaker.allow(11000));
+        assert!(!breaker.allow(11001));
+
+        // A failed trial reopens the circuit
+        breaker.record_failure(11500);
+        assert!(!breaker.allow(20000));
+        assert!(breaker.allow(21500));
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow(21501));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_counts_as_failure() {
+        let breaker = CircuitBreaker::new("test", 1, Duration::from_secs(10));
+        let result = breaker.call(Duration::from_millis(10), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok::<_, ()>(1)
+        }).await;
+        assert_eq!(None, result);
+        assert!(breaker.is_open());
+        assert_eq!(None, breaker.call(Duration::from_secs(1), async { Ok::<_, ()>(1) }).await);
+    }
+
+    #[tokio::test]
+    async fn test_classified_errors() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::from_secs(10));
+        let is_failure = |e: &&str| *e != "bad request";
+        for _ in 0..5 {
+            let result = breaker.call_classified(Duration::from_secs(1), async { Err::<i32, _>("bad request") }, is_failure).await;
+            assert_eq!(None, result);
+        }
+        assert!(!breaker.is_open());
+        for _ in 0..2 {
+            breaker.call_classified(Duration::from_secs(1), async { Err::<i32, _>("internal") }, is_failure).await;
+        }
+        assert!(breaker.is_open());
+    }
+}
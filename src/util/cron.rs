@@ -0,0 +1,189 @@
+//! The cron expressions of the scheduled jobs, see [crate::util::scheduler].
+//!
+//! The standard 5 fields `minute hour day-of-month month day-of-week` are supported, each one is `*`, a value, a
+//! range `a-b` or a list of them separated by commas, optionally with a step like `*/15` or `1-5/2`. The day of
+//! week is 0-7 where both 0 and 7 are Sunday. Like the classic cron, if both day fields are restricted, a day
+//! matching either of them matches. The macros `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are
+//! accepted as well.
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Searching the next time stops after this many days, e.g. for `0 0 30 2 *`
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronExpr {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    /// Sunday is 0
+    days_of_week: u64,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+/// Parse a field into a bitmask of the allowed values in `min..=max`
+fn parse_field(field: &str, min: u32, max: u32) -> anyhow::Result<u64> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().with_context(|| format!("Invalid step: {part}"))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("Invalid step: {part}");
+        }
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (a.parse()?, b.parse()?)
+        } else {
+            let value = range.parse().with_context(|| format!("Invalid value: {part}"))?;
+            // `a/n` means from `a` to the max
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            bail!("Out of range {min}-{max}: {part}");
+        }
+        for x in (start..=end).step_by(step as usize) {
+            mask |= 1 << x;
+        }
+    }
+    Ok(mask)
+}
+
+impl FromStr for CronExpr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expanded = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" => "0 0 1 1 *",
+            x => x,
+        };
+        let fields = expanded.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(anyhow!("Expected 5 fields: {s}"));
+        };
+        let mut days_of_week = parse_field(day_of_week, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if days_of_week & 1 << 7 != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days_of_month: parse_field(day_of_month, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            days_of_week,
+            day_of_month_restricted: !day_of_month.starts_with('*'),
+            day_of_week_restricted: !day_of_week.starts_with('*'),
+        })
+    }
+}
+
+impl Display for CronExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl CronExpr {
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let dom = self.days_of_month & 1 << date.day() != 0;
+        let dow = self.days_of_week & 1 << date.weekday().num_days_from_sunday() != 0;
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// The first matching minute after `time` in its time zone, `None` if there is no such time in years.
+    ///
+    /// The local times skipped by the daylight saving time never match, and the repeated ones match once.
+    pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let local = time.naive_local();
+        let mut next = local.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = local + Duration::days(MAX_SEARCH_DAYS);
+        while next < limit {
+            let date = next.date();
+            if self.months & 1 << date.month() == 0 {
+                let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+                next = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(date) {
+                next = date.succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & 1 << next.hour() == 0 {
+                next = next.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & 1 << next.minute() == 0 {
+                next += Duration::minutes(1);
+            } else if let Some(x) = time.timezone().from_local_datetime(&next).earliest().filter(|x| x > time) {
+                return Some(x);
+            } else {
+                next += Duration::minutes(1);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn next(expr: &str, time: &str) -> String {
+        expr.parse::<CronExpr>().unwrap().next_after(&at(time)).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!("* * * * *".parse::<CronExpr>().is_ok());
+        assert!("*/15 0-6/2 1,15 * 1-5".parse::<CronExpr>().is_ok());
+        assert!("@daily".parse::<CronExpr>().is_ok());
+        assert!("* * * *".parse::<CronExpr>().is_err());
+        assert!("60 * * * *".parse::<CronExpr>().is_err());
+        assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+        assert!("5-1 * * * *".parse::<CronExpr>().is_err());
+        assert!("a * * * *".parse::<CronExpr>().is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!("2026-04-30T10:01:00+00:00", next("* * * * *", "2026-04-30T10:00:30Z"));
+        assert_eq!("2026-04-30T10:15:00+00:00", next("*/15 * * * *", "2026-04-30T10:00:00Z"));
+        assert_eq!("2026-05-01T04:00:00+00:00", next("0 4 * * *", "2026-04-30T04:00:00Z"));
+        // 2026-05-04 is a Monday, both 1 and 7 are accepted
+        assert_eq!("2026-05-04T04:00:00+00:00", next("0 4 * * 1", "2026-04-30T10:00:00Z"));
+        assert_eq!("2026-05-03T00:00:00+00:00", next("0 0 * * 7", "2026-04-30T10:00:00Z"));
+        // Either day field matches if both are restricted
+        assert_eq!("2026-05-01T00:00:00+00:00", next("0 0 1 * 1", "2026-04-30T10:00:00Z"));
+        assert_eq!("2027-01-01T00:00:00+00:00", next("@yearly", "2026-04-30T10:00:00Z"));
+        assert_eq!("2028-02-29T00:00:00+00:00", next("0 0 29 2 *", "2026-04-30T10:00:00Z"));
+        assert!("0 0 30 2 *".parse::<CronExpr>().unwrap().next_after(&at("2026-04-30T10:00:00Z")).is_none());
+    }
+
+    #[test]
+    fn test_next_after_time_zone() {
+        let time = at("2026-04-30T10:00:00Z").with_timezone(&chrono_tz::Asia::Shanghai);
+        let next = "0 4 * * *".parse::<CronExpr>().unwrap().next_after(&time).unwrap();
+        assert_eq!("2026-04-30T20:00:00+00:00", next.with_timezone(&Utc).to_rfc3339());
+
+        // 02:30 is skipped when the daylight saving time starts
+        let time = at("2026-03-08T06:00:00Z").with_timezone(&chrono_tz::America::New_York);
+        let next = "30 2 * * *".parse::<CronExpr>().unwrap().next_after(&time).unwrap();
+        assert_eq!("2026-03-09T06:30:00+00:00", next.with_timezone(&Utc).to_rfc3339());
+    }
+}
@@ -0,0 +1,163 @@
+//! Lexicographic sort keys, so an item can be moved or inserted by writing only its own key.
+//!
+//! Keys are base-62 fractions in `(0, 1)` compared bytewise (`COLLATE "C"` in Postgres),
+//! and never end with `0`, so there is always a key between any two keys.
+
+const DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE: usize = 62;
+/// The spacing of the normalized keys
+const NORMALIZED_STEP: u64 = 62 * 62;
+const NORMALIZED_MIN_WIDTH: u32 = 4;
+
+/// Keys longer than this should be normalized
+pub const NORMALIZE_THRESHOLD: usize = 12;
+
+fn digit_of(c: u8) -> u8 {
+    DIGITS.iter().position(|x| *x == c).unwrap_or(0) as u8
+}
+
+fn encode(digits: &[u8]) -> String {
+    digits.iter().map(|x| DIGITS[*x as usize] as char).collect()
+}
+
+fn decode(key: &str) -> Vec<u8> {
+    key.bytes().map(digit_of).collect()
+}
+
+/// The shortest key strictly between `a` and `b`, `None` for `b` means the end.
+///
+/// Returns `None` if there is no key between them, i.e. `a` is not before `b`.
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> Option<Vec<u8>> {
+    if let Some(b) = b {
+        // Keep the common prefix, `a` is padded with zeros
+        let n = b.iter().enumerate()
+            .take_while(|(i, x)| a.get(*i).copied().unwrap_or(0) == **x)
+            .count();
+        if n > 0 {
+            let mut result = b[..n].to_vec();
+            result.extend(midpoint(a.get(n..).unwrap_or_default(), Some(&b[n..]))?);
+            return Some(result);
+        }
+    }
+    let da = a.first().copied().unwrap_or(0) as usize;
+    let db = match b {
+        Some(b) => *b.first()? as usize,
+        None => BASE,
+    };
+    if db <= da {
+        None
+    } else if db - da > 1 {
+        Some(vec![((da + db) / 2) as u8])
+    } else if let Some(b) = b && b.len() > 1 {
+        Some(vec![b[0]])
+    } else {
+        let mut result = vec![da as u8];
+        result.extend(midpoint(a.get(1..).unwrap_or_default(), None)?);
+        Some(result)
+    }
+}
+
+/// The next key after `a`, growing by one digit every 61 appends instead of halving the remaining space
+fn successor(a: &[u8]) -> Vec<u8> {
+    match a.first() {
+        None => vec![1],
+        Some(&x) if (x as usize) < BASE - 1 => vec![x + 1],
+        Some(&x) => {
+            let mut result = vec![x];
+            result.extend(successor(&a[1..]));
+            result
+        }
+    }
+}
+
+/// A key between `before` and `after`, `None` if they are not ordered, e.g. the same key.
+/// Pass `None` as `before` to insert at the start, or `None` as `after` to append.
+pub fn key_between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let a = before.map(decode).unwrap_or_default();
+    let b = after.map(decode);
+    match b {
+        None if !a.is_empty() => Some(encode(&successor(&a))),
+        _ => midpoint(&a, b.as_deref()).map(|x| encode(&x)),
+    }
+}
+
+/// The key to append after `before`, or the first key if `None`
+pub fn key_after(before: Option<&str>) -> String {
+    match before {
+        Some(x) if !x.is_empty() => encode(&successor(&decode(x))),
+        _ => encode(&[BASE as u8 / 2]),
+    }
+}
+
+/// Evenly spaced keys for `n` items, leaving room for the appends after them
+pub fn normalized_keys(n: usize) -> Vec<String> {
+    let mut width = NORMALIZED_MIN_WIDTH;
+    while (n as u64 + 1) * NORMALIZED_STEP >= (BASE as u64).pow(width) {
+        width += 1;
+    }
+    (1..=n as u64)
+        .map(|i| {
+            // Plus one to never end with zero
+            let mut value = i * NORMALIZED_STEP + 1;
+            let mut digits = vec![0u8; width as usize];
+            for x in digits.iter_mut().rev() {
+                *x = (value % BASE as u64) as u8;
+                value /= BASE as u64;
+            }
+            encode(&digits)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_between() {
+        let first = key_between(None, None).unwrap();
+        assert_eq!(first, key_after(None));
+        let second = key_between(Some(&first), None).unwrap();
+        assert_eq!(second, key_after(Some(&first)));
+        assert!(first < second);
+        let mid = key_between(Some(&first), Some(&second)).unwrap();
+        assert!(first < mid && mid < second);
+        let head = key_between(None, Some(&first)).unwrap();
+        assert!(head < first);
+
+        // Not ordered, e.g. read before a concurrent reorder
+        assert_eq!(None, key_between(Some(&mid), Some(&mid)));
+        assert_eq!(None, key_between(Some(&second), Some(&first)));
+        assert_eq!(None, key_between(Some(&first), Some("")));
+        assert_eq!(None, key_between(None, Some("0")));
+
+        // Repeatedly insert at the same place
+        let mut low = first.clone();
+        for _ in 0..200 {
+            let key = key_between(Some(&low), Some(&mid)).unwrap();
+            assert!(low < key && key < mid, "{low} < {key} < {mid}");
+            assert!(!key.ends_with('0'));
+            low = key;
+        }
+    }
+
+    #[test]
+    fn test_appends_grow_slowly() {
+        let keys = normalized_keys(1000);
+        let mut last = keys.last().unwrap().clone();
+        for _ in 0..500 {
+            let key = key_after(Some(&last));
+            assert!(last < key);
+            last = key;
+        }
+        assert!(last.len() < NORMALIZE_THRESHOLD, "{last}");
+    }
+
+    #[test]
+    fn test_normalized_keys() {
+        let keys = normalized_keys(5000);
+        assert!(keys.windows(2).all(|x| x[0] < x[1]));
+        assert!(keys.iter().all(|x| x.len() == keys[0].len() && !x.ends_with('0')));
+        assert_eq!(4, normalized_keys(10)[0].len());
+    }
+}
@@ -2,7 +2,9 @@ use crate::web::result::{CommonError, WebError};
 use crate::{common, err};
 use anyhow::bail;
 use std::collections::HashMap;
+use std::io::Read;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::LazyLock;
 use url::Url;
@@ -10,6 +12,11 @@ use url::Url;
 pub mod gracefully_shutdown;
 pub mod redlock;
 pub mod bilibili;
+pub mod scheduler;
+pub mod cron;
+pub mod redis_health;
+pub mod circuit_breaker;
+pub mod fractional_index;
 
 pub trait IsBlank {
     fn is_blank(&self) -> bool; 
@@ -52,6 +59,44 @@ pub fn convert_ip_to_anonymous_uid(ip: &str) -> anyhow::Result<i64> {
     }
 }
 
+/// Whether it's a valid client fingerprint, which is the hex of a SHA-256 salted by the client
+pub fn is_valid_client_fingerprint(fingerprint: &str) -> bool {
+    fingerprint.len() == 64 && fingerprint.bytes().all(|x| x.is_ascii_hexdigit())
+}
+
+/// The anonymous uid of a client with a fingerprint.
+///
+/// The fingerprint is combined with the network of the IP (/16 for IPv4, /32 for IPv6), so the clients behind the
+/// same NAT are told apart, while a mobile client switching IPs within the carrier network stays the same.
+pub fn convert_fingerprint_to_anonymous_uid(ip: &str, fingerprint: &str) -> anyhow::Result<i64> {
+    let network = if let Ok(ipv4) = Ipv4Addr::from_str(ip) {
+        ipv4.octets()[..2].to_vec()
+    } else if let Ok(ipv6) = Ipv6Addr::from_str(ip) {
+        ipv6.octets()[..4].to_vec()
+    } else {
+        bail!("Invalid IP address format: {ip}")
+    };
+    let mut hasher = openssl::sha::Sha256::new();
+    hasher.update(&network);
+    hasher.update(fingerprint.to_ascii_lowercase().as_bytes());
+    let hash = hasher.finish();
+    Ok(i64::from_be_bytes(hash[..8].try_into()?))
+}
+
+/// The hex of the SHA-256 of the file, read in blocks instead of into memory
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = openssl::sha::Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finish()))
+}
 
 static PLATFORM_HOST_MAP: LazyLock<HashMap<&'static str, Vec<&'static str>>> = LazyLock::new(|| {
     let mut map = HashMap::new();
@@ -85,7 +130,16 @@ pub fn validate_platforms(platform: &str, url: &str) -> Result<bool, WebError<Co
 
 #[cfg(test)]
 mod tests {
-    use crate::util::validate_platforms;
+    use crate::util::{convert_fingerprint_to_anonymous_uid, is_valid_client_fingerprint, sha256_file, validate_platforms};
+
+    #[test]
+    fn test_sha256_file() {
+        let path = std::env::temp_dir().join(format!("hachimi-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"abc").unwrap();
+        let hash = sha256_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad", hash.unwrap());
+    }
 
     #[test]
     fn test_validate_platforms() {
@@ -97,4 +151,25 @@ mod tests {
         assert!(validate_platforms("bilibili", "https://www.youtube.com/watch?v=114514").is_err());
         assert_eq!(validate_platforms("instgram", "https://www.youtube.com/watch?v=114514").unwrap(), false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fingerprint_anonymous_uid() {
+        let a = "a".repeat(64);
+        let b = "B".repeat(64);
+        let b_lower = b.to_lowercase();
+        assert!(is_valid_client_fingerprint(&a));
+        assert!(is_valid_client_fingerprint(&b));
+        assert!(!is_valid_client_fingerprint(&"g".repeat(64)));
+        assert!(!is_valid_client_fingerprint(&"a".repeat(32)));
+
+        let uid = |ip: &str, fp: &str| convert_fingerprint_to_anonymous_uid(ip, fp).unwrap();
+        // Same network
+        assert_eq!(uid("10.1.2.3", &a), uid("10.1.200.4", &a));
+        assert_eq!(uid("2001:db8::1", &a), uid("2001:db8:1::2", &a));
+        assert_eq!(uid("10.1.2.3", &b), uid("10.1.2.3", &b_lower));
+        // Behind the same NAT
+        assert_ne!(uid("10.1.2.3", &a), uid("10.1.2.3", &b));
+        assert_ne!(uid("10.1.2.3", &a), uid("10.2.2.3", &a));
+        assert!(convert_fingerprint_to_anonymous_uid("localhost", &a).is_err());
+    }
+}
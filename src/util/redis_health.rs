@@ -0,0 +1,121 @@
+use metrics::{counter, gauge};
+use redis::aio::ConnectionManager;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Trip after this many consecutive errors
+const FAILURE_THRESHOLD: u32 = 5;
+/// Recover after this many consecutive successful pings
+const RECOVERY_THRESHOLD: u32 = 3;
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// Also applied to the cache operations, so a hanging Redis doesn't hold the requests
+const OPERATION_TIMEOUT: Duration = Duration::from_millis(500);
+
+static DEGRADED: AtomicBool = AtomicBool::new(false);
+static CONSECUTIVE_FAILURES: AtomicU32 = AtomicU32::new(0);
+static CONSECUTIVE_SUCCESSES: AtomicU32 = AtomicU32::new(0);
+
+/// Whether Redis is considered unavailable.
+///
+/// In degraded mode, the read paths skip the caches and read from the database directly,
+/// and the best-effort checks backed by Redis, such as cooldowns, are skipped.
+pub fn is_degraded() -> bool {
+    DEGRADED.load(Ordering::Relaxed)
+}
+
+pub fn record_success() {
+    CONSECUTIVE_FAILURES.store(0, Ordering::Relaxed);
+    let successes = CONSECUTIVE_SUCCESSES.fetch_add(1, Ordering::Relaxed) + 1;
+    if successes >= RECOVERY_THRESHOLD && DEGRADED.swap(false, Ordering::Relaxed) {
+        info!("Redis recovered, leaving degraded mode");
+        gauge!("redis_degraded").set(0);
+    }
+}
+
+pub fn record_failure() {
+    counter!("redis_error_count").increment(1);
+    CONSECUTIVE_SUCCESSES.store(0, Ordering::Relaxed);
+    let failures = CONSECUTIVE_FAILURES.fetch_add(1, Ordering::Relaxed) + 1;
+    if failures >= FAILURE_THRESHOLD && !DEGRADED.swap(true, Ordering::Relaxed) {
+        error!("Redis failed {failures} times in a row, entering degraded mode");
+        counter!("redis_degraded_trip_count").increment(1);
+        gauge!("redis_degraded").set(1);
+    }
+}
+
+/// Run a cache operation through the circuit breaker.
+///
+/// Returns `None` if degraded, failed or timed out, and the caller should fall back to the database.
+pub async fn cached<T, E: Debug>(operation: impl Future<Output = Result<T, E>>) -> Option<T> {
+    if is_degraded() {
+        counter!("redis_degraded_skip_count").increment(1);
+        return None;
+    }
+    match tokio::time::timeout(OPERATION_TIMEOUT, operation).await {
+        Ok(Ok(x)) => {
+            record_success();
+            Some(x)
+        }
+        Ok(Err(e)) => {
+            warn!("Redis operation failed: {:?}", e);
+            record_failure();
+            None
+        }
+        Err(_) => {
+            warn!("Redis operation timed out");
+            record_failure();
+            None
+        }
+    }
+}
+
+/// Ping Redis periodically until cancelled, which is the only way to leave degraded mode
+pub async fn run_watchdog(mut redis: ConnectionManager, cancel_token: CancellationToken) {
+    let mut interval = tokio::time::interval(PING_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    gauge!("redis_degraded").set(0);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = cancel_token.cancelled() => break,
+        }
+        let result = tokio::time::timeout(OPERATION_TIMEOUT, redis::cmd("PING").query_async::<String>(&mut redis)).await;
+        match result {
+            Ok(Ok(_)) => record_success(),
+            Ok(Err(e)) => {
+                warn!("Redis ping failed: {:?}", e);
+                record_failure();
+            }
+            Err(_) => {
+                warn!("Redis ping timed out");
+                record_failure();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_trip_and_recover() {
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(!is_degraded());
+            record_failure();
+        }
+        assert!(is_degraded());
+        assert_eq!(None, cached(async { Ok::<_, redis::RedisError>(1) }).await);
+
+        for _ in 0..RECOVERY_THRESHOLD {
+            assert!(is_degraded());
+            record_success();
+        }
+        assert!(!is_degraded());
+        assert_eq!(Some(1), cached(async { Ok::<_, redis::RedisError>(1) }).await);
+    }
+}
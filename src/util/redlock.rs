@@ -66,9 +66,14 @@ impl RedLock {
 
     /// Spin lock until the lock is acquired or timeout
     pub async fn lock_with_timeout(&self, res_name: &str, timeout: Duration) -> anyhow::Result<Option<RedLockGuard>> {
+        self.lock_with_ttl_timeout(res_name, Duration::from_secs(30), timeout).await
+    }
+
+    /// Spin lock until the lock is acquired or timeout, the lock is released after `ttl` if the holder never unlocks it
+    pub async fn lock_with_ttl_timeout(&self, res_name: &str, ttl: Duration, timeout: Duration) -> anyhow::Result<Option<RedLockGuard>> {
         let start = std::time::Instant::now();
         loop {
-            match self.try_lock(res_name).await? {
+            match self.try_lock_with_ttl(res_name, ttl).await? {
                 Some(guard) => return Ok(Some(guard)),
                 None => {
                     if start.elapsed().as_secs() > timeout.as_secs() {
@@ -82,13 +87,18 @@ impl RedLock {
 
     /// Try to acquire the lock
     pub async fn try_lock(&self, res_name: &str) -> anyhow::Result<Option<RedLockGuard>> {
+        self.try_lock_with_ttl(res_name, Duration::from_secs(30)).await
+    }
+
+    /// Try to acquire the lock, which is released after `ttl` if the holder never unlocks it
+    pub async fn try_lock_with_ttl(&self, res_name: &str, ttl: Duration) -> anyhow::Result<Option<RedLockGuard>> {
         info!("try to acquire the lock: {}", res_name);
         let mut conn = self.inner.redis_conn.clone();
         let lock_name = generate_lock_key(res_name);
         let lock_info = LockInfo::new(&lock_name);
         let opt = SetOptions::default()
             .conditional_set(ExistenceCheck::NX)
-            .with_expiration(SetExpiry::PX(30000));
+            .with_expiration(SetExpiry::PX(ttl.as_millis() as u64));
         let result: Value = conn.set_options(&lock_name, &lock_info.sign, opt).await?;
         match result {
             Value::Okay => {
@@ -0,0 +1,330 @@
+use crate::config::Config;
+use crate::util::cron::CronExpr;
+use crate::util::redlock::RedLock;
+use crate::web::state::AppState;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use metrics::{counter, gauge, histogram};
+use rand::Rng;
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions, Value};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn, Instrument};
+
+/// Identifies this server instance in the claims and metrics, the `HOSTNAME` if present
+pub static INSTANCE_ID: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("HOSTNAME").ok()
+        .filter(|x| !x.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+});
+
+/// The claim of a scheduled time is kept this long after the jitter, so a late instance won't claim it again
+const CLAIM_TTL: Duration = Duration::from_secs(3600);
+/// A run holds the lock of its job at most this long, so a crashed instance only blocks the job for this long
+const RUN_LOCK_TTL: Duration = Duration::from_secs(3600);
+
+/// A periodic job, its schedule and jitter can be overridden by the `scheduler` config
+#[derive(Debug, Clone, Copy)]
+pub struct Job {
+    pub name: &'static str,
+    /// The cron expression in the time zone of the config, see [crate::util::cron]
+    pub schedule: &'static str,
+    /// Each instance waits a random delay up to this after the scheduled time, to spread the load
+    pub max_jitter: Duration,
+}
+
+/// Optional `scheduler` section of the config file, every job runs by its default schedule if it's absent.
+///
+/// ```yaml
+/// scheduler:
+///   # The time zone of the cron expressions, UTC by default
+///   timezone: Asia/Shanghai
+///   jobs:
+///     verify_song_stats:
+///       schedule: "0 4 * * 1"
+///       jitter_secs: 300
+///     warm_hot_cache:
+///       enabled: false
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerCfg {
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub jobs: HashMap<String, JobCfg>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobCfg {
+    pub schedule: Option<String>,
+    pub jitter_secs: Option<u64>,
+    /// Enabled by default
+    pub enabled: Option<bool>,
+}
+
+impl SchedulerCfg {
+    /// Load the `scheduler` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("scheduler")?.is_some() {
+            config.get_and_parse("scheduler")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// The effective definition of the job
+    pub fn resolve(&self, job: &Job) -> anyhow::Result<ResolvedJob> {
+        let timezone = match &self.timezone {
+            Some(x) => x.parse::<Tz>().map_err(|e| anyhow::anyhow!("Invalid time zone {x}: {e}"))?,
+            None => Tz::UTC,
+        };
+        let cfg = self.jobs.get(job.name).cloned().unwrap_or_default();
+        let schedule = cfg.schedule.as_deref().unwrap_or(job.schedule);
+        Ok(ResolvedJob {
+            name: job.name,
+            schedule: schedule.parse().with_context(|| format!("Invalid schedule of job {}", job.name))?,
+            timezone,
+            max_jitter: cfg.jitter_secs.map(Duration::from_secs).unwrap_or(job.max_jitter),
+            enabled: cfg.enabled.unwrap_or(true),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedJob {
+    pub name: &'static str,
+    pub schedule: CronExpr,
+    pub timezone: Tz,
+    pub max_jitter: Duration,
+    pub enabled: bool,
+}
+
+impl ResolvedJob {
+    pub fn next_after(&self, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.next_after(&time.with_timezone(&self.timezone)).map(|x| x.with_timezone(&Utc))
+    }
+}
+
+/// The last run of a job, kept in Redis
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LastRun {
+    pub scheduled_time: DateTime<Utc>,
+    pub start_time: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub instance: String,
+    /// `None` if it succeeded
+    pub error: Option<String>,
+}
+
+/// Runs the periodic jobs by their cron schedules, exactly once per scheduled time across all server instances.
+///
+/// At every scheduled time, each instance waits a random jitter and tries to claim the time with `SET NX` on
+/// `schedule:{job}:{timestamp}`, and only the winner runs the job. The winner also takes the lock of the job, so
+/// a run is skipped if the previous one is still running. The last run is saved in Redis, and an instance
+/// started after a missed scheduled time runs the job at once.
+#[derive(Clone)]
+pub struct Scheduler {
+    redis: ConnectionManager,
+    red_lock: RedLock,
+    cfg: Arc<SchedulerCfg>,
+}
+
+impl Scheduler {
+    pub fn new(redis: ConnectionManager, red_lock: RedLock, cfg: SchedulerCfg) -> Self {
+        Self { redis, red_lock, cfg: Arc::new(cfg) }
+    }
+
+    pub fn from_state(state: &AppState) -> anyhow::Result<Self> {
+        Ok(Self::new(state.redis_conn.clone(), state.red_lock.clone(), SchedulerCfg::load(&state.config)?))
+    }
+
+    pub fn cfg(&self) -> &SchedulerCfg {
+        &self.cfg
+    }
+
+    /// Spawn a task running `job` by its schedule until cancelled, it ends at once if the job is disabled
+    pub fn spawn<F, Fut>(
+        &self,
+        job: &Job,
+        cancel_token: CancellationToken,
+        f: F,
+    ) -> anyhow::Result<JoinHandle<()>>
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let job = self.cfg.resolve(job)?;
+        let scheduler = self.clone();
+        let span = tracing::info_span!("scheduled_job", job = job.name);
+        Ok(tokio::spawn(async move {
+            if !job.enabled {
+                info!("Scheduled job {} is disabled", job.name);
+                return;
+            }
+            info!("Scheduled job {} started on instance {}, schedule: {} ({})", job.name, *INSTANCE_ID, job.schedule, job.timezone);
+            let mut missed = match scheduler.get_missed_time(&job).await {
+                Ok(x) => x,
+                Err(e) => {
+                    warn!("Failed to get the last run of {}: {:?}", job.name, e);
+                    None
+                }
+            };
+            loop {
+                let scheduled_time = match missed.take() {
+                    Some(x) => x,
+                    None => {
+                        let Some(next) = job.next_after(Utc::now()) else {
+                            warn!("Scheduled job {} will never run again", job.name);
+                            break;
+                        };
+                        let wait = (next - Utc::now()).to_std().unwrap_or_default() + random_jitter(job.max_jitter);
+                        tokio::select! {
+                            _ = tokio::time::sleep(wait) => {}
+                            _ = cancel_token.cancelled() => break,
+                        }
+                        next
+                    }
+                };
+                match scheduler.try_claim(job.name, scheduled_time, job.max_jitter + CLAIM_TTL).await {
+                    Ok(true) => scheduler.run(&job, scheduled_time, f()).await,
+                    Ok(false) => debug!("{scheduled_time} of {} has been claimed by another instance", job.name),
+                    Err(e) => warn!("Failed to claim {scheduled_time} of {}: {:?}", job.name, e),
+                }
+            }
+            info!("Scheduled job {} stopped", job.name);
+        }.instrument(span)))
+    }
+
+    /// Try to become the executor of the scheduled time, returns false if another instance has claimed it
+    pub async fn try_claim(&self, job_name: &str, scheduled_time: DateTime<Utc>, ttl: Duration) -> anyhow::Result<bool> {
+        let mut redis = self.redis.clone();
+        let opt = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::PX(ttl.as_millis() as u64));
+        let result: Value = redis.set_options(claim_key(job_name, scheduled_time), INSTANCE_ID.as_str(), opt).await?;
+        Ok(matches!(result, Value::Okay))
+    }
+
+    /// The instance that claimed the scheduled time, if any
+    pub async fn get_executor(&self, job_name: &str, scheduled_time: DateTime<Utc>) -> anyhow::Result<Option<String>> {
+        let mut redis = self.redis.clone();
+        Ok(redis.get(claim_key(job_name, scheduled_time)).await?)
+    }
+
+    pub async fn get_last_run(&self, job_name: &str) -> anyhow::Result<Option<LastRun>> {
+        let mut redis = self.redis.clone();
+        let value: Option<String> = redis.get(last_run_key(job_name)).await?;
+        Ok(value.map(|x| serde_json::from_str(&x)).transpose()?)
+    }
+
+    /// The first scheduled time after the last run if it has passed
+    async fn get_missed_time(&self, job: &ResolvedJob) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let Some(last_run) = self.get_last_run(job.name).await? else {
+            return Ok(None);
+        };
+        Ok(job.next_after(last_run.scheduled_time).filter(|x| *x <= Utc::now()))
+    }
+
+    async fn run(&self, job: &ResolvedJob, scheduled_time: DateTime<Utc>, f: impl Future<Output = anyhow::Result<()>>) {
+        let job_name = job.name;
+        let guard = match self.red_lock.try_lock_with_ttl(&run_lock_key(job_name), RUN_LOCK_TTL).await {
+            Ok(Some(x)) => x,
+            Ok(None) => {
+                counter!("scheduled_job_skipped_count", "job" => job_name).increment(1);
+                warn!("Scheduled job {job_name} is still running, skipped {scheduled_time}");
+                return;
+            }
+            Err(e) => {
+                warn!("Failed to lock the scheduled job {job_name}: {:?}", e);
+                return;
+            }
+        };
+
+        let instance = INSTANCE_ID.clone();
+        let start_time = Utc::now();
+        let start = Instant::now();
+        let result = f.await;
+        drop(guard);
+        histogram!("scheduled_job_duration_seconds", "job" => job_name).record(start.elapsed().as_secs_f64());
+        counter!("scheduled_job_run_count", "job" => job_name, "instance" => instance.clone()).increment(1);
+        gauge!("scheduled_job_last_run_timestamp_seconds", "job" => job_name, "instance" => instance.clone())
+            .set(Utc::now().timestamp_millis() as f64 / 1000.0);
+        if let Err(e) = &result {
+            counter!("scheduled_job_error_count", "job" => job_name).increment(1);
+            warn!("Scheduled job {job_name} failed: {:?}", e);
+        }
+
+        let last_run = LastRun {
+            scheduled_time,
+            start_time,
+            duration_ms: start.elapsed().as_millis() as u64,
+            instance,
+            error: result.err().map(|e| e.to_string()),
+        };
+        if let Err(e) = self.save_last_run(job_name, &last_run).await {
+            warn!("Failed to save the last run of {job_name}: {:?}", e);
+        }
+    }
+
+    async fn save_last_run(&self, job_name: &str, value: &LastRun) -> anyhow::Result<()> {
+        let mut redis = self.redis.clone();
+        let _: () = redis.set(last_run_key(job_name), serde_json::to_string(value)?).await?;
+        Ok(())
+    }
+}
+
+fn random_jitter(max: Duration) -> Duration {
+    Duration::from_millis(rand::rng().random_range(0..=max.as_millis() as u64))
+}
+
+fn claim_key(job_name: &str, scheduled_time: DateTime<Utc>) -> String {
+    format!("schedule:{job_name}:{}", scheduled_time.timestamp())
+}
+
+fn last_run_key(job_name: &str) -> String {
+    format!("schedule:last_run:{job_name}")
+}
+
+fn run_lock_key(job_name: &str) -> String {
+    format!("schedule:running:{job_name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOB: Job = Job {
+        name: "test_job",
+        schedule: "0 4 * * *",
+        max_jitter: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn test_resolve() {
+        let time = DateTime::parse_from_rfc3339("2026-04-30T10:00:00Z").unwrap().with_timezone(&Utc);
+        let job = SchedulerCfg::default().resolve(&JOB).unwrap();
+        assert!(job.enabled);
+        assert_eq!("2026-05-01T04:00:00+00:00", job.next_after(time).unwrap().to_rfc3339());
+
+        let cfg: SchedulerCfg = serde_yaml::from_str(r#"
+            timezone: Asia/Shanghai
+            jobs:
+              test_job:
+                jitter_secs: 5
+                enabled: false
+        "#).unwrap();
+        let job = cfg.resolve(&JOB).unwrap();
+        assert!(!job.enabled);
+        assert_eq!(Duration::from_secs(5), job.max_jitter);
+        assert_eq!("2026-04-30T20:00:00+00:00", job.next_after(time).unwrap().to_rfc3339());
+
+        let cfg: SchedulerCfg = serde_yaml::from_str("jobs: { test_job: { schedule: '0 4 * *' } }").unwrap();
+        assert!(cfg.resolve(&JOB).is_err());
+    }
+}
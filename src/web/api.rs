@@ -0,0 +1,367 @@
+//! The typed manifest of the JSON endpoints, pairing each path with its request and response DTOs.
+//!
+//! The integration tests call the endpoints through it, `src/bin/api_manifest.rs` prints it as JSON with the
+//! JSON schemas of the DTOs, and [api_codegen](super::api_codegen) generates the Rust client crate and the
+//! TypeScript types in `clients/` from it. Add the endpoint here when adding a JSON route used by the clients,
+//! derive [JsonSchema] for its DTOs, and run `cargo gen-api-client`.
+
+use crate::db::review_guideline_snippet::ReviewGuidelineSnippet;
+use crate::web::pagination::{CursorQuery, PageQuery};
+use crate::web::routes::publish::{self, crew, jmid, review, template};
+use crate::web::routes::{admin, auth, bootstrap, contributor, events, legal, notification, onboarding, play_history, player, playlist, post, search, song, song_comment, test_mode, user, version};
+use schemars::generate::SchemaSettings;
+use schemars::{JsonSchema, Schema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    /// The request is sent as the query
+    Get,
+    /// The request is sent as the JSON body
+    Post,
+}
+
+pub trait Endpoint {
+    const METHOD: HttpMethod;
+    /// The full path under `/api`
+    const PATH: &'static str;
+    type Req: Serialize + DeserializeOwned + JsonSchema;
+    type Resp: Serialize + DeserializeOwned + JsonSchema;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointInfo {
+    pub name: String,
+    pub method: HttpMethod,
+    pub path: String,
+    /// The fully qualified type name, `()` if there is no request
+    pub req: String,
+    pub resp: String,
+    /// The JSON schema of the request, referring to the [ApiManifest::definitions]
+    pub req_schema: Schema,
+    pub resp_schema: Schema,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiManifest {
+    pub endpoints: Vec<EndpointInfo>,
+    /// The schemas of the DTOs by name, so the `#/$defs/<name>` references resolve against the manifest itself
+    #[serde(rename = "$defs")]
+    pub definitions: serde_json::Map<String, serde_json::Value>,
+}
+
+macro_rules! endpoints {
+    ($($name:ident: $method:ident $path:literal, $req:ty => $resp:ty;)*) => {
+        $(
+            pub struct $name;
+
+            impl Endpoint for $name {
+                const METHOD: HttpMethod = HttpMethod::$method;
+                const PATH: &'static str = $path;
+                type Req = $req;
+                type Resp = $resp;
+            }
+        )*
+
+        pub fn manifest() -> ApiManifest {
+            let mut generator = SchemaSettings::draft2020_12().into_generator();
+            let endpoints = vec![$(
+                EndpointInfo {
+                    name: stringify!($name).to_string(),
+                    method: HttpMethod::$method,
+                    path: $path.to_string(),
+                    req: std::any::type_name::<$req>().to_string(),
+                    resp: std::any::type_name::<$resp>().to_string(),
+                    req_schema: generator.subschema_for::<$req>(),
+                    resp_schema: generator.subschema_for::<$resp>(),
+                },
+            )*];
+            ApiManifest { endpoints, definitions: generator.take_definitions(true) }
+        }
+    };
+}
+
+endpoints! {
+    AuthSendEmailCode: Post "/auth/send_email_code", auth::SendVerificationReq => ();
+    AuthResendEmailCode: Post "/auth/resend_email_code", auth::SendVerificationReq => ();
+    AuthRegisterEmail: Post "/auth/register/email", auth::EmailRegisterReq => auth::EmailRegisterResp;
+    AuthLoginEmail: Post "/auth/login/email", auth::LoginReq => auth::LoginResp;
+    AuthLoginMagicLinkRequest: Post "/auth/login/magic_link/request", auth::MagicLinkRequestReq => ();
+    AuthLoginMagicLinkVerify: Post "/auth/login/magic_link/verify", auth::MagicLinkVerifyReq => auth::LoginResp;
+    AuthOAuthAuthorize: Get "/auth/oauth/authorize", auth::OAuthAuthorizeReq => auth::OAuthAuthorizeResp;
+    AuthOAuthCallback: Post "/auth/oauth/callback", auth::OAuthCallbackReq => auth::OAuthLoginResp;
+    AuthOAuthTwoFactor: Post "/auth/oauth/2fa", auth::OAuthTwoFactorReq => auth::OAuthLoginResp;
+    AuthTwoFactorEnroll: Post "/auth/2fa/enroll", () => auth::TwoFactorEnrollResp;
+    AuthTwoFactorConfirm: Post "/auth/2fa/confirm", auth::TwoFactorConfirmReq => auth::TwoFactorConfirmResp;
+    AuthTwoFactorDisable: Post "/auth/2fa/disable", auth::TwoFactorDisableReq => ();
+    AuthQrCreate: Post "/auth/qr/create", auth::QrCreateReq => auth::QrCreateResp;
+    AuthQrScan: Post "/auth/qr/scan", auth::QrScanReq => auth::QrScanResp;
+    AuthQrApprove: Post "/auth/qr/approve", auth::QrApproveReq => auth::QrApproveResp;
+    AuthQrPoll: Post "/auth/qr/poll", auth::QrPollReq => auth::QrPollResp;
+    AuthRefreshToken: Post "/auth/refresh_token", auth::RefreshTokenReq => auth::TokenPair;
+    AuthResetPassword: Post "/auth/reset_password", auth::ResetPasswordReq => ();
+    AuthDeviceList: Get "/auth/device/list", () => auth::DeviceListResp;
+    AuthDeviceLogout: Post "/auth/device/logout", auth::DeviceLogoutReq => ();
+    AuthDeviceRename: Post "/auth/device/rename", auth::DeviceRenameReq => ();
+    AuthDeviceTrust: Post "/auth/device/trust", auth::DeviceTrustReq => auth::DeviceTrustResp;
+    AuthDeviceUntrust: Post "/auth/device/untrust", auth::DeviceLogoutReq => ();
+    AuthCaptchaGenerate: Get "/auth/captcha/generate", () => auth::GenerateCaptchaResp;
+    AuthCaptchaSubmit: Post "/auth/captcha/submit", auth::SubmitCaptchaReq => ();
+    AuthProtected: Get "/auth/protected", () => ();
+
+    UserProfile: Get "/user/profile", user::GetProfileReq => user::PublicUserProfile;
+    UserGreet: Get "/user/greet", () => String;
+    UserUpdateProfile: Post "/user/update_profile", user::UpdateProfileReq => ();
+    UserSearch: Get "/user/search", user::SearchReq => user::SearchResp;
+    UserLinkedAccounts: Get "/user/linked_accounts", () => user::LinkedAccountsResp;
+    UserUnlinkAccount: Post "/user/unlink_account", user::UnlinkAccountReq => ();
+    UserLinkAccountAuthorize: Get "/user/link_account/authorize", auth::OAuthAuthorizeReq => auth::OAuthAuthorizeResp;
+    UserLinkAccount: Post "/user/link_account", user::LinkAccountReq => ();
+    UserStorageUsage: Get "/user/storage_usage", () => user::StorageUsageResp;
+    UserFollow: Post "/user/follow", user::FollowReq => ();
+    UserUnfollow: Post "/user/unfollow", user::FollowReq => ();
+    UserFollowers: Get "/user/followers", user::PageFollowQuery => user::PageFollowResp;
+    UserFollowing: Get "/user/following", user::PageFollowQuery => user::PageFollowResp;
+    UserReleaseChannel: Get "/user/release_channel", () => user::ReleaseChannelData;
+    UserSetReleaseChannel: Post "/user/set_release_channel", user::ReleaseChannelData => ();
+    UserLanguage: Get "/user/language", () => user::LanguageData;
+    UserSetLanguage: Post "/user/set_language", user::LanguageData => ();
+    UserSetSupportLinks: Post "/user/set_support_links", user::SetSupportLinksReq => ();
+    UserPreferences: Get "/user/preferences", () => user::PreferencesResp;
+    UserConnectionList: Get "/user/connection/list", () => user::ConnectionResp;
+    UserConnectionUnlink: Post "/user/connection/unlink", user::ConnectionUnlinkReq => ();
+    UserConnectionSetVisibility: Post "/user/connection/set_visibility", user::ConnectionSetVisibilityReq => ();
+    UserConnectionSync: Post "/user/connection/sync", user::ConnectionSyncReq => ();
+    UserConnectionGenerateChallenge: Post "/user/connection/generate_challenge", user::GenerateChallengeReq => user::GenerateChallengeResp;
+    UserConnectionVerifyChallenge: Post "/user/connection/verify_challenge", user::VerifyChallengeReq => ();
+    UserNotificationPage: Get "/user/notifications/page", notification::PageNotificationQuery => notification::PageNotificationResp;
+    UserNotificationMarkRead: Post "/user/notifications/mark_read", notification::MarkReadReq => notification::MarkReadResp;
+    UserNotificationUnreadCount: Get "/user/notifications/unread_count", () => notification::UnreadCountResp;
+    EventsTicket: Post "/events/ticket", () => events::TicketResp;
+    OnboardingStarter: Get "/onboarding/starter", () => onboarding::StarterResp;
+    OnboardingInterests: Post "/onboarding/interests", onboarding::InterestsReq => onboarding::InterestsResp;
+
+    SongDetail: Get "/song/detail", song::DetailReq => song::DetailResp;
+    SongDetailById: Get "/song/detail_by_id", song::DetailByIdReq => song::DetailResp;
+    SongPageByUser: Get "/song/page_by_user", song::PageByUserQuery => song::PageByUserResp;
+    SongPublish: Post "/song/publish", publish::PublishReq => publish::PublishResp;
+    SongReviewHistory: Get "/song/review_history", review::SongReviewHistoryReq => review::SongReviewHistoryResp;
+    SongPlay: Post "/song/play", play_history::TouchReq => ();
+    SongShare: Post "/song/share", song::ShareReq => ();
+    SongReport: Post "/song/report", song::ReportSongReq => song::ReportSongResp;
+    SongDelete: Post "/song/delete", publish::DeleteReq => ();
+    SongLikesLike: Post "/song/likes/like", song::LikeReq => song::LikeResp;
+    SongLikesUnlike: Post "/song/likes/unlike", song::UnlikeReq => song::LikeResp;
+    SongLikesStatus: Get "/song/likes/status", song::LikeStatusReq => song::LikeStatusResp;
+    SongLikesPageMyLikes: Get "/song/likes/page_my_likes", PageQuery => song::MyLikesResp;
+    PlayHistoryCursor: Get "/play_history/cursor", CursorQuery => play_history::CursorResp;
+    PlayHistoryTouch: Post "/play_history/touch", play_history::TouchReq => ();
+    PlayHistoryTouchAnonymous: Post "/play_history/touch_anonymous", play_history::TouchReq => ();
+    PlayHistoryDelete: Post "/play_history/delete", play_history::DeleteReq => ();
+    PlayerQueueGet: Get "/player/queue/get", () => player::GetQueueResp;
+    PlayerQueueSet: Post "/player/queue/set", player::SetQueueReq => player::SetQueueResp;
+    LegalLatest: Get "/legal/latest", () => legal::LatestResp;
+    LegalAccept: Post "/legal/accept", legal::AcceptReq => ();
+    SongUploadPresign: Post "/song/upload/presign", publish::PresignAudioUploadReq => publish::PresignAudioUploadResp;
+    SongUploadConfirm: Post "/song/upload/confirm", publish::ConfirmAudioUploadReq => publish::UploadAudioFileResp;
+    SongCommentCreate: Post "/song/comment/create", song_comment::CreateCommentReq => song_comment::CreateCommentResp;
+    SongCommentReply: Post "/song/comment/reply", song_comment::ReplyCommentReq => song_comment::CreateCommentResp;
+    SongCommentDelete: Post "/song/comment/delete", song_comment::CommentIdReq => ();
+    SongCommentReport: Post "/song/comment/report", song_comment::ReportCommentReq => ();
+    SongCommentPage: Get "/song/comment/page", song_comment::PageCommentQuery => song_comment::PageCommentResp;
+    SongRecentV2: Get "/song/recent_v2", song::RecentReq => song::RecentResp;
+    SongRecommend: Get "/song/recommend", () => song::RecommendResp;
+    SongRecommendAnonymous: Get "/song/recommend_anonymous", () => song::RecommendResp;
+    SongHotWeekly: Get "/song/hot/weekly", () => song::HotResp;
+    SongSearch: Get "/song/search", song::SearchReq => song::SearchResp;
+    SongSearchSuggest: Get "/song/search/suggest", song::SearchSuggestReq => song::SearchSuggestResp;
+    SearchFeedback: Post "/search/feedback", search::SearchFeedbackReq => ();
+    SongNotInterested: Post "/song/not_interested", song::NotInterestedReq => ();
+    SongTagCreate: Post "/song/tag/create", song::TagCreateReq => song::TagCreateResp;
+    SongTagSearch: Get "/song/tag/search", song::TagSearchReq => song::TagSearchResp;
+    SongTagRecommend: Get "/song/tag/recommend", () => song::TagRecommendResp;
+    SongTagRecommendAnonymous: Get "/song/tag/recommend_anonymous", () => song::TagRecommendResp;
+
+    PlaylistCreate: Post "/playlist/create", playlist::CreatePlaylistReq => playlist::CreatePlaylistResp;
+    PlaylistList: Get "/playlist/list", () => playlist::ListResp;
+    PlaylistDetail: Get "/playlist/detail", playlist::DetailReq => playlist::DetailResp;
+    PlaylistDetailPrivate: Get "/playlist/detail_private", playlist::DetailReq => playlist::DetailResp;
+    PlaylistDetailPublic: Get "/playlist/detail_public", playlist::DetailReq => playlist::DetailResp;
+    PlaylistListPublicByUser: Get "/playlist/list_public_by_user", playlist::ListPublicByUserReq => playlist::ListPublicByUserResp;
+    PlaylistPageByUser: Get "/playlist/page_by_user", playlist::PageByUserQuery => playlist::PageByUserResp;
+    PlaylistUpdate: Post "/playlist/update", playlist::UpdatePlaylistReq => ();
+    PlaylistDelete: Post "/playlist/delete", playlist::DeletePlaylistReq => ();
+    PlaylistListContaining: Get "/playlist/list_containing", playlist::ListContainingReq => playlist::ListContainingResp;
+    PlaylistAddSong: Post "/playlist/add_song", playlist::AddSongReq => playlist::AddSongResp;
+    PlaylistAddSongs: Post "/playlist/add_songs", playlist::AddSongsReq => playlist::AddSongsResp;
+    PlaylistRemoveSong: Post "/playlist/remove_song", playlist::RemoveSongReq => playlist::RemoveSongResp;
+    PlaylistChangeOrder: Post "/playlist/change_order", playlist::ChangeOrderReq => ();
+    PlaylistSearch: Get "/playlist/search", playlist::SearchReq => playlist::SearchResp;
+    PlaylistFavoriteAdd: Post "/playlist/favorite/add", playlist::AddFavoriteReq => ();
+    PlaylistFavoriteRemove: Post "/playlist/favorite/remove", playlist::RemoveFavoriteReq => ();
+    PlaylistFavoriteCheck: Get "/playlist/favorite/check", playlist::CheckFavoriteReq => playlist::CheckFavoriteResp;
+    PlaylistFavoritePage: Get "/playlist/favorite/page", PageQuery => playlist::PageFavoritesResp;
+    PlaylistOfficialWeekly: Get "/playlist/official/weekly", playlist::OfficialWeeklyReq => playlist::OfficialWeeklyResp;
+    PlaylistOfficialWeeklyArchive: Get "/playlist/official/weekly/archive", PageQuery => playlist::OfficialWeeklyArchiveResp;
+
+    PublishJmidCheckPrefix: Get "/publish/jmid/check_prefix", jmid::JmidCheckPReq => jmid::JmidCheckPResp;
+    PublishJmidGetNext: Get "/publish/jmid/get_next", () => jmid::JmidGetNextResp;
+    PublishJmidCheck: Get "/publish/jmid/check", jmid::JmidCheckReq => jmid::JmidCheckResp;
+    PublishJmidMine: Get "/publish/jmid/mine", () => jmid::JmidMineResp;
+    PublishPublish: Post "/publish/publish", publish::PublishReq => publish::PublishResp;
+    PublishModify: Post "/publish/modify", publish::ModifyReq => publish::ModifyResp;
+    PublishDelete: Post "/publish/delete", publish::DeleteReq => ();
+    PublishChangeJmid: Post "/publish/change_jmid", publish::ChangeJmidReq => ();
+    PublishExportTemplate: Get "/publish/export_template", template::ExportTemplateReq => template::PublishTemplate;
+    PublishAudioChunkCreate: Post "/publish/upload_audio_chunk/create", publish::CreateChunkedUploadReq => publish::CreateChunkedUploadResp;
+    PublishAudioChunkStatus: Get "/publish/upload_audio_chunk/status", publish::ChunkedUploadReq => publish::ChunkedUploadStatusResp;
+    PublishAudioChunkComplete: Post "/publish/upload_audio_chunk/complete", publish::ChunkedUploadReq => publish::UploadAudioFileResp;
+    PublishAudioChunkAbort: Post "/publish/upload_audio_chunk/abort", publish::ChunkedUploadReq => ();
+    PublishReviewDashboard: Get "/publish/review/dashboard", () => review::DashboardResp;
+    PublishReviewPage: Get "/publish/review/page", PageQuery => publish::PageResp;
+    PublishReviewPageContributor: Get "/publish/review/page_contributor", PageQuery => publish::PageResp;
+    PublishReviewCursorContributor: Get "/publish/review/cursor_contributor", review::CursorContributorQuery => review::CursorContributorResp;
+    PublishReviewDetail: Get "/publish/review/detail", review::DetailReq => review::DetailResp;
+    PublishReviewGuidelineList: Get "/publish/review/guideline/list", () => review::GuidelineListResp;
+    PublishReviewApprove: Post "/publish/review/approve", review::ApproveReviewReq => ();
+    PublishReviewReject: Post "/publish/review/reject", review::RejectReviewReq => ();
+    PublishReviewModify: Post "/publish/review/modify", review::ReviewModifyReq => ();
+    PublishReviewCommentCreate: Post "/publish/review/comment/create", review::ReviewCommentCreateReq => review::ReviewCommentCreateResp;
+    PublishReviewCommentDelete: Post "/publish/review/comment/delete", review::ReviewCommentDeleteReq => ();
+    PublishReviewCommentList: Get "/publish/review/comment/list", review::ReviewCommentListQuery => review::ReviewCommentListResp;
+    PublishReviewHistoryList: Get "/publish/review/history/list", review::ReviewHistoryListQuery => review::ReviewHistoryListResp;
+    PublishCrewInvitations: Get "/publish/crew/invitations", () => crew::CrewInvitationsResp;
+    PublishCrewConfirm: Post "/publish/crew/confirm", crew::CrewRespondReq => ();
+    PublishCrewDecline: Post "/publish/crew/decline", crew::CrewRespondReq => ();
+
+    PostPage: Get "/post/page", PageQuery => post::PageResp;
+    PostDetail: Get "/post/detail", post::PostIdReq => post::PostItem;
+    PostCreate: Post "/post/create", post::CreateReq => post::CreateResp;
+    PostEdit: Post "/post/edit", post::EditReq => post::EditResp;
+    PostDelete: Post "/post/delete", post::PostIdReq => ();
+
+    ContributorNotificationPreview: Get "/contributor/notification/preview", contributor::PreviewNotificationReq => contributor::PreviewNotificationResp;
+    ContributorCheck: Get "/contributor/check", () => contributor::CheckContributorResp;
+
+    AdminUserBan: Post "/admin/user/ban", admin::BanUserReq => ();
+    AdminUserUnban: Post "/admin/user/unban", admin::BanUserReq => ();
+    AdminUserShadowBan: Post "/admin/user/shadow_ban", admin::ShadowBanUserReq => ();
+    AdminUserShadowBanList: Get "/admin/user/shadow_ban/list", () => admin::ListShadowBansResp;
+    AdminUserLegalHold: Post "/admin/user/legal_hold", admin::SetLegalHoldReq => admin::SetLegalHoldResp;
+    AdminUserLegalHoldList: Get "/admin/user/legal_hold/list", () => admin::ListLegalHoldsResp;
+    AdminSongEdit: Post "/admin/song/edit", admin::EditSongReq => ();
+    AdminSongRecount: Post "/admin/song/recount", admin::RecountSongReq => admin::RecountSongResp;
+    AdminSongHide: Post "/admin/song/hide", admin::HideSongReq => admin::HideSongResp;
+    AdminSongReportPage: Get "/admin/song/report/page", admin::PageSongReportsQuery => admin::PageSongReportsResp;
+    AdminSongReportResolve: Post "/admin/song/report/resolve", admin::ResolveSongReportReq => ();
+    AdminFeaturedList: Get "/admin/featured/list", admin::ListFeaturedReq => admin::ListFeaturedResp;
+    AdminFeaturedAdd: Post "/admin/featured/add", admin::AddFeaturedReq => ();
+    AdminFeaturedRemove: Post "/admin/featured/remove", admin::RemoveFeaturedReq => ();
+    AdminSearchStats: Get "/admin/search/stats", admin::SearchStatsReq => admin::SearchStatsResp;
+    AdminGuidelineList: Get "/admin/guideline/list", () => admin::ListGuidelinesResp;
+    AdminGuidelineSave: Post "/admin/guideline/save", admin::SaveGuidelineReq => ReviewGuidelineSnippet;
+    AdminGuidelineVersions: Get "/admin/guideline/versions", admin::GuidelineVersionsReq => admin::GuidelineVersionsResp;
+    AdminSchedulerJobs: Get "/admin/scheduler/jobs", () => admin::SchedulerJobsResp;
+    AdminTagAliasList: Get "/admin/tag/alias/list", () => admin::ListTagAliasesResp;
+    AdminTagAliasAdd: Post "/admin/tag/alias/add", admin::AddTagAliasReq => admin::AddTagAliasResp;
+    AdminTagAliasDelete: Post "/admin/tag/alias/delete", admin::DeleteTagAliasReq => ();
+    AdminRoleGrant: Post "/admin/role/grant", admin::RoleReq => admin::RoleResp;
+    AdminRoleRevoke: Post "/admin/role/revoke", admin::RoleReq => admin::RoleResp;
+    AdminLegalPublish: Post "/admin/legal/publish", admin::PublishLegalReq => admin::PublishLegalResp;
+
+    VersionLatest: Get "/version/latest", version::LatestVersionReq => Option<version::LatestVersionResp>;
+    VersionLatestBatch: Post "/version/latest_batch", version::LatestVersionBatchReq => Vec<version::LatestVersionResp>;
+    VersionLatestBatchV2: Post "/version/latest_batch_v2", version::LatestVersionBatchReq => version::LatestVersionBatchResp;
+    VersionServer: Get "/version/server", () => version::ServerVersion;
+    VersionPage: Get "/version/page", version::PageVersionsReq => version::PageVersionsResp;
+    VersionPublish: Post "/version/publish", version::PublishVersionReq => version::PublishVersionResp;
+    VersionDelete: Post "/version/delete", version::DeleteVersionReq => ();
+
+    Bootstrap: Get "/bootstrap", () => bootstrap::BootstrapResp;
+    TestEmails: Get "/test/emails", test_mode::TestEmailsReq => test_mode::TestEmailsResp;
+    TestWeeklySelectionPublish: Post "/test/weekly_selection/publish", test_mode::PublishWeeklySelectionReq => test_mode::PublishWeeklySelectionResp;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service;
+    use crate::web::limits::LimitsCfg;
+    use crate::web::routes;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_manifest_unique() {
+        let manifest = manifest();
+        let mut names = HashSet::new();
+        let mut paths = HashSet::new();
+        for x in &manifest.endpoints {
+            assert!(x.path.starts_with('/'), "{}", x.path);
+            assert!(names.insert(&x.name), "Duplicated endpoint {}", x.name);
+            assert!(paths.insert(&x.path), "Duplicated path {}", x.path);
+        }
+    }
+
+    /// The routes not taking or returning JSON, e.g. the multipart uploads, the provider webhooks and the streams
+    const NON_JSON_ROUTES: &[&str] = &[
+        "/auth/captcha",
+        "/user/set_avatar",
+        "/song/upload_audio_file",
+        "/song/upload_cover_image",
+        "/playlist/set_cover",
+        "/playlist/export",
+        "/publish/upload_audio_file",
+        "/publish/upload_audio_chunk",
+        "/publish/upload_cover_image",
+        "/post/upload_image",
+        "/storage/webhook",
+        "/email/webhook",
+        "/events",
+    ];
+
+    /// The paths of the real API router.
+    ///
+    /// axum has no API listing the routes, but the `Debug` of a router prints the paths of its routes with
+    /// the nested routers already flattened, as `RouteId(0): "/auth/login/email"`.
+    fn router_paths() -> Vec<String> {
+        let router = routes::router(&LimitsCfg::default());
+        let debug = format!("{router:?}");
+        // Leave out the fallback routes added by axum itself
+        let (debug, _) = debug.split_once("fallback_router").unwrap();
+        let regex = regex::Regex::new(r#"RouteId\(\d+\): "([^"]*)""#).unwrap();
+        regex.captures_iter(debug).map(|x| x[1].to_string()).collect()
+    }
+
+    #[test]
+    fn test_manifest_covers_routes() {
+        let manifest = manifest();
+        let paths: HashSet<_> = manifest.endpoints.iter().map(|x| x.path.as_str()).collect();
+
+        let routes = router_paths();
+        assert!(routes.len() > manifest.endpoints.len() / 2, "Too few routes found in the router: {}", routes.len());
+        let routes: HashSet<_> = routes.iter().map(|x| x.strip_suffix('/').unwrap_or(x)).collect();
+
+        let missing: Vec<_> = routes.iter()
+            .filter(|x| !paths.contains(*x) && !NON_JSON_ROUTES.contains(x))
+            .collect();
+        assert!(missing.is_empty(), "Routes missing from the manifest: {missing:?}");
+
+        // The test mode routes are only nested when the test mode is enabled
+        let unrouted: Vec<_> = paths.iter()
+            .filter(|x| !routes.contains(*x) && (service::test_mode::is_enabled() || !x.starts_with("/test/")))
+            .collect();
+        assert!(unrouted.is_empty(), "Endpoints not in the router: {unrouted:?}");
+    }
+
+    #[test]
+    fn test_manifest_schemas_resolve() {
+        let manifest = manifest();
+        let json = serde_json::to_string(&manifest).unwrap();
+        for x in json.split("\"$ref\":\"#/$defs/").skip(1) {
+            let name = &x[..x.find('"').unwrap()];
+            assert!(manifest.definitions.contains_key(name), "Unresolved schema {name}");
+        }
+    }
+}
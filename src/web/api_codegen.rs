@@ -0,0 +1,572 @@
+//! Generate the Rust client crate and the TypeScript types of the clients from the [manifest](super::api::manifest).
+//!
+//! Run `cargo gen-api-client` after changing the endpoints or their DTOs, `test_generated_up_to_date` fails
+//! until the files in `clients/` are regenerated.
+
+use crate::web::api::{ApiManifest, HttpMethod};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// The generated module of the `hachimi-world-api` client crate, relative to the root of the repository
+pub const RUST_CLIENT_PATH: &str = "clients/rust/src/lib.rs";
+/// The generated TypeScript declarations, relative to the root of the repository
+pub const TYPESCRIPT_PATH: &str = "clients/typescript/api.d.ts";
+
+const HEADER: &str = "Generated by `cargo gen-api-client` from the endpoint manifest of the server, don't edit it by hand.";
+
+/// The type of a schema, the schemas not understood here fallback to [Ty::Any]
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Unit,
+    Bool,
+    Int(&'static str),
+    Float(&'static str),
+    String,
+    Any,
+    Ref(String),
+    Option(Box<Ty>),
+    Vec(Box<Ty>),
+    Tuple(Vec<Ty>),
+    Map(Box<Ty>),
+}
+
+struct Field {
+    name: String,
+    doc: Option<String>,
+    ty: Ty,
+    required: bool,
+}
+
+struct Variant {
+    value: String,
+    doc: Option<String>,
+    fields: Vec<Field>,
+}
+
+enum Def {
+    Struct(Vec<Field>),
+    /// Unit variants serialized as strings
+    StringEnum(Vec<Variant>),
+    /// Struct variants tagged by the field
+    Tagged { tag: String, variants: Vec<Variant> },
+    Alias(Ty),
+}
+
+fn doc_of(schema: &Value) -> Option<String> {
+    schema.get("description").and_then(|x| x.as_str()).map(|x| x.to_string())
+}
+
+fn parse_ty(schema: &Value) -> Ty {
+    if let Some(name) = schema.get("$ref").and_then(|x| x.as_str()) {
+        return Ty::Ref(name.trim_start_matches("#/$defs/").to_string());
+    }
+    if let Some(variants) = schema.get("anyOf").and_then(|x| x.as_array()) {
+        let non_null: Vec<_> = variants.iter().filter(|x| x.get("type") != Some(&Value::from("null"))).collect();
+        return match non_null.as_slice() {
+            [x] if non_null.len() < variants.len() => Ty::Option(Box::new(parse_ty(x))),
+            _ => Ty::Any,
+        };
+    }
+    match schema.get("type") {
+        Some(Value::Array(types)) => {
+            let non_null: Vec<_> = types.iter().filter(|x| x.as_str() != Some("null")).collect();
+            match non_null.as_slice() {
+                [x] => {
+                    let mut inner = schema.clone();
+                    inner["type"] = (*x).clone();
+                    let inner = parse_ty(&inner);
+                    if non_null.len() < types.len() { Ty::Option(Box::new(inner)) } else { inner }
+                }
+                _ => Ty::Any,
+            }
+        }
+        Some(Value::String(x)) => {
+            let format = schema.get("format").and_then(|x| x.as_str()).unwrap_or_default();
+            match x.as_str() {
+                "null" => Ty::Unit,
+                "boolean" => Ty::Bool,
+                "string" => Ty::String,
+                "integer" => Ty::Int(match format {
+                    "int8" => "i8",
+                    "int16" => "i16",
+                    "int32" => "i32",
+                    "uint8" => "u8",
+                    "uint16" => "u16",
+                    "uint32" => "u32",
+                    "uint" | "uint64" => "u64",
+                    _ => "i64",
+                }),
+                "number" => Ty::Float(if format == "float" { "f32" } else { "f64" }),
+                "array" => match (schema.get("prefixItems").and_then(|x| x.as_array()), schema.get("items")) {
+                    (Some(items), _) => Ty::Tuple(items.iter().map(parse_ty).collect()),
+                    (None, Some(items)) => Ty::Vec(Box::new(parse_ty(items))),
+                    (None, None) => Ty::Vec(Box::new(Ty::Any)),
+                },
+                "object" => match schema.get("additionalProperties") {
+                    Some(x) if x.is_object() => Ty::Map(Box::new(parse_ty(x))),
+                    _ => Ty::Any,
+                },
+                _ => Ty::Any,
+            }
+        }
+        _ => Ty::Any,
+    }
+}
+
+fn parse_fields(schema: &Value) -> Vec<Field> {
+    let required: Vec<&str> = schema.get("required")
+        .and_then(|x| x.as_array())
+        .map(|x| x.iter().filter_map(|x| x.as_str()).collect())
+        .unwrap_or_default();
+    let Some(properties) = schema.get("properties").and_then(|x| x.as_object()) else {
+        return vec![];
+    };
+    properties.iter()
+        .map(|(name, x)| Field {
+            name: name.clone(),
+            doc: doc_of(x),
+            ty: parse_ty(x),
+            required: required.contains(&name.as_str()),
+        })
+        .collect()
+}
+
+/// The string values of a `{"type": "string", "enum": [...]}` or `{"type": "string", "const": ...}` schema
+fn string_values(schema: &Value) -> Option<Vec<String>> {
+    if let Some(x) = schema.get("const").and_then(|x| x.as_str()) {
+        return Some(vec![x.to_string()]);
+    }
+    schema.get("enum")?.as_array()?.iter().map(|x| x.as_str().map(|x| x.to_string())).collect()
+}
+
+fn parse_def(schema: &Value) -> Def {
+    if let Some(values) = string_values(schema) {
+        return Def::StringEnum(values.into_iter().map(|value| Variant { value, doc: None, fields: vec![] }).collect());
+    }
+    if let Some(items) = schema.get("oneOf").and_then(|x| x.as_array()) {
+        // A unit enum with documented variants, each in its own schema
+        if let Some(variants) = items.iter()
+            .map(|item| string_values(item).map(|values| {
+                let doc = doc_of(item);
+                values.into_iter().map(move |value| Variant { value, doc: doc.clone(), fields: vec![] })
+            }))
+            .collect::<Option<Vec<_>>>()
+        {
+            return Def::StringEnum(variants.into_iter().flatten().collect());
+        }
+
+        // An internally tagged enum, the tag is the field with a const value in every variant
+        let tag = items.first()
+            .and_then(|x| x.get("properties")?.as_object())
+            .and_then(|properties| properties.iter()
+                .map(|(name, _)| name)
+                .find(|name| items.iter().all(|x| x["properties"].get(name.as_str()).and_then(|x| x.get("const")).is_some())));
+        if let Some(tag) = tag {
+            let variants = items.iter()
+                .map(|item| Variant {
+                    value: item["properties"][tag]["const"].as_str().unwrap_or_default().to_string(),
+                    doc: doc_of(item),
+                    fields: parse_fields(item).into_iter().filter(|x| &x.name != tag).collect(),
+                })
+                .collect();
+            return Def::Tagged { tag: tag.clone(), variants };
+        }
+        return Def::Alias(Ty::Any);
+    }
+    if schema.get("type") == Some(&Value::from("object")) && schema.get("additionalProperties").is_none_or(|x| !x.is_object()) {
+        return Def::Struct(parse_fields(schema));
+    }
+    Def::Alias(parse_ty(schema))
+}
+
+fn pascal_case(value: &str) -> String {
+    let name: String = value.split(|x: char| !x.is_ascii_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            let mut chars = x.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            format!("{first}{}", chars.as_str().to_ascii_lowercase())
+        })
+        .collect();
+    if name.starts_with(|x: char| x.is_ascii_digit()) { format!("V{name}") } else { name }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn", "for", "gen",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct",
+    "trait", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// The Rust field name, and whether it needs a `#[serde(rename)]`
+fn rust_field_name(name: &str) -> (String, bool) {
+    if RUST_KEYWORDS.contains(&name) {
+        (format!("r#{name}"), false)
+    } else if name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') && !name.starts_with(|x: char| x.is_ascii_digit()) {
+        (name.to_string(), false)
+    } else {
+        let sanitized: String = name.chars().map(|x| if x.is_ascii_alphanumeric() { x.to_ascii_lowercase() } else { '_' }).collect();
+        (format!("_{}", sanitized.trim_start_matches('_')), true)
+    }
+}
+
+fn rust_ty(ty: &Ty, owner: &str) -> String {
+    match ty {
+        Ty::Unit => "()".to_string(),
+        Ty::Bool => "bool".to_string(),
+        Ty::Int(x) | Ty::Float(x) => x.to_string(),
+        Ty::String => "String".to_string(),
+        Ty::Any => "serde_json::Value".to_string(),
+        // A direct self reference would be infinitely sized
+        Ty::Ref(x) if x == owner => format!("Box<{x}>"),
+        Ty::Ref(x) => x.clone(),
+        Ty::Option(x) => format!("Option<{}>", rust_ty(x, owner)),
+        Ty::Vec(x) => format!("Vec<{}>", rust_ty(x, "")),
+        Ty::Tuple(x) => format!("({})", x.iter().map(|x| rust_ty(x, owner)).collect::<Vec<_>>().join(", ")),
+        Ty::Map(x) => format!("std::collections::HashMap<String, {}>", rust_ty(x, "")),
+    }
+}
+
+fn rust_doc(out: &mut String, indent: &str, doc: Option<&str>) {
+    let Some(doc) = doc else { return };
+    let mut in_code = false;
+    for line in doc.lines() {
+        let mut line = line.to_string();
+        if line.trim_start().starts_with("```") {
+            // The code blocks without a language are doc tests in Rust
+            if !in_code && line.trim() == "```" {
+                line.push_str("text");
+            }
+            in_code = !in_code;
+        }
+        if line.is_empty() {
+            writeln!(out, "{indent}///").unwrap();
+        } else {
+            writeln!(out, "{indent}/// {line}").unwrap();
+        }
+    }
+}
+
+fn rust_fields(out: &mut String, indent: &str, vis: &str, owner: &str, fields: &[Field]) {
+    for field in fields {
+        rust_doc(out, indent, field.doc.as_deref());
+        let (name, rename) = rust_field_name(&field.name);
+        let mut attrs = vec![];
+        if rename {
+            attrs.push(format!("rename = \"{}\"", field.name));
+        }
+        let ty = match (&field.ty, field.required) {
+            (_, true) => rust_ty(&field.ty, owner),
+            (Ty::Option(_), false) => {
+                attrs.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+                rust_ty(&field.ty, owner)
+            }
+            (ty, false) => {
+                attrs.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+                format!("Option<{}>", rust_ty(ty, owner))
+            }
+        };
+        if !attrs.is_empty() {
+            writeln!(out, "{indent}#[serde({})]", attrs.join(", ")).unwrap();
+        }
+        writeln!(out, "{indent}{vis}{name}: {ty},").unwrap();
+    }
+}
+
+const RUST_PRELUDE: &str = r#"use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// The request is sent as the query
+    Get,
+    /// The request is sent as the JSON body
+    Post,
+}
+
+pub trait Endpoint {
+    const METHOD: HttpMethod;
+    /// The full path under `/api`
+    const PATH: &'static str;
+    type Req: Serialize;
+    type Resp: DeserializeOwned;
+}
+
+/// The data of a response with `"ok": false`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommonError {
+    pub code: String,
+    pub msg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_msg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The error responded by the server
+    Api(CommonError),
+    Http(reqwest::Error),
+    Decode(serde_json::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Api(e) => write!(f, "{}: {}", e.code, e.msg),
+            Error::Http(e) => write!(f, "{e}"),
+            Error::Decode(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        Error::Http(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Error::Decode(value)
+    }
+}
+
+#[derive(Deserialize)]
+struct WebResponse {
+    ok: bool,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// `base_url` is the URL of `/api`, e.g. `https://example.com/api`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Client { http: reqwest::Client::new(), base_url: base_url.into(), token: None }
+    }
+
+    /// Set the access token sent as the bearer token
+    pub fn set_token(&mut self, token: impl Into<String>) {
+        self.token = Some(token.into());
+    }
+
+    pub async fn call<E: Endpoint>(&self, req: &E::Req) -> Result<E::Resp, Error> {
+        let url = format!("{}{}", self.base_url, E::PATH);
+        let builder = match E::METHOD {
+            HttpMethod::Get => self.http.get(url).query(req),
+            HttpMethod::Post => self.http.post(url).json(req),
+        };
+        let builder = match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        };
+        let resp: WebResponse = builder.send().await?.json().await?;
+        if resp.ok {
+            Ok(serde_json::from_value(resp.data)?)
+        } else {
+            Err(Error::Api(serde_json::from_value(resp.data)?))
+        }
+    }
+}
+"#;
+
+/// The module of the client crate: the DTOs, the endpoints and a small [reqwest] client calling them
+pub fn rust_client(manifest: &ApiManifest) -> String {
+    let mut out = String::new();
+    writeln!(out, "//! {HEADER}\n").unwrap();
+    out.push_str(RUST_PRELUDE);
+
+    for (name, schema) in &manifest.definitions {
+        out.push('\n');
+        rust_doc(&mut out, "", doc_of(schema).as_deref());
+        match parse_def(schema) {
+            Def::Struct(fields) => {
+                writeln!(out, "#[derive(Debug, Clone, Serialize, Deserialize)]\npub struct {name} {{").unwrap();
+                rust_fields(&mut out, "    ", "pub ", name, &fields);
+                out.push_str("}\n");
+            }
+            Def::StringEnum(variants) => {
+                writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\npub enum {name} {{").unwrap();
+                for x in variants {
+                    rust_doc(&mut out, "    ", x.doc.as_deref());
+                    writeln!(out, "    #[serde(rename = \"{}\")]\n    {},", x.value, pascal_case(&x.value)).unwrap();
+                }
+                out.push_str("}\n");
+            }
+            Def::Tagged { tag, variants } => {
+                writeln!(out, "#[derive(Debug, Clone, Serialize, Deserialize)]\n#[serde(tag = \"{tag}\")]\npub enum {name} {{").unwrap();
+                for x in variants {
+                    rust_doc(&mut out, "    ", x.doc.as_deref());
+                    writeln!(out, "    #[serde(rename = \"{}\")]\n    {} {{", x.value, pascal_case(&x.value)).unwrap();
+                    rust_fields(&mut out, "        ", "", name, &x.fields);
+                    out.push_str("    },\n");
+                }
+                out.push_str("}\n");
+            }
+            Def::Alias(ty) => writeln!(out, "pub type {name} = {};", rust_ty(&ty, "")).unwrap(),
+        }
+    }
+
+    out.push_str("\npub mod endpoints {\n    use super::*;\n");
+    for x in &manifest.endpoints {
+        let method = match x.method {
+            HttpMethod::Get => "Get",
+            HttpMethod::Post => "Post",
+        };
+        writeln!(out, "\n    /// `{} {}`", method.to_uppercase(), x.path).unwrap();
+        writeln!(out, "    pub struct {};\n", x.name).unwrap();
+        writeln!(out, "    impl Endpoint for {} {{", x.name).unwrap();
+        writeln!(out, "        const METHOD: HttpMethod = HttpMethod::{method};").unwrap();
+        writeln!(out, "        const PATH: &'static str = \"{}\";", x.path).unwrap();
+        writeln!(out, "        type Req = {};", rust_ty(&parse_ty(x.req_schema.as_value()), "")).unwrap();
+        writeln!(out, "        type Resp = {};", rust_ty(&parse_ty(x.resp_schema.as_value()), "")).unwrap();
+        out.push_str("    }\n");
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn ts_ty(ty: &Ty) -> String {
+    match ty {
+        Ty::Unit => "null".to_string(),
+        Ty::Bool => "boolean".to_string(),
+        Ty::Int(_) | Ty::Float(_) => "number".to_string(),
+        Ty::String => "string".to_string(),
+        Ty::Any => "unknown".to_string(),
+        Ty::Ref(x) => x.clone(),
+        Ty::Option(x) => format!("{} | null", ts_ty(x)),
+        Ty::Vec(x) if matches!(**x, Ty::Option(_)) => format!("({})[]", ts_ty(x)),
+        Ty::Vec(x) => format!("{}[]", ts_ty(x)),
+        Ty::Tuple(x) => format!("[{}]", x.iter().map(ts_ty).collect::<Vec<_>>().join(", ")),
+        Ty::Map(x) => format!("Record<string, {}>", ts_ty(x)),
+    }
+}
+
+fn ts_doc(out: &mut String, indent: &str, doc: Option<&str>) {
+    let Some(doc) = doc else { return };
+    let doc = doc.replace("*/", "*\\/");
+    if !doc.contains('\n') {
+        writeln!(out, "{indent}/** {doc} */").unwrap();
+        return;
+    }
+    writeln!(out, "{indent}/**").unwrap();
+    for line in doc.lines() {
+        if line.is_empty() {
+            writeln!(out, "{indent} *").unwrap();
+        } else {
+            writeln!(out, "{indent} * {line}").unwrap();
+        }
+    }
+    writeln!(out, "{indent} */").unwrap();
+}
+
+fn ts_fields(out: &mut String, indent: &str, fields: &[Field]) {
+    for field in fields {
+        ts_doc(out, indent, field.doc.as_deref());
+        let optional = if field.required { "" } else { "?" };
+        writeln!(out, "{indent}{}{optional}: {};", ts_property_name(&field.name), ts_ty(&field.ty)).unwrap();
+    }
+}
+
+fn ts_property_name(name: &str) -> String {
+    if name.chars().all(|x| x.is_ascii_alphanumeric() || x == '_') && !name.starts_with(|x: char| x.is_ascii_digit()) {
+        name.to_string()
+    } else {
+        format!("\"{name}\"")
+    }
+}
+
+/// The TypeScript declarations of the DTOs and the `Endpoints` map from the endpoint names to their method, path,
+/// request and response
+pub fn typescript(manifest: &ApiManifest) -> String {
+    let mut out = String::new();
+    writeln!(out, "// {HEADER}\n").unwrap();
+    out.push_str("export interface CommonError {\n  code: string;\n  msg: string;\n  localized_msg?: string;\n  detail?: unknown;\n}\n\n");
+    out.push_str("export type WebResponse<T> = { ok: true; data: T } | { ok: false; data: CommonError };\n");
+
+    for (name, schema) in &manifest.definitions {
+        out.push('\n');
+        ts_doc(&mut out, "", doc_of(schema).as_deref());
+        match parse_def(schema) {
+            Def::Struct(fields) => {
+                writeln!(out, "export interface {name} {{").unwrap();
+                ts_fields(&mut out, "  ", &fields);
+                out.push_str("}\n");
+            }
+            Def::StringEnum(variants) => {
+                let values: Vec<_> = variants.iter().map(|x| format!("\"{}\"", x.value)).collect();
+                writeln!(out, "export type {name} = {};", values.join(" | ")).unwrap();
+            }
+            Def::Tagged { tag, variants } => {
+                writeln!(out, "export type {name} =").unwrap();
+                for (i, x) in variants.iter().enumerate() {
+                    ts_doc(&mut out, "  ", x.doc.as_deref());
+                    writeln!(out, "  | {{\n    {tag}: \"{}\";", x.value).unwrap();
+                    ts_fields(&mut out, "    ", &x.fields);
+                    out.push_str(if i + 1 == variants.len() { "  };\n" } else { "  }\n" });
+                }
+            }
+            Def::Alias(ty) => writeln!(out, "export type {name} = {};", ts_ty(&ty)).unwrap(),
+        }
+    }
+
+    out.push_str("\nexport interface Endpoints {\n");
+    for x in &manifest.endpoints {
+        let method = match x.method {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+        };
+        writeln!(
+            out,
+            "  {}: {{ method: \"{method}\"; path: \"{}\"; req: {}; resp: {} }};",
+            x.name,
+            x.path,
+            ts_ty(&parse_ty(x.req_schema.as_value())),
+            ts_ty(&parse_ty(x.resp_schema.as_value())),
+        ).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::api::manifest;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_ty() {
+        assert_eq!(Ty::Option(Box::new(Ty::Int("i64"))), parse_ty(&json!({"type": ["integer", "null"], "format": "int64"})));
+        assert_eq!(Ty::Option(Box::new(Ty::Ref("Song".into()))), parse_ty(&json!({"anyOf": [{"$ref": "#/$defs/Song"}, {"type": "null"}]})));
+        assert_eq!(Ty::Tuple(vec![Ty::String, Ty::String]), parse_ty(&json!({"type": "array", "prefixItems": [{"type": "string"}, {"type": "string"}]})));
+        assert_eq!(Ty::Any, parse_ty(&json!({"description": "Depends on the type"})));
+    }
+
+    #[test]
+    fn test_parse_tagged_enum() {
+        let schema = json!({"oneOf": [
+            {"type": "object", "properties": {"kind": {"type": "string", "const": "a"}, "x": {"type": "string"}}, "required": ["kind", "x"]},
+            {"type": "object", "properties": {"kind": {"type": "string", "const": "b"}}, "required": ["kind"]},
+        ]});
+        let Def::Tagged { tag, variants } = parse_def(&schema) else { panic!("Not parsed as a tagged enum") };
+        assert_eq!("kind", tag);
+        assert_eq!(vec!["a", "b"], variants.iter().map(|x| x.value.as_str()).collect::<Vec<_>>());
+        assert_eq!(1, variants[0].fields.len());
+    }
+
+    #[test]
+    fn test_generated_up_to_date() {
+        let manifest = manifest();
+        assert!(rust_client(&manifest) == include_str!("../../clients/rust/src/lib.rs"), "Run `cargo gen-api-client` to update {RUST_CLIENT_PATH}");
+        assert!(typescript(&manifest) == include_str!("../../clients/typescript/api.d.ts"), "Run `cargo gen-api-client` to update {TYPESCRIPT_PATH}");
+    }
+}
@@ -0,0 +1,224 @@
+use crate::config::Config;
+use anyhow::Context;
+use axum::http::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::{LazyLock, OnceLock};
+use tracing::info;
+
+static RESOLVER: OnceLock<Resolver> = OnceLock::new();
+static DEFAULT_RESOLVER: LazyLock<Resolver> = LazyLock::new(|| Resolver::new(&ClientIpCfg::default()).unwrap());
+
+/// Optional `client_ip` section of the config file, the absent fields take the defaults.
+///
+/// The headers are only trusted if the peer is one of `trusted_proxies`, otherwise the peer is the client,
+/// so the clients reaching the server directly can't spoof their IPs. The headers are tried in order, and
+/// `X-Forwarded-For` is walked from the right, skipping the trusted proxies, by at most `forwarded_depth` hops.
+///
+/// ```yaml
+/// client_ip:
+///   trusted_proxies:
+///     - 127.0.0.1
+///     - 10.0.0.0/8
+///   headers:
+///     - X-Real-IP
+///     - X-Forwarded-For
+///   forwarded_depth: 1
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientIpCfg {
+    /// IPs or CIDR blocks, the loopback and the private networks by default
+    pub trusted_proxies: Vec<String>,
+    pub headers: Vec<String>,
+    /// The proxies appending to `X-Forwarded-For` in front of the server
+    pub forwarded_depth: usize,
+}
+
+impl Default for ClientIpCfg {
+    fn default() -> Self {
+        ClientIpCfg {
+            trusted_proxies: ["127.0.0.0/8", "::1", "10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"]
+                .iter().map(|x| x.to_string()).collect(),
+            headers: vec!["X-Real-IP".to_string(), "X-Forwarded-For".to_string()],
+            forwarded_depth: 1,
+        }
+    }
+}
+
+impl ClientIpCfg {
+    /// Load the `client_ip` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("client_ip")?.is_some() {
+            config.get_and_parse("client_ip")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+/// An IP or a CIDR block
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IpRange {
+    addr: IpAddr,
+    prefix_len: u32,
+}
+
+impl IpRange {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix_len) = match value.split_once('/') {
+            Some((addr, len)) => (addr.parse::<IpAddr>().ok()?, Some(len.parse::<u32>().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix_len.unwrap_or(max_len);
+        (prefix_len <= max_len).then_some(IpRange { addr: addr.to_canonical(), prefix_len })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(a), IpAddr::V4(b)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_len).unwrap_or(0);
+                u32::from(a) & mask == u32::from(b) & mask
+            }
+            (IpAddr::V6(a), IpAddr::V6(b)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_len).unwrap_or(0);
+                u128::from(a) & mask == u128::from(b) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Resolver {
+    trusted_proxies: Vec<IpRange>,
+    headers: Vec<String>,
+    forwarded_depth: usize,
+}
+
+impl Resolver {
+    fn new(cfg: &ClientIpCfg) -> anyhow::Result<Self> {
+        let trusted_proxies = cfg.trusted_proxies.iter()
+            .map(|x| IpRange::parse(x).with_context(|| format!("Invalid trusted_proxies entry: {}", x)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Resolver {
+            trusted_proxies,
+            headers: cfg.headers.clone(),
+            forwarded_depth: cfg.forwarded_depth,
+        })
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.trusted_proxies.iter().any(|x| x.contains(ip))
+    }
+
+    fn resolve(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+        // Without the peer, e.g. in the tests, the headers are all there is
+        if let Some(peer) = peer && !self.is_trusted(peer) {
+            return Some(peer.to_canonical());
+        }
+        self.headers.iter()
+            .find_map(|name| {
+                let value = headers.get(name.as_str())?.to_str().ok()?;
+                if name.eq_ignore_ascii_case("X-Forwarded-For") {
+                    self.resolve_forwarded_for(value)
+                } else {
+                    value.trim().parse::<IpAddr>().ok()
+                }
+            })
+            .map(|x| x.to_canonical())
+            .or(peer.map(|x| x.to_canonical()))
+    }
+
+    /// The rightmost hop not added by a trusted proxy
+    fn resolve_forwarded_for(&self, value: &str) -> Option<IpAddr> {
+        let hops = value.split(',').map(|x| x.trim().parse::<IpAddr>().ok()).collect::<Vec<_>>();
+        let mut result = None;
+        for hop in hops.into_iter().rev().take(self.forwarded_depth) {
+            // A malformed hop can't be told apart from a spoofed one
+            let hop = hop?;
+            result = Some(hop);
+            if !self.is_trusted(hop) {
+                break;
+            }
+        }
+        result
+    }
+}
+
+/// Should be called once before serving, the default config is used without it
+pub fn initialize(cfg: ClientIpCfg) -> anyhow::Result<()> {
+    info!("Trusting the client IP headers {:?} from {} proxy ranges", cfg.headers, cfg.trusted_proxies.len());
+    if RESOLVER.set(Resolver::new(&cfg)?).is_err() {
+        anyhow::bail!("Client IP resolver is already initialized");
+    }
+    Ok(())
+}
+
+/// Whether the peer is one of `trusted_proxies`, so the headers set by it can be trusted
+pub fn is_trusted_proxy(peer: IpAddr) -> bool {
+    RESOLVER.get().unwrap_or(&DEFAULT_RESOLVER).is_trusted(peer)
+}
+
+/// The IP of the client, from the headers if the peer is a trusted proxy, otherwise the peer
+pub fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+    RESOLVER.get().unwrap_or(&DEFAULT_RESOLVER).resolve(headers, peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_range() {
+        let range = IpRange::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains("10.1.2.3".parse().unwrap()));
+        assert!(!range.contains("11.0.0.1".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+
+        let single = IpRange::parse("192.168.1.10").unwrap();
+        assert!(single.contains("192.168.1.10".parse().unwrap()));
+        assert!(!single.contains("192.168.1.11".parse().unwrap()));
+
+        assert!(IpRange::parse("0.0.0.0/0").unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!(IpRange::parse("fd00::/8").unwrap().contains("fd12::1".parse().unwrap()));
+        assert!(IpRange::parse("10.0.0.0/33").is_none());
+        assert!(IpRange::parse("example.com").is_none());
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_resolve() {
+        let resolver = Resolver::new(&ClientIpCfg {
+            forwarded_depth: 2,
+            ..ClientIpCfg::default()
+        }).unwrap();
+        let proxy = Some("127.0.0.1".parse().unwrap());
+        let direct = Some("8.8.8.8".parse().unwrap());
+        let ip = |x: &str| Some(x.parse::<IpAddr>().unwrap());
+
+        // The headers of the clients reaching the server directly are ignored
+        assert_eq!(direct, resolver.resolve(&headers(&[("X-Real-IP", "1.1.1.1")]), direct));
+        assert_eq!(ip("1.1.1.1"), resolver.resolve(&headers(&[("X-Real-IP", "1.1.1.1")]), proxy));
+        // In the order of preference
+        assert_eq!(ip("1.1.1.1"), resolver.resolve(&headers(&[("X-Real-IP", "1.1.1.1"), ("X-Forwarded-For", "2.2.2.2")]), proxy));
+        // The spoofed hops on the left are beyond the depth
+        assert_eq!(ip("2.2.2.2"), resolver.resolve(&headers(&[("X-Forwarded-For", "3.3.3.3, 2.2.2.2")]), proxy));
+        assert_eq!(ip("2.2.2.2"), resolver.resolve(&headers(&[("X-Forwarded-For", "3.3.3.3, 2.2.2.2, 10.0.0.2")]), proxy));
+        assert_eq!(ip("10.0.0.1"), resolver.resolve(&headers(&[("X-Forwarded-For", "3.3.3.3, 10.0.0.1, 10.0.0.2")]), proxy));
+        // Fallback to the peer
+        assert_eq!(proxy, resolver.resolve(&headers(&[("X-Forwarded-For", "garbage")]), proxy));
+        assert_eq!(proxy, resolver.resolve(&HeaderMap::new(), proxy));
+        // Not trusted unless configured
+        assert_eq!(proxy, resolver.resolve(&headers(&[("CF-Connecting-IP", "1.1.1.1")]), proxy));
+        assert_eq!(ip("1.1.1.1"), resolver.resolve(&headers(&[("X-Real-IP", "::ffff:1.1.1.1")]), proxy));
+    }
+}
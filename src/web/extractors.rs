@@ -1,7 +1,10 @@
-use axum::extract::FromRequestParts;
+use axum::extract::{ConnectInfo, FromRequestParts};
 use axum::http::request::Parts;
+use std::net::SocketAddr;
+use crate::web::client_ip;
 use crate::web::result::WebError;
 
+/// The IP of the client, the proxy headers are only trusted from the configured proxies, see [client_ip::ClientIpCfg]
 #[derive(Debug, Clone)]
 pub struct XRealIP(pub String);
 
@@ -12,12 +15,29 @@ where
     type Rejection = WebError<()>;
 
     async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
-        let value = parts
-            .headers
-            .get("X-Real-IP")
-            .or_else(|| parts.headers.get("CF-Connecting-IP")) // TODO[security]: Is it safe?
-            .ok_or_else(|| WebError::Internal(anyhow::anyhow!("X-Real-IP header not found")))?;
+        let peer = parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|x| x.ip());
+        let ip = client_ip::client_ip(&parts.headers, peer)
+            .ok_or_else(|| WebError::Internal(anyhow::anyhow!("Client IP not found")))?;
+        Ok(XRealIP(ip.to_string()))
+    }
+}
+
+/// The optional `X-Client-Fingerprint` header, a salted hash computed by the client to tell the anonymous
+/// clients apart. The format is not validated here.
+#[derive(Debug, Clone)]
+pub struct ClientFingerprint(pub Option<String>);
 
-        Ok(XRealIP(value.to_str()?.to_string()))
+impl<S> FromRequestParts<S> for ClientFingerprint
+where
+    S: Send + Sync,
+{
+    type Rejection = WebError<()>;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let value = match parts.headers.get("X-Client-Fingerprint") {
+            Some(x) => Some(x.to_str()?.to_string()),
+            None => None,
+        };
+        Ok(ClientFingerprint(value))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,33 @@
+use crate::web::state::AppState;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use tracing::warn;
+
+/// Serve the stored files for local development, see [crate::file_hosting::StorageCfg]
+pub fn router() -> Router<AppState> {
+    Router::new().route("/{*key}", get(serve_file))
+}
+
+async fn serve_file(
+    state: State<AppState>,
+    Path(key): Path<String>,
+) -> Response {
+    match state.file_host.download(&key).await {
+        Ok(Some(object)) => {
+            let mut response = Body::from(object.bytes).into_response();
+            if let Some(content_type) = object.content_type.and_then(|x| HeaderValue::from_str(&x).ok()) {
+                response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+            }
+            response
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            warn!("{:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -1,14 +1,240 @@
-use axum::http::{HeaderName, Request};
+use crate::config::Config;
+use crate::service::test_mode;
+use crate::service::magic_link::MagicLinkLimit;
+use crate::service::verification_code::EmailCodeLimit;
+use crate::web::{client_ip, jwt};
+use axum::extract::{ConnectInfo, Request};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use governor::clock::{Clock, DefaultClock};
 use governor::middleware::NoOpMiddleware;
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroU32;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use tower_governor::governor::GovernorConfigBuilder;
 use tower_governor::key_extractor::KeyExtractor;
 use tower_governor::{GovernorError, GovernorLayer};
-use tracing::error;
+use tracing::{error, info};
 
-pub fn governor_layer<RespBody>() -> GovernorLayer<RealIPExtractor, NoOpMiddleware, RespBody> {
+static LIMITERS: OnceLock<Arc<Limiters>> = OnceLock::new();
+
+/// Optional `rate_limits` section of the config file, the absent fields take the defaults.
+///
+/// Each request is limited by the longest matching `routes`, or `default` if none matches. The anonymous requests
+/// are keyed by the client IP and the authenticated ones by the uid, so the users behind the same NAT don't share
+/// a quota. A request replenishes every `period_ms` after `burst_size` requests, the excess requests are responded
+/// with `429` and `Retry-After`.
+///
+/// ```yaml
+/// rate_limits:
+///   default:
+///     period_ms: 500
+///     burst_size: 16
+///   routes:
+///     - route: /api/auth/send_email_code
+///       period_ms: 20000
+///       burst_size: 3
+///     - route: /api/song/detail
+///       period_ms: 100
+///       burst_size: 64
+///   email_code:
+///     per_email_per_hour: 10
+///     per_ip_per_hour: 30
+///   magic_link:
+///     per_email_per_hour: 5
+/// ```
+///
+/// The default `routes` are replaced if it's set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitsCfg {
+    pub default: RateLimit,
+    pub routes: Vec<RouteRateLimit>,
+    /// The verification codes sent per hour, shared by the instances unlike the others
+    pub email_code: EmailCodeLimit,
+    /// The magic links sent per hour, shared by the instances too
+    pub magic_link: MagicLinkLimit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub period_ms: u64,
+    pub burst_size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteRateLimit {
+    /// Path prefix, including the `/api` prefix
+    pub route: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+}
+
+impl RouteRateLimit {
+    fn new(route: &str, period_ms: u64, burst_size: u32) -> Self {
+        RouteRateLimit { route: route.to_string(), limit: RateLimit { period_ms, burst_size } }
+    }
+}
+
+impl Default for RateLimitsCfg {
+    fn default() -> Self {
+        RateLimitsCfg {
+            default: RateLimit { period_ms: 500, burst_size: 16 },
+            routes: vec![
+                // Sending the emails costs and can be abused to spam the addresses
+                RouteRateLimit::new("/api/auth/send_email_code", 20_000, 3),
+                RouteRateLimit::new("/api/auth/resend_email_code", 20_000, 3),
+                RouteRateLimit::new("/api/auth/login", 2_000, 10),
+                // Cheap and cached, the clients load them in batches
+                RouteRateLimit::new("/api/song/detail", 100, 64),
+            ],
+            email_code: EmailCodeLimit::default(),
+            magic_link: MagicLinkLimit::default(),
+        }
+    }
+}
+
+impl RateLimitsCfg {
+    /// Load the `rate_limits` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("rate_limits")?.is_some() {
+            config.get_and_parse("rate_limits")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// The index of the longest matching route of the path, `None` for the default
+    fn route_of(&self, path: &str) -> Option<usize> {
+        self.routes.iter()
+            .enumerate()
+            .filter(|(_, x)| path.starts_with(x.route.as_str()))
+            .max_by_key(|(_, x)| x.route.len())
+            .map(|(i, _)| i)
+    }
+}
+
+impl RateLimit {
+    fn quota(&self) -> anyhow::Result<Quota> {
+        let burst_size = NonZeroU32::new(self.burst_size)
+            .ok_or_else(|| anyhow::anyhow!("Rate limit burst_size must be positive"))?;
+        let quota = Quota::with_period(Duration::from_millis(self.period_ms))
+            .ok_or_else(|| anyhow::anyhow!("Rate limit period_ms must be positive"))?;
+        Ok(quota.allow_burst(burst_size))
+    }
+}
+
+/// The requests of a user share a quota wherever they come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RateKey {
+    Ip(IpAddr),
+    User(i64),
+}
+
+struct Limiters {
+    cfg: RateLimitsCfg,
+    default: DefaultKeyedRateLimiter<RateKey>,
+    /// In the order of `cfg.routes`
+    routes: Vec<DefaultKeyedRateLimiter<RateKey>>,
+}
+
+impl Limiters {
+    fn new(cfg: RateLimitsCfg) -> anyhow::Result<Self> {
+        let default = RateLimiter::keyed(cfg.default.quota()?);
+        let routes = cfg.routes.iter()
+            .map(|x| x.limit.quota().map(RateLimiter::keyed))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Limiters { cfg, default, routes })
+    }
+
+    /// Check the quota of the key on the route of the path, returns the matched route and the time to wait if
+    /// it's exceeded
+    fn check(&self, path: &str, key: RateKey) -> (&str, Option<Duration>) {
+        let (route, limiter) = match self.cfg.route_of(path) {
+            Some(i) => (self.cfg.routes[i].route.as_str(), &self.routes[i]),
+            None => ("default", &self.default),
+        };
+        let wait = limiter.check_key(&key).err().map(|x| x.wait_time_from(DefaultClock::default().now()));
+        (route, wait)
+    }
+
+    /// Forget the keys whose quotas are replenished
+    fn retain_recent(&self) {
+        for limiter in std::iter::once(&self.default).chain(&self.routes) {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+        }
+    }
+}
+
+/// Should be called once before serving, nothing is limited without it
+pub fn initialize(cfg: RateLimitsCfg) -> anyhow::Result<()> {
+    info!("Rate limiting {} routes, {:?} by default", cfg.routes.len(), cfg.default);
+    let limiters = Arc::new(Limiters::new(cfg)?);
+    if LIMITERS.set(limiters.clone()).is_err() {
+        anyhow::bail!("Rate limits are already initialized");
+    }
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            limiters.retain_recent();
+        }
+    });
+    Ok(())
+}
+
+/// The uid of a valid access token, otherwise the client IP. An invalid token is rejected later by the routes
+fn rate_key(headers: &HeaderMap, peer: Option<IpAddr>) -> Option<RateKey> {
+    let uid = headers.get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .and_then(|x| jwt::decode_and_validate_access_token(x).ok())
+        .and_then(|x| x.sub.parse().ok());
+    match uid {
+        Some(uid) => Some(RateKey::User(uid)),
+        None => client_ip::client_ip(headers, peer).map(RateKey::Ip),
+    }
+}
+
+/// Reject the requests over the rate limit of their routes, nothing is limited in the test mode
+pub async fn limit_rate(req: Request, next: Next) -> Response {
+    let Some(limiters) = LIMITERS.get() else {
+        return next.run(req).await;
+    };
+    // The integration tests register and log in many users from the same IP
+    if test_mode::is_enabled() {
+        return next.run(req).await;
+    }
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|x| x.ip());
+    let Some(key) = rate_key(req.headers(), peer) else {
+        error!("Failed to extract real IP from headers");
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to extract the client IP").into_response();
+    };
+    let (route, wait) = limiters.check(req.uri().path(), key);
+    match wait {
+        None => next.run(req).await,
+        Some(wait) => {
+            counter!("rate_limited_count", "route" => route.to_string()).increment(1);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, wait.as_secs().max(1).to_string())],
+                "Too many requests, please try again later",
+            ).into_response()
+        }
+    }
+}
+
+/// The stricter limit layered on the routes creating the user content, one request every `period_secs` after the burst
+pub fn content_governor_layer<RespBody>(period_secs: u64, burst_size: u32) -> GovernorLayer<RealIPExtractor, NoOpMiddleware, RespBody> {
     let governor_conf = GovernorConfigBuilder::default()
-        .burst_size(16)
+        .per_second(period_secs)
+        .burst_size(burst_size)
         .key_extractor(RealIPExtractor)
         .finish().unwrap();
     GovernorLayer::new(governor_conf)
@@ -20,14 +246,57 @@ pub struct RealIPExtractor;
 impl KeyExtractor for RealIPExtractor {
     type Key = IpAddr;
     fn extract<T>(&self, req: &Request<T>) -> Result<Self::Key, GovernorError> {
-        let headers = req.headers();
-        let real_ip = headers.get(HeaderName::from_static("x-real-ip"))
-            .and_then(|hv| hv.to_str().ok())
-            .and_then(|s| s.parse::<IpAddr>().ok());
         let peer_ip = req.extensions().get::<axum::extract::ConnectInfo<SocketAddr>>().map(|sa| sa.ip());
-        real_ip.or(peer_ip).ok_or_else(|| {
+        client_ip::client_ip(req.headers(), peer_ip).ok_or_else(|| {
             error!("Failed to extract real IP from headers");
             GovernorError::UnableToExtractKey
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let cfg: RateLimitsCfg = serde_yaml::from_str(r#"
+            default:
+              period_ms: 1000
+              burst_size: 2
+            routes:
+              - route: /api/auth
+                period_ms: 60000
+                burst_size: 1
+              - route: /api/auth/login
+                period_ms: 60000
+                burst_size: 3
+        "#).unwrap();
+        let limiters = Limiters::new(cfg).unwrap();
+        let user = RateKey::User(1);
+        let ip = RateKey::Ip("10.0.0.1".parse().unwrap());
+
+        assert_eq!(("/api/auth", None), limiters.check("/api/auth/send_email_code", user));
+        let (route, wait) = limiters.check("/api/auth/send_email_code", user);
+        assert_eq!("/api/auth", route);
+        assert!(wait.is_some_and(|x| x > Duration::from_secs(50)));
+        // The keys and the routes have their own quotas
+        assert_eq!(None, limiters.check("/api/auth/send_email_code", ip).1);
+        for _ in 0..3 {
+            assert_eq!(("/api/auth/login", None), limiters.check("/api/auth/login", user));
+        }
+        assert!(limiters.check("/api/auth/login", user).1.is_some());
+        assert_eq!(("default", None), limiters.check("/api/song/detail", user));
+    }
+
+    #[test]
+    fn test_invalid_cfg() {
+        let cfg = RateLimitsCfg { default: RateLimit { period_ms: 1000, burst_size: 0 }, routes: vec![], email_code: EmailCodeLimit::default(), magic_link: MagicLinkLimit::default() };
+        assert!(Limiters::new(cfg).is_err());
+        // The absent fields take the defaults
+        let cfg: RateLimitsCfg = serde_yaml::from_str("routes: []\nemail_code:\n  per_ip_per_hour: 100").unwrap();
+        assert_eq!(RateLimitsCfg::default().default, cfg.default);
+        assert_eq!(EmailCodeLimit { per_ip_per_hour: 100, ..EmailCodeLimit::default() }, cfg.email_code);
+        assert_eq!(MagicLinkLimit::default(), cfg.magic_link);
+    }
+}
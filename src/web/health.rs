@@ -0,0 +1,176 @@
+use crate::config::Config;
+use crate::web::state::AppState;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sqlx::Connection;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Optional `health` section of the config file, the absent fields take the defaults.
+///
+/// ```yaml
+/// health:
+///   timeout_ms: 2000
+///   check_storage: false
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthCfg {
+    /// Of each check, a dependency taking longer is considered down
+    pub timeout_ms: u64,
+    /// Also check the storage, e.g. `HeadBucket` of S3, which may be billed per request
+    pub check_storage: bool,
+}
+
+impl Default for HealthCfg {
+    fn default() -> Self {
+        HealthCfg { timeout_ms: 2000, check_storage: false }
+    }
+}
+
+impl HealthCfg {
+    /// Load the `health` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("health")?.is_some() {
+            config.get_and_parse("health")
+        } else {
+            Ok(Self::default())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResp {
+    /// `ok`, `degraded` if a soft dependency is down, or `down` if a hard dependency is down
+    pub status: String,
+    pub checks: BTreeMap<String, CheckResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    /// Whether the server can't serve without it
+    pub required: bool,
+    pub latency_ms: u64,
+    /// Only logged, `/health` is public and the errors may tell the internal addresses
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+/// Always succeeds while the server is running, for the liveness probe
+pub async fn live() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Check the dependencies for the readiness probe, responds 503 if any hard dependency is down
+pub async fn ready(State(state): State<AppState>) -> Response {
+    let cfg = match HealthCfg::load(&state.config) {
+        Ok(x) => x,
+        Err(e) => {
+            warn!("Invalid health config, using the default: {:?}", e);
+            HealthCfg::default()
+        }
+    };
+    let timeout = Duration::from_millis(cfg.timeout_ms);
+
+    let (postgres, redis, meilisearch, storage) = tokio::join!(
+        check(timeout, true, async {
+            let mut conn = state.sql_pool.acquire().await?;
+            conn.ping().await?;
+            anyhow::Ok(())
+        }),
+        check(timeout, true, async {
+            redis::cmd("PING").query_async::<String>(&mut state.redis_conn.clone()).await?;
+            anyhow::Ok(())
+        }),
+        check(timeout, true, async {
+            state.meilisearch.health().await?;
+            anyhow::Ok(())
+        }),
+        async {
+            if cfg.check_storage {
+                Some(check(timeout, false, state.file_host.check()).await)
+            } else {
+                None
+            }
+        },
+    );
+    let mut checks = BTreeMap::new();
+    checks.insert("postgres".to_string(), postgres);
+    checks.insert("redis".to_string(), redis);
+    checks.insert("meilisearch".to_string(), meilisearch);
+    if let Some(storage) = storage {
+        checks.insert("storage".to_string(), storage);
+    }
+
+    let (status, resp) = summarize(checks);
+    (status, Json(resp)).into_response()
+}
+
+async fn check(
+    timeout: Duration,
+    required: bool,
+    future: impl Future<Output = anyhow::Result<()>>,
+) -> CheckResult {
+    let start = Instant::now();
+    let error = match tokio::time::timeout(timeout, future).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(format!("{:#}", e)),
+        Err(_) => Some(format!("Timed out after {}ms", timeout.as_millis())),
+    };
+    CheckResult {
+        ok: error.is_none(),
+        required,
+        latency_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+fn summarize(checks: BTreeMap<String, CheckResult>) -> (StatusCode, HealthResp) {
+    let failed = checks.iter().filter(|(_, x)| !x.ok).collect::<Vec<_>>();
+    for (name, x) in &failed {
+        warn!("Health check {} failed: {}", name, x.error.as_deref().unwrap_or_default());
+    }
+    let (status_code, status) = if failed.iter().any(|(_, x)| x.required) {
+        (StatusCode::SERVICE_UNAVAILABLE, "down")
+    } else if !failed.is_empty() {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+    (status_code, HealthResp { status: status.to_string(), checks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_summarize() {
+        let timeout = Duration::from_millis(50);
+        let ok = check(timeout, true, async { Ok(()) }).await;
+        let soft_down = check(timeout, false, async { anyhow::bail!("unreachable") }).await;
+        let hard_down = check(timeout, true, async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        }).await;
+        assert_eq!(Some("unreachable"), soft_down.error.as_deref());
+        assert_eq!(Some("Timed out after 50ms"), hard_down.error.as_deref());
+
+        let checks = |xs: &[&CheckResult]| xs.iter().enumerate()
+            .map(|(i, x)| (i.to_string(), (*x).clone()))
+            .collect::<BTreeMap<_, _>>();
+        let (code, resp) = summarize(checks(&[&ok]));
+        assert_eq!((StatusCode::OK, "ok"), (code, resp.status.as_str()));
+        let (code, resp) = summarize(checks(&[&ok, &soft_down]));
+        assert_eq!((StatusCode::OK, "degraded"), (code, resp.status.as_str()));
+        let (code, resp) = summarize(checks(&[&ok, &soft_down, &hard_down]));
+        assert_eq!((StatusCode::SERVICE_UNAVAILABLE, "down"), (code, resp.status.as_str()));
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(!json.contains("unreachable"), "{json}");
+    }
+}
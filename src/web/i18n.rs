@@ -0,0 +1,162 @@
+use crate::service::user;
+use crate::web::jwt;
+use crate::web::state::AppState;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap};
+use axum::middleware::Next;
+use axum::response::Response;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tracing::warn;
+
+const ERROR_CATALOG: &str = include_str!("i18n/errors.yaml");
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum Lang {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Lang {
+    /// The language tag, the same as in JSON
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Lang::ZhCn => "zh-CN",
+            Lang::En => "en",
+        }
+    }
+
+    /// Match a language tag like `zh-Hans-CN` or `en-US` by the primary subtag
+    pub fn from_tag(tag: &str) -> Option<Lang> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        if primary.eq_ignore_ascii_case("zh") {
+            Some(Lang::ZhCn)
+        } else if primary.eq_ignore_ascii_case("en") {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    /// Pick the supported language with the highest quality in an `Accept-Language` header
+    pub fn from_accept_language(value: &str) -> Option<Lang> {
+        parse_accept_language(value).iter().find_map(|x| Lang::from_tag(x))
+    }
+}
+
+/// The language tags of an `Accept-Language` header by descending quality, without the wildcard and the rejected ones
+pub fn parse_accept_language(value: &str) -> Vec<String> {
+    value.split(',')
+        .filter_map(|item| {
+            let mut parts = item.trim().split(';');
+            let tag = parts.next()?.trim();
+            if tag.is_empty() || tag == "*" {
+                return None;
+            }
+            let quality = parts
+                .find_map(|x| x.trim().strip_prefix("q="))
+                .and_then(|x| x.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (quality > 0.0).then(|| (tag.to_string(), quality))
+        })
+        // Stable, the first one wins on ties
+        .sorted_by(|a, b| b.1.total_cmp(&a.1))
+        .map(|x| x.0)
+        .collect()
+}
+
+static CATALOG: LazyLock<HashMap<String, HashMap<Lang, String>>> = LazyLock::new(|| {
+    serde_yaml::from_str(ERROR_CATALOG).expect("invalid error catalog")
+});
+
+tokio::task_local! {
+    static CURRENT_LANG: Lang;
+    static ACCEPTED_LANGUAGES: Vec<String>;
+}
+
+/// The language of the current request, see [negotiate_language]
+pub fn current_lang() -> Lang {
+    CURRENT_LANG.try_with(|x| *x).unwrap_or_default()
+}
+
+/// The languages accepted by the current request by preference, see [parse_accept_language].
+/// Unlike [current_lang], they are not limited to the supported languages, e.g. for the localized titles.
+pub fn accepted_languages() -> Vec<String> {
+    ACCEPTED_LANGUAGES.try_with(|x| x.clone()).unwrap_or_default()
+}
+
+/// The localized message of the error code, `None` if the code is not in the catalog
+pub fn localize(code: &str, lang: Lang) -> Option<&'static str> {
+    CATALOG.get(code)
+        .and_then(|x| x.get(&lang).or_else(|| x.get(&Lang::default())))
+        .map(|x| x.as_str())
+}
+
+/// Select the language for the business errors of the request, the one saved by the authenticated user first,
+/// then by `Accept-Language`.
+///
+/// The selected [Lang] is also inserted into the request extensions, and all the accepted languages are kept for
+/// [accepted_languages], led by the saved one.
+pub async fn negotiate_language(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    let mut accepted = req.headers().get(header::ACCEPT_LANGUAGE)
+        .and_then(|x| x.to_str().ok())
+        .map(parse_accept_language)
+        .unwrap_or_default();
+    let lang = match saved_lang(&state, req.headers()).await {
+        Some(x) => {
+            accepted.insert(0, x.tag().to_string());
+            x
+        }
+        None => accepted.iter().find_map(|x| Lang::from_tag(x)).unwrap_or_default(),
+    };
+    req.extensions_mut().insert(lang);
+    CURRENT_LANG.scope(lang, ACCEPTED_LANGUAGES.scope(accepted, next.run(req))).await
+}
+
+/// The language saved by the user of the access token, `None` for the anonymous requests and the invalid tokens
+async fn saved_lang(state: &AppState, headers: &HeaderMap) -> Option<Lang> {
+    let token = headers.get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))?;
+    let uid = jwt::decode_and_validate_access_token(token).ok()?.uid();
+    user::preferred_lang(state.redis_conn.clone(), &state.sql_pool, uid).await
+        .inspect_err(|e| warn!("Failed to get the preferred language of user {uid}: {e:#}"))
+        .ok()
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_language() {
+        assert_eq!(Some(Lang::En), Lang::from_accept_language("en-US,en;q=0.9,zh-CN;q=0.8"));
+        assert_eq!(Some(Lang::ZhCn), Lang::from_accept_language("ja;q=1.0, zh-Hans-CN;q=0.9, en;q=0.5"));
+        assert_eq!(Some(Lang::ZhCn), Lang::from_accept_language("en;q=0, zh"));
+        assert_eq!(None, Lang::from_accept_language("ja, *"));
+    }
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(vec!["ja", "zh-Hans-CN", "en"], parse_accept_language("en;q=0.5, ja, zh-Hans-CN;q=0.9, fr;q=0, *;q=0.1"));
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_catalog_is_complete() {
+        for (code, messages) in CATALOG.iter() {
+            for lang in [Lang::ZhCn, Lang::En] {
+                assert!(messages.contains_key(&lang), "{code} is missing {lang:?}");
+            }
+        }
+        assert_eq!(Some("内容不存在"), localize("not_found", Lang::ZhCn));
+        assert_eq!(None, localize("no_such_code", Lang::En));
+    }
+}
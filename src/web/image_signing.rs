@@ -0,0 +1,257 @@
+use crate::web::state::AppState;
+use anyhow::Context;
+use axum::body::Body;
+use axum::extract::{Path, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::{error, warn};
+
+static URL_SIGNER: OnceLock<UrlSigner> = OnceLock::new();
+
+/// Optional `image_url_signing` section of the config file.
+///
+/// When enabled, every image URL of the public domain in JSON responses is rewritten into a signed,
+/// expiring URL. Otherwise, the plain public URLs are kept.
+///
+/// - `cdn` mode: `https://{public_domain}/_s/{expires}/{sig}/{key}`, validated by a CDN worker which then
+///   serves `https://{public_domain}/{key}`.
+/// - `proxy` mode: `{proxy_base_url}/{expires}/{sig}/{key}`, validated and served by this server via `/api/image`.
+///
+/// `sig` is the first 32 hex chars of `HMAC-SHA256(secret, "{expires}/{key}")`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUrlSigningCfg {
+    #[serde(default)]
+    pub enabled: bool,
+    pub secret: String,
+    #[serde(default = "default_ttl_secs")]
+    pub ttl_secs: i64,
+    #[serde(default)]
+    pub mode: SigningMode,
+    pub proxy_base_url: Option<String>,
+}
+
+fn default_ttl_secs() -> i64 { 86400 }
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningMode {
+    #[default]
+    Cdn,
+    Proxy,
+}
+
+pub struct UrlSigner {
+    cfg: ImageUrlSigningCfg,
+    public_domain: String,
+    url_regex: Regex,
+}
+
+/// Initialize the signer if `image_url_signing.enabled` is true, should be called once before serving.
+pub fn initialize(cfg: Option<ImageUrlSigningCfg>, public_domain: &str) -> anyhow::Result<()> {
+    let Some(cfg) = cfg.filter(|x| x.enabled) else {
+        return Ok(());
+    };
+    if cfg.mode == SigningMode::Proxy && cfg.proxy_base_url.is_none() {
+        anyhow::bail!("image_url_signing.proxy_base_url is required in proxy mode");
+    }
+    let signer = UrlSigner::new(cfg, public_domain)?;
+    URL_SIGNER.set(signer).map_err(|_| anyhow::anyhow!("Image URL signer already initialized"))?;
+    Ok(())
+}
+
+impl UrlSigner {
+    pub fn new(cfg: ImageUrlSigningCfg, public_domain: &str) -> anyhow::Result<Self> {
+        // The expiry is aligned to it
+        if cfg.ttl_secs <= 0 {
+            anyhow::bail!("image_url_signing.ttl_secs must be positive");
+        }
+        let url_regex = Regex::new(&format!(r#"https://{}/(images/[^"\\?#\s]+)"#, regex::escape(public_domain)))?;
+        Ok(UrlSigner {
+            cfg,
+            public_domain: public_domain.to_string(),
+            url_regex,
+        })
+    }
+
+    /// The expiry is aligned to `ttl_secs`, so the signed URL stays the same within a window and remains
+    /// cacheable by clients and CDN. A URL is valid for at least `ttl_secs`.
+    fn expires_at(&self, now: i64) -> i64 {
+        (now / self.cfg.ttl_secs + 2) * self.cfg.ttl_secs
+    }
+
+    fn signature(&self, expires: i64, key: &str) -> anyhow::Result<String> {
+        let pkey = PKey::hmac(self.cfg.secret.as_bytes())?;
+        let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+        signer.update(format!("{expires}/{key}").as_bytes())?;
+        let mut sig = hex::encode(signer.sign_to_vec()?);
+        sig.truncate(32);
+        Ok(sig)
+    }
+
+    pub fn sign_key(&self, key: &str, now: i64) -> anyhow::Result<String> {
+        let expires = self.expires_at(now);
+        let sig = self.signature(expires, key)?;
+        let url = match self.cfg.mode {
+            SigningMode::Cdn => format!("https://{}/_s/{expires}/{sig}/{key}", self.public_domain),
+            SigningMode::Proxy => format!(
+                "{}/{expires}/{sig}/{key}",
+                self.cfg.proxy_base_url.as_deref().unwrap_or_default().trim_end_matches('/')
+            ),
+        };
+        Ok(url)
+    }
+
+    pub fn verify(&self, expires: i64, sig: &str, key: &str, now: i64) -> bool {
+        if expires < now {
+            return false;
+        }
+        match self.signature(expires, key) {
+            Ok(expected) => expected.len() == sig.len() && openssl::memcmp::eq(expected.as_bytes(), sig.as_bytes()),
+            Err(_) => false,
+        }
+    }
+
+    /// Replace all public image URLs in the text with signed URLs
+    pub fn sign_all(&self, text: &str, now: i64) -> String {
+        self.url_regex.replace_all(text, |caps: &Captures| {
+            self.sign_key(&caps[1], now).unwrap_or_else(|_| caps[0].to_string())
+        }).into_owned()
+    }
+}
+
+/// Middleware that rewrites the image URLs in JSON responses into signed URLs.
+///
+/// It's a no-op if signing is disabled. Rewriting at response time also covers responses built from caches.
+pub async fn sign_image_urls(req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    let Some(signer) = URL_SIGNER.get() else {
+        return response;
+    };
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(x) => x,
+        Err(e) => {
+            error!("Failed to read response body for image url signing: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(x) => x,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    let signed = signer.sign_all(text, chrono::Utc::now().timestamp());
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(signed))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/{expires}/{sig}/{*key}", get(proxy_image))
+}
+
+/// Serve signed image URLs in proxy mode
+async fn proxy_image(
+    state: State<AppState>,
+    Path((expires, sig, key)): Path<(i64, String, String)>,
+) -> Response {
+    let Some(signer) = URL_SIGNER.get().filter(|x| x.cfg.mode == SigningMode::Proxy) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let now = chrono::Utc::now().timestamp();
+    if !key.starts_with("images/") || !signer.verify(expires, &sig, &key, now) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let result = state.file_host.download(&key).await
+        .with_context(|| format!("Failed to download {key}"));
+    match result {
+        Ok(Some(object)) => {
+            let mut response = Body::from(object.bytes).into_response();
+            let headers = response.headers_mut();
+            if let Some(content_type) = object.content_type.and_then(|x| HeaderValue::from_str(&x).ok()) {
+                headers.insert(header::CONTENT_TYPE, content_type);
+            }
+            let max_age = expires - now;
+            if let Ok(x) = HeaderValue::from_str(&format!("public, max-age={max_age}")) {
+                headers.insert(header::CACHE_CONTROL, x);
+            }
+            response
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            warn!("{:?}", e);
+            StatusCode::BAD_GATEWAY.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer(mode: SigningMode) -> UrlSigner {
+        UrlSigner::new(ImageUrlSigningCfg {
+            enabled: true,
+            secret: "secret".to_string(),
+            ttl_secs: 3600,
+            mode,
+            proxy_base_url: Some("https://api.example.com/api/image/".to_string()),
+        }, "storage.example.com").unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = signer(SigningMode::Cdn);
+        let now = 1_700_000_000;
+        let json = r#"{"cover_url":"https://storage.example.com/images/cover/abc.webp","audio":"https://storage.example.com/songs/a.mp3"}"#;
+        let signed = signer.sign_all(json, now);
+
+        let caps = Regex::new(r#"https://storage\.example\.com/_s/(\d+)/([0-9a-f]+)/(images/cover/abc\.webp)"#).unwrap()
+            .captures(&signed)
+            .unwrap();
+        let expires: i64 = caps[1].parse().unwrap();
+        assert!(expires - now >= 3600);
+        assert!(signer.verify(expires, &caps[2], &caps[3], now));
+        assert!(!signer.verify(expires, &caps[2], "images/cover/other.webp", now));
+        assert!(!signer.verify(expires, &caps[2], &caps[3], expires + 1));
+        // Non-image URLs are untouched
+        assert!(signed.contains("https://storage.example.com/songs/a.mp3"));
+        // The signed URL is stable within a window
+        assert_eq!(signed, signer.sign_all(json, now + 1));
+    }
+
+    #[test]
+    fn test_invalid_ttl() {
+        let cfg = ImageUrlSigningCfg {
+            enabled: true,
+            secret: "secret".to_string(),
+            ttl_secs: 0,
+            mode: SigningMode::Cdn,
+            proxy_base_url: None,
+        };
+        assert!(UrlSigner::new(cfg, "storage.example.com").is_err());
+    }
+
+    #[test]
+    fn test_proxy_url() {
+        let signer = signer(SigningMode::Proxy);
+        let url = signer.sign_key("images/avatar/a.webp", 0).unwrap();
+        assert!(url.starts_with("https://api.example.com/api/image/7200/"));
+        assert!(url.ends_with("/images/avatar/a.webp"));
+    }
+}
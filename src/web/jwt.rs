@@ -1,5 +1,6 @@
+use crate::config::Config;
 use crate::web::state::AppState;
-use axum::extract::FromRequestParts;
+use axum::extract::{FromRequestParts, OptionalFromRequestParts};
 use axum::http::request::Parts;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -7,32 +8,60 @@ use axum::{Json, RequestPartsExt};
 use axum_extra::headers::authorization::Bearer;
 use axum_extra::headers::Authorization;
 use axum_extra::TypedHeader;
-use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header, Validation};
+use anyhow::{bail, Context};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::OnceLock;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-static JWT_KEYS: OnceLock<Keys> = OnceLock::new();
+/// Replaced as a whole when the keys are rotated
+static JWT_KEYS: RwLock<Option<Arc<Keys>>> = RwLock::new(None);
+/// How often the config file is checked for the rotated keys
+const RELOAD_INTERVAL: Duration = Duration::from_secs(30);
 
-pub fn initialize_jwt_key(keys: Keys) {
-    match JWT_KEYS.set(keys) {
-        Ok(_) => {}
-        Err(_) => {
-            panic!("JWT keys already initialized");
-        }
-    };
+/// Set the keys, or replace them after the rotation. The tokens issued before are still accepted
+/// if their keys are kept for decoding.
+pub fn set_jwt_keys(keys: Keys) {
+    *JWT_KEYS.write().unwrap() = Some(Arc::new(keys));
+}
+
+fn keys() -> Arc<Keys> {
+    JWT_KEYS.read().unwrap().clone().expect("JWT keys not initialized")
+}
+
+fn encode(keys: &Keys, claims: &impl Serialize) -> String {
+    let header = Header { kid: keys.signing_kid.clone(), ..Header::default() };
+    jsonwebtoken::encode(&header, claims, &keys.encoding).unwrap()
 }
 
-pub fn generate_access_token(uid: &str, exp: i64) -> String {
+/// Pick the decoding key by the key id in the header, the tokens without it are issued by the legacy `jwt_secret`
+fn decode<T: DeserializeOwned>(keys: &Keys, token: &str) -> jsonwebtoken::errors::Result<T> {
+    let key = match jsonwebtoken::decode_header(token)?.kid {
+        Some(kid) => keys.decoding.get(&kid),
+        None => keys.legacy.as_ref(),
+    }.ok_or(ErrorKind::InvalidSignature)?;
+    Ok(jsonwebtoken::decode::<T>(token, key, &Validation::default())?.claims)
+}
+
+/// The roles are trusted until the role epoch of the user changes, see [crate::service::contributor::is_contributor]
+pub fn generate_access_token(uid: &str, exp: i64, roles: Vec<String>, role_epoch: i64) -> String {
     let claims = Claims {
         sub: uid.to_string(),
         iss: "hachimi-world".to_string(),
         iat: chrono::Utc::now().timestamp(),
         exp: exp,
         jti: Uuid::new_v4().to_string(),
+        roles,
+        role_epoch: Some(role_epoch),
     };
-    encode(&Header::default(), &claims, &JWT_KEYS.get().unwrap().encoding).unwrap()
+    encode(&keys(), &claims)
 }
 
 pub fn generate_refresh_token(uid: &str) -> (String, RefreshTokenClaims) {
@@ -43,10 +72,36 @@ pub fn generate_refresh_token(uid: &str) -> (String, RefreshTokenClaims) {
         exp: (chrono::Utc::now() + chrono::Duration::days(60)).timestamp() as usize,
         jti: Uuid::new_v4().to_string(),
     };
-    let encoded = encode(&Header::default(), &claims, &JWT_KEYS.get().unwrap().encoding).unwrap();
+    let encoded = encode(&keys(), &claims);
+    (encoded, claims)
+}
+
+/// The token sent by email for logging in without the password.
+///
+/// It lacks the fields of the access and refresh tokens, so it can't be used as them.
+pub fn generate_magic_link_token(uid: &str, ttl_secs: i64) -> (String, MagicLinkClaims) {
+    let claims = MagicLinkClaims {
+        r#type: "magic_link".to_string(),
+        sub: uid.to_string(),
+        exp: (chrono::Utc::now() + chrono::Duration::seconds(ttl_secs)).timestamp() as usize,
+        jti: Uuid::new_v4().to_string(),
+    };
+    let encoded = encode(&keys(), &claims);
     (encoded, claims)
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MagicLinkClaims {
+    pub r#type: String,
+    pub sub: String,
+    pub exp: usize,
+    pub jti: String,
+}
+
+pub fn decode_and_validate_magic_link_token(token: &str) -> jsonwebtoken::errors::Result<MagicLinkClaims> {
+    decode(&keys(), token)
+}
+
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct RefreshTokenClaims {
@@ -58,33 +113,111 @@ pub struct RefreshTokenClaims {
 }
 
 pub fn decode_and_validate_access_token(token: &str) -> anyhow::Result<Claims> {
-    let r = jsonwebtoken::decode::<Claims>(
-        token,
-        &JWT_KEYS.get().unwrap().decoding,
-        &Validation::default(),
-    )?;
-    Ok(r.claims)
+    Ok(decode(&keys(), token)?)
 }
 
 pub fn decode_and_validate_refresh_token(token: &str) -> jsonwebtoken::errors::Result<RefreshTokenClaims> {
-    let r = jsonwebtoken::decode::<RefreshTokenClaims>(
-        token,
-        &JWT_KEYS.get().unwrap().decoding,
-        &Validation::default(),
-    )?;
-    Ok(r.claims)
+    decode(&keys(), token)
+}
+
+/// The JWT part of the `server` config.
+///
+/// To rotate a leaked key, add a new key to `jwt_keys`, sign with it, and remove the leaked one.
+/// The sessions signed by the other keys are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JwtCfg {
+    /// The key of the tokens without a key id. Signs the new tokens if `jwt_keys` is absent
+    #[serde(default)]
+    pub jwt_secret: Option<String>,
+    /// @since 260505
+    #[serde(default)]
+    pub jwt_keys: Option<JwtKeysCfg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JwtKeysCfg {
+    /// The key id to sign the new tokens, must be one of `keys`
+    pub signing_kid: String,
+    /// All the keys still accepted
+    pub keys: Vec<JwtKeyCfg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JwtKeyCfg {
+    pub kid: String,
+    pub secret: String,
 }
 
 pub struct Keys {
+    /// In the header of the new tokens, `None` with a single secret
+    signing_kid: Option<String>,
     encoding: EncodingKey,
-    decoding: DecodingKey,
+    decoding: HashMap<String, DecodingKey>,
+    /// For the tokens without a key id
+    legacy: Option<DecodingKey>,
 }
 
 impl Keys {
     pub fn new(secret: &[u8]) -> Self {
         Self {
+            signing_kid: None,
             encoding: EncodingKey::from_secret(secret),
-            decoding: DecodingKey::from_secret(secret),
+            decoding: HashMap::new(),
+            legacy: Some(DecodingKey::from_secret(secret)),
+        }
+    }
+
+    pub fn from_cfg(cfg: &JwtCfg) -> anyhow::Result<Self> {
+        let Some(keys_cfg) = &cfg.jwt_keys else {
+            let secret = cfg.jwt_secret.as_ref().context("Either `jwt_secret` or `jwt_keys` is required")?;
+            return Ok(Self::new(secret.as_bytes()));
+        };
+        let mut decoding = HashMap::new();
+        for key in &keys_cfg.keys {
+            if key.kid.is_empty() || key.secret.is_empty() {
+                bail!("The JWT key id and secret can't be empty");
+            }
+            if decoding.insert(key.kid.clone(), DecodingKey::from_secret(key.secret.as_bytes())).is_some() {
+                bail!("Duplicated JWT key id: {}", key.kid);
+            }
+        }
+        let signing = keys_cfg.keys.iter()
+            .find(|x| x.kid == keys_cfg.signing_kid)
+            .with_context(|| format!("The signing JWT key {} is not found", keys_cfg.signing_kid))?;
+        Ok(Self {
+            signing_kid: Some(signing.kid.clone()),
+            encoding: EncodingKey::from_secret(signing.secret.as_bytes()),
+            decoding,
+            legacy: cfg.jwt_secret.as_ref().map(|x| DecodingKey::from_secret(x.as_bytes())),
+        })
+    }
+}
+
+/// Reload the keys when the JWT config in the file changes. An invalid config is logged, and the current keys are kept.
+pub async fn run_key_reloader(config: Arc<Config>, mut current: JwtCfg, cancel_token: CancellationToken) -> anyhow::Result<()> {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(RELOAD_INTERVAL) => {}
+            _ = cancel_token.cancelled() => return Ok(()),
+        }
+        let cfg = match config.reload().and_then(|x| x.get_and_parse::<JwtCfg>("server")) {
+            Ok(x) => x,
+            Err(e) => {
+                warn!("Failed to reload the JWT config: {:?}", e);
+                continue;
+            }
+        };
+        if cfg == current {
+            continue;
+        }
+        match Keys::from_cfg(&cfg) {
+            Ok(keys) => {
+                let signing_kid = keys.signing_kid.clone();
+                set_jwt_keys(keys);
+                info!(?signing_kid, "JWT keys reloaded");
+                current = cfg;
+            }
+            Err(e) => warn!("Invalid JWT config, keeping the current keys: {:?}", e),
         }
     }
 }
@@ -96,6 +229,14 @@ pub struct Claims {
     pub iat: i64,
     pub exp: i64,
     pub jti: String,
+    /// @since 260505
+    /// The roles when issued, e.g. `contributor`
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// @since 260505
+    /// The role epoch of the user when issued, `None` in the tokens issued before the roles are embedded
+    #[serde(default)]
+    pub role_epoch: Option<i64>,
 }
 
 impl Claims {
@@ -123,6 +264,21 @@ impl FromRequestParts<AppState> for Claims {
     }
 }
 
+/// `Option<Claims>` is `None` without the authorization header, but an invalid token is still rejected
+impl OptionalFromRequestParts<AppState> for Claims {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        if !parts.headers.contains_key(axum::http::header::AUTHORIZATION) {
+            return Ok(None);
+        }
+        <Claims as FromRequestParts<AppState>>::from_request_parts(parts, state).await.map(Some)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PublishVersionClaims {
 
@@ -180,28 +336,78 @@ impl IntoResponse for AuthError {
 #[cfg(test)]
 mod test {
     use crate::web::jwt;
-    use crate::web::jwt::{initialize_jwt_key, Keys};
+    use crate::web::jwt::{decode, encode, set_jwt_keys, Claims, JwtCfg, JwtKeyCfg, JwtKeysCfg, Keys};
     use chrono::{DateTime, Utc};
     use jsonwebtoken::errors::ErrorKind::ExpiredSignature;
 
     #[test]
     fn test_generate_and_validate() {
 
-        initialize_jwt_key(Keys::new(b"test"));
+        set_jwt_keys(Keys::new(b"test"));
         let expires_in = Utc::now() + chrono::Duration::days(1);
-        let access_token = jwt::generate_access_token(&"test", expires_in.timestamp());
+        let access_token = jwt::generate_access_token(&"test", expires_in.timestamp(), vec![], 0);
         let token = jwt::decode_and_validate_access_token(&access_token).unwrap();
         assert_eq!(token.sub, "test");
     }
 
     #[test]
     fn test_validate_expired_token() {
-        initialize_jwt_key(Keys::new(b"test"));
+        set_jwt_keys(Keys::new(b"test"));
         let expires_in = DateTime::parse_from_rfc3339("2023-01-01T00:00:00+00:00").unwrap();
-        let access_token = jwt::generate_access_token(&"test", expires_in.timestamp());
+        let access_token = jwt::generate_access_token(&"test", expires_in.timestamp(), vec![], 0);
         let token = jwt::decode_and_validate_access_token(&access_token);
         let token_err = token.unwrap_err();
         let err = token_err.downcast::<jsonwebtoken::errors::Error>().unwrap();
         assert_eq!(err.kind(), &ExpiredSignature);
     }
+
+    fn keys_cfg(legacy: Option<&str>, signing_kid: &str, kids: &[&str]) -> JwtCfg {
+        JwtCfg {
+            jwt_secret: legacy.map(|x| x.to_string()),
+            jwt_keys: Some(JwtKeysCfg {
+                signing_kid: signing_kid.to_string(),
+                keys: kids.iter().map(|x| JwtKeyCfg { kid: x.to_string(), secret: format!("secret-{x}") }).collect(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let claims = Claims {
+            sub: "1".to_string(),
+            iss: "hachimi-world".to_string(),
+            iat: Utc::now().timestamp(),
+            exp: (Utc::now() + chrono::Duration::days(1)).timestamp(),
+            jti: "jti".to_string(),
+            roles: vec![],
+            role_epoch: None,
+        };
+        let legacy = Keys::new(b"legacy");
+        let legacy_token = encode(&legacy, &claims);
+
+        // Switch to the key ids, keeping the legacy sessions
+        let keys = Keys::from_cfg(&keys_cfg(Some("legacy"), "a", &["a"])).unwrap();
+        assert_eq!("1", decode::<Claims>(&keys, &legacy_token).unwrap().sub);
+        let token_a = encode(&keys, &claims);
+        assert_eq!(Some("a".to_string()), jsonwebtoken::decode_header(&token_a).unwrap().kid);
+
+        // Rotate to b, the sessions signed by a are kept
+        let keys = Keys::from_cfg(&keys_cfg(None, "b", &["a", "b"])).unwrap();
+        assert!(decode::<Claims>(&keys, &token_a).is_ok());
+        assert!(decode::<Claims>(&keys, &legacy_token).is_err());
+        let token_b = encode(&keys, &claims);
+
+        // Retire a
+        let keys = Keys::from_cfg(&keys_cfg(None, "b", &["b"])).unwrap();
+        assert!(decode::<Claims>(&keys, &token_a).is_err());
+        assert!(decode::<Claims>(&keys, &token_b).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_cfg() {
+        assert!(Keys::from_cfg(&JwtCfg { jwt_secret: None, jwt_keys: None }).is_err());
+        assert!(Keys::from_cfg(&keys_cfg(None, "c", &["a", "b"])).is_err());
+        assert!(Keys::from_cfg(&keys_cfg(None, "a", &["a", "a"])).is_err());
+        assert!(Keys::from_cfg(&keys_cfg(None, "", &[""])).is_err());
+    }
 }
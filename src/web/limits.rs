@@ -0,0 +1,99 @@
+use crate::config::Config;
+use axum::extract::DefaultBodyLimit;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The size of the chunks of `/publish/upload_audio_chunk` but the last, above the minimum part size of S3
+pub const AUDIO_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+/// Room for the boundaries and the other small fields of a multipart upload
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Optional `limits` section of the config file, the absent fields take the defaults.
+///
+/// The limits are returned by `/bootstrap`, so the clients can validate before uploading.
+///
+/// ```yaml
+/// limits:
+///   audio_max_bytes: 20971520
+///   audio_chunked_max_bytes: 209715200
+///   audio_direct_max_bytes: 209715200
+///   image_max_bytes: 10485760
+///   bio_max_chars: 300
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct LimitsCfg {
+    pub audio_max_bytes: usize,
+    /// Audio uploaded in chunks, see [AUDIO_CHUNK_BYTES]
+    /// @since 260505
+    pub audio_chunked_max_bytes: usize,
+    /// Audio uploaded to the storage directly with `/song/upload/presign`
+    /// @since 260505
+    pub audio_direct_max_bytes: usize,
+    /// Covers, avatars and post images
+    pub image_max_bytes: usize,
+    pub username_max_chars: usize,
+    pub bio_max_chars: usize,
+    pub playlist_name_max_chars: usize,
+    pub playlist_description_max_chars: usize,
+    pub post_title_max_chars: usize,
+    pub post_content_max_chars: usize,
+    /// Song comments, review comments, and the comments of approvals and rejections
+    pub comment_max_chars: usize,
+    pub tag_name_max_chars: usize,
+}
+
+impl Default for LimitsCfg {
+    fn default() -> Self {
+        LimitsCfg {
+            audio_max_bytes: 20 * 1024 * 1024,
+            audio_chunked_max_bytes: 200 * 1024 * 1024,
+            audio_direct_max_bytes: 200 * 1024 * 1024,
+            image_max_bytes: 10 * 1024 * 1024,
+            username_max_chars: 10,
+            bio_max_chars: 300,
+            playlist_name_max_chars: 32,
+            playlist_description_max_chars: 300,
+            post_title_max_chars: 200,
+            post_content_max_chars: 10_000,
+            comment_max_chars: 1000,
+            tag_name_max_chars: 10,
+        }
+    }
+}
+
+impl LimitsCfg {
+    /// Load the `limits` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("limits")?.is_some() {
+            config.get_and_parse("limits")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn audio_body_limit(&self) -> DefaultBodyLimit {
+        DefaultBodyLimit::max(self.audio_max_bytes + MULTIPART_OVERHEAD_BYTES)
+    }
+
+    pub fn audio_chunk_body_limit(&self) -> DefaultBodyLimit {
+        DefaultBodyLimit::max(AUDIO_CHUNK_BYTES + MULTIPART_OVERHEAD_BYTES)
+    }
+
+    pub fn image_body_limit(&self) -> DefaultBodyLimit {
+        DefaultBodyLimit::max(self.image_max_bytes + MULTIPART_OVERHEAD_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config() {
+        let cfg: LimitsCfg = serde_yaml::from_str("bio_max_chars: 500").unwrap();
+        assert_eq!(500, cfg.bio_max_chars);
+        assert_eq!(LimitsCfg::default().audio_max_bytes, cfg.audio_max_bytes);
+    }
+}
@@ -1,45 +1,87 @@
 use crate::web::state::AppState;
-use axum::http::StatusCode;
 use axum::routing::get;
 use axum::Router;
 use serde::Deserialize;
 use std::net::SocketAddr;
 use tokio::net::ToSocketAddrs;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{error, info, info_span, Instrument};
 
 pub mod routes;
+pub mod api;
+pub mod api_codegen;
 pub mod state;
 
-mod jwt;
+pub mod jwt;
 pub mod result;
 mod web_metrics;
 mod extractors;
+mod client_ip;
+pub mod pagination;
+pub mod multipart;
+pub mod i18n;
+pub mod limits;
 mod governor;
 mod request_id;
 mod cors;
+mod image_signing;
+mod region_gate;
+mod security_headers;
+mod overload;
+mod health;
+#[cfg(debug_assertions)]
+mod files;
 
 #[derive(Deserialize)]
 pub struct ServerCfg {
     pub listen: String,
     pub metrics_listen: String,
-    pub jwt_secret: String,
+    /// Bind the metrics server on the loopback address only, whatever the host of `metrics_listen` is
+    #[serde(default = "default_metrics_localhost_only")]
+    pub metrics_localhost_only: bool,
+    /// Require `Authorization: Bearer <token>` to scrape the metrics if set
+    #[serde(default)]
+    pub metrics_token: Option<String>,
+    /// The IPs or CIDR blocks allowed to scrape the metrics, any IP is allowed if empty
+    #[serde(default)]
+    pub metrics_allow_ips: Vec<String>,
+    #[serde(flatten)]
+    pub jwt: jwt::JwtCfg,
     pub allow_origins: Vec<String>,
     pub publish_version_token: String
 }
 
+fn default_metrics_localhost_only() -> bool { true }
+
 pub async fn run_web_app(
     cfg: ServerCfg,
     app_state: AppState,
     cancel_token: CancellationToken,
 ) -> anyhow::Result<()> {
-    jwt::initialize_jwt_key(jwt::Keys::new(cfg.jwt_secret.as_bytes()));
+    let metrics_access = web_metrics::MetricsAccess::from_cfg(&cfg)?;
+    jwt::set_jwt_keys(jwt::Keys::from_cfg(&cfg.jwt)?);
+    tokio::spawn({
+        let config = app_state.config.clone();
+        let jwt_cfg = cfg.jwt.clone();
+        let cancel_token = cancel_token.clone();
+        async move {
+            if let Err(e) = jwt::run_key_reloader(config, jwt_cfg, cancel_token).await {
+                error!("JWT key reloading failed: {:?}", e);
+            }
+        }.instrument(info_span!("jwt_key_reloader"))
+    });
     jwt::initialize_version_token(cfg.publish_version_token);
+    initialize_image_signing(&app_state)?;
+    client_ip::initialize(client_ip::ClientIpCfg::load(&app_state.config)?)?;
+    region_gate::initialize(region_gate::RegionGateCfg::load(&app_state.config)?)?;
+    security_headers::initialize(security_headers::SecurityHeadersCfg::load(&app_state.config)?)?;
+    overload::initialize(overload::RequestLimitsCfg::load(&app_state.config)?)?;
+    governor::initialize(governor::RateLimitsCfg::load(&app_state.config)?)?;
 
     let allow_origins = cfg.allow_origins.iter().map(|x| x.as_str()).collect::<Vec<&str>>();
     let (_main_server, _metrics_server) = tokio::join!(
-        start_main_server(app_state, cfg.listen, &allow_origins, cancel_token.clone()),
-        web_metrics::start_metrics_server(cfg.metrics_listen, cancel_token)
+        start_main_server(app_state, &cfg.listen, &allow_origins, cancel_token.clone()),
+        web_metrics::start_metrics_server(&cfg.metrics_listen, cfg.metrics_localhost_only, metrics_access, cancel_token)
     );
 
     info!("Web server stopped");
@@ -57,11 +99,21 @@ async fn start_main_server(
     info!("HTTP Server started at {}", listener.local_addr()?);
     
     let app = Router::new()
-        .nest("/api", routes::router())
-        .route("/health", get(health))
-        .with_state(app_state)
-        .layer(governor::governor_layer())
+        .nest("/api", routes::router(&app_state.limits))
+        .nest("/api/image", image_signing::router())
+        .route("/health", get(health::ready))
+        .route("/health/live", get(health::live));
+    #[cfg(debug_assertions)]
+    let app = app.nest("/files", files::router());
+    let app = app
+        .with_state(app_state.clone())
+        .layer(axum::middleware::from_fn(image_signing::sign_image_urls))
+        .layer(axum::middleware::from_fn(security_headers::set_security_headers))
+        .layer(axum::middleware::from_fn(region_gate::gate_regions))
+        .layer(axum::middleware::from_fn_with_state(app_state, i18n::negotiate_language))
+        .layer(axum::middleware::from_fn(governor::limit_rate))
         .layer(request_id::request_id_layer())
+        .layer(axum::middleware::from_fn(overload::limit_requests))
         .layer(cors::cors_layer(allow_origins))
         .route_layer(axum::middleware::from_fn(web_metrics::track_metrics));
 
@@ -74,7 +126,12 @@ async fn start_main_server(
     Ok(())
 }
 
-async fn health() -> StatusCode {
-    // TODO[refactor]: Check more services
-    StatusCode::OK
+fn initialize_image_signing(app_state: &AppState) -> anyhow::Result<()> {
+    let cfg = if app_state.config.get("image_url_signing")?.is_some() {
+        Some(app_state.config.get_and_parse::<image_signing::ImageUrlSigningCfg>("image_url_signing")?)
+    } else {
+        None
+    };
+    let public_domain = app_state.config.get_str("s3.public_domain")?.unwrap_or_default();
+    image_signing::initialize(cfg, &public_domain)
 }
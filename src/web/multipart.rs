@@ -0,0 +1,119 @@
+use crate::common;
+use crate::web::result::{CommonError, WebError};
+use axum::extract::Multipart;
+use bytes::{Bytes, BytesMut};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+pub const IMAGE_CONTENT_TYPES: &[&str] = &[
+    "image/png", "image/jpeg", "image/webp", "image/gif", "image/avif", "application/octet-stream",
+];
+pub const AUDIO_CONTENT_TYPES: &[&str] = &[
+    "audio/mpeg", "audio/mp3", "audio/flac", "audio/x-flac", "audio/aac", "audio/mp4", "application/octet-stream",
+];
+pub const JSON_CONTENT_TYPES: &[&str] = &["application/json", "text/plain"];
+
+/// An expected field of a multipart form
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    pub max_size: usize,
+    /// Allowed content types, any type is allowed if empty.
+    /// A field without content type is always allowed, since the handlers validate the content anyway.
+    pub content_types: &'static [&'static str],
+    pub required: bool,
+}
+
+impl FieldSpec {
+    pub const fn new(name: &'static str, max_size: usize, content_types: &'static [&'static str]) -> Self {
+        FieldSpec { name, max_size, content_types, required: true }
+    }
+
+    pub const fn image(name: &'static str, max_size: usize) -> Self {
+        Self::new(name, max_size, IMAGE_CONTENT_TYPES)
+    }
+
+    pub const fn audio(name: &'static str, max_size: usize) -> Self {
+        Self::new(name, max_size, AUDIO_CONTENT_TYPES)
+    }
+
+    pub const fn json(name: &'static str, max_size: usize) -> Self {
+        Self::new(name, max_size, JSON_CONTENT_TYPES)
+    }
+
+    pub const fn optional(self) -> Self {
+        FieldSpec { required: false, ..self }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceivedField {
+    pub file_name: Option<String>,
+    pub content_type: Option<String>,
+    pub bytes: Bytes,
+}
+
+/// The fields of a multipart form, see [parse]
+#[derive(Debug)]
+pub struct MultipartFields {
+    fields: HashMap<&'static str, ReceivedField>,
+}
+
+impl MultipartFields {
+    /// Take the field, `None` if an optional field is absent
+    pub fn take(&mut self, name: &str) -> Option<ReceivedField> {
+        self.fields.remove(name)
+    }
+
+    /// Take a required field, the presence has been checked in [parse]
+    pub fn take_required(&mut self, name: &str) -> Result<ReceivedField, WebError<CommonError>> {
+        self.take(name).ok_or_else(|| common!("missing_field", "Field {name} is required"))
+    }
+
+    /// Take a required field and parse it as JSON
+    pub fn take_json<T: DeserializeOwned>(&mut self, name: &str) -> Result<T, WebError<CommonError>> {
+        let field = self.take_required(name)?;
+        serde_json::from_slice(&field.bytes).map_err(|_| common!("invalid_field", "Field {name} is not valid JSON"))
+    }
+}
+
+/// Read the fields by name instead of by position.
+///
+/// Unknown and duplicated fields are rejected, and a field is rejected as soon as it exceeds the size limit
+/// without buffering the rest.
+pub async fn parse(mut multipart: Multipart, specs: &[FieldSpec]) -> Result<MultipartFields, WebError<CommonError>> {
+    let mut fields = HashMap::new();
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        let Some(spec) = specs.iter().find(|x| x.name == name) else {
+            return Err(common!("unexpected_field", "Unexpected field {name}"));
+        };
+        if fields.contains_key(spec.name) {
+            return Err(common!("duplicated_field", "Field {name} is duplicated"));
+        }
+
+        let content_type = field.content_type().map(|x| x.to_string());
+        if let Some(content_type) = &content_type {
+            let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+            if !spec.content_types.is_empty() && !spec.content_types.contains(&essence.as_str()) {
+                return Err(common!("unsupported_content_type", "Content type {content_type} is not allowed for field {name}"));
+            }
+        }
+
+        let file_name = field.file_name().map(|x| x.to_string());
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = field.chunk().await? {
+            if buf.len() + chunk.len() > spec.max_size {
+                return Err(common!("field_too_large", "Field {name} must be less than {} bytes", spec.max_size));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+
+        fields.insert(spec.name, ReceivedField { file_name, content_type, bytes: buf.freeze() });
+    }
+
+    if let Some(spec) = specs.iter().find(|x| x.required && !fields.contains_key(x.name)) {
+        return Err(common!("missing_field", "Field {} is required", spec.name));
+    }
+    Ok(MultipartFields { fields })
+}
@@ -0,0 +1,174 @@
+use crate::config::Config;
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+static LIMITER: OnceLock<Limiter> = OnceLock::new();
+
+/// Optional `request_limits` section of the config file, the absent fields take the defaults.
+///
+/// Each request is cut off after `timeout_secs`, or the `timeout_secs` of the longest matching `route_timeouts`,
+/// 0 means no timeout. At most `max_concurrent_requests` requests are handled at the same time (0 means no limit),
+/// the others are shed immediately. Both are responded with `503` and `Retry-After`, so a slow dependency can't
+/// pile up the requests until the server runs out of memory.
+///
+/// ```yaml
+/// request_limits:
+///   timeout_secs: 30
+///   max_concurrent_requests: 1024
+///   retry_after_secs: 1
+///   route_timeouts:
+///     - route: /api/publish/upload_audio_file
+///       timeout_secs: 300
+///     - route: /api/song/detail
+///       timeout_secs: 5
+/// ```
+///
+/// The default `route_timeouts` are replaced if it's set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestLimitsCfg {
+    pub timeout_secs: u64,
+    pub max_concurrent_requests: usize,
+    pub retry_after_secs: u64,
+    pub route_timeouts: Vec<RouteTimeout>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteTimeout {
+    /// Path prefix, including the `/api` prefix
+    pub route: String,
+    pub timeout_secs: u64,
+}
+
+impl RouteTimeout {
+    fn new(route: &str, timeout_secs: u64) -> Self {
+        RouteTimeout { route: route.to_string(), timeout_secs }
+    }
+}
+
+impl Default for RequestLimitsCfg {
+    fn default() -> Self {
+        RequestLimitsCfg {
+            timeout_secs: 30,
+            max_concurrent_requests: 1024,
+            retry_after_secs: 1,
+            route_timeouts: vec![
+                // Receiving and processing the files
+                RouteTimeout::new("/api/publish/upload_audio_file", 300),
+                RouteTimeout::new("/api/publish/upload_cover_image", 120),
+                RouteTimeout::new("/api/user/set_avatar", 120),
+                // Cheap and cached, failing fast is better than holding the connections
+                RouteTimeout::new("/api/song/detail", 5),
+                RouteTimeout::new("/api/auth/refresh_token", 5),
+                RouteTimeout::new("/api/bootstrap", 5),
+            ],
+        }
+    }
+}
+
+impl RequestLimitsCfg {
+    /// Load the `request_limits` config, fallback to the default if it's absent
+    pub fn load(config: &Config) -> anyhow::Result<Self> {
+        if config.get("request_limits")?.is_some() {
+            config.get_and_parse("request_limits")
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// The timeout and the matched route of the path, `None` if there is no timeout
+    pub fn timeout_of(&self, path: &str) -> (Option<Duration>, &str) {
+        let (secs, route) = self.route_timeouts.iter()
+            .filter(|x| path.starts_with(x.route.as_str()))
+            .max_by_key(|x| x.route.len())
+            .map_or((self.timeout_secs, "default"), |x| (x.timeout_secs, x.route.as_str()));
+        ((secs > 0).then(|| Duration::from_secs(secs)), route)
+    }
+}
+
+struct Limiter {
+    cfg: RequestLimitsCfg,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+/// Should be called once before serving, nothing is limited without it
+pub fn initialize(cfg: RequestLimitsCfg) -> anyhow::Result<()> {
+    info!("Request timeout {}s, at most {} concurrent requests", cfg.timeout_secs, cfg.max_concurrent_requests);
+    let semaphore = (cfg.max_concurrent_requests > 0).then(|| Arc::new(Semaphore::new(cfg.max_concurrent_requests)));
+    if LIMITER.set(Limiter { cfg, semaphore }).is_err() {
+        anyhow::bail!("Request limits are already initialized");
+    }
+    Ok(())
+}
+
+fn unavailable(retry_after_secs: u64, msg: &'static str) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, retry_after_secs.to_string())],
+        msg,
+    ).into_response()
+}
+
+/// Shed the requests over the concurrency limit, and cut off the requests over the timeout
+pub async fn limit_requests(req: Request, next: Next) -> Response {
+    let Some(limiter) = LIMITER.get() else {
+        return next.run(req).await;
+    };
+    let _permit = match &limiter.semaphore {
+        Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+            Ok(x) => Some(x),
+            Err(_) => {
+                counter!("request_shed_count").increment(1);
+                return unavailable(limiter.cfg.retry_after_secs, "The server is busy, please try again later");
+            }
+        },
+        None => None,
+    };
+
+    let path = req.uri().path().to_string();
+    let (timeout, route) = limiter.cfg.timeout_of(&path);
+    let Some(timeout) = timeout else {
+        return next.run(req).await;
+    };
+    match tokio::time::timeout(timeout, next.run(req)).await {
+        Ok(resp) => resp,
+        Err(_) => {
+            warn!("Request to {path} timed out after {timeout:?}");
+            counter!("request_timeout_count", "route" => route.to_string()).increment(1);
+            unavailable(limiter.cfg.retry_after_secs, "The request timed out, please try again later")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_of() {
+        let cfg: RequestLimitsCfg = serde_yaml::from_str(r#"
+            timeout_secs: 30
+            route_timeouts:
+              - route: /api/song
+                timeout_secs: 10
+              - route: /api/song/detail
+                timeout_secs: 5
+              - route: /api/sse
+                timeout_secs: 0
+        "#).unwrap();
+        assert_eq!((Some(Duration::from_secs(5)), "/api/song/detail"), cfg.timeout_of("/api/song/detail"));
+        assert_eq!((Some(Duration::from_secs(10)), "/api/song"), cfg.timeout_of("/api/song/search"));
+        assert_eq!((Some(Duration::from_secs(30)), "default"), cfg.timeout_of("/api/user/profile"));
+        assert_eq!((None, "/api/sse"), cfg.timeout_of("/api/sse/events"));
+        // The absent fields take the defaults
+        assert_eq!(RequestLimitsCfg::default().max_concurrent_requests, cfg.max_concurrent_requests);
+    }
+}
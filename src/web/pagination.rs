@@ -0,0 +1,166 @@
+use crate::common;
+use crate::web::result::{CommonError, WebError};
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// The raw paging parameters in the query string, e.g. `?page_index=0&page_size=20`.
+///
+/// `page` and `size` are accepted as aliases for the legacy endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageQuery {
+    #[serde(default, alias = "page")]
+    pub page_index: i64,
+    #[serde(default = "default_page_size", alias = "size")]
+    pub page_size: i64,
+}
+
+/// The raw cursor parameters in the query string, e.g. `?cursor=xxx&page_size=20`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CursorQuery {
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_size", alias = "size")]
+    pub page_size: i64,
+}
+
+fn default_page_size() -> i64 { 20 }
+
+/// Validated page-based pagination extracted from the query string.
+///
+/// `MAX` is the hard cap of `page_size` for the route, requests exceeding it are rejected
+/// with `invalid_page_size` instead of being silently clamped.
+///
+/// ```ignore
+/// async fn page(page: Pagination<50>, req: Query<XxxReq>) -> WebResult<Page<Item>> { ... }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Pagination<const MAX: i64 = 50> {
+    pub page_index: i64,
+    pub page_size: i64,
+}
+
+impl<const MAX: i64> Pagination<MAX> {
+    pub fn offset(&self) -> i64 {
+        self.page_index * self.page_size
+    }
+
+    /// Wrap the items of the current page into the response envelope
+    pub fn into_page<T>(self, items: Vec<T>, total: i64) -> Page<T> {
+        Page {
+            items,
+            page_index: self.page_index,
+            page_size: self.page_size,
+            total,
+        }
+    }
+}
+
+impl<S, const MAX: i64> FromRequestParts<S> for Pagination<MAX>
+where
+    S: Send + Sync,
+{
+    type Rejection = WebError<CommonError>;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PageQuery>::try_from_uri(&parts.uri)
+            .map_err(|e| common!("invalid_pagination", "{}", e.body_text()))?;
+
+        if query.page_index < 0 {
+            return Err(common!("invalid_page_index", "Page index must be non-negative"));
+        }
+        if query.page_size < 1 || query.page_size > MAX {
+            return Err(common!("invalid_page_size", "Page size must be between 1 and {}", MAX));
+        }
+        // So that `offset` never overflows
+        if query.page_index.checked_mul(query.page_size).is_none() {
+            return Err(common!("invalid_page_index", "Page index is too large"));
+        }
+
+        Ok(Pagination {
+            page_index: query.page_index,
+            page_size: query.page_size,
+        })
+    }
+}
+
+/// Validated cursor-based pagination extracted from the query string.
+///
+/// The cursor is opaque to this extractor, each route decides how to decode it.
+#[derive(Debug, Clone)]
+pub struct CursorPagination<const MAX: i64 = 50> {
+    pub cursor: Option<String>,
+    pub page_size: i64,
+}
+
+impl<S, const MAX: i64> FromRequestParts<S> for CursorPagination<MAX>
+where
+    S: Send + Sync,
+{
+    type Rejection = WebError<CommonError>;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<CursorQuery>::try_from_uri(&parts.uri)
+            .map_err(|e| common!("invalid_pagination", "{}", e.body_text()))?;
+
+        if query.page_size < 1 || query.page_size > MAX {
+            return Err(common!("invalid_page_size", "Page size must be between 1 and {}", MAX));
+        }
+
+        Ok(CursorPagination {
+            cursor: query.cursor.filter(|x| !x.is_empty()),
+            page_size: query.page_size,
+        })
+    }
+}
+
+/// The unified response envelope of page-based list endpoints.
+///
+/// ```json
+/// {
+///     "items": [],
+///     "page_index": 0,
+///     "page_size": 20,
+///     "total": 100
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page_index: i64,
+    pub page_size: i64,
+    pub total: i64,
+}
+
+/// The unified response envelope of cursor-based list endpoints.
+///
+/// `next_cursor` is `null` when there are no more items.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn extract(uri: &str) -> Result<Pagination<50>, WebError<CommonError>> {
+        let (mut parts, _) = Request::builder().uri(uri).body(()).unwrap().into_parts();
+        Pagination::<50>::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let page = extract("/?page_index=2&page_size=20").await.unwrap();
+        assert_eq!(40, page.offset());
+        let page = extract("/?page=1&size=10").await.unwrap();
+        assert_eq!(10, page.offset());
+
+        assert!(extract("/?page_index=-1").await.is_err());
+        assert!(extract("/?page_size=51").await.is_err());
+        // The offset would overflow
+        assert!(extract(&format!("/?page_index={}&page_size=50", i64::MAX / 2)).await.is_err());
+    }
+}
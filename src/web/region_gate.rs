@@ -0,0 +1,301 @@
+use crate::common;
+use crate::config::Config;
+use crate::web::client_ip;
+use axum::extract::{ConnectInfo, Request};
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+static REGION_GATE: OnceLock<RegionGate> = OnceLock::new();
+
+const GEOIP_TIMEOUT: Duration = Duration::from_secs(2);
+const GEOIP_CACHE_TTL: Duration = Duration::from_secs(3600);
+const GEOIP_CACHE_CAPACITY: usize = 100_000;
+
+/// Optional `region_gate` section of the config file, required by the distribution restrictions of some content.
+///
+/// The country of the client is taken from `country_header` set by the CDN, or looked up by IP from the GeoIP
+/// service if the header is absent. The header is only read if the peer is one of the `trusted_proxies` of the
+/// `client_ip` section, otherwise any client could claim its country by it.
+///
+/// The requests from an unknown country are restricted by every rule covering the route unless `unknown_country`
+/// is `allow`, so failing to look up the country doesn't open the restricted content.
+///
+/// ```yaml
+/// region_gate:
+///   enabled: true
+///   country_header: CF-IPCountry
+///   # `{ip}` is replaced, the response should be a JSON object with the ISO 3166-1 alpha-2 code in `geoip_field`
+///   geoip_url: https://geoip.example.com/json/{ip}
+///   geoip_field: country_code
+///   unknown_country: restrict # restrict | allow
+///   rules:
+///     - countries: [ZZ]
+///       routes: [/api/song/detail, /api/song/search]
+///       action: block # block | read_only
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionGateCfg {
+    #[serde(default)]
+    pub enabled: bool,
+    pub country_header: Option<String>,
+    pub geoip_url: Option<String>,
+    #[serde(default = "default_geoip_field")]
+    pub geoip_field: String,
+    #[serde(default)]
+    pub unknown_country: UnknownCountryPolicy,
+    #[serde(default)]
+    pub rules: Vec<RegionRule>,
+}
+
+fn default_geoip_field() -> String { "country_code".to_string() }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionRule {
+    pub countries: Vec<String>,
+    /// Path prefixes, including the `/api` prefix
+    pub routes: Vec<String>,
+    #[serde(default)]
+    pub action: RegionAction,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownCountryPolicy {
+    /// Apply the rules of every country
+    #[default]
+    Restrict,
+    Allow,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionAction {
+    /// Reject every request
+    #[default]
+    Block,
+    /// Only allow the `GET` requests
+    ReadOnly,
+}
+
+impl RegionGateCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("region_gate")?.is_some() {
+            Ok(Some(config.get_and_parse("region_gate")?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether the request of the country is restricted, `None` for an unknown country
+    pub fn is_restricted(&self, country: Option<&str>, method: &Method, path: &str) -> bool {
+        if country.is_none() && self.unknown_country == UnknownCountryPolicy::Allow {
+            return false;
+        }
+        self.rules.iter()
+            .filter(|rule| country.is_none_or(|country| rule.countries.iter().any(|x| x.eq_ignore_ascii_case(country))))
+            .filter(|rule| rule.routes.iter().any(|x| path.starts_with(x.as_str())))
+            .any(|rule| match rule.action {
+                RegionAction::Block => true,
+                RegionAction::ReadOnly => method != Method::GET && method != Method::HEAD,
+            })
+    }
+}
+
+struct RegionGate {
+    cfg: RegionGateCfg,
+    http: reqwest::Client,
+    cache: Mutex<HashMap<IpAddr, (Option<String>, Instant)>>,
+}
+
+impl RegionGate {
+    /// Only set by the trusted proxies
+    fn country_from_header(&self, req: &Request) -> Option<String> {
+        let header = self.cfg.country_header.as_ref()?;
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|x| x.ip())?;
+        if !client_ip::is_trusted_proxy(peer) {
+            return None;
+        }
+        req.headers().get(header.as_str())
+            .and_then(|x| x.to_str().ok())
+            // Cloudflare uses XX for unknown
+            .filter(|x| x.len() == 2 && *x != "XX")
+            .map(|x| x.to_ascii_uppercase())
+    }
+
+    async fn country_by_ip(&self, ip: IpAddr) -> Option<String> {
+        let now = Instant::now();
+        if let Some((country, expire)) = self.cache.lock().unwrap().get(&ip) && *expire > now {
+            return country.clone();
+        }
+        let country = self.lookup(ip).await;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= GEOIP_CACHE_CAPACITY {
+            cache.retain(|_, (_, expire)| *expire > now);
+            if cache.len() >= GEOIP_CACHE_CAPACITY {
+                cache.clear();
+            }
+        }
+        cache.insert(ip, (country.clone(), now + GEOIP_CACHE_TTL));
+        country
+    }
+
+    async fn countries_by_ips<'a>(&self, ips: impl IntoIterator<Item = &'a str>) -> HashMap<&'a str, String> {
+        let ips: HashSet<&str> = ips.into_iter().collect();
+        let lookups = ips.into_iter()
+            .filter_map(|raw| Some((raw, raw.parse::<IpAddr>().ok()?)))
+            .map(async |(raw, ip)| (raw, self.country_by_ip(ip).await));
+        futures::future::join_all(lookups).await
+            .into_iter()
+            .filter_map(|(raw, country)| Some((raw, country?)))
+            .collect()
+    }
+
+    async fn lookup(&self, ip: IpAddr) -> Option<String> {
+        let url = self.cfg.geoip_url.as_ref()?.replace("{ip}", &ip.to_string());
+        let result = async {
+            let value: serde_json::Value = self.http.get(url)
+                .timeout(GEOIP_TIMEOUT)
+                .send().await?
+                .error_for_status()?
+                .json().await?;
+            anyhow::Ok(value.get(&self.cfg.geoip_field).and_then(|x| x.as_str()).map(|x| x.to_ascii_uppercase()))
+        }.await;
+        match result {
+            Ok(x) => x,
+            Err(e) => {
+                warn!("GeoIP lookup failed for {ip}: {:?}", e);
+                counter!("geoip_lookup_error_count").increment(1);
+                None
+            }
+        }
+    }
+}
+
+fn client_ip(req: &Request) -> Option<IpAddr> {
+    let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|x| x.ip());
+    client_ip::client_ip(req.headers(), peer)
+}
+
+/// Enable the gate if `region_gate.enabled` is true, should be called once before serving.
+pub fn initialize(cfg: Option<RegionGateCfg>) -> anyhow::Result<()> {
+    let Some(cfg) = cfg.filter(|x| x.enabled) else {
+        return Ok(());
+    };
+    info!("Region gate enabled with {} rules", cfg.rules.len());
+    let gate = RegionGate { cfg, http: reqwest::Client::new(), cache: Mutex::new(HashMap::new()) };
+    if REGION_GATE.set(gate).is_err() {
+        anyhow::bail!("Region gate is already initialized");
+    }
+    Ok(())
+}
+
+/// Look up the countries of the IP addresses by the GeoIP service, each distinct one once and all of them
+/// concurrently. Keyed by the IP addresses as given, the unknown ones are absent and so are all of them if the gate
+/// is disabled.
+pub async fn countries_of<'a>(ips: impl IntoIterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    match REGION_GATE.get() {
+        Some(gate) => gate.countries_by_ips(ips).await,
+        None => HashMap::new(),
+    }
+}
+
+/// Reject the requests restricted in the country of the client with `region_restricted`
+pub async fn gate_regions(req: Request, next: Next) -> Response {
+    let Some(gate) = REGION_GATE.get() else {
+        return next.run(req).await;
+    };
+    // Only look up the country if any rule covers the route
+    let path = req.uri().path();
+    if !gate.cfg.rules.iter().any(|rule| rule.routes.iter().any(|x| path.starts_with(x.as_str()))) {
+        return next.run(req).await;
+    }
+    let country = match gate.country_from_header(&req) {
+        Some(x) => Some(x),
+        None => match client_ip(&req) {
+            Some(ip) => gate.country_by_ip(ip).await,
+            None => None,
+        },
+    };
+    if country.is_none() {
+        counter!("region_gate_unknown_country_count").increment(1);
+    }
+    if gate.cfg.is_restricted(country.as_deref(), req.method(), req.uri().path()) {
+        let country = country.unwrap_or_else(|| "unknown".to_string());
+        counter!("region_gate_blocked_count", "country" => country.clone()).increment(1);
+        return common!("region_restricted", "This content is not available in your region ({country})").into_response();
+    }
+    next.run(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_restricted() {
+        let cfg: RegionGateCfg = serde_yaml::from_str(r#"
+            enabled: true
+            rules:
+              - countries: [AA]
+                routes: [/api/song/detail]
+              - countries: [BB]
+                routes: [/api/publish]
+                action: read_only
+        "#).unwrap();
+        assert!(cfg.is_restricted(Some("AA"), &Method::GET, "/api/song/detail"));
+        assert!(cfg.is_restricted(Some("aa"), &Method::GET, "/api/song/detail"));
+        assert!(!cfg.is_restricted(Some("AA"), &Method::GET, "/api/song/search"));
+        assert!(!cfg.is_restricted(Some("CC"), &Method::GET, "/api/song/detail"));
+        assert!(!cfg.is_restricted(Some("BB"), &Method::GET, "/api/publish/review/page"));
+        assert!(cfg.is_restricted(Some("BB"), &Method::POST, "/api/publish/upload_audio_file"));
+        // Fail closed
+        assert!(cfg.is_restricted(None, &Method::GET, "/api/song/detail"));
+        assert!(!cfg.is_restricted(None, &Method::GET, "/api/publish/review/page"));
+        assert!(cfg.is_restricted(None, &Method::POST, "/api/publish/upload_audio_file"));
+        assert!(!cfg.is_restricted(None, &Method::GET, "/api/song/search"));
+
+        let cfg = RegionGateCfg { unknown_country: UnknownCountryPolicy::Allow, ..cfg };
+        assert!(!cfg.is_restricted(None, &Method::GET, "/api/song/detail"));
+    }
+
+    #[test]
+    fn test_country_from_header() {
+        let gate = RegionGate {
+            cfg: serde_yaml::from_str("country_header: CF-IPCountry").unwrap(),
+            http: reqwest::Client::new(),
+            cache: Mutex::new(HashMap::new()),
+        };
+        let request = |peer: &str, country: &str| {
+            let mut req = Request::builder().header("CF-IPCountry", country).body(axum::body::Body::empty()).unwrap();
+            req.extensions_mut().insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 443)));
+            req
+        };
+        assert_eq!(Some("AA".to_string()), gate.country_from_header(&request("127.0.0.1", "aa")));
+        assert_eq!(None, gate.country_from_header(&request("127.0.0.1", "XX")));
+        // Spoofed by the client reaching the server directly
+        assert_eq!(None, gate.country_from_header(&request("8.8.8.8", "AA")));
+    }
+
+    #[tokio::test]
+    async fn test_countries_by_ips() {
+        // Without `geoip_url`, only the cached countries are known
+        let gate = RegionGate {
+            cfg: serde_yaml::from_str("enabled: true").unwrap(),
+            http: reqwest::Client::new(),
+            cache: Mutex::new(HashMap::from([
+                ("1.2.3.4".parse().unwrap(), (Some("AA".to_string()), Instant::now() + GEOIP_CACHE_TTL)),
+            ])),
+        };
+        let countries = gate.countries_by_ips(["1.2.3.4", "1.2.3.4", "5.6.7.8", "not an ip"]).await;
+        assert_eq!(HashMap::from([("1.2.3.4", "AA".to_string())]), countries);
+        assert_eq!(2, gate.cache.lock().unwrap().len());
+    }
+}
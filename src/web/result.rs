@@ -1,3 +1,4 @@
+use crate::web::i18n;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::Json;
@@ -156,7 +157,8 @@ where
 ///     "ok": false,
 ///     "data": {
 ///         "code": "permission_denied",
-///         "msg": "You don't have permission to access this resource"
+///         "msg": "You don't have permission to access this resource",
+///         "localized_msg": "你没有权限进行此操作"
 ///     }
 /// }
 /// ```
@@ -168,6 +170,13 @@ pub struct CommonError {
     /// Explain the reason, could be displayed to user.
     /// e.g: `"You don't have permission to access this resource"`
     pub msg: String,
+    /// The message of the code in the language of the request, filled when responding.
+    /// Absent if the code is not in the catalog, see [crate::web::i18n]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localized_msg: Option<String>,
+    /// Structured data for the clients to handle the error, e.g. the current usage of `storage_quota_exceeded`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<serde_json::Value>,
 }
 
 impl <T> WebResponse<T> {
@@ -191,6 +200,17 @@ impl WebError<CommonError> {
         WebError::Business(CommonError {
             code: code.to_string(),
             msg: msg.to_string(),
+            localized_msg: None,
+            detail: None,
+        })
+    }
+
+    pub fn common_with_detail(code: &str, msg: &str, detail: serde_json::Value) -> Self {
+        WebError::Business(CommonError {
+            code: code.to_string(),
+            msg: msg.to_string(),
+            localized_msg: None,
+            detail: Some(detail),
         })
     }
 }
@@ -206,7 +226,12 @@ impl<E: Serialize + 'static> IntoResponse for WebError<E> {
                         error_code = as_common_err.code,
                         error_msg = as_common_err.msg,
                         "Common error"
-                    )
+                    );
+                    let localized = CommonError {
+                        localized_msg: i18n::localize(&as_common_err.code, i18n::current_lang()).map(|x| x.to_string()),
+                        ..as_common_err.clone()
+                    };
+                    return Json(WebResponse::err(localized)).into_response();
                 } else {
                     tracing::info!("Business error")
                 }
@@ -0,0 +1,903 @@
+use crate::db::error::DbError;
+use crate::db::featured_song::{FeaturedSong, FeaturedSongDao, IFeaturedSongDao};
+use crate::db::hidden_song::{HiddenSongDao, IHiddenSongDao};
+use crate::db::legal_document::{ILegalDocumentDao, LegalDocument, LegalDocumentDao};
+use crate::db::review_guideline_snippet::{IReviewGuidelineSnippetDao, ReviewGuidelineSnippet, ReviewGuidelineSnippetDao, ReviewGuidelineSnippetVersion};
+use crate::db::search_stat::{ISearchStatDao, SearchPositionTotal, SearchQueryTotal, SearchStatDao};
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::song_edit_log::{SongEditLog, SongEditLogDao};
+use crate::db::song_report::{self, ISongReportDao, SongReportDao};
+use crate::db::song_tag::{ISongTagDao, SongTagAlias, SongTagDao};
+use crate::db::user::UserDao;
+use crate::db::user_legal_hold::{IUserLegalHoldDao, UserLegalHold, UserLegalHoldDao};
+use crate::db::user_shadow_ban::{IUserShadowBanDao, UserShadowBan, UserShadowBanDao};
+use crate::db::CrudDao;
+use crate::service::contributor::{ensure_contributor, RoleError};
+use crate::service::moderation::SetBannedError;
+use crate::service::review_guideline::{SaveSnippetError, SnippetEdit};
+use crate::service::tag_alias::{self, AliasError};
+use crate::service::{cache_warming, contributor, legal, moderation, near_duplicate, playlist, recommend_v2, retention, review_guideline, shadow_ban, song, song_play, song_report as song_report_service, song_stats, tombstone, upload_cleanup, weekly_selection};
+use crate::util::scheduler::{LastRun, Scheduler};
+use crate::util::IsBlank;
+use crate::web::jwt::Claims;
+use crate::web::pagination::{Page, PageQuery, Pagination};
+use crate::web::result::WebResult;
+use crate::web::routes;
+use crate::web::state::AppState;
+use crate::{common, err, ok, search};
+use async_backtrace::framed;
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/user/ban", post(ban_user))
+        .route("/user/unban", post(unban_user))
+        // @since 260430
+        .route("/user/shadow_ban", post(shadow_ban_user))
+        // @since 260430
+        .route("/user/shadow_ban/list", get(list_shadow_bans))
+        .route("/song/edit", post(edit_song))
+        .route("/featured/list", get(list_featured))
+        .route("/featured/add", post(add_featured))
+        .route("/featured/remove", post(remove_featured))
+        .route("/search/stats", get(search_stats))
+        // @since 260430
+        .route("/song/recount", post(recount_song))
+        // @since 260430
+        .route("/guideline/list", get(list_guidelines))
+        // @since 260430
+        .route("/guideline/save", post(save_guideline))
+        // @since 260430
+        .route("/guideline/versions", get(guideline_versions))
+        // @since 260430
+        .route("/scheduler/jobs", get(scheduler_jobs))
+        // @since 260502
+        .route("/tag/alias/list", get(list_tag_aliases))
+        // @since 260502
+        .route("/tag/alias/add", post(add_tag_alias))
+        // @since 260502
+        .route("/tag/alias/delete", post(delete_tag_alias))
+        // @since 260505
+        .route("/role/grant", post(grant_role))
+        // @since 260505
+        .route("/role/revoke", post(revoke_role))
+        // @since 260505
+        .route("/legal/publish", post(publish_legal))
+        // @since 260505
+        .route("/song/report/page", get(page_song_reports))
+        // @since 260505
+        .route("/song/report/resolve", post(resolve_song_report))
+        // @since 260505
+        .route("/song/hide", post(hide_song))
+        // @since 260505
+        .route("/user/legal_hold", post(set_legal_hold))
+        // @since 260505
+        .route("/user/legal_hold/list", get(list_legal_holds))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BanUserReq {
+    pub uid: i64,
+}
+
+#[framed]
+async fn ban_user(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<BanUserReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    if req.uid == claims.uid() {
+        err!("invalid_uid", "You can't ban yourself")
+    }
+    set_banned(&state, req.uid, true).await
+}
+
+#[framed]
+async fn unban_user(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<BanUserReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    set_banned(&state, req.uid, false).await
+}
+
+async fn set_banned(state: &AppState, uid: i64, banned: bool) -> WebResult<()> {
+    match moderation::set_banned(state, uid, banned).await {
+        Ok(_) => ok!(()),
+        Err(SetBannedError::UserNotFound { .. }) => err!("not_found", "User not found"),
+        Err(e) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ShadowBanUserReq {
+    pub uid: i64,
+    /// The shadow-banned features, see [shadow_ban::FEATURES], empty to lift the shadow ban
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Set the shadow-banned features of a spam account, see [shadow_ban]
+#[framed]
+async fn shadow_ban_user(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ShadowBanUserReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    if req.uid == claims.uid() {
+        err!("invalid_uid", "You can't ban yourself")
+    }
+    if !shadow_ban::is_valid_features(&req.features) {
+        err!("invalid_shadow_ban_features", "Unknown features, must be in {:?}", shadow_ban::FEATURES)
+    }
+    if UserDao::get_by_id(&state.sql_pool, req.uid).await?.is_none() {
+        err!("not_found", "User not found")
+    }
+    shadow_ban::set(&state.sql_pool, claims.uid(), req.uid, &req.features, &req.reason).await?;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListShadowBansResp {
+    pub items: Vec<UserShadowBan>,
+}
+
+#[framed]
+async fn list_shadow_bans(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<ListShadowBansResp> {
+    ensure_contributor(&state, &claims).await?;
+    let items = UserShadowBanDao::list(&state.sql_pool).await?;
+    ok!(ListShadowBansResp { items })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetLegalHoldReq {
+    pub uid: i64,
+    /// `false` to release the hold
+    pub hold: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetLegalHoldResp {
+    /// Whether the hold status changed
+    pub changed: bool,
+}
+
+/// Exempt the data of the user from the retention purges or release it, see [retention]
+#[framed]
+async fn set_legal_hold(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<SetLegalHoldReq>,
+) -> WebResult<SetLegalHoldResp> {
+    ensure_contributor(&state, &claims).await?;
+    if UserDao::get_by_id(&state.sql_pool, req.uid).await?.is_none() {
+        err!("not_found", "User not found")
+    }
+    let changed = if req.hold {
+        UserLegalHoldDao::insert(&state.sql_pool, &UserLegalHold {
+            user_id: req.uid,
+            reason: req.reason.trim().to_string(),
+            held_by: claims.uid(),
+            create_time: Utc::now(),
+        }).await?
+    } else {
+        UserLegalHoldDao::delete_by_user_id(&state.sql_pool, req.uid).await?
+    };
+    info!("User {} set the legal hold of user {}: {}, changed: {}", claims.uid(), req.uid, req.hold, changed);
+    ok!(SetLegalHoldResp { changed })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListLegalHoldsResp {
+    pub items: Vec<UserLegalHold>,
+}
+
+#[framed]
+async fn list_legal_holds(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<ListLegalHoldsResp> {
+    ensure_contributor(&state, &claims).await?;
+    let items = UserLegalHoldDao::list(&state.sql_pool).await?;
+    ok!(ListLegalHoldsResp { items })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EditSongReq {
+    pub song_id: i64,
+    /// Fields that are `None` are kept unchanged
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub description: Option<String>,
+    pub tag_ids: Option<Vec<i64>>,
+    /// Why the song is edited, saved in the audit record
+    pub note: Option<String>,
+}
+
+/// The editable metadata, saved as the before and after data of the audit record
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SongMetadata {
+    title: String,
+    subtitle: String,
+    description: String,
+    tag_ids: Vec<i64>,
+}
+
+/// Fix the metadata of a published song in place, without going through the review again
+#[framed]
+async fn edit_song(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<EditSongReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+
+    if let Some(tag_ids) = &req.tag_ids {
+        let tags = SongTagDao::list_by_ids(&state.sql_pool, tag_ids).await?;
+        if tags.len() != tag_ids.iter().unique().count() {
+            err!("tag_not_found", "Some tags not found");
+        }
+    }
+
+    let mut song = SongDao::get_by_id(&state.sql_pool, req.song_id).await?
+        .ok_or_else(|| common!("not_found", "Song not found"))?;
+    let before = SongMetadata {
+        title: song.title.clone(),
+        subtitle: song.subtitle.clone(),
+        description: song.description.clone(),
+        tag_ids: SongDao::list_tags_by_song_id(&state.sql_pool, song.id).await?,
+    };
+    let after = SongMetadata {
+        title: req.title.clone().unwrap_or_else(|| before.title.clone()),
+        subtitle: req.subtitle.clone().unwrap_or_else(|| before.subtitle.clone()),
+        description: req.description.clone().unwrap_or_else(|| before.description.clone()),
+        tag_ids: req.tag_ids.as_ref().map(|x| x.iter().copied().unique().collect())
+            .unwrap_or_else(|| before.tag_ids.clone()),
+    };
+    if before == after {
+        ok!(())
+    }
+    routes::publish::validate_song_metadata(&after.title, &after.subtitle, &after.description)?;
+
+    song.title = after.title.clone();
+    song.subtitle = after.subtitle.clone();
+    song.description = after.description.clone();
+    song.update_time = Utc::now();
+
+    let mut tx = state.sql_pool.begin().await?;
+    match SongDao::update_by_id_checked(&mut *tx, &song).await {
+        Ok(_) => {}
+        Err(DbError::VersionConflict) => err!("version_conflict", "The song has been modified concurrently, please retry"),
+        Err(e) => Err(e)?,
+    }
+    if after.tag_ids != before.tag_ids {
+        SongDao::update_song_tags(&mut tx, song.id, after.tag_ids.clone()).await?;
+    }
+    if after.title != before.title {
+        near_duplicate::save_fingerprint(&mut *tx, song.id, &song.title, &song.lyrics).await?;
+    }
+    SongEditLogDao::insert(&mut *tx, &SongEditLog {
+        id: 0,
+        song_id: song.id,
+        editor_uid: claims.uid(),
+        note: req.note.clone(),
+        before_data: serde_json::to_value(&before)?,
+        after_data: serde_json::to_value(&after)?,
+        create_time: Utc::now(),
+    }).await?;
+    tx.commit().await?;
+
+    // Make the edit visible immediately
+    song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(&song)).await?;
+    routes::song::evict_page_by_user_cache(state.redis_conn.clone(), song.uploader_uid).await?;
+    recommend_v2::evict_list_caches(state.redis_conn.clone()).await?;
+    search::song::add_or_replace_document(&state.meilisearch, &state.sql_pool, &[song.id]).await?;
+    ok!(())
+}
+
+/// The longest name of a featured collection
+const MAX_COLLECTION_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListFeaturedReq {
+    pub collection: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListFeaturedResp {
+    pub songs: Vec<FeaturedSong>,
+}
+
+#[framed]
+async fn list_featured(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<ListFeaturedReq>,
+) -> WebResult<ListFeaturedResp> {
+    ensure_contributor(&state, &claims).await?;
+    let songs = FeaturedSongDao::list_by_collection(&state.sql_pool, &req.collection).await?;
+    ok!(ListFeaturedResp { songs })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddFeaturedReq {
+    /// e.g. `onboarding` for the starter set of the new users, or `weekly_picks` for the next weekly selection
+    pub collection: String,
+    pub song_id: i64,
+    /// Smaller first, re-adding a song moves it
+    #[serde(default)]
+    pub position: i32,
+    pub note: Option<String>,
+}
+
+/// Add a song to a featured collection, or update it if it's already in
+#[framed]
+async fn add_featured(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<AddFeaturedReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    if req.collection.is_blank() || req.collection.len() > MAX_COLLECTION_LEN {
+        err!("invalid_collection", "Collection must be 1 to {MAX_COLLECTION_LEN} characters")
+    }
+    if SongDao::get_by_id(&state.sql_pool, req.song_id).await?.is_none() {
+        err!("not_found", "Song not found")
+    }
+    FeaturedSongDao::upsert(&state.sql_pool, &FeaturedSong {
+        id: 0,
+        collection: req.collection.clone(),
+        song_id: req.song_id,
+        position: req.position,
+        note: req.note.clone(),
+        featured_by: claims.uid(),
+        create_time: Utc::now(),
+    }).await?;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoveFeaturedReq {
+    pub collection: String,
+    pub song_id: i64,
+}
+
+#[framed]
+async fn remove_featured(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<RemoveFeaturedReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    if !FeaturedSongDao::remove(&state.sql_pool, &req.collection, req.song_id).await? {
+        err!("not_found", "Song is not in the collection")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchStatsReq {
+    /// Since this many days ago, 7 by default
+    pub days: Option<i64>,
+    /// The most searched queries, 100 by default
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchStatsResp {
+    pub queries: Vec<SearchQueryTotal>,
+    /// The clicks by the zero-based position of the hit
+    pub positions: Vec<SearchPositionTotal>,
+}
+
+/// The click-through rollups of the song search, for evaluating the ranking changes
+#[framed]
+async fn search_stats(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<SearchStatsReq>,
+) -> WebResult<SearchStatsResp> {
+    ensure_contributor(&state, &claims).await?;
+    let days = req.days.unwrap_or(7).clamp(1, 90);
+    let limit = req.limit.unwrap_or(100).clamp(1, 1000);
+    let since = Utc::now().date_naive() - chrono::Days::new(days as u64 - 1);
+    let queries = SearchStatDao::list_query_totals(&state.sql_pool, since, limit).await?;
+    let positions = SearchStatDao::list_position_totals(&state.sql_pool, since).await?;
+    ok!(SearchStatsResp { queries, positions })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecountSongReq {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecountSongResp {
+    pub play_count: i64,
+    pub like_count: i64,
+    /// The counts before recounting differed from the plays and likes, and are repaired
+    pub drifted: bool,
+    pub old_play_count: i64,
+    pub old_like_count: i64,
+}
+
+/// Recompute the play and like counts of the song from its plays and likes
+#[framed]
+async fn recount_song(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<RecountSongReq>,
+) -> WebResult<RecountSongResp> {
+    ensure_contributor(&state, &claims).await?;
+    let Some(song) = SongDao::get_by_id(&state.sql_pool, req.id).await? else {
+        err!("not_found", "Song not found")
+    };
+    let drifts = song_stats::recount(&mut state.redis_conn.clone(), &state.red_lock, &state.sql_pool, Some(song.id)).await?;
+    let resp = match drifts.into_iter().next() {
+        Some(x) => RecountSongResp {
+            play_count: x.play_count,
+            like_count: x.like_count,
+            drifted: true,
+            old_play_count: x.old_play_count,
+            old_like_count: x.old_like_count,
+        },
+        None => RecountSongResp {
+            play_count: song.play_count,
+            like_count: song.like_count,
+            drifted: false,
+            old_play_count: song.play_count,
+            old_like_count: song.like_count,
+        },
+    };
+    ok!(resp)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListGuidelinesResp {
+    /// Including the archived ones
+    pub snippets: Vec<ReviewGuidelineSnippet>,
+}
+
+#[framed]
+async fn list_guidelines(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<ListGuidelinesResp> {
+    ensure_contributor(&state, &claims).await?;
+    let snippets = ReviewGuidelineSnippetDao::list(&state.sql_pool, true).await?;
+    ok!(ListGuidelinesResp { snippets })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SaveGuidelineReq {
+    pub slug: String,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub archived: bool,
+    /// The version being edited, `None` to create the snippet
+    pub version: Option<i32>,
+}
+
+/// Create or edit a review guideline snippet, see [review_guideline]
+#[framed]
+async fn save_guideline(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<SaveGuidelineReq>,
+) -> WebResult<ReviewGuidelineSnippet> {
+    ensure_contributor(&state, &claims).await?;
+    if !review_guideline::is_valid_slug(&req.slug) {
+        err!("invalid_slug", "Slug must be 1 to {} lowercase letters, digits or dashes", review_guideline::SLUG_MAX_CHARS)
+    }
+    if req.title.is_blank() || req.title.chars().count() > review_guideline::TITLE_MAX_CHARS {
+        err!("invalid_title", "Title must be 1 to {} characters", review_guideline::TITLE_MAX_CHARS)
+    }
+    // Inserted into the review comments
+    if req.content.is_blank() {
+        err!("comment_required", "Content is required")
+    }
+    if req.content.chars().count() > state.limits.comment_max_chars {
+        err!("content_too_long", "Content is too long")
+    }
+
+    let edit = SnippetEdit {
+        title: req.title.clone(),
+        content: req.content.clone(),
+        archived: req.archived,
+    };
+    let result = match req.version {
+        None => review_guideline::create(&state.sql_pool, claims.uid(), &req.slug, edit).await,
+        Some(version) => review_guideline::update(&state.sql_pool, claims.uid(), &req.slug, version, edit).await,
+    };
+    match result {
+        Ok(x) => ok!(x),
+        Err(SaveSnippetError::SlugExists { .. }) => err!("slug_exists", "Snippet {} already exists", req.slug),
+        Err(SaveSnippetError::NotFound { .. }) => err!("not_found", "Snippet not found"),
+        Err(SaveSnippetError::VersionConflict { .. }) => err!("version_conflict", "The snippet has been modified concurrently, please retry"),
+        Err(e) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GuidelineVersionsReq {
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GuidelineVersionsResp {
+    /// Newest first
+    pub versions: Vec<ReviewGuidelineSnippetVersion>,
+}
+
+#[framed]
+async fn guideline_versions(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<GuidelineVersionsReq>,
+) -> WebResult<GuidelineVersionsResp> {
+    ensure_contributor(&state, &claims).await?;
+    let Some(snippet) = ReviewGuidelineSnippetDao::get_by_slug(&state.sql_pool, &req.slug).await? else {
+        err!("not_found", "Snippet not found")
+    };
+    let versions = ReviewGuidelineSnippetDao::list_versions(&state.sql_pool, snippet.id).await?;
+    ok!(GuidelineVersionsResp { versions })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SchedulerJobsResp {
+    pub items: Vec<SchedulerJobItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SchedulerJobItem {
+    pub name: String,
+    pub schedule: String,
+    pub timezone: String,
+    pub enabled: bool,
+    pub next_time: Option<DateTime<Utc>>,
+    pub last_run: Option<LastRun>,
+}
+
+/// The periodic jobs with their effective schedules and last runs
+#[framed]
+async fn scheduler_jobs(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<SchedulerJobsResp> {
+    ensure_contributor(&state, &claims).await?;
+    let scheduler = Scheduler::from_state(&state)?;
+    let jobs = [
+        cache_warming::RECENT_JOB,
+        cache_warming::HOT_JOB,
+        playlist::SORT_KEY_NORMALIZATION_JOB,
+        song_stats::VERIFICATION_JOB,
+        near_duplicate::BACKFILL_JOB,
+        weekly_selection::PUBLISH_JOB,
+        retention::PURGE_JOB,
+        tombstone::PURGE_JOB,
+        song_play::FLUSH_JOB,
+        song_play::ROLLUP_JOB,
+        upload_cleanup::CLEANUP_JOB,
+    ];
+    let mut items = Vec::with_capacity(jobs.len());
+    for job in &jobs {
+        let job = scheduler.cfg().resolve(job)?;
+        items.push(SchedulerJobItem {
+            name: job.name.to_string(),
+            schedule: job.schedule.to_string(),
+            timezone: job.timezone.to_string(),
+            enabled: job.enabled,
+            next_time: job.next_after(Utc::now()),
+            last_run: scheduler.get_last_run(job.name).await?,
+        });
+    }
+    ok!(SchedulerJobsResp { items })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListTagAliasesResp {
+    pub items: Vec<SongTagAlias>,
+}
+
+#[framed]
+async fn list_tag_aliases(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<ListTagAliasesResp> {
+    ensure_contributor(&state, &claims).await?;
+    let items = SongTagDao::list_aliases(&state.sql_pool).await?;
+    ok!(ListTagAliasesResp { items })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddTagAliasReq {
+    /// The canonical tag
+    pub tag_id: i64,
+    /// May be the name of another tag, which is resolved to the canonical tag when publishing since then
+    pub alias: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddTagAliasResp {
+    pub id: i64,
+}
+
+#[framed]
+async fn add_tag_alias(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<AddTagAliasReq>,
+) -> WebResult<AddTagAliasResp> {
+    ensure_contributor(&state, &claims).await?;
+    let alias = req.alias.trim();
+    if alias.is_empty() || alias.chars().count() > state.limits.tag_name_max_chars {
+        err!("invalid_name", "Invalid alias")
+    }
+    match tag_alias::add_alias(&state, req.tag_id, alias).await {
+        Ok(id) => ok!(AddTagAliasResp { id }),
+        Err(e @ AliasError::TagNotFound(_)) => err!("tag_not_found", "{}", e),
+        Err(e @ (AliasError::SameAsName | AliasError::AliasExists(_))) => err!("name_exists", "{}", e),
+        Err(AliasError::Sqlx(e)) => Err(e)?,
+        Err(AliasError::Db(e)) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteTagAliasReq {
+    pub id: i64,
+}
+
+#[framed]
+async fn delete_tag_alias(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<DeleteTagAliasReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    if !tag_alias::delete_alias(&state, req.id).await? {
+        err!("not_found", "Alias not found")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoleReq {
+    pub uid: i64,
+    /// e.g. `contributor`
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RoleResp {
+    /// Whether the roles of the user changed, `false` if they had or didn't have the role already
+    pub changed: bool,
+}
+
+/// Grant a role to the user, the role in their issued tokens takes effect on the next request
+#[framed]
+async fn grant_role(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<RoleReq>,
+) -> WebResult<RoleResp> {
+    ensure_contributor(&state, &claims).await?;
+    let result = contributor::grant_role(&state, req.uid, &req.role, Some(claims.uid())).await;
+    role_resp(result)
+}
+
+#[framed]
+async fn revoke_role(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<RoleReq>,
+) -> WebResult<RoleResp> {
+    ensure_contributor(&state, &claims).await?;
+    if req.uid == claims.uid() {
+        err!("invalid_uid", "You can't revoke your own roles")
+    }
+    let result = contributor::revoke_role(&state, req.uid, &req.role).await;
+    role_resp(result)
+}
+
+fn role_resp(result: Result<bool, RoleError>) -> WebResult<RoleResp> {
+    match result {
+        Ok(changed) => ok!(RoleResp { changed }),
+        Err(e @ RoleError::RoleNotFound(_)) => err!("role_not_found", "{}", e),
+        Err(RoleError::UserNotFound(_)) => err!("not_found", "User not found"),
+        Err(RoleError::Db(e)) => Err(e)?,
+        Err(RoleError::Other(e)) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PublishLegalReq {
+    /// `terms` or `privacy`
+    pub kind: String,
+    pub title: String,
+    /// Markdown
+    pub content: String,
+    /// The users must accept it before logging in again
+    pub mandatory: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PublishLegalResp {
+    pub version: i32,
+}
+
+/// Publish the next version of a legal document, effective immediately
+#[framed]
+async fn publish_legal(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<PublishLegalReq>,
+) -> WebResult<PublishLegalResp> {
+    ensure_contributor(&state, &claims).await?;
+    if !legal::KINDS.contains(&req.kind.as_str()) {
+        err!("invalid_legal_document", "Unknown kind {}", req.kind)
+    }
+    if req.title.is_blank() || req.content.is_blank() {
+        err!("invalid_legal_document", "The title and content must not be blank")
+    }
+    let now = Utc::now();
+    let version = LegalDocumentDao::insert_next_version(&state.sql_pool, &LegalDocument {
+        id: 0,
+        kind: req.kind.clone(),
+        version: 0,
+        title: req.title.trim().to_string(),
+        content: req.content.clone(),
+        mandatory: req.mandatory,
+        publish_time: now,
+        create_time: now,
+    }).await?;
+    ok!(PublishLegalResp { version })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageSongReportsReq {
+    /// 0: pending, 1: resolved, 2: dismissed, all if absent
+    pub status: Option<i16>,
+}
+
+/// The whole query of `/admin/song/report/page`, [PageSongReportsReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageSongReportsQuery {
+    #[serde(flatten)]
+    pub req: PageSongReportsReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SongReportItem {
+    pub id: i64,
+    pub song_id: i64,
+    /// `None` if the song is deleted
+    pub song_display_id: Option<String>,
+    pub song_title: Option<String>,
+    /// Whether the song is hidden by `/admin/song/hide`
+    pub song_hidden: bool,
+    pub reporter_uid: i64,
+    pub category: String,
+    pub reason: String,
+    pub status: i16,
+    pub resolution_note: Option<String>,
+    pub resolved_by: Option<i64>,
+    pub resolve_time: Option<DateTime<Utc>>,
+    pub create_time: DateTime<Utc>,
+}
+
+pub type PageSongReportsResp = Page<SongReportItem>;
+
+/// The song reports, latest first
+#[framed]
+async fn page_song_reports(
+    claims: Claims,
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageSongReportsReq>,
+) -> WebResult<PageSongReportsResp> {
+    ensure_contributor(&state, &claims).await?;
+    let reports = SongReportDao::page_by_status(&state.sql_pool, req.status, pagination.page_index, pagination.page_size).await?;
+    let total = SongReportDao::count_by_status(&state.sql_pool, req.status).await?;
+    let song_ids = reports.iter().map(|x| x.song_id).unique().collect_vec();
+    let songs = SongDao::list_by_ids(&state.sql_pool, &song_ids).await?;
+    let hidden_ids = HiddenSongDao::list_hidden_ids(&state.sql_pool, &song_ids).await?;
+    let items = reports.into_iter()
+        .map(|x| {
+            let song = songs.iter().find(|s| s.id == x.song_id);
+            SongReportItem {
+                id: x.id,
+                song_id: x.song_id,
+                song_display_id: song.map(|s| s.display_id.clone()),
+                song_title: song.map(|s| s.title.clone()),
+                song_hidden: hidden_ids.contains(&x.song_id),
+                reporter_uid: x.reporter_uid,
+                category: x.category,
+                reason: x.reason,
+                status: x.status,
+                resolution_note: x.resolution_note,
+                resolved_by: x.resolved_by,
+                resolve_time: x.resolve_time,
+                create_time: x.create_time,
+            }
+        })
+        .collect_vec();
+    ok!(pagination.into_page(items, total))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ResolveSongReportReq {
+    pub id: i64,
+    /// Dismiss the report as invalid instead of resolving it
+    #[serde(default)]
+    pub dismiss: bool,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Close a pending report, the song is not hidden or unhidden by it, see `/admin/song/hide`
+#[framed]
+async fn resolve_song_report(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ResolveSongReportReq>,
+) -> WebResult<()> {
+    ensure_contributor(&state, &claims).await?;
+    let status = if req.dismiss { song_report::STATUS_DISMISSED } else { song_report::STATUS_RESOLVED };
+    let note = req.note.as_deref().map(str::trim).filter(|x| !x.is_empty());
+    if !SongReportDao::resolve(&state.sql_pool, req.id, status, claims.uid(), note, Utc::now()).await? {
+        if SongReportDao::get_by_id(&state.sql_pool, req.id).await?.is_none() {
+            err!("not_found", "Report not found")
+        }
+        err!("report_resolved", "The report has been resolved")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HideSongReq {
+    pub song_id: i64,
+    /// `false` to unhide
+    pub hidden: bool,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HideSongResp {
+    /// Whether the song was not in the status yet
+    pub changed: bool,
+}
+
+/// Hide a song, e.g. pending the review of its reports, it's treated as not existing until unhidden
+#[framed]
+async fn hide_song(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<HideSongReq>,
+) -> WebResult<HideSongResp> {
+    ensure_contributor(&state, &claims).await?;
+    let Some(song) = SongDao::get_by_id(&state.sql_pool, req.song_id).await? else {
+        err!("not_found", "Song not found")
+    };
+    let changed = song_report_service::set_hidden(&state, &song, req.hidden, claims.uid(), req.reason.trim()).await?;
+    ok!(HideSongResp { changed })
+}
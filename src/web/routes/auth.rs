@@ -1,37 +1,79 @@
 use crate::db::refresh_token::{IRefreshTokenDao, RefreshToken, RefreshTokenDao};
 use crate::db::user::{IUserDao, User, UserDao};
+use crate::db::error::{retry_on_conflict, DbError};
 use crate::db::CrudDao;
-use crate::service::{mailer, verification_code};
+use crate::service::email_delivery::SendBlock;
+use crate::service::{contributor, device_trust, email_delivery, magic_link, mailer, oauth, qr_login, totp, verification_code};
 use crate::web::extractors::XRealIP;
+use crate::web::governor::RateLimitsCfg;
 use crate::web::jwt::Claims;
-use crate::web::result::{WebResult};
+use crate::web::result::{CommonError, WebError, WebResult};
+use crate::web::security_headers::{js_string_literal, CspSources};
 use crate::web::state::AppState;
-use crate::web::{jwt};
-use crate::{err, ok, search, service};
+use crate::web::{i18n, jwt, region_gate};
+use crate::{common, err, ok, search, service};
 use axum::http::{StatusCode};
-use axum::response::{Html};
+use axum::response::{Html, IntoResponse, Response};
 use axum::routing::get;
 use axum::{debug_handler, extract::State, routing::post, Json, Router};
 use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
 use axum::extract::Query;
 use axum_extra::headers::UserAgent;
 use axum_extra::TypedHeader;
 use jsonwebtoken::errors::ErrorKind;
 use tracing::{error};
+use url::Url;
 use crate::search::user::{UserDocument};
 use crate::service::captcha::verify_captcha;
+use crate::service::magic_link::MagicLinkCfg;
+use crate::service::linked_account::LinkError;
+use crate::service::oauth::{OAuthError, OAuthState, PendingLogin};
+use crate::service::qr_login::{ApproveError, PollResult};
+use crate::service::legal::{self, LegalAcceptance};
+use crate::service::totp::TotpError;
 use crate::service::mailer::EmailConfig;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/register/email", post(email_register))
         .route("/login/email", post(email_login))
+        // @since 260429
+        .route("/login/magic_link/request", post(magic_link_request))
+        // @since 260429
+        .route("/login/magic_link/verify", post(magic_link_verify))
+        // @since 260429
+        .route("/qr/create", post(qr_create))
+        // @since 260429
+        .route("/qr/approve", post(qr_approve))
+        // @since 260505
+        .route("/qr/scan", post(qr_scan))
+        // @since 260429
+        .route("/qr/poll", post(qr_poll))
+        // @since 260430
+        .route("/oauth/authorize", get(oauth_authorize))
+        // @since 260430
+        .route("/oauth/callback", post(oauth_callback))
+        // @since 260505
+        .route("/oauth/2fa", post(oauth_two_factor))
+        // @since 260501
+        .route("/2fa/enroll", post(two_factor_enroll))
+        // @since 260501
+        .route("/2fa/confirm", post(two_factor_confirm))
+        // @since 260501
+        .route("/2fa/disable", post(two_factor_disable))
         .route("/send_email_code", post(send_email_code))
+        .route("/resend_email_code", post(resend_email_code))
         .route("/device/list", get(device_list))
         .route("/device/logout", post(device_logout))
+        // @since 260504
+        .route("/device/rename", post(device_rename))
+        // @since 260504
+        .route("/device/trust", post(device_trust))
+        // @since 260504
+        .route("/device/untrust", post(device_untrust))
         .route("/refresh_token", post(refresh_token))
         .route("/protected", get(protected))
         .route("/reset_password", post(reset_password))
@@ -40,23 +82,27 @@ pub fn router() -> Router<AppState> {
         .route("/captcha/submit", post(submit_captcha))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct TokenPair {
     pub access_token: String,
     pub refresh_token: String,
     pub expires_in: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct EmailRegisterReq {
     pub email: String,
     pub password: String,
     pub code: String,
     pub device_info: String,
     pub captcha_key: String,
+    /// The legal documents accepted, must cover the latest mandatory versions from `/legal/latest`
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct EmailRegisterResp {
     pub uid: i64,
     pub generated_username: String,
@@ -91,13 +137,20 @@ async fn email_register(
         if UserDao::get_by_email(&state.sql_pool, &req.email).await?.is_some() {
             err!("email_existed", "Email already exists!")
         }
+        if let Err(e) = legal::validate(&state.sql_pool, &req.accepted_legal).await {
+            return Err(legal::map_accept_error(e));
+        }
+        let pending = legal::pending_for_registration(&state.sql_pool, &req.accepted_legal).await?;
+        if !pending.is_empty() {
+            return Err(legal::acceptance_required(&pending));
+        }
 
         // 2. Generate username and hash password
         let username = generate_username();
         let password_hash = bcrypt::hash(&req.password, bcrypt::DEFAULT_COST)?;
 
         // 3. Create user
-        let mut entity = User {
+        let entity = User {
             id: 0,
             username: username.clone(),
             email: req.email.clone(),
@@ -109,8 +162,17 @@ async fn email_register(
             last_login_time: None,
             create_time: Utc::now(),
             update_time: Utc::now(),
+            version: 0,
+            preferred_language: Some(i18n::current_lang().tag().to_string()),
+        };
+        let uid = match UserDao::insert(&state.sql_pool, &entity).await.map_err(DbError::from) {
+            Ok(uid) => uid,
+            Err(e) if e.is_unique_violation_of("users_email_key") => err!("email_existed", "Email already exists!"),
+            Err(e) => Err(e)?,
         };
-        let uid = UserDao::insert(&state.sql_pool, &mut entity).await?;
+        if let Err(e) = legal::accept(&state.sql_pool, uid, &req.accepted_legal).await {
+            return Err(legal::map_accept_error(e));
+        }
 
         search::user::update_user_document(&state.meilisearch, UserDocument {
             id: uid,
@@ -121,7 +183,7 @@ async fn email_register(
 
         // 4. Generate tokens
         let token =
-            generate_token_pairs_and_save(ip, uid, ua.to_string(), req.device_info.clone(), &state.sql_pool)
+            generate_token_pairs_and_save(ip, uid, ua.to_string(), req.device_info.clone(), &[], &state)
                 .await?;
 
         ok!(EmailRegisterResp {
@@ -134,16 +196,25 @@ async fn email_register(
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LoginReq {
     pub email: String,
     pub password: String,
     pub device_info: String,
+    /// The TOTP or recovery code, required if the user enabled the 2FA
     pub code: Option<String>,
     pub captcha_key: String,
+    /// Returned by `/auth/device/trust`, the captcha and the 2FA code are not required if it's valid
+    /// @since 260504
+    #[serde(default)]
+    pub trusted_device_token: Option<String>,
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LoginResp {
     pub uid: i64,
     pub username: String,
@@ -158,55 +229,304 @@ async fn email_login(
     mut state: State<AppState>,
     req: Json<LoginReq>,
 ) -> WebResult<LoginResp> {
-    let captcha = service::captcha::verify_captcha(&mut state.redis_conn, &req.captcha_key).await?;
-    if !captcha {
-        err!("invalid_captcha", "Invalid captcha")
+    let user = UserDao::get_by_email(&state.sql_pool, &req.email).await?;
+    let trusted_device = match (&user, &req.trusted_device_token) {
+        (Some(user), Some(token)) => device_trust::get_trusted_device(&state.sql_pool, user.id, token, Utc::now()).await?,
+        _ => None,
+    };
+
+    if trusted_device.is_none() {
+        let captcha = service::captcha::verify_captcha(&mut state.redis_conn, &req.captcha_key).await?;
+        if !captcha {
+            err!("invalid_captcha", "Invalid captcha")
+        }
     }
 
-    match &req.code {
-        None => {
-            // TODO[security](auth): Check if 2fa is required.
-            /*let should_2fa = false;
-            if should_2fa {
-                err!("2fa_required", "2FA is required!")
-            }*/
+    let user = if let Some(user) = user {
+        user
+    } else {
+        err!("password_not_match", "Password not match!")
+    };
 
-            let user = if let Some(user) = UserDao::get_by_email(&state.sql_pool, &req.email).await? {
-                user
-            } else {
-                err!("password_not_match", "Password not match!")
-            };
+    if !bcrypt::verify(&req.password, &user.password_hash)? {
+        err!("password_not_match", "Password not match!")
+    }
 
-            if !bcrypt::verify(&req.password, &user.password_hash)? {
-                err!("password_not_match", "Password not match!")
-            }
+    if trusted_device.is_none() &&
+        let Err(e) = totp::verify_login(&state.sql_pool, &mut state.redis_conn.clone(), user.id, req.code.as_deref()).await {
+        return Err(map_totp_error(e));
+    }
 
-            let token = generate_token_pairs_and_save(
-                ip.0,
-                user.id,
-                ua.to_string(),
-                req.device_info.clone(),
-                &state.sql_pool,
-            ).await?;
-
-            let resp = LoginResp {
-                uid: user.id,
-                username: user.username,
-                token,
-            };
-            ok!(resp)
+    let token = if let Some(device) = trusted_device {
+        ensure_legal_accepted(&state, user.id, &req.accepted_legal).await?;
+        // The device logs in again, the old token is replaced, keeping the name and the trust
+        let roles = contributor::current_roles(&state, user.id).await?;
+        let (token, entity) = build_token_pairs(ip.0, user.id, ua.to_string(), req.device_info.clone(), roles);
+        let entity = RefreshToken {
+            device_name: device.device_name,
+            trust_token_hash: device.trust_token_hash,
+            trust_time: device.trust_time,
+            ..entity
+        };
+        let mut tx = state.sql_pool.begin().await?;
+        RefreshTokenDao::delete_by_id(&mut *tx, device.id).await?;
+        RefreshTokenDao::insert(&mut *tx, &entity).await?;
+        tx.commit().await?;
+        token
+    } else {
+        generate_token_pairs_and_save(
+            ip.0,
+            user.id,
+            ua.to_string(),
+            req.device_info.clone(),
+            &req.accepted_legal,
+            &state,
+        ).await?
+    };
+
+    let resp = LoginResp {
+        uid: user.id,
+        username: user.username,
+        token,
+    };
+    ok!(resp)
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MagicLinkRequestReq {
+    pub email: String,
+}
+
+/// Send a login link to the email. It always succeeds for an unknown address, so the registered addresses
+/// can't be probed by it.
+#[async_backtrace::framed]
+async fn magic_link_request(
+    state: State<AppState>,
+    req: Json<MagicLinkRequestReq>,
+) -> WebResult<()> {
+    let Some(cfg) = MagicLinkCfg::load(&state.config)? else {
+        err!("magic_link_disabled", "The magic link login is disabled")
+    };
+    let mut redis = state.redis_conn.clone();
+    let limit = RateLimitsCfg::load(&state.config)?.magic_link;
+    if magic_link::is_limited(&state.sql_pool, &mut redis, &req.email, &limit).await? {
+        err!("too_many_requests", "Too many requests, please try again later!")
+    }
+    let Some(user) = UserDao::get_by_email(&state.sql_pool, &req.email).await? else {
+        ok!(())
+    };
+
+    let (token, claims) = jwt::generate_magic_link_token(&user.id.to_string(), magic_link::TOKEN_TTL_SECS);
+    magic_link::save_token(&mut redis, &claims.jti, user.id).await?;
+    let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
+    email_delivery::track(
+        &state.sql_pool,
+        email_delivery::TYPE_MAGIC_LINK,
+        &user.email,
+        mailer::send_magic_link(&email_cfg, service::user::email_lang(&user), &user.email, &cfg.link(&token), magic_link::TOKEN_TTL_SECS / 60),
+    ).await?;
+    ok!(())
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct MagicLinkVerifyReq {
+    pub token: String,
+    pub device_info: String,
+    /// The TOTP or recovery code, required if the user enabled the 2FA
+    #[serde(default)]
+    pub code: Option<String>,
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
+}
+
+#[async_backtrace::framed]
+async fn magic_link_verify(
+    mut state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    TypedHeader(ua): TypedHeader<UserAgent>,
+    req: Json<MagicLinkVerifyReq>,
+) -> WebResult<LoginResp> {
+    let claims = match jwt::decode_and_validate_magic_link_token(&req.token) {
+        Ok(x) if x.r#type == "magic_link" => x,
+        _ => err!("invalid_magic_link", "The link is invalid, expired or used"),
+    };
+    let uid: i64 = claims.sub.parse()?;
+    if !magic_link::is_token_valid(&mut state.redis_conn, &claims.jti, uid).await? {
+        err!("invalid_magic_link", "The link is invalid, expired or used")
+    }
+    // Checked before consuming, so the link can be used again after a wrong code, until too many are entered
+    match totp::verify_login(&state.sql_pool, &mut state.redis_conn.clone(), uid, req.code.as_deref()).await {
+        Ok(_) => {}
+        Err(e @ TotpError::InvalidCode) => {
+            magic_link::record_failed_code(&mut state.redis_conn, &claims.jti).await?;
+            return Err(map_totp_error(e));
         }
-        Some(_) => {
-            // TODO[security](auth): check 2fa code
-            err!("invalid_code", "Invalid code")
+        Err(e) => return Err(map_totp_error(e)),
+    }
+    ensure_legal_accepted(&state, uid, &req.accepted_legal).await?;
+    if !magic_link::consume_token(&mut state.redis_conn, &claims.jti, uid).await? {
+        err!("invalid_magic_link", "The link is invalid, expired or used")
+    }
+    let Some(user) = UserDao::get_by_id(&state.sql_pool, uid).await? else {
+        err!("invalid_magic_link", "The link is invalid, expired or used")
+    };
+
+    let token = generate_token_pairs_and_save(
+        ip,
+        user.id,
+        ua.to_string(),
+        req.device_info.clone(),
+        &[],
+        &state,
+    ).await?;
+    ok!(LoginResp {
+        uid: user.id,
+        username: user.username,
+        token,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrCreateReq {
+    pub device_info: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrCreateResp {
+    /// Displayed as the QR code for the mobile app
+    pub code: String,
+    /// Kept by the desktop client for polling, never display it
+    pub poll_token: String,
+    /// Seconds until the session expires
+    pub expires_in: u64,
+}
+
+/// Create a QR login session on the desktop client
+async fn qr_create(
+    state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    req: Json<QrCreateReq>,
+) -> WebResult<QrCreateResp> {
+    let session = qr_login::create(&mut state.redis_conn.clone(), &req.device_info, &ip).await?;
+    ok!(QrCreateResp {
+        code: session.code,
+        poll_token: session.poll_token,
+        expires_in: qr_login::SESSION_TTL_SECS,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrScanReq {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrScanResp {
+    /// The device info of the desktop client requesting the login
+    pub device_info: String,
+    /// The IP the desktop client created the code from
+    pub ip: String,
+    pub create_time: DateTime<Utc>,
+}
+
+/// Show the desktop client of the scanned QR code on the mobile app, let the user confirm it's their own
+/// device before approving
+async fn qr_scan(
+    _claims: Claims,
+    state: State<AppState>,
+    req: Json<QrScanReq>,
+) -> WebResult<QrScanResp> {
+    let Some(session) = qr_login::scan(&mut state.redis_conn.clone(), &req.code).await? else {
+        err!("qr_code_expired", "The QR code is expired")
+    };
+    ok!(QrScanResp {
+        device_info: session.device_info,
+        ip: session.ip,
+        create_time: session.create_time,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrApproveReq {
+    pub code: String,
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrApproveResp {
+    /// The device info of the desktop client being logged in
+    pub device_info: String,
+}
+
+/// Approve the QR code scanned by the logged in mobile app
+async fn qr_approve(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<QrApproveReq>,
+) -> WebResult<QrApproveResp> {
+    // Checked on the mobile app, the desktop client can't accept them
+    ensure_legal_accepted(&state, claims.uid(), &req.accepted_legal).await?;
+    match qr_login::approve(&mut state.redis_conn.clone(), &req.code, claims.uid()).await {
+        Ok(device_info) => ok!(QrApproveResp { device_info }),
+        Err(x) => match x {
+            ApproveError::Expired => err!("qr_code_expired", "{}", x.to_string()),
+            ApproveError::AlreadyApproved => err!("qr_code_approved", "{}", x.to_string()),
+            ApproveError::Other(e) => Err(e)?,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrPollReq {
+    pub code: String,
+    pub poll_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct QrPollResp {
+    /// `None` if the code is not approved yet, poll again later
+    pub login: Option<LoginResp>,
+}
+
+/// Polled by the desktop client, the tokens are only returned once
+async fn qr_poll(
+    state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    TypedHeader(ua): TypedHeader<UserAgent>,
+    req: Json<QrPollReq>,
+) -> WebResult<QrPollResp> {
+    let (uid, device_info) = match qr_login::poll(&mut state.redis_conn.clone(), &req.code, &req.poll_token).await? {
+        PollResult::Expired => err!("qr_code_expired", "The QR code is expired"),
+        PollResult::Pending => ok!(QrPollResp { login: None }),
+        PollResult::Approved { uid, device_info } => (uid, device_info),
+    };
+    let Some(user) = UserDao::get_by_id(&state.sql_pool, uid).await? else {
+        err!("qr_code_expired", "The QR code is expired")
+    };
+
+    let token = generate_token_pairs_and_save(ip, user.id, ua.to_string(), device_info, &[], &state).await?;
+    ok!(QrPollResp {
+        login: Some(LoginResp {
+            uid: user.id,
+            username: user.username,
+            token,
+        }),
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct RefreshTokenReq {
     pub refresh_token: String,
     pub device_info: String,
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
 }
 
 async fn refresh_token(
@@ -250,9 +570,12 @@ async fn refresh_token(
     }
 
     let uid = entry.user_id;
+    ensure_legal_accepted(&state, uid, &req.accepted_legal).await?;
 
+    // Derived again, the demoted users lose the roles
+    let (roles, role_epoch) = contributor::current_roles(&state, uid).await?;
     let expires_in = Utc::now() + Duration::minutes(5);
-    let access_token = jwt::generate_access_token(&uid.to_string(), expires_in.timestamp());
+    let access_token = jwt::generate_access_token(&uid.to_string(), expires_in.timestamp(), roles, role_epoch);
 
     let token = if entry.expires_time - Utc::now() < Duration::days(7) {
         // When the refresh token is about to expire, generate a new one.
@@ -288,16 +611,28 @@ async fn refresh_token(
     });
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct SendVerificationReq {
     pub email: String,
 }
 
+/// Refuses the addresses that bounced or complained, so the users with a mistyped address get told instead of waiting
 #[async_backtrace::framed]
 async fn send_email_code(
+    ip: XRealIP,
     State(state): State<AppState>,
     Json(req): Json<SendVerificationReq>,
 ) -> WebResult<()> {
+    match email_delivery::check_suppressed(&state.sql_pool, &req.email).await? {
+        Some(SendBlock::Bounced) => {
+            err!("email_bounced", "The email to {} bounced, please check if the address is correct", req.email)
+        }
+        Some(SendBlock::Complained) => {
+            err!("email_complained", "The email to {} was reported as spam, please use another address", req.email)
+        }
+        None => {}
+    }
+
     let mut redis = state.redis_conn;
 
     let limit_absent: bool = verification_code::set_limit_nx(&mut redis, &req.email).await?;
@@ -305,65 +640,402 @@ async fn send_email_code(
     if !limit_absent {
         err!("too_many_requests","Too many requests, please try again later!");
     }
+    let limit = RateLimitsCfg::load(&state.config)?.email_code;
+    if verification_code::hourly_limited(&mut redis, &req.email, &ip.0, &limit).await? {
+        err!("too_many_requests","Too many requests, please try again later!");
+    }
 
     let code = verification_code::generate_verify_code();
 
     let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
-    mailer::send_verification_code(&email_cfg, &req.email, &code).await?;
+    email_delivery::track(
+        &state.sql_pool,
+        email_delivery::TYPE_VERIFICATION_CODE,
+        &req.email,
+        mailer::send_verification_code(&email_cfg, i18n::current_lang(), &req.email, &code),
+    ).await?;
 
     verification_code::set_code(&mut redis, &req.email, &code).await?;
     ok!(())
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Same as `send_email_code`, kept for the clients calling it to send the code again
+#[async_backtrace::framed]
+async fn resend_email_code(
+    ip: XRealIP,
+    state: State<AppState>,
+    req: Json<SendVerificationReq>,
+) -> WebResult<()> {
+    send_email_code(ip, state, req).await
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct OAuthAuthorizeReq {
+    /// `github` or `google`
+    pub provider: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct OAuthAuthorizeResp {
+    /// Open it in the browser, the provider redirects back to the configured page with the `code` and `state`
+    pub url: String,
+    pub state: String,
+}
+
+/// The authorize URL for logging in, see [oauth] for the flow
+async fn oauth_authorize(
+    mut state: State<AppState>,
+    req: Query<OAuthAuthorizeReq>,
+) -> WebResult<OAuthAuthorizeResp> {
+    let Some((provider, cfg)) = oauth::load_provider(&state.config, &req.provider)? else {
+        err!("oauth_disabled", "Logging in by {} is not available", req.provider)
+    };
+    let oauth_state = oauth::create_state(&mut state.redis_conn, &OAuthState { provider, uid: None }).await?;
+    ok!(OAuthAuthorizeResp {
+        url: oauth::authorize_url(provider, &cfg, &oauth_state),
+        state: oauth_state,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct OAuthCallbackReq {
+    pub code: String,
+    pub state: String,
+    pub device_info: String,
+    /// The legal documents accepted, the new users have to accept the mandatory ones of `/legal/latest`
+    /// @since 260505
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct OAuthLoginResp {
+    pub uid: i64,
+    pub username: String,
+    /// The user is registered by this login, the client may guide them to edit the profile
     pub first_access: bool,
     pub token: TokenPair,
 }
 
-// async fn oauth_github() {
-    // https://docs.github.com/en/apps/oauth-apps/building-oauth-apps/authorizing-oauth-apps
-    // scopes = read:user, user:email
-    // 1. Build authorize url
-    // 2. Github callback
-    // 3. Pickup code
-    // 4. Read user profile(username, email, avatar)
-    // 5. Login/register
-    // 6. Return tokens
-// }
+#[async_backtrace::framed]
+async fn oauth_callback(
+    mut state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    TypedHeader(ua): TypedHeader<UserAgent>,
+    req: Json<OAuthCallbackReq>,
+) -> WebResult<OAuthLoginResp> {
+    let Some(oauth_state) = oauth::consume_state(&mut state.redis_conn, &req.state).await?
+        .filter(|x| x.uid.is_none()) else {
+        err!("invalid_oauth_state", "The authorization is expired, please try again")
+    };
+    let Some((provider, cfg)) = oauth::load_provider(&state.config, oauth_state.provider.name())? else {
+        err!("oauth_disabled", "Logging in by {} is not available", oauth_state.provider.name())
+    };
+
+    let result = match oauth::fetch_profile(provider, &cfg, &req.code).await {
+        Ok(profile) => oauth::sign_in(&state.sql_pool, provider, &profile, generate_username(), Some(&ip)).await,
+        Err(e) => Err(e),
+    };
+    let sign_in = match result {
+        Ok(x) => x,
+        Err(e) => match e {
+            OAuthError::InvalidCode => err!("invalid_oauth_code", "{}", e.to_string()),
+            OAuthError::ProviderApi(_) => {
+                error!("Failed to request {}: {:?}", provider.name(), e);
+                err!("provider_api_error", "{}", e.to_string())
+            }
+            OAuthError::Link(LinkError::Sqlx(e)) => Err(e)?,
+            OAuthError::Link(_) => err!("already_linked", "{}", e.to_string()),
+            e => Err(e)?,
+        }
+    };
+
+    if sign_in.first_access {
+        search::user::update_user_document(&state.meilisearch, UserDocument {
+            id: sign_in.user.id,
+            avatar_url: None,
+            name: sign_in.user.username.clone(),
+            follower_count: 0,
+        }).await?;
+    }
+
+    match totp::verify_login(&state.sql_pool, &mut state.redis_conn.clone(), sign_in.user.id, None).await {
+        Ok(()) => {}
+        Err(TotpError::Required) => {
+            let pending = PendingLogin { uid: sign_in.user.id, first_access: sign_in.first_access };
+            let ticket = oauth::create_pending_login(&mut state.redis_conn, &pending).await?;
+            return Err(WebError::common_with_detail(
+                "2fa_required",
+                &TotpError::Required.to_string(),
+                serde_json::json!({ "ticket": ticket }),
+            ));
+        }
+        Err(e) => return Err(map_totp_error(e)),
+    }
+
+    let token = generate_token_pairs_and_save(
+        ip,
+        sign_in.user.id,
+        ua.to_string(),
+        req.device_info.clone(),
+        &req.accepted_legal,
+        &state,
+    ).await?;
+    ok!(OAuthLoginResp {
+        uid: sign_in.user.id,
+        username: sign_in.user.username,
+        first_access: sign_in.first_access,
+        token,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct OAuthTwoFactorReq {
+    /// The `ticket` in the detail of the `2fa_required` error of the callback
+    pub ticket: String,
+    /// The TOTP or recovery code
+    pub code: String,
+    pub device_info: String,
+    /// The legal documents accepted, required if `legal_acceptance_required` was returned
+    #[serde(default)]
+    pub accepted_legal: Vec<LegalAcceptance>,
+}
+
+/// Finish the OAuth login of the user who enabled the 2FA
+#[async_backtrace::framed]
+async fn oauth_two_factor(
+    mut state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    TypedHeader(ua): TypedHeader<UserAgent>,
+    req: Json<OAuthTwoFactorReq>,
+) -> WebResult<OAuthLoginResp> {
+    let Some(pending) = oauth::get_pending_login(&mut state.redis_conn, &req.ticket).await? else {
+        err!("invalid_oauth_state", "The authorization is expired, please try again")
+    };
+    if let Err(e) = totp::verify_login(&state.sql_pool, &mut state.redis_conn.clone(), pending.uid, Some(&req.code)).await {
+        return Err(map_totp_error(e));
+    }
+    ensure_legal_accepted(&state, pending.uid, &req.accepted_legal).await?;
+    if !oauth::consume_pending_login(&mut state.redis_conn, &req.ticket).await? {
+        err!("invalid_oauth_state", "The authorization is expired, please try again")
+    }
+    let Some(user) = UserDao::get_by_id(&state.sql_pool, pending.uid).await? else {
+        err!("invalid_oauth_state", "The authorization is expired, please try again")
+    };
+
+    let token = generate_token_pairs_and_save(
+        ip,
+        user.id,
+        ua.to_string(),
+        req.device_info.clone(),
+        &[],
+        &state,
+    ).await?;
+    ok!(OAuthLoginResp {
+        uid: user.id,
+        username: user.username,
+        first_access: pending.first_access,
+        token,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TwoFactorEnrollResp {
+    /// Base32, for entering into the authenticator manually
+    pub secret: String,
+    /// The `otpauth://` URI, displayed as the QR code for the authenticator to scan
+    pub provisioning_uri: String,
+}
+
+/// Generate a TOTP secret, which is enforced after confirmed by `/auth/2fa/confirm`
+#[async_backtrace::framed]
+async fn two_factor_enroll(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<TwoFactorEnrollResp> {
+    let Some(user) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? else {
+        err!("user_not_found", "User not found")
+    };
+    match totp::enroll(&state.sql_pool, user.id, &user.email).await {
+        Ok(x) => ok!(TwoFactorEnrollResp {
+            secret: x.secret,
+            provisioning_uri: x.provisioning_uri,
+        }),
+        Err(e) => Err(map_totp_error(e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TwoFactorConfirmReq {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TwoFactorConfirmResp {
+    /// Only returned once, each of them can be used once in place of a TOTP code
+    pub recovery_codes: Vec<String>,
+}
+
+/// Enable the 2FA by a code from the authenticator
+#[async_backtrace::framed]
+async fn two_factor_confirm(
+    claims: Claims,
+    state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    req: Json<TwoFactorConfirmReq>,
+) -> WebResult<TwoFactorConfirmResp> {
+    match totp::confirm(&state.sql_pool, &mut state.redis_conn.clone(), claims.uid(), &req.code, Some(&ip)).await {
+        Ok(recovery_codes) => ok!(TwoFactorConfirmResp { recovery_codes }),
+        Err(e) => Err(map_totp_error(e)),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TwoFactorDisableReq {
+    /// The TOTP or recovery code
+    pub code: String,
+}
+
+#[async_backtrace::framed]
+async fn two_factor_disable(
+    claims: Claims,
+    state: State<AppState>,
+    XRealIP(ip): XRealIP,
+    req: Json<TwoFactorDisableReq>,
+) -> WebResult<()> {
+    match totp::disable(&state.sql_pool, &mut state.redis_conn.clone(), claims.uid(), &req.code, Some(&ip)).await {
+        Ok(()) => ok!(()),
+        Err(e) => Err(map_totp_error(e)),
+    }
+}
+
+fn map_totp_error(e: TotpError) -> WebError<CommonError> {
+    match e {
+        TotpError::Required => common!("2fa_required", "{}", e.to_string()),
+        TotpError::AlreadyEnabled => common!("2fa_already_enabled", "{}", e.to_string()),
+        TotpError::NotEnabled => common!("2fa_not_enabled", "{}", e.to_string()),
+        TotpError::NotEnrolled => common!("2fa_not_enrolled", "{}", e.to_string()),
+        TotpError::InvalidCode => common!("invalid_2fa_code", "{}", e.to_string()),
+        TotpError::TooManyFailures => common!("too_many_requests", "{}", e.to_string()),
+        TotpError::Sqlx(e) => e.into(),
+        TotpError::Other(e) => e.into(),
+    }
+}
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeviceListResp {
     pub devices: Vec<DeviceItem>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeviceItem {
     pub id: i64,
     pub device_info: Option<String>,
     pub ip_address: Option<String>,
     pub last_used_time: Option<DateTime<Utc>>,
     pub create_time: DateTime<Utc>,
+    /// @since 260504
+    #[serde(default)]
+    pub device_name: Option<String>,
+    /// @since 260504
+    #[serde(default)]
+    pub trusted: bool,
+    /// The ISO 3166-1 alpha-2 country code of `ip_address` looked up by the GeoIP service of the region gate.
+    /// `None` if unknown, and always `None` if the `region_gate` section is absent or disabled.
+    /// @since 260504
+    #[serde(default)]
+    pub last_location: Option<String>,
 }
 
 async fn device_list(
     State(state): State<AppState>,
     claims: Claims,
 ) -> WebResult<DeviceListResp> {
-    let devices = RefreshTokenDao::list_by_uid(&state.sql_pool, claims.uid()).await?
-        .into_iter()
-        .map(|x| DeviceItem {
-            id: x.id, // Should we use device id instead?
-            device_info: x.device_info,
-            ip_address: x.ip_address,
-            last_used_time: x.last_used_time,
-            create_time: x.create_time,
-        })
-        .collect();
+    let devices = RefreshTokenDao::list_by_uid(&state.sql_pool, claims.uid()).await?;
+    let countries = region_gate::countries_of(devices.iter().filter_map(|x| x.ip_address.as_deref())).await;
+    let devices = devices.iter().map(|x| DeviceItem {
+        id: x.id, // Should we use device id instead?
+        device_info: x.device_info.clone(),
+        ip_address: x.ip_address.clone(),
+        last_used_time: x.last_used_time,
+        create_time: x.create_time,
+        device_name: x.device_name.clone(),
+        trusted: x.trust_time.is_some_and(|t| t > Utc::now() - device_trust::TRUST_TTL),
+        last_location: x.ip_address.as_deref().and_then(|ip| countries.get(ip).cloned()),
+    }).collect();
     ok!(DeviceListResp {devices})
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceRenameReq {
+    pub device_id: i64,
+    /// Cleared if blank
+    pub name: String,
+}
+
+async fn device_rename(
+    State(state): State<AppState>,
+    claims: Claims,
+    req: Json<DeviceRenameReq>,
+) -> WebResult<()> {
+    if !device_trust::is_valid_name(&req.name) {
+        err!("invalid_device_name", "The device name must be at most {} characters", device_trust::MAX_NAME_CHARS)
+    }
+    let name = device_trust::normalize_name(&req.name);
+    if !RefreshTokenDao::rename(&state.sql_pool, req.device_id, claims.uid(), name).await? {
+        err!("invalid_device", "Invalid device id")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceTrustReq {
+    /// The refresh token of the current device, only the current device can be trusted
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceTrustResp {
+    /// Kept by the device for `/auth/login/email`, it's only returned once
+    pub trusted_device_token: String,
+    pub expire_time: DateTime<Utc>,
+}
+
+/// Trust the current device, it can log in without the captcha and the 2FA code for 90 days after that
+async fn device_trust(
+    State(state): State<AppState>,
+    claims: Claims,
+    req: Json<DeviceTrustReq>,
+) -> WebResult<DeviceTrustResp> {
+    let device = match jwt::decode_and_validate_refresh_token(&req.refresh_token) {
+        Ok(x) => RefreshTokenDao::get_by_token_id(&state.sql_pool, &x.jti).await?,
+        Err(_) => None,
+    };
+    let Some(device) = device.filter(|x| x.user_id == claims.uid() && !x.is_revoked) else {
+        err!("invalid_token", "Invalid refresh token")
+    };
+
+    let token = device_trust::generate_token();
+    let now = Utc::now();
+    RefreshTokenDao::set_trust(&state.sql_pool, device.id, claims.uid(), Some((&device_trust::hash_token(&token), now))).await?;
+    ok!(DeviceTrustResp {
+        trusted_device_token: token,
+        expire_time: now + device_trust::TRUST_TTL,
+    })
+}
+
+async fn device_untrust(
+    State(state): State<AppState>,
+    claims: Claims,
+    req: Json<DeviceLogoutReq>,
+) -> WebResult<()> {
+    if !RefreshTokenDao::set_trust(&state.sql_pool, req.device_id, claims.uid(), None).await? {
+        err!("invalid_device", "Invalid device id")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DeviceLogoutReq {
     pub device_id: i64,
 }
@@ -392,7 +1064,7 @@ async fn protected(_: Claims) -> WebResult<()> {
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ResetPasswordReq {
     pub email: String,
     pub code: String,
@@ -411,21 +1083,26 @@ async fn reset_password(
             err!("invalid_captcha", "Invalid captcha")
         }
 
-        let mut tx = state.sql_pool.begin().await?;
-        let mut user = if let Some(user) = UserDao::get_by_email(&mut *tx, req.email.as_str()).await? {
-            user
-        } else {
-            err!("invalid_user", "Invalid user")
-        };
-        user.password_hash = bcrypt::hash(req.new_password.as_str(), bcrypt::DEFAULT_COST)?;
-        user.update_time = Utc::now();
+        let password_hash = bcrypt::hash(req.new_password.as_str(), bcrypt::DEFAULT_COST)?;
+        let found = retry_on_conflict(|| async {
+            let mut tx = state.sql_pool.begin().await?;
+            let Some(mut user) = UserDao::get_by_email(&mut *tx, req.email.as_str()).await? else {
+                return Ok(false);
+            };
+            user.password_hash = password_hash.clone();
+            user.update_time = Utc::now();
 
-        UserDao::update_by_id(&mut *tx, &user).await?;
+            UserDao::update_by_id_checked(&mut *tx, &user).await?;
 
-        if req.logout_all_devices {
-            RefreshTokenDao::delete_all_by_uid(&mut *tx, user.id).await?;
+            if req.logout_all_devices {
+                RefreshTokenDao::delete_all_by_uid(&mut *tx, user.id).await?;
+            }
+            tx.commit().await?;
+            Ok(true)
+        }).await?;
+        if !found {
+            err!("invalid_user", "Invalid user")
         }
-        tx.commit().await?;
         ok!(())
     } else {
         err!("invalid_user", "Invalid user")
@@ -436,15 +1113,50 @@ fn generate_username() -> String {
     format!("神人{:08}", rand::rng().random_range(0..100000000))
 }
 
+/// Record the versions accepted with the login, and refuse it if the user still has mandatory ones to accept.
+///
+/// Checked after the credentials, so the pending versions are only told to the user.
+async fn ensure_legal_accepted(
+    state: &AppState,
+    uid: i64,
+    accepted: &[LegalAcceptance],
+) -> Result<(), WebError<CommonError>> {
+    if let Err(e) = legal::accept(&state.sql_pool, uid, accepted).await {
+        return Err(legal::map_accept_error(e));
+    }
+    let pending = legal::pending_for_user(&state.sql_pool, uid).await?;
+    if !pending.is_empty() {
+        return Err(legal::acceptance_required(&pending));
+    }
+    Ok(())
+}
+
+/// Issue the tokens of a new login, the login is refused if the user has the mandatory legal versions to accept
 async fn generate_token_pairs_and_save(
     ip: String,
     uid: i64,
     ua: String,
     device_info: String,
-    sql_pool: &PgPool,
-) -> anyhow::Result<TokenPair> {
+    accepted_legal: &[LegalAcceptance],
+    state: &AppState,
+) -> Result<TokenPair, WebError<CommonError>> {
+    ensure_legal_accepted(state, uid, accepted_legal).await?;
+    let roles = contributor::current_roles(state, uid).await?;
+    let (token, entity) = build_token_pairs(ip, uid, ua, device_info, roles);
+    RefreshTokenDao::insert(&state.sql_pool, &entity).await?;
+    Ok(token)
+}
+
+/// Generate the token pair and the refresh token row to save, the roles are from [contributor::current_roles]
+fn build_token_pairs(
+    ip: String,
+    uid: i64,
+    ua: String,
+    device_info: String,
+    (roles, role_epoch): (Vec<String>, i64),
+) -> (TokenPair, RefreshToken) {
     let expires_in = Utc::now() + Duration::minutes(5);
-    let access_token = jwt::generate_access_token(&uid.to_string(), expires_in.timestamp());
+    let access_token = jwt::generate_access_token(&uid.to_string(), expires_in.timestamp(), roles, role_epoch);
     let (refresh_token, claims) = jwt::generate_refresh_token(&uid.to_string());
 
     let entity = RefreshToken {
@@ -459,15 +1171,17 @@ async fn generate_token_pairs_and_save(
         ip_address: Some(ip),
         is_revoked: false,
         user_agent: Some(ua),
+        device_name: None,
+        trust_token_hash: None,
+        trust_time: None,
     };
 
-    RefreshTokenDao::insert(sql_pool, &entity).await?;
-
-    Ok(TokenPair {
+    let token = TokenPair {
         access_token,
         refresh_token,
         expires_in,
-    })
+    };
+    (token, entity)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -475,25 +1189,38 @@ pub struct CaptchaReq {
     pub captcha_key: String,
 }
 
+const TURNSTILE_ORIGIN: &str = "https://challenges.cloudflare.com";
+
 #[debug_handler]
 async fn captcha(
     state: State<AppState>,
     _: Query<CaptchaReq>,
-) -> (StatusCode, Html<String>) {
+) -> Response {
     let cfg = match state.config.get_and_parse::<TurnstileCfg>("turnstile") {
         Ok(v) => { v }
         Err(err) => {
             error!("{:?}", err);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Html("Error".to_string()));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Html("Error".to_string())).into_response();
         }
     };
 
-    let html = CAPTCHA_HTML.replace("{{API_BASE_URL}}", cfg.api_base_url.as_str())
-        .replace("{{SITE_KEY}}", cfg.site_key.as_str());
-    (StatusCode::OK, Html(html))
+    let mut sources = CspSources::with_nonce();
+    sources.script_src.push(TURNSTILE_ORIGIN.to_string());
+    sources.frame_src.push(TURNSTILE_ORIGIN.to_string());
+    if let Ok(url) = Url::parse(&cfg.api_base_url) {
+        sources.connect_src.push(url.origin().ascii_serialization());
+    }
+    // The config values are escaped in case they contain quotes or tags
+    let html = CAPTCHA_HTML
+        .replace("{{NONCE}}", sources.nonce.as_deref().unwrap_or_default())
+        .replace("{{API_BASE_URL}}", &js_string_literal(&cfg.api_base_url))
+        .replace("{{SITE_KEY}}", &js_string_literal(&cfg.site_key));
+    let mut resp = (StatusCode::OK, Html(html)).into_response();
+    resp.extensions_mut().insert(sources);
+    resp
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GenerateCaptchaResp {
     pub captcha_key: String,
     pub url: String,
@@ -521,7 +1248,7 @@ async fn generate_captcha(
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SubmitCaptchaReq {
     pub captcha_key: String,
     pub token: String,
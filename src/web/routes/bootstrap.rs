@@ -0,0 +1,32 @@
+use crate::ok;
+use crate::service::playlist::PlaylistCfg;
+use crate::web::limits::LimitsCfg;
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::routing::get;
+use axum::Router;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(bootstrap))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BootstrapResp {
+    pub limits: LimitsCfg,
+    pub playlist_max_songs: i64,
+}
+
+/// The server side constraints, so the clients don't need to hardcode them
+#[framed]
+async fn bootstrap(state: State<AppState>) -> WebResult<BootstrapResp> {
+    let playlist = PlaylistCfg::load(&state.config)?;
+    ok!(BootstrapResp {
+        limits: state.limits.as_ref().clone(),
+        playlist_max_songs: playlist.max_songs,
+    })
+}
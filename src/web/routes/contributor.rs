@@ -1,19 +1,25 @@
-use crate::ok;
-use crate::service::contributor;
+use crate::service::contributor::{self, ensure_contributor};
+use crate::service::mailer;
+use crate::service::notification_templates::NotificationTemplate;
+use crate::web::i18n::{self, Lang};
 use crate::web::jwt::Claims;
 use crate::web::result::WebResult;
 use crate::web::state::AppState;
-use axum::extract::State;
+use crate::{err, ok};
+use axum::extract::{Query, State};
 use axum::Router;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         // @since 260125
         .route("/check", axum::routing::get(check_contributor))
+        // @since 260427
+        .route("/notification/preview", axum::routing::get(preview_notification))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CheckContributorResp {
     pub is_contributor: bool,
 }
@@ -22,14 +28,46 @@ async fn check_contributor(
     claims: Claims,
     state: State<AppState>,
 ) -> WebResult<CheckContributorResp> {
-    let result = contributor::check_contributor(
-        &state.config,
-        state.redis_conn.clone(),
-        &state.red_lock,
-        &state.sql_pool,
-        claims.uid()
-    ).await?;
+    let result = contributor::is_contributor(&state, &claims).await?;
     ok!(CheckContributorResp {
         is_contributor: result,
     })
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewNotificationReq {
+    /// The template name, e.g. `review_rejected`
+    pub template: String,
+    /// @since 260505
+    /// The language to render in, the request language if absent
+    #[serde(default)]
+    pub lang: Option<Lang>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreviewNotificationResp {
+    /// The sample data filled in the template
+    pub data: NotificationTemplate,
+    pub subject: String,
+    pub plain: String,
+    pub html: String,
+}
+
+/// Render a notification email template with the sample data
+async fn preview_notification(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<PreviewNotificationReq>,
+) -> WebResult<PreviewNotificationResp> {
+    ensure_contributor(&state, &claims).await?;
+    let Some(data) = NotificationTemplate::sample(&req.template) else {
+        err!("not_found", "Template not found")
+    };
+    let lang = req.lang.unwrap_or_else(i18n::current_lang);
+    let rendered = data.render(lang);
+    ok!(PreviewNotificationResp {
+        html: mailer::render_notification_html(lang, &rendered.subject, &rendered.content)?,
+        subject: rendered.subject,
+        plain: rendered.content,
+        data,
+    })
+}
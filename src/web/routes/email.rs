@@ -0,0 +1,49 @@
+use crate::service::email_delivery;
+use crate::service::email_delivery::EmailWebhookCfg;
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::{err, ok};
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::routing::post;
+use axum::Router;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/webhook", post(webhook))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookResp {
+    pub updated: u64,
+}
+
+/// Receive the delivery, bounce, and complaint events of the mail provider, see [EmailWebhookCfg]
+#[framed]
+async fn webhook(
+    state: State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> WebResult<WebhookResp> {
+    let Some(cfg) = EmailWebhookCfg::load(&state.config)? else {
+        err!("not_found", "Email webhook is not enabled")
+    };
+    let token = headers.get(header::AUTHORIZATION)
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !cfg.verify(token) {
+        warn!("Rejected email webhook with invalid token");
+        err!("invalid_token", "Invalid token")
+    }
+
+    let Ok(events) = email_delivery::parse_events(&body) else {
+        err!("invalid_body", "Unrecognized event format")
+    };
+    let updated = email_delivery::ingest(&state.sql_pool, &events).await?;
+    ok!(WebhookResp { updated })
+}
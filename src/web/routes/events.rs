@@ -0,0 +1,133 @@
+use crate::service::events::{self, EventsTicket, Subscription};
+use crate::web::jwt::{AuthError, Claims};
+use crate::web::result::{CommonError, WebError, WebResult};
+use crate::web::state::AppState;
+use crate::{common, ok};
+use axum::extract::{Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use chrono::Utc;
+use futures::Stream;
+use metrics::counter;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+/// Sent if some events were dropped for the slow connection, the client should reload the lists
+pub const EVENT_RESYNC: &str = "resync";
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(stream))
+        // @since 260505
+        .route("/ticket", post(ticket))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TicketResp {
+    /// Pass it as the `ticket` query of `/events`, it can be used once
+    pub ticket: String,
+    /// Seconds until it expires if unused
+    pub expires_in: u64,
+}
+
+/// Create a ticket to connect to `/events` with `EventSource`, which can't send the authorization header
+async fn ticket(claims: Claims, mut state: State<AppState>) -> WebResult<TicketResp> {
+    let value = EventsTicket { uid: claims.uid(), expire_at: claims.exp };
+    let ticket = events::create_ticket(&mut state.redis_conn, &value).await?;
+    ok!(TicketResp { ticket, expires_in: events::TICKET_TTL_SECS })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamReq {
+    /// Created by `/events/ticket`, required without the authorization header
+    pub ticket: Option<String>,
+}
+
+/// Push the events of the current user as server-sent events, named by the `EVENT_*` constants of `service::events`.
+///
+/// Authorized by either the access token in the header or a ticket. The stream ends when the access token expires,
+/// the client should reconnect with a refreshed one.
+async fn stream(
+    claims: Option<Claims>,
+    mut state: State<AppState>,
+    req: Query<StreamReq>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, Response> {
+    let (uid, expire_at) = match (claims, &req.ticket) {
+        (Some(claims), _) => (claims.uid(), claims.exp),
+        (None, Some(ticket)) => match events::consume_ticket(&mut state.redis_conn, ticket).await {
+            Ok(Some(x)) => (x.uid, x.expire_at),
+            Ok(None) => return Err(AuthError::InvalidToken.into_response()),
+            Err(e) => return Err(WebError::<CommonError>::from(e).into_response()),
+        },
+        (None, None) => return Err(AuthError::MissingCredentials.into_response()),
+    };
+    let Some(subscription) = events::subscribe(uid) else {
+        counter!("event_connection_rejected_count").increment(1);
+        let error: WebError<CommonError> = common!("too_many_connections", "Too many connections of the events");
+        return Err(error.into_response());
+    };
+    let expires_in = Duration::from_secs((expire_at - Utc::now().timestamp()).max(0) as u64);
+    Ok(Sse::new(user_events(Instant::now() + expires_in, subscription)).keep_alive(KeepAlive::default()))
+}
+
+/// The events of the subscribed user until the deadline or cancelled
+fn user_events(deadline: Instant, subscription: Subscription) -> impl Stream<Item = Result<Event, axum::Error>> {
+    futures::stream::unfold(subscription, move |mut subscription| async move {
+        let received = tokio::select! {
+            x = subscription.receiver.recv() => x,
+            _ = tokio::time::sleep_until(deadline) => return None,
+            _ = subscription.cancel_token.cancelled() => return None,
+        };
+        let event = match received {
+            Ok(x) => Event::default().event(&x.event).json_data(&x.data),
+            Err(RecvError::Lagged(n)) => {
+                counter!("event_lagged_count").increment(n);
+                Ok(Event::default().event(EVENT_RESYNC).data("{}"))
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((event, subscription))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::events::UserEvent;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_user_events() {
+        let uid = rand::random_range(1..i64::MAX / 2);
+        let subscription = events::subscribe(uid).unwrap();
+        let stream = user_events(Instant::now() + Duration::from_secs(60), subscription);
+        let mut stream = std::pin::pin!(stream.map(|x| format!("{:?}", x.unwrap())));
+        let event = |uid: i64, data: usize| UserEvent { uid, event: events::EVENT_FOLLOWER.to_string(), data: json!(data) };
+        // The events of the other users are not received
+        events::dispatch(event(uid + 1, 0));
+        events::dispatch(event(uid, 1));
+        let received = stream.next().await.unwrap();
+        assert!(received.contains(events::EVENT_FOLLOWER) && received.contains('1'));
+
+        // The oldest are dropped
+        for x in 0..100 {
+            events::dispatch(event(uid, x));
+        }
+        assert!(stream.next().await.unwrap().contains(EVENT_RESYNC));
+        assert!(stream.next().await.unwrap().contains(events::EVENT_FOLLOWER));
+    }
+
+    #[tokio::test]
+    async fn test_user_events_deadline() {
+        let subscription = events::subscribe(rand::random_range(1..i64::MAX / 2)).unwrap();
+        let stream = user_events(Instant::now() + Duration::from_millis(10), subscription);
+        let mut stream = std::pin::pin!(stream);
+        assert!(stream.next().await.is_none());
+    }
+}
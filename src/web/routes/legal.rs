@@ -0,0 +1,86 @@
+use crate::db::legal_document::{ILegalDocumentDao, LegalDocumentDao};
+use crate::service::legal::{self, LegalAcceptance};
+use crate::web::jwt::Claims;
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::ok;
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/latest", get(latest))
+        .route("/accept", post(accept))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LegalDocumentItem {
+    /// `terms` or `privacy`
+    pub kind: String,
+    pub version: i32,
+    pub title: String,
+    /// Markdown
+    pub content: String,
+    /// Whether it must be accepted before logging in
+    pub mandatory: bool,
+    pub publish_time: DateTime<Utc>,
+    /// The version accepted by the current user, `None` if not logged in or never accepted
+    pub accepted_version: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LatestResp {
+    pub documents: Vec<LegalDocumentItem>,
+    /// The versions the current user has to accept, always empty if not logged in
+    pub pending: Vec<LegalAcceptance>,
+}
+
+/// The latest published version of each legal document
+#[framed]
+async fn latest(
+    claims: Option<Claims>,
+    state: State<AppState>,
+) -> WebResult<LatestResp> {
+    let (accepted, pending) = match claims {
+        Some(claims) => (
+            LegalDocumentDao::list_acceptances(&state.sql_pool, claims.uid()).await?,
+            legal::pending_for_user(&state.sql_pool, claims.uid()).await?,
+        ),
+        None => (vec![], vec![]),
+    };
+    let documents = LegalDocumentDao::list_latest(&state.sql_pool, Utc::now()).await?
+        .into_iter()
+        .map(|x| LegalDocumentItem {
+            accepted_version: accepted.iter().find(|a| a.kind == x.kind).map(|a| a.version),
+            kind: x.kind,
+            version: x.version,
+            title: x.title,
+            content: x.content,
+            mandatory: x.mandatory,
+            publish_time: x.publish_time,
+        })
+        .collect();
+    ok!(LatestResp { documents, pending })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AcceptReq {
+    pub items: Vec<LegalAcceptance>,
+}
+
+#[framed]
+async fn accept(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<AcceptReq>,
+) -> WebResult<()> {
+    if let Err(e) = legal::accept(&state.sql_pool, claims.uid(), &req.items).await {
+        return Err(legal::map_accept_error(e));
+    }
+    ok!(())
+}
@@ -1,25 +1,56 @@
 pub mod user;
 pub mod auth;
 pub mod song;
+pub mod song_comment;
 pub mod playlist;
 pub mod version;
 pub mod play_history;
 pub mod publish;
 pub mod post;
 pub mod contributor;
+pub mod admin;
+pub mod storage;
+pub mod email;
+pub mod onboarding;
+pub mod bootstrap;
+pub mod search;
+pub mod test_mode;
+pub mod notification;
+pub mod player;
+pub mod legal;
+pub mod events;
 
+use crate::service;
+use crate::web::limits::LimitsCfg;
 use crate::web::state::AppState;
 use axum::Router;
 
-pub fn router() -> Router<AppState> {
-    Router::new()
+pub fn router(limits: &LimitsCfg) -> Router<AppState> {
+    let router = Router::new()
         .nest("/auth", auth::router())
-        .nest("/user", user::router())
-        .nest("/song", song::router())
+        .nest("/user", user::router(limits))
+        .nest("/song", song::router(limits))
         .nest("/play_history", play_history::router())
-        .nest("/playlist", playlist::router())
+        .nest("/playlist", playlist::router(limits))
         .nest("/version", version::router())
-        .nest("/publish", publish::router())
-        .nest("/post", post::router())
+        .nest("/publish", publish::router(limits))
+        .nest("/post", post::router(limits))
         .nest("/contributor", contributor::router())
+        .nest("/admin", admin::router())
+        .nest("/storage", storage::router())
+        .nest("/email", email::router())
+        .nest("/onboarding", onboarding::router())
+        .nest("/bootstrap", bootstrap::router())
+        .nest("/search", search::router())
+        // @since 260505
+        .nest("/player", player::router())
+        // @since 260505
+        .nest("/legal", legal::router())
+        // @since 260505
+        .nest("/events", events::router());
+    if service::test_mode::is_enabled() {
+        router.nest("/test", test_mode::router())
+    } else {
+        router
+    }
 }
\ No newline at end of file
@@ -0,0 +1,132 @@
+use crate::db::notification::{INotificationDao, NotificationDao};
+use crate::service::user;
+use crate::web::jwt::Claims;
+use crate::web::pagination::{Page, PageQuery, Pagination};
+use crate::web::result::WebResult;
+use crate::web::routes::user::PublicUserProfile;
+use crate::web::state::AppState;
+use crate::{err, ok};
+use async_backtrace::framed;
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// At most this many notifications can be marked read by IDs at once
+const MAX_MARK_READ_IDS: usize = 100;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/page", get(page))
+        .route("/mark_read", post(mark_read))
+        .route("/unread_count", get(unread_count))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageNotificationReq {
+    #[serde(default)]
+    pub unread_only: bool,
+}
+
+/// The whole query of `/user/notifications/page`, [PageNotificationReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageNotificationQuery {
+    #[serde(flatten)]
+    pub req: PageNotificationReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationItem {
+    pub id: i64,
+    /// See the `TYPE_*` constants of `service::notification`
+    pub r#type: String,
+    /// `None` for the system, or if the actor is not found
+    pub actor: Option<PublicUserProfile>,
+    /// Depends on the type
+    pub data: serde_json::Value,
+    pub read: bool,
+    pub create_time: DateTime<Utc>,
+}
+
+pub type PageNotificationResp = Page<NotificationItem>;
+
+/// The notifications of the current user, latest first
+#[framed]
+async fn page(
+    claims: Claims,
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageNotificationReq>,
+) -> WebResult<PageNotificationResp> {
+    let notifications = NotificationDao::page_by_user(
+        &state.sql_pool,
+        claims.uid(),
+        req.unread_only,
+        pagination.page_index,
+        pagination.page_size,
+    ).await?;
+    let total = NotificationDao::count_by_user(&state.sql_pool, claims.uid(), req.unread_only).await?;
+
+    let actor_uids = notifications.iter().filter_map(|x| x.actor_uid).unique().collect_vec();
+    let profiles = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &actor_uids).await?;
+    let items = notifications.into_iter()
+        .map(|x| NotificationItem {
+            id: x.id,
+            actor: x.actor_uid.and_then(|uid| profiles.get(&uid).cloned()),
+            r#type: x.r#type,
+            data: x.data,
+            read: x.read_time.is_some(),
+            create_time: x.create_time,
+        })
+        .collect_vec();
+    ok!(pagination.into_page(items, total))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarkReadReq {
+    /// Mark all the notifications read if absent
+    pub ids: Option<Vec<i64>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MarkReadResp {
+    /// The notifications changed from unread to read
+    pub count: u64,
+}
+
+#[framed]
+async fn mark_read(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<MarkReadReq>,
+) -> WebResult<MarkReadResp> {
+    let count = match req.ids {
+        Some(ref ids) => {
+            if ids.len() > MAX_MARK_READ_IDS {
+                err!("too_many_ids", "At most {} notifications can be marked at once", MAX_MARK_READ_IDS)
+            }
+            NotificationDao::mark_read(&state.sql_pool, claims.uid(), ids, Utc::now()).await?
+        }
+        None => NotificationDao::mark_all_read(&state.sql_pool, claims.uid(), Utc::now()).await?,
+    };
+    ok!(MarkReadResp { count })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnreadCountResp {
+    pub count: i64,
+}
+
+#[framed]
+async fn unread_count(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<UnreadCountResp> {
+    let count = NotificationDao::count_by_user(&state.sql_pool, claims.uid(), true).await?;
+    ok!(UnreadCountResp { count })
+}
@@ -0,0 +1,85 @@
+use crate::db::featured_song::{FeaturedSongDao, IFeaturedSongDao, COLLECTION_ONBOARDING};
+use crate::db::song_tag::{ISongTagDao, SongTagDao};
+use crate::service::song::PublicSongDetail;
+use crate::service::{preference, recommend_v2, song};
+use crate::web::jwt::Claims;
+use crate::web::result::WebResult;
+use crate::web::routes::song::TagItem;
+use crate::web::routes::user;
+use crate::web::state::AppState;
+use crate::ok;
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// The number of tags offered in the interest picker
+const PICKER_TAGS: i64 = 30;
+/// The number of hot songs used when no starter song is featured
+const FALLBACK_SONGS: i64 = 30;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/starter", get(starter))
+        .route("/interests", post(interests))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StarterResp {
+    /// The starter set featured by the contributors, or the weekly hot songs if it's empty
+    pub songs: Vec<PublicSongDetail>,
+    /// The tags to pick the interests from
+    pub tags: Vec<TagItem>,
+}
+
+/// The content for the users without any history
+#[framed]
+async fn starter(
+    state: State<AppState>,
+) -> WebResult<StarterResp> {
+    let featured = FeaturedSongDao::list_by_collection(&state.sql_pool, COLLECTION_ONBOARDING).await?;
+    let songs = if featured.is_empty() {
+        recommend_v2::get_hot_songs(&state.redis_conn, &state.sql_pool, 7, FALLBACK_SONGS).await?
+    } else {
+        let ids = featured.iter().map(|x| x.song_id).collect_vec();
+        let mut details = song::get_public_detail_with_cache(state.redis_conn.clone(), &state.sql_pool, &ids).await?;
+        // Keep the featured order, the deleted songs are skipped
+        ids.iter().filter_map(|x| details.remove(x)).collect()
+    };
+    let tags = SongTagDao::list_popular(&state.sql_pool, PICKER_TAGS).await?
+        .into_iter()
+        .map(|x| TagItem { id: x.id, name: x.name, description: x.description })
+        .collect();
+    ok!(StarterResp { songs, tags })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InterestsReq {
+    pub tag_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct InterestsResp {
+    /// Today's recommendations re-rolled with the picked interests
+    pub songs: Vec<PublicSongDetail>,
+}
+
+/// Save the picked tags as the favorite tags, and return the recommendations seeded by them
+#[framed]
+async fn interests(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<InterestsReq>,
+) -> WebResult<InterestsResp> {
+    preference::seed_favorites(&state.sql_pool, claims.uid(), &req.tag_ids).await
+        .map_err(user::preferences_error)?;
+    if let Err(e) = recommend_v2::evict_user_recommend(state.redis_conn.clone(), claims.uid()).await {
+        warn!("Failed to evict the recommendations of user {}: {:?}", claims.uid(), e);
+    }
+    let songs = recommend_v2::get_recommend(claims.uid(), state.red_lock.clone(), state.redis_conn.clone(), &state.sql_pool).await?;
+    ok!(InterestsResp { songs })
+}
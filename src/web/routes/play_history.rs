@@ -1,20 +1,22 @@
 use crate::db::song::{ISongDao, SongDao, SongPlay};
 use crate::db::user_play_history::{IUserPlayHistory, IUserPlayHistoryExt, UserPlayHistoryDao};
-use crate::service::song;
+use crate::service::{song, song_exclusion, song_play};
 use crate::service::song::PublicSongDetail;
-use crate::web::extractors::XRealIP;
+use crate::util::redis_health;
+use crate::web::extractors::{ClientFingerprint, XRealIP};
 use crate::web::jwt::Claims;
+use crate::web::pagination::{CursorPage, CursorPagination};
 use crate::web::result::WebResult;
 use crate::web::state::AppState;
 use crate::{err, ok, util};
-use axum::extract::{Query, State};
+use axum::extract::State;
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use metrics::gauge;
-use redis::aio::ConnectionManager;
-use redis::{AsyncCommands, ExistenceCheck, SetExpiry, SetOptions};
+use redis::AsyncCommands;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -26,18 +28,12 @@ pub fn router() -> Router<AppState> {
         .route("/delete", post(delete))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CursorReq {
-    pub cursor: Option<DateTime<Utc>>,
-    pub size: usize
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CursorResp {
-    pub list: Vec<PlayHistoryItem>
-}
+/// The cursor is `{play_time in unix micros}_{song_id}` of the last item.
+///
+/// The legacy cursors in RFC 3339 format are still accepted.
+pub type CursorResp = CursorPage<PlayHistoryItem>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PlayHistoryItem {
     /// @deprecated since 260331
     /// @remove in 260901
@@ -49,13 +45,21 @@ pub struct PlayHistoryItem {
 async fn cursor(
     claims: Claims,
     state: State<AppState>,
-    req: Query<CursorReq>
+    pagination: CursorPagination<64>,
 ) -> WebResult<CursorResp> {
-    if req.size > 64 {
-        err!("size_exceeded", "Page size must be less than 64")
-    }
-    let before_time = req.cursor.unwrap_or_else(|| Utc::now());
-    let history = UserPlayHistoryDao::cursor_by_user_id(&state.sql_pool, claims.uid(), before_time, req.size).await?;
+    let before = match pagination.cursor {
+        Some(ref x) => match parse_cursor(x) {
+            Some(x) => Some(x),
+            None => err!("invalid_cursor", "Invalid cursor")
+        },
+        None => None
+    };
+    let history = UserPlayHistoryDao::cursor_by_user_id(&state.sql_pool, claims.uid(), before, pagination.page_size as usize).await?;
+    let next_cursor = if history.len() as i64 == pagination.page_size {
+        history.last().map(|x| format!("{}_{}", x.create_time.timestamp_micros(), x.song_id))
+    } else {
+        None
+    };
 
     let song_ids_distinct = history.iter().map(|x| x.song_id)
         .collect::<HashSet<i64>>().into_iter().collect_vec();
@@ -73,24 +77,34 @@ async fn cursor(
         )
         .collect_vec();
 
-    ok!(CursorResp { list: result })
+    ok!(CursorResp { items: result, next_cursor })
+}
+
+fn parse_cursor(cursor: &str) -> Option<(DateTime<Utc>, i64)> {
+    if let Ok(x) = DateTime::parse_from_rfc3339(cursor) {
+        // Strictly before the time, as the legacy cursor did
+        return Some((x.with_timezone(&Utc), i64::MIN));
+    }
+    let (micros, song_id) = cursor.split_once('_')?;
+    Some((DateTime::from_timestamp_micros(micros.parse().ok()?)?, song_id.parse().ok()?))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TouchReq {
     pub song_id: i64
 }
 
-async fn touch(
+pub(crate) async fn touch(
     claims: Claims,
     mut state: State<AppState>,
     req: Json<TouchReq>
 ) -> WebResult<()> {
-    if cooldown(claims.uid(), req.song_id, &mut state.redis_conn).await? {
+    if !song::is_public(state.redis_conn.clone(), &state.sql_pool, req.song_id).await? {
+        err!("song_not_found", "Song not found")
+    }
+    if song_play::cooldown(claims.uid(), req.song_id, &mut state.redis_conn).await? {
         err!("cooldown", "Please wait 60 seconds before touching again");
     }
-    let mut tx = state.sql_pool.begin().await?;
-
     let data = SongPlay {
         id: 0,
         song_id: req.song_id,
@@ -98,31 +112,47 @@ async fn touch(
         anonymous_uid: None,
         create_time: Utc::now(),
     };
-    SongDao::insert_plays(&mut *tx, &[data]).await?;
+    let mut tx = state.sql_pool.begin().await?;
     UserPlayHistoryDao::delete_and_insert(&mut tx, claims.uid(), req.song_id).await?;
     tx.commit().await?;
+    song_play::record_play(state.redis_conn.clone(), &state.sql_pool, data).await?;
+    song_play::add_pending_play(state.redis_conn.clone(), &state.sql_pool, Some(claims.uid()), req.song_id).await?;
+    song_exclusion::record_played(state.redis_conn.clone(), claims.uid(), req.song_id).await;
 
     let dau_key = format!("dau:hll:{}", Utc::now().date_naive().to_string());
-    let r: bool = state.redis_conn.pfadd(&dau_key, claims.uid()).await?;
-    if r {
-        let dau: i64 = state.redis_conn.pfcount(dau_key).await?;
+    let r: Option<bool> = redis_health::cached(state.redis_conn.pfadd(&dau_key, claims.uid())).await;
+    if r == Some(true) && let Some(dau) = redis_health::cached(state.redis_conn.pfcount::<_, i64>(dau_key)).await {
         gauge!("daily_active_user").set(dau as f64);
     }
 
     ok!(())
 }
 
-async fn touch_anonymous(
+/// Since 260428, the clients can send a `X-Client-Fingerprint` header, the hex of a SHA-256 salted by the client,
+/// to be told apart from the others behind the same IP. The plays of a song from an IP are still capped per hour
+/// regardless of the fingerprints.
+pub(crate) async fn touch_anonymous(
     ip: XRealIP,
+    fingerprint: ClientFingerprint,
     mut state: State<AppState>,
     req: Json<TouchReq>
 ) -> WebResult<()>{
     // Convert to anonymous uid
-    let anonymous_uid = util::convert_ip_to_anonymous_uid(&ip.0)?;
+    let anonymous_uid = match fingerprint.0 {
+        Some(ref x) if !util::is_valid_client_fingerprint(x) => err!("invalid_fingerprint", "Invalid client fingerprint"),
+        Some(ref x) => util::convert_fingerprint_to_anonymous_uid(&ip.0, x)?,
+        None => util::convert_ip_to_anonymous_uid(&ip.0)?,
+    };
 
-    if cooldown(anonymous_uid, req.song_id, &mut state.redis_conn).await? {
+    if !song::is_public(state.redis_conn.clone(), &state.sql_pool, req.song_id).await? {
+        err!("song_not_found", "Song not found")
+    }
+    if song_play::cooldown(anonymous_uid, req.song_id, &mut state.redis_conn).await? {
         err!("cooldown", "Please wait 60 seconds before touching again");
     }
+    if song_play::anonymous_ip_limited(&ip.0, req.song_id, &mut state.redis_conn).await? {
+        err!("cooldown", "Too many plays of this song from your network, please try again later");
+    }
     let data = SongPlay {
         id: 0,
         song_id: req.song_id,
@@ -130,19 +160,19 @@ async fn touch_anonymous(
         anonymous_uid: Some(anonymous_uid),
         create_time: Utc::now(),
     };
-    SongDao::insert_plays(&state.sql_pool, &[data]).await?;
+    song_play::record_play(state.redis_conn.clone(), &state.sql_pool, data).await?;
+    song_play::add_pending_play(state.redis_conn.clone(), &state.sql_pool, None, req.song_id).await?;
 
     let daau = format!("dau_anonymous:hll:{}", Utc::now().date_naive().to_string());
 
-    let r: bool = state.redis_conn.pfadd(&daau, &ip.0).await?;
-    if r {
-        let dau: i64 = state.redis_conn.pfcount(daau).await?;
+    let r: Option<bool> = redis_health::cached(state.redis_conn.pfadd(&daau, anonymous_uid)).await;
+    if r == Some(true) && let Some(dau) = redis_health::cached(state.redis_conn.pfcount::<_, i64>(daau)).await {
         gauge!("daily_active_anonymous_user").set(dau as f64);
     }
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteReq {
     pub history_id: i64,
 }
@@ -155,17 +185,3 @@ async fn delete(
     SongDao::delete_play(&state.sql_pool, claims.uid(), req.history_id).await?;
     ok!(())
 }
-
-async fn cooldown(
-    user_id: i64,
-    song_id: i64,
-    redis: &mut ConnectionManager
-) -> anyhow::Result<bool> {
-    let cooldown_key = format!("play:touch_cooldown:{}:{}", user_id, song_id);
-    let cooldown_absent: bool = redis.set_options(
-        cooldown_key, 0,
-        SetOptions::default().conditional_set(ExistenceCheck::NX)
-            .with_expiration(SetExpiry::EX(60)) // CD for 60 secs
-    ).await?;
-    Ok(!cooldown_absent)
-}
\ No newline at end of file
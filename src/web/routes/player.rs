@@ -0,0 +1,92 @@
+use crate::service::play_queue::{self, PlayQueue, SaveQueueError};
+use crate::web::jwt::Claims;
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::{err, ok};
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/queue/get", get(get_queue))
+        .route("/queue/set", post(set_queue))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueueData {
+    pub song_ids: Vec<i64>,
+    pub current_index: usize,
+    pub position_ms: u64,
+    pub device: Option<String>,
+    pub update_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GetQueueResp {
+    /// 0 if there is no queue saved
+    pub revision: i64,
+    pub queue: Option<QueueData>,
+}
+
+#[framed]
+async fn get_queue(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<GetQueueResp> {
+    let (revision, queue) = play_queue::get(state.redis_conn.clone(), claims.uid()).await?;
+    ok!(GetQueueResp {
+        revision,
+        queue: queue.map(|x| QueueData {
+            song_ids: x.song_ids,
+            current_index: x.current_index,
+            position_ms: x.position_ms,
+            device: x.device,
+            update_time: x.update_time,
+        }),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetQueueReq {
+    /// The revision the queue is based on, from `/player/queue/get` or the last save
+    pub revision: i64,
+    pub song_ids: Vec<i64>,
+    pub current_index: usize,
+    pub position_ms: u64,
+    pub device: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetQueueResp {
+    pub revision: i64,
+}
+
+/// Rejected with `queue_conflict` if the queue is saved by another device since the revision,
+/// the client should get the queue again and decide which one to keep
+#[framed]
+async fn set_queue(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<SetQueueReq>,
+) -> WebResult<SetQueueResp> {
+    let req = req.0;
+    let queue = PlayQueue {
+        song_ids: req.song_ids,
+        current_index: req.current_index,
+        position_ms: req.position_ms,
+        device: req.device.map(|x| x.chars().take(64).collect()),
+        update_time: Utc::now(),
+    };
+    match play_queue::save(state.redis_conn.clone(), claims.uid(), req.revision, &queue).await {
+        Ok(revision) => ok!(SetQueueResp { revision }),
+        Err(e @ SaveQueueError::TooManySongs) => err!("too_many_songs", "{}", e),
+        Err(e @ SaveQueueError::InvalidIndex) => err!("invalid_index", "{}", e),
+        Err(e @ SaveQueueError::Conflict(_)) => err!("queue_conflict", "{}", e),
+        Err(e) => Err(e)?,
+    }
+}
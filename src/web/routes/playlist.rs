@@ -1,41 +1,58 @@
 use crate::db::playlist::{FavoritePlaylist, IPlaylistDao, Playlist, PlaylistDao, PlaylistSong};
-use crate::db::song::SongDao;
+use crate::db::error::{begin_serializable, retry_on_conflict, DbError};
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::localized_title::{self, ILocalizedTitleDao, LocalizedTitleDao};
+use crate::db::user_storage_object;
+use crate::db::weekly_selection::{IWeeklySelectionDao, WeeklySelectionDao};
 use crate::db::CrudDao;
 use crate::service::playlist;
-use crate::service::playlist::{GetDetailError, PlaylistMetadata};
-use crate::service::upload::ResizeType;
-use crate::util::IsBlank;
+use crate::service::playlist::{ExportFormat, GetDetailError, PlaylistCfg, PlaylistMetadata};
+use crate::service::image::{ImageCfg, ImageProcessOptions};
+use crate::service::localization::{self, LocalizedTitleItem};
+use crate::service::storage_quota;
+use crate::service::upload::UploadMetrics;
+use crate::util::{fractional_index, IsBlank};
 use crate::web::jwt::Claims;
+use crate::web::limits::LimitsCfg;
+use crate::web::multipart::{self, FieldSpec};
+use crate::web::pagination::{Page, PageQuery, Pagination};
 use crate::web::result::{CommonError, WebError, WebResult};
 use crate::web::routes::user::PublicUserProfile;
 use crate::web::state::AppState;
 use crate::{common, err, ok, search, service};
-use anyhow::Context;
 use async_backtrace::framed;
-use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
+use axum::extract::{Multipart, Query, State};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Json, Router};
-use bytes::Bytes;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-pub fn router() -> Router<AppState> {
+pub fn router(limits: &LimitsCfg) -> Router<AppState> {
     Router::new()
-        .route("/set_cover", post(set_cover).layer(DefaultBodyLimit::max(10 * 1024 * 1024)))
+        .route("/set_cover", post(set_cover).layer(limits.image_body_limit()))
         // @since 260121
         .route("/detail", get(detail))
         .route("/detail_private", get(detail_private))
+        // @since 260504
+        .route("/detail_public", get(detail_public))
+        .route("/export", get(export))
         .route("/list", get(list))
         // @since 260121
         .route("/list_public_by_user", get(list_public_by_user))
+        // @since 260504
+        .route("/page_by_user", get(page_by_user))
         .route("/list_containing", get(list_containing))
         .route("/create", post(create))
         .route("/update", post(update))
         .route("/delete", post(delete))
         .route("/add_song", post(add_song))
+        .route("/add_songs", post(add_songs))
         .route("/remove_song", post(remove_song))
         .route("/change_order", post(change_order))
         // @since 260121
@@ -48,14 +65,18 @@ pub fn router() -> Router<AppState> {
         .route("/favorite/remove", post(remove_favorite))
         // @since 260122
         .route("/favorite/check", get(check_favorite))
+        // @since 260505
+        .route("/official/weekly", get(official_weekly))
+        // @since 260505
+        .route("/official/weekly/archive", get(official_weekly_archive))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailReq {
     pub id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailResp {
     pub playlist_info: PlaylistItem,
     pub songs: Vec<SongItem>,
@@ -64,7 +85,7 @@ pub struct DetailResp {
 }
 
 // Basic song information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SongItem {
     pub song_id: i64,
     pub song_display_id: String,
@@ -111,12 +132,62 @@ async fn detail_private(
     }
 }
 
+/// The detail of a public playlist for sharing, without logging in.
+///
+/// The private playlists are reported as not found, so their existence is not revealed.
+/// @since 260504
+#[framed]
+async fn detail_public(
+    state: State<AppState>,
+    req: Query<DetailReq>,
+) -> WebResult<DetailResp> {
+    match playlist::get_detail(&state, None, req.id).await {
+        Ok(x) => ok!(x),
+        Err(e) => match e {
+            GetDetailError::NotFound { .. } | GetDetailError::NotOwner { .. } => err!("not_found", "Playlist not found"),
+            e => Err(e)?
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportReq {
+    pub id: i64,
+    /// `m3u` or `json`
+    pub format: String,
+}
+
+/// Download the playlist as an M3U8 with the stream URLs, or a JSON manifest of the display ids and titles.
+///
+/// Others' playlists can be exported if they are public.
+#[framed]
+async fn export(
+    claims: Option<Claims>,
+    state: State<AppState>,
+    req: Query<ExportReq>,
+) -> Result<Response, WebError<CommonError>> {
+    let Some(format) = ExportFormat::parse(&req.format) else {
+        err!("invalid_format", "Format must be m3u or json")
+    };
+    let content = match playlist::export(&state, claims.map(|x| x.uid()), req.id, format).await {
+        Ok(x) => x,
+        Err(GetDetailError::NotFound { .. }) => err!("not_found", "Playlist not found"),
+        Err(GetDetailError::NotOwner { .. }) => err!("not_owner", "You are not the owner of this playlist"),
+        Err(e) => Err(e)?,
+    };
+    let disposition = format!("attachment; filename=\"playlist-{}.{}\"", req.id, format.extension());
+    Ok((
+        [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        content,
+    ).into_response())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListResp {
     pub playlists: Vec<PlaylistItem>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PlaylistItem {
     pub id: i64,
     pub name: String,
@@ -127,6 +198,39 @@ pub struct PlaylistItem {
     pub songs_count: i64,
     /// @since 260122
     pub update_time: DateTime<Utc>,
+    /// The names (title) and descriptions (subtitle) in the other languages, the `name` and `description` are
+    /// replaced by the one preferred by `Accept-Language` in the detail.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Vec<LocalizedTitleItem>,
+    /// The language of the `name` if it's localized, `None` for the original name.
+    /// @since 260430
+    #[serde(default)]
+    pub name_lang: Option<String>,
+    /// The original name and description if they are replaced.
+    /// @since 260430
+    #[serde(default)]
+    pub default_name: Option<String>,
+    /// @since 260430
+    #[serde(default)]
+    pub default_description: Option<String>,
+}
+
+impl PlaylistItem {
+    /// Take the name preferred by the current request, see [localization::select_for_request]
+    pub fn localize(&mut self) {
+        if self.name_lang.is_some() {
+            return;
+        }
+        if let Some(x) = localization::select_for_request(&self.localized_titles) {
+            self.default_name = Some(std::mem::replace(&mut self.name, x.title.clone()));
+            // Keep the original description if it's not localized
+            if !x.subtitle.is_blank() {
+                self.default_description = self.description.replace(x.subtitle.clone());
+            }
+            self.name_lang = Some(x.lang.clone());
+        }
+    }
 }
 
 #[framed]
@@ -137,6 +241,8 @@ async fn list(
     let playlists = PlaylistDao::list_by_user(&state.sql_pool, claims.uid()).await?;
     let playlist_ids = playlists.iter().map(|x| x.id).collect_vec();
     let count = PlaylistDao::count_songs(&state.sql_pool, &playlist_ids).await?;
+    // The owner's own list is not localized
+    let mut localized_titles = localization::list_by_ids(&state.sql_pool, localized_title::ENTITY_PLAYLIST, &playlist_ids).await?;
     let mut result = Vec::<PlaylistItem>::new();
 
     for x in playlists {
@@ -149,6 +255,10 @@ async fn list(
             update_time: x.update_time,
             is_public: x.is_public,
             songs_count: count.get(&x.id).cloned().unwrap_or(0),
+            localized_titles: localized_titles.remove(&x.id).unwrap_or_default(),
+            name_lang: None,
+            default_name: None,
+            default_description: None,
         };
         result.push(item);
     }
@@ -157,12 +267,12 @@ async fn list(
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListPublicByUserReq {
     pub user_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListPublicByUserResp {
     pub playlists: Vec<PlaylistMetadata>,
 }
@@ -188,12 +298,49 @@ async fn list_public_by_user(
     ok!(result)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageByUserReq {
+    pub user_id: i64,
+}
+
+/// The whole query of `/playlist/page_by_user`, [PageByUserReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageByUserQuery {
+    #[serde(flatten)]
+    pub req: PageByUserReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
+}
+
+pub type PageByUserResp = Page<PlaylistMetadata>;
+
+/// The public playlists of the user, the recently updated first, without logging in
+/// @since 260504
+#[framed]
+async fn page_by_user(
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageByUserReq>,
+) -> WebResult<PageByUserResp> {
+    let count = PlaylistDao::count_public_by_user(&state.sql_pool, req.user_id).await?;
+    if count == 0 {
+        ok!(pagination.into_page(vec![], 0))
+    }
+    let playlist_ids = PlaylistDao::page_public_by_user(&state.sql_pool, req.user_id, pagination.page_index, pagination.page_size).await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect_vec();
+    let mut playlists = playlist::list_playlist_metadata(state.redis_conn.clone(), &state.sql_pool, &playlist_ids, true).await?;
+    let result = playlist_ids.iter().filter_map(|x| playlists.remove(x)).collect_vec();
+    ok!(pagination.into_page(result, count))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListContainingReq {
     pub song_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ListContainingResp {
     pub playlist_ids: Vec<i64>,
 }
@@ -213,16 +360,18 @@ async fn list_containing(
     ok!(ListContainingResp {playlist_ids: result})
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreatePlaylistReq {
     pub name: String,
-    // pub use_song_cover: bool,
+    /// Generate the cover from the first songs, defaults to true
+    #[serde(default)]
+    pub use_song_cover: Option<bool>,
     // pub cover_temp_id: Option<String>,
     pub description: Option<String>,
     pub is_public: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreatePlaylistResp {
     pub id: i64,
 }
@@ -234,10 +383,10 @@ async fn create(
     req: Json<CreatePlaylistReq>,
 ) -> WebResult<CreatePlaylistResp> {
     // Validate input
-    if req.name.is_blank() || req.name.chars().count() > 32 {
+    if req.name.is_blank() || req.name.chars().count() > state.limits.playlist_name_max_chars {
         err!("invalid_name", "Playlist name invalid")
     }
-    if let Some(ref desc) = req.description && desc.chars().count() > 300 {
+    if let Some(ref desc) = req.description && desc.chars().count() > state.limits.playlist_description_max_chars {
         err!("description_too_long", "Playlist description is too long")
     }
 
@@ -254,10 +403,12 @@ async fn create(
         name: req.name.clone(),
         description: req.description.clone(),
         user_id: uid,
-        cover_url: None, // TODO: Pick a song cover by default
+        cover_url: None,
         is_public: req.is_public,
         create_time: Utc::now(),
         update_time: Utc::now(),
+        use_song_cover: req.use_song_cover.unwrap_or(true),
+        version: 0,
     };
     let id = PlaylistDao::insert(&state.sql_pool, &entity).await?;
 
@@ -276,14 +427,20 @@ async fn create(
     ok!(CreatePlaylistResp { id })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UpdatePlaylistReq {
     pub id: i64,
     pub name: String,
-    // pub use_song_cover: bool,
+    /// Generate the cover from the first songs, unchanged if absent
+    #[serde(default)]
+    pub use_song_cover: Option<bool>,
     // pub cover_temp_id: Option<String>,
     pub description: Option<String>,
     pub is_public: bool,
+    /// Replace the names and descriptions in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
 }
 
 #[framed]
@@ -293,25 +450,36 @@ async fn update(
     req: Json<UpdatePlaylistReq>,
 ) -> WebResult<()> {
     // Validate input
-    if req.name.is_blank() || req.name.chars().count() > 32 {
+    if req.name.is_blank() || req.name.chars().count() > state.limits.playlist_name_max_chars {
         err!("invalid_name", "Playlist name invalid")
     }
-    if let Some(ref desc) = req.description && desc.chars().count() > 300 {
+    if let Some(ref desc) = req.description && desc.chars().count() > state.limits.playlist_description_max_chars {
         err!("description_too_long", "Playlist description is too long")
     }
+    if let Some(ref x) = req.localized_titles {
+        localization::validate(x, state.limits.playlist_name_max_chars, state.limits.playlist_description_max_chars)?;
+    }
 
     let playlist = check_ownership(&claims, &state.sql_pool, req.id).await?;
+    let song_cover_enabled = req.use_song_cover == Some(true) && !playlist.use_song_cover;
+
+    modify_playlist(&state.sql_pool, req.id, |playlist| {
+        playlist.name = req.name.clone();
+        playlist.description = req.description.clone();
+        playlist.is_public = req.is_public;
+        if let Some(x) = req.use_song_cover {
+            playlist.use_song_cover = x;
+        }
+    }).await?;
+    if let Some(ref x) = req.localized_titles {
+        let mut tx = state.sql_pool.begin().await?;
+        localization::replace(&mut tx, localized_title::ENTITY_PLAYLIST, req.id, x).await?;
+        tx.commit().await?;
+    }
 
-    PlaylistDao::update_by_id(
-        &state.sql_pool,
-        &Playlist {
-            name: req.name.clone(),
-            description: req.description.clone(),
-            is_public: req.is_public,
-            update_time: Utc::now(),
-            ..playlist
-        },
-    ).await?;
+    if song_cover_enabled {
+        playlist::spawn_refresh_song_cover(&state, req.id);
+    }
 
     // Update search document if needed.
     if req.is_public {
@@ -327,7 +495,7 @@ async fn update(
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DeletePlaylistReq {
     pub id: i64,
 }
@@ -339,76 +507,220 @@ async fn delete(
     req: Json<DeletePlaylistReq>,
 ) -> WebResult<()> {
     let playlist = check_ownership(&claims, &state.sql_pool, req.id).await?;
-    PlaylistDao::delete_by_id(&state.sql_pool, playlist.id).await?;
+    let mut tx = state.sql_pool.begin().await?;
+    PlaylistDao::delete_by_id(&mut *tx, playlist.id).await?;
+    LocalizedTitleDao::delete_by_entity_id(&mut *tx, localized_title::ENTITY_PLAYLIST, playlist.id).await?;
+    tx.commit().await?;
 
     let _ = search::playlist::delete_playlist_document(&state.meilisearch, &[playlist.id]).await;
 
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AddSongReq {
     pub playlist_id: i64,
     pub song_id: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddSongResp {
+    /// The number of songs in the playlist after adding
+    pub songs_count: i64,
+}
+
+enum AddSongOutcome {
+    Added(i64),
+    Existed,
+    Full,
+}
+
 #[framed]
 async fn add_song(
     claims: Claims,
     state: State<AppState>,
     req: Json<AddSongReq>,
-) -> WebResult<()> {
-    let mut playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
+) -> WebResult<AddSongResp> {
+    let playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
+    let cfg = PlaylistCfg::load(&state.config)?;
 
     let song = SongDao::get_by_id(&state.sql_pool, req.song_id).await?
         .ok_or_else(|| common!("song_not_found", "Song not found"))?;
 
-    let songs = PlaylistDao::list_songs(&state.sql_pool, playlist.id).await?;
-    if songs.len() >= 1000 {
-        err!("playlist_full", "The playlist is full")
+    // The version check of the playlist serializes the concurrent additions, so the cap can't be exceeded
+    let result = retry_on_conflict(|| async {
+        let mut tx = state.sql_pool.begin().await?;
+        let mut playlist = PlaylistDao::get_by_id(&mut *tx, playlist.id).await?
+            .ok_or(DbError::NotFound)?;
+        let count = PlaylistDao::count_songs_of(&mut *tx, playlist.id).await?;
+        if count >= cfg.max_songs {
+            return Ok(AddSongOutcome::Full);
+        }
+        let last_key = PlaylistDao::get_last_sort_key(&mut *tx, playlist.id).await?;
+        let added = PlaylistDao::add_song(
+            &mut *tx,
+            &PlaylistSong {
+                playlist_id: playlist.id,
+                song_id: song.id,
+                sort_key: fractional_index::key_after(last_key.as_deref()),
+                add_time: Utc::now(),
+            },
+        ).await?;
+        if !added {
+            return Ok(AddSongOutcome::Existed);
+        }
+        playlist.update_time = Utc::now();
+        PlaylistDao::update_by_id_checked(&mut *tx, &playlist).await?;
+        tx.commit().await?;
+        Ok(AddSongOutcome::Added(count + 1))
+    }).await;
+    match result {
+        Ok(AddSongOutcome::Added(songs_count)) => {
+            if songs_count <= playlist::COVER_SONGS as i64 {
+                playlist::spawn_refresh_song_cover(&state, req.playlist_id);
+            }
+            ok!(AddSongResp { songs_count })
+        }
+        Ok(AddSongOutcome::Existed) => err!("song_existed", "Song {} already exists in the playlist {}", song.id, playlist.id),
+        Ok(AddSongOutcome::Full) => err!("playlist_full", "The playlist is full, at most {} songs", cfg.max_songs),
+        Err(DbError::NotFound) => err!("not_found", "Playlist not found"),
+        Err(e) => Err(e)?,
     }
-    let existed = songs.iter().any(|x| x.song_id == song.id);
-    if existed {
-        err!("song_existed", "Song {} already exists in the playlist {}", song.id, playlist.id);
+}
+
+/// At most this many songs can be added in a `/playlist/add_songs` request
+pub const MAX_BATCH_ADD_SONGS: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddSongsReq {
+    pub playlist_id: i64,
+    /// Added in this order
+    pub song_ids: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddSongsResp {
+    pub results: Vec<AddSongsItem>,
+    /// The number of songs in the playlist after adding
+    pub songs_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AddSongsItem {
+    pub song_id: i64,
+    pub result: AddSongsResult,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AddSongsResult {
+    Added,
+    /// Already in the playlist, or repeated in the request
+    Existed,
+    SongNotFound,
+    PlaylistFull,
+}
+
+#[framed]
+async fn add_songs(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<AddSongsReq>,
+) -> WebResult<AddSongsResp> {
+    if req.song_ids.is_empty() || req.song_ids.len() > MAX_BATCH_ADD_SONGS {
+        err!("invalid_song_ids", "1 to {} songs can be added at once", MAX_BATCH_ADD_SONGS)
     }
-    let target_order = songs.len() as i32;
-    playlist.update_time = Utc::now();
+    let playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
+    let cfg = PlaylistCfg::load(&state.config)?;
 
-    let mut tx = state.sql_pool.begin().await?;
+    let found: HashSet<i64> = SongDao::list_by_ids(&state.sql_pool, &req.song_ids).await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
 
-    PlaylistDao::add_song(
-        &mut *tx,
-        &PlaylistSong {
-            playlist_id: playlist.id,
-            song_id: song.id,
-            order_index: target_order,
-            add_time: Utc::now(),
-        },
-    ).await?;
-    PlaylistDao::update_by_id(&mut *tx, &playlist).await?;
-    tx.commit().await?;
-    ok!(())
+    let result = retry_on_conflict(|| async {
+        let mut tx = state.sql_pool.begin().await?;
+        let mut playlist = PlaylistDao::get_by_id(&mut *tx, playlist.id).await?
+            .ok_or(DbError::NotFound)?;
+        let count_before = PlaylistDao::count_songs_of(&mut *tx, playlist.id).await?;
+        let mut count = count_before;
+        let mut last_key = PlaylistDao::get_last_sort_key(&mut *tx, playlist.id).await?;
+        let mut seen = HashSet::new();
+        let mut results = Vec::with_capacity(req.song_ids.len());
+        for &song_id in &req.song_ids {
+            let result = if !found.contains(&song_id) {
+                AddSongsResult::SongNotFound
+            } else if !seen.insert(song_id) {
+                AddSongsResult::Existed
+            } else if count >= cfg.max_songs {
+                AddSongsResult::PlaylistFull
+            } else {
+                let sort_key = fractional_index::key_after(last_key.as_deref());
+                let added = PlaylistDao::add_song(
+                    &mut *tx,
+                    &PlaylistSong { playlist_id: playlist.id, song_id, sort_key: sort_key.clone(), add_time: Utc::now() },
+                ).await?;
+                if added {
+                    count += 1;
+                    last_key = Some(sort_key);
+                    AddSongsResult::Added
+                } else {
+                    AddSongsResult::Existed
+                }
+            };
+            results.push(AddSongsItem { song_id, result });
+        }
+        if count > count_before {
+            playlist.update_time = Utc::now();
+            PlaylistDao::update_by_id_checked(&mut *tx, &playlist).await?;
+        }
+        tx.commit().await?;
+        Ok((results, count_before, count))
+    }).await;
+    let (results, count_before, songs_count) = match result {
+        Ok(x) => x,
+        Err(DbError::NotFound) => err!("not_found", "Playlist not found"),
+        Err(e) => Err(e)?,
+    };
+
+    if songs_count > count_before && count_before < playlist::COVER_SONGS as i64 {
+        playlist::spawn_refresh_song_cover(&state, playlist.id);
+    }
+    ok!(AddSongsResp { results, songs_count })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RemoveSongReq {
     pub playlist_id: i64,
     pub song_id: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RemoveSongResp {
+    /// The number of songs in the playlist after removing
+    pub songs_count: i64,
+}
+
 #[framed]
 async fn remove_song(
     claims: Claims,
     state: State<AppState>,
     req: Json<RemoveSongReq>,
-) -> WebResult<()> {
+) -> WebResult<RemoveSongResp> {
     let playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
 
+    let songs = PlaylistDao::list_songs(&state.sql_pool, playlist.id).await?;
+    let covered = songs.iter().take(playlist::COVER_SONGS).any(|x| x.song_id == req.song_id);
+
     PlaylistDao::remove_song(&state.sql_pool, playlist.id, req.song_id).await?;
-    ok!(())
+    if covered {
+        playlist::spawn_refresh_song_cover(&state, playlist.id);
+    }
+    let songs_count = PlaylistDao::count_songs_of(&state.sql_pool, playlist.id).await?;
+    ok!(RemoveSongResp { songs_count })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChangeOrderReq {
     pub playlist_id: i64,
     pub song_id: i64,
@@ -416,6 +728,14 @@ pub struct ChangeOrderReq {
     pub target_order: usize,
 }
 
+enum MoveOutcome {
+    /// The previous position of the song
+    Moved(usize),
+    NotFound,
+    /// The neighbors have no room between them, e.g. a malformed key
+    Unordered,
+}
+
 #[framed]
 async fn change_order(
     claims: Claims,
@@ -424,33 +744,71 @@ async fn change_order(
 ) -> WebResult<()> {
     let playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
 
-    let mut songs = PlaylistDao::list_songs(&state.sql_pool, playlist.id).await?;
-    songs.sort_by(|a, b| a.order_index.cmp(&b.order_index));
-
-    let src_index = songs.iter().position(|x| x.song_id == req.song_id)
-        .ok_or_else(|| common!("song_not_found", "Song not found"))?;
-
-    // Move to target order_index
-    if src_index == req.target_order {
-        ok!(())
-    }
-    // Reorder
-    if req.target_order > src_index {
-        // move down
-        songs[src_index..=req.target_order].rotate_left(1);
-    } else {
-        // move up
-        songs[req.target_order..=src_index].rotate_right(1);
+    // Only the moved song is written, in a serializable transaction so concurrent reorders won't pick the same key
+    let found = retry_on_conflict(|| async {
+        let mut tx = begin_serializable(&state.sql_pool).await?;
+        let mut songs = PlaylistDao::list_songs(&mut *tx, playlist.id).await?;
+        if songs.windows(2).any(|x| x[0].sort_key >= x[1].sort_key) {
+            // Duplicated keys leave no room between them
+            playlist::normalize_sort_keys(&mut songs);
+            PlaylistDao::update_songs_orders(&mut tx, &songs).await?;
+        }
+        let Some(position) = songs.iter().position(|x| x.song_id == req.song_id) else {
+            return Ok(MoveOutcome::NotFound);
+        };
+        let Some(sort_key) = sort_key_for_move(&songs, req.song_id, req.target_order) else {
+            return Ok(MoveOutcome::Unordered);
+        };
+        PlaylistDao::update_song_sort_key(&mut *tx, playlist.id, req.song_id, &sort_key).await?;
+        tx.commit().await?;
+        Ok(MoveOutcome::Moved(position))
+    }).await?;
+
+    let position = match found {
+        MoveOutcome::Moved(x) => x,
+        MoveOutcome::NotFound => err!("song_not_found", "Song not found"),
+        MoveOutcome::Unordered => err!("invalid_sort_key", "The songs of the playlist are not ordered, please try again"),
+    };
+    if position.min(req.target_order) < playlist::COVER_SONGS {
+        playlist::spawn_refresh_song_cover(&state, playlist.id);
     }
-    // Apply order
-    for (i, song) in songs.iter_mut().enumerate() {
-        song.order_index = i as i32;
+    ok!(())
+}
+
+/// The sort key to move the song to `target_order` between its new neighbors, `None` if the song is not found or
+/// the neighbors are not ordered.
+///
+/// The songs must be sorted by the sort key.
+fn sort_key_for_move(songs: &[PlaylistSong], song_id: i64, target_order: usize) -> Option<String> {
+    let rest = songs.iter().filter(|x| x.song_id != song_id).collect_vec();
+    if rest.len() == songs.len() {
+        return None;
     }
+    let target_order = target_order.min(rest.len());
+    let before = target_order.checked_sub(1).map(|i| rest[i].sort_key.as_str());
+    let after = rest.get(target_order).map(|x| x.sort_key.as_str());
+    fractional_index::key_between(before, after)
+}
 
-    let mut tx = state.sql_pool.begin().await?;
-    PlaylistDao::update_songs_orders(&mut tx, &songs).await?;
-    tx.commit().await?;
-    ok!(())
+/// Re-read the playlist and apply the modification, it runs again if the playlist is updated concurrently
+async fn modify_playlist(
+    pool: &PgPool,
+    playlist_id: i64,
+    f: impl Fn(&mut Playlist),
+) -> Result<Playlist, WebError<CommonError>> {
+    let result = retry_on_conflict(|| async {
+        let mut playlist = PlaylistDao::get_by_id(pool, playlist_id).await?
+            .ok_or(DbError::NotFound)?;
+        f(&mut playlist);
+        playlist.update_time = Utc::now();
+        playlist.version = PlaylistDao::update_by_id_checked(pool, &playlist).await?;
+        Ok(playlist)
+    }).await;
+    match result {
+        Ok(x) => Ok(x),
+        Err(DbError::NotFound) => Err(common!("not_found", "Playlist not found")),
+        Err(e) => Err(e.into()),
+    }
 }
 
 async fn check_ownership(
@@ -471,40 +829,36 @@ pub struct SetCoverReq {
     pub playlist_id: i64,
 }
 
+/// Multipart fields: `json` of [SetCoverReq] and the image `file`
 async fn set_cover(
     claims: Claims,
     state: State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> WebResult<()> {
-    let body_field = multipart.next_field().await?
-        .with_context(|| "No data field found")?;
-    let json = body_field.text().await?;
-    let req: SetCoverReq = serde_json::from_str(&json).with_context(|| "Invalid JSON body")?;
-
-    let mut playlist = check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
-
-    let data_field = multipart
-        .next_field()
-        .await?
-        .with_context(|| "No data field found")?;
-    let bytes = data_field.bytes().await?;
-
-    // Validate image
-    if bytes.len() > 8 * 1024 * 1024 {
-        err!("image_too_large", "Image size must be less than 8MB");
-    }
-
-    let webp = service::upload::scale_down_to_webp(512, 512, bytes.clone(), ResizeType::Crop, 80f32)
-        .map_err(|_| common!("invalid_image", "The image is not supported"))?;
-
-    // Upload image
-    let sha1 = openssl::sha::sha1(&webp);
-    let filename = format!("images/playlist/{}.webp", hex::encode(sha1));
-    let result = state.file_host.upload(Bytes::from(webp), &filename).await?;
-
-    playlist.cover_url = Some(result.public_url);
-    playlist.update_time = Utc::now();
-    PlaylistDao::update_by_id(&state.sql_pool, &playlist).await?;
+    let mut upload_metrics = UploadMetrics::start("playlist");
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[
+        FieldSpec::json("json", 16 * 1024),
+        FieldSpec::image("file", state.limits.image_max_bytes),
+    ]).await)?;
+    let req: SetCoverReq = upload_metrics.check(fields.take_json("json"))?;
+
+    check_ownership(&claims, &state.sql_pool, req.playlist_id).await?;
+
+    let bytes = upload_metrics.check(fields.take_required("file"))?.bytes;
+    upload_metrics.received(bytes.len());
+    upload_metrics.check(storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), bytes.len()).await)?;
+
+    // Process and upload image
+    let options = ImageProcessOptions::playlist_cover(&ImageCfg::load(&state.config)?, &state.limits);
+    let result = upload_metrics.check(service::image::process_and_upload(state.file_host.as_ref(), "playlist", bytes, &options).await
+        .map_err(|e| e.into_web_error()))?;
+    storage_quota::track(&state.sql_pool, claims.uid(), user_storage_object::KIND_IMAGE, &result).await?;
+    upload_metrics.succeed();
+
+    let playlist = modify_playlist(&state.sql_pool, req.playlist_id, |playlist| {
+        playlist.cover_url = Some(result.public_url.clone());
+        playlist.use_song_cover = false;
+    }).await?;
 
     if playlist.is_public {
         search::playlist::add_or_replace_document(
@@ -517,7 +871,7 @@ async fn set_cover(
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchReq {
     pub q: String,
     pub limit: Option<usize>,
@@ -528,7 +882,7 @@ pub struct SearchReq {
 
 type SearchPlaylistItem = PlaylistMetadata;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResp {
     pub hits: Vec<SearchPlaylistItem>,
     pub query: String,
@@ -566,7 +920,9 @@ async fn search(
         sort_method,
     };
 
-    let result = search::playlist::search_playlists(state.meilisearch.as_ref(), &search_query).await?;
+    let Some(result) = search::guarded(search::playlist::search_playlists(state.meilisearch.as_ref(), &search_query)).await else {
+        err!("search_unavailable", "Search is temporarily unavailable, please try again later")
+    };
     let hit_ids: Vec<i64> = result.hits.into_iter().map(|x| x.id).collect();
 
     let hits = playlist::list_playlist_metadata(state.redis_conn.clone(), &state.sql_pool, &hit_ids, false).await?
@@ -584,21 +940,9 @@ async fn search(
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageFavoritesReq {
-    pub page_index: i64,
-    pub page_size: i64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageFavoritesResp {
-    pub data: Vec<FavoritePlaylistItem>,
-    pub page_index: i64,
-    pub page_size: i64,
-    pub total: i64,
-}
+pub type PageFavoritesResp = Page<FavoritePlaylistItem>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FavoritePlaylistItem {
     pub metadata: PlaylistMetadata,
     pub order_index: i32,
@@ -608,15 +952,13 @@ pub struct FavoritePlaylistItem {
 async fn page_favorites(
     claims: Claims,
     state: State<AppState>,
-    req: Query<PageFavoritesReq>,
+    pagination: Pagination<50>,
 ) -> WebResult<PageFavoritesResp> {
-    let page_index = req.page_index.max(0);
-    let page_size = req.page_size.clamp(1, 50);
     let count = PlaylistDao::count_favorites(&state.sql_pool, claims.uid()).await?;
     if count == 0 {
-        ok!(PageFavoritesResp {data: vec![], page_index, page_size, total: 0})
+        ok!(pagination.into_page(vec![], 0))
     }
-    let items: HashMap<i64, _> = PlaylistDao::page_favorites(&state.sql_pool, claims.uid(), page_index, page_size).await?
+    let items: HashMap<i64, _> = PlaylistDao::page_favorites(&state.sql_pool, claims.uid(), pagination.page_index, pagination.page_size).await?
         .into_iter()
         .map(|x| (x.playlist_id, x))
         .collect();
@@ -628,10 +970,10 @@ async fn page_favorites(
         order_index: items.get(&v.id).unwrap().order_index,
         metadata: v,
     }).collect_vec();
-    ok!(PageFavoritesResp{data: result, page_index, page_size, total: count})
+    ok!(pagination.into_page(result, count))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct AddFavoriteReq {
     pub playlist_id: i64,
 }
@@ -665,7 +1007,7 @@ pub struct AddFavoriteResp {
     pub playlist_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RemoveFavoriteReq {
     pub playlist_id: i64,
 }
@@ -679,12 +1021,12 @@ async fn remove_favorite(
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CheckFavoriteReq {
     pub playlist_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CheckFavoriteResp {
     pub playlist_id: i64,
     pub is_favorite: bool,
@@ -702,4 +1044,61 @@ async fn check_favorite(
         is_favorite: result.is_some(),
         add_time: result.map(|x| x.add_time),
     })
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OfficialWeeklyReq {
+    /// The Monday of an archived week, the latest week if absent
+    #[serde(default)]
+    pub week_start: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OfficialWeeklyResp {
+    pub week_start: NaiveDate,
+    pub detail: DetailResp,
+}
+
+/// The official weekly selection, see [crate::service::weekly_selection]
+#[framed]
+async fn official_weekly(
+    state: State<AppState>,
+    req: Query<OfficialWeeklyReq>,
+) -> WebResult<OfficialWeeklyResp> {
+    let selection = match req.week_start {
+        Some(week_start) => WeeklySelectionDao::get_by_week(&state.sql_pool, week_start).await?,
+        None => WeeklySelectionDao::get_latest(&state.sql_pool).await?,
+    };
+    let Some(selection) = selection else {
+        err!("not_found", "Weekly selection not found")
+    };
+    match playlist::get_detail(&state, None, selection.playlist_id).await {
+        Ok(detail) => ok!(OfficialWeeklyResp { week_start: selection.week_start, detail }),
+        Err(GetDetailError::NotFound { .. } | GetDetailError::NotOwner { .. }) => err!("not_found", "Weekly selection not found"),
+        Err(e) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct OfficialWeeklyArchiveItem {
+    pub week_start: NaiveDate,
+    pub playlist: PlaylistMetadata,
+}
+
+pub type OfficialWeeklyArchiveResp = Page<OfficialWeeklyArchiveItem>;
+
+/// The weekly selections, the latest first
+#[framed]
+async fn official_weekly_archive(
+    state: State<AppState>,
+    pagination: Pagination<50>,
+) -> WebResult<OfficialWeeklyArchiveResp> {
+    let total = WeeklySelectionDao::count(&state.sql_pool).await?;
+    let selections = WeeklySelectionDao::page(&state.sql_pool, pagination.page_index, pagination.page_size).await?;
+    let playlist_ids = selections.iter().map(|x| x.playlist_id).collect_vec();
+    let mut playlists = playlist::list_playlist_metadata(state.redis_conn.clone(), &state.sql_pool, &playlist_ids, true).await?;
+    let items = selections.into_iter()
+        .filter_map(|x| playlists.remove(&x.playlist_id).map(|playlist| OfficialWeeklyArchiveItem { week_start: x.week_start, playlist }))
+        .collect_vec();
+    ok!(pagination.into_page(items, total))
+}
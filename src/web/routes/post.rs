@@ -1,21 +1,29 @@
-use crate::db::post::{Post, PostDao};
+use crate::db::mention;
+use crate::db::mention::{IMentionDao, MentionDao};
+use crate::db::post::{IPostDao, Post, PostDao};
 use crate::db::CrudDao;
-use crate::service::upload::{upload_cover_image_as_temp_id, ImageProcessOptions, ResizeType};
+use crate::service::image::{ImageCfg, ImageProcessOptions};
+use crate::service::upload::upload_cover_image_as_temp_id;
+use crate::service::mention::{MentionEntity, MentionError};
+use crate::service::tombstone::DELETED_PLACEHOLDER;
 use crate::service::{contributor, upload, user};
 use crate::web::jwt::Claims;
-use crate::web::result::WebResult;
+use crate::web::limits::LimitsCfg;
+use crate::web::pagination::{Page, Pagination};
+use crate::web::result::{CommonError, WebError, WebResult};
 use crate::web::routes::user::PublicUserProfile;
 use crate::web::state::AppState;
-use crate::{err, ok};
+use crate::{err, ok, service};
 use async_backtrace::framed;
-use axum::extract::{DefaultBodyLimit, Json, Multipart, Query, State};
+use axum::extract::{Json, Multipart, Query, State};
 use axum::routing::{get, post};
 use axum::Router;
 use chrono::Utc;
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-pub(crate) fn router() -> Router<AppState> {
+pub(crate) fn router(limits: &LimitsCfg) -> Router<AppState> {
     Router::new()
         // @since 260125
         .route("/page", get(page))
@@ -28,21 +36,12 @@ pub(crate) fn router() -> Router<AppState> {
         // @since 260125
         .route("/delete", post(delete))
         // @since 260125
-        .route("/upload_image", post(upload_image).layer(DefaultBodyLimit::max(10 * 1024 * 1024)))
+        .route("/upload_image", post(upload_image).layer(limits.image_body_limit()))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageReq {
-    pub page_index: i32,
-    pub page_size: i32,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageResp {
-    pub posts: Vec<PostItem>,
-}
+pub type PageResp = Page<PostItem>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PostItem {
     pub id: i64,
     pub author: PublicUserProfile,
@@ -52,17 +51,21 @@ pub struct PostItem {
     pub cover_url: Option<String>,
     pub create_time: chrono::DateTime<Utc>,
     pub update_time: chrono::DateTime<Utc>,
+    /// The users mentioned in the content, empty if the content is not returned
+    pub mentions: Vec<MentionEntity>,
+    /// A deleted post is returned by the detail as a placeholder, without the author and content.
+    /// @since 260505
+    #[serde(default)]
+    pub deleted: bool,
 }
 
 #[framed]
 pub async fn page(
     state: State<AppState>,
-    req: Query<PageReq>,
+    pagination: Pagination<50>,
 ) -> WebResult<PageResp> {
-    let page_index = req.page_index.max(0);
-    let page_size = req.page_size.clamp(1, 50);
-
-    let posts = PostDao::page(&state.sql_pool, page_index as i64, page_size as i64).await?;
+    let posts = PostDao::page(&state.sql_pool, pagination.page_index, pagination.page_size).await?;
+    let total = PostDao::count(&state.sql_pool).await?;
     let user_ids = posts.iter().map(|p| p.author_uid).collect_vec();
     let users = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &user_ids).await?;
 
@@ -79,6 +82,8 @@ pub async fn page(
                     gender: None,
                     is_banned: false,
                     connected_accounts: vec![],
+                    follower_count: 0,
+                    support_links: vec![],
                 }).clone(),
             title: p.title,
             content: "".to_string(),
@@ -86,13 +91,15 @@ pub async fn page(
             cover_url: p.cover_url,
             create_time: p.create_time,
             update_time: p.update_time,
+            mentions: vec![],
+            deleted: false,
         })
         .collect();
 
-    ok!(PageResp { posts: items })
+    ok!(pagination.into_page(items, total))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PostIdReq {
     pub post_id: i64,
 }
@@ -103,6 +110,20 @@ pub async fn detail(
     req: Query<PostIdReq>,
 ) -> WebResult<PostItem> {
     if let Some(p) = PostDao::get_by_id(&state.sql_pool, req.post_id).await? {
+        if p.delete_time.is_some() {
+            ok!(PostItem {
+                id: p.id,
+                author: PublicUserProfile::deleted(),
+                title: DELETED_PLACEHOLDER.to_string(),
+                content: String::new(),
+                content_type: p.content_type,
+                cover_url: None,
+                create_time: p.create_time,
+                update_time: p.update_time,
+                mentions: vec![],
+                deleted: true,
+            })
+        }
         let user = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &[p.author_uid]).await?
             .remove(&p.author_uid)
             .unwrap_or_else(|| PublicUserProfile {
@@ -113,7 +134,12 @@ pub async fn detail(
                 gender: None,
                 is_banned: false,
                 connected_accounts: vec![],
+                follower_count: 0,
+                support_links: vec![],
             });
+        let mentions = service::mention::list_by_sources(&state.sql_pool, mention::SOURCE_POST, &[p.id]).await?
+            .remove(&p.id)
+            .unwrap_or_default();
         let item = PostItem {
             id: p.id,
             title: p.title,
@@ -123,6 +149,8 @@ pub async fn detail(
             create_time: p.create_time,
             update_time: p.update_time,
             author: user,
+            mentions,
+            deleted: false,
         };
         ok!(item)
     } else {
@@ -130,7 +158,7 @@ pub async fn detail(
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateReq {
     pub title: String,
     pub content: String,
@@ -138,9 +166,19 @@ pub struct CreateReq {
     pub cover_file_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreateResp {
     pub id: i64,
+    pub mentions: Vec<MentionEntity>,
+}
+
+/// Parse the mentions in the content, see [service::mention::resolve]
+pub(crate) async fn resolve_mentions(state: &AppState, content: &str) -> Result<Vec<MentionEntity>, WebError<CommonError>> {
+    match service::mention::resolve(&state.sql_pool, content).await {
+        Ok(x) => Ok(x),
+        Err(e @ MentionError::TooManyMentions) => err!("too_many_mentions", "{}", e),
+        Err(MentionError::Sqlx(e)) => Err(e)?,
+    }
 }
 
 #[framed]
@@ -150,18 +188,19 @@ pub async fn create(
     req: Json<CreateReq>,
 ) -> WebResult<CreateResp> {
     // Only contributors can create posts
-    contributor::ensure_contributor(&state, claims.uid()).await?;
+    contributor::ensure_contributor(&state, &claims).await?;
 
     // Validate input
-    if req.title.trim().is_empty() || req.title.chars().count() > 200 {
+    if req.title.trim().is_empty() || req.title.chars().count() > state.limits.post_title_max_chars {
         err!("invalid_title", "Title is invalid")
     }
-    if req.content.chars().count() > 10_000 {
+    if req.content.chars().count() > state.limits.post_content_max_chars {
         err!("content_too_long", "Content is too long")
     }
     if req.content_type != "markdown" {
         err!("unsupported_content_type", "Unsupported content type")
     }
+    let mentions = resolve_mentions(&state, &req.content).await?;
 
     // Resolve cover url if provided
     let mut cover_url: Option<String> = None;
@@ -184,13 +223,18 @@ pub async fn create(
         cover_url,
         create_time: now,
         update_time: now,
+        delete_time: None,
     };
 
-    let id = PostDao::insert(&state.sql_pool, &entity).await?;
-    ok!(CreateResp { id })
+    let mut tx = state.sql_pool.begin().await?;
+    let id = PostDao::insert(&mut *tx, &entity).await?;
+    let mentioned = service::mention::save(&mut tx, mention::SOURCE_POST, id, claims.uid(), &mentions).await?;
+    tx.commit().await?;
+    service::mention::notify(&state, mention::SOURCE_POST, id, claims.uid(), &mentioned).await;
+    ok!(CreateResp { id, mentions })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EditReq {
     pub post_id: i64,
     pub title: Option<String>,
@@ -199,31 +243,44 @@ pub struct EditReq {
     pub cover_file_id: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EditResp {
+    /// The users mentioned in the content after the edit
+    pub mentions: Vec<MentionEntity>,
+}
+
 #[framed]
 pub async fn edit(
     claims: Claims,
     mut state: State<AppState>,
     req: Json<EditReq>,
-) -> WebResult<()> {
-    let mut post = if let Some(p) = PostDao::get_by_id(&state.sql_pool, req.post_id).await? { p } else { err!("not_found", "Post not found") };
+) -> WebResult<EditResp> {
+    let mut post = match PostDao::get_by_id(&state.sql_pool, req.post_id).await? {
+        Some(p) if p.delete_time.is_none() => p,
+        _ => err!("not_found", "Post not found"),
+    };
 
     // Only author or contributor can edit
     if post.author_uid != claims.uid() {
-        contributor::ensure_contributor(&state, claims.uid()).await?;
+        contributor::ensure_contributor(&state, &claims).await?;
     }
 
     if let Some(ref t) = req.title {
-        if t.trim().is_empty() || t.chars().count() > 200 {
+        if t.trim().is_empty() || t.chars().count() > state.limits.post_title_max_chars {
             err!("invalid_title", "Title is invalid")
         }
         post.title = t.clone();
     }
     if let Some(ref c) = req.content {
-        if c.chars().count() > 10_000 {
+        if c.chars().count() > state.limits.post_content_max_chars {
             err!("content_too_long", "Content is too long")
         }
         post.content = c.clone();
     }
+    let mentions = match req.content {
+        Some(ref c) => Some(resolve_mentions(&state, c).await?),
+        None => None,
+    };
 
     if let Some(ref temp_id) = req.cover_file_id {
         let cover_img = upload::retrieve_from_temp_id(&mut state.redis_conn, "post", &temp_id).await?;
@@ -235,9 +292,23 @@ pub async fn edit(
     }
 
     post.update_time = Utc::now();
-    PostDao::update_by_id(&state.sql_pool, &post).await?;
-
-    ok!(())
+    let mut tx = state.sql_pool.begin().await?;
+    PostDao::update_by_id(&mut *tx, &post).await?;
+    // Only the newly mentioned users are notified
+    let mentioned = match mentions {
+        Some(ref x) => service::mention::save(&mut tx, mention::SOURCE_POST, post.id, post.author_uid, x).await?,
+        None => vec![],
+    };
+    tx.commit().await?;
+    service::mention::notify(&state, mention::SOURCE_POST, post.id, claims.uid(), &mentioned).await;
+
+    let mentions = match mentions {
+        Some(x) => x,
+        None => service::mention::list_by_sources(&state.sql_pool, mention::SOURCE_POST, &[post.id]).await?
+            .remove(&post.id)
+            .unwrap_or_default(),
+    };
+    ok!(EditResp { mentions })
 }
 
 #[framed]
@@ -247,14 +318,15 @@ pub async fn delete(
     req: Json<PostIdReq>,
 ) -> WebResult<()> {
     // Only contributors can delete posts (keep existing behavior)
-    contributor::ensure_contributor(&state, claims.uid()).await?;
+    contributor::ensure_contributor(&state, &claims).await?;
 
-    // Ensure exists
-    if PostDao::get_by_id(&state.sql_pool, req.post_id).await?.is_none() {
+    // The post is left as a placeholder, see [crate::service::tombstone]
+    let mut tx = state.sql_pool.begin().await?;
+    if !PostDao::soft_delete_by_id(&mut *tx, req.post_id, Utc::now()).await? {
         err!("not_found", "Post not found")
     }
-
-    PostDao::delete_by_id(&state.sql_pool, req.post_id).await?;
+    MentionDao::delete_by_source(&mut *tx, mention::SOURCE_POST, req.post_id).await?;
+    tx.commit().await?;
 
     ok!(() )
 }
@@ -270,19 +342,10 @@ pub async fn upload_image(
     state: State<AppState>,
     multipart: Multipart,
 ) -> WebResult<UploadImageResp> {
-    contributor::ensure_contributor(&state, claims.uid()).await?;
+    contributor::ensure_contributor(&state, &claims).await?;
 
-    let file_id = upload_cover_image_as_temp_id(
-        "post",
-        state,
-        multipart,
-        10 * 1024 * 1024,
-        ImageProcessOptions {
-            max_width: 512,
-            max_height: 512,
-            resize_type: ResizeType::Fit,
-            quality: 85f32,
-        }).await?;
+    let options = ImageProcessOptions::post_image(&ImageCfg::load(&state.config)?, &state.limits);
+    let file_id = upload_cover_image_as_temp_id("post", claims.uid(), state, multipart, options).await?;
 
     ok!(UploadImageResp { file_id })
 }
\ No newline at end of file
@@ -1,53 +1,77 @@
 pub mod review;
 pub mod jmid;
+pub mod crew;
+pub mod template;
 
 use crate::audio::ParseError;
-use crate::config::Config;
 use crate::db::creator::{Creator, CreatorDao};
+use crate::db::error::DbError;
 use crate::db::song::{ISongDao, Song, SongDao, SongExternalLink, SongOriginInfo, SongProductionCrew};
 use crate::db::song_publishing_review::{ISongPublishingReviewDao, SongPublishingReview, SongPublishingReviewDao};
 use crate::db::song_publishing_review_comment::{ISongPublishingReviewCommentDao, SongPublishingReviewComment, SongPublishingReviewCommentDao};
 use crate::db::song_publishing_review_history::{self, ISongPublishingReviewHistoryDao, SongPublishingReviewHistory, SongPublishingReviewHistoryDao};
 use crate::db::song_tag::{ISongTagDao, SongTag, SongTagDao};
 use crate::db::user::UserDao;
+use crate::db::user_storage_object::{self, IUserStorageObjectDao, UserStorageObjectDao};
 use crate::db::{song_publishing_review, CrudDao};
-use crate::service::contributor::{check_contributor, ensure_contributor, CommunityCfg};
-use crate::service::mailer::EmailConfig;
+use crate::file_hosting::{self, CompletedPart, PresignUpload, PresignedRequest, UploadOptions, UploadResult};
+use crate::service::contributor::contributor_emails;
+use crate::service::jobs::{self, BackgroundJob};
+use crate::service::notification_templates::NotificationTemplate;
 use crate::service::song::{CreationTypeInfo, ExternalLink};
-use crate::service::upload::{scale_down_to_webp, ResizeType};
-use crate::service::{mailer, user};
+use crate::service::image::{ImageCfg, ImageProcessOptions};
+use crate::service::localization::{self, LocalizedTitleItem};
+use crate::service::{storage_quota, upload_cleanup};
+use crate::service::upload::{self, UploadMetrics};
+use crate::service::{email_delivery, tag_alias, user};
 use crate::util::{validate_platforms, IsBlank};
 use crate::web::jwt::Claims;
+use crate::web::limits::{LimitsCfg, AUDIO_CHUNK_BYTES};
+use crate::web::multipart::{self, FieldSpec};
+use crate::web::pagination::Page;
 use crate::web::result::{CommonError, WebError, WebResult};
 use crate::web::routes::publish::jmid::{check_jmid_available, parse_jmid};
 use crate::web::routes::song::TagItem;
 use crate::web::routes::user::PublicUserProfile;
 use crate::web::state::AppState;
-use crate::{audio, common, err, ok, search, service};
-use anyhow::Context;
+use crate::{audio, common, err, ok, service, util};
 use async_backtrace::framed;
-use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
+use axum::extract::{Multipart, Query, State};
 use axum::routing::{get, post};
 use axum::{Json, Router};
 use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use redis::AsyncTypedCommands;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
+use std::time::Duration;
 use tracing::{info, warn};
 
-pub(crate) fn router() -> Router<AppState> {
+pub(crate) fn router(limits: &LimitsCfg) -> Router<AppState> {
     Router::new()
-        .route("/upload_audio_file", post(upload_audio_file).layer(DefaultBodyLimit::max(20 * 1024 * 1024)) )
-        .route("/upload_cover_image", post(upload_cover_image).layer(DefaultBodyLimit::max(10 * 1024 * 1024)) )
+        .route("/upload_audio_file", post(upload_audio_file).layer(limits.audio_body_limit()))
+        // @since 260505
+        .route("/upload_audio_chunk/create", post(create_audio_chunk_upload))
+        // @since 260505
+        .route("/upload_audio_chunk", post(upload_audio_chunk).layer(limits.audio_chunk_body_limit()))
+        // @since 260505
+        .route("/upload_audio_chunk/status", get(audio_chunk_upload_status))
+        // @since 260505
+        .route("/upload_audio_chunk/complete", post(complete_audio_chunk_upload))
+        // @since 260505
+        .route("/upload_audio_chunk/abort", post(abort_audio_chunk_upload))
+        .route("/upload_cover_image", post(upload_cover_image).layer(limits.image_body_limit()))
         .route("/publish", post(publish))
         .route("/modify", post(modify))
         .route("/delete", post(delete))
         .route("/change_jmid", post(change_jmid))
         .route("/review/page", get(review::page))
         .route("/review/page_contributor", get(review::page_contributor))
+        // @since 260505
+        .route("/review/cursor_contributor", get(review::cursor_contributor))
         .route("/review/detail", get(review::detail))
         .route("/review/approve", post(review::review_approve))
         .route("/review/reject", post(review::review_reject))
@@ -61,29 +85,49 @@ pub(crate) fn router() -> Router<AppState> {
         .route("/review/comment/delete", post(review::review_comment_delete))
         // @since 260407 @experimental
         .route("/review/history/list", get(review::review_history_list))
+        // @since 260428
+        .route("/review/dashboard", get(review::dashboard))
+        // @since 260430
+        .route("/review/guideline/list", get(review::guideline_list))
         // .route("/review/suggestion/create", post(review_suggestion_create))
         // .route("/review/suggestion/delete", post(review_suggestion_delete))
         // .route("/review/suggestion/list", get(review_suggestion_list))
         // .route("/review/suggestion/accept", post(review_suggestion_accept))
         // .route("/review/suggestion/reject", post(review_suggestion_reject))
+        .route("/crew/invitations", get(crew::invitations))
+        .route("/crew/confirm", post(crew::confirm))
+        .route("/crew/decline", post(crew::decline))
         .route("/jmid/check_prefix", get(jmid::jmid_check_prefix))
         .route("/jmid/check", get(jmid::jmid_check))
         .route("/jmid/mine", get(jmid::jmid_mine))
         .route("/jmid/get_next", get(jmid::jmid_get_next))
+        // @since 260427
+        .route("/export_template", get(template::export_template))
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PublishReq {
     pub song_temp_id: String,
+    /// The cover embedded in the audio is used if empty, see [UploadAudioFileResp::cover_temp_id]
+    #[serde(default)]
     pub cover_temp_id: String,
+    // The fields below can be omitted since 260427 if they are given in the template
+    #[serde(default)]
     pub title: String,
+    #[serde(default)]
     pub subtitle: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
     pub lyrics: String,
+    #[serde(default)]
     pub tag_ids: Vec<i64>,
+    #[serde(default)]
     pub creation_info: CreationInfo,
+    #[serde(default)]
     pub production_crew: Vec<ProductionItem>,
+    #[serde(default)]
     pub external_links: Vec<ExternalLink>,
     /// @since 251105, should be required in new client.
     pub explicit: Option<bool>,
@@ -91,9 +135,17 @@ pub struct PublishReq {
     pub jmid: Option<String>,
     /// @since 251114
     pub comment: Option<String>,
+    /// The template exported by `/publish/export_template`, fills the empty fields above.
+    /// @since 260427
+    #[serde(default)]
+    pub template: Option<template::PublishTemplate>,
+    /// The titles in the other languages.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Vec<LocalizedTitleItem>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct CreationInfo {
     /// 0: original, 1: derivative work, 2: tertiary work
     pub creation_type: i32,
@@ -102,7 +154,7 @@ pub struct CreationInfo {
     pub derivative_info: Option<CreationTypeInfo>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ProductionItem {
     pub role: String,
     pub uid: Option<i64>,
@@ -110,7 +162,7 @@ pub struct ProductionItem {
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PublishResp {
     pub review_id: i64,
     pub song_display_id: String,
@@ -125,6 +177,10 @@ pub async fn publish(
     let _guard = state.red_lock.try_lock(&format!("lock:song_publish:{}", claims.uid())).await?
         .ok_or_else(|| common!("operation_in_progress", "Operation in progress"))?;
 
+    if let Some(template) = req.template.take() {
+        req.apply_template(template);
+    }
+
     let uid = claims.uid();
     let user = UserDao::get_by_id(&state.sql_pool, uid).await?.ok_or_else(|| common!("user_not_found", "User not found"))?;
 
@@ -133,7 +189,11 @@ pub async fn publish(
     let song_temp_data = song_temp_data.ok_or_else(|| common!("invalid_song_temp_id", "Invalid song temp id"))?;
     let song_temp_data: SongTempData = serde_json::from_str(&song_temp_data)?;
 
-    let cover_url: Option<String> = state.redis_conn.get(build_image_temp_key(&req.cover_temp_id)).await?;
+    let cover_url: Option<String> = if req.cover_temp_id.is_empty() {
+        song_temp_data.cover_url.clone()
+    } else {
+        state.redis_conn.get(build_image_temp_key(&req.cover_temp_id)).await?
+    };
     let cover_url = cover_url.ok_or_else(|| common!("invalid_cover_temp_id", "Invalid cover temp id"))?;
 
     // Check the jmid
@@ -190,7 +250,10 @@ pub async fn publish(
         create_time: now,
         update_time: now, // Do we really need three time data?
         gain: song_temp_data.gain,
+        loudness_lufs: song_temp_data.loudness_lufs,
+        true_peak: song_temp_data.true_peak,
         explicit: req.explicit,
+        version: 0,
     };
 
     let review_data = build_internal_review_data(
@@ -200,6 +263,7 @@ pub async fn publish(
         &req.creation_info,
         &req.production_crew,
         &req.external_links,
+        Some(req.localized_titles.clone()),
     ).await?;
 
     let submit_comment = req.comment.clone();
@@ -215,6 +279,7 @@ pub async fn publish(
         status: song_publishing_review::STATUS_PENDING,
         r#type: song_publishing_review::TYPE_CREATE,
         comment: req.comment.take(),
+        reviewer_uid: None,
     };
 
     let mut tx = state.sql_pool.begin().await?;
@@ -245,12 +310,11 @@ pub async fn publish(
         }).await?;
     }
     tx.commit().await?;
+    charge_embedded_cover(&state, uid, &song_temp_data, &cover_url).await;
 
-    // TODO: Refactor with message queue
-    tokio::spawn(async move {
-        send_notification_to_maintainer(&state.config, &req.title, &user.username).await?;
-        Ok::<(), anyhow::Error>(())
-    });
+    if let Err(e) = enqueue_notification_to_maintainer(&state, &req.title, &user.username).await {
+        warn!("Failed to notify the maintainer of review {}: {:?}", review_id, e);
+    }
 
     ok!(PublishResp {
         review_id: review_id,
@@ -258,15 +322,37 @@ pub async fn publish(
     })
 }
 
-async fn send_notification_to_maintainer(
-    config: &Config,
+async fn enqueue_notification_to_maintainer(
+    state: &AppState,
     title: &str,
     author: &str
 ) -> anyhow::Result<()> {
-    let email_cfg: EmailConfig = config.get_and_parse("email")?;
-    let community_cfg: CommunityCfg = config.get_and_parse("community")?;
-    if let Some(email) = community_cfg.contributors.first() {
-        mailer::send_notification(&email_cfg, email, "有新的稿件待审核", &format!("{} - {}", title, author)).await?;
+    if let Some(email) = contributor_emails(&state.sql_pool).await?.first() {
+        jobs::enqueue(state, BackgroundJob::SendEmail {
+            email_type: email_delivery::TYPE_REVIEW_UPDATE.to_string(),
+            to: email.clone(),
+            template: NotificationTemplate::ReviewPending {
+                song_title: title.to_string(),
+                author: author.to_string(),
+            },
+            lang: user::email_lang_by_address(&state.sql_pool, email).await?,
+        }).await;
+    }
+    Ok(())
+}
+
+pub const SONG_DESCRIPTION_MAX_CHARS: usize = 5000;
+
+/// Check the title, subtitle and description of a song, when publishing, modifying and editing by the contributors
+pub(crate) fn validate_song_metadata(title: &str, subtitle: &str, description: &str) -> Result<(), WebError<CommonError>> {
+    if title.is_blank() || title.chars().count() > localization::SONG_TITLE_MAX_CHARS {
+        err!("invalid_title", "Title must be 1 to {} characters", localization::SONG_TITLE_MAX_CHARS)
+    }
+    if subtitle.chars().count() > localization::SONG_SUBTITLE_MAX_CHARS {
+        err!("subtitle_too_long", "Subtitle must be at most {} characters", localization::SONG_SUBTITLE_MAX_CHARS)
+    }
+    if description.chars().count() > SONG_DESCRIPTION_MAX_CHARS {
+        err!("description_too_long", "Description must be at most {} characters", SONG_DESCRIPTION_MAX_CHARS)
     }
     Ok(())
 }
@@ -278,7 +364,13 @@ async fn build_internal_review_data(
     creation_info: &CreationInfo,
     production_crew_req: &[ProductionItem],
     external_links_req: &[ExternalLink],
+    localized_titles: Option<Vec<LocalizedTitleItem>>,
 ) -> Result<InternalSongPublishReviewData, WebError<CommonError>> {
+    validate_song_metadata(&song.title, &song.subtitle, &song.description)?;
+    if let Some(ref x) = localized_titles {
+        localization::validate(x, localization::SONG_TITLE_MAX_CHARS, localization::SONG_SUBTITLE_MAX_CHARS)?;
+    }
+
     // Validate creation_type
     if creation_info.creation_type == 1 && creation_info.origin_info.is_none() {
         err!("missing_origin_info", "Missing origin info for derivative song");
@@ -292,6 +384,7 @@ async fn build_internal_review_data(
     if tags.len() != tag_ids.len() {
         err!("tag_not_found", "Some tags not found");
     }
+    let tags = tag_alias::resolve_tags(sql_pool, tags).await?;
 
     // Origin infos
     let mut song_origin_infos = Vec::new();
@@ -348,6 +441,8 @@ async fn build_internal_review_data(
                 role: member.role.clone(),
                 uid: Some(user.id),
                 person_name: Some(user.username),
+                // Decided on approval
+                pending: false,
             });
         }
 
@@ -358,6 +453,7 @@ async fn build_internal_review_data(
                 role: member.role.clone(),
                 uid: None,
                 person_name: Some(name.clone()),
+                pending: false,
             });
         }
     }
@@ -392,6 +488,7 @@ async fn build_internal_review_data(
         song_production_crew: production_crew,
         song_tags: tags,
         song_external_links: links,
+        song_localized_titles: localized_titles,
     })
 }
 
@@ -448,12 +545,12 @@ async fn check_jmid_prefix_for_publication(
     Ok(create_new_jmid)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ModifyResp {
     pub review_id: i64
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ModifyReq {
     pub song_id: i64,
     pub song_temp_id: Option<String>,
@@ -468,6 +565,10 @@ pub struct ModifyReq {
     pub external_links: Vec<ExternalLink>,
     pub explicit: bool, // It's required because this is a new api
     pub comment: Option<String>,
+    /// Replace the titles in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
 }
 
 pub async fn modify(
@@ -489,22 +590,27 @@ pub async fn modify(
     let now = Utc::now();
 
     // Resolve audio (use temp if provided, otherwise original)
-    let (file_url, duration_secs, gain) = if let Some(ref temp_id) = req.song_temp_id {
-        let song_temp_data: Option<String> =
+    let mut song_temp_data = None;
+    let (file_url, duration_secs, gain, loudness_lufs, true_peak) = if let Some(ref temp_id) = req.song_temp_id {
+        let data: Option<String> =
             state.redis_conn.get(build_temp_key(temp_id)).await?;
-        let song_temp_data = song_temp_data
+        let data = data
             .ok_or_else(|| common!("invalid_song_temp_id", "Invalid song temp id"))?;
-        let song_temp_data: SongTempData = serde_json::from_str(&song_temp_data)?;
+        let data: &SongTempData = song_temp_data.insert(serde_json::from_str(&data)?);
         (
-            song_temp_data.file_url,
-            song_temp_data.duration_secs,
-            song_temp_data.gain,
+            data.file_url.clone(),
+            data.duration_secs,
+            data.gain,
+            data.loudness_lufs,
+            data.true_peak,
         )
     } else {
         (
             orig_song.file_url.clone(),
             orig_song.duration_seconds as u64,
             orig_song.gain,
+            orig_song.loudness_lufs,
+            orig_song.true_peak,
         )
     };
 
@@ -528,7 +634,7 @@ pub async fn modify(
         // artist will be overwritten from production crew in helper
         artist: orig_song.artist.clone(),
         file_url,
-        cover_art_url,
+        cover_art_url: cover_art_url.clone(),
         lyrics: req.lyrics.to_string(),
         duration_seconds: duration_secs as i32,
         uploader_uid: orig_song.uploader_uid,
@@ -541,8 +647,11 @@ pub async fn modify(
         create_time: orig_song.create_time,
         update_time: now,
         gain,
+        loudness_lufs,
+        true_peak,
         // If explicit is provided, override; otherwise keep original
         explicit: Some(req.explicit),
+        version: orig_song.version,
     };
 
     // Reuse the same validation and data-building logic as `publish`
@@ -553,6 +662,7 @@ pub async fn modify(
         &req.creation_info,
         &req.production_crew,
         &req.external_links,
+        req.localized_titles.clone(),
     )
         .await?;
 
@@ -571,6 +681,7 @@ pub async fn modify(
         // TYPE_MODIFY review
         r#type: song_publishing_review::TYPE_MODIFY,
         comment: req.comment.take(),
+        reviewer_uid: None,
     };
 
     let mut tx = state.sql_pool.begin().await?;
@@ -585,22 +696,48 @@ pub async fn modify(
         create_time: now,
     }).await?;
     tx.commit().await?;
+    if let Some(temp_data) = &song_temp_data {
+        charge_embedded_cover(&state, claims.uid(), temp_data, &cover_art_url).await;
+    }
 
     ok!(ModifyResp { review_id: review_id })
 
 }
 
-pub async fn delete() -> WebResult<()> {
-    err!("no_impl", "Not implemented yet");
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeleteReq {
+    pub song_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Delete the song of the uploader, along with its files in the storage
+pub async fn delete(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<DeleteReq>,
+) -> WebResult<()> {
+    let song = SongDao::get_by_id(&state.sql_pool, req.song_id)
+        .await?
+        .ok_or_else(|| common!("song_not_found", "Song was not found"))?;
+    if song.uploader_uid != claims.uid() {
+        err!("permission_denied", "You are not allowed to delete this song");
+    }
+    let deleted_files = service::song::delete_song(&state, &song).await?;
+    info!(uid = claims.uid(), song_id = song.id, "Deleted song {}, deleted {} files", song.display_id, deleted_files);
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UploadAudioFileResp {
     pub temp_id: String,
     pub duration_secs: u64,
     pub title: Option<String>,
     pub bitrate: Option<String>,
     pub artist: Option<String>,
+    /// The cover embedded in the audio, usable as the `cover_temp_id` of the publishing.
+    /// It's counted in the storage usage only if it's used.
+    /// @since 260505
+    #[serde(default)]
+    pub cover_temp_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -608,76 +745,637 @@ pub struct SongTempData {
     pub file_url: String,
     pub duration_secs: u64,
     pub gain: Option<f32>,
+    #[serde(default)]
+    pub loudness_lufs: Option<f32>,
+    #[serde(default)]
+    pub true_peak: Option<f32>,
+    /// The cover embedded in the audio
+    #[serde(default)]
+    pub cover_url: Option<String>,
+    /// The stored bytes of the embedded cover, charged once it's used, see [charge_embedded_cover]
+    #[serde(default)]
+    pub cover_size: Option<usize>,
 }
 
 #[framed]
 pub async fn upload_audio_file(
-    _claims: Claims,
+    claims: Claims,
     mut state: State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> WebResult<UploadAudioFileResp> {
     // 1. Receive streams
-    let data_field = multipart
-        .next_field()
-        .await?
-        .with_context(|| "No data field found")?;
-
-    let file_name = data_field.file_name().map(|x| x.to_string());
-
     // TODO[opt](song): decode and receive in parallel
-    let bytes = data_field.bytes().await?;
+    let mut upload_metrics = UploadMetrics::start("audio");
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[FieldSpec::audio("file", state.limits.audio_max_bytes)]).await)?;
+    let data_field = upload_metrics.check(fields.take_required("file"))?;
+    let file_name = data_field.file_name;
+    let bytes = data_field.bytes;
+    upload_metrics.received(bytes.len());
+    upload_metrics.check(storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), bytes.len()).await)?;
     let cursor = Cursor::new(bytes.clone());
 
     // 2. Validate metadata
     let metadata =
         match audio::parse_and_validate(Box::new(cursor), file_name.as_ref().map(|x| x.as_str())) {
             Ok(v) => v,
-            Err(err) => match err {
-                ParseError::FormatUnsupported => {
-                    err!("format_unsupported", "Audio format not supported")
-                }
-                ParseError::TrackNotFound => err!("track_not_found", "Audio track not found"),
-                ParseError::MetadataNotFound(key) => err!(
-                    "metadata_not_found",
-                    "Metadata {key} not found in audio"
-                ),
-                ParseError::ParsingDurationError => err!("parsing_duration_error", "Failed to parse duration"),
-                ParseError::Parse(err) => {
-                    tracing::error!("Error parsing audio: {:?}", err);
-                    err!("parse_error", "Error parsing audio")
-                }
-                ParseError::CalculatingGainPeakError => err!("calculating_gain_peak_error", "Failed to calculate gain and peak"),
-            },
+            Err(err) => return Err(upload_metrics.reject(audio_parse_error(err))),
         };
 
     // 3. Upload to s3
     // Generate a random filename
     let file_name = format!("{}.{}", uuid::Uuid::new_v4(), metadata.format);
-    let result = state
-        .file_host
-        .upload(bytes, &format!("songs/{}", file_name))
-        .await?;
+    let content_type = audio::mime_type_of(&metadata.format).unwrap_or("application/octet-stream");
+    let result = upload::store(
+        state.file_host.as_ref(),
+        "audio",
+        bytes,
+        &format!("songs/{}", file_name),
+        &UploadOptions::audio(content_type),
+    ).await?;
+    storage_quota::track(&state.sql_pool, claims.uid(), user_storage_object::KIND_AUDIO, &result).await?;
+    let cover = store_embedded_cover(&mut state, metadata.cover).await;
 
     let temp_id = uuid::Uuid::new_v4().to_string();
     let data = serde_json::to_string(&SongTempData {
         file_url: result.public_url.to_string(),
         duration_secs: metadata.duration_secs,
         gain: Some(metadata.gain_db),
+        loudness_lufs: metadata.loudness.map(|x| x.integrated_lufs),
+        true_peak: metadata.loudness.map(|x| x.true_peak_dbtp),
+        cover_url: cover.as_ref().map(|(_, x)| x.public_url.clone()),
+        cover_size: cover.as_ref().map(|(_, x)| x.size),
     })?;
     let _: () = state
         .redis_conn
-        .set_ex(build_temp_key(&temp_id), data, 3600)
+        .set_ex(build_temp_key(&temp_id), data, upload_cleanup::TEMP_DATA_TTL_SECS)
         .await?;
+    upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
 
+    upload_metrics.succeed();
     ok!(UploadAudioFileResp {
         temp_id: temp_id,
         title: metadata.title,
         duration_secs: metadata.duration_secs,
         bitrate: None,
         artist: None,
+        cover_temp_id: cover.map(|(temp_id, _)| temp_id),
     })
 }
 
+/// Store the cover embedded in the audio as an uploaded cover, returns its temp ID and the stored object.
+/// The cover is optional, so failing to store it is only logged.
+///
+/// It's not charged to the uploader until it's used, see [charge_embedded_cover], and it's cleaned up as a temp
+/// upload otherwise.
+async fn store_embedded_cover(state: &mut AppState, cover: Option<audio::EmbeddedCover>) -> Option<(String, UploadResult)> {
+    let cover = cover?;
+    if cover.data.len() > state.limits.image_max_bytes {
+        info!("The embedded cover is too large: {} bytes", cover.data.len());
+        return None;
+    }
+    let result = async {
+        let options = ImageProcessOptions::song_cover(&ImageCfg::load(&state.config)?, &state.limits);
+        let result = service::image::process_and_upload(state.file_host.as_ref(), "cover", cover.data.into(), &options).await?;
+        let temp_id = uuid::Uuid::new_v4().to_string();
+        let _: () = state.redis_conn
+            .set_ex(build_image_temp_key(&temp_id), &result.public_url, upload_cleanup::TEMP_DATA_TTL_SECS)
+            .await?;
+        upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
+        anyhow::Ok((temp_id, result))
+    }.await;
+    match result {
+        Ok(x) => Some(x),
+        Err(e) => {
+            warn!("Failed to store the embedded cover ({}): {:?}", cover.media_type, e);
+            None
+        }
+    }
+}
+
+/// Charge the embedded cover of the audio to the uploader if it's chosen as the cover
+async fn charge_embedded_cover(state: &AppState, uid: i64, temp_data: &SongTempData, cover_url: &str) {
+    let Some(url) = temp_data.cover_url.as_ref().filter(|x| *x == cover_url) else {
+        return;
+    };
+    let Some(key) = state.file_host.key_of(url) else {
+        return;
+    };
+    let result = UploadResult { key, public_url: url.clone(), size: temp_data.cover_size.unwrap_or_default() };
+    if let Err(e) = storage_quota::track(&state.sql_pool, uid, user_storage_object::KIND_IMAGE, &result).await {
+        warn!(uid, "Failed to charge the embedded cover {}: {:?}", result.key, e);
+    }
+}
+
+fn audio_parse_error(err: ParseError) -> WebError<CommonError> {
+    match err {
+        ParseError::FormatUnsupported => {
+            common!("format_unsupported", "Audio format not supported")
+        }
+        ParseError::TrackNotFound => common!("track_not_found", "Audio track not found"),
+        ParseError::MetadataNotFound(key) => common!(
+            "metadata_not_found",
+            "Metadata {key} not found in audio"
+        ),
+        ParseError::ParsingDurationError => common!("parsing_duration_error", "Failed to parse duration"),
+        ParseError::Parse(err) => {
+            tracing::error!("Error parsing audio: {:?}", err);
+            common!("parse_error", "Error parsing audio")
+        }
+        ParseError::CalculatingGainPeakError => common!("calculating_gain_peak_error", "Failed to calculate gain and peak"),
+    }
+}
+
+/// How long the presigned request and the pending upload are valid
+const DIRECT_UPLOAD_TTL_SECS: u64 = 3600;
+/// The bytes read to check the format before downloading the whole file
+const DIRECT_UPLOAD_PROBE_BYTES: u64 = 256 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PresignAudioUploadReq {
+    /// `mp3`, `aac` or `flac`
+    pub format: String,
+    pub size: u64,
+    /// The hex of the SHA-256 of the file
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PresignAudioUploadResp {
+    /// Confirm with `/song/upload/confirm` after uploading
+    pub upload_id: String,
+    /// Send the file as the body of the request, with all the headers
+    pub request: PresignedRequest,
+    pub expire_time: DateTime<Utc>,
+}
+
+/// A direct upload waiting to be confirmed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirectUpload {
+    uid: i64,
+    key: String,
+    format: String,
+    size: u64,
+    sha256: String,
+}
+
+fn build_direct_upload_key(upload_id: &str) -> String {
+    format!("song_upload:direct:{}", upload_id)
+}
+
+/// Let the client upload the audio file to the storage directly, instead of through `/song/upload_audio_file`.
+///
+/// The storage only accepts the file of the exact size and SHA-256, which is checked again by the confirmation.
+#[framed]
+pub async fn presign_audio_upload(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Json<PresignAudioUploadReq>,
+) -> WebResult<PresignAudioUploadResp> {
+    let Some(content_type) = audio::mime_type_of(&req.format) else {
+        err!("format_unsupported", "Audio format not supported")
+    };
+    if req.size == 0 || req.size > state.limits.audio_direct_max_bytes as u64 {
+        err!("field_too_large", "Field file must be less than {} bytes", state.limits.audio_direct_max_bytes)
+    }
+    if req.sha256.len() != 64 || !req.sha256.bytes().all(|x| x.is_ascii_hexdigit()) {
+        err!("invalid_sha256", "Invalid SHA-256")
+    }
+    storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), req.size as usize).await?;
+
+    let upload = DirectUpload {
+        uid: claims.uid(),
+        key: format!("songs/{}.{}", uuid::Uuid::new_v4(), req.format),
+        format: req.format.clone(),
+        size: req.size,
+        sha256: req.sha256.to_ascii_lowercase(),
+    };
+    let presign = PresignUpload {
+        size: upload.size,
+        sha256: upload.sha256.clone(),
+        expires_in: Duration::from_secs(DIRECT_UPLOAD_TTL_SECS),
+    };
+    let Some(request) = state.file_host.presign_upload(&upload.key, &UploadOptions::audio(content_type), &presign).await? else {
+        err!("direct_upload_unsupported", "Direct upload is not supported, use /song/upload_audio_file instead")
+    };
+
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let _: () = state.redis_conn
+        .set_ex(build_direct_upload_key(&upload_id), serde_json::to_string(&upload)?, DIRECT_UPLOAD_TTL_SECS)
+        .await?;
+    // Charged before it's uploaded so the concurrent uploads can't exceed the quota, and released along with the
+    // object if it's never confirmed
+    storage_quota::track(&state.sql_pool, upload.uid, user_storage_object::KIND_AUDIO, &UploadResult {
+        key: upload.key.clone(),
+        public_url: state.file_host.public_url(&upload.key),
+        size: upload.size as usize,
+    }).await?;
+    upload_cleanup::track_temp_for(&mut state.redis_conn, &upload.key, DIRECT_UPLOAD_TTL_SECS).await;
+    ok!(PresignAudioUploadResp {
+        upload_id,
+        request,
+        expire_time: Utc::now() + Duration::from_secs(DIRECT_UPLOAD_TTL_SECS),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConfirmAudioUploadReq {
+    pub upload_id: String,
+}
+
+/// Verify the directly uploaded file like `/song/upload_audio_file` does, and issue the `temp_id` for publishing.
+///
+/// The size and checksum are checked first, then the format is probed from the beginning of the file, and the
+/// whole file is only downloaded from the storage to calculate the duration and gain after that.
+#[framed]
+pub async fn confirm_audio_upload(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Json<ConfirmAudioUploadReq>,
+) -> WebResult<UploadAudioFileResp> {
+    let pending_key = build_direct_upload_key(&req.upload_id);
+    let upload: Option<String> = state.redis_conn.get(&pending_key).await?;
+    let Some(upload) = upload.and_then(|x| serde_json::from_str::<DirectUpload>(&x).ok())
+        .filter(|x| x.uid == claims.uid()) else {
+        err!("upload_not_found", "The upload is not found or expired")
+    };
+
+    let mut upload_metrics = UploadMetrics::start("audio_direct");
+    let Some(head) = state.file_host.head(&upload.key).await? else {
+        err!("upload_not_found", "The file is not uploaded yet")
+    };
+    if head.size != upload.size || head.sha256.as_ref().is_some_and(|x| *x != upload.sha256) {
+        return Err(upload_metrics.reject(common!("upload_mismatch", "The uploaded file doesn't match the declared size or SHA-256")));
+    }
+    upload_metrics.received(head.size as usize);
+
+    // The checksum is not always stored by the storage, the downloaded file is hashed then
+    let unverified_sha256 = head.sha256.is_none().then_some(upload.sha256.as_str());
+    let resp = accept_stored_audio(&mut state, claims.uid(), upload.key, &upload.format, head.size, unverified_sha256, &mut upload_metrics).await?;
+    state.redis_conn.del(&pending_key).await?;
+
+    upload_metrics.succeed();
+    ok!(resp)
+}
+
+/// Verify the audio file in the storage like `/publish/upload_audio_file` does, and issue the `temp_id` for publishing.
+///
+/// The format is probed from the beginning of the file first, and the whole file is only downloaded from the storage
+/// to calculate the duration and gain after that, to a temp file in ranges instead of into memory.
+///
+/// `sha256` is verified against the downloaded file if set, for the storages not verifying the checksums.
+async fn accept_stored_audio(
+    state: &mut AppState,
+    uid: i64,
+    key: String,
+    format: &str,
+    size: u64,
+    sha256: Option<&str>,
+    upload_metrics: &mut UploadMetrics,
+) -> Result<UploadAudioFileResp, WebError<CommonError>> {
+    let probe = state.file_host.download_range(&key, 0..DIRECT_UPLOAD_PROBE_BYTES).await?.unwrap_or_default();
+    let file_name = format!("file.{}", format);
+    match audio::probe_format(Box::new(Cursor::new(probe)), Some(&file_name)) {
+        Ok(Some(x)) if x != format => return Err(upload_metrics.reject(common!("format_mismatch", "The file is not {}", format))),
+        Ok(_) => {}
+        Err(e) => return Err(upload_metrics.reject(audio_parse_error(e))),
+    }
+
+    let temp_file = TempFile(std::env::temp_dir().join(format!("hachimi-upload-{}.{}", uuid::Uuid::new_v4(), format)));
+    if !file_hosting::download_to_file(state.file_host.as_ref(), &key, size, &temp_file.0).await? {
+        err!("upload_not_found", "The file is not uploaded yet")
+    }
+    if let Some(expected) = sha256 {
+        let path = temp_file.0.clone();
+        let actual = tokio::task::spawn_blocking(move || util::sha256_file(&path)).await??;
+        if actual != expected {
+            return Err(upload_metrics.reject(common!("upload_mismatch", "The uploaded file doesn't match the declared size or SHA-256")));
+        }
+    }
+    // The parsing is CPU-bound, and the guard moves along so the file is removed even if the request is dropped
+    let parsed = tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&temp_file.0)?;
+        anyhow::Ok(audio::parse_and_validate(Box::new(file), Some(&file_name)))
+    }).await??;
+    let metadata = match parsed {
+        Ok(v) => v,
+        Err(err) => return Err(upload_metrics.reject(audio_parse_error(err))),
+    };
+    if metadata.format != format {
+        return Err(upload_metrics.reject(common!("format_mismatch", "The file is not {}", format)));
+    }
+
+    let result = UploadResult {
+        public_url: state.file_host.public_url(&key),
+        key,
+        size: size as usize,
+    };
+    storage_quota::track(&state.sql_pool, uid, user_storage_object::KIND_AUDIO, &result).await?;
+    let cover = store_embedded_cover(state, metadata.cover).await;
+
+    let temp_id = uuid::Uuid::new_v4().to_string();
+    let data = serde_json::to_string(&SongTempData {
+        file_url: result.public_url,
+        duration_secs: metadata.duration_secs,
+        gain: Some(metadata.gain_db),
+        loudness_lufs: metadata.loudness.map(|x| x.integrated_lufs),
+        true_peak: metadata.loudness.map(|x| x.true_peak_dbtp),
+        cover_url: cover.as_ref().map(|(_, x)| x.public_url.clone()),
+        cover_size: cover.as_ref().map(|(_, x)| x.size),
+    })?;
+    let _: () = state.redis_conn.set_ex(build_temp_key(&temp_id), data, upload_cleanup::TEMP_DATA_TTL_SECS).await?;
+    upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
+
+    Ok(UploadAudioFileResp {
+        temp_id,
+        title: metadata.title,
+        duration_secs: metadata.duration_secs,
+        bitrate: None,
+        artist: None,
+        cover_temp_id: cover.map(|(temp_id, _)| temp_id),
+    })
+}
+
+/// Removes the temp file when dropped
+struct TempFile(std::path::PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) && e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove {}: {:?}", self.0.display(), e);
+        }
+    }
+}
+
+/// How long a chunked upload can be resumed since the last chunk
+const CHUNKED_UPLOAD_TTL_SECS: u64 = 24 * 3600;
+/// Held while completing a chunked upload, longer than downloading and verifying the largest file
+const CHUNKED_UPLOAD_COMPLETE_LOCK_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateChunkedUploadReq {
+    /// `mp3`, `aac` or `flac`
+    pub format: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateChunkedUploadResp {
+    pub upload_id: String,
+    /// The size of every chunk but the last, which is the rest
+    pub chunk_size: u64,
+    pub chunk_count: u32,
+    pub expire_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChunkedUploadReq {
+    pub upload_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadAudioChunkReq {
+    pub upload_id: String,
+    /// From 0
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ChunkedUploadStatusResp {
+    pub chunk_size: u64,
+    pub chunk_count: u32,
+    /// The indexes of the uploaded chunks in ascending order, the others are to be uploaded when resuming
+    pub uploaded: Vec<u32>,
+}
+
+/// A chunked upload in progress, the uploaded parts are in the hash of [build_chunked_upload_parts_key]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChunkedUpload {
+    uid: i64,
+    key: String,
+    format: String,
+    size: u64,
+    /// The multipart upload of the storage
+    storage_upload_id: String,
+    chunk_count: u32,
+    /// The chunks are assembled into the object, but it's not verified yet
+    #[serde(default)]
+    completed: bool,
+}
+
+impl ChunkedUpload {
+    fn chunk_len(&self, index: u32) -> u64 {
+        let chunk_size = AUDIO_CHUNK_BYTES as u64;
+        if index + 1 == self.chunk_count {
+            self.size - index as u64 * chunk_size
+        } else {
+            chunk_size
+        }
+    }
+}
+
+fn build_chunked_upload_key(upload_id: &str) -> String {
+    format!("song_upload:chunked:{}", upload_id)
+}
+
+/// The part numbers to the ETags of the uploaded chunks
+fn build_chunked_upload_parts_key(upload_id: &str) -> String {
+    format!("song_upload:chunked:{}:parts", upload_id)
+}
+
+async fn get_chunked_upload(state: &mut AppState, uid: i64, upload_id: &str) -> Result<ChunkedUpload, WebError<CommonError>> {
+    let upload: Option<String> = state.redis_conn.get(build_chunked_upload_key(upload_id)).await?;
+    match upload.and_then(|x| serde_json::from_str::<ChunkedUpload>(&x).ok()).filter(|x| x.uid == uid) {
+        Some(x) => Ok(x),
+        None => err!("upload_not_found", "The upload is not found or expired"),
+    }
+}
+
+/// Start uploading an audio file larger than `/publish/upload_audio_file` accepts, in chunks of `chunk_size`.
+///
+/// The chunks are uploaded with `/publish/upload_audio_chunk` in any order, and the upload can be resumed within
+/// a day since the last chunk, with the missing chunks listed by `/publish/upload_audio_chunk/status`.
+#[framed]
+pub async fn create_audio_chunk_upload(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Json<CreateChunkedUploadReq>,
+) -> WebResult<CreateChunkedUploadResp> {
+    let Some(content_type) = audio::mime_type_of(&req.format) else {
+        err!("format_unsupported", "Audio format not supported")
+    };
+    if req.size == 0 || req.size > state.limits.audio_chunked_max_bytes as u64 {
+        err!("field_too_large", "Field file must be less than {} bytes", state.limits.audio_chunked_max_bytes)
+    }
+    storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), req.size as usize).await?;
+
+    let key = format!("songs/{}.{}", uuid::Uuid::new_v4(), req.format);
+    let storage_upload_id = state.file_host.create_multipart_upload(&key, &UploadOptions::audio(content_type)).await?;
+    let upload = ChunkedUpload {
+        uid: claims.uid(),
+        key,
+        format: req.format.clone(),
+        size: req.size,
+        storage_upload_id,
+        chunk_count: req.size.div_ceil(AUDIO_CHUNK_BYTES as u64) as u32,
+        completed: false,
+    };
+
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let _: () = state.redis_conn
+        .set_ex(build_chunked_upload_key(&upload_id), serde_json::to_string(&upload)?, CHUNKED_UPLOAD_TTL_SECS)
+        .await?;
+    // Reserved like the direct uploads, and the assembled file is deleted if it's never accepted
+    storage_quota::track(&state.sql_pool, upload.uid, user_storage_object::KIND_AUDIO, &UploadResult {
+        key: upload.key.clone(),
+        public_url: state.file_host.public_url(&upload.key),
+        size: upload.size as usize,
+    }).await?;
+    upload_cleanup::track_temp_multipart(&mut state.redis_conn, &upload.key, &upload.storage_upload_id, CHUNKED_UPLOAD_TTL_SECS).await;
+    upload_cleanup::track_temp_for(&mut state.redis_conn, &upload.key, CHUNKED_UPLOAD_TTL_SECS).await;
+    ok!(CreateChunkedUploadResp {
+        upload_id,
+        chunk_size: AUDIO_CHUNK_BYTES as u64,
+        chunk_count: upload.chunk_count,
+        expire_time: Utc::now() + Duration::from_secs(CHUNKED_UPLOAD_TTL_SECS),
+    })
+}
+
+/// Multipart fields: `json` of [UploadAudioChunkReq] and the `file` of the chunk. Uploading a chunk again replaces it.
+#[framed]
+pub async fn upload_audio_chunk(
+    claims: Claims,
+    mut state: State<AppState>,
+    multipart: Multipart,
+) -> WebResult<()> {
+    let mut upload_metrics = UploadMetrics::start("audio_chunk");
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[
+        FieldSpec::json("json", 16 * 1024),
+        FieldSpec::audio("file", AUDIO_CHUNK_BYTES),
+    ]).await)?;
+    let req: UploadAudioChunkReq = upload_metrics.check(fields.take_json("json"))?;
+    let upload = get_chunked_upload(&mut state, claims.uid(), &req.upload_id).await?;
+    if upload.completed {
+        err!("upload_completed", "The chunks are already assembled")
+    }
+    if req.index >= upload.chunk_count {
+        err!("invalid_chunk", "The chunk index must be less than {}", upload.chunk_count)
+    }
+
+    let bytes = upload_metrics.check(fields.take_required("file"))?.bytes;
+    upload_metrics.received(bytes.len());
+    let expected_len = upload.chunk_len(req.index);
+    if bytes.len() as u64 != expected_len {
+        return Err(upload_metrics.reject(common!("invalid_chunk", "The chunk {} must be {} bytes", req.index, expected_len)));
+    }
+    let part = state.file_host.upload_part(&upload.key, &upload.storage_upload_id, req.index as i32 + 1, bytes).await?;
+
+    let parts_key = build_chunked_upload_parts_key(&req.upload_id);
+    let _: () = redis::pipe()
+        .hset(&parts_key, part.part_number, part.etag).ignore()
+        .expire(&parts_key, CHUNKED_UPLOAD_TTL_SECS as i64).ignore()
+        .expire(build_chunked_upload_key(&req.upload_id), CHUNKED_UPLOAD_TTL_SECS as i64).ignore()
+        .query_async(&mut state.redis_conn)
+        .await?;
+    upload_cleanup::track_temp_multipart(&mut state.redis_conn, &upload.key, &upload.storage_upload_id, CHUNKED_UPLOAD_TTL_SECS).await;
+    upload_cleanup::track_temp_for(&mut state.redis_conn, &upload.key, CHUNKED_UPLOAD_TTL_SECS).await;
+    upload_metrics.succeed();
+    ok!(())
+}
+
+#[framed]
+pub async fn audio_chunk_upload_status(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Query<ChunkedUploadReq>,
+) -> WebResult<ChunkedUploadStatusResp> {
+    let upload = get_chunked_upload(&mut state, claims.uid(), &req.upload_id).await?;
+    let part_numbers = state.redis_conn.hkeys(build_chunked_upload_parts_key(&req.upload_id)).await?;
+    let uploaded = part_numbers.iter()
+        .filter_map(|x| x.parse::<u32>().ok())
+        .map(|x| x - 1)
+        .sorted()
+        .collect();
+    ok!(ChunkedUploadStatusResp {
+        chunk_size: AUDIO_CHUNK_BYTES as u64,
+        chunk_count: upload.chunk_count,
+        uploaded,
+    })
+}
+
+/// Assemble the chunks and verify the file like `/song/upload/confirm` does, then issue the `temp_id` for publishing.
+///
+/// The upload is kept until the file is verified, so a failed completion can be retried without assembling the
+/// chunks again. The invalid file is deleted by the cleanup of the temp uploads once the upload expires.
+#[framed]
+pub async fn complete_audio_chunk_upload(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Json<ChunkedUploadReq>,
+) -> WebResult<UploadAudioFileResp> {
+    let lock_key = format!("lock:chunked_upload:{}", req.upload_id);
+    let _guard = state.red_lock.try_lock_with_ttl(&lock_key, CHUNKED_UPLOAD_COMPLETE_LOCK_TTL).await?
+        .ok_or_else(|| common!("operation_in_progress", "Operation in progress"))?;
+    let mut upload = get_chunked_upload(&mut state, claims.uid(), &req.upload_id).await?;
+    let upload_key = build_chunked_upload_key(&req.upload_id);
+    let parts_key = build_chunked_upload_parts_key(&req.upload_id);
+    let mut upload_metrics = UploadMetrics::start("audio_chunked");
+    if !upload.completed {
+        let parts: HashMap<String, String> = state.redis_conn.hgetall(&parts_key).await?;
+        let parts = parts.into_iter()
+            .filter_map(|(part_number, etag)| Some(CompletedPart { part_number: part_number.parse().ok()?, etag }))
+            .collect_vec();
+        if parts.len() != upload.chunk_count as usize {
+            err!("upload_incomplete", "{} of {} chunks are uploaded", parts.len(), upload.chunk_count)
+        }
+
+        state.file_host.complete_multipart_upload(&upload.key, &upload.storage_upload_id, &parts).await?;
+        upload_cleanup::untrack_temp_multipart(&mut state.redis_conn, &upload.key, &upload.storage_upload_id).await;
+        // The storage doesn't keep the chunks after the completion, so it must not be completed again on a retry
+        upload.completed = true;
+        let _: () = state.redis_conn.set_ex(&upload_key, serde_json::to_string(&upload)?, CHUNKED_UPLOAD_TTL_SECS).await?;
+    }
+
+    let Some(head) = state.file_host.head(&upload.key).await? else {
+        err!("upload_not_found", "The file is not uploaded yet")
+    };
+    if head.size != upload.size {
+        return Err(upload_metrics.reject(common!("upload_mismatch", "The uploaded file doesn't match the declared size")));
+    }
+    upload_metrics.received(head.size as usize);
+
+    let resp = accept_stored_audio(&mut state, claims.uid(), upload.key, &upload.format, head.size, None, &mut upload_metrics).await?;
+    let _: () = redis::pipe()
+        .del(&upload_key).ignore()
+        .del(&parts_key).ignore()
+        .query_async(&mut state.redis_conn)
+        .await?;
+    upload_metrics.succeed();
+    ok!(resp)
+}
+
+/// Discard the uploaded chunks
+#[framed]
+pub async fn abort_audio_chunk_upload(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Json<ChunkedUploadReq>,
+) -> WebResult<()> {
+    let upload = get_chunked_upload(&mut state, claims.uid(), &req.upload_id).await?;
+    if upload.completed {
+        state.file_host.delete(&upload.key).await?;
+    } else {
+        state.file_host.abort_multipart_upload(&upload.key, &upload.storage_upload_id).await?;
+        upload_cleanup::untrack_temp_multipart(&mut state.redis_conn, &upload.key, &upload.storage_upload_id).await;
+    }
+    UserStorageObjectDao::delete_by_key(&state.sql_pool, &upload.key).await?;
+    let _: () = redis::pipe()
+        .del(build_chunked_upload_key(&req.upload_id)).ignore()
+        .del(build_chunked_upload_parts_key(&req.upload_id)).ignore()
+        .query_async(&mut state.redis_conn)
+        .await?;
+    ok!(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadImageResp {
     pub temp_id: String,
@@ -687,7 +1385,7 @@ pub struct UploadImageResp {
 pub async fn upload_cover_image(
     claims: Claims,
     mut state: State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> WebResult<UploadImageResp> {
     let _ = if let Some(x) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? {
         x
@@ -695,29 +1393,24 @@ pub async fn upload_cover_image(
         err!("not_found", "User not found")
     };
 
-    let data_field = multipart
-        .next_field()
-        .await?
-        .with_context(|| "No data field found")?;
-    let bytes = data_field.bytes().await?;
-
-    // Validate image
-    if bytes.len() > 8 * 1024 * 1024 {
-        err!("image_too_large", "Image size must be less than 8MB");
-    }
-
-    let webp = scale_down_to_webp(1024, 1024, bytes.clone(), ResizeType::Fit, 90f32)
-        .map_err(|_| common!("invalid_image", "The image is not supported"))?;
-
-    // Upload image
-    let sha1 = openssl::sha::sha1(&webp);
-    let filename = format!("images/cover/{}.webp", hex::encode(sha1));
-    let result = state.file_host.upload(webp.into(), &filename).await?;
+    let mut upload_metrics = UploadMetrics::start("cover");
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[FieldSpec::image("file", state.limits.image_max_bytes)]).await)?;
+    let bytes = upload_metrics.check(fields.take_required("file"))?.bytes;
+    upload_metrics.received(bytes.len());
+    upload_metrics.check(storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), bytes.len()).await)?;
+
+    // Process and upload image
+    let options = ImageProcessOptions::song_cover(&ImageCfg::load(&state.config)?, &state.limits);
+    let result = upload_metrics.check(service::image::process_and_upload(state.file_host.as_ref(), "cover", bytes, &options).await
+        .map_err(|e| e.into_web_error()))?;
+    storage_quota::track(&state.sql_pool, claims.uid(), user_storage_object::KIND_IMAGE, &result).await?;
     let temp_id = uuid::Uuid::new_v4().to_string();
+    upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
     let _: () = state.redis_conn
-        .set_ex(build_image_temp_key(&temp_id), result.public_url, 3600)
+        .set_ex(build_image_temp_key(&temp_id), result.public_url, upload_cleanup::TEMP_DATA_TTL_SECS)
         .await?;
 
+    upload_metrics.succeed();
     ok!(UploadImageResp { temp_id })
 }
 
@@ -730,21 +1423,9 @@ fn build_image_temp_key(temp_id: &str) -> String {
     key
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageReq {
-    pub page_index: i64,
-    pub page_size: i64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageResp {
-    pub data: Vec<SongPublishReviewBrief>,
-    pub page_index: i64,
-    pub page_size: i64,
-    pub total: i64,
-}
+pub type PageResp = Page<SongPublishReviewBrief>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SongPublishReviewBrief {
     pub review_id: i64,
     pub display_id: String,
@@ -788,9 +1469,12 @@ pub struct InternalSongPublishReviewData {
     pub song_production_crew: Vec<SongProductionCrew>,
     pub song_tags: Vec<SongTag>,
     pub song_external_links: Vec<SongExternalLink>,
+    /// `None` if the localized titles are unchanged by a modification
+    #[serde(default)]
+    pub song_localized_titles: Option<Vec<LocalizedTitleItem>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ChangeJmidReq {
     pub song_id: i64,
     pub old_jmid: String,
@@ -842,8 +1526,12 @@ async fn change_jmid(
     let old_jmid = song.display_id;
     song.display_id = req.new_jmid.clone();
 
-    // Update song jmid
-    SongDao::update_by_id(&mut *tx, &song).await?;
+    // Update song jmid, the song might be modified since we read it
+    match SongDao::update_by_id_checked(&mut *tx, &song).await {
+        Ok(_) => {}
+        Err(DbError::VersionConflict) => err!("version_conflict", "The song has been modified concurrently, please retry"),
+        Err(e) => Err(e)?,
+    }
 
     // 6. Update the corresponding review's jmid field
     SongPublishingReviewDao::swap_jmid(&mut *tx, &old_jmid, &req.new_jmid).await?;
@@ -851,7 +1539,7 @@ async fn change_jmid(
     tx.commit().await?;
 
     // 7. Update search index
-    search::song::add_or_replace_document(&state.meilisearch, &state.sql_pool, &[song.id]).await?;
+    jobs::enqueue(&state, BackgroundJob::IndexSongs { song_ids: vec![song.id] }).await;
     service::recommend_v2::notify_update(song.id, state.redis_conn.clone()).await?;
     ok!(())
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::CrudDao;
+use crate::service::jobs::{self, BackgroundJob};
+use crate::service::song;
+use crate::web::jwt::Claims;
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::{common, err, ok};
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::Json;
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrewInvitation {
+    pub song_id: i64,
+    pub song_display_id: String,
+    pub song_title: String,
+    pub uploader_uid: i64,
+    /// The roles the user is credited as
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrewInvitationsResp {
+    pub invitations: Vec<CrewInvitation>,
+}
+
+/// The credits waiting for the confirmation of the current user, latest first
+#[framed]
+pub async fn invitations(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<CrewInvitationsResp> {
+    let pending = SongDao::list_pending_production_crew_by_uid(&state.sql_pool, claims.uid()).await?;
+    let song_ids = pending.iter().map(|x| x.song_id).unique().collect_vec();
+    let songs: HashMap<i64, _> = SongDao::list_by_ids(&state.sql_pool, &song_ids).await?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+    let mut roles = pending.into_iter().into_group_map_by(|x| x.song_id);
+    let invitations = song_ids.iter()
+        .filter_map(|id| {
+            let song = songs.get(id)?;
+            Some(CrewInvitation {
+                song_id: song.id,
+                song_display_id: song.display_id.clone(),
+                song_title: song.title.clone(),
+                uploader_uid: song.uploader_uid,
+                roles: roles.remove(id).unwrap_or_default().into_iter().map(|x| x.role).collect(),
+            })
+        })
+        .collect();
+    ok!(CrewInvitationsResp { invitations })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CrewRespondReq {
+    pub song_id: i64,
+}
+
+/// Confirm all the pending credits of the current user on the song
+#[framed]
+pub async fn confirm(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<CrewRespondReq>,
+) -> WebResult<()> {
+    let song = SongDao::get_by_id(&state.sql_pool, req.song_id).await?
+        .ok_or_else(|| common!("not_found", "Song not found"))?;
+    if SongDao::confirm_production_crew(&state.sql_pool, song.id, claims.uid()).await? == 0 {
+        err!("not_found", "No pending credit on the song")
+    }
+    song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(&song)).await?;
+    ok!(())
+}
+
+/// Remove the current user from the credits of the song, the credited names are kept.
+///
+/// Confirmed credits can be declined as well.
+#[framed]
+pub async fn decline(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<CrewRespondReq>,
+) -> WebResult<()> {
+    let song = SongDao::get_by_id(&state.sql_pool, req.song_id).await?
+        .ok_or_else(|| common!("not_found", "Song not found"))?;
+    if song.uploader_uid == claims.uid() {
+        err!("uploader_credit", "The uploader can't decline the credits of their own song")
+    }
+    if SongDao::unlink_production_crew(&state.sql_pool, song.id, claims.uid()).await? == 0 {
+        err!("not_found", "No credit on the song")
+    }
+    song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(&song)).await?;
+    jobs::enqueue(&state, BackgroundJob::IndexSongs { song_ids: vec![song.id] }).await;
+    ok!(())
+}
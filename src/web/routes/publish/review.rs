@@ -1,29 +1,41 @@
-use crate::config::Config;
 use crate::db::creator::CreatorDao;
-use crate::db::song::{Song, SongDao, SongProductionCrew};
-use crate::db::song_publishing_review::{ISongPublishingReviewDao, SongPublishingReview, SongPublishingReviewDao};
+use crate::db::mention;
+use crate::db::mention::{IMentionDao, MentionDao};
+use crate::db::error::DbError;
+use crate::db::review_guideline_snippet::{IReviewGuidelineSnippetDao, ReviewGuidelineSnippetDao};
+use crate::db::song::{ISongDao, Song, SongDao, SongProductionCrew};
+use crate::db::song_publishing_review::{DailyCount, ISongPublishingReviewDao, SongPublishingReview, SongPublishingReviewDao, StatusCount};
 use crate::db::song_publishing_review_comment::{ISongPublishingReviewCommentDao, SongPublishingReviewComment, SongPublishingReviewCommentDao};
 use crate::db::song_publishing_review_history::{ISongPublishingReviewHistoryDao, SongPublishingReviewHistory, SongPublishingReviewHistoryDao};
 use crate::db::user::UserDao;
-use crate::db::{song_publishing_review, song_publishing_review_history, CrudDao};
-use crate::service::contributor::{check_contributor, ensure_contributor, CommunityCfg};
-use crate::service::mailer::EmailConfig;
+use crate::db::{localized_title, song_publishing_review, song_publishing_review_history, CrudDao};
+use crate::service::contributor::{check_contributor, contributor_emails, ensure_contributor, is_contributor};
+use crate::service::jobs::{self, BackgroundJob};
+use crate::service::mention::MentionEntity;
+use crate::service::notification_templates::NotificationTemplate;
 use crate::service::song::{CreationTypeInfo, ExternalLink};
-use crate::service::{mailer, user};
-use crate::util::IsBlank;
+use crate::service::localization::{self, LocalizedTitleItem};
+use crate::service::near_duplicate::{self, SimilarSong};
+use crate::service::{email_delivery, events, notification, user};
+use crate::util::{redis_health, IsBlank};
 use crate::web::jwt::Claims;
+use crate::web::pagination::{CursorPage, CursorPagination, CursorQuery, Page, PageQuery, Pagination};
 use crate::web::result::{CommonError, WebError, WebResult};
-use crate::web::routes::publish::{build_image_temp_key, build_internal_review_data, build_temp_key, parse_jmid, CreationInfo, InternalSongPublishReviewData, PageReq, PageResp, ProductionItem, SongPublishReviewBrief, SongTempData};
+use crate::web::routes;
+use crate::web::routes::publish::{build_image_temp_key, build_internal_review_data, build_temp_key, parse_jmid, CreationInfo, InternalSongPublishReviewData, PageResp, ProductionItem, SongPublishReviewBrief, SongTempData};
 use crate::web::routes::song::TagItem;
 use crate::web::routes::user::PublicUserProfile;
 use crate::web::state::AppState;
-use crate::{common, err, ok, search, service};
+use crate::{common, err, ok, service};
 use anyhow::Context;
 use axum::extract::{Query, State};
 use axum::Json;
 use chrono::{DateTime, Utc};
+use itertools::Itertools;
 use redis::AsyncTypedCommands;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sqlx::PgPool;
 use std::collections::{HashMap, HashSet};
 use tracing::warn;
@@ -31,97 +43,231 @@ use tracing::warn;
 pub async fn page(
     claims: Claims,
     state: State<AppState>,
-    req: Query<PageReq>,
+    pagination: Pagination<50>,
 ) -> WebResult<PageResp> {
-    if req.page_size > 50 {
-        err!("page_size_exceeded", "Page size too large");
-    }
-    let result = SongPublishingReviewDao::page_by_user(&state.sql_pool, claims.uid(), req.page_index, req.page_size).await?;
-    let brief: Vec<_> = result.into_iter().map(|x| {
-        match SongPublishReviewBrief::try_from(x.clone()) {
-            Ok(v) => {
-                v
-            }
-            Err(err) => {
-                warn!("Error during decoding song publish review data: {:?}", err);
-                SongPublishReviewBrief {
-                    review_id: x.id,
-                    display_id: "Unknown".to_string(),
-                    title: "Unknown".to_string(),
-                    subtitle: "Unknown".to_string(),
-                    artist: "Unknown".to_string(),
-                    cover_url: "Unknown".to_string(),
-                    submit_time: x.submit_time,
-                    review_time: x.review_time,
-                    review_comment: x.review_comment,
-                    status: x.status,
-                    r#type: x.r#type,
-                }
-            }
-        }
-    }).collect();
+    let result = SongPublishingReviewDao::page_by_user(&state.sql_pool, claims.uid(), pagination.page_index, pagination.page_size).await?;
+    let brief: Vec<_> = result.into_iter().map(brief_or_unknown).collect();
     let count = SongPublishingReviewDao::count_by_user(&state.sql_pool, claims.uid()).await?;
-    let resp = PageResp {
-        data: brief,
-        page_index: req.page_index,
-        page_size: req.page_size,
-        total: count,
-    };
-    ok!(resp)
+    ok!(pagination.into_page(brief, count))
 }
 
 pub async fn page_contributor(
     claims: Claims,
     state: State<AppState>,
-    req: Query<PageReq>,
+    pagination: Pagination<50>,
 ) -> WebResult<PageResp> {
-    if req.page_size > 50 {
-        err!("page_size_exceeded", "Page size too large");
-    }
-    ensure_contributor(&state, claims.uid()).await?;
+    ensure_contributor(&state, &claims).await?;
 
-    let result = SongPublishingReviewDao::page(&state.sql_pool, req.page_index, req.page_size).await?;
-    let brief: Vec<_> = result.into_iter().map(|x| {
-        match SongPublishReviewBrief::try_from(x.clone()) {
-            Ok(v) => {
-                v
-            }
-            Err(err) => {
-                warn!("Error during decoding song publish review data: {:?}", err);
-                SongPublishReviewBrief {
-                    review_id: x.id,
-                    display_id: "Unknown".to_string(),
-                    title: "Unknown".to_string(),
-                    subtitle: "Unknown".to_string(),
-                    artist: "Unknown".to_string(),
-                    cover_url: "Unknown".to_string(),
-                    submit_time: x.submit_time,
-                    review_time: x.review_time,
-                    review_comment: x.review_comment,
-                    status: x.status,
-                    r#type: x.r#type,
-                }
+    let result = SongPublishingReviewDao::page(&state.sql_pool, pagination.page_index, pagination.page_size).await?;
+    let brief: Vec<_> = result.into_iter().map(brief_or_unknown).collect();
+    let count = SongPublishingReviewDao::count(&state.sql_pool).await?;
+    ok!(pagination.into_page(brief, count))
+}
+
+fn brief_or_unknown(x: SongPublishingReview) -> SongPublishReviewBrief {
+    match SongPublishReviewBrief::try_from(x.clone()) {
+        Ok(v) => {
+            v
+        }
+        Err(err) => {
+            warn!("Error during decoding song publish review data: {:?}", err);
+            SongPublishReviewBrief {
+                review_id: x.id,
+                display_id: "Unknown".to_string(),
+                title: "Unknown".to_string(),
+                subtitle: "Unknown".to_string(),
+                artist: "Unknown".to_string(),
+                cover_url: "Unknown".to_string(),
+                submit_time: x.submit_time,
+                review_time: x.review_time,
+                review_comment: x.review_comment,
+                status: x.status,
+                r#type: x.r#type,
             }
         }
-    }).collect();
-    let count = SongPublishingReviewDao::count(&state.sql_pool).await?;
-    let resp = PageResp {
-        data: brief,
-        page_index: req.page_index,
-        page_size: req.page_size,
-        total: count,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CursorContributorReq {
+    /// 0: pending, 1: approved, 2: rejected, all if absent
+    pub status: Option<i32>,
+    /// The prefix of the display id to search
+    pub display_id: Option<String>,
+}
+
+/// The whole query of `/publish/review/cursor_contributor`, [CursorContributorReq] along with the cursor
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CursorContributorQuery {
+    #[serde(flatten)]
+    pub req: CursorContributorReq,
+    #[serde(flatten)]
+    pub cursor: CursorQuery,
+}
+
+/// The cursor is `{submit_time in unix micros}_{review_id}` of the last item
+pub type CursorContributorResp = CursorPage<SongPublishReviewBrief>;
+
+/// The reviews of all users for the contributors, newest first
+pub async fn cursor_contributor(
+    claims: Claims,
+    state: State<AppState>,
+    pagination: CursorPagination<50>,
+    req: Query<CursorContributorReq>,
+) -> WebResult<CursorContributorResp> {
+    ensure_contributor(&state, &claims).await?;
+
+    if let Some(status) = req.status
+        && ![song_publishing_review::STATUS_PENDING, song_publishing_review::STATUS_APPROVED, song_publishing_review::STATUS_REJECTED].contains(&status) {
+        err!("invalid_review_status", "Invalid review status")
+    }
+    let before = match pagination.cursor {
+        Some(ref x) => match parse_review_cursor(x) {
+            Some(x) => Some(x),
+            None => err!("invalid_cursor", "Invalid cursor")
+        },
+        None => None
+    };
+    let display_id = req.display_id.as_deref().map(str::trim).filter(|x| !x.is_empty());
+
+    let result = SongPublishingReviewDao::cursor_filtered(&state.sql_pool, req.status, display_id, before, pagination.page_size).await?;
+    let next_cursor = if result.len() as i64 == pagination.page_size {
+        result.last().map(|x| format!("{}_{}", x.submit_time.timestamp_micros(), x.id))
+    } else {
+        None
+    };
+    let items = result.into_iter().map(brief_or_unknown).collect();
+    ok!(CursorContributorResp { items, next_cursor })
+}
+
+fn parse_review_cursor(cursor: &str) -> Option<(DateTime<Utc>, i64)> {
+    let (micros, id) = cursor.split_once('_')?;
+    Some((DateTime::from_timestamp_micros(micros.parse().ok()?)?, id.parse().ok()?))
+}
+
+/// The days of the submission trend in the dashboard
+const DASHBOARD_TREND_DAYS: i64 = 14;
+const DASHBOARD_CACHE_TTL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DashboardResp {
+    pub pending_count: i64,
+    pub approved_count: i64,
+    pub rejected_count: i64,
+    /// `None` if nothing is pending
+    pub oldest_pending_submit_time: Option<DateTime<Utc>>,
+    pub oldest_pending_age_secs: Option<i64>,
+    /// The submissions of the last 14 days in UTC, oldest first, including the days without submissions
+    pub daily_submissions: Vec<DailyCount>,
+    /// The reviews approved or rejected by the caller
+    pub my_stats: MyReviewStats,
+    /// It's cached for a minute
+    pub create_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MyReviewStats {
+    pub approved_count: i64,
+    pub rejected_count: i64,
+    pub last_14_days_count: i64,
+}
+
+/// The summary for the home screen of the reviewers.
+///
+/// Permission: Only available for contributors.
+pub async fn dashboard(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<DashboardResp> {
+    ensure_contributor(&state, &claims).await?;
+
+    let mut redis = state.redis_conn.clone();
+    let cache_key = format!("review:dashboard:{}", claims.uid());
+    let cached = redis_health::cached(redis.get(&cache_key)).await.flatten()
+        .and_then(|x| serde_json::from_str::<DashboardResp>(&x).ok());
+    if let Some(x) = cached {
+        ok!(x)
+    }
+
+    let now = Utc::now();
+    let status_counts = SongPublishingReviewDao::count_group_by_status(&state.sql_pool).await?;
+    let count_of = |counts: &[StatusCount], status: i32| counts.iter().find(|x| x.status == status).map_or(0, |x| x.count);
+    let oldest_pending = SongPublishingReviewDao::get_oldest_pending_submit_time(&state.sql_pool).await?;
+
+    let today = now.date_naive();
+    let first_day = today - chrono::Days::new(DASHBOARD_TREND_DAYS as u64 - 1);
+    let since = first_day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let submissions: HashMap<_, _> = SongPublishingReviewDao::count_submissions_by_day(&state.sql_pool, since).await?
+        .into_iter()
+        .map(|x| (x.date, x.count))
+        .collect();
+    let daily_submissions = first_day.iter_days().take(DASHBOARD_TREND_DAYS as usize)
+        .map(|date| DailyCount { date, count: submissions.get(&date).copied().unwrap_or(0) })
+        .collect();
+
+    let reviewed = SongPublishingReviewDao::count_reviewed_by(&state.sql_pool, claims.uid(), None).await?;
+    let reviewed_recently = SongPublishingReviewDao::count_reviewed_by(&state.sql_pool, claims.uid(), Some(since)).await?;
+
+    let resp = DashboardResp {
+        pending_count: count_of(&status_counts, song_publishing_review::STATUS_PENDING),
+        approved_count: count_of(&status_counts, song_publishing_review::STATUS_APPROVED),
+        rejected_count: count_of(&status_counts, song_publishing_review::STATUS_REJECTED),
+        oldest_pending_submit_time: oldest_pending,
+        oldest_pending_age_secs: oldest_pending.map(|x| (now - x).num_seconds()),
+        daily_submissions,
+        my_stats: MyReviewStats {
+            approved_count: count_of(&reviewed, song_publishing_review::STATUS_APPROVED),
+            rejected_count: count_of(&reviewed, song_publishing_review::STATUS_REJECTED),
+            last_14_days_count: reviewed_recently.iter().map(|x| x.count).sum(),
+        },
+        create_time: now,
     };
+    redis_health::cached(redis.set_ex(&cache_key, serde_json::to_string(&resp)?, DASHBOARD_CACHE_TTL_SECS)).await;
     ok!(resp)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GuidelineListResp {
+    pub snippets: Vec<GuidelineSnippetItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct GuidelineSnippetItem {
+    pub slug: String,
+    pub title: String,
+    /// Inserted into the comments as is
+    pub content: String,
+    pub version: i32,
+}
+
+/// The review guideline snippets for the reviewers to insert into the comments, without the archived ones.
+///
+/// Permission: Only available for contributors.
+pub async fn guideline_list(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<GuidelineListResp> {
+    ensure_contributor(&state, &claims).await?;
+    let snippets = ReviewGuidelineSnippetDao::list(&state.sql_pool, false).await?
+        .into_iter()
+        .map(|x| GuidelineSnippetItem {
+            slug: x.slug,
+            title: x.title,
+            content: x.content,
+            version: x.version,
+        })
+        .collect();
+    ok!(GuidelineListResp { snippets })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailReq {
     pub review_id: i64,
 }
 
 pub type DetailResp = PublishSongPublishReviewData;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PublishSongPublishReviewData {
     pub review_id: i64,
     pub submit_time: DateTime<Utc>,
@@ -145,6 +291,15 @@ pub struct PublishSongPublishReviewData {
     pub origin_infos: Vec<CreationTypeInfo>,
     pub external_link: Vec<ExternalLink>,
     pub explicit: Option<bool>,
+    /// The published songs with nearly the same title and lyrics, only filled in the detail of the pending
+    /// reviews for the contributors
+    /// @since 260430
+    #[serde(default)]
+    pub similar_songs: Vec<SimilarSong>,
+    /// The titles in the other languages, `None` if they are unchanged by a modification
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
 }
 
 struct PublishSongPublishReviewMeta {
@@ -215,6 +370,8 @@ async fn compose_publish_song_publish_review_data(
             url: x.url,
         }).collect(),
         explicit: data.song_info.explicit,
+        similar_songs: vec![],
+        localized_titles: data.song_localized_titles,
     })
 }
 
@@ -233,7 +390,16 @@ pub async fn detail(
         let data = serde_json::from_value::<InternalSongPublishReviewData>(review.data)
             .with_context(|| format!("Error during decoding song publish review({}) data", review.id))?;
 
-        let result = compose_publish_song_publish_review_data(
+        let is_contributor = is_contributor(&state, &claims).await?;
+        let similar_songs = if review.status == 0 && is_contributor {
+            // The song itself is excluded when modifying
+            let exclude_song_id = (review.r#type == song_publishing_review::TYPE_MODIFY).then_some(data.song_info.id);
+            near_duplicate::find_similar(&state.sql_pool, &data.song_info.title, &data.song_info.lyrics, exclude_song_id).await?
+        } else {
+            vec![]
+        };
+
+        let mut result = compose_publish_song_publish_review_data(
             &state.sql_pool,
             PublishSongPublishReviewMeta {
                 review_id: review.id,
@@ -245,13 +411,14 @@ pub async fn detail(
             },
             data,
         ).await?;
+        result.similar_songs = similar_songs;
         ok!(result)
     } else {
         err!("not_found", "Review not found")
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewModifyReq {
     pub review_id: i64,
     pub song_temp_id: Option<String>,
@@ -266,6 +433,10 @@ pub struct ReviewModifyReq {
     pub external_links: Vec<ExternalLink>,
     pub explicit: bool,
     pub comment: Option<String>,
+    /// Replace the titles in the other languages, unchanged if absent.
+    /// @since 260430
+    #[serde(default)]
+    pub localized_titles: Option<Vec<LocalizedTitleItem>>,
 }
 
 pub async fn review_modify(
@@ -276,7 +447,7 @@ pub async fn review_modify(
     let _guard = state.red_lock.try_lock(&format!("lock:song_publish:{}", claims.uid())).await?
         .ok_or_else(|| common!("operation_in_progress", "Operation in progress"))?;
 
-    if let Some(ref x) = req.comment && x.chars().count() > 1000 {
+    if let Some(ref x) = req.comment && x.chars().count() > state.limits.comment_max_chars {
         err!("comment_too_long", "Comment is too long")
     }
 
@@ -292,7 +463,7 @@ pub async fn review_modify(
     let current_data: InternalSongPublishReviewData = serde_json::from_value(review.data.clone())
         .with_context(|| format!("Error during decoding song publish review({}) data", review.id))?;
 
-    let (file_url, duration_secs, gain) = if let Some(ref temp_id) = req.song_temp_id {
+    let (file_url, duration_secs, gain, loudness_lufs, true_peak) = if let Some(ref temp_id) = req.song_temp_id {
         let song_temp_data: Option<String> = state.redis_conn.get(build_temp_key(temp_id)).await?;
         let song_temp_data = song_temp_data
             .ok_or_else(|| common!("invalid_song_temp_id", "Invalid song temp id"))?;
@@ -301,12 +472,16 @@ pub async fn review_modify(
             song_temp_data.file_url,
             song_temp_data.duration_secs,
             song_temp_data.gain,
+            song_temp_data.loudness_lufs,
+            song_temp_data.true_peak,
         )
     } else {
         (
             current_data.song_info.file_url.clone(),
             current_data.song_info.duration_seconds as u64,
             current_data.song_info.gain,
+            current_data.song_info.loudness_lufs,
+            current_data.song_info.true_peak,
         )
     };
 
@@ -339,7 +514,10 @@ pub async fn review_modify(
         create_time: current_data.song_info.create_time,
         update_time: now,
         gain,
+        loudness_lufs,
+        true_peak,
         explicit: Some(req.explicit),
+        version: current_data.song_info.version,
     };
 
     let review_data = build_internal_review_data(
@@ -349,6 +527,7 @@ pub async fn review_modify(
         &req.creation_info,
         &req.production_crew,
         &req.external_links,
+        req.localized_titles.clone().or(current_data.song_localized_titles),
     ).await?;
 
     let mut tx = state.sql_pool.begin().await?;
@@ -380,34 +559,34 @@ pub async fn review_modify(
     }).await?;
     tx.commit().await?;
 
-    let config = state.config.clone();
-    let sql_pool = state.sql_pool.clone();
-    let actor_uid = claims.uid();
-    let review_id = review.id;
-    let note = req.comment.clone();
-    tokio::spawn(async move {
-        send_review_modified_notification(&config, &sql_pool, review_id, actor_uid, note.as_deref()).await?;
-        Ok::<(), anyhow::Error>(())
-    });
+    if let Err(e) = enqueue_review_modified_notification(&state, review.id, claims.uid(), req.comment.as_deref()).await {
+        warn!("Failed to notify the modification of review {}: {:?}", review.id, e);
+    }
 
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewCommentCreateReq {
     pub review_id: i64,
     pub content: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewCommentCreateResp {
+    pub id: i64,
+    pub mentions: Vec<MentionEntity>,
+}
+
 pub async fn review_comment_create(
     claims: Claims,
     state: State<AppState>,
     req: Json<ReviewCommentCreateReq>,
-) -> WebResult<()> {
+) -> WebResult<ReviewCommentCreateResp> {
     if req.content.is_blank() {
         err!("comment_required", "Comment is required")
     }
-    if req.content.chars().count() > 1000 {
+    if req.content.chars().count() > state.limits.comment_max_chars {
         err!("comment_too_long", "Comment is too long")
     }
 
@@ -418,6 +597,7 @@ pub async fn review_comment_create(
     }
 
     ensure_review_comment_create_permission(&state, &review, claims.uid()).await?;
+    let mentions = routes::post::resolve_mentions(&state, &req.content).await?;
 
     let now = Utc::now();
     let comment = SongPublishingReviewComment {
@@ -428,47 +608,51 @@ pub async fn review_comment_create(
         create_time: now,
         update_time: now,
     };
-    SongPublishingReviewCommentDao::insert(&state.sql_pool, &comment).await?;
+    let mut tx = state.sql_pool.begin().await?;
+    let comment_id = SongPublishingReviewCommentDao::insert(&mut *tx, &comment).await?;
+    let mentioned = service::mention::save(&mut tx, mention::SOURCE_REVIEW_COMMENT, comment_id, claims.uid(), &mentions).await?;
+    tx.commit().await?;
+    // The users who can't see the review are not notified
+    let mentioned = filter_review_viewers(&state, &review, mentioned).await.unwrap_or_else(|e| {
+        warn!("Failed to check the viewers of review {}: {:?}", review.id, e);
+        vec![]
+    });
+    service::mention::notify(&state, mention::SOURCE_REVIEW_COMMENT, comment_id, claims.uid(), &mentioned).await;
 
     let actor = UserDao::get_by_id(&state.sql_pool, claims.uid()).await?
         .ok_or_else(|| common!("user_not_found", "User not found"))?;
-    let config = state.config.clone();
-    let sql_pool = state.sql_pool.clone();
-    let review_id = review.id;
-    let actor_uid = actor.id;
-    let actor_name = actor.username;
-    let content = req.content.clone();
-    tokio::spawn(async move {
-        send_review_comment_notification(&config, &sql_pool, review_id, actor_uid, &actor_name, &content).await?;
-        Ok::<(), anyhow::Error>(())
-    });
+    if let Err(e) = enqueue_review_comment_notification(&state, review.id, actor.id, &actor.username, &req.content).await {
+        warn!("Failed to notify the comment of review {}: {:?}", review.id, e);
+    }
 
-    ok!(())
+    ok!(ReviewCommentCreateResp { id: comment_id, mentions })
 }
 
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewCommentListReq {
     pub review_id: i64,
-    pub page_index: i64,
-    pub page_size: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ReviewCommentListResp {
-    pub data: Vec<ReviewCommentItem>,
-    pub page_index: i64,
-    pub page_size: i64,
-    pub total: i64,
+/// The whole query of `/publish/review/comment/list`, [ReviewCommentListReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewCommentListQuery {
+    #[serde(flatten)]
+    pub req: ReviewCommentListReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub type ReviewCommentListResp = Page<ReviewCommentItem>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewCommentItem {
     pub id: i64,
     pub review_id: i64,
     pub author: Option<PublicUserProfile>,
     pub content: String,
+    pub mentions: Vec<MentionEntity>,
     pub create_time: DateTime<Utc>,
     pub update_time: DateTime<Utc>,
 }
@@ -476,12 +660,9 @@ pub struct ReviewCommentItem {
 pub async fn review_comment_list(
     claims: Claims,
     state: State<AppState>,
+    pagination: Pagination<50>,
     req: Query<ReviewCommentListReq>,
 ) -> WebResult<ReviewCommentListResp> {
-    if req.page_size > 50 {
-        err!("page_size_exceeded", "Page size too large")
-    }
-
     let review = SongPublishingReviewDao::get_by_id(&state.sql_pool, req.review_id).await?
         .ok_or_else(|| common!("not_found", "Review not found"))?;
     ensure_review_visible(&state, &review, claims.uid()).await?;
@@ -489,8 +670,8 @@ pub async fn review_comment_list(
     let comments = SongPublishingReviewCommentDao::page_by_review_id(
         &state.sql_pool,
         req.review_id,
-        req.page_index,
-        req.page_size,
+        pagination.page_index,
+        pagination.page_size,
     ).await?;
     let total = SongPublishingReviewCommentDao::count_by_review_id(&state.sql_pool, req.review_id).await?;
 
@@ -500,6 +681,8 @@ pub async fn review_comment_list(
         .into_iter()
         .collect::<Vec<_>>();
     let users = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &uids).await?;
+    let comment_ids = comments.iter().map(|x| x.id).collect::<Vec<_>>();
+    let mut mentions = service::mention::list_by_sources(&state.sql_pool, mention::SOURCE_REVIEW_COMMENT, &comment_ids).await?;
 
     for comment in comments {
         data.push(ReviewCommentItem {
@@ -507,21 +690,17 @@ pub async fn review_comment_list(
             review_id: comment.review_id,
             author: users.get(&comment.user_id).cloned(),
             content: comment.content,
+            mentions: mentions.remove(&comment.id).unwrap_or_default(),
             create_time: comment.create_time,
             update_time: comment.update_time,
         });
     }
 
-    ok!(ReviewCommentListResp {
-        data,
-        page_index: req.page_index,
-        page_size: req.page_size,
-        total,
-    })
+    ok!(pagination.into_page(data, total))
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewCommentDeleteReq {
     pub comment_id: i64,
 }
@@ -537,26 +716,30 @@ pub async fn review_comment_delete(
         .ok_or_else(|| common!("not_found", "Review not found"))?;
     ensure_review_visible(&state, &review, claims.uid()).await?;
     ensure_review_comment_delete_permission(&state, &comment, claims.uid()).await?;
-    SongPublishingReviewCommentDao::delete_by_id(&state.sql_pool, req.comment_id).await?;
+    let mut tx = state.sql_pool.begin().await?;
+    SongPublishingReviewCommentDao::delete_by_id(&mut *tx, req.comment_id).await?;
+    MentionDao::delete_by_source(&mut *tx, mention::SOURCE_REVIEW_COMMENT, req.comment_id).await?;
+    tx.commit().await?;
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewHistoryListReq {
     pub review_id: i64,
-    pub page_index: i64,
-    pub page_size: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ReviewHistoryListResp {
-    pub data: Vec<ReviewHistoryItem>,
-    pub page_index: i64,
-    pub page_size: i64,
-    pub total: i64,
+/// The whole query of `/publish/review/history/list`, [ReviewHistoryListReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewHistoryListQuery {
+    #[serde(flatten)]
+    pub req: ReviewHistoryListReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub type ReviewHistoryListResp = Page<ReviewHistoryItem>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReviewHistoryItem {
     pub id: i64,
     pub review_id: i64,
@@ -570,12 +753,9 @@ pub struct ReviewHistoryItem {
 pub async fn review_history_list(
     claims: Claims,
     state: State<AppState>,
+    pagination: Pagination<50>,
     req: Query<ReviewHistoryListReq>,
 ) -> WebResult<ReviewHistoryListResp> {
-    if req.page_size > 50 {
-        err!("page_size_exceeded", "Page size too large")
-    }
-
     let review = SongPublishingReviewDao::get_by_id(&state.sql_pool, req.review_id).await?
         .ok_or_else(|| common!("not_found", "Review not found"))?;
     ensure_review_visible(&state, &review, claims.uid()).await?;
@@ -583,8 +763,8 @@ pub async fn review_history_list(
     let histories = SongPublishingReviewHistoryDao::page_by_review_id(
         &state.sql_pool,
         req.review_id,
-        req.page_index,
-        req.page_size,
+        pagination.page_index,
+        pagination.page_size,
     ).await?;
     let total = SongPublishingReviewHistoryDao::count_by_review_id(&state.sql_pool, req.review_id).await?;
 
@@ -632,15 +812,99 @@ pub async fn review_history_list(
         });
     }
 
-    ok!(ReviewHistoryListResp {
-        data,
-        page_index: req.page_index,
-        page_size: req.page_size,
-        total,
-    })
+    ok!(pagination.into_page(data, total))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SongReviewHistoryReq {
+    pub display_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SongReviewHistoryResp {
+    /// Oldest first
+    pub rounds: Vec<SongReviewRound>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SongReviewRound {
+    pub review_id: i64,
+    /// 0: create, 1: modify
+    pub r#type: i32,
+    /// 0: pending, 1: approved, 2: rejected
+    pub status: i32,
+    /// The title submitted in this round, `None` if the data can't be decoded
+    pub title: Option<String>,
+    pub submitter: Option<PublicUserProfile>,
+    /// The comment of the submitter
+    pub comment: Option<String>,
+    pub submit_time: DateTime<Utc>,
+    pub reviewer: Option<PublicUserProfile>,
+    /// The reason of the approval or rejection
+    pub review_comment: Option<String>,
+    pub review_time: Option<DateTime<Utc>>,
+    /// Oldest first
+    pub comments: Vec<ReviewCommentItem>,
 }
 
-async fn ensure_review_visible(
+/// All review rounds of a song, so the creators can trace why the older versions were rejected.
+///
+/// Permission: The uploader of the song, the submitters of the reviews and the contributors.
+pub async fn song_review_history(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<SongReviewHistoryReq>,
+) -> WebResult<SongReviewHistoryResp> {
+    let mut reviews = SongPublishingReviewDao::list_by_jmid(&state.sql_pool, &req.display_id).await?;
+    if reviews.is_empty() {
+        err!("not_found", "Song not found")
+    }
+    reviews.sort_by_key(|x| (x.submit_time, x.id));
+
+    let uploader_uid = SongDao::get_by_display_id(&state.sql_pool, &req.display_id).await?.map(|x| x.uploader_uid);
+    let is_owner = uploader_uid == Some(claims.uid()) || reviews.iter().any(|x| x.user_id == claims.uid());
+    if !is_owner && !is_contributor(&state, &claims).await? {
+        err!("permission_denied", "You are not allowed to view the reviews of this song")
+    }
+
+    let review_ids = reviews.iter().map(|x| x.id).collect::<Vec<_>>();
+    let comments = SongPublishingReviewCommentDao::list_by_review_ids(&state.sql_pool, &review_ids).await?;
+    let uids = reviews.iter()
+        .flat_map(|x| [Some(x.user_id), x.reviewer_uid])
+        .flatten()
+        .chain(comments.iter().map(|x| x.user_id))
+        .unique()
+        .collect::<Vec<_>>();
+    let users = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &uids).await?;
+    let comment_ids = comments.iter().map(|x| x.id).collect::<Vec<_>>();
+    let mut mentions = service::mention::list_by_sources(&state.sql_pool, mention::SOURCE_REVIEW_COMMENT, &comment_ids).await?;
+    let mut comments = comments.into_iter().into_group_map_by(|x| x.review_id);
+
+    let rounds = reviews.into_iter().map(|x| SongReviewRound {
+        review_id: x.id,
+        r#type: x.r#type,
+        status: x.status,
+        title: serde_json::from_value::<InternalSongPublishReviewData>(x.data).ok().map(|x| x.song_info.title),
+        submitter: users.get(&x.user_id).cloned(),
+        comment: x.comment,
+        submit_time: x.submit_time,
+        reviewer: x.reviewer_uid.and_then(|uid| users.get(&uid).cloned()),
+        review_comment: x.review_comment,
+        review_time: x.review_time,
+        comments: comments.remove(&x.id).unwrap_or_default().into_iter().map(|comment| ReviewCommentItem {
+            id: comment.id,
+            review_id: comment.review_id,
+            author: users.get(&comment.user_id).cloned(),
+            content: comment.content,
+            mentions: mentions.remove(&comment.id).unwrap_or_default(),
+            create_time: comment.create_time,
+            update_time: comment.update_time,
+        }).collect(),
+    }).collect();
+    ok!(SongReviewHistoryResp { rounds })
+}
+
+pub(super) async fn ensure_review_visible(
     state: &AppState,
     review: &SongPublishingReview,
     uid: i64,
@@ -650,7 +914,6 @@ async fn ensure_review_visible(
     }
 
     let is_contributor = check_contributor(
-        &state.config,
         state.redis_conn.clone(),
         &state.red_lock,
         &state.sql_pool,
@@ -663,6 +926,26 @@ async fn ensure_review_visible(
     err!("permission_denied", "You are not allowed to view this review")
 }
 
+/// The users among `uids` who can view the review
+async fn filter_review_viewers(
+    state: &AppState,
+    review: &SongPublishingReview,
+    uids: Vec<i64>,
+) -> anyhow::Result<Vec<i64>> {
+    let mut result = Vec::with_capacity(uids.len());
+    for uid in uids {
+        if uid == review.user_id || check_contributor(
+            state.redis_conn.clone(),
+            &state.red_lock,
+            &state.sql_pool,
+            uid,
+        ).await? {
+            result.push(uid);
+        }
+    }
+    Ok(result)
+}
+
 async fn ensure_review_comment_create_permission(
     state: &AppState,
     review: &SongPublishingReview,
@@ -673,7 +956,6 @@ async fn ensure_review_comment_create_permission(
     }
 
     let is_contributor = check_contributor(
-        &state.config,
         state.redis_conn.clone(),
         &state.red_lock,
         &state.sql_pool,
@@ -695,7 +977,6 @@ async fn ensure_review_comment_delete_permission(
     }
 
     let is_contributor = check_contributor(
-        &state.config,
         state.redis_conn.clone(),
         &state.red_lock,
         &state.sql_pool,
@@ -707,74 +988,81 @@ async fn ensure_review_comment_delete_permission(
     Ok(())
 }
 
-async fn send_review_comment_notification(
-    config: &Config,
-    sql_pool: &PgPool,
+async fn enqueue_review_comment_notification(
+    state: &AppState,
     review_id: i64,
     actor_uid: i64,
     actor_name: &str,
     content: &str,
 ) -> anyhow::Result<()> {
-    let email_cfg: EmailConfig = config.get_and_parse("email")?;
-    let community_cfg: CommunityCfg = config.get_and_parse("community")?;
-    let review = SongPublishingReviewDao::get_by_id(sql_pool, review_id).await?
+    let review = SongPublishingReviewDao::get_by_id(&state.sql_pool, review_id).await?
         .with_context(|| format!("Review {} not found", review_id))?;
-    let actor = UserDao::get_by_id(sql_pool, actor_uid).await?
+    let actor = UserDao::get_by_id(&state.sql_pool, actor_uid).await?
         .with_context(|| format!("User {} not found", actor_uid))?;
-    let uploader = UserDao::get_by_id(sql_pool, review.user_id).await?
+    let uploader = UserDao::get_by_id(&state.sql_pool, review.user_id).await?
         .with_context(|| format!("User {} not found", review.user_id))?;
 
     let mut recipients = HashSet::new();
     recipients.insert(uploader.email.clone());
-    recipients.extend(community_cfg.contributors);
+    recipients.extend(contributor_emails(&state.sql_pool).await?);
     recipients.remove(&actor.email);
 
-    let subject = format!("稿件评论更新：{}", review.song_display_id);
-    let body = format!(
-        "{actor_name} 在稿件 {} 下发表了新评论：\n\n{}",
-        review.song_display_id,
-        content,
-    );
+    let template = NotificationTemplate::ReviewComment {
+        actor_name: actor_name.to_string(),
+        song_display_id: review.song_display_id.clone(),
+        content: content.to_string(),
+    };
 
+    let recipients = recipients.into_iter().collect_vec();
+    let mut langs = user::email_langs_by_addresses(&state.sql_pool, &recipients).await?;
     for email in recipients {
-        mailer::send_notification(&email_cfg, &email, &subject, &body).await?;
+        let lang = langs.remove(&email).unwrap_or_default();
+        jobs::enqueue(state, BackgroundJob::SendEmail {
+            email_type: email_delivery::TYPE_REVIEW_UPDATE.to_string(),
+            to: email,
+            template: template.clone(),
+            lang,
+        }).await;
     }
     Ok(())
 }
 
-async fn send_review_modified_notification(
-    config: &Config,
-    sql_pool: &PgPool,
+async fn enqueue_review_modified_notification(
+    state: &AppState,
     review_id: i64,
     actor_uid: i64,
     note: Option<&str>,
 ) -> anyhow::Result<()> {
-    let email_cfg: EmailConfig = config.get_and_parse("email")?;
-    let community_cfg: CommunityCfg = config.get_and_parse("community")?;
-    let review = SongPublishingReviewDao::get_by_id(sql_pool, review_id).await?
+    let review = SongPublishingReviewDao::get_by_id(&state.sql_pool, review_id).await?
         .with_context(|| format!("Review {} not found", review_id))?;
-    let actor = UserDao::get_by_id(sql_pool, actor_uid).await?
+    let actor = UserDao::get_by_id(&state.sql_pool, actor_uid).await?
         .with_context(|| format!("User {} not found", actor_uid))?;
 
     let mut recipients = HashSet::new();
-    recipients.extend(community_cfg.contributors);
+    recipients.extend(contributor_emails(&state.sql_pool).await?);
     recipients.remove(&actor.email);
 
-    let subject = format!("稿件已更新：{}", review.song_display_id);
-    let body = format!(
-        "{} 更新了稿件 {}。{}",
-        actor.username,
-        review.song_display_id,
-        note.map(|x| format!("\n\n备注：{x}")).unwrap_or_default(),
-    );
+    let template = NotificationTemplate::ReviewModified {
+        actor_name: actor.username,
+        song_display_id: review.song_display_id,
+        note: note.map(|x| x.to_string()),
+    };
 
+    let recipients = recipients.into_iter().collect_vec();
+    let mut langs = user::email_langs_by_addresses(&state.sql_pool, &recipients).await?;
     for email in recipients {
-        mailer::send_notification(&email_cfg, &email, &subject, &body).await?;
+        let lang = langs.remove(&email).unwrap_or_default();
+        jobs::enqueue(state, BackgroundJob::SendEmail {
+            email_type: email_delivery::TYPE_REVIEW_UPDATE.to_string(),
+            to: email,
+            template: template.clone(),
+            lang,
+        }).await;
     }
     Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ApproveReviewReq {
     pub review_id: i64,
     pub comment: Option<String>,
@@ -785,9 +1073,9 @@ pub async fn review_approve(
     state: State<AppState>,
     req: Json<ApproveReviewReq>,
 ) -> WebResult<()> {
-    ensure_contributor(&state, claims.uid()).await?;
+    ensure_contributor(&state, &claims).await?;
 
-    if let Some(ref x) = req.comment && x.chars().count() > 1000 {
+    if let Some(ref x) = req.comment && x.chars().count() > state.limits.comment_max_chars {
         err!("comment_too_long", "Comment is too long")
     }
 
@@ -808,6 +1096,7 @@ pub async fn review_approve(
     review.review_comment = req.comment.clone();
     review.review_time = Some(Utc::now());
     review.status = 1;
+    review.reviewer_uid = Some(claims.uid());
     SongPublishingReviewDao::update_by_id(&mut *tx, &review).await?;
 
     if review.r#type == song_publishing_review::TYPE_CREATE {
@@ -819,10 +1108,15 @@ pub async fn review_approve(
 
         // Update corresponding data
         let tag_ids = data.song_tags.iter().map(|x| x.id).collect();
+        let invited = service::crew::mark_pending(&[], review.user_id, &mut data.song_production_crew);
         SongDao::update_song_origin_info(&mut tx, song_id, &data.song_origin_infos).await?;
         SongDao::update_song_production_crew(&mut tx, song_id, &data.song_production_crew).await?;
         SongDao::update_song_external_links(&mut tx, song_id, &data.song_external_links).await?;
         SongDao::update_song_tags(&mut tx, song_id, tag_ids).await?;
+        near_duplicate::save_fingerprint(&mut *tx, song_id, &data.song_info.title, &data.song_info.lyrics).await?;
+        if let Some(ref x) = data.song_localized_titles {
+            localization::replace(&mut tx, localized_title::ENTITY_SONG, song_id, x).await?;
+        }
 
         // Activate the jmid prefix
         let (prefix, _number) = parse_jmid(&review.song_display_id)
@@ -842,22 +1136,23 @@ pub async fn review_approve(
         tx.commit().await?;
 
         // Write behind, data consistence is not guaranteed.
-        search::song::add_or_replace_document(
-            &state.meilisearch,
-            &state.sql_pool,
-            &[song_id],
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::IndexSongs { song_ids: vec![song_id] }).await;
+        jobs::enqueue(&state, BackgroundJob::TranscodeSong { song_id }).await;
         service::recommend_v2::notify_update(song_id, state.redis_conn.clone()).await?;
 
-        let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
-        service::mailer::send_review_approved_notification(
-            &email_cfg,
-            &uploader.email,
-            &data.song_info.display_id,
-            &data.song_info.title,
-            &uploader.username,
-            review.review_comment.as_deref(),
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::SendReviewResult {
+            review_id: review.id,
+            status: review.status,
+            to: uploader.email.clone(),
+            template: NotificationTemplate::ReviewApproved {
+                user_name: uploader.username.clone(),
+                song_display_id: data.song_info.display_id.clone(),
+                song_title: data.song_info.title.clone(),
+                comment: review.review_comment.clone(),
+            },
+            lang: user::email_lang(&uploader),
+        }).await;
+        send_crew_invitations(&state, &data.song_info.display_id, &data.song_info.title, &uploader.username, &invited).await;
     } else if review.r#type == song_publishing_review::TYPE_MODIFY {
         // Update existing song
         let song_id = data.song_info.id;
@@ -884,37 +1179,80 @@ pub async fn review_approve(
             update_time: Utc::now(), // Current time
             explicit: data.song_info.explicit,
             gain: data.song_info.gain,
+            loudness_lufs: data.song_info.loudness_lufs,
+            true_peak: data.song_info.true_peak,
+            version: orig_song.version,
         };
 
-        SongDao::update_by_id(&mut *tx, &new_song).await?;
+        match SongDao::update_by_id_checked(&mut *tx, &new_song).await {
+            Ok(_) => {}
+            Err(DbError::VersionConflict) => err!("version_conflict", "The song has been modified concurrently, please retry"),
+            Err(e) => Err(e)?,
+        }
         // Update corresponding data
         let tag_ids = data.song_tags.iter().map(|x| x.id).collect();
+        let existing_crew = SongDao::list_production_crew_by_song_id(&mut *tx, song_id).await?;
+        let invited = service::crew::mark_pending(&existing_crew, review.user_id, &mut data.song_production_crew);
         SongDao::update_song_origin_info(&mut tx, song_id, &data.song_origin_infos).await?;
         SongDao::update_song_production_crew(&mut tx, song_id, &data.song_production_crew).await?;
         SongDao::update_song_external_links(&mut tx, song_id, &data.song_external_links).await?;
         SongDao::update_song_tags(&mut tx, song_id, tag_ids).await?;
+        near_duplicate::save_fingerprint(&mut *tx, song_id, &new_song.title, &new_song.lyrics).await?;
+        if let Some(ref x) = data.song_localized_titles {
+            localization::replace(&mut tx, localized_title::ENTITY_SONG, song_id, x).await?;
+        }
         tx.commit().await?;
+        // Also cached by the display id
+        service::song::evict_detail_cache(state.redis_conn.clone(), std::slice::from_ref(&new_song)).await?;
 
-        search::song::add_or_replace_document(
-            &state.meilisearch,
-            &state.sql_pool,
-            &[song_id],
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::IndexSongs { song_ids: vec![song_id] }).await;
+        jobs::enqueue(&state, BackgroundJob::TranscodeSong { song_id }).await;
         service::recommend_v2::notify_update(song_id, state.redis_conn.clone()).await?;
 
-        let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
-        service::mailer::send_review_modify_approved_notification(
-            &email_cfg,
-            &uploader.email,
-            &data.song_info.display_id,
-            &uploader.username,
-            review.review_comment.as_deref(),
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::SendReviewResult {
+            review_id: review.id,
+            status: review.status,
+            to: uploader.email.clone(),
+            template: NotificationTemplate::ReviewModifyApproved {
+                user_name: uploader.username.clone(),
+                song_display_id: data.song_info.display_id.clone(),
+                comment: review.review_comment.clone(),
+            },
+            lang: user::email_lang(&uploader),
+        }).await;
+        send_crew_invitations(&state, &new_song.display_id, &new_song.title, &uploader.username, &invited).await;
     }
+    notify_review_result(&state, &review, notification::TYPE_REVIEW_APPROVED).await;
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The in-app counterpart of the result emails, and the status pushed to the online uploader
+async fn notify_review_result(state: &AppState, review: &SongPublishingReview, r#type: &str) {
+    let data = json!({
+        "review_id": review.id,
+        "review_type": review.r#type,
+        "song_display_id": review.song_display_id,
+        "comment": review.review_comment,
+    });
+    notification::notify(state, &[review.user_id], r#type, review.reviewer_uid, data).await;
+    let data = json!({
+        "review_id": review.id,
+        "review_type": review.r#type,
+        "song_display_id": review.song_display_id,
+        "status": review.status,
+    });
+    events::publish(&mut state.redis_conn.clone(), review.user_id, events::EVENT_REVIEW_STATUS, data).await;
+}
+
+/// The song is already published, failing to invite is only logged
+async fn send_crew_invitations(state: &AppState, display_id: &str, title: &str, uploader_name: &str, uids: &[i64]) {
+    let result = service::crew::send_invitations(&state.config, &state.sql_pool, display_id, title, uploader_name, uids).await;
+    if let Err(e) = result {
+        warn!("Failed to send crew invitations of song {display_id}: {:?}", e);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RejectReviewReq {
     pub review_id: i64,
     pub comment: String,
@@ -925,12 +1263,12 @@ pub async fn review_reject(
     state: State<AppState>,
     req: Json<RejectReviewReq>,
 ) -> WebResult<()> {
-    ensure_contributor(&state, claims.uid()).await?;
+    ensure_contributor(&state, &claims).await?;
 
     if req.comment.is_blank() {
         err!("comment_required", "Comment is required")
     }
-    if req.comment.chars().count() > 1000 {
+    if req.comment.chars().count() > state.limits.comment_max_chars {
         err!("comment_too_long", "Comment is too long")
     }
 
@@ -947,6 +1285,7 @@ pub async fn review_reject(
     review.review_comment = Some(req.comment.clone());
     review.review_time = Some(Utc::now());
     review.status = 2;
+    review.reviewer_uid = Some(claims.uid());
 
     let mut tx = state.sql_pool.begin().await?;
     SongPublishingReviewDao::update_by_id(&mut *tx, &review).await?;
@@ -969,26 +1308,33 @@ pub async fn review_reject(
         }
         tx.commit().await?;
 
-        let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
-        service::mailer::send_review_rejected_notification(
-            &email_cfg,
-            &uploader.email,
-            &review.song_display_id,
-            &data.song_info.title,
-            &uploader.username,
-            &req.comment,
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::SendReviewResult {
+            review_id: review.id,
+            status: review.status,
+            to: uploader.email.clone(),
+            template: NotificationTemplate::ReviewRejected {
+                user_name: uploader.username.clone(),
+                song_display_id: review.song_display_id.clone(),
+                song_title: data.song_info.title.clone(),
+                comment: req.comment.clone(),
+            },
+            lang: user::email_lang(&uploader),
+        }).await;
     } else if review.r#type == song_publishing_review::TYPE_MODIFY {
         tx.commit().await?;
 
-        let email_cfg: EmailConfig = state.config.get_and_parse("email")?;
-        service::mailer::send_review_modify_rejected_notification(
-            &email_cfg,
-            &uploader.email,
-            &review.song_display_id,
-            &uploader.username,
-            &req.comment,
-        ).await?;
+        jobs::enqueue(&state, BackgroundJob::SendReviewResult {
+            review_id: review.id,
+            status: review.status,
+            to: uploader.email.clone(),
+            template: NotificationTemplate::ReviewModifyRejected {
+                user_name: uploader.username.clone(),
+                song_display_id: review.song_display_id.clone(),
+                comment: req.comment.clone(),
+            },
+            lang: user::email_lang(&uploader),
+        }).await;
     }
+    notify_review_result(&state, &review, notification::TYPE_REVIEW_REJECTED).await;
     ok!(())
 }
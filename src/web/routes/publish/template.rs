@@ -0,0 +1,127 @@
+use crate::db::song::{ISongDao, SongDao};
+use crate::db::song_publishing_review::SongPublishingReviewDao;
+use crate::db::CrudDao;
+use crate::service::localization::LocalizedTitleItem;
+use crate::service::song::{CreationTypeInfo, ExternalLink};
+use crate::web::jwt::Claims;
+use crate::web::result::WebResult;
+use crate::web::routes::publish::review::ensure_review_visible;
+use crate::web::routes::publish::{CreationInfo, InternalSongPublishReviewData, ProductionItem, PublishReq};
+use crate::web::state::AppState;
+use crate::{err, ok};
+use anyhow::Context;
+use async_backtrace::framed;
+use axum::extract::{Query, State};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The reusable metadata of a publication, which is [PublishReq] without the temp ids, the jmid and the comment.
+///
+/// Exported by `/publish/export_template` and accepted by `/song/publish` to prefill the repeated fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct PublishTemplate {
+    pub title: String,
+    pub subtitle: String,
+    pub description: String,
+    pub lyrics: String,
+    pub tag_ids: Vec<i64>,
+    pub creation_info: CreationInfo,
+    pub production_crew: Vec<ProductionItem>,
+    pub external_links: Vec<ExternalLink>,
+    pub explicit: Option<bool>,
+    /// @since 260430
+    pub localized_titles: Vec<LocalizedTitleItem>,
+}
+
+impl PublishReq {
+    /// Fill the empty fields of the request from the template
+    pub fn apply_template(&mut self, template: PublishTemplate) {
+        let fill = |x: &mut String, y: String| if x.is_empty() { *x = y };
+        fill(&mut self.title, template.title);
+        fill(&mut self.subtitle, template.subtitle);
+        fill(&mut self.description, template.description);
+        fill(&mut self.lyrics, template.lyrics);
+        if self.tag_ids.is_empty() {
+            self.tag_ids = template.tag_ids;
+        }
+        // An original work without the origin infos is the same as the omitted one
+        if self.creation_info.creation_type == 0 && self.creation_info.origin_info.is_none() && self.creation_info.derivative_info.is_none() {
+            self.creation_info = template.creation_info;
+        }
+        if self.production_crew.is_empty() {
+            self.production_crew = template.production_crew;
+        }
+        if self.external_links.is_empty() {
+            self.external_links = template.external_links;
+        }
+        self.explicit = self.explicit.or(template.explicit);
+        if self.localized_titles.is_empty() {
+            self.localized_titles = template.localized_titles;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExportTemplateReq {
+    pub review_id: i64,
+}
+
+/// Export the metadata of a submitted review as a template.
+///
+/// Permission: Only available for the uploader and contributors.
+#[framed]
+pub async fn export_template(
+    claims: Claims,
+    state: State<AppState>,
+    req: Query<ExportTemplateReq>,
+) -> WebResult<PublishTemplate> {
+    let Some(review) = SongPublishingReviewDao::get_by_id(&state.sql_pool, req.review_id).await? else {
+        err!("not_found", "Review not found")
+    };
+    ensure_review_visible(&state, &review, claims.uid()).await?;
+
+    let data = serde_json::from_value::<InternalSongPublishReviewData>(review.data)
+        .with_context(|| format!("Error during decoding song publish review({}) data", review.id))?;
+
+    let origin_song_ids = data.song_origin_infos.iter().filter_map(|x| x.origin_song_id).collect_vec();
+    let display_ids: HashMap<i64, String> = SongDao::list_by_ids(&state.sql_pool, &origin_song_ids).await?
+        .into_iter()
+        .map(|x| (x.id, x.display_id))
+        .collect();
+    // The derivative info is saved after the origin info, see `build_internal_review_data`
+    let mut origin_infos = data.song_origin_infos.into_iter().map(|x| {
+        let display_id = x.origin_song_id.and_then(|id| display_ids.get(&id).cloned());
+        CreationTypeInfo::from_song_origin_info(x, display_id)
+    });
+    let creation_info = CreationInfo {
+        creation_type: data.song_info.creation_type,
+        origin_info: origin_infos.next(),
+        derivative_info: origin_infos.next(),
+    };
+
+    ok!(PublishTemplate {
+        title: data.song_info.title,
+        subtitle: data.song_info.subtitle,
+        description: data.song_info.description,
+        lyrics: data.song_info.lyrics,
+        tag_ids: data.song_tags.into_iter().map(|x| x.id).collect(),
+        creation_info,
+        // The registered users are referenced by the uid only
+        production_crew: data.song_production_crew.into_iter()
+            .map(|x| ProductionItem {
+                role: x.role,
+                name: if x.uid.is_some() { None } else { x.person_name },
+                uid: x.uid,
+            })
+            .collect(),
+        external_links: data.song_external_links.into_iter()
+            .map(|x| ExternalLink { platform: x.platform, url: x.url })
+            .collect(),
+        explicit: data.song_info.explicit,
+        // Absent in the modifications keeping the titles
+        localized_titles: data.song_localized_titles.unwrap_or_default(),
+    })
+}
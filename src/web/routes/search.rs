@@ -0,0 +1,37 @@
+use crate::service::search_feedback::{self, FeedbackError};
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::{err, ok};
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/feedback", post(feedback))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchFeedbackReq {
+    /// The `query_id` returned by `/song/search`
+    pub query_id: String,
+    /// The clicked hit
+    pub song_id: i64,
+}
+
+/// Report a clicked hit of a search, anonymous users can report as well
+#[framed]
+async fn feedback(
+    state: State<AppState>,
+    req: Json<SearchFeedbackReq>,
+) -> WebResult<()> {
+    match search_feedback::record_click(state.redis_conn.clone(), &state.sql_pool, &req.query_id, req.song_id).await {
+        Ok(_) => ok!(()),
+        Err(e @ FeedbackError::SearchNotFound) => err!("search_expired", "{}", e),
+        Err(e @ FeedbackError::NotAHit) => err!("not_a_hit", "{}", e),
+        Err(e) => Err(e)?,
+    }
+}
@@ -1,46 +1,72 @@
+use crate::db::error::DbError;
 use crate::db::song::{ISongDao, SongDao};
 use crate::db::song_tag::{ISongTagDao, SongTag, SongTagDao};
+use crate::db::user::{IUserDao, UserDao};
 use crate::db::CrudDao;
+use crate::search::song::SongSuggestion;
 use crate::service::song::PublicSongDetail;
 use crate::service::tag_recommend;
-use crate::service::{recommend_v2, song, song_like};
+use crate::service::song_report::{self, ReportError};
+use crate::service::song_share::{self, SharePlatform};
+use crate::service::{preference, recommend_v2, search_feedback, song, song_exclusion, song_like};
 use crate::util::IsBlank;
-use crate::web::extractors::XRealIP;
-use crate::web::jwt::Claims;
+use crate::web::extractors::{ClientFingerprint, XRealIP};
+use crate::web::jwt::{AuthError, Claims};
+use crate::web::limits::LimitsCfg;
+use crate::web::pagination::{Page, PageQuery, Pagination};
 use crate::web::result::WebResult;
-use crate::web::routes::publish;
+use crate::web::routes::{play_history, publish};
+use crate::web::routes::publish::review;
+use crate::web::routes::song_comment;
 use crate::web::state::AppState;
-use crate::{err, ok, search};
+use crate::{err, ok, search, util};
 use async_backtrace::framed;
-use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::extract::{Query, State};
 use axum::routing::{get, post};
 use axum::Json;
 use axum::Router;
 use chrono::{DateTime, TimeDelta, Utc};
+use futures::TryStreamExt;
 use itertools::Itertools;
 use redis::aio::ConnectionManager;
-use redis::AsyncCommands;
+use redis::{AsyncCommands, AsyncIter};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::ops::Sub;
 use std::time::Duration;
 use tracing::log::warn;
 
-pub fn router() -> Router<AppState> {
+pub fn router(limits: &LimitsCfg) -> Router<AppState> {
     Router::new()
         // Core operations
-        .route("/upload_audio_file", post(publish::upload_audio_file).layer(DefaultBodyLimit::max(20 * 1024 * 1024)))
-        .route("/upload_cover_image", post(publish::upload_cover_image).layer(DefaultBodyLimit::max(10 * 1024 * 1024)))
+        .route("/upload_audio_file", post(publish::upload_audio_file).layer(limits.audio_body_limit()))
+        .route("/upload_cover_image", post(publish::upload_cover_image).layer(limits.image_body_limit()))
+        // @since 260503
+        .route("/upload/presign", post(publish::presign_audio_upload))
+        // @since 260503
+        .route("/upload/confirm", post(publish::confirm_audio_upload))
         .route("/delete", post(publish::delete))
         .route("/publish", post(publish::publish))
         .route("/detail", get(detail))
         .route("/detail_by_id", get(detail_by_id))
         .route("/page_by_user", get(page_by_user))
+        // @since 260501
+        .route("/review_history", get(review::song_review_history))
+        // @since 260502
+        .route("/play", post(play))
+        // @since 260505
+        .route("/share", post(share))
+        // @since 260505
+        .route("/report", post(report))
         // Discovery
         .route("/search", get(search))
+        // @since 260505
+        .route("/search/suggest", get(search_suggest))
         .route("/recent_v2", get(recent_v2))
         .route("/hot/weekly", get(hot_weekly))
         .route("/recommend", get(recommend))
         .route("/recommend_anonymous", get(recommend_anonymous))
+        .route("/not_interested", post(not_interested))
         // Tags
         .route("/tag/create", post(tag_create))
         .route("/tag/search", get(tag_search))
@@ -53,9 +79,11 @@ pub fn router() -> Router<AppState> {
             .route("/status", get(likes_status))
             .route("/page_my_likes", get(page_my_likes)),
         )
+        // @since 260502
+        .nest("/comment", song_comment::router())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailReq {
     /// Actually the JMID
     pub id: String,
@@ -74,12 +102,15 @@ async fn detail(
         &params.id,
     ).await?;
     match data {
-        Some(x) => ok!(x),
+        Some(mut x) => {
+            x.localize();
+            ok!(x)
+        }
         None => err!("not_found", "Song not found")
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DetailByIdReq {
     pub id: i64,
 }
@@ -95,26 +126,30 @@ async fn detail_by_id(
     ).await?;
     let data = data.remove(&params.id);
     match data {
-        Some(x) => ok!(x),
+        Some(mut x) => {
+            x.localize();
+            ok!(x)
+        }
         None => err!("not_found", "Song not found")
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PageByUserReq {
     pub user_id: i64,
-    pub page: Option<i64>,
-    pub size: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PageByUserResp {
-    pub songs: Vec<DetailResp>,
-    pub total: i64,
-    pub page: i64,
-    pub size: i64,
+/// The whole query of `/song/page_by_user`, [PageByUserReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageByUserQuery {
+    #[serde(flatten)]
+    pub req: PageByUserReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
 }
 
+pub type PageByUserResp = Page<DetailResp>;
+
 pub struct DeleteReq {
     pub song_id: i64,
 }
@@ -122,11 +157,11 @@ pub struct DeleteReq {
 #[framed]
 async fn page_by_user(
     state: State<AppState>,
+    pagination: Pagination<50>,
     req: Query<PageByUserReq>,
 ) -> WebResult<PageByUserResp> {
-    let page = req.page.unwrap_or(0).max(0);
-    let size = req.size.unwrap_or(20).min(50);
-
+    let page = pagination.page_index;
+    let size = pagination.page_size;
 
     // Try to get from the cache first
     if let Some(cached) = page_by_user_cache(state.redis_conn.clone(), req.user_id, page, size).await? {
@@ -141,9 +176,16 @@ async fn page_by_user(
         ok!(cached)
     }
 
-    let songs = SongDao::page_by_user(&state.sql_pool, req.user_id, page, size).await?;
-    let song_ids = songs.iter().map(|x| x.id).collect::<Vec<i64>>();
-    let total = SongDao::count_by_user(&state.sql_pool, req.user_id).await?;
+    // Songs of banned users are hidden
+    let is_banned = !UserDao::list_banned_ids(&state.sql_pool, &[req.user_id]).await?.is_empty();
+    let (song_ids, total) = if is_banned {
+        (vec![], 0)
+    } else {
+        let songs = SongDao::page_by_user(&state.sql_pool, req.user_id, page, size).await?;
+        let song_ids = songs.iter().map(|x| x.id).collect::<Vec<i64>>();
+        let total = SongDao::count_by_user(&state.sql_pool, req.user_id).await?;
+        (song_ids, total)
+    };
 
     let songs = song::get_public_detail_with_cache(
         state.redis_conn.clone(),
@@ -151,7 +193,7 @@ async fn page_by_user(
         &song_ids,
     ).await?;
     let songs: Vec<PublicSongDetail> = song_ids.iter().filter_map(|id| songs.get(id).cloned()).collect();
-    let resp = PageByUserResp { songs, total, page, size };
+    let resp = pagination.into_page(songs, total);
 
     // Cache for 5 minutes
     set_page_by_user_cache(state.redis_conn.clone(), req.user_id, page, size, resp.clone()).await?;
@@ -177,13 +219,23 @@ async fn page_by_user_cache(mut redis: ConnectionManager, user_id: i64, page: i6
     }
 }
 
+/// Delete all cached pages of the user's songs
+pub async fn evict_page_by_user_cache(mut redis: ConnectionManager, user_id: i64) -> anyhow::Result<()> {
+    let keys: AsyncIter<String> = redis.scan_match(format!("user_songs:{}:*", user_id)).await?;
+    let keys = keys.try_collect::<Vec<_>>().await?;
+    if !keys.is_empty() {
+        let _: () = redis.del(&keys).await?;
+    }
+    Ok(())
+}
+
 async fn set_page_by_user_cache(mut redis: ConnectionManager, user_id: i64, page: i64, size: i64, resp: PageByUserResp) -> anyhow::Result<()> {
     let cache_key = format!("user_songs:{}:{}:{}", user_id, page, size);
     let _: () = redis.set_ex(&cache_key, serde_json::to_string(&resp)?, 300).await?;
     Ok(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchReq {
     pub q: String,
     pub limit: Option<usize>,
@@ -193,7 +245,7 @@ pub struct SearchReq {
     pub sort_by: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResp {
     pub hits: Vec<SearchSongItem>,
     pub query: String,
@@ -201,9 +253,12 @@ pub struct SearchResp {
     pub total_hits: Option<usize>,
     pub limit: usize,
     pub offset: usize,
+    /// Since 260427, report the clicked hits to `/search/feedback` with it. `None` if unavailable
+    #[serde(default)]
+    pub query_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchSongItem {
     pub id: i64,
     pub display_id: String,
@@ -226,10 +281,129 @@ pub struct SearchSongItem {
     pub original_artists: Vec<String>,
     /// since 260114
     pub original_titles: Vec<String>,
+    /// The language of the `title` if it's localized, see [PublicSongDetail::title_lang].
+    /// @since 260430
+    #[serde(default)]
+    pub title_lang: Option<String>,
+}
+
+/// Record a play of the song, counted into the play count of the song at the next flush.
+///
+/// The same as `/play_history/touch` for the logged-in users, or `/play_history/touch_anonymous` otherwise.
+async fn play(
+    claims: Option<Claims>,
+    ip: XRealIP,
+    fingerprint: ClientFingerprint,
+    state: State<AppState>,
+    req: Json<play_history::TouchReq>,
+) -> WebResult<()> {
+    match claims {
+        Some(claims) => play_history::touch(claims, state, req).await,
+        None => play_history::touch_anonymous(ip, fingerprint, state, req).await,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ShareReq {
+    pub song_id: i64,
+    pub platform: SharePlatform,
+}
+
+/// Record a share of the song to the platform, counted into the share count of the song.
+///
+/// Anonymous users are told apart the same as `/song/play`.
+#[framed]
+async fn share(
+    claims: Option<Claims>,
+    ip: XRealIP,
+    fingerprint: ClientFingerprint,
+    mut state: State<AppState>,
+    req: Json<ShareReq>,
+) -> WebResult<()> {
+    let uid = match (&claims, fingerprint.0) {
+        (Some(claims), _) => claims.uid(),
+        (None, Some(ref x)) if !util::is_valid_client_fingerprint(x) => err!("invalid_fingerprint", "Invalid client fingerprint"),
+        (None, Some(ref x)) => util::convert_fingerprint_to_anonymous_uid(&ip.0, x)?,
+        (None, None) => util::convert_ip_to_anonymous_uid(&ip.0)?,
+    };
+    if song_share::cooldown(uid, req.song_id, &mut state.redis_conn).await? {
+        err!("cooldown", "Please wait 60 seconds before sharing again");
+    }
+    if claims.is_none() && song_share::anonymous_ip_limited(&ip.0, req.song_id, &mut state.redis_conn).await? {
+        err!("cooldown", "Too many shares of this song from your network, please try again later");
+    }
+    // The hidden songs are not shared, the same as played and liked
+    if !song::is_public(state.redis_conn.clone(), &state.sql_pool, req.song_id).await? {
+        err!("not_found", "Song not found")
+    }
+    song_share::record_share(&state.sql_pool, claims.map(|x| x.uid()), req.song_id, req.platform).await?;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportSongReq {
+    pub song_id: i64,
+    /// `copyright`, `explicit_content` or `wrong_origin_info`
+    pub category: String,
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportSongResp {
+    /// `None` if reported already and not handled yet
+    pub report_id: Option<i64>,
+}
+
+/// Report an infringing or miscategorized song to the contributors
+#[framed]
+async fn report(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ReportSongReq>,
+) -> WebResult<ReportSongResp> {
+    match song_report::report(&state, claims.uid(), req.song_id, &req.category, &req.reason).await {
+        Ok(report_id) => ok!(ReportSongResp { report_id }),
+        Err(e @ ReportError::InvalidCategory(_)) => err!("invalid_category", "{}", e),
+        Err(e @ ReportError::InvalidReason) => err!("invalid_reason", "{}", e),
+        Err(e @ ReportError::TooManyReports) => err!("too_many_requests", "{}", e),
+        Err(ReportError::SongNotFound(_)) => err!("not_found", "Song not found"),
+        Err(ReportError::Sqlx(e)) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchSuggestReq {
+    pub q: String,
+    /// 5 by default, at most 10
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchSuggestResp {
+    pub hits: Vec<SongSuggestion>,
+}
+
+/// The songs suggested while typing the query, empty if MeiliSearch is unavailable
+#[framed]
+async fn search_suggest(
+    state: State<AppState>,
+    req: Query<SearchSuggestReq>,
+) -> WebResult<SearchSuggestResp> {
+    let q = req.q.trim();
+    if q.is_empty() {
+        ok!(SearchSuggestResp { hits: vec![] })
+    }
+    let limit = req.limit.unwrap_or(5).clamp(1, 10);
+    let hits = search::guarded(search::song::suggest_songs(state.meilisearch.as_ref(), q, limit)).await
+        .unwrap_or_default();
+    ok!(SearchSuggestResp { hits })
 }
 
 #[framed]
 async fn search(
+    // The search is public, so an expired token is searched as anonymous instead of rejected
+    claims: Result<Claims, AuthError>,
     state: State<AppState>,
     req: Query<SearchReq>,
 ) -> WebResult<SearchResp> {
@@ -249,24 +423,52 @@ async fn search(
         }
     };
 
+    // The preferences only tailor the results, so the search goes on without them if they fail
+    let prefs = match claims {
+        Ok(claims) => preference::get_effective(&state.sql_pool, claims.uid()).await
+            .inspect_err(|e| warn!("Failed to get the preferences of {}: {:?}", claims.uid(), e))
+            .ok(),
+        Err(_) => None,
+    };
+    // Left out by the search, so the pages are full and the total hits don't count them
+    let exclude_tags = match &prefs {
+        Some(prefs) if !prefs.blocked_tag_ids.is_empty() => SongTagDao::list_by_ids(&state.sql_pool, &prefs.blocked_tag_ids).await?
+            .into_iter()
+            .map(|x| x.name)
+            .collect_vec(),
+        _ => vec![],
+    };
+
+    let by_relevance = sort_method.is_none();
     let search_query = search::song::SearchQuery {
         q: req.q.clone(),
         limit: req.limit,
         offset: req.offset,
         filter: req.filter.clone(),
         sort_method,
+        exclude_tags,
     };
 
-    let result = search::song::search_songs(state.meilisearch.as_ref(), &search_query).await?;
+    let Some(result) = search::song::search_songs_or_fallback(state.meilisearch.as_ref(), &state.sql_pool, &search_query).await? else {
+        err!("search_unavailable", "Search is temporarily unavailable, please try again later")
+    };
     let hit_ids: Vec<i64> = result.hits.into_iter().map(|x| x.id).collect();
 
-    let details = song::get_public_detail_with_cache(
+    let mut details = song::get_public_detail_with_cache(
         state.redis_conn.clone(),
         &state.sql_pool,
         &hit_ids,
-    ).await?
-        .into_iter()
-        .map(|(_, song)| SearchSongItem {
+    ).await?;
+    let mut songs = hit_ids.iter().filter_map(|x| details.remove(x)).collect_vec();
+
+    // Move the favorite tags ahead within the page if sorted by relevance
+    if let Some(prefs) = prefs && by_relevance {
+        songs = prefs.rank(songs, |x| x.tags.iter().map(|t| t.id).collect_vec());
+    }
+
+    songs.iter_mut().for_each(PublicSongDetail::localize);
+    let details = songs.into_iter()
+        .map(|song| SearchSongItem {
             id: song.id,
             display_id: song.display_id,
             title: song.title,
@@ -283,9 +485,19 @@ async fn search(
             explicit: song.explicit,
             original_artists: song.origin_infos.iter().filter_map(|x| x.artist.clone()).collect_vec(),
             original_titles: song.origin_infos.iter().filter_map(|x| x.title.clone()).collect_vec(),
+            title_lang: song.title_lang,
         })
         .collect::<Vec<_>>();
 
+    let query_id = search_feedback::record_search(
+        state.redis_conn.clone(),
+        &state.sql_pool,
+        &req.q,
+        req.sort_by.as_deref().unwrap_or("relevance"),
+        result.hits_info.offset,
+        details.iter().map(|x| x.id).collect(),
+    ).await;
+
     ok!(SearchResp {
         hits: details,
         query: result.query,
@@ -293,18 +505,19 @@ async fn search(
         total_hits: result.hits_info.total_hits,
         limit: result.hits_info.limit,
         offset: result.hits_info.offset,
+        query_id,
     })
 }
 
 /// Since 251102
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RecentReq {
     pub cursor: Option<DateTime<Utc>>,
     pub limit: Option<i32>,
     pub after: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RecentResp {
     pub songs: Vec<DetailResp>,
 }
@@ -338,7 +551,7 @@ async fn recent_v2(
     ok!(RecentResp {songs})
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct HotResp {
     pub songs: Vec<DetailResp>,
 }
@@ -351,7 +564,7 @@ async fn hot_weekly(
     ok!(HotResp {songs})
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct RecommendResp {
     pub songs: Vec<DetailResp>,
 }
@@ -374,27 +587,55 @@ async fn recommend_anonymous(
     ok!(resp)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LikeReq {
     pub song_id: i64,
     pub playback_position_secs: Option<i32>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LikeResp {
+    /// The like count of the song after this operation
+    pub like_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct NotInterestedReq {
+    pub song_id: i64,
+}
+
+/// Stop recommending the song to the user for a few weeks, also removes it from today's recommendations
+#[framed]
+async fn not_interested(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<NotInterestedReq>,
+) -> WebResult<()> {
+    if SongDao::get_by_id(&state.sql_pool, req.song_id).await?.is_none() {
+        err!("not_found", "Song not found")
+    }
+    song_exclusion::mark_not_interested(state.redis_conn.clone(), claims.uid(), req.song_id).await?;
+    ok!(())
+}
+
 #[framed]
 async fn like(
     claims: Claims,
     state: State<AppState>,
     req: Json<LikeReq>,
-) -> WebResult<()> {
-    song_like::like(
+) -> WebResult<LikeResp> {
+    if !song::is_public(state.redis_conn.clone(), &state.sql_pool, req.song_id).await? {
+        err!("song_not_found", "Song not found")
+    }
+    let like_count = song_like::like(
         &state.redis_conn, &state.sql_pool,
         claims.uid(), req.song_id,
         req.playback_position_secs
     ).await?;
-    ok!(())
+    ok!(LikeResp { like_count })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UnlikeReq {
     pub song_id: i64,
 }
@@ -404,21 +645,21 @@ async fn unlike(
     claims: Claims,
     state: State<AppState>,
     req: Json<UnlikeReq>,
-) -> WebResult<()> {
-    song_like::unlike(
+) -> WebResult<LikeResp> {
+    let like_count = song_like::unlike(
         &state.redis_conn,
         &state.sql_pool,
         claims.uid(), req.song_id
     ).await?;
-    ok!(())
+    ok!(LikeResp { like_count })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LikeStatusReq {
     pub song_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LikeStatusResp {
     pub liked: bool,
 }
@@ -437,21 +678,9 @@ async fn likes_status(
     ok!(LikeStatusResp { liked })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MyLikesReq {
-    pub page_index: i64,
-    pub page_size: i64,
-}
+pub type MyLikesResp = Page<MyLikeItem>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MyLikesResp {
-    pub data: Vec<MyLikeItem>,
-    pub page_size: i64,
-    pub page_index: i64,
-    pub total: i64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct MyLikeItem {
     pub song_data: PublicSongDetail,
     pub liked_time: DateTime<Utc>,
@@ -461,23 +690,14 @@ pub struct MyLikeItem {
 async fn page_my_likes(
     claims: Claims,
     state: State<AppState>,
-    req: Query<MyLikesReq>,
+    pagination: Pagination<50>,
 ) -> WebResult<MyLikesResp> {
-    // Validate
-    if req.page_index < 0 {
-        err!("invalid_page_index", "Page index must be non-negative")
-    }
-    if req.page_size <= 0 || req.page_size > 50 {
-        err!("invalid_page_size", "Page size must be between 1 and 50")
-    }
-
-    // Process
     let (total, songs) = song_like::page_by_user(
         &state.redis_conn,
         &state.sql_pool,
         claims.uid(),
-        req.page_index,
-        req.page_size,
+        pagination.page_index,
+        pagination.page_size,
     ).await?;
     let song_ids = songs.iter().map(|song| song.song_id).collect::<Vec<_>>();
     let song_details = song::get_public_detail_with_cache(
@@ -495,25 +715,20 @@ async fn page_my_likes(
             None => None
         }
     }).collect::<Vec<_>>();
-    ok!(MyLikesResp {
-        data: composed,
-        page_size: req.page_size,
-        page_index: req.page_index,
-        total
-    })
+    ok!(pagination.into_page(composed, total))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagSearchReq {
     pub query: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagSearchResp {
     pub result: Vec<TagItem>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagItem {
     pub id: i64,
     pub name: String,
@@ -541,13 +756,13 @@ async fn tag_search(
     ok!(TagSearchResp {result})
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagCreateReq {
     pub name: String,
     pub description: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagCreateResp {
     pub id: i64,
 }
@@ -555,16 +770,19 @@ pub struct TagCreateResp {
 #[framed]
 async fn tag_create(_claims: Claims, state: State<AppState>, req: Json<TagCreateReq>) -> WebResult<TagCreateResp> {
     // TODO[feat](song-tag): Need audit procedure
-    if req.name.is_empty() || req.name.chars().count() > 10 {
+    if req.name.is_empty() || req.name.chars().count() > state.limits.tag_name_max_chars {
         err!("invalid_name", "Invalid name")
     }
 
-    // TODO[data-racing]: Add a mutex lock for these two operations
     if SongTagDao::get_by_name(&state.sql_pool, req.name.as_str()).await?.is_some() {
         err!("name_exists", "Tag name already exists")
     }
+    if !SongTagDao::list_aliases_by_names(&state.sql_pool, std::slice::from_ref(&req.name)).await?.is_empty() {
+        err!("name_exists", "Tag name already exists as an alias")
+    }
 
-    let id = SongTagDao::insert(
+    // The unique constraint covers the race between the check above and the insert
+    let result = SongTagDao::insert(
         &state.sql_pool,
         &SongTag {
             id: 0,
@@ -574,12 +792,17 @@ async fn tag_create(_claims: Claims, state: State<AppState>, req: Json<TagCreate
             create_time: Utc::now(),
             update_time: Utc::now(),
         },
-    ).await?;
+    ).await.map_err(DbError::from);
+    let id = match result {
+        Ok(id) => id,
+        Err(e) if e.is_unique_violation() => err!("name_exists", "Tag name already exists"),
+        Err(e) => Err(e)?,
+    };
 
     ok!(TagCreateResp { id })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagRecommendItem {
     pub id: i64,
     pub name: String,
@@ -587,7 +810,7 @@ pub struct TagRecommendItem {
     pub score: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TagRecommendResp {
     pub result: Vec<TagRecommendItem>,
 }
@@ -0,0 +1,302 @@
+use crate::db::song::{Song, SongDao};
+use crate::db::song_comment::{ISongCommentDao, SongComment, SongCommentDao};
+use crate::db::user_shadow_ban::FEATURE_COMMENTS;
+use crate::db::CrudDao;
+use crate::service::tombstone::DELETED_PLACEHOLDER;
+use crate::service::{notification, shadow_ban, user};
+use crate::util::IsBlank;
+use crate::web::governor;
+use crate::web::jwt::Claims;
+use crate::web::pagination::{Page, PageQuery, Pagination};
+use crate::web::result::{CommonError, WebError, WebResult};
+use crate::web::routes::user::PublicUserProfile;
+use crate::web::state::AppState;
+use crate::{common, err, ok};
+use async_backtrace::framed;
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+const REPORT_REASON_MAX_CHARS: usize = 200;
+
+pub(crate) fn router() -> Router<AppState> {
+    // One comment every 10 seconds per IP after a burst of 5, shared by creating and replying
+    let writes = Router::new()
+        .route("/create", post(create))
+        .route("/reply", post(reply))
+        .route_layer(governor::content_governor_layer(10, 5));
+    Router::new()
+        .route("/page", get(page))
+        .route("/delete", post(delete))
+        .route("/report", post(report))
+        .merge(writes)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommentItem {
+    pub id: i64,
+    pub song_id: i64,
+    pub author: PublicUserProfile,
+    /// The root comment of the thread, `None` for the root comments
+    pub parent_id: Option<i64>,
+    /// The author of the replied comment, `None` if replying to the root comment
+    pub reply_to: Option<PublicUserProfile>,
+    pub content: String,
+    /// Always 0 for the replies
+    pub reply_count: i64,
+    pub create_time: DateTime<Utc>,
+    /// A deleted comment is kept in the thread as a placeholder, without the author and content.
+    /// @since 260505
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateCommentReq {
+    pub song_id: i64,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreateCommentResp {
+    pub comment_id: i64,
+}
+
+#[framed]
+async fn create(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<CreateCommentReq>,
+) -> WebResult<CreateCommentResp> {
+    ensure_content_valid(&state, &req.content)?;
+    let song = ensure_song_visible(&state, req.song_id, claims.uid()).await?;
+
+    let comment_id = insert_comment(&state, req.song_id, claims.uid(), None, None, &req.content).await?;
+    notify_comment(&state, song.uploader_uid, claims.uid(), req.song_id, comment_id, None).await;
+    ok!(CreateCommentResp { comment_id })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplyCommentReq {
+    /// The root comment or a reply in its thread
+    pub comment_id: i64,
+    pub content: String,
+}
+
+#[framed]
+async fn reply(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ReplyCommentReq>,
+) -> WebResult<CreateCommentResp> {
+    ensure_content_valid(&state, &req.content)?;
+    let target = get_comment(&state, req.comment_id).await?;
+    ensure_song_visible(&state, target.song_id, claims.uid()).await?;
+
+    // The replies are flattened into the thread of the root comment
+    let (parent_id, reply_to_uid) = match target.parent_id {
+        Some(parent_id) => (parent_id, Some(target.user_id)),
+        None => (target.id, None),
+    };
+    let comment_id = insert_comment(&state, target.song_id, claims.uid(), Some(parent_id), reply_to_uid, &req.content).await?;
+    notify_comment(&state, target.user_id, claims.uid(), target.song_id, comment_id, Some(parent_id)).await;
+    ok!(CreateCommentResp { comment_id })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageCommentReq {
+    pub song_id: i64,
+    /// Page the replies of the root comment instead of the root comments
+    pub parent_id: Option<i64>,
+}
+
+/// The whole query of `/song/comment/page`, [PageCommentReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageCommentQuery {
+    #[serde(flatten)]
+    pub req: PageCommentReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
+}
+
+pub type PageCommentResp = Page<CommentItem>;
+
+/// The root comments are latest first, the replies are oldest first
+#[framed]
+async fn page(
+    claims: Option<Claims>,
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageCommentReq>,
+) -> WebResult<PageCommentResp> {
+    let viewer_uid = claims.map(|x| x.uid()).unwrap_or_default();
+    ensure_song_visible(&state, req.song_id, viewer_uid).await?;
+
+    let (comments, total) = match req.parent_id {
+        None => (
+            SongCommentDao::page_roots_by_song_id(&state.sql_pool, req.song_id, viewer_uid, pagination.page_index, pagination.page_size).await?,
+            SongCommentDao::count_roots_by_song_id(&state.sql_pool, req.song_id, viewer_uid).await?,
+        ),
+        Some(parent_id) => {
+            let parent = SongCommentDao::get_by_id(&state.sql_pool, parent_id).await?
+                .filter(|x| x.song_id == req.song_id && x.parent_id.is_none())
+                .ok_or_else(|| common!("not_found", "Comment not found"))?;
+            if parent.user_id != viewer_uid && shadow_ban::is_banned(&state.sql_pool, parent.user_id, FEATURE_COMMENTS).await? {
+                err!("not_found", "Comment not found")
+            }
+            let reply_count = SongCommentDao::count_replies_batch(&state.sql_pool, &[parent.id], viewer_uid).await?
+                .get(&parent.id).copied().unwrap_or(0);
+            (
+                SongCommentDao::page_replies(&state.sql_pool, parent.id, viewer_uid, pagination.page_index, pagination.page_size).await?,
+                reply_count,
+            )
+        }
+    };
+
+    let root_ids = comments.iter().filter(|x| x.parent_id.is_none()).map(|x| x.id).collect_vec();
+    let reply_counts = SongCommentDao::count_replies_batch(&state.sql_pool, &root_ids, viewer_uid).await?;
+    let uids = comments.iter()
+        .flat_map(|x| [x.delete_time.is_none().then_some(x.user_id), x.reply_to_uid])
+        .flatten()
+        .collect_vec();
+    let profiles = user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &uids).await?;
+
+    let items = comments.into_iter()
+        .filter_map(|x| {
+            let deleted = x.delete_time.is_some();
+            let (author, content) = if deleted {
+                (PublicUserProfile::deleted(), DELETED_PLACEHOLDER.to_string())
+            } else {
+                (profiles.get(&x.user_id)?.clone(), x.content)
+            };
+            Some(CommentItem {
+                id: x.id,
+                song_id: x.song_id,
+                author,
+                parent_id: x.parent_id,
+                reply_to: x.reply_to_uid.and_then(|uid| profiles.get(&uid).cloned()),
+                content,
+                reply_count: reply_counts.get(&x.id).copied().unwrap_or(0),
+                create_time: x.create_time,
+                deleted,
+            })
+        })
+        .collect_vec();
+    ok!(pagination.into_page(items, total))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CommentIdReq {
+    pub comment_id: i64,
+}
+
+/// The author and the uploader of the song can delete the comment.
+///
+/// The comment is left as a placeholder, so the replies are kept in the thread, see [crate::service::tombstone].
+#[framed]
+async fn delete(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<CommentIdReq>,
+) -> WebResult<()> {
+    let comment = get_comment(&state, req.comment_id).await?;
+    if comment.user_id != claims.uid() {
+        let uploader_uid = SongDao::get_by_id(&state.sql_pool, comment.song_id).await?
+            .map(|x| x.uploader_uid);
+        if uploader_uid != Some(claims.uid()) {
+            err!("permission_denied", "You are not allowed to delete this comment")
+        }
+    }
+    if !SongCommentDao::soft_delete_by_id(&state.sql_pool, comment.id, Utc::now()).await? {
+        err!("not_found", "Comment not found")
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReportCommentReq {
+    pub comment_id: i64,
+    pub reason: String,
+}
+
+/// Reporting the same comment again is ignored
+#[framed]
+async fn report(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ReportCommentReq>,
+) -> WebResult<()> {
+    if req.reason.chars().count() > REPORT_REASON_MAX_CHARS {
+        err!("invalid_reason", "Reason must be {} characters or less", REPORT_REASON_MAX_CHARS)
+    }
+    let comment = get_comment(&state, req.comment_id).await?;
+    ensure_song_visible(&state, comment.song_id, claims.uid()).await?;
+    SongCommentDao::insert_report(&state.sql_pool, comment.id, claims.uid(), &req.reason).await?;
+    ok!(())
+}
+
+fn ensure_content_valid(state: &AppState, content: &str) -> Result<(), WebError<CommonError>> {
+    if content.is_blank() {
+        err!("comment_empty", "Comment cannot be empty")
+    }
+    if content.chars().count() > state.limits.comment_max_chars {
+        err!("comment_too_long", "Comment is too long")
+    }
+    Ok(())
+}
+
+/// The comment not deleted
+async fn get_comment(state: &AppState, comment_id: i64) -> Result<SongComment, WebError<CommonError>> {
+    match SongCommentDao::get_by_id(&state.sql_pool, comment_id).await? {
+        Some(x) if x.delete_time.is_none() => Ok(x),
+        _ => err!("not_found", "Comment not found"),
+    }
+}
+
+/// The private songs are only visible to the uploader
+async fn ensure_song_visible(state: &AppState, song_id: i64, uid: i64) -> Result<Song, WebError<CommonError>> {
+    match SongDao::get_by_id(&state.sql_pool, song_id).await? {
+        Some(song) if !song.is_private || song.uploader_uid == uid => Ok(song),
+        _ => err!("song_not_found", "Song not found"),
+    }
+}
+
+async fn insert_comment(
+    state: &AppState,
+    song_id: i64,
+    uid: i64,
+    parent_id: Option<i64>,
+    reply_to_uid: Option<i64>,
+    content: &str,
+) -> anyhow::Result<i64> {
+    let now = Utc::now();
+    let id = SongCommentDao::insert(&state.sql_pool, &SongComment {
+        id: 0,
+        song_id,
+        user_id: uid,
+        parent_id,
+        reply_to_uid,
+        content: content.to_string(),
+        create_time: now,
+        update_time: now,
+        delete_time: None,
+    }).await?;
+    Ok(id)
+}
+
+async fn notify_comment(state: &AppState, to_uid: i64, uid: i64, song_id: i64, comment_id: i64, parent_id: Option<i64>) {
+    // The recipient can't see the comment
+    match shadow_ban::is_banned(&state.sql_pool, uid, FEATURE_COMMENTS).await {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(e) => warn!("Failed to check the shadow ban of user {uid}: {:?}", e),
+    }
+    let data = json!({ "song_id": song_id, "comment_id": comment_id, "parent_id": parent_id });
+    notification::notify(state, &[to_uid], notification::TYPE_COMMENT, Some(uid), data).await;
+}
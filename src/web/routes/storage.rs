@@ -0,0 +1,48 @@
+use crate::service::storage_events;
+use crate::service::storage_events::{ReconcileSummary, StorageWebhookCfg};
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use crate::{err, ok};
+use async_backtrace::framed;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::Router;
+use bytes::Bytes;
+use tracing::warn;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/webhook", post(webhook))
+}
+
+/// Receive the object created/deleted notifications of the bucket, see [StorageWebhookCfg]
+#[framed]
+async fn webhook(
+    state: State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> WebResult<ReconcileSummary> {
+    let Some(cfg) = StorageWebhookCfg::load(&state.config)? else {
+        err!("not_found", "Storage webhook is not enabled")
+    };
+
+    let timestamp = headers.get("X-Storage-Timestamp")
+        .and_then(|x| x.to_str().ok())
+        .and_then(|x| x.parse::<i64>().ok());
+    let signature = headers.get("X-Storage-Signature")
+        .and_then(|x| x.to_str().ok());
+    let (Some(timestamp), Some(signature)) = (timestamp, signature) else {
+        err!("invalid_signature", "Missing signature")
+    };
+    if !cfg.verify(timestamp, signature, &body, chrono::Utc::now().timestamp()) {
+        warn!("Rejected storage webhook with invalid signature");
+        err!("invalid_signature", "Invalid signature")
+    }
+
+    let Ok(events) = storage_events::parse_events(&body) else {
+        err!("invalid_body", "Unrecognized event format")
+    };
+    let summary = storage_events::reconcile(state.file_host.as_ref(), &state.sql_pool, &events).await?;
+    ok!(summary)
+}
@@ -0,0 +1,58 @@
+use crate::ok;
+use crate::service::test_mode::{self, CapturedEmail};
+use crate::service::weekly_selection::{self, WeeklySelectionCfg};
+use crate::web::result::WebResult;
+use crate::web::state::AppState;
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Only mounted in the test mode
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/emails", get(emails))
+        // @since 260505
+        .route("/weekly_selection/publish", post(publish_weekly_selection))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TestEmailsReq {
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TestEmailsResp {
+    /// Latest first
+    pub emails: Vec<CapturedEmail>,
+}
+
+async fn emails(req: Query<TestEmailsReq>) -> WebResult<TestEmailsResp> {
+    ok!(TestEmailsResp { emails: test_mode::list_captured(&req.to) })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PublishWeeklySelectionReq {
+    /// The account publishing the playlist instead of `weekly_selection.official_uid`
+    pub official_uid: i64,
+    /// Any day of the week to publish
+    pub week: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PublishWeeklySelectionResp {
+    /// `None` if the week is published already or there are no songs
+    pub playlist_id: Option<i64>,
+}
+
+/// Publish the weekly selection of the week now instead of waiting for the job
+async fn publish_weekly_selection(
+    state: State<AppState>,
+    req: Json<PublishWeeklySelectionReq>,
+) -> WebResult<PublishWeeklySelectionResp> {
+    let cfg = WeeklySelectionCfg { official_uid: Some(req.official_uid), ..WeeklySelectionCfg::load(&state.config)? };
+    let playlist_id = weekly_selection::publish_week(&state, &cfg, weekly_selection::week_start_of(req.week)).await?;
+    ok!(PublishWeeklySelectionResp { playlist_id })
+}
@@ -1,5 +1,5 @@
 use std::io::Cursor;
-use anyhow::{anyhow, Context};
+use anyhow::anyhow;
 use async_backtrace::framed;
 use axum::extract::{DefaultBodyLimit, Multipart, State};
 use axum::Router;
@@ -8,7 +8,10 @@ use image::{ImageFormat, ImageReader};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use crate::{common, err, ok};
+use crate::file_hosting::UploadOptions;
+use crate::service::upload_cleanup;
 use crate::web::jwt::Claims;
+use crate::web::multipart::{self, FieldSpec};
 use crate::web::result::{WebResult};
 use crate::web::state::AppState;
 
@@ -26,17 +29,14 @@ pub struct UploadImageResp {
 async fn upload_image(
     claims: Claims,
     mut state: State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> WebResult<UploadImageResp> {
-    let data_field = multipart.next_field().await?.with_context(|| "No data field found")?;
-    let bytes = data_field.bytes().await?;
+    let mut fields = multipart::parse(multipart, &[FieldSpec::image("file", 8 * 1024 * 1024)]).await?;
+    let bytes = fields.take_required("file")?.bytes;
 
     let start_time = std::time::Instant::now();
 
     // Validate image
-    if bytes.len() > 8 * 1024 * 1024 {
-        err!("image_too_large", "Image size must be less than 8MB");
-    }
     let format = ImageReader::new(Cursor::new(bytes.clone()))
         .with_guessed_format()
         .map_err(|_| common!("invalid_image", "Invalid image"))?
@@ -53,10 +53,12 @@ async fn upload_image(
     // Upload image
     let sha1 = openssl::sha::sha1(&bytes);
     let filename = format!("images/cover/{}.{}", hex::encode(sha1), format_ext);
-    let result = state.file_host.upload(bytes, &filename).await?;
+    let upload_options = UploadOptions::hashed_image(format.to_mime_type());
+    let result = state.file_host.upload(bytes, &filename, &upload_options).await?;
     let temp_id = uuid::Uuid::new_v4().to_string();
     
-    let _: () = state.redis_conn.set_ex(build_image_temp_key(&temp_id), result.public_url, 3600).await?;
+    let _: () = state.redis_conn.set_ex(build_image_temp_key(&temp_id), result.public_url, upload_cleanup::TEMP_DATA_TTL_SECS).await?;
+    upload_cleanup::track_temp(&mut state.redis_conn, &result.key).await;
 
     // Add metrics
     let duration = start_time.elapsed();
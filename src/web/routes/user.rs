@@ -1,28 +1,54 @@
+use crate::db::song_tag::{ISongTagDao, SongTagDao};
 use crate::db::user::{IUserDao, UserDao};
+use crate::db::user_follow::{IUserFollowDao, UserFollowDao};
+use crate::db::user_oauth_identity::{IUserOAuthIdentityDao, UserOAuthIdentityDao};
+use crate::db::user_release_channel::{IUserReleaseChannelDao, UserReleaseChannel, UserReleaseChannelDao};
+use crate::db::version;
+use crate::db::user_storage_object;
+use crate::db::error::retry_on_conflict;
 use crate::db::CrudDao;
-use crate::search::user::UserDocument;
 use crate::service::connection_account::{GenerateChallengeError, VerifyChallengeError};
-use crate::service::upload::ResizeType;
+use crate::service::follow::FollowError;
+use crate::service::linked_account::{LinkError, UnlinkError};
+use crate::service::oauth::{self, OAuthError, OAuthState};
+use crate::service::image::{ImageCfg, ImageProcessOptions};
+use crate::service::storage_quota;
+use crate::service::support_link::{self, SupportLink, SupportLinkError};
+use crate::service::upload::UploadMetrics;
+use crate::service::preference::SavePreferencesError;
+use crate::service::recommend_v2;
+use crate::service::tombstone::DELETED_PLACEHOLDER;
+use crate::web::extractors::XRealIP;
+use crate::web::i18n::Lang;
 use crate::web::jwt::Claims;
-use crate::web::result::WebResult;
+use crate::web::limits::LimitsCfg;
+use crate::web::multipart::{self, FieldSpec};
+use crate::web::pagination::{Page, PageQuery, Pagination};
+use crate::web::result::{CommonError, WebError, WebResult};
+use crate::web::routes::auth::{OAuthAuthorizeReq, OAuthAuthorizeResp};
+use crate::web::routes::notification;
+use crate::web::routes::song::TagItem;
 use crate::web::state::AppState;
 use crate::{common, err, ok, search, service};
-use anyhow::Context;
 use async_backtrace::framed;
-use axum::extract::{DefaultBodyLimit, Multipart, Query};
+use axum::extract::{Multipart, Query};
 use axum::routing::post;
 use axum::{extract::State, routing::get, Json, Router};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
 
-pub fn router() -> Router<AppState> {
+pub fn router(limits: &LimitsCfg) -> Router<AppState> {
     Router::new()
         .route("/greet", get(greet))
         .route("/profile", get(get_profile))
         .route("/update_profile", post(update_profile))
-        .route("/set_avatar", post(set_avatar).layer(DefaultBodyLimit::max(10 * 1024 * 1024)))
+        .route("/set_avatar", post(set_avatar).layer(limits.image_body_limit()))
         .route("/search", get(search))
+        .route("/preferences", get(get_preferences).post(update_preferences))
         // @since 260402 @experimental
         .nest("/connection", Router::new()
             .route("/list", get(connection_list))
@@ -32,18 +58,48 @@ pub fn router() -> Router<AppState> {
             .route("/generate_challenge", post(connection_generate_challenge))
             .route("/verify_challenge", post(connection_verify_challenge)),
         )
+        // @since 260429
+        .route("/linked_accounts", get(linked_accounts))
+        // @since 260429
+        .route("/unlink_account", post(unlink_account))
+        // @since 260430
+        .route("/link_account/authorize", get(link_account_authorize))
+        // @since 260430
+        .route("/link_account", post(link_account))
+        // @since 260430
+        .route("/storage_usage", get(storage_usage))
+        // @since 260501
+        .route("/follow", post(follow))
+        // @since 260501
+        .route("/unfollow", post(unfollow))
+        // @since 260501
+        .route("/followers", get(followers))
+        // @since 260501
+        .route("/following", get(following))
+        // @since 260503
+        .route("/release_channel", get(get_release_channel))
+        // @since 260503
+        .route("/set_release_channel", post(set_release_channel))
+        // @since 260505
+        .route("/language", get(get_language))
+        // @since 260505
+        .route("/set_language", post(set_language))
+        // @since 260505
+        .route("/set_support_links", post(set_support_links))
+        // @since 260505
+        .nest("/notifications", notification::router())
 }
 
 async fn greet() -> WebResult<&'static str> {
     ok!("Hello from Hachimi World!")
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GetProfileReq {
     pub uid: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PublicUserProfile {
     pub uid: i64,
     pub username: String,
@@ -53,9 +109,48 @@ pub struct PublicUserProfile {
     pub is_banned: bool,
     /// @since 260402
     pub connected_accounts: Vec<ConnectedAccountItem>,
+    /// @since 260501
+    #[serde(default)]
+    pub follower_count: i64,
+    /// The links to support the user on the donation platforms
+    /// @since 260505
+    #[serde(default)]
+    pub support_links: Vec<SupportLink>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl PublicUserProfile {
+    /// The profile shown in place of a banned user, only the uid and the ban status are kept
+    pub fn tombstone(uid: i64) -> Self {
+        PublicUserProfile {
+            uid,
+            username: "Banned user".to_string(),
+            avatar_url: None,
+            bio: None,
+            gender: None,
+            is_banned: true,
+            connected_accounts: vec![],
+            follower_count: 0,
+            support_links: vec![],
+        }
+    }
+
+    /// The profile shown as the author of a deleted comment or post
+    pub fn deleted() -> Self {
+        PublicUserProfile {
+            uid: 0,
+            username: DELETED_PLACEHOLDER.to_string(),
+            avatar_url: None,
+            bio: None,
+            gender: None,
+            is_banned: false,
+            connected_accounts: vec![],
+            follower_count: 0,
+            support_links: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectedAccountItem {
     pub r#type: String,
     pub id: String,
@@ -72,11 +167,18 @@ async fn get_profile(
     } else {
         err!("not_found", "User not found")
     };
+    if user.is_banned {
+        ok!(PublicUserProfile::tombstone(user.id))
+    }
 
     let connected_accounts = service::connection_account::list_connections(
         &state.sql_pool, state.redis_conn.clone(),
         req.uid, true,
     ).await?;
+    let follower_count = UserFollowDao::count_followers(&state.sql_pool, user.id).await?;
+    let support_links = support_link::list_by_user_ids(&state.sql_pool, &[user.id]).await?
+        .remove(&user.id)
+        .unwrap_or_default();
 
     let mapped = PublicUserProfile {
         uid: user.id,
@@ -90,12 +192,14 @@ async fn get_profile(
             id: c.id,
             name: c.name,
         }).collect_vec(),
+        follower_count,
+        support_links,
     };
 
     ok!(mapped)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct UpdateProfileReq {
     pub username: String,
     pub bio: Option<String>,
@@ -111,10 +215,11 @@ async fn update_profile(
     if req.username.is_empty() {
         err!("invalid_username", "Username cannot be empty");
     }
-    if req.username.chars().count() > 10 {
+    if req.username.chars().count() > state.limits.username_max_chars {
         err!(
             "invalid_username",
-            "Username must be less than 10 characters"
+            "Username must be {} characters or less",
+            state.limits.username_max_chars
         );
     }
 
@@ -125,8 +230,8 @@ async fn update_profile(
     }
 
     if let Some(ref bio) = req.bio {
-        if bio.chars().count() > 300 {
-            err!("invalid_vio", "Bio must be 300 characters or less");
+        if bio.chars().count() > state.limits.bio_max_chars {
+            err!("invalid_vio", "Bio must be {} characters or less", state.limits.bio_max_chars);
         }
     }
 
@@ -137,23 +242,25 @@ async fn update_profile(
         err!("invalid_gender", "Gender must be 'null', 0, or 1");
     }
 
-    // Update user profile
-    let mut user = if let Some(x) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? {
-        x
-    } else {
+    // Update user profile, re-read and apply again if the user is updated concurrently
+    let user = retry_on_conflict(|| async {
+        let Some(mut user) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? else {
+            return Ok(None);
+        };
+        user.username = req.username.clone();
+        user.gender = req.gender;
+        user.bio = req.bio.clone();
+        user.update_time = Utc::now();
+        user.version = UserDao::update_by_id_checked(&state.sql_pool, &user).await?;
+        Ok(Some(user))
+    }).await?;
+    let Some(user) = user else {
         err!("not_found", "User not found")
     };
-    user.username = req.username.clone();
-    user.gender = req.gender;
-    user.bio = req.bio.clone();
-    user.update_time = Utc::now();
-    UserDao::update_by_id(&state.sql_pool, &user).await?;
-    search::user::update_user_document(&state.meilisearch, UserDocument {
-        id: user.id,
-        avatar_url: user.avatar_url,
-        name: user.username,
-        follower_count: 0,
-    }).await?;
+    // Banned users are kept out of the index
+    if !user.is_banned {
+        search::user::sync_user_document(&state.meilisearch, &state.sql_pool, &user).await?;
+    }
 
     ok!(())
 }
@@ -162,52 +269,52 @@ async fn update_profile(
 async fn set_avatar(
     claims: Claims,
     state: State<AppState>,
-    mut multipart: Multipart,
+    multipart: Multipart,
 ) -> WebResult<()> {
     // TODO[opt]: Limit access rate
-    let mut user = if let Some(x) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? {
-        x
-    } else {
+    if UserDao::get_by_id(&state.sql_pool, claims.uid()).await?.is_none() {
         err!("not_found", "User not found")
-    };
+    }
 
-    let data_field = multipart
-        .next_field()
-        .await?
-        .with_context(|| "No data field found")?;
-    let bytes = data_field.bytes().await?;
+    let mut upload_metrics = UploadMetrics::start("avatar");
+    let mut fields = upload_metrics.check(multipart::parse(multipart, &[FieldSpec::image("file", state.limits.image_max_bytes)]).await)?;
+    let bytes = upload_metrics.check(fields.take_required("file"))?.bytes;
+    upload_metrics.received(bytes.len());
+    upload_metrics.check(storage_quota::ensure_quota(&state.config, &state.sql_pool, claims.uid(), bytes.len()).await)?;
 
     let start = std::time::Instant::now();
 
-    // Validate image
-    if bytes.len() > 8 * 1024 * 1024 {
-        err!("image_too_large", "Image size must be less than 8MB");
-    }
-    let webp = service::upload::scale_down_to_webp(256, 256, bytes.clone(), ResizeType::Crop, 80f32)
-        .map_err(|_| common!("invalid_image", "The image might not be supported"))?;
-
-    // Upload image
-    let sha1 = openssl::sha::sha1(&webp);
-    let filename = format!("images/avatar/{}.webp", hex::encode(sha1));
+    // Process and upload image
+    let options = ImageProcessOptions::avatar(&ImageCfg::load(&state.config)?, &state.limits);
+    let result = upload_metrics.check(service::image::process_and_upload(state.file_host.as_ref(), "avatar", bytes, &options).await
+        .map_err(|e| e.into_web_error()))?;
+    storage_quota::track(&state.sql_pool, claims.uid(), user_storage_object::KIND_IMAGE, &result).await?;
+    upload_metrics.succeed();
 
     metrics::histogram!("avatar_processing_duration_secs").record(start.elapsed().as_secs_f64());
-    let result = state.file_host.upload(webp.into(), &filename).await?;
-
-    // Save url
-    user.avatar_url = Some(result.public_url);
-    UserDao::update_by_id(&state.sql_pool, &mut user).await?;
 
-    search::user::update_user_document(&state.meilisearch, UserDocument {
-        id: user.id,
-        avatar_url: user.avatar_url,
-        name: user.username,
-        follower_count: 0,
+    // Save url, re-read and apply again if the user is updated concurrently
+    let user = retry_on_conflict(|| async {
+        let Some(mut user) = UserDao::get_by_id(&state.sql_pool, claims.uid()).await? else {
+            return Ok(None);
+        };
+        user.avatar_url = Some(result.public_url.clone());
+        user.update_time = Utc::now();
+        user.version = UserDao::update_by_id_checked(&state.sql_pool, &user).await?;
+        Ok(Some(user))
     }).await?;
+    let Some(user) = user else {
+        err!("not_found", "User not found")
+    };
+
+    if !user.is_banned {
+        search::user::sync_user_document(&state.meilisearch, &state.sql_pool, &user).await?;
+    }
 
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchReq {
     pub q: String,
     pub page: u32,
@@ -217,7 +324,7 @@ pub struct SearchReq {
 
 fn default_search_size() -> u32 { 20 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResp {
     pub hits: Vec<PublicUserProfile>,
     pub query: String,
@@ -234,12 +341,14 @@ async fn search(
     if req.size > 50 { err!("invalid_size", "Size must be less than 50"); }
 
     let offset = req.page * req.size;
-    let result = search::user::search_users(
+    let Some(result) = search::guarded(search::user::search_users(
         &state.meilisearch,
         &req.q,
         Some(req.size as usize),
         Some(offset as usize),
-    ).await?;
+    )).await else {
+        err!("search_unavailable", "Search is temporarily unavailable, please try again later")
+    };
 
     let user_ids: Vec<i64> = result.hits.iter().map(|u| u.id).collect();
     let users = service::user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &user_ids).await?
@@ -256,12 +365,12 @@ async fn search(
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectionResp {
     pub items: Vec<ConnectionItem>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectionItem {
     pub r#type: String,
     pub id: String,
@@ -290,7 +399,7 @@ async fn connection_list(
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectionUnlinkReq {
     pub r#type: String,
 }
@@ -304,7 +413,7 @@ async fn connection_unlink(
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectionSetVisibilityReq {
     pub r#type: String,
     pub visible: bool,
@@ -318,13 +427,13 @@ async fn connection_set_visibility(
     ok!(())
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GenerateChallengeReq {
     pub r#type: String,
     pub provider_account_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct GenerateChallengeResp {
     pub challenge_id: String,
     pub challenge: String,
@@ -359,7 +468,7 @@ async fn connection_generate_challenge(
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct VerifyChallengeReq {
     pub challenge_id: String,
 }
@@ -389,7 +498,7 @@ async fn connection_verify_challenge(
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ConnectionSyncReq {
     r#type: String,
 }
@@ -401,4 +510,367 @@ async fn connection_sync(
 ) -> WebResult<()> {
     service::connection_account::sync(&state.sql_pool, claims.uid(), &req.r#type).await?;
     ok!(())
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PreferencesResp {
+    pub favorite_tags: Vec<TagItem>,
+    pub blocked_tags: Vec<TagItem>,
+    /// The favorite tags are inferred from the play history since the user never set them
+    pub inferred: bool,
+}
+
+#[framed]
+async fn get_preferences(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<PreferencesResp> {
+    let prefs = service::preference::get_effective(&state.sql_pool, claims.uid()).await?;
+    let ids = prefs.favorite_tag_ids.iter().chain(prefs.blocked_tag_ids.iter()).copied().collect_vec();
+    let tags: HashMap<i64, TagItem> = SongTagDao::list_by_ids(&state.sql_pool, &ids).await?
+        .into_iter()
+        .map(|x| (x.id, TagItem { id: x.id, name: x.name, description: x.description }))
+        .collect();
+    let to_items = |ids: &[i64]| ids.iter().filter_map(|x| tags.get(x).cloned()).collect_vec();
+    ok!(PreferencesResp {
+        favorite_tags: to_items(&prefs.favorite_tag_ids),
+        blocked_tags: to_items(&prefs.blocked_tag_ids),
+        inferred: prefs.inferred,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePreferencesReq {
+    pub favorite_tag_ids: Vec<i64>,
+    pub blocked_tag_ids: Vec<i64>,
+}
+
+/// Map the errors of saving the tag preferences, shared with the onboarding
+pub(crate) fn preferences_error(e: SavePreferencesError) -> WebError<CommonError> {
+    match e {
+        SavePreferencesError::TooManyTags => common!("too_many_tags", "{}", e),
+        SavePreferencesError::TagNotFound(_) => common!("tag_not_found", "{}", e),
+        SavePreferencesError::Conflict(_) => common!("tag_conflict", "{}", e),
+        SavePreferencesError::Sqlx(e) => e.into(),
+    }
+}
+
+#[framed]
+async fn update_preferences(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<UpdatePreferencesReq>,
+) -> WebResult<()> {
+    service::preference::save(&state.sql_pool, claims.uid(), &req.favorite_tag_ids, &req.blocked_tag_ids).await
+        .map_err(preferences_error)?;
+    // Re-roll today's recommendations with the new preferences
+    if let Err(e) = recommend_v2::evict_user_recommend(state.redis_conn.clone(), claims.uid()).await {
+        warn!("Failed to evict the recommendations of user {}: {:?}", claims.uid(), e);
+    }
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReleaseChannelData {
+    /// `stable`, `beta` or `nightly`, used by `/version/latest` if the client doesn't specify one
+    pub channel: String,
+}
+
+async fn get_release_channel(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<ReleaseChannelData> {
+    let channel = UserReleaseChannelDao::get_by_user_id(&state.sql_pool, claims.uid()).await?
+        .unwrap_or_else(|| version::CHANNEL_STABLE.to_string());
+    ok!(ReleaseChannelData { channel })
+}
+
+async fn set_release_channel(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<ReleaseChannelData>,
+) -> WebResult<()> {
+    if version::included_channels(&req.channel).is_none() {
+        err!("invalid_channel", "Invalid channel: {}", req.channel)
+    }
+    UserReleaseChannelDao::upsert(&state.sql_pool, &UserReleaseChannel {
+        user_id: claims.uid(),
+        channel: req.channel.clone(),
+        update_time: Utc::now(),
+    }).await?;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LanguageData {
+    /// The language of the emails, `zh-CN` or `en`. `None` to follow the default
+    pub language: Option<String>,
+}
+
+async fn get_language(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<LanguageData> {
+    let user = UserDao::get_by_id(&state.sql_pool, claims.uid()).await?
+        .ok_or_else(|| common!("user_not_found", "User not found"))?;
+    ok!(LanguageData { language: user.preferred_language })
+}
+
+async fn set_language(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<LanguageData>,
+) -> WebResult<()> {
+    let language = match req.language.as_deref() {
+        // Stored as the supported tag, e.g. `en` for `en-US`
+        Some(x) => match Lang::from_tag(x) {
+            Some(lang) => Some(lang.tag()),
+            None => err!("invalid_language", "Unsupported language: {}", x),
+        },
+        None => None,
+    };
+    UserDao::set_preferred_language(&state.sql_pool, claims.uid(), language).await?;
+    service::user::evict_preferred_lang(state.redis_conn.clone(), claims.uid()).await;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SetSupportLinksReq {
+    /// Replaces all the links, in the order shown on the profile
+    pub links: Vec<SupportLink>,
+}
+
+async fn set_support_links(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<SetSupportLinksReq>,
+) -> WebResult<()> {
+    match support_link::set_links(&state, claims.uid(), &req.links).await {
+        Ok(()) => ok!(()),
+        Err(e @ SupportLinkError::TooManyLinks) => err!("too_many_support_links", "{}", e),
+        Err(e @ (SupportLinkError::UnsupportedPlatform(_) | SupportLinkError::InvalidUrl(_) | SupportLinkError::DuplicatePlatform(_))) => {
+            err!("invalid_support_link", "{}", e)
+        }
+        Err(SupportLinkError::Sqlx(e)) => Err(e)?,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkedAccountsResp {
+    /// Whether the user can log in by the password, the last linked account can't be unlinked without it
+    pub has_password: bool,
+    pub items: Vec<LinkedAccountItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkedAccountItem {
+    pub provider: String,
+    pub provider_user_name: String,
+    pub provider_email: Option<String>,
+    pub link_time: DateTime<Utc>,
+    pub last_login_time: Option<DateTime<Utc>>,
+}
+
+/// The external accounts linked for logging in, not to be confused with the `/connection` accounts shown on the profile
+async fn linked_accounts(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<LinkedAccountsResp> {
+    let user = UserDao::get_by_id(&state.sql_pool, claims.uid()).await?
+        .ok_or_else(|| anyhow::anyhow!("User {} not found", claims.uid()))?;
+    let items = UserOAuthIdentityDao::list_by_user_id(&state.sql_pool, claims.uid()).await?
+        .into_iter()
+        .map(|x| LinkedAccountItem {
+            provider: x.provider,
+            provider_user_name: x.provider_user_name,
+            provider_email: x.provider_email,
+            link_time: x.create_time,
+            last_login_time: x.last_login_time,
+        })
+        .collect_vec();
+    ok!(LinkedAccountsResp {
+        has_password: service::linked_account::has_password(&user),
+        items,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnlinkAccountReq {
+    pub provider: String,
+}
+
+async fn unlink_account(
+    claims: Claims,
+    XRealIP(ip): XRealIP,
+    state: State<AppState>,
+    req: Json<UnlinkAccountReq>,
+) -> WebResult<()> {
+    match service::linked_account::unlink(&state.sql_pool, claims.uid(), &req.provider, Some(&ip)).await {
+        Ok(()) => ok!(()),
+        Err(x) => match x {
+            UnlinkError::NotLinked(_) => err!("not_linked", "{}", x.to_string()),
+            UnlinkError::LastLoginMethod => err!("last_login_method", "{}", x.to_string()),
+            UnlinkError::Sqlx(e) => Err(e)?,
+        }
+    }
+}
+
+/// The authorize URL for linking an account to the current user, the page redirected to should call `/link_account`
+async fn link_account_authorize(
+    claims: Claims,
+    mut state: State<AppState>,
+    req: Query<OAuthAuthorizeReq>,
+) -> WebResult<OAuthAuthorizeResp> {
+    let Some((provider, cfg)) = oauth::load_provider(&state.config, &req.provider)? else {
+        err!("oauth_disabled", "Logging in by {} is not available", req.provider)
+    };
+    let oauth_state = oauth::create_state(&mut state.redis_conn, &OAuthState {
+        provider,
+        uid: Some(claims.uid()),
+    }).await?;
+    ok!(OAuthAuthorizeResp {
+        url: oauth::authorize_url(provider, &cfg, &oauth_state),
+        state: oauth_state,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LinkAccountReq {
+    pub code: String,
+    pub state: String,
+}
+
+async fn link_account(
+    claims: Claims,
+    XRealIP(ip): XRealIP,
+    mut state: State<AppState>,
+    req: Json<LinkAccountReq>,
+) -> WebResult<()> {
+    let Some(oauth_state) = oauth::consume_state(&mut state.redis_conn, &req.state).await?
+        .filter(|x| x.uid == Some(claims.uid())) else {
+        err!("invalid_oauth_state", "The authorization is expired, please try again")
+    };
+    let Some((provider, cfg)) = oauth::load_provider(&state.config, oauth_state.provider.name())? else {
+        err!("oauth_disabled", "Logging in by {} is not available", oauth_state.provider.name())
+    };
+    let profile = match oauth::fetch_profile(provider, &cfg, &req.code).await {
+        Ok(x) => x,
+        Err(e @ OAuthError::InvalidCode) => err!("invalid_oauth_code", "{}", e.to_string()),
+        Err(e @ OAuthError::ProviderApi(_)) => {
+            warn!("Failed to request {}: {:?}", provider.name(), e);
+            err!("provider_api_error", "{}", e.to_string())
+        }
+        Err(e) => Err(e)?,
+    };
+    match oauth::link(&state.sql_pool, claims.uid(), provider, &profile, Some(&ip)).await {
+        Ok(_) => ok!(()),
+        Err(LinkError::Sqlx(e)) => Err(e)?,
+        Err(e) => err!("already_linked", "{}", e.to_string()),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StorageUsageResp {
+    pub audio_bytes: i64,
+    pub image_bytes: i64,
+    pub used_bytes: i64,
+    /// `None` if unlimited
+    pub quota_bytes: Option<i64>,
+    pub tier: Option<String>,
+}
+
+/// The bytes of the uploaded files, the uploads exceeding the quota are rejected with `storage_quota_exceeded`
+async fn storage_usage(
+    claims: Claims,
+    state: State<AppState>,
+) -> WebResult<StorageUsageResp> {
+    let status = storage_quota::get_status(&state.config, &state.sql_pool, claims.uid()).await?;
+    ok!(StorageUsageResp {
+        audio_bytes: status.usage.audio_bytes,
+        image_bytes: status.usage.image_bytes,
+        used_bytes: status.usage.total_bytes(),
+        quota_bytes: status.quota_bytes,
+        tier: status.tier,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FollowReq {
+    pub uid: i64,
+}
+
+async fn follow(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<FollowReq>,
+) -> WebResult<()> {
+    match service::follow::follow(&state, claims.uid(), req.uid).await {
+        Ok(_) => ok!(()),
+        Err(e @ FollowError::FollowSelf) => err!("follow_self", "{}", e.to_string()),
+        Err(e @ FollowError::UserNotFound(_)) => err!("user_not_found", "{}", e.to_string()),
+        Err(FollowError::Sqlx(e)) => Err(e)?,
+    }
+}
+
+async fn unfollow(
+    claims: Claims,
+    state: State<AppState>,
+    req: Json<FollowReq>,
+) -> WebResult<()> {
+    service::follow::unfollow(&state, claims.uid(), req.uid).await?;
+    ok!(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageFollowReq {
+    pub uid: i64,
+}
+
+/// The whole query of `/user/followers` and `/user/following`, [PageFollowReq] along with the pagination
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PageFollowQuery {
+    #[serde(flatten)]
+    pub req: PageFollowReq,
+    #[serde(flatten)]
+    pub page: PageQuery,
+}
+
+pub type PageFollowResp = Page<FollowItem>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FollowItem {
+    pub user: PublicUserProfile,
+    pub follow_time: DateTime<Utc>,
+}
+
+/// The users following `uid`, latest first
+async fn followers(
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageFollowReq>,
+) -> WebResult<PageFollowResp> {
+    let total = UserFollowDao::count_followers(&state.sql_pool, req.uid).await?;
+    let follows = UserFollowDao::page_followers(&state.sql_pool, req.uid, pagination.page_index, pagination.page_size).await?;
+    let items = to_follow_items(&state, follows.into_iter().map(|x| (x.follower_id, x.create_time)).collect()).await?;
+    ok!(pagination.into_page(items, total))
+}
+
+/// The users followed by `uid`, latest first
+async fn following(
+    state: State<AppState>,
+    pagination: Pagination<50>,
+    req: Query<PageFollowReq>,
+) -> WebResult<PageFollowResp> {
+    let total = UserFollowDao::count_following(&state.sql_pool, req.uid).await?;
+    let follows = UserFollowDao::page_following(&state.sql_pool, req.uid, pagination.page_index, pagination.page_size).await?;
+    let items = to_follow_items(&state, follows.into_iter().map(|x| (x.followee_id, x.create_time)).collect()).await?;
+    ok!(pagination.into_page(items, total))
+}
+
+async fn to_follow_items(state: &AppState, follows: Vec<(i64, DateTime<Utc>)>) -> anyhow::Result<Vec<FollowItem>> {
+    let uids = follows.iter().map(|(uid, _)| *uid).collect_vec();
+    let mut profiles = service::user::get_public_profile(state.redis_conn.clone(), &state.sql_pool, &uids).await?;
+    let items = follows.into_iter()
+        .filter_map(|(uid, follow_time)| profiles.remove(&uid).map(|user| FollowItem { user, follow_time }))
+        .collect_vec();
+    Ok(items)
+}
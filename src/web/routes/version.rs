@@ -1,7 +1,9 @@
-use crate::db::version::{Version, VersionDao};
+use crate::db::user_release_channel::{IUserReleaseChannelDao, UserReleaseChannelDao};
+use crate::db::version::{self, Version, VersionDao};
 use crate::db::CrudDao;
-use crate::web::jwt::PublishVersionClaims;
-use crate::web::result::WebResult;
+use crate::util::redis_health;
+use crate::web::jwt::{Claims, PublishVersionClaims};
+use crate::web::result::{CommonError, WebError, WebResult};
 use crate::web::state::AppState;
 use crate::{err, ok};
 use axum::extract::{Query, State};
@@ -10,20 +12,30 @@ use axum::{Json, Router};
 use chrono::{DateTime, Utc};
 use redis::aio::ConnectionManager;
 use redis::{AsyncTypedCommands, HashFieldExpirationOptions, SetExpiry};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::time::Duration;
+use tracing::warn;
+
+const CACHE_KEY: &str = "version:latest";
+const CACHE_TTL_SECS: u64 = 60 * 60;
+const NEGATIVE_CACHE_TTL_SECS: u64 = 60;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/server", get(server))
         .route("/latest", get(latest_version))
         .route("/latest_batch", post(latest_version_batch))
+        // @since 260503
+        .route("/latest_batch_v2", post(latest_version_batch_v2))
         .route("/page", get(page_versions))
         .route("/publish", post(publish_version))
         .route("/delete", post(delete_version))
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ServerVersion {
     pub version: i32,
     pub min_version: i32,
@@ -37,71 +49,162 @@ async fn server() -> WebResult<ServerVersion> {
 }
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LatestVersionReq {
     pub variant: String,
+    /// `stable`, `beta` or `nightly`, the channel opted in by the user (or `stable`) if absent.
+    /// The releases of the more stable channels are included.
+    /// @since 260503
+    #[serde(default)]
+    pub channel: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LatestVersionResp {
     pub version_name: String,
     pub version_number: i32,
     pub changelog: String,
     pub variant: String,
+    /// @since 260503
+    #[serde(default)]
+    pub channel: String,
     pub url: String,
     pub release_time: DateTime<Utc>,
 }
 
-async fn latest_version(state: State<AppState>, req: Query<LatestVersionReq>) -> WebResult<Option<LatestVersionResp>> {
-    let version = get_from_cache_or_db(&state.sql_pool, state.redis_conn.clone(), &req.variant).await?;
-    if let Some(version) = version {
-        let result = LatestVersionResp {
+impl From<Version> for LatestVersionResp {
+    fn from(version: Version) -> Self {
+        LatestVersionResp {
             variant: version.variant,
+            channel: version.channel,
             version_name: version.version_name,
             version_number: version.version_number,
             changelog: version.changelog,
             url: version.url,
             release_time: version.release_time,
-        };
-        ok!(Some(result))
-    } else {
-        ok!(None)
+        }
+    }
+}
+
+/// The requested channel, or the one opted in by the user
+async fn resolve_channel(
+    sql_pool: &PgPool,
+    claims: Option<&Claims>,
+    channel: Option<&str>,
+) -> Result<&'static str, WebError<CommonError>> {
+    let channel = match (channel, claims) {
+        (Some(x), _) => x.to_string(),
+        (None, Some(claims)) => UserReleaseChannelDao::get_by_user_id(sql_pool, claims.uid()).await?
+            .unwrap_or_else(|| version::CHANNEL_STABLE.to_string()),
+        (None, None) => version::CHANNEL_STABLE.to_string(),
+    };
+    match version::CHANNELS.iter().find(|x| **x == channel) {
+        Some(x) => Ok(x),
+        None => err!("invalid_channel", "Invalid channel: {}", channel),
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+async fn latest_version(
+    claims: Option<Claims>,
+    state: State<AppState>,
+    req: Query<LatestVersionReq>,
+) -> WebResult<Option<LatestVersionResp>> {
+    let channel = resolve_channel(&state.sql_pool, claims.as_ref(), req.channel.as_deref()).await?;
+    let version = get_from_cache_or_db(&state.sql_pool, state.redis_conn.clone(), &req.variant, channel).await?;
+    ok!(version.map(Into::into))
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct LatestVersionBatchReq {
     pub variants: Vec<String>,
+    /// See [LatestVersionReq::channel]
+    /// @since 260503
+    #[serde(default)]
+    pub channel: Option<String>,
 }
-async fn latest_version_batch(state: State<AppState>, req: Json<LatestVersionBatchReq>) -> WebResult<Vec<LatestVersionResp>> {
+
+/// The latest versions of the variants with a release, a variant failed to look up is left out the same as
+/// the variants without a release, see `/version/latest_batch_v2` to tell them apart
+async fn latest_version_batch(
+    claims: Option<Claims>,
+    state: State<AppState>,
+    req: Json<LatestVersionBatchReq>,
+) -> WebResult<Vec<LatestVersionResp>> {
     if req.variants.len() > 16 {
         err!("bad_request", "Variants must be less than 16")
     }
+    let channel = resolve_channel(&state.sql_pool, claims.as_ref(), req.channel.as_deref()).await?;
+    let versions = futures::future::join_all(req.variants.iter().map(|x| {
+        get_with_retry(&state.sql_pool, state.redis_conn.clone(), x, channel)
+    })).await;
     let mut result = vec![];
-    for x in req.variants.iter() {
-        let version = get_from_cache_or_db(&state.sql_pool, state.redis_conn.clone(), x).await?;
-        if let Some(version) = version {
-            result.push(LatestVersionResp {
-                variant: version.variant,
-                version_name: version.version_name,
-                version_number: version.version_number,
-                changelog: version.changelog,
-                url: version.url,
-                release_time: version.release_time,
-            })
+    for (variant, version) in req.variants.iter().zip(versions) {
+        match version {
+            Ok(Some(version)) => result.push(version.into()),
+            Ok(None) => {}
+            Err(e) => warn!("Failed to get the latest version of {}: {:?}", variant, e),
         }
     }
     ok!(result)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LatestVersionBatchItem {
+    pub variant: String,
+    /// `None` if there is no release of the variant, or it failed to look up
+    pub version: Option<LatestVersionResp>,
+    /// The error code if it failed to look up, the client should keep the current version of the variant
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LatestVersionBatchResp {
+    /// In the order of the requested variants
+    pub results: Vec<LatestVersionBatchItem>,
+}
+
+/// Like `/version/latest_batch`, but a failed variant is reported in its item instead of failing the request
+async fn latest_version_batch_v2(
+    claims: Option<Claims>,
+    state: State<AppState>,
+    req: Json<LatestVersionBatchReq>,
+) -> WebResult<LatestVersionBatchResp> {
+    if req.variants.len() > 16 {
+        err!("bad_request", "Variants must be less than 16")
+    }
+    let channel = resolve_channel(&state.sql_pool, claims.as_ref(), req.channel.as_deref()).await?;
+    let results = futures::future::join_all(req.variants.iter().map(async |x| {
+        match get_with_retry(&state.sql_pool, state.redis_conn.clone(), x, channel).await {
+            Ok(version) => LatestVersionBatchItem {
+                variant: x.clone(),
+                version: version.map(Into::into),
+                error: None,
+            },
+            Err(e) => {
+                warn!("Failed to get the latest version of {}: {:?}", x, e);
+                LatestVersionBatchItem {
+                    variant: x.clone(),
+                    version: None,
+                    error: Some("internal_error".to_string()),
+                }
+            }
+        }
+    })).await;
+    ok!(LatestVersionBatchResp { results })
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PageVersionsReq {
     pub variant: Option<String>,
+    /// Only the releases of the channel if set
+    /// @since 260503
+    #[serde(default)]
+    pub channel: Option<String>,
     pub page_index: i64,
     pub page_size: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PageVersionsResp {
     pub data: Vec<LatestVersionResp>,
     pub page_index: i64,
@@ -113,27 +216,22 @@ async fn page_versions(state: State<AppState>, req: Query<PageVersionsReq>) -> W
     let page_index = req.page_index.max(0);
     let page_size = req.page_size.clamp(1, 50);
 
+    let channel = req.channel.as_deref();
     let (versions, total) = if let Some(variant) = &req.variant {
-        let versions = VersionDao::page_by_variant(&state.sql_pool, variant, page_index, page_size).await?;
-        let total = VersionDao::count(&state.sql_pool, Some(variant.as_str())).await?;
+        let versions = VersionDao::page_by_variant(&state.sql_pool, variant, channel, page_index, page_size).await?;
+        let total = VersionDao::count(&state.sql_pool, Some(variant.as_str()), channel).await?;
+        (versions, total)
+    } else if let Some(channel) = channel {
+        let versions = VersionDao::page_by_channel(&state.sql_pool, channel, page_index, page_size).await?;
+        let total = VersionDao::count(&state.sql_pool, None, Some(channel)).await?;
         (versions, total)
     } else {
         let versions = VersionDao::page(&state.sql_pool, page_index, page_size).await?;
-        let total = VersionDao::count(&state.sql_pool, None).await?;
+        let total = VersionDao::count(&state.sql_pool, None, None).await?;
         (versions, total)
     };
 
-    let data = versions
-        .into_iter()
-        .map(|v| LatestVersionResp {
-            variant: v.variant,
-            version_name: v.version_name,
-            version_number: v.version_number,
-            changelog: v.changelog,
-            url: v.url,
-            release_time: v.release_time,
-        })
-        .collect();
+    let data = versions.into_iter().map(Into::into).collect();
 
     ok!(PageVersionsResp {
         data,
@@ -143,17 +241,21 @@ async fn page_versions(state: State<AppState>, req: Query<PageVersionsReq>) -> W
     })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PublishVersionReq {
     pub version_name: String,
     pub version_number: i32,
     pub changelog: String,
     pub variant: String,
+    /// The channel to release in, `stable` if absent
+    /// @since 260503
+    #[serde(default)]
+    pub channel: Option<String>,
     pub url: String,
     pub release_time: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct PublishVersionResp {
     pub id: i64,
 }
@@ -163,12 +265,17 @@ async fn publish_version(
     state: State<AppState>,
     req: Json<PublishVersionReq>,
 ) -> WebResult<PublishVersionResp> {
+    let channel = req.channel.as_deref().unwrap_or(version::CHANNEL_STABLE);
+    if version::included_channels(channel).is_none() {
+        err!("invalid_channel", "Invalid channel: {}", channel)
+    }
     let entity = Version {
         id: 0,
         version_name: req.version_name.clone(),
         version_number: req.version_number,
         changelog: req.changelog.clone(),
         variant: req.variant.clone(),
+        channel: channel.to_string(),
         url: req.url.clone(),
         release_time: req.release_time,
         create_time: Utc::now(),
@@ -180,7 +287,7 @@ async fn publish_version(
     ok!(PublishVersionResp { id })
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DeleteVersionReq {
     pub id: i64,
 }
@@ -195,28 +302,51 @@ async fn delete_version(
     ok!(())
 }
 
+/// Look up the latest version, retrying once after a short delay on errors
+async fn get_with_retry(
+    sql_pool: &PgPool,
+    redis: ConnectionManager,
+    variant: &str,
+    channel: &str,
+) -> anyhow::Result<Option<Version>> {
+    match get_from_cache_or_db(sql_pool, redis.clone(), variant, channel).await {
+        Ok(x) => Ok(x),
+        Err(e) => {
+            warn!("Failed to get the latest version of {}, retrying: {:?}", variant, e);
+            tokio::time::sleep(RETRY_DELAY).await;
+            get_from_cache_or_db(sql_pool, redis, variant, channel).await
+        }
+    }
+}
+
+/// The cache is best-effort, it's read from the database if Redis is unavailable
 async fn get_from_cache_or_db(
     sql_pool: &PgPool,
-    mut redis: ConnectionManager,
+    redis: ConnectionManager,
     variant: &str,
+    channel: &str,
 ) -> anyhow::Result<Option<Version>> {
-    let data = redis.hget("version:latest", variant).await?;
-    let result = if let Some(data) = &data &&
+    let field = format!("{}:{}", variant, channel);
+    let data = redis_health::cached(redis.clone().hget(CACHE_KEY, &field)).await.flatten();
+    if let Some(data) = &data &&
         let Ok(v) = serde_json::from_str::<Option<Version>>(data) {
-        v
-    } else {
-        let version = VersionDao::get_latest_version(sql_pool, &variant, Utc::now()).await?;
-        redis.hset_ex(
-            "version:latest",
-            &HashFieldExpirationOptions::default().set_expiration(SetExpiry::EX(60 * 60)),
-            &[(variant, serde_json::to_string(&version)?)]
-        ).await?;
-        version
-    };
-    Ok(result)
+        return Ok(v);
+    }
+
+    let channels = version::included_channels(channel).unwrap_or(&[version::CHANNEL_STABLE]);
+    let version = VersionDao::get_latest_version(sql_pool, variant, channels, Utc::now()).await?;
+    // The missing variants are cached shortly, so they don't hit the database every time
+    let ttl = if version.is_some() { CACHE_TTL_SECS } else { NEGATIVE_CACHE_TTL_SECS };
+    let data = serde_json::to_string(&version)?;
+    redis_health::cached(redis.clone().hset_ex(
+        CACHE_KEY,
+        &HashFieldExpirationOptions::default().set_expiration(SetExpiry::EX(ttl)),
+        &[(field, data)]
+    )).await;
+    Ok(version)
 }
 
 async fn clear_cache(mut redis: ConnectionManager) -> anyhow::Result<()> {
-    redis.del("version:latest").await?;
+    redis.del(CACHE_KEY).await?;
     Ok(())
 }
\ No newline at end of file
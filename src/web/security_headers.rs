@@ -0,0 +1,158 @@
+use crate::config::Config;
+use axum::extract::Request;
+use axum::http::{header, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::info;
+
+static SECURITY_HEADERS: OnceLock<SecurityHeadersCfg> = OnceLock::new();
+
+/// Optional `security_headers` section of the config file.
+///
+/// The HTML pages can't be framed by default, list the client origins embedding them (e.g. the captcha page in a
+/// web view of the desktop client) in `frame_ancestors`.
+///
+/// ```yaml
+/// security_headers:
+///   frame_ancestors:
+///     - https://hachimi.world
+///     - tauri://localhost
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityHeadersCfg {
+    #[serde(default)]
+    pub frame_ancestors: Vec<String>,
+}
+
+impl SecurityHeadersCfg {
+    pub fn load(config: &Config) -> anyhow::Result<Option<Self>> {
+        if config.get("security_headers")?.is_some() {
+            Ok(Some(config.get_and_parse("security_headers")?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The extra sources allowed by the CSP of an HTML response, inserted into the response extensions by the handler
+#[derive(Debug, Clone, Default)]
+pub struct CspSources {
+    /// The nonce of the inline scripts
+    pub nonce: Option<String>,
+    pub script_src: Vec<String>,
+    pub connect_src: Vec<String>,
+    pub frame_src: Vec<String>,
+}
+
+impl CspSources {
+    /// With a random nonce for the inline scripts
+    pub fn with_nonce() -> Self {
+        CspSources {
+            nonce: Some(hex::encode(rand::random::<[u8; 16]>())),
+            ..Default::default()
+        }
+    }
+}
+
+fn join(defaults: &[&str], extra: &[String]) -> String {
+    let all = defaults.iter().copied().chain(extra.iter().map(|x| x.as_str())).collect::<Vec<_>>();
+    if all.is_empty() { "'none'".to_string() } else { all.join(" ") }
+}
+
+pub fn render_csp(sources: &CspSources, frame_ancestors: &[String]) -> String {
+    let mut script_src = sources.script_src.clone();
+    if let Some(nonce) = &sources.nonce {
+        script_src.insert(0, format!("'nonce-{nonce}'"));
+    }
+    [
+        "default-src 'self'".to_string(),
+        format!("script-src {}", join(&["'self'"], &script_src)),
+        // The inline styles are written by us, and harmless without the scripts
+        "style-src 'self' 'unsafe-inline'".to_string(),
+        "img-src 'self' data: https:".to_string(),
+        format!("connect-src {}", join(&["'self'"], &sources.connect_src)),
+        format!("frame-src {}", join(&[], &sources.frame_src)),
+        "object-src 'none'".to_string(),
+        "base-uri 'none'".to_string(),
+        "form-action 'self'".to_string(),
+        format!("frame-ancestors {}", join(&[], frame_ancestors)),
+    ].join("; ")
+}
+
+/// Should be called once before serving, the defaults are used without it
+pub fn initialize(cfg: Option<SecurityHeadersCfg>) -> anyhow::Result<()> {
+    let cfg = cfg.unwrap_or_default();
+    info!("HTML pages can be framed by {:?}", cfg.frame_ancestors);
+    if SECURITY_HEADERS.set(cfg).is_err() {
+        anyhow::bail!("Security headers are already initialized");
+    }
+    Ok(())
+}
+
+/// Set the CSP, `X-Frame-Options` and `Referrer-Policy` of the HTML responses
+pub async fn set_security_headers(req: Request, next: Next) -> Response {
+    let mut resp = next.run(req).await;
+    let is_html = resp.headers().get(header::CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("text/html"));
+    if !is_html {
+        return resp;
+    }
+
+    let frame_ancestors = SECURITY_HEADERS.get().map(|x| x.frame_ancestors.as_slice()).unwrap_or_default();
+    let sources = resp.extensions_mut().remove::<CspSources>().unwrap_or_default();
+    let headers = resp.headers_mut();
+    if let Ok(csp) = HeaderValue::from_str(&render_csp(&sources, frame_ancestors)) {
+        headers.insert(header::CONTENT_SECURITY_POLICY, csp);
+    }
+    // Superseded by `frame-ancestors`, only for the old browsers which can't be told the allowed origins
+    if frame_ancestors.is_empty() {
+        headers.insert(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    }
+    // The pages may carry keys in the query, e.g. the captcha key
+    headers.insert(header::REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    headers.insert(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    resp
+}
+
+/// Escape the value as a JavaScript string literal safe to be embedded in a `<script>` element
+pub fn js_string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_csp() {
+        let csp = render_csp(&CspSources::default(), &[]);
+        assert!(csp.contains("script-src 'self';"));
+        assert!(csp.contains("frame-src 'none';"));
+        assert!(csp.ends_with("frame-ancestors 'none'"));
+
+        let sources = CspSources {
+            nonce: Some("abc".to_string()),
+            script_src: vec!["https://challenges.cloudflare.com".to_string()],
+            ..Default::default()
+        };
+        let csp = render_csp(&sources, &["https://hachimi.world".to_string()]);
+        assert!(csp.contains("script-src 'self' 'nonce-abc' https://challenges.cloudflare.com;"));
+        assert!(csp.ends_with("frame-ancestors https://hachimi.world"));
+    }
+
+    #[test]
+    fn test_js_string_literal() {
+        assert_eq!(r#""https://example.com/api""#, js_string_literal("https://example.com/api"));
+        let escaped = js_string_literal("\"</script><script>alert(1)</script>");
+        assert!(!escaped.contains("</script>"));
+        assert!(escaped.starts_with(r#""\"\u003c/script"#));
+    }
+}
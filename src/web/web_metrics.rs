@@ -1,24 +1,87 @@
 use std::future::ready;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
 use std::time::Instant;
-use axum::extract::{MatchedPath, Request};
+use anyhow::Context;
+use axum::extract::{ConnectInfo, MatchedPath, Request, State};
+use axum::http::{header, StatusCode};
 use axum::middleware::Next;
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 use axum::Router;
 use axum::routing::get;
 use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
-use tokio::net::ToSocketAddrs;
 use tokio_util::sync::CancellationToken;
-use tracing::info;
+use tracing::{info, warn};
+use crate::web::client_ip::IpRange;
+use crate::web::ServerCfg;
+
+/// Who can scrape the metrics, see the `metrics_*` fields of [ServerCfg]
+#[derive(Debug, Clone, Default)]
+pub struct MetricsAccess {
+    token: Option<String>,
+    /// Any IP is allowed if empty
+    allow_ips: Vec<IpRange>,
+}
+
+impl MetricsAccess {
+    pub fn from_cfg(cfg: &ServerCfg) -> anyhow::Result<Self> {
+        let allow_ips = cfg.metrics_allow_ips.iter()
+            .map(|x| IpRange::parse(x).with_context(|| format!("Invalid metrics_allow_ips entry: {}", x)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(MetricsAccess {
+            token: cfg.metrics_token.clone().filter(|x| !x.is_empty()),
+            allow_ips,
+        })
+    }
+
+    fn allows_ip(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.allow_ips.is_empty() || self.allow_ips.iter().any(|x| x.contains(ip))
+    }
+
+    fn allows_token(&self, authorization: Option<&str>) -> bool {
+        let Some(ref token) = self.token else { return true };
+        match authorization.and_then(|x| x.strip_prefix("Bearer ")) {
+            Some(x) => x.len() == token.len() && openssl::memcmp::eq(x.as_bytes(), token.as_bytes()),
+            None => false,
+        }
+    }
+}
+
+/// Replace the host of `listen` with the loopback address if `localhost_only`
+fn resolve_listen(listen: &str, localhost_only: bool) -> anyhow::Result<String> {
+    if !localhost_only {
+        return Ok(listen.to_string());
+    }
+    let (host, port) = listen.rsplit_once(':')
+        .with_context(|| format!("Invalid metrics_listen: {}", listen))?;
+    let loopback = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(x) if x.is_loopback() => return Ok(listen.to_string()),
+        Ok(IpAddr::V6(_)) => "[::1]".to_string(),
+        _ => Ipv4Addr::LOCALHOST.to_string(),
+    };
+    Ok(format!("{}:{}", loopback, port))
+}
 
 pub async fn start_metrics_server(
-    addr: impl ToSocketAddrs,
+    listen: &str,
+    localhost_only: bool,
+    access: MetricsAccess,
     cancel_token: CancellationToken,
 ) -> anyhow::Result<()> {
-    let app = metrics_app();
+    let addr = resolve_listen(listen, localhost_only)?;
+    if addr != listen {
+        info!("Binding the metrics server on {} instead of {}, set metrics_localhost_only to false to expose it", addr, listen);
+    }
+    if !localhost_only && access.token.is_none() && access.allow_ips.is_empty() {
+        warn!("The metrics server is exposed without a token or an IP allowlist");
+    }
+    let app = metrics_app()
+        .layer(axum::middleware::from_fn_with_state(Arc::new(access), check_access));
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("Metrics server listening on {}", listener.local_addr()?);
 
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(async move {
             cancel_token.cancelled().await;
             info!("Shutting down metrics server...");
@@ -32,6 +95,22 @@ fn metrics_app() -> Router {
     Router::new().route("/metrics", get(move || ready(recorder_handle.render())))
 }
 
+async fn check_access(
+    State(access): State<Arc<MetricsAccess>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !access.allows_ip(peer.ip()) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let authorization = req.headers().get(header::AUTHORIZATION).and_then(|x| x.to_str().ok());
+    if !access.allows_token(authorization) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(req).await
+}
+
 fn setup_metrics_recorder() -> PrometheusHandle {
     const EXPONENTIAL_SECONDS: &[f64] = &[
         0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
@@ -72,3 +151,32 @@ pub async fn track_metrics(req: Request, next: Next) -> impl IntoResponse {
 
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access() {
+        let access = MetricsAccess {
+            token: Some("secret".to_string()),
+            allow_ips: vec![IpRange::parse("127.0.0.1").unwrap()],
+        };
+        // IPv4-mapped addresses of the dual-stack listeners
+        assert!(access.allows_ip("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(!access.allows_ip("10.0.0.1".parse().unwrap()));
+        assert!(access.allows_token(Some("Bearer secret")));
+        assert!(!access.allows_token(Some("Bearer wrong")));
+        assert!(!access.allows_token(None));
+        assert!(MetricsAccess::default().allows_token(None));
+    }
+
+    #[test]
+    fn test_resolve_listen() {
+        assert_eq!("0.0.0.0:3001", resolve_listen("0.0.0.0:3001", false).unwrap());
+        assert_eq!("127.0.0.1:3001", resolve_listen("0.0.0.0:3001", true).unwrap());
+        assert_eq!("[::1]:3001", resolve_listen("[::]:3001", true).unwrap());
+        assert_eq!("[::1]:3001", resolve_listen("[::1]:3001", true).unwrap());
+        assert_eq!("127.0.0.1:3001", resolve_listen("localhost:3001", true).unwrap());
+    }
+}
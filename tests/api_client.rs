@@ -0,0 +1,26 @@
+mod common;
+
+use common::{auth, with_test_environment};
+use hachimi_world_api::endpoints::{Bootstrap, UserProfile, VersionServer};
+use hachimi_world_api::{Client, Error, GetProfileReq};
+use std::env;
+
+/// The generated client in `clients/rust` works against the server without sharing its types
+#[tokio::test]
+async fn test_generated_client() {
+    with_test_environment(|mut env| async move {
+        let user = auth::with_new_random_test_user(&mut env).await;
+        let mut client = Client::new(env::var("TEST_HTTP_BASE_URL").unwrap());
+
+        let bootstrap = client.call::<Bootstrap>(&()).await.unwrap();
+        assert!(bootstrap.limits.image_max_bytes.is_some_and(|x| x > 0));
+        client.call::<VersionServer>(&()).await.unwrap();
+
+        client.set_token(user.token.access_token.clone());
+        let profile = client.call::<UserProfile>(&GetProfileReq { uid: user.uid }).await.unwrap();
+        assert_eq!(user.uid, profile.uid);
+
+        let err = client.call::<UserProfile>(&GetProfileReq { uid: -1 }).await.unwrap_err();
+        assert!(matches!(err, Error::Api(ref x) if x.code == "not_found"), "{err}");
+    }).await
+}
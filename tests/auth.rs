@@ -3,11 +3,17 @@ pub mod common;
 use crate::common::{assert_is_err, assert_is_ok, CommonParse};
 use common::with_test_environment;
 use hachimi_world_server::web::result::WebResponse;
-use hachimi_world_server::web::routes::auth::{DeviceListResp, DeviceLogoutReq, EmailRegisterReq, LoginReq, LoginResp, RefreshTokenReq, ResetPasswordReq, TokenPair};
+use hachimi_world_server::web::routes::auth::{DeviceListResp, DeviceLogoutReq, DeviceRenameReq, DeviceTrustReq, EmailRegisterReq, LoginReq, LoginResp, RefreshTokenReq, ResetPasswordReq, TokenPair};
+use hachimi_world_server::web::routes::test_mode::TestEmailsReq;
+use regex::Regex;
 use reqwest::StatusCode;
 use serde_json::json;
-use hachimi_world_server::service;
-use crate::common::auth::{generate_pass_captcha_key, generate_pass_verification_code};
+use crate::common::auth::{generate_pass_captcha_key, latest_legal, generate_pass_verification_code, receive_verification_code, with_new_random_test_user};
+use crate::common::{ApiClient, ApiResult};
+use hachimi_world_server::service::oauth::{self, PendingLogin};
+use hachimi_world_server::service::{magic_link, totp};
+use hachimi_world_server::web::api::{AuthDeviceList, AuthDeviceRename, AuthDeviceTrust, AuthDeviceUntrust, AuthLoginEmail, AuthLoginMagicLinkRequest, AuthLoginMagicLinkVerify, AuthOAuthTwoFactor, AuthQrApprove, AuthQrCreate, AuthQrPoll, AuthQrScan, AuthTwoFactorConfirm, AuthTwoFactorDisable, AuthTwoFactorEnroll, TestEmails};
+use hachimi_world_server::web::routes::auth::{MagicLinkRequestReq, MagicLinkVerifyReq, OAuthTwoFactorReq, QrApproveReq, QrCreateReq, QrPollReq, QrScanReq, TwoFactorConfirmReq, TwoFactorDisableReq};
 
 #[tokio::test]
 async fn test_send_verification_code() {
@@ -32,11 +38,9 @@ async fn test_register_and_login() {
     with_test_environment(|mut env| async move {
         let random_email = format!("test_{}@mail.com", uuid::Uuid::new_v4());
 
-        // TODO[test]: Mock email sender?
         // TODO[test]: Separate these tests
 
-        // Put a fake email code for test
-        let code = generate_pass_verification_code(&mut env.redis, &random_email).await;
+        let code = receive_verification_code(&env.api, &random_email).await;
 
         let captcha_key = generate_pass_captcha_key(&env.api).await;
         // Test register with code
@@ -48,6 +52,7 @@ async fn test_register_and_login() {
                 code,
                 device_info: "test".to_string(),
                 captcha_key,
+                accepted_legal: latest_legal(&env.api).await,
             },
         ).await;
         assert_is_ok(resp).await;
@@ -62,6 +67,8 @@ async fn test_register_and_login() {
                 device_info: "test".to_string(),
                 code: None,
                 captcha_key,
+                trusted_device_token: None,
+                accepted_legal: vec![],
             },
         ).await;
         assert_is_err(resp).await;
@@ -76,6 +83,8 @@ async fn test_register_and_login() {
                 device_info: "test".to_string(),
                 code: None,
                 captcha_key,
+                trusted_device_token: None,
+                accepted_legal: latest_legal(&env.api).await,
             },
         ).await.parse_resp::<LoginResp>().await.unwrap();
 
@@ -86,6 +95,7 @@ async fn test_register_and_login() {
         let new_token: TokenPair = env.api.post("/auth/refresh_token", &RefreshTokenReq {
             refresh_token: token.refresh_token,
             device_info: "test".to_string(),
+            accepted_legal: latest_legal(&env.api).await,
         }).await.parse_resp::<TokenPair>().await.unwrap();
 
         // Test get logged device list
@@ -104,15 +114,16 @@ async fn test_register_and_login() {
         let resp = env.api.post("/auth/refresh_token",  &RefreshTokenReq {
             refresh_token: new_token.refresh_token,
             device_info: "test".to_string(),
+            accepted_legal: vec![],
         }).await;
         assert_is_err(resp).await;
 
-        // Test reset password
+        // Test reset password, put a fake code since sending again is rate limited
         let captcha_key = generate_pass_captcha_key(&env.api).await;
-        service::verification_code::set_code(&mut env.redis, &random_email, "12345678").await.unwrap();
+        let code = generate_pass_verification_code(&mut env.redis, &random_email).await;
         let resp = env.api.post("/auth/reset_password", &ResetPasswordReq {
             email: random_email.to_string(),
-            code: "12345678".to_string(),
+            code,
             new_password: "test-changed".to_string(),
             logout_all_devices: true,
             captcha_key,
@@ -127,6 +138,8 @@ async fn test_register_and_login() {
             device_info: "test".to_string(),
             code: None,
             captcha_key,
+            trusted_device_token: None,
+            accepted_legal: latest_legal(&env.api).await,
         }).await;
         assert_is_ok(resp).await;
     }).await;
@@ -155,4 +168,221 @@ async fn test_access_with_expired_token() {
 #[tokio::test]
 async fn test_refresh_token() {
     // TODO: How to mock refresh tokens?
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_qr_login() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let mut desktop = ApiClient::new(std::env::var("TEST_HTTP_BASE_URL").unwrap());
+        let session = desktop.call::<AuthQrCreate>(&QrCreateReq { device_info: "desktop".to_string() }).await.unwrap();
+        let poll_req = QrPollReq { code: session.code.clone(), poll_token: session.poll_token.clone() };
+
+        // Not approved yet
+        let resp = desktop.call::<AuthQrPoll>(&poll_req).await.unwrap();
+        assert!(resp.login.is_none());
+        // The code alone can't poll the tokens
+        let err = desktop.call::<AuthQrPoll>(&QrPollReq { code: session.code.clone(), poll_token: "wrong".to_string() }).await.unwrap_err();
+        assert_eq!("qr_code_expired", err.code);
+
+        // The mobile app shows the desktop before approving
+        let scanned = env.api.call::<AuthQrScan>(&QrScanReq { code: session.code.clone() }).await.unwrap();
+        assert_eq!("desktop", scanned.device_info);
+        let err = env.api.call::<AuthQrScan>(&QrScanReq { code: "unknown".to_string() }).await.unwrap_err();
+        assert_eq!("qr_code_expired", err.code);
+
+        let approve_req = QrApproveReq { code: session.code.clone(), accepted_legal: latest_legal(&env.api).await };
+        let approved = env.api.call::<AuthQrApprove>(&approve_req).await.unwrap();
+        assert_eq!("desktop", approved.device_info);
+        let err = env.api.call::<AuthQrApprove>(&approve_req).await.unwrap_err();
+        assert_eq!("qr_code_approved", err.code);
+
+        let login = desktop.call::<AuthQrPoll>(&poll_req).await.unwrap().login.unwrap();
+        assert_eq!(user.uid, login.uid);
+        desktop.set_token(login.token.access_token);
+        assert_eq!(StatusCode::OK, desktop.get("/auth/protected").await.status());
+
+        // The tokens are only issued once
+        let err = desktop.call::<AuthQrPoll>(&poll_req).await.unwrap_err();
+        assert_eq!("qr_code_expired", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_two_factor() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let enrollment = env.api.call::<AuthTwoFactorEnroll>(&()).await.unwrap();
+        assert!(enrollment.provisioning_uri.contains(&enrollment.secret));
+        let secret = totp::base32_decode(&enrollment.secret).unwrap();
+
+        let err = env.api.call::<AuthTwoFactorConfirm>(&TwoFactorConfirmReq { code: totp::code_at(&secret, 0).unwrap() }).await.unwrap_err();
+        assert_eq!("invalid_2fa_code", err.code);
+        let code = totp::code_at(&secret, chrono::Utc::now().timestamp() / 30).unwrap();
+        let confirmed = env.api.call::<AuthTwoFactorConfirm>(&TwoFactorConfirmReq { code }).await.unwrap();
+        assert_eq!(totp::RECOVERY_CODE_COUNT, confirmed.recovery_codes.len());
+
+        let err = login_with_code(&env.api, &user.email, None).await.unwrap_err();
+        assert_eq!("2fa_required", err.code);
+        let login = login_with_code(&env.api, &user.email, Some(&confirmed.recovery_codes[0])).await.unwrap();
+        assert_eq!(user.uid, login.uid);
+        // Each recovery code can be used once
+        let err = login_with_code(&env.api, &user.email, Some(&confirmed.recovery_codes[0])).await.unwrap_err();
+        assert_eq!("invalid_2fa_code", err.code);
+
+        env.api.call::<AuthTwoFactorDisable>(&TwoFactorDisableReq { code: confirmed.recovery_codes[1].clone() }).await.unwrap();
+        login_with_code(&env.api, &user.email, None).await.unwrap();
+    }).await;
+}
+
+#[tokio::test]
+async fn test_oauth_two_factor() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let enrollment = env.api.call::<AuthTwoFactorEnroll>(&()).await.unwrap();
+        let secret = totp::base32_decode(&enrollment.secret).unwrap();
+        let code = totp::code_at(&secret, chrono::Utc::now().timestamp() / 30).unwrap();
+        let confirmed = env.api.call::<AuthTwoFactorConfirm>(&TwoFactorConfirmReq { code }).await.unwrap();
+
+        // Returned by the callback in place of the tokens
+        let ticket = oauth::create_pending_login(&mut env.redis, &PendingLogin { uid: user.uid, first_access: false }).await.unwrap();
+        let accepted_legal = latest_legal(&env.api).await;
+        let req = |code: &str| OAuthTwoFactorReq {
+            ticket: ticket.clone(),
+            code: code.to_string(),
+            device_info: "test".to_string(),
+            accepted_legal: accepted_legal.clone(),
+        };
+        let err = env.api.call::<AuthOAuthTwoFactor>(&req("000000x")).await.unwrap_err();
+        assert_eq!("invalid_2fa_code", err.code);
+        // Kept after a wrong code
+        let login = env.api.call::<AuthOAuthTwoFactor>(&req(&confirmed.recovery_codes[0])).await.unwrap();
+        assert_eq!(user.uid, login.uid);
+        assert!(!login.first_access);
+        // Single-use
+        let err = env.api.call::<AuthOAuthTwoFactor>(&req(&confirmed.recovery_codes[1])).await.unwrap_err();
+        assert_eq!("invalid_oauth_state", err.code);
+    }).await;
+}
+
+async fn login_with_code(api: &ApiClient, email: &str, code: Option<&str>) -> ApiResult<LoginResp> {
+    api.call::<AuthLoginEmail>(&LoginReq {
+        email: email.to_string(),
+        password: "test12345678".to_string(),
+        device_info: "test".to_string(),
+        code: code.map(|x| x.to_string()),
+        captcha_key: generate_pass_captcha_key(api).await,
+        trusted_device_token: None,
+        accepted_legal: latest_legal(api).await,
+    }).await
+}
+
+#[tokio::test]
+async fn test_trusted_device() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let devices = env.api.call::<AuthDeviceList>(&()).await.unwrap().devices;
+        let device = devices.first().unwrap();
+        assert!(!device.trusted);
+
+        let err = env.api.call::<AuthDeviceRename>(&DeviceRenameReq { device_id: device.id, name: "x".repeat(65) }).await.unwrap_err();
+        assert_eq!("invalid_device_name", err.code);
+        env.api.call::<AuthDeviceRename>(&DeviceRenameReq { device_id: device.id, name: " My phone ".to_string() }).await.unwrap();
+
+        let trust = env.api.call::<AuthDeviceTrust>(&DeviceTrustReq { refresh_token: user.token.refresh_token.clone() }).await.unwrap();
+
+        // No captcha and 2FA code required
+        let accepted_legal = latest_legal(&env.api).await;
+        let login = |token: Option<String>| LoginReq {
+            email: user.email.clone(),
+            password: "test12345678".to_string(),
+            device_info: "test".to_string(),
+            code: None,
+            captcha_key: "invalid".to_string(),
+            trusted_device_token: token,
+            accepted_legal: accepted_legal.clone(),
+        };
+        let resp = env.api.call::<AuthLoginEmail>(&login(Some(trust.trusted_device_token.clone()))).await.unwrap();
+        env.api.set_token(resp.token.access_token.clone());
+
+        // The old token is replaced, keeping the name and the trust
+        let devices = env.api.call::<AuthDeviceList>(&()).await.unwrap().devices;
+        assert_eq!(1, devices.len());
+        let device = devices.first().unwrap();
+        assert_eq!(Some("My phone"), device.device_name.as_deref());
+        assert!(device.trusted);
+
+        env.api.call::<AuthDeviceUntrust>(&DeviceLogoutReq { device_id: device.id }).await.unwrap();
+        let err = env.api.call::<AuthLoginEmail>(&login(Some(trust.trusted_device_token))).await.unwrap_err();
+        assert_eq!("invalid_captcha", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_magic_link_login() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        env.api.call::<AuthLoginMagicLinkRequest>(&MagicLinkRequestReq { email: user.email.clone() }).await.unwrap();
+        let emails = env.api.call::<TestEmails>(&TestEmailsReq { to: user.email.clone() }).await.unwrap();
+        let latest = emails.emails.first().expect("No email captured, is the test server in the test mode?");
+        // The token is a JWT in the link, its characters are kept by the URL encoding
+        let token = Regex::new(r"eyJ[\w-]+\.[\w-]+\.[\w-]+").unwrap()
+            .find(&latest.plain)
+            .expect("No token in the magic link email")
+            .as_str()
+            .to_string();
+
+        let req = MagicLinkVerifyReq {
+            token,
+            device_info: "test".to_string(),
+            code: None,
+            accepted_legal: latest_legal(&env.api).await,
+        };
+        let resp = env.api.call::<AuthLoginMagicLinkVerify>(&req).await.unwrap();
+        assert_eq!(user.uid, resp.uid);
+        env.api.set_token(resp.token.access_token);
+        assert_is_ok(env.api.get("/auth/protected").await).await;
+
+        // The link is used once
+        let err = env.api.call::<AuthLoginMagicLinkVerify>(&req).await.unwrap_err();
+        assert_eq!("invalid_magic_link", err.code);
+        let err = env.api.call::<AuthLoginMagicLinkVerify>(&MagicLinkVerifyReq { token: format!("{}x", req.token), ..req }).await.unwrap_err();
+        assert_eq!("invalid_magic_link", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_magic_link_wrong_codes() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let enrollment = env.api.call::<AuthTwoFactorEnroll>(&()).await.unwrap();
+        let secret = totp::base32_decode(&enrollment.secret).unwrap();
+        let code = totp::code_at(&secret, chrono::Utc::now().timestamp() / 30).unwrap();
+        let confirmed = env.api.call::<AuthTwoFactorConfirm>(&TwoFactorConfirmReq { code }).await.unwrap();
+
+        env.api.call::<AuthLoginMagicLinkRequest>(&MagicLinkRequestReq { email: user.email.clone() }).await.unwrap();
+        let emails = env.api.call::<TestEmails>(&TestEmailsReq { to: user.email.clone() }).await.unwrap();
+        let token = Regex::new(r"eyJ[\w-]+\.[\w-]+\.[\w-]+").unwrap()
+            .find(&emails.emails.first().unwrap().plain)
+            .expect("No token in the magic link email")
+            .as_str()
+            .to_string();
+        let req = MagicLinkVerifyReq {
+            token,
+            device_info: "test".to_string(),
+            code: Some("wrong-code".to_string()),
+            accepted_legal: latest_legal(&env.api).await,
+        };
+
+        for _ in 0..magic_link::MAX_FAILED_CODES {
+            let err = env.api.call::<AuthLoginMagicLinkVerify>(&req).await.unwrap_err();
+            assert_eq!("invalid_2fa_code", err.code);
+        }
+        // Revoked after too many wrong codes, even the right one can't use it
+        let err = env.api.call::<AuthLoginMagicLinkVerify>(&MagicLinkVerifyReq {
+            code: Some(confirmed.recovery_codes[0].clone()),
+            ..req
+        }).await.unwrap_err();
+        assert_eq!("invalid_magic_link", err.code);
+    }).await;
+}
@@ -1,8 +1,11 @@
 use std::env;
 use redis::aio::ConnectionManager;
-use crate::common::{assert_is_ok, ApiClient, CommonParse, TestEnvironment};
+use crate::common::{ApiClient, TestEnvironment};
 use hachimi_world_server::service;
-use hachimi_world_server::web::routes::auth::{EmailRegisterReq, EmailRegisterResp, GenerateCaptchaResp, LoginReq, LoginResp, SubmitCaptchaReq, TokenPair};
+use hachimi_world_server::service::legal::LegalAcceptance;
+use hachimi_world_server::web::api::{AuthCaptchaGenerate, AuthLoginEmail, AuthRegisterEmail, AuthSendEmailCode, LegalLatest, TestEmails};
+use hachimi_world_server::web::routes::auth::{EmailRegisterReq, LoginReq, SendVerificationReq, TokenPair};
+use hachimi_world_server::web::routes::test_mode::TestEmailsReq;
 
 pub struct TestUser {
     pub uid: i64,
@@ -16,23 +19,19 @@ pub async fn with_new_random_test_user(env: &mut TestEnvironment) -> TestUser {
 }
 
 pub async fn with_new_test_user(env: &mut TestEnvironment, email: &str) -> TestUser {
-    // Put a fake email code for test
-    service::verification_code::set_code(&mut env.redis, &email, "12345678")
-        .await
-        .unwrap();
+    let code = receive_verification_code(&env.api, email).await;
 
     // Test registering with code
     let captcha_key = generate_pass_captcha_key(&env.api).await;
-    let reg_resp = env.api.post(
-        "/auth/register/email",
-        &EmailRegisterReq {
-            email: email.to_string(),
-            password: "test12345678".to_string(),
-            code: "12345678".to_string(),
-            device_info: "test".to_string(),
-            captcha_key,
-        },
-    ).await.parse_resp::<EmailRegisterResp>().await.unwrap();
+    let accepted_legal = latest_legal(&env.api).await;
+    let reg_resp = env.api.call::<AuthRegisterEmail>(&EmailRegisterReq {
+        email: email.to_string(),
+        password: "test12345678".to_string(),
+        code,
+        device_info: "test".to_string(),
+        captcha_key,
+        accepted_legal,
+    }).await.unwrap();
 
     env.api.set_token(reg_resp.token.access_token.clone());
 
@@ -48,13 +47,15 @@ pub async fn with_test_contributor_user(env: &mut TestEnvironment) -> TestUser {
     let email = env::var("TEST_CONTRIBUTOR_EMAIL").unwrap();
     let pass = env::var("TEST_CONTRIBUTOR_PASSWORD").unwrap();
     let captcha_key = generate_pass_captcha_key(&env.api).await;
-    let resp = env.api.post("/auth/login/email", &LoginReq {
+    let resp = env.api.call::<AuthLoginEmail>(&LoginReq {
         email: email.clone(),
         password: pass,
         device_info: "test".to_string(),
         code: None,
         captcha_key: captcha_key,
-    }).await.parse_resp::<LoginResp>().await.unwrap();
+        trusted_device_token: None,
+        accepted_legal: latest_legal(&env.api).await,
+    }).await.unwrap();
     env.api.set_token(resp.token.access_token.clone());
     TestUser {
         uid: resp.uid,
@@ -63,17 +64,27 @@ pub async fn with_test_contributor_user(env: &mut TestEnvironment) -> TestUser {
     }
 }
 
-/// Make sure using test-captcha environment in integrated tests
+/// Accept whatever is published, so the tests can log in while the others publish the mandatory versions
+pub async fn latest_legal(api: &ApiClient) -> Vec<LegalAcceptance> {
+    api.call::<LegalLatest>(&()).await.unwrap().documents.into_iter()
+        .map(|x| LegalAcceptance { kind: x.kind, version: x.version })
+        .collect()
+}
+
+/// The test server must run in the test mode, so the generated captchas are passed already
 pub async fn generate_pass_captcha_key(api: &ApiClient) -> String {
-    let captcha_key = api.get("/auth/captcha/generate").await.parse_resp::<GenerateCaptchaResp>().await.unwrap();
+    api.call::<AuthCaptchaGenerate>(&()).await.unwrap().captcha_key
+}
 
-    // Submit the test token, see cloudflare turnstile doc [Testing](https://developers.cloudflare.com/turnstile/troubleshooting/testing/)
-    let r = api.post("/auth/captcha/submit", &SubmitCaptchaReq {
-        captcha_key: captcha_key.captcha_key.clone(),
-        token: "XXXX.DUMMY.TOKEN.XXXX".to_string()
-    }).await;
-    assert_is_ok(r).await;
-    captcha_key.captcha_key
+/// Send a verification code to the email, and read it from the emails captured by the test server
+pub async fn receive_verification_code(api: &ApiClient, email: &str) -> String {
+    api.call::<AuthSendEmailCode>(&SendVerificationReq {
+        email: email.to_string(),
+    }).await.unwrap();
+    let resp = api.call::<TestEmails>(&TestEmailsReq { to: email.to_string() }).await.unwrap();
+    let latest = resp.emails.first().expect("No email captured, is the test server in the test mode?");
+    // The plain text starts with the code
+    latest.plain.split_whitespace().next().unwrap().to_string()
 }
 
 /// Generate a fake verification code for testing, directly set to "12345678" in redis
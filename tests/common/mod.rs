@@ -2,6 +2,8 @@ pub mod auth;
 pub mod song;
 
 use axum::http::HeaderMap;
+use hachimi_world_server::web::api::{Endpoint, HttpMethod};
+use hachimi_world_server::web::pagination::PageQuery;
 use hachimi_world_server::web::result::CommonError;
 use redis::aio::ConnectionManager;
 use reqwest::{RequestBuilder, Response};
@@ -88,6 +90,22 @@ impl ApiClient {
         resp
     }
 
+    /// GET with the request query and the `page_index`/`page_size` query
+    pub async fn get_query_paged<T: Serialize>(&self, path: &str, query: &T, page: &PageQuery) -> Response {
+        let client = reqwest::Client::new();
+
+        let resp = client
+            .get(format!("{}{path}", self.base_url))
+            .headers(self.default_headers())
+            .query(&query)
+            .query(&page)
+            .send()
+            .await
+            .unwrap();
+        println!("[{}] GET to {}; Query: {}; Page: {}", resp.status(), path, serde_urlencoded::to_string(query).unwrap(), serde_urlencoded::to_string(page).unwrap());
+        resp
+    }
+
     pub async fn post<T: Serialize>(&self, path: &str, body: &T) -> Response {
         let client = reqwest::Client::new();
         let body = serde_json::to_value(body).unwrap();
@@ -102,6 +120,14 @@ impl ApiClient {
         resp
     }
     
+    /// Call the endpoint of the manifest with the typed request and response
+    pub async fn call<E: Endpoint>(&self, req: &E::Req) -> ApiResult<E::Resp> {
+        match E::METHOD {
+            HttpMethod::Get => self.get_query(E::PATH, req).await.parse_resp().await,
+            HttpMethod::Post => self.post(E::PATH, req).await.parse_resp().await,
+        }
+    }
+
     pub fn post_raw(&self, path: &str) -> RequestBuilder {
         let client = reqwest::Client::new();
         client.post(format!("{}{path}", self.base_url))
@@ -1,5 +1,12 @@
-use hachimi_world_server::web::routes::song::TagCreateReq;
-use crate::common::{assert_is_ok, ApiClient};
+use hachimi_world_server::service::localization::LocalizedTitleItem;
+use hachimi_world_server::service::song::CreationTypeInfo;
+use hachimi_world_server::web::api::{PublishReviewApprove, SongDetail};
+use hachimi_world_server::web::routes::publish::review::ApproveReviewReq;
+use hachimi_world_server::web::routes::publish::{CreationInfo, PublishReq, PublishResp, UploadAudioFileResp, UploadImageResp};
+use hachimi_world_server::web::routes::song::{DetailReq, DetailResp, TagCreateReq};
+use reqwest::multipart::{Form, Part};
+use std::fs;
+use crate::common::{assert_is_ok, auth, ApiClient, CommonParse, TestEnvironment};
 
 pub async fn create_tags(api: &ApiClient) {
     // TODO[test](song): We should add cleanup code to make the test repeatable.
@@ -17,3 +24,59 @@ pub async fn create_tags(api: &ApiClient) {
         assert_is_ok(resp).await;
     }
 }
+
+pub async fn publish_template(env: &TestEnvironment) -> PublishReq {
+    // Upload a song
+    let upload_resp: UploadAudioFileResp = env.api
+        .post_raw("/publish/upload_audio_file")
+        .multipart(Form::new().part("file", Part::bytes(fs::read(".local/test_res/test.mp3").unwrap())))
+        .send().await.unwrap().parse_resp().await.unwrap();
+
+    // Upload a cover
+    let upload_img_resp: UploadImageResp = env
+        .api
+        .post_raw("/publish/upload_cover_image")
+        .multipart(Form::new().part("file", Part::bytes(fs::read(".local/test_Res/test.webp").unwrap())))
+        .send().await.unwrap().parse_resp().await.unwrap();
+
+    PublishReq {
+        song_temp_id: upload_resp.temp_id.clone(),
+        cover_temp_id: upload_img_resp.temp_id.clone(),
+        title: "Test".to_string(),
+        subtitle: "A test music".to_string(),
+        description: "This is a fucking test music".to_string(),
+        lyrics: "哈基米哈基米哈基米".to_string(),
+        tag_ids: vec![],
+        creation_info: CreationInfo {
+            creation_type: 0,
+            origin_info: Some(CreationTypeInfo {
+                song_display_id: None,
+                title: Some("原作".into()),
+                artist: Some("群星".into()),
+                url: None,
+                origin_type: 0,
+            }),
+            derivative_info: None,
+        },
+        production_crew: vec![],
+        external_links: vec![],
+        explicit: Some(false),
+        jmid: Some("JM-ABCD-000".into()),
+        comment: Some("Test comment in review".into()),
+        template: None,
+        localized_titles: vec![LocalizedTitleItem {
+            lang: "en".to_string(),
+            title: "Test in English".to_string(),
+            subtitle: String::new(),
+        }],
+    }
+}
+
+/// Publish a song by the current user and approve it, the token of the contributor is set after it
+pub async fn publish_approved_song(env: &mut TestEnvironment) -> DetailResp {
+    let req = PublishReq { jmid: None, ..publish_template(env).await };
+    let resp: PublishResp = env.api.post("/publish/publish", &req).await.parse_resp().await.unwrap();
+    auth::with_test_contributor_user(env).await;
+    env.api.call::<PublishReviewApprove>(&ApproveReviewReq { review_id: resp.review_id, comment: None }).await.unwrap();
+    env.api.call::<SongDetail>(&DetailReq { id: resp.song_display_id }).await.unwrap()
+}
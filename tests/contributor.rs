@@ -1,6 +1,10 @@
-use crate::common::auth::{with_new_random_test_user, with_test_contributor_user};
+use crate::common::auth::{latest_legal, with_new_random_test_user, with_test_contributor_user};
 use crate::common::with_test_environment;
+use crate::common::song::publish_approved_song;
 use crate::common::CommonParse;
+use hachimi_world_server::web::api::{AdminSongEdit, AuthRefreshToken};
+use hachimi_world_server::web::routes::auth::RefreshTokenReq;
+use hachimi_world_server::web::routes::admin::{EditSongReq, ListLegalHoldsResp, RoleReq, RoleResp, SetLegalHoldReq, SetLegalHoldResp};
 use hachimi_world_server::web::routes::contributor::CheckContributorResp;
 
 mod common;
@@ -23,4 +27,126 @@ async fn test_check_contributor() {
             .parse_resp().await.unwrap();
         assert_eq!(resp.is_contributor, true);
     }).await;
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_grant_and_revoke_role() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let contributor = with_test_contributor_user(&mut env).await;
+        let req = RoleReq { uid: user.uid, role: "contributor".to_string() };
+
+        let resp: RoleResp = env.api.post("/admin/role/grant", &req).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+        let resp: RoleResp = env.api.post("/admin/role/grant", &req).await.parse_resp().await.unwrap();
+        assert!(!resp.changed);
+        let err = env.api.post("/admin/role/grant", &RoleReq { uid: user.uid, role: "nobody".to_string() }).await
+            .parse_resp::<RoleResp>().await.unwrap_err();
+        assert_eq!("role_not_found", err.code);
+
+        // The token issued before the grant sees the new role
+        env.api.set_token(user.token.access_token.clone());
+        let resp: CheckContributorResp = env.api.get("/contributor/check").await.parse_resp().await.unwrap();
+        assert!(resp.is_contributor);
+
+        // Can't lock themselves out
+        let err = env.api.post("/admin/role/revoke", &req).await.parse_resp::<RoleResp>().await.unwrap_err();
+        assert_eq!("invalid_uid", err.code);
+
+        env.api.set_token(contributor.token.access_token.clone());
+        let resp: RoleResp = env.api.post("/admin/role/revoke", &req).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+
+        env.api.set_token(user.token.access_token.clone());
+        let resp: CheckContributorResp = env.api.get("/contributor/check").await.parse_resp().await.unwrap();
+        assert!(!resp.is_contributor);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_demoted_contributor_loses_access() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let contributor = with_test_contributor_user(&mut env).await;
+        let req = RoleReq { uid: user.uid, role: "contributor".to_string() };
+        let resp: RoleResp = env.api.post("/admin/role/grant", &req).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+
+        // The refreshed token embeds the role, so it's trusted without looking up the contributors
+        let accepted_legal = latest_legal(&env.api).await;
+        let token = env.api.call::<AuthRefreshToken>(&RefreshTokenReq {
+            refresh_token: user.token.refresh_token.clone(),
+            device_info: "test".to_string(),
+            accepted_legal,
+        }).await.unwrap();
+        env.api.set_token(token.access_token.clone());
+        env.api.get("/admin/user/shadow_ban/list").await.parse_resp::<serde_json::Value>().await.unwrap();
+
+        env.api.set_token(contributor.token.access_token.clone());
+        let resp: RoleResp = env.api.post("/admin/role/revoke", &req).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+
+        // The role in the token is stale after the demotion
+        env.api.set_token(token.access_token.clone());
+        let err = env.api.get("/admin/user/shadow_ban/list").await.parse_resp::<serde_json::Value>().await.unwrap_err();
+        assert_eq!("permission_denied", err.code);
+        let resp: CheckContributorResp = env.api.get("/contributor/check").await.parse_resp().await.unwrap();
+        assert!(!resp.is_contributor);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_legal_hold() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let req = SetLegalHoldReq { uid: user.uid, hold: true, reason: "Litigation".to_string() };
+        let err = env.api.post("/admin/user/legal_hold", &req).await.parse_resp::<SetLegalHoldResp>().await.unwrap_err();
+        assert_eq!("permission_denied", err.code);
+
+        with_test_contributor_user(&mut env).await;
+        let resp: SetLegalHoldResp = env.api.post("/admin/user/legal_hold", &req).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+        let resp: SetLegalHoldResp = env.api.post("/admin/user/legal_hold", &req).await.parse_resp().await.unwrap();
+        assert!(!resp.changed);
+        let resp: ListLegalHoldsResp = env.api.get("/admin/user/legal_hold/list").await.parse_resp().await.unwrap();
+        let hold = resp.items.iter().find(|x| x.user_id == user.uid).unwrap();
+        assert_eq!("Litigation", hold.reason);
+
+        let release = SetLegalHoldReq { hold: false, ..req };
+        let resp: SetLegalHoldResp = env.api.post("/admin/user/legal_hold", &release).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+        let resp: ListLegalHoldsResp = env.api.get("/admin/user/legal_hold/list").await.parse_resp().await.unwrap();
+        assert!(resp.items.iter().all(|x| x.user_id != user.uid));
+
+        let err = env.api.post("/admin/user/legal_hold", &SetLegalHoldReq { uid: -1, ..release }).await
+            .parse_resp::<SetLegalHoldResp>().await.unwrap_err();
+        assert_eq!("not_found", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_edit_song_validation() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        let req = EditSongReq {
+            song_id: song.id,
+            title: None,
+            subtitle: None,
+            description: None,
+            tag_ids: None,
+            note: None,
+        };
+
+        let err = env.api.call::<AdminSongEdit>(&EditSongReq { title: Some("  ".to_string()), ..req.clone() }).await.unwrap_err();
+        assert_eq!("invalid_title", err.code);
+        let err = env.api.call::<AdminSongEdit>(&EditSongReq { title: Some("a".repeat(101)), ..req.clone() }).await.unwrap_err();
+        assert_eq!("invalid_title", err.code);
+        let err = env.api.call::<AdminSongEdit>(&EditSongReq { subtitle: Some("a".repeat(201)), ..req.clone() }).await.unwrap_err();
+        assert_eq!("subtitle_too_long", err.code);
+        let err = env.api.call::<AdminSongEdit>(&EditSongReq { description: Some("a".repeat(5001)), ..req.clone() }).await.unwrap_err();
+        assert_eq!("description_too_long", err.code);
+
+        env.api.call::<AdminSongEdit>(&EditSongReq { title: Some("Edited".to_string()), ..req }).await.unwrap();
+    }).await;
+}
@@ -0,0 +1,88 @@
+use crate::common::auth::{generate_pass_captcha_key, latest_legal, with_new_random_test_user, with_test_contributor_user};
+use crate::common::with_test_environment;
+use crate::common::CommonParse;
+use hachimi_world_server::service::legal::{LegalAcceptance, KIND_PRIVACY, KIND_TERMS};
+use hachimi_world_server::web::api::{AuthLoginEmail, AuthRefreshToken, LegalAccept, LegalLatest};
+use hachimi_world_server::web::routes::auth::{LoginReq, RefreshTokenReq};
+use hachimi_world_server::web::routes::admin::{PublishLegalReq, PublishLegalResp};
+use hachimi_world_server::web::routes::legal::AcceptReq;
+
+mod common;
+
+#[tokio::test]
+async fn test_legal_documents() {
+    with_test_environment(|mut env| async move {
+        // Not mandatory, so the other tests can still log in
+        with_test_contributor_user(&mut env).await;
+        let published: PublishLegalResp = env.api.post("/admin/legal/publish", &PublishLegalReq {
+            kind: KIND_PRIVACY.to_string(),
+            title: "Privacy Policy".to_string(),
+            content: "# Privacy Policy".to_string(),
+            mandatory: false,
+        }).await.parse_resp().await.unwrap();
+
+        with_new_random_test_user(&mut env).await;
+        let latest = env.api.call::<LegalLatest>(&()).await.unwrap();
+        let doc = latest.documents.iter().find(|x| x.kind == KIND_PRIVACY).unwrap();
+        assert!(doc.version >= published.version);
+        // Accepted at the registration
+        assert_eq!(Some(doc.version), doc.accepted_version);
+        assert!(latest.pending.is_empty());
+
+        let err = env.api.call::<LegalAccept>(&AcceptReq {
+            items: vec![LegalAcceptance { kind: KIND_PRIVACY.to_string(), version: i32::MAX }],
+        }).await.unwrap_err();
+        assert_eq!("invalid_legal_version", err.code);
+        env.api.call::<LegalAccept>(&AcceptReq {
+            items: vec![LegalAcceptance { kind: KIND_PRIVACY.to_string(), version: published.version }],
+        }).await.unwrap();
+    }).await;
+}
+
+#[tokio::test]
+async fn test_mandatory_legal_document() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        with_test_contributor_user(&mut env).await;
+        let published: PublishLegalResp = env.api.post("/admin/legal/publish", &PublishLegalReq {
+            kind: KIND_TERMS.to_string(),
+            title: "Terms of Service".to_string(),
+            content: "# Terms of Service".to_string(),
+            mandatory: true,
+        }).await.parse_resp().await.unwrap();
+        let terms = LegalAcceptance { kind: KIND_TERMS.to_string(), version: published.version };
+
+        let login = |accepted_legal: Vec<LegalAcceptance>| {
+            let (api, email) = (&env.api, user.email.clone());
+            async move {
+                api.call::<AuthLoginEmail>(&LoginReq {
+                    email,
+                    password: "test12345678".to_string(),
+                    device_info: "test".to_string(),
+                    code: None,
+                    captcha_key: generate_pass_captcha_key(api).await,
+                    trusted_device_token: None,
+                    accepted_legal,
+                }).await
+            }
+        };
+        let err = login(vec![]).await.unwrap_err();
+        assert_eq!("legal_acceptance_required", err.code);
+        assert!(err.detail.unwrap()["pending"].as_array().unwrap().iter()
+            .any(|x| x["kind"] == KIND_TERMS && x["version"] == published.version));
+        // The logged in devices are blocked too
+        let refresh = |accepted_legal: Vec<LegalAcceptance>| RefreshTokenReq {
+            refresh_token: user.token.refresh_token.clone(),
+            device_info: "test".to_string(),
+            accepted_legal,
+        };
+        let err = env.api.call::<AuthRefreshToken>(&refresh(vec![])).await.unwrap_err();
+        assert_eq!("legal_acceptance_required", err.code);
+
+        // The later versions may be published by the other tests
+        let mut accepted_legal = latest_legal(&env.api).await;
+        accepted_legal.push(terms);
+        assert_eq!(user.uid, login(accepted_legal).await.unwrap().uid);
+        env.api.call::<AuthRefreshToken>(&refresh(latest_legal(&env.api).await)).await.unwrap();
+    }).await;
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::with_test_environment;
+use hachimi_world_server::web::api::{PlayerQueueGet, PlayerQueueSet};
+use hachimi_world_server::web::routes::player::SetQueueReq;
+use crate::common::auth;
+
+#[tokio::test]
+async fn test_queue_sync() {
+    with_test_environment(|mut env| async move {
+        auth::with_new_random_test_user(&mut env).await;
+        let resp = env.api.call::<PlayerQueueGet>(&()).await.unwrap();
+        assert_eq!(0, resp.revision);
+        assert!(resp.queue.is_none());
+
+        let req = |revision: i64, current_index: usize| SetQueueReq {
+            revision,
+            song_ids: vec![1, 2, 3],
+            current_index,
+            position_ms: 1500,
+            device: Some("phone".to_string()),
+        };
+        let err = env.api.call::<PlayerQueueSet>(&req(0, 3)).await.unwrap_err();
+        assert_eq!("invalid_index", err.code);
+        let saved = env.api.call::<PlayerQueueSet>(&req(0, 1)).await.unwrap();
+        assert_eq!(1, saved.revision);
+
+        // Another device saving a stale queue
+        let err = env.api.call::<PlayerQueueSet>(&req(0, 2)).await.unwrap_err();
+        assert_eq!("queue_conflict", err.code);
+
+        let resp = env.api.call::<PlayerQueueGet>(&()).await.unwrap();
+        assert_eq!(1, resp.revision);
+        let queue = resp.queue.unwrap();
+        assert_eq!(vec![1, 2, 3], queue.song_ids);
+        assert_eq!(1, queue.current_index);
+        assert_eq!(2, env.api.call::<PlayerQueueSet>(&req(resp.revision, 2)).await.unwrap().revision);
+    }).await
+}
@@ -1,7 +1,17 @@
 use crate::common::auth::with_new_random_test_user;
+use crate::common::song::publish_approved_song;
 use crate::common::with_test_environment;
-use crate::common::CommonParse;
-use hachimi_world_server::web::routes::playlist::{AddFavoriteReq, AddSongReq, ChangeOrderReq, CheckFavoriteReq, CheckFavoriteResp, CreatePlaylistReq, CreatePlaylistResp, DetailReq, DetailResp, ListContainingReq, ListContainingResp, ListResp, PageFavoritesReq, PageFavoritesResp, SearchReq, SearchResp};
+use crate::common::{assert_is_err, ApiClient, CommonParse};
+use hachimi_world_server::db::user::UserDao;
+use hachimi_world_server::db::CrudDao;
+use hachimi_world_server::service::playlist::PlaylistManifest;
+use chrono::{Days, NaiveDate};
+use hachimi_world_server::web::api::{PlaylistCreate, PlaylistDetailPublic, PlaylistOfficialWeekly, PlaylistOfficialWeeklyArchive, TestWeeklySelectionPublish};
+use hachimi_world_server::web::routes::admin::AddFeaturedReq;
+use hachimi_world_server::web::routes::test_mode::PublishWeeklySelectionReq;
+use hachimi_world_server::web::pagination::PageQuery;
+use std::env;
+use hachimi_world_server::web::routes::playlist::{AddFavoriteReq, AddSongReq, AddSongResp, AddSongsReq, AddSongsResp, AddSongsResult, ChangeOrderReq, CheckFavoriteReq, CheckFavoriteResp, CreatePlaylistReq, CreatePlaylistResp, DetailReq, DetailResp, ExportReq, ListContainingReq, ListContainingResp, ListResp, OfficialWeeklyReq, PageByUserReq, PageByUserResp, PageFavoritesResp, SearchReq, SearchResp};
 
 mod common;
 
@@ -13,6 +23,7 @@ async fn test_playlist() {
         // Create a playlist with invalid input
         let resp = env.api.post("/playlist/create", &CreatePlaylistReq {
             name: "Test Long Name".repeat(20),
+            use_song_cover: None,
             description: None,
             is_public: false,
         }).await.parse_resp::<CreatePlaylistResp>().await;
@@ -22,6 +33,7 @@ async fn test_playlist() {
         // Create a playlist without cover
         let playlist_resp = env.api.post("/playlist/create", &CreatePlaylistReq {
             name: "Test Playlist".to_string(),
+            use_song_cover: None,
             description: None,
             is_public: false,
         }).await.parse_resp::<CreatePlaylistResp>().await.unwrap();
@@ -29,14 +41,21 @@ async fn test_playlist() {
         let playlist_id = playlist_resp.id;
 
         let songs_to_add = vec![1, 2, 3, 4, 5];
-        for x in songs_to_add {
+        for (i, x) in songs_to_add.into_iter().enumerate() {
             let r = env.api.post("/playlist/add_song", &AddSongReq {
                 playlist_id,
                 song_id: x,
-            }).await.parse_resp::<()>().await;
-            assert!(r.is_ok())
+            }).await.parse_resp::<AddSongResp>().await.unwrap();
+            assert_eq!(i as i64 + 1, r.songs_count);
         }
 
+        // Add a duplicated song
+        let r = env.api.post("/playlist/add_song", &AddSongReq {
+            playlist_id,
+            song_id: 1,
+        }).await.parse_resp::<AddSongResp>().await;
+        assert_eq!("song_existed", r.err().unwrap().code);
+
         let playlist_resp = env.api.get("/playlist/list").await.parse_resp::<ListResp>().await.unwrap();
         assert_eq!(1, playlist_resp.playlists.len());
 
@@ -80,10 +99,19 @@ async fn test_playlist() {
         let songs = detail.songs.iter().map(|x| x.song_id).collect::<Vec<_>>();
         assert_eq!(&vec![3, 2, 4, 5, 1], &songs);
 
+        // Batch add with a duplicated and a missing song
+        let r = env.api.post("/playlist/add_songs", &AddSongsReq {
+            playlist_id,
+            song_ids: vec![6, 1, 6, -1],
+        }).await.parse_resp::<AddSongsResp>().await.unwrap();
+        let results = r.results.iter().map(|x| x.result).collect::<Vec<_>>();
+        assert_eq!(vec![AddSongsResult::Added, AddSongsResult::Existed, AddSongsResult::Existed, AddSongsResult::SongNotFound], results);
+        assert_eq!(6, r.songs_count);
 
         // Create a playlist without songs
         env.api.post("/playlist/create", &CreatePlaylistReq {
             name: "Test Playlist2".to_string(),
+            use_song_cover: None,
             description: None,
             is_public: false,
         }).await.parse_resp::<CreatePlaylistResp>().await.unwrap();
@@ -99,6 +127,21 @@ async fn test_playlist() {
             song_id: 0,
         }).await.parse_resp::<ListContainingResp>().await.unwrap();
         assert_eq!(0, resp.playlist_ids.len());
+
+        // Test export
+        let resp = env.api.get_query("/playlist/export", &ExportReq { id: playlist_id, format: "m3u".to_string() }).await;
+        assert_eq!("audio/x-mpegurl; charset=utf-8", resp.headers()["content-type"]);
+        let m3u = resp.text().await.unwrap();
+        assert!(m3u.starts_with("#EXTM3U\n"));
+        assert_eq!(6, m3u.matches("#EXTINF:").count());
+        let resp = env.api.get_query("/playlist/export", &ExportReq { id: playlist_id, format: "json".to_string() }).await;
+        let manifest: PlaylistManifest = resp.json().await.unwrap();
+        assert_eq!(6, manifest.songs.len());
+
+        // Private playlists can't be exported by others
+        let other = ApiClient::new(env::var("TEST_HTTP_BASE_URL").unwrap());
+        let resp = other.get_query("/playlist/export", &ExportReq { id: playlist_id, format: "json".to_string() }).await;
+        assert_is_err(resp).await;
         ()
     }).await;
 }
@@ -111,6 +154,7 @@ async fn test_invalid_input() {
         // Create a playlist with invalid input
         let resp = env.api.post("/playlist/create", &CreatePlaylistReq {
             name: "Test Long Name".repeat(20),
+            use_song_cover: None,
             description: None,
             is_public: false,
         }).await.parse_resp::<CreatePlaylistResp>().await;
@@ -120,6 +164,7 @@ async fn test_invalid_input() {
         // Create a playlist with invalid input
         let resp = env.api.post("/playlist/create", &CreatePlaylistReq {
             name: "Test Name".to_string(),
+            use_song_cover: None,
             description: Some("Test description".repeat(100)),
             is_public: false,
         }).await.parse_resp::<CreatePlaylistResp>().await;
@@ -152,9 +197,9 @@ async fn test_search_playlist() {
 async fn test_favorites() {
     with_test_environment(|mut env| async move {
         let _user = with_new_random_test_user(&mut env).await;
-        let resp = env.api.get_query("/playlist/favorite/page", &PageFavoritesReq { page_index: 0, page_size: 100 }).await.parse_resp::<PageFavoritesResp>().await.unwrap();
+        let resp = env.api.get_query("/playlist/favorite/page", &PageQuery { page_index: 0, page_size: 50 }).await.parse_resp::<PageFavoritesResp>().await.unwrap();
         assert_eq!(0, resp.total);
-        assert_eq!(0, resp.data.len());
+        assert_eq!(0, resp.items.len());
         assert_eq!(50, resp.page_size);
         assert_eq!(0, resp.page_index);
 
@@ -163,15 +208,15 @@ async fn test_favorites() {
 
         let favs = env.api.get_query(
             "/playlist/favorite/page",
-            &PageFavoritesReq { page_index: 0, page_size: 50 }
+            &PageQuery { page_index: 0, page_size: 50 }
         ).await.parse_resp::<PageFavoritesResp>().await.unwrap();
         assert_eq!(0, favs.page_index);
         assert_eq!(50, favs.page_size);
         assert_eq!(2, favs.total);
-        assert_eq!(2, favs.data.len());
+        assert_eq!(2, favs.items.len());
 
-        assert!(favs.data.iter().any(|x| x.metadata.id == 1 && x.order_index == 0));
-        assert!(favs.data.iter().any(|x| x.metadata.id == 2 && x.order_index == 1));
+        assert!(favs.items.iter().any(|x| x.metadata.id == 1 && x.order_index == 0));
+        assert!(favs.items.iter().any(|x| x.metadata.id == 2 && x.order_index == 1));
 
         let resp = env.api.get_query(
             "/playlist/favorite/check",
@@ -191,7 +236,108 @@ async fn test_favorites() {
         ).await.parse_resp::<CheckFavoriteResp>().await.unwrap();
         assert_eq!(false, resp3.is_favorite);
 
-        let resp = env.api.get_query("/playlist/favorite/page", &PageFavoritesReq { page_index: 0, page_size: 50 }).await.parse_resp::<PageFavoritesResp>().await.unwrap();
+        let resp = env.api.get_query("/playlist/favorite/page", &PageQuery { page_index: 0, page_size: 50 }).await.parse_resp::<PageFavoritesResp>().await.unwrap();
         assert_eq!(1, resp.total);
     }).await;
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_public_playlist_sharing() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let create = |name: &str, is_public: bool| CreatePlaylistReq {
+            name: name.to_string(),
+            use_song_cover: None,
+            description: None,
+            is_public,
+        };
+        let public = env.api.call::<PlaylistCreate>(&create("Public Playlist", true)).await.unwrap();
+        let private = env.api.call::<PlaylistCreate>(&create("Private Playlist", false)).await.unwrap();
+
+        // Without logging in
+        let anonymous = ApiClient::new(env::var("TEST_HTTP_BASE_URL").unwrap());
+        let detail = anonymous.call::<PlaylistDetailPublic>(&DetailReq { id: public.id }).await.unwrap();
+        assert_eq!("Public Playlist", detail.playlist_info.name);
+        let err = anonymous.call::<PlaylistDetailPublic>(&DetailReq { id: private.id }).await.unwrap_err();
+        assert_eq!("not_found", err.code);
+
+        let page: PageByUserResp = anonymous.get_query_paged(
+            "/playlist/page_by_user",
+            &PageByUserReq { user_id: user.uid },
+            &PageQuery { page_index: 0, page_size: 10 },
+        ).await.parse_resp().await.unwrap();
+        assert_eq!(1, page.total);
+        assert_eq!(vec![public.id], page.items.iter().map(|x| x.id).collect::<Vec<_>>());
+
+        // Nor counted once the user is banned
+        let mut banned = UserDao::get_by_id(&env.pool, user.uid).await.unwrap().unwrap();
+        banned.is_banned = true;
+        UserDao::update_by_id(&env.pool, &banned).await.unwrap();
+        let page: PageByUserResp = anonymous.get_query_paged(
+            "/playlist/page_by_user",
+            &PageByUserReq { user_id: user.uid },
+            &PageQuery { page_index: 0, page_size: 10 },
+        ).await.parse_resp().await.unwrap();
+        assert_eq!(0, page.total);
+        assert!(page.items.is_empty());
+    }).await;
+}
+
+#[tokio::test]
+async fn test_official_weekly() {
+    with_test_environment(|env| async move {
+        let archive = env.api.call::<PlaylistOfficialWeeklyArchive>(&PageQuery { page_index: 0, page_size: 10 }).await.unwrap();
+        let Some(latest) = archive.items.first() else {
+            let err = env.api.call::<PlaylistOfficialWeekly>(&OfficialWeeklyReq { week_start: None }).await.unwrap_err();
+            assert_eq!("not_found", err.code);
+            return;
+        };
+
+        let resp = env.api.call::<PlaylistOfficialWeekly>(&OfficialWeeklyReq { week_start: None }).await.unwrap();
+        assert_eq!(latest.week_start, resp.week_start);
+        assert_eq!(latest.playlist.id, resp.detail.playlist_info.id);
+        assert!(resp.detail.playlist_info.is_public);
+        // An archived week
+        let resp = env.api.call::<PlaylistOfficialWeekly>(&OfficialWeeklyReq { week_start: Some(latest.week_start) }).await.unwrap();
+        assert_eq!(latest.playlist.id, resp.detail.playlist_info.id);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_publish_official_weekly() {
+    with_test_environment(|mut env| async move {
+        let official = with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        let pick = AddFeaturedReq { collection: "weekly_picks".to_string(), song_id: song.id, position: 0, note: None };
+        env.api.post("/admin/featured/add", &pick).await.parse_resp::<()>().await.unwrap();
+
+        // A past week not published yet
+        let week = loop {
+            let week = NaiveDate::from_ymd_opt(2000, 1, 3).unwrap() + Days::new(rand::random_range(0..2000) * 7);
+            let resp = env.api.call::<PlaylistOfficialWeekly>(&OfficialWeeklyReq { week_start: Some(week) }).await;
+            if resp.is_err_and(|e| e.code == "not_found") {
+                break week;
+            }
+        };
+        let req = PublishWeeklySelectionReq { official_uid: official.uid, week };
+        let playlist_id = env.api.call::<TestWeeklySelectionPublish>(&req).await.unwrap().playlist_id.unwrap();
+        // Published once per week
+        assert_eq!(None, env.api.call::<TestWeeklySelectionPublish>(&req).await.unwrap().playlist_id);
+
+        let resp = env.api.call::<PlaylistOfficialWeekly>(&OfficialWeeklyReq { week_start: Some(week) }).await.unwrap();
+        assert_eq!(playlist_id, resp.detail.playlist_info.id);
+        assert_eq!(official.uid, resp.detail.creator_profile.uid);
+        // The picks go first
+        assert_eq!(song.id, resp.detail.songs[0].song_id);
+
+        let mut page = PageQuery { page_index: 0, page_size: 50 };
+        let item = loop {
+            let archive = env.api.call::<PlaylistOfficialWeeklyArchive>(&page).await.unwrap();
+            if let Some(item) = archive.items.into_iter().find(|x| x.week_start == week) {
+                break item;
+            }
+            assert!((page.page_index + 1) * page.page_size < archive.total, "the week should be archived");
+            page.page_index += 1;
+        };
+        assert_eq!(playlist_id, item.playlist.id);
+    }).await;
+}
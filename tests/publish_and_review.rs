@@ -1,16 +1,30 @@
 mod common;
 
 use crate::common::auth::{with_new_random_test_user, with_new_test_user, with_test_contributor_user};
-use crate::common::{assert_is_err, CommonParse, TestEnvironment};
+use crate::common::song::publish_template;
+use crate::common::{assert_is_err, CommonParse};
 use crate::common::{assert_is_ok, with_test_environment, ApiClient};
 use chrono::Utc;
 use hachimi_world_server::db::creator::{Creator, CreatorDao};
+use hachimi_world_server::db::email_delivery::{EmailDeliveryDao, IEmailDeliveryDao};
+use hachimi_world_server::db::song_publishing_review::{self, SongPublishingReviewDao};
+use hachimi_world_server::db::user::UserDao;
 use hachimi_world_server::db::CrudDao;
-use hachimi_world_server::service::song::{CreationTypeInfo, ExternalLink};
+use hachimi_world_server::file_hosting::local::{LocalFileHost, LocalStorageCfg};
+use hachimi_world_server::file_hosting::{FileHost, UploadOptions};
+use hachimi_world_server::service::email_delivery;
+use hachimi_world_server::service::mailer::transport::DeliveryReceipt;
+use hachimi_world_server::service::upload_cleanup::{self, UploadCleanupCfg};
+use hachimi_world_server::service::song::{CreationTypeInfo, ExternalLink, DELETED_SONG_REVIEW_COMMENT};
+use hachimi_world_server::web::limits::AUDIO_CHUNK_BYTES;
+use hachimi_world_server::web::pagination::PageQuery;
 use hachimi_world_server::web::routes::publish::jmid::{JmidCheckPReq, JmidCheckPResp, JmidMineResp};
-use hachimi_world_server::web::routes::publish::review::{ApproveReviewReq, RejectReviewReq, ReviewCommentCreateReq, ReviewCommentDeleteReq, ReviewCommentListReq, ReviewCommentListResp, ReviewHistoryListReq, ReviewHistoryListResp, ReviewModifyReq};
-use hachimi_world_server::web::routes::publish::{review, CreationInfo, PageReq, PageResp, ProductionItem, PublishReq, PublishResp, UploadAudioFileResp, UploadImageResp};
+use hachimi_world_server::web::routes::publish::review::{ApproveReviewReq, RejectReviewReq, ReviewCommentCreateReq, ReviewCommentCreateResp, ReviewCommentDeleteReq, ReviewCommentListReq, ReviewCommentListResp, ReviewHistoryListReq, ReviewHistoryListResp, ReviewModifyReq, SongReviewHistoryReq};
+use hachimi_world_server::web::routes::publish::template::{ExportTemplateReq, PublishTemplate};
+use hachimi_world_server::web::routes::publish::{review, ChunkedUploadReq, ConfirmAudioUploadReq, CreateChunkedUploadReq, CreationInfo, DeleteReq, ModifyReq, ModifyResp, PresignAudioUploadReq, PageResp, ProductionItem, PublishReq, PublishResp, UploadAudioFileResp, UploadImageResp};
 use hachimi_world_server::web::routes::song::{DetailReq, DetailResp, TagCreateReq, TagSearchReq, TagSearchResp};
+use hachimi_world_server::web::api::{PublishAudioChunkComplete, PublishAudioChunkCreate, PublishAudioChunkStatus, PublishReviewApprove, PublishReviewDashboard, SongDelete, SongDetail, SongReviewHistory, SongUploadConfirm, SongUploadPresign};
+use bytes::Bytes;
 use reqwest::multipart::{Form, Part};
 use std::fs;
 use std::time::Duration;
@@ -97,6 +111,8 @@ async fn test_publish_with_random_jmid() {
                         explicit: Some(false),
                         jmid: None,
                         comment: None,
+                        template: None,
+                        localized_titles: vec![],
                     },
                 )
                 .await.parse_resp().await.unwrap();
@@ -105,21 +121,21 @@ async fn test_publish_with_random_jmid() {
             tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
 
-        let resp: PageResp = env.api.get_query("/publish/review/page", &PageReq {
+        let resp: PageResp = env.api.get_query("/publish/review/page", &PageQuery {
             page_index: 0,
             page_size: 20,
         }).await.parse_resp().await.unwrap();
-        assert_eq!(resp.data.len(), test_song_titles.len());
+        assert_eq!(resp.items.len(), test_song_titles.len());
 
         let contributor_user = with_test_contributor_user(&mut env).await;
 
         // Test get the submitted review
-        let resp: PageResp = env.api.get_query("/publish/review/page_contributor", &PageReq {
+        let resp: PageResp = env.api.get_query("/publish/review/page_contributor", &PageQuery {
             page_index: 0,
             page_size: 20,
         }).await.parse_resp().await.unwrap();
-        let first_review = resp.data.first().unwrap();
-        let second_review = resp.data.get(1).unwrap();
+        let first_review = resp.items.first().unwrap();
+        let second_review = resp.items.get(1).unwrap();
         assert_eq!(first_review.display_id, last_song_display_id);
 
         // Test reject second review
@@ -167,7 +183,7 @@ async fn create_tags(api: &ApiClient) {
 async fn test_get_reviews() {
     with_test_environment(|mut env| async move {
         let _contributor_user = with_test_contributor_user(&mut env).await;
-        let resp: PageResp = env.api.get_query("/publish/review/page_contributor", &PageReq {
+        let resp: PageResp = env.api.get_query("/publish/review/page_contributor", &PageQuery {
             page_index: 0,
             page_size: 20,
         }).await.parse_resp().await.unwrap();
@@ -175,6 +191,20 @@ async fn test_get_reviews() {
     }).await
 }
 
+#[tokio::test]
+async fn test_review_dashboard() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let resp = env.api.call::<PublishReviewDashboard>(&()).await;
+        assert_eq!("permission_denied", resp.unwrap_err().code);
+
+        with_test_contributor_user(&mut env).await;
+        let resp = env.api.call::<PublishReviewDashboard>(&()).await.unwrap();
+        assert_eq!(14, resp.daily_submissions.len());
+        assert!(resp.my_stats.last_14_days_count <= resp.my_stats.approved_count + resp.my_stats.rejected_count);
+    }).await
+}
+
 #[tokio::test]
 async fn test_grant() {
     with_test_environment(|mut env| async move {
@@ -320,45 +350,54 @@ async fn test_publish_with_jmid() {
     }).await
 }
 
-async fn publish_template(env: &TestEnvironment) -> PublishReq {
-    // Upload a song
-    let upload_resp: UploadAudioFileResp = env.api
-        .post_raw("/publish/upload_audio_file")
-        .multipart(Form::new().part("file", Part::bytes(fs::read(".local/test_res/test.mp3").unwrap())))
-        .send().await.unwrap().parse_resp().await.unwrap();
-
-    // Upload a cover
-    let upload_img_resp: UploadImageResp = env
-        .api
-        .post_raw("/publish/upload_cover_image")
-        .multipart(Form::new().part("file", Part::bytes(fs::read(".local/test_Res/test.webp").unwrap())))
-        .send().await.unwrap().parse_resp().await.unwrap();
-
-    PublishReq {
-        song_temp_id: upload_resp.temp_id.clone(),
-        cover_temp_id: upload_img_resp.temp_id.clone(),
-        title: "Test".to_string(),
-        subtitle: "A test music".to_string(),
-        description: "This is a fucking test music".to_string(),
-        lyrics: "哈基米哈基米哈基米".to_string(),
-        tag_ids: vec![],
-        creation_info: CreationInfo {
-            creation_type: 0,
-            origin_info: Some(CreationTypeInfo {
-                song_display_id: None,
-                title: Some("原作".into()),
-                artist: Some("群星".into()),
-                url: None,
-                origin_type: 0,
-            }),
-            derivative_info: None,
-        },
-        production_crew: vec![],
-        external_links: vec![],
-        explicit: Some(false),
-        jmid: Some("JM-ABCD-000".into()),
-        comment: Some("Test comment in review".into()),
-    }
+#[tokio::test]
+async fn test_delete_song() {
+    with_test_environment(|mut env| async move {
+        let uploader = with_new_random_test_user(&mut env).await;
+        let template = PublishReq { jmid: None, ..publish_template(&env).await };
+        let publish_resp: PublishResp = env.api.post("/publish/publish", &template).await.parse_resp().await.unwrap();
+
+        with_test_contributor_user(&mut env).await;
+        env.api.call::<PublishReviewApprove>(&ApproveReviewReq {
+            review_id: publish_resp.review_id,
+            comment: None,
+        }).await.unwrap();
+        let song = env.api.call::<SongDetail>(&DetailReq { id: publish_resp.song_display_id.clone() }).await.unwrap();
+
+        // Only the uploader can delete it
+        let resp = env.api.call::<SongDelete>(&DeleteReq { song_id: song.id }).await;
+        assert_eq!("permission_denied", resp.unwrap_err().code);
+
+        env.api.set_token(uploader.token.access_token.clone());
+        let modify_resp: ModifyResp = env.api.post("/publish/modify", &ModifyReq {
+            song_id: song.id,
+            song_temp_id: None,
+            cover_temp_id: None,
+            title: "Modified".to_string(),
+            subtitle: template.subtitle.clone(),
+            description: template.description.clone(),
+            lyrics: template.lyrics.clone(),
+            tag_ids: vec![],
+            creation_info: template.creation_info.clone(),
+            production_crew: vec![],
+            external_links: vec![],
+            explicit: false,
+            comment: None,
+            localized_titles: None,
+        }).await.parse_resp().await.unwrap();
+
+        env.api.call::<SongDelete>(&DeleteReq { song_id: song.id }).await.unwrap();
+        assert!(env.api.call::<SongDetail>(&DetailReq { id: song.display_id.clone() }).await.is_err());
+        let resp = env.api.call::<SongDelete>(&DeleteReq { song_id: song.id }).await;
+        assert_eq!("song_not_found", resp.unwrap_err().code);
+
+        // The pending modification is rejected along with the song
+        let review = SongPublishingReviewDao::get_by_id(&env.pool, modify_resp.review_id).await.unwrap().unwrap();
+        assert_eq!(song_publishing_review::STATUS_REJECTED, review.status);
+        assert_eq!(Some(DELETED_SONG_REVIEW_COMMENT.to_string()), review.review_comment);
+        let review = SongPublishingReviewDao::get_by_id(&env.pool, publish_resp.review_id).await.unwrap().unwrap();
+        assert_eq!(song_publishing_review::STATUS_APPROVED, review.status);
+    }).await
 }
 
 #[tokio::test]
@@ -392,6 +431,7 @@ async fn test_review_modify_and_history() {
             external_links: template.external_links.clone(),
             explicit: template.explicit.unwrap_or(false),
             comment: updated_comment.clone(),
+            localized_titles: None,
         }).await;
         assert_is_ok(resp).await;
 
@@ -404,19 +444,18 @@ async fn test_review_modify_and_history() {
         assert_eq!(detail.description, updated_description);
         assert_eq!(detail.lyrics, updated_lyrics);
         assert_eq!(detail.comment, updated_comment);
+        // The localized titles are kept if absent
+        assert_eq!(detail.localized_titles, Some(template.localized_titles.clone()));
 
-        let history: ReviewHistoryListResp = env.api.get_query(
+        let history: ReviewHistoryListResp = env.api.get_query_paged(
             "/publish/review/history/list",
-            &ReviewHistoryListReq {
-                review_id: publish_resp.review_id,
-                page_index: 0,
-                page_size: 20,
-            },
+            &ReviewHistoryListReq { review_id: publish_resp.review_id },
+            &PageQuery { page_index: 0, page_size: 20 },
         ).await.parse_resp().await.unwrap();
         assert_eq!(history.total, 2);
-        assert_eq!(history.data.len(), 2);
-        assert_eq!(history.data[0].note, Some("Updated note for contributors".to_string()));
-        assert_eq!(history.data[0].snapshot.as_ref().map(|x| x.title.clone()), Some("Updated Test Title".to_string()));
+        assert_eq!(history.items.len(), 2);
+        assert_eq!(history.items[0].note, Some("Updated note for contributors".to_string()));
+        assert_eq!(history.items[0].snapshot.as_ref().map(|x| x.title.clone()), Some("Updated Test Title".to_string()));
 
         let other_user = with_new_random_test_user(&mut env).await;
         let resp = env.api.post("/publish/review/modify", &ReviewModifyReq {
@@ -443,35 +482,63 @@ async fn test_review_modify_and_history() {
             external_links: vec![],
             explicit: false,
             comment: Some("Should fail".to_string()),
+            localized_titles: None,
         }).await;
         assert_is_err(resp).await;
 
         env.api.set_token(uploader.token.access_token.clone());
         let contributor = with_test_contributor_user(&mut env).await;
         env.api.set_token(contributor.token.access_token.clone());
-        let history: ReviewHistoryListResp = env.api.get_query(
+        let history: ReviewHistoryListResp = env.api.get_query_paged(
             "/publish/review/history/list",
-            &ReviewHistoryListReq {
-                review_id: publish_resp.review_id,
-                page_index: 0,
-                page_size: 20,
-            },
+            &ReviewHistoryListReq { review_id: publish_resp.review_id },
+            &PageQuery { page_index: 0, page_size: 20 },
         ).await.parse_resp().await.unwrap();
-        assert_eq!(history.data.len(), 2);
+        assert_eq!(history.items.len(), 2);
 
         env.api.set_token(other_user.token.access_token);
-        let resp = env.api.get_query(
+        let resp = env.api.get_query_paged(
             "/publish/review/history/list",
-            &ReviewHistoryListReq {
-                review_id: publish_resp.review_id,
-                page_index: 0,
-                page_size: 20,
-            },
+            &ReviewHistoryListReq { review_id: publish_resp.review_id },
+            &PageQuery { page_index: 0, page_size: 20 },
         ).await;
         assert_is_err(resp).await;
     }).await;
 }
 
+#[tokio::test]
+async fn test_song_review_history() {
+    with_test_environment(|mut env| async move {
+        let uploader = with_new_random_test_user(&mut env).await;
+        let template = publish_template(&env).await;
+        let publish_resp: PublishResp = env.api.post("/publish/publish", &template)
+            .await
+            .parse_resp()
+            .await
+            .unwrap();
+        let req = SongReviewHistoryReq { display_id: publish_resp.song_display_id.clone() };
+
+        let contributor = with_test_contributor_user(&mut env).await;
+        let resp = env.api.post("/publish/review/reject", &RejectReviewReq {
+            review_id: publish_resp.review_id,
+            comment: "Reject for testing".into(),
+        }).await;
+        assert_is_ok(resp).await;
+
+        env.api.set_token(uploader.token.access_token.clone());
+        let history = env.api.call::<SongReviewHistory>(&req).await.unwrap();
+        assert_eq!(1, history.rounds.len());
+        assert_eq!(publish_resp.review_id, history.rounds[0].review_id);
+        assert_eq!(Some("Reject for testing".to_string()), history.rounds[0].review_comment);
+        assert_eq!(Some(contributor.uid), history.rounds[0].reviewer.as_ref().map(|x| x.uid));
+
+        // Invisible for the others
+        with_new_random_test_user(&mut env).await;
+        let err = env.api.call::<SongReviewHistory>(&req).await.unwrap_err();
+        assert_eq!("permission_denied", err.code);
+    }).await
+}
+
 #[tokio::test]
 async fn test_review_comments() {
     with_test_environment(|mut env| async move {
@@ -498,26 +565,22 @@ async fn test_review_comments() {
         }).await;
         assert_is_ok(resp).await;
 
-        let resp: ReviewCommentListResp = env.api.get_query("/publish/review/comment/list", &ReviewCommentListReq {
+        let resp: ReviewCommentListResp = env.api.get_query_paged("/publish/review/comment/list", &ReviewCommentListReq {
             review_id: publish_resp.review_id,
-            page_index: 0,
-            page_size: 20,
-        }).await.parse_resp().await.unwrap();
-        assert_eq!(resp.data.len(), 2);
-        assert!(resp.data.iter().any(|x| x.content == maintainer_comment));
-        assert!(resp.data.iter().any(|x| x.content == uploader_comment));
+        }, &PageQuery { page_index: 0, page_size: 20 }).await.parse_resp().await.unwrap();
+        assert_eq!(resp.items.len(), 2);
+        assert!(resp.items.iter().any(|x| x.content == maintainer_comment));
+        assert!(resp.items.iter().any(|x| x.content == uploader_comment));
 
-        let uploader_comment_id = resp.data.iter()
+        let uploader_comment_id = resp.items.iter()
             .find(|x| x.content == uploader_comment)
             .map(|x| x.id)
             .unwrap();
 
         let other_user = with_new_random_test_user(&mut env).await;
-        let resp = env.api.get_query("/publish/review/comment/list", &ReviewCommentListReq {
+        let resp = env.api.get_query_paged("/publish/review/comment/list", &ReviewCommentListReq {
             review_id: publish_resp.review_id,
-            page_index: 0,
-            page_size: 20,
-        }).await;
+        }, &PageQuery { page_index: 0, page_size: 20 }).await;
         assert_is_err(resp).await;
 
         env.api.set_token(uploader.token.access_token.clone());
@@ -527,13 +590,27 @@ async fn test_review_comments() {
         assert_is_ok(resp).await;
 
         env.api.set_token(maintainer.token.access_token.clone());
-        let resp: ReviewCommentListResp = env.api.get_query("/publish/review/comment/list", &ReviewCommentListReq {
+        let resp: ReviewCommentListResp = env.api.get_query_paged("/publish/review/comment/list", &ReviewCommentListReq {
             review_id: publish_resp.review_id,
-            page_index: 0,
-            page_size: 20,
+        }, &PageQuery { page_index: 0, page_size: 20 }).await.parse_resp().await.unwrap();
+        assert_eq!(resp.items.len(), 1);
+        assert_eq!(resp.items[0].content, maintainer_comment);
+
+        // Mention the uploader
+        let uploader_name = UserDao::get_by_id(&env.pool, uploader.uid).await.unwrap().unwrap().username;
+        let resp: ReviewCommentCreateResp = env.api.post("/publish/review/comment/create", &ReviewCommentCreateReq {
+            review_id: publish_resp.review_id,
+            content: format!("@{uploader_name} please check, @no_such_user_name"),
         }).await.parse_resp().await.unwrap();
-        assert_eq!(resp.data.len(), 1);
-        assert_eq!(resp.data[0].content, maintainer_comment);
+        // The unknown name is plain text
+        assert_eq!(1, resp.mentions.len());
+        assert_eq!(uploader.uid, resp.mentions[0].uid);
+
+        let resp: ReviewCommentCreateResp = env.api.post("/publish/review/comment/create", &ReviewCommentCreateReq {
+            review_id: publish_resp.review_id,
+            content: "@no_such_user_name".to_string(),
+        }).await.parse_resp().await.unwrap();
+        assert!(resp.mentions.is_empty());
 
         let resp = env.api.post("/publish/review/comment/create", &ReviewCommentCreateReq {
             review_id: publish_resp.review_id,
@@ -549,3 +626,277 @@ async fn test_review_comments() {
         assert_is_err(resp).await;
     }).await;
 }
+
+#[tokio::test]
+async fn test_export_template() {
+    with_test_environment(|mut env| async move {
+        let _uploader = with_new_random_test_user(&mut env).await;
+        let mut req = publish_template(&env).await;
+        req.jmid = None;
+        req.production_crew = vec![ProductionItem { role: "Mixing".into(), uid: None, name: Some("Someone".into()) }];
+        req.external_links = vec![ExternalLink { platform: "bilibili".into(), url: "https://www.bilibili.com/video/BV1xx411c7mD".into() }];
+        let publish_resp: PublishResp = env.api.post("/publish/publish", &req).await.parse_resp().await.unwrap();
+
+        let exported: PublishTemplate = env.api.get_query(
+            "/publish/export_template",
+            &ExportTemplateReq { review_id: publish_resp.review_id },
+        ).await.parse_resp().await.unwrap();
+        assert_eq!(exported.title, req.title);
+        assert_eq!(exported.production_crew.len(), 1);
+        assert_eq!(exported.external_links.len(), 1);
+        assert!(exported.creation_info.origin_info.is_some());
+
+        // Publish again with the template, the given fields take precedence
+        let again = publish_template(&env).await;
+        let resp: PublishResp = env.api.post("/publish/publish", &PublishReq {
+            title: "From template".into(),
+            production_crew: vec![],
+            external_links: vec![],
+            jmid: None,
+            template: Some(exported),
+            ..again
+        }).await.parse_resp().await.unwrap();
+        let detail: review::DetailResp = env.api.get_query(
+            "/publish/review/detail",
+            &review::DetailReq { review_id: resp.review_id },
+        ).await.parse_resp().await.unwrap();
+        assert_eq!(detail.title, "From template");
+        assert_eq!(detail.production_crew.len(), 1);
+        assert_eq!(detail.external_link.len(), 1);
+
+        // Not visible to the other users
+        let _other_user = with_new_random_test_user(&mut env).await;
+        let resp = env.api.get_query(
+            "/publish/export_template",
+            &ExportTemplateReq { review_id: publish_resp.review_id },
+        ).await;
+        assert_is_err(resp).await;
+    }).await
+}
+
+#[tokio::test]
+async fn test_direct_audio_upload() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let file = fs::read(".local/test.mp3").unwrap();
+        let sha256 = hex::encode(openssl::sha::sha256(&file));
+
+        let resp = env.api.call::<SongUploadPresign>(&PresignAudioUploadReq {
+            format: "mp3".to_string(),
+            size: file.len() as u64,
+            sha256: sha256.clone(),
+        }).await;
+        let presign = match resp {
+            Ok(x) => x,
+            // The local storage doesn't support it
+            Err(e) if e.code == "direct_upload_unsupported" => return,
+            Err(e) => panic!("Failed to presign: {:?}", e),
+        };
+
+        // Not uploaded yet
+        let resp = env.api.call::<SongUploadConfirm>(&ConfirmAudioUploadReq { upload_id: presign.upload_id.clone() }).await;
+        assert_eq!("upload_not_found", resp.unwrap_err().code);
+
+        let mut request = reqwest::Client::new().put(&presign.request.url).body(file);
+        for (name, value) in &presign.request.headers {
+            request = request.header(name, value);
+        }
+        assert!(request.send().await.unwrap().status().is_success());
+
+        let resp = env.api.call::<SongUploadConfirm>(&ConfirmAudioUploadReq { upload_id: presign.upload_id.clone() }).await.unwrap();
+        assert!(!resp.temp_id.is_empty());
+
+        // The upload is consumed
+        let resp = env.api.call::<SongUploadConfirm>(&ConfirmAudioUploadReq { upload_id: presign.upload_id }).await;
+        assert_eq!("upload_not_found", resp.unwrap_err().code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_chunked_audio_upload() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        // Larger than two chunks
+        let file = synthetic_flac(2 * AUDIO_CHUNK_BYTES + 1024 * 1024);
+
+        let upload = env.api.call::<PublishAudioChunkCreate>(&CreateChunkedUploadReq {
+            format: "flac".to_string(),
+            size: file.len() as u64,
+        }).await.unwrap();
+        let chunks = file.chunks(upload.chunk_size as usize).collect::<Vec<_>>();
+        assert_eq!(3, upload.chunk_count);
+        assert_eq!(upload.chunk_count as usize, chunks.len());
+
+        let upload_chunk = |index: usize, bytes: Vec<u8>| {
+            let json = serde_json::json!({ "upload_id": upload.upload_id, "index": index }).to_string();
+            env.api.post_raw("/publish/upload_audio_chunk")
+                .multipart(Form::new().text("json", json).part("file", Part::bytes(bytes)))
+                .send()
+        };
+        // The size of a chunk is fixed
+        assert_is_err(upload_chunk(0, vec![0; 10]).await.unwrap()).await;
+        // Uploaded in reverse, and the first is left for resuming
+        for (index, chunk) in chunks.iter().enumerate().skip(1).rev() {
+            assert_is_ok(upload_chunk(index, chunk.to_vec()).await.unwrap()).await;
+        }
+
+        let req = ChunkedUploadReq { upload_id: upload.upload_id.clone() };
+        let resp = env.api.call::<PublishAudioChunkComplete>(&req).await;
+        assert_eq!("upload_incomplete", resp.unwrap_err().code);
+        let status = env.api.call::<PublishAudioChunkStatus>(&req).await.unwrap();
+        assert_eq!((1..chunks.len() as u32).collect::<Vec<_>>(), status.uploaded);
+
+        assert_is_ok(upload_chunk(0, chunks[0].to_vec()).await.unwrap()).await;
+        let resp = env.api.call::<PublishAudioChunkComplete>(&req).await.unwrap();
+        assert!(!resp.temp_id.is_empty());
+        assert_eq!(Some("Synthetic".to_string()), resp.title);
+
+        // The upload is consumed
+        let resp = env.api.call::<PublishAudioChunkComplete>(&req).await;
+        assert_eq!("upload_not_found", resp.unwrap_err().code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_cleanup_expired_uploads() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let root = std::env::temp_dir().join(format!("hachimi-test-{}", uuid::Uuid::new_v4()));
+        let file_host = LocalFileHost::new(LocalStorageCfg {
+            root: root.clone(),
+            public_base_url: "http://localhost/files".to_string(),
+        }).await.unwrap();
+        let options = UploadOptions::audio("audio/mpeg");
+
+        let orphan = file_host.upload(Bytes::from_static(b"orphan"), &format!("songs/{}.mp3", uuid::Uuid::new_v4()), &options).await.unwrap();
+        let avatar = file_host.upload(Bytes::from_static(b"avatar"), &format!("images/{}.webp", uuid::Uuid::new_v4()), &options).await.unwrap();
+        let mut user = UserDao::get_by_id(&env.pool, user.uid).await.unwrap().unwrap();
+        user.avatar_url = Some(avatar.public_url.clone());
+        UserDao::update_by_id(&env.pool, &user).await.unwrap();
+
+        let multipart_key = format!("songs/{}.mp3", uuid::Uuid::new_v4());
+        let upload_id = file_host.create_multipart_upload(&multipart_key, &options).await.unwrap();
+        let part = file_host.upload_part(&multipart_key, &upload_id, 1, Bytes::from_static(b"part")).await.unwrap();
+
+        // Expired right away
+        upload_cleanup::track_temp_for(&mut env.redis, &orphan.key, 0).await;
+        upload_cleanup::track_temp_for(&mut env.redis, &avatar.key, 0).await;
+        upload_cleanup::track_temp_multipart(&mut env.redis, &multipart_key, &upload_id, 0).await;
+        let cfg = UploadCleanupCfg { grace_secs: 0, batch_size: 1000 };
+        let summary = upload_cleanup::cleanup_expired(&file_host, &env.pool, &mut env.redis, &cfg).await.unwrap();
+        assert!(summary.deleted >= 1 && summary.kept >= 1 && summary.aborted >= 1, "{:?}", summary);
+
+        assert!(file_host.head(&orphan.key).await.unwrap().is_none());
+        // Referenced by the avatar
+        assert!(file_host.head(&avatar.key).await.unwrap().is_some());
+        // The parts are discarded
+        assert!(file_host.complete_multipart_upload(&multipart_key, &upload_id, &[part]).await.is_err());
+
+        // Cleaned up once
+        let summary = upload_cleanup::cleanup_expired(&file_host, &env.pool, &mut env.redis, &cfg).await.unwrap();
+        assert_eq!(0, summary.aborted);
+        tokio::fs::remove_dir_all(&root).await.unwrap();
+    }).await;
+}
+
+/// A valid FLAC of a sine wave at least `min_bytes` long, stored verbatim without compression
+fn synthetic_flac(min_bytes: usize) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 4096;
+    const SAMPLE_RATE: u64 = 44100;
+    // 2 channels of 16 bits
+    let frame_count = min_bytes.div_ceil(BLOCK_SIZE * 4);
+    let total_samples = (frame_count * BLOCK_SIZE) as u64;
+
+    let mut flac = b"fLaC".to_vec();
+    // STREAMINFO, the frame sizes and MD5 are unknown
+    flac.extend([0x00, 0x00, 0x00, 34]);
+    flac.extend((BLOCK_SIZE as u16).to_be_bytes());
+    flac.extend((BLOCK_SIZE as u16).to_be_bytes());
+    flac.extend([0; 6]);
+    flac.extend((SAMPLE_RATE << 44 | 1 << 41 | 15 << 36 | total_samples).to_be_bytes());
+    flac.extend([0; 16]);
+    // VORBIS_COMMENT, the last metadata block
+    let vendor = b"hachimi";
+    let comment = b"TITLE=Synthetic";
+    let len = 4 + vendor.len() + 4 + 4 + comment.len();
+    flac.extend([0x84, (len >> 16) as u8, (len >> 8) as u8, len as u8]);
+    flac.extend((vendor.len() as u32).to_le_bytes());
+    flac.extend(vendor);
+    flac.extend(1u32.to_le_bytes());
+    flac.extend((comment.len() as u32).to_le_bytes());
+    flac.extend(comment);
+
+    for frame_number in 0..frame_count {
+        let start = flac.len();
+        // Fixed block size of 4096, 44.1kHz, independent stereo of 16 bits
+        flac.extend([0xFF, 0xF8, 0xC9, 0x18]);
+        // The frame number in the UTF-8 coding
+        match frame_number {
+            0..0x80 => flac.push(frame_number as u8),
+            0x80..0x800 => flac.extend([0xC0 | (frame_number >> 6) as u8, 0x80 | (frame_number & 0x3F) as u8]),
+            _ => flac.extend([
+                0xE0 | (frame_number >> 12) as u8,
+                0x80 | ((frame_number >> 6) & 0x3F) as u8,
+                0x80 | (frame_number & 0x3F) as u8,
+            ]),
+        }
+        flac.push(crc8(&flac[start..]));
+        // Both channels are the same VERBATIM subframe
+        let mut subframe = vec![0x02];
+        for i in 0..BLOCK_SIZE {
+            let t = (frame_number * BLOCK_SIZE + i) as f64 / SAMPLE_RATE as f64;
+            let sample = ((t * 440.0 * std::f64::consts::TAU).sin() * 8000.0) as i16;
+            subframe.extend(sample.to_be_bytes());
+        }
+        flac.extend(&subframe);
+        flac.extend(&subframe);
+        let crc = crc16(&flac[start..]);
+        flac.extend(crc.to_be_bytes());
+    }
+    flac
+}
+
+fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |mut crc, x| {
+        crc ^= x;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { crc << 1 ^ 0x07 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0u16, |mut crc, x| {
+        crc ^= (*x as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { crc << 1 ^ 0x8005 } else { crc << 1 };
+        }
+        crc
+    })
+}
+
+#[tokio::test]
+async fn test_track_review_result_once() {
+    with_test_environment(|env| async move {
+        let review_id = -rand::random_range(1..i64::MAX / 2);
+        let to = format!("test_{}@example.com", uuid::Uuid::new_v4());
+        let since = Utc::now() - chrono::Duration::minutes(1);
+        let hash = email_delivery::recipient_hash(&to);
+        let count = || EmailDeliveryDao::count_since(&env.pool, &hash, email_delivery::TYPE_REVIEW_RESULT, since);
+        let sent = || async { Ok(Some(DeliveryReceipt { provider: "test".to_string(), message_id: None })) };
+
+        // The retried approvals send the result once
+        for _ in 0..2 {
+            email_delivery::track_review_result(&env.pool, env.redis.clone(), review_id, song_publishing_review::STATUS_APPROVED, &to, sent()).await.unwrap();
+        }
+        assert_eq!(1, count().await.unwrap());
+
+        // A failed sending is recorded and released, so the retry sends it
+        let failed = async { Err(anyhow::anyhow!("SMTP is down")) };
+        assert!(email_delivery::track_review_result(&env.pool, env.redis.clone(), review_id, song_publishing_review::STATUS_REJECTED, &to, failed).await.is_err());
+        assert_eq!(2, count().await.unwrap());
+        email_delivery::track_review_result(&env.pool, env.redis.clone(), review_id, song_publishing_review::STATUS_REJECTED, &to, sent()).await.unwrap();
+        assert_eq!(3, count().await.unwrap());
+    }).await;
+}
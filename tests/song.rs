@@ -1,22 +1,39 @@
 mod common;
 
-use crate::common::auth::with_new_random_test_user;
+use crate::common::auth::{with_new_random_test_user, with_test_contributor_user};
 use crate::common::{assert_is_err, assert_is_ok, CommonParse};
 use crate::common::{with_test_environment, TestEnvironment};
+use crate::common::song::publish_approved_song;
 use futures::future::join_all;
-use hachimi_world_server::service::song_like;
+use redis::AsyncCommands;
+use hachimi_world_server::db::song::{ISongDao, SongDao, SongPlay};
+use hachimi_world_server::db::CrudDao;
+use hachimi_world_server::service::{song_like, song_play, song_stats};
+use hachimi_world_server::util::redlock::RedLock;
+use hachimi_world_server::service::song_share::SharePlatform;
+use hachimi_world_server::web::api::{SearchFeedback, SongCommentCreate, SongCommentDelete, SongCommentPage, SongCommentReply, SongCommentReport, SongReport, SongSearchSuggest, SongShare};
+use hachimi_world_server::web::pagination::PageQuery;
+use hachimi_world_server::web::routes::admin::{HideSongReq, HideSongResp, PageSongReportsResp, ResolveSongReportReq};
+use hachimi_world_server::web::routes::play_history::TouchReq;
+use hachimi_world_server::web::routes::search::SearchFeedbackReq;
+use hachimi_world_server::web::routes::song_comment::{CommentIdReq, CreateCommentReq, PageCommentQuery, PageCommentReq, PageCommentResp, ReplyCommentReq, ReportCommentReq};
 use hachimi_world_server::web::routes::song::{
     DetailReq,
     DetailResp,
     LikeReq,
+    LikeResp,
     LikeStatusResp,
-    MyLikesReq,
     MyLikesResp,
+    NotInterestedReq,
     PageByUserReq,
+    PageByUserResp,
     RecentReq,
     RecentResp,
+    ReportSongReq,
     SearchReq,
     SearchResp,
+    SearchSuggestReq,
+    ShareReq,
     TagCreateReq,
     TagSearchReq,
     TagSearchResp,
@@ -70,6 +87,26 @@ async fn test_get_recommend_songs() {
     }).await;
 }
 
+#[tokio::test]
+async fn test_not_interested() {
+    with_test_environment(|mut env| async move {
+        // At least a song to recommend
+        with_new_random_test_user(&mut env).await;
+        publish_approved_song(&mut env).await;
+        let _user = with_new_random_test_user(&mut env).await;
+        let before: RecentResp = env.api.get("/song/recommend").await.parse_resp().await.unwrap();
+        let song = before.songs.first().expect("the recommendations should not be empty");
+
+        let resp = env.api.post("/song/not_interested", &NotInterestedReq { song_id: song.id }).await;
+        assert_is_ok(resp).await;
+        let after: RecentResp = env.api.get("/song/recommend").await.parse_resp().await.unwrap();
+        assert!(after.songs.iter().all(|x| x.id != song.id), "the song should be removed from the recommendations");
+
+        let resp = env.api.post("/song/not_interested", &NotInterestedReq { song_id: -1 }).await;
+        assert_is_err(resp).await;
+    }).await;
+}
+
 #[tokio::test]
 async fn test_get_weekly_hot_songs() {
     with_test_environment(|mut env| async move {
@@ -91,6 +128,14 @@ async fn test_search() {
             sort_by: None,
         }).await.parse_resp().await.unwrap();
         println!("{:#?}", search_result);
+
+        // Report a click, and a song not in the hits
+        let query_id = search_result.query_id.unwrap();
+        if let Some(hit) = search_result.hits.first() {
+            env.api.call::<SearchFeedback>(&SearchFeedbackReq { query_id: query_id.clone(), song_id: hit.id }).await.unwrap();
+        }
+        let err = env.api.call::<SearchFeedback>(&SearchFeedbackReq { query_id, song_id: -1 }).await.unwrap_err();
+        assert_eq!("not_a_hit", err.code);
     }).await
 }
 
@@ -153,36 +198,30 @@ async fn click_farming_likes(env: &TestEnvironment, song_id: i64, number: i64) {
 async fn test_page_by_users() {
     with_test_environment(|mut env| async move {
         // Test first page with small page size
-        let resp: RecentResp = env.api.get_query(
+        let resp: PageByUserResp = env.api.get_query_paged(
             "/song/page_by_user",
-            &PageByUserReq {
-                user_id: 100004,
-                page: Some(0),
-                size: Some(20),
-            },
+            &PageByUserReq { user_id: 100004 },
+            &PageQuery { page_index: 0, page_size: 20 },
         )
             .await
             .parse_resp()
             .await
             .unwrap();
-        assert!(resp.songs.iter().all(|x| x.uploader_uid == 100004));
+        assert!(resp.items.iter().all(|x| x.uploader_uid == 100004));
 
-        println!("First page: {:#?}", resp.songs);
+        println!("First page: {:#?}", resp.items);
 
         // Test second page
-        let resp2: RecentResp = env.api.get_query(
+        let resp2: PageByUserResp = env.api.get_query_paged(
             "/song/page_by_user",
-            &PageByUserReq {
-                user_id: 100004,
-                page: Some(1),
-                size: Some(20),
-            },
+            &PageByUserReq { user_id: 100004 },
+            &PageQuery { page_index: 1, page_size: 20 },
         ).await.parse_resp().await.unwrap();
         // Assert no songs appear in both pages
-        let resp2_ids = resp2.songs.iter().map(|song| song.id).collect::<std::collections::HashSet<_>>();
-        assert!(resp.songs.iter().all(|song1| !resp2_ids.contains(&song1.id)));
+        let resp2_ids = resp2.items.iter().map(|song| song.id).collect::<std::collections::HashSet<_>>();
+        assert!(resp.items.iter().all(|song1| !resp2_ids.contains(&song1.id)));
 
-        println!("Second page: {:#?}", resp2.songs);
+        println!("Second page: {:#?}", resp2.items);
     }).await
 }
 
@@ -201,7 +240,11 @@ async fn test_likes() {
             playback_position_secs: Some(123),
         };
 
-        assert_is_ok(env.api.post("/song/likes/like", &like_req).await).await;
+        let liked: LikeResp = env.api.post("/song/likes/like", &like_req).await.parse_resp().await.unwrap();
+        let detail: DetailResp = env.api
+            .get_query("/song/detail", &DetailReq { id: song.display_id.clone() }).await
+            .parse_resp().await.unwrap();
+        assert_eq!(detail.like_count, liked.like_count, "cached detail should reflect the like");
 
         let status: LikeStatusResp = env.api
             .get_query("/song/likes/status", &like_req).await
@@ -211,7 +254,7 @@ async fn test_likes() {
         let page: MyLikesResp = env.api
             .get_query(
                 "/song/likes/page_my_likes",
-                &MyLikesReq {
+                &PageQuery {
                     page_index: 0,
                     page_size: 10,
                 },
@@ -219,10 +262,14 @@ async fn test_likes() {
         assert_eq!(page.page_index, 0);
         assert_eq!(page.page_size, 10);
         assert_eq!(page.total, 1);
-        assert_eq!(page.data.len(), 1);
-        assert_eq!(page.data[0].song_data.id, song.id);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].song_data.id, song.id);
 
-        assert_is_ok(env.api.post("/song/likes/unlike", &like_req).await).await;
+        let unliked: LikeResp = env.api.post("/song/likes/unlike", &like_req).await.parse_resp().await.unwrap();
+        let detail: DetailResp = env.api
+            .get_query("/song/detail", &DetailReq { id: song.display_id.clone() }).await
+            .parse_resp().await.unwrap();
+        assert_eq!(detail.like_count, unliked.like_count, "cached detail should reflect the unlike");
 
         let status: LikeStatusResp = env.api.get_query("/song/likes/status", &like_req)
             .await.parse_resp().await.unwrap();
@@ -232,14 +279,14 @@ async fn test_likes() {
             .api
             .get_query(
                 "/song/likes/page_my_likes",
-                &MyLikesReq {
+                &PageQuery {
                     page_index: 0,
                     page_size: 10,
                 },
             )
             .await.parse_resp().await.unwrap();
         assert_eq!(page.total, 0);
-        assert!(page.data.is_empty());
+        assert!(page.items.is_empty());
     }).await
 }
 
@@ -252,7 +299,7 @@ async fn test_likes_validation() {
             .api
             .get_query(
                 "/song/likes/page_my_likes",
-                &MyLikesReq {
+                &PageQuery {
                     page_index: -1,
                     page_size: 10,
                 },
@@ -264,7 +311,7 @@ async fn test_likes_validation() {
             .api
             .get_query(
                 "/song/likes/page_my_likes",
-                &MyLikesReq {
+                &PageQuery {
                     page_index: 0,
                     page_size: 0,
                 },
@@ -272,4 +319,278 @@ async fn test_likes_validation() {
             .await;
         assert_is_err(invalid_size).await;
     }).await
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_comments() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        let user = with_new_random_test_user(&mut env).await;
+        let page = PageQuery { page_index: 0, page_size: 20 };
+
+        let err = env.api.call::<SongCommentCreate>(&CreateCommentReq { song_id: song.id, content: " ".to_string() }).await.unwrap_err();
+        assert_eq!("comment_empty", err.code);
+        let err = env.api.call::<SongCommentCreate>(&CreateCommentReq { song_id: -1, content: "hello".to_string() }).await.unwrap_err();
+        assert_eq!("song_not_found", err.code);
+
+        let root = env.api.call::<SongCommentCreate>(&CreateCommentReq { song_id: song.id, content: "hello".to_string() }).await.unwrap();
+        let reply = env.api.call::<SongCommentReply>(&ReplyCommentReq { comment_id: root.comment_id, content: "reply".to_string() }).await.unwrap();
+        // Replying to a reply stays in the thread of the root comment
+        env.api.call::<SongCommentReply>(&ReplyCommentReq { comment_id: reply.comment_id, content: "reply of reply".to_string() }).await.unwrap();
+
+        let roots: PageCommentResp = env.api.call::<SongCommentPage>(&PageCommentQuery {
+            req: PageCommentReq { song_id: song.id, parent_id: None },
+            page: page.clone(),
+        }).await.unwrap();
+        let item = roots.items.iter().find(|x| x.id == root.comment_id).unwrap();
+        assert_eq!(2, item.reply_count);
+        assert_eq!(user.uid, item.author.uid);
+
+        let replies: PageCommentResp = env.api.call::<SongCommentPage>(&PageCommentQuery {
+            req: PageCommentReq { song_id: song.id, parent_id: Some(root.comment_id) },
+            page: page.clone(),
+        }).await.unwrap();
+        assert_eq!(2, replies.total);
+        assert!(replies.items[0].reply_to.is_none());
+        assert_eq!(Some(user.uid), replies.items[1].reply_to.as_ref().map(|x| x.uid));
+
+        env.api.call::<SongCommentReport>(&ReportCommentReq { comment_id: reply.comment_id, reason: "spam".to_string() }).await.unwrap();
+        env.api.call::<SongCommentReport>(&ReportCommentReq { comment_id: reply.comment_id, reason: "spam".to_string() }).await.unwrap();
+
+        env.api.call::<SongCommentDelete>(&CommentIdReq { comment_id: root.comment_id }).await.unwrap();
+        // The root is kept as a placeholder so the replies stay in the thread
+        env.api.call::<SongCommentReport>(&ReportCommentReq { comment_id: reply.comment_id, reason: "spam".to_string() }).await.unwrap();
+        let roots: PageCommentResp = env.api.call::<SongCommentPage>(&PageCommentQuery {
+            req: PageCommentReq { song_id: song.id, parent_id: None },
+            page: page.clone(),
+        }).await.unwrap();
+        let item = roots.items.iter().find(|x| x.id == root.comment_id).unwrap();
+        assert!(item.deleted);
+        assert_eq!("[deleted]", item.content);
+        let err = env.api.call::<SongCommentDelete>(&CommentIdReq { comment_id: root.comment_id }).await.unwrap_err();
+        assert_eq!("not_found", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_share() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        with_new_random_test_user(&mut env).await;
+
+        env.api.call::<SongShare>(&ShareReq { song_id: song.id, platform: SharePlatform::Wechat }).await.unwrap();
+        // In the cooldown, regardless of the platform
+        let err = env.api.call::<SongShare>(&ShareReq { song_id: song.id, platform: SharePlatform::Link }).await.unwrap_err();
+        assert_eq!("cooldown", err.code);
+        let err = env.api.call::<SongShare>(&ShareReq { song_id: -1, platform: SharePlatform::Link }).await.unwrap_err();
+        assert_eq!("not_found", err.code);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_report() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        with_new_random_test_user(&mut env).await;
+
+        let req = |category: &str| ReportSongReq { song_id: song.id, category: category.to_string(), reason: "test".to_string() };
+        let err = env.api.call::<SongReport>(&req("unknown")).await.unwrap_err();
+        assert_eq!("invalid_category", err.code);
+
+        let resp = env.api.call::<SongReport>(&req("wrong_origin_info")).await.unwrap();
+        let report_id = resp.report_id.unwrap();
+        // Reported before handled
+        let resp = env.api.call::<SongReport>(&req("copyright")).await.unwrap();
+        assert_eq!(None, resp.report_id);
+
+        with_test_contributor_user(&mut env).await;
+        let page: PageSongReportsResp = env.api.get("/admin/song/report/page?status=0&page_size=50").await.parse_resp().await.unwrap();
+        let item = page.items.iter().find(|x| x.id == report_id).unwrap();
+        assert_eq!(song.id, item.song_id);
+        assert!(!item.song_hidden);
+
+        // Hidden songs are treated as not existing
+        let hide = HideSongReq { song_id: song.id, hidden: true, reason: "Pending review".to_string() };
+        let resp: HideSongResp = env.api.post("/admin/song/hide", &hide).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+        let resp: HideSongResp = env.api.post("/admin/song/hide", &hide).await.parse_resp().await.unwrap();
+        assert!(!resp.changed);
+        let page: PageSongReportsResp = env.api.get("/admin/song/report/page?status=0&page_size=50").await.parse_resp().await.unwrap();
+        assert!(page.items.iter().find(|x| x.id == report_id).unwrap().song_hidden);
+
+        let err = env.api.post("/song/play", &TouchReq { song_id: song.id }).await.parse_resp::<()>().await.unwrap_err();
+        assert_eq!("song_not_found", err.code);
+        let like_req = LikeReq { song_id: song.id, playback_position_secs: None };
+        let err = env.api.post("/song/likes/like", &like_req).await.parse_resp::<LikeResp>().await.unwrap_err();
+        assert_eq!("song_not_found", err.code);
+        let err = env.api.call::<SongShare>(&ShareReq { song_id: song.id, platform: SharePlatform::Link }).await.unwrap_err();
+        assert_eq!("not_found", err.code);
+
+        let resolve = ResolveSongReportReq { id: report_id, dismiss: false, note: Some("Confirmed".to_string()) };
+        env.api.post("/admin/song/report/resolve", &resolve).await.parse_resp::<()>().await.unwrap();
+        let err = env.api.post("/admin/song/report/resolve", &resolve).await.parse_resp::<()>().await.unwrap_err();
+        assert_eq!("report_resolved", err.code);
+        let err = env.api.post("/admin/song/report/resolve", &ResolveSongReportReq { id: -1, ..resolve }).await
+            .parse_resp::<()>().await.unwrap_err();
+        assert_eq!("not_found", err.code);
+        let page: PageSongReportsResp = env.api.get("/admin/song/report/page?status=0&page_size=50").await.parse_resp().await.unwrap();
+        assert!(page.items.iter().all(|x| x.id != report_id));
+
+        let resp: HideSongResp = env.api.post("/admin/song/hide", &HideSongReq { hidden: false, ..hide }).await.parse_resp().await.unwrap();
+        assert!(resp.changed);
+        env.api.post("/song/play", &TouchReq { song_id: song.id }).await.parse_resp::<()>().await.unwrap();
+        let liked: LikeResp = env.api.post("/song/likes/like", &like_req).await.parse_resp().await.unwrap();
+        assert_eq!(1, liked.like_count);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_search_suggest() {
+    with_test_environment(|env| async move {
+        let resp = env.api.call::<SongSearchSuggest>(&SearchSuggestReq { q: "基".to_string(), limit: Some(100) }).await.unwrap();
+        assert!(resp.hits.len() <= 10);
+        println!("{:#?}", resp);
+
+        let resp = env.api.call::<SongSearchSuggest>(&SearchSuggestReq { q: " ".to_string(), limit: None }).await.unwrap();
+        assert!(resp.hits.is_empty());
+    }).await;
+}
+
+#[tokio::test]
+async fn test_flush_pending_plays() {
+    with_test_environment(|mut env| async move {
+        with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        for _ in 0..3 {
+            song_play::add_pending_play(env.redis.clone(), &env.pool, None, song.id).await.unwrap();
+        }
+
+        // The concurrent flushes, including the one of the server, add the plays once
+        let red_lock = RedLock::new(env.redis.clone()).unwrap();
+        let flush = || {
+            let mut redis = env.redis.clone();
+            let red_lock = red_lock.clone();
+            let pool = env.pool.clone();
+            async move { song_play::flush_pending_plays(&mut redis, &red_lock, &pool).await.unwrap() }
+        };
+        join_all((0..4).map(|_| flush())).await;
+        let mut play_count = 0;
+        for _ in 0..50 {
+            play_count = SongDao::get_by_id(&env.pool, song.id).await.unwrap().unwrap().play_count;
+            if play_count >= 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            flush().await;
+        }
+        assert_eq!(3, play_count);
+        flush().await;
+        assert_eq!(3, SongDao::get_by_id(&env.pool, song.id).await.unwrap().unwrap().play_count);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_recount_with_pending_plays() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        // The same as the play routes, the event is buffered before the play is pending
+        for _ in 0..3 {
+            song_play::record_play(env.redis.clone(), &env.pool, SongPlay {
+                id: 0,
+                song_id: song.id,
+                user_id: Some(user.uid),
+                anonymous_uid: None,
+                create_time: chrono::Utc::now(),
+            }).await.unwrap();
+            song_play::add_pending_play(env.redis.clone(), &env.pool, Some(user.uid), song.id).await.unwrap();
+        }
+
+        let red_lock = RedLock::new(env.redis.clone()).unwrap();
+        let mut redis = env.redis.clone();
+        song_stats::recount(&mut redis, &red_lock, &env.pool, Some(song.id)).await.unwrap();
+        assert_eq!(3, SongDao::get_by_id(&env.pool, song.id).await.unwrap().unwrap().play_count);
+        // The plays counted by the recount are not added again
+        song_play::flush_pending_plays(&mut redis, &red_lock, &env.pool).await.unwrap();
+        assert_eq!(3, SongDao::get_by_id(&env.pool, song.id).await.unwrap().unwrap().play_count);
+    }).await;
+}
+
+#[tokio::test]
+async fn test_flush_play_events() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        for _ in 0..5 {
+            song_play::record_play(env.redis.clone(), &env.pool, SongPlay {
+                id: 0,
+                song_id: song.id,
+                user_id: Some(user.uid),
+                anonymous_uid: None,
+                create_time: chrono::Utc::now(),
+            }).await.unwrap();
+        }
+
+        // The concurrent flushes, including the one of the server, insert the plays once
+        let red_lock = RedLock::new(env.redis.clone()).unwrap();
+        let flush = || {
+            let mut redis = env.redis.clone();
+            let red_lock = red_lock.clone();
+            let pool = env.pool.clone();
+            async move { song_play::flush_play_events(&mut redis, &red_lock, &pool).await.unwrap() }
+        };
+        join_all((0..4).map(|_| flush())).await;
+        let mut plays = 0;
+        for _ in 0..50 {
+            plays = SongDao::count_plays(&env.pool, song.id).await.unwrap();
+            if plays >= 5 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            flush().await;
+        }
+        assert_eq!(5, plays);
+        flush().await;
+        assert_eq!(5, SongDao::count_plays(&env.pool, song.id).await.unwrap());
+    }).await;
+}
+
+#[tokio::test]
+async fn test_flush_play_events_batch_once() {
+    with_test_environment(|mut env| async move {
+        let user = with_new_random_test_user(&mut env).await;
+        let song = publish_approved_song(&mut env).await;
+        let play = serde_json::to_string(&SongPlay {
+            id: 0,
+            song_id: song.id,
+            user_id: Some(user.uid),
+            anonymous_uid: None,
+            create_time: chrono::Utc::now(),
+        }).unwrap();
+
+        // A batch inserted by a flush which outlasted its lock, then claimed again by another flush
+        let batch_id = uuid::Uuid::new_v4().to_string();
+        SongDao::insert_play_event_batch(&env.pool, &batch_id).await.unwrap();
+        let _: () = redis::pipe()
+            .atomic()
+            .rpush("song:play_events:processing", &[play, "unreadable".to_string()]).ignore()
+            .set("song:play_events:processing_batch", &batch_id).ignore()
+            .query_async(&mut env.redis).await.unwrap();
+
+        let red_lock = RedLock::new(env.redis.clone()).unwrap();
+        let mut redis = env.redis.clone();
+        for _ in 0..50 {
+            let processing: bool = redis.exists("song:play_events:processing").await.unwrap();
+            if !processing {
+                break;
+            }
+            // The flusher of the server may take the batch first
+            song_play::flush_play_events(&mut redis, &red_lock, &env.pool).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        }
+        assert_eq!(0, SongDao::count_plays(&env.pool, song.id).await.unwrap());
+    }).await;
+}
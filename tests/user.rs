@@ -1,8 +1,19 @@
 mod common;
 
 use common::with_test_environment;
-use hachimi_world_server::web::routes::user::{GetProfileReq, PublicUserProfile, SearchReq, SearchResp, UpdateProfileReq};
-use crate::common::{assert_is_ok, auth, CommonParse};
+use hachimi_world_server::service::events;
+use hachimi_world_server::web::i18n::{localize, Lang};
+use hachimi_world_server::web::api::{Bootstrap, EventsTicket, UserFollow, UserLanguage, UserNotificationMarkRead, UserNotificationUnreadCount, UserProfile, UserSetLanguage, UserSetSupportLinks, UserUnfollow};
+use hachimi_world_server::service::support_link::SupportLink;
+use hachimi_world_server::web::pagination::PageQuery;
+use hachimi_world_server::web::routes::notification::{MarkReadReq, PageNotificationReq, PageNotificationResp};
+use hachimi_world_server::web::routes::user::{FollowReq, GetProfileReq, LanguageData, PageFollowReq, PageFollowResp, PublicUserProfile, SearchReq, SearchResp, SetSupportLinksReq, UpdateProfileReq};
+use hachimi_world_server::web::routes::events::StreamReq;
+use crate::common::{assert_is_ok, auth, ApiClient, CommonParse};
+use reqwest::multipart::{Form, Part};
+use serde_json::json;
+use std::env;
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_get_and_update_profile() {
@@ -33,7 +44,33 @@ async fn test_get_and_update_profile() {
 
 #[tokio::test]
 async fn test_set_avatar() {
-    // TODO[integrated-test]: Test set user avatar and update profile
+    with_test_environment(|mut env| async move {
+        let user = auth::with_new_random_test_user(&mut env).await;
+        let bootstrap = env.api.call::<Bootstrap>(&()).await.unwrap();
+
+        // Between the 8MB once hardcoded for the avatars and the configured limit
+        let png = noise_png(1728);
+        assert!(png.len() > 8 * 1024 * 1024 && png.len() < bootstrap.limits.image_max_bytes);
+
+        let resp = env.api.post_raw("/user/set_avatar")
+            .multipart(Form::new().part("file", Part::bytes(png)))
+            .send().await.unwrap();
+        assert_is_ok(resp).await;
+
+        let profile = env.api.call::<UserProfile>(&GetProfileReq { uid: user.uid }).await.unwrap();
+        assert!(profile.avatar_url.is_some());
+    }).await
+}
+
+/// A PNG of random pixels, which can't be compressed much
+fn noise_png(size: u32) -> Vec<u8> {
+    let mut pixels = vec![0u8; (size * size * 3) as usize];
+    rand::fill(&mut pixels[..]);
+    let mut png = Vec::new();
+    image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(size, size, pixels).unwrap())
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    png
 }
 
 #[tokio::test]
@@ -46,4 +83,151 @@ async fn test_search() {
         }).await.parse_resp().await.unwrap();
         println!("{:?}", resp);
     }).await
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_follow() {
+    with_test_environment(|mut env| async move {
+        let followee = auth::with_new_random_test_user(&mut env).await;
+        let follower = auth::with_new_random_test_user(&mut env).await;
+        let page = PageQuery { page_index: 0, page_size: 20 };
+
+        let err = env.api.call::<UserFollow>(&FollowReq { uid: follower.uid }).await.unwrap_err();
+        assert_eq!("follow_self", err.code);
+        let err = env.api.call::<UserFollow>(&FollowReq { uid: -1 }).await.unwrap_err();
+        assert_eq!("user_not_found", err.code);
+
+        // Following twice is fine
+        env.api.call::<UserFollow>(&FollowReq { uid: followee.uid }).await.unwrap();
+        env.api.call::<UserFollow>(&FollowReq { uid: followee.uid }).await.unwrap();
+
+        let profile = env.api.call::<UserProfile>(&GetProfileReq { uid: followee.uid }).await.unwrap();
+        assert_eq!(1, profile.follower_count);
+
+        let followers: PageFollowResp = env.api.get_query_paged("/user/followers", &PageFollowReq { uid: followee.uid }, &page)
+            .await.parse_resp().await.unwrap();
+        assert_eq!(1, followers.total);
+        assert_eq!(follower.uid, followers.items[0].user.uid);
+
+        let following: PageFollowResp = env.api.get_query_paged("/user/following", &PageFollowReq { uid: follower.uid }, &page)
+            .await.parse_resp().await.unwrap();
+        assert_eq!(1, following.total);
+        assert_eq!(followee.uid, following.items[0].user.uid);
+
+        env.api.call::<UserUnfollow>(&FollowReq { uid: followee.uid }).await.unwrap();
+        env.api.call::<UserUnfollow>(&FollowReq { uid: followee.uid }).await.unwrap();
+        let profile = env.api.call::<UserProfile>(&GetProfileReq { uid: followee.uid }).await.unwrap();
+        assert_eq!(0, profile.follower_count);
+        let followers: PageFollowResp = env.api.get_query_paged("/user/followers", &PageFollowReq { uid: followee.uid }, &page)
+            .await.parse_resp().await.unwrap();
+        assert_eq!(0, followers.total);
+    }).await
+}
+
+#[tokio::test]
+async fn test_notifications() {
+    with_test_environment(|mut env| async move {
+        let followee = auth::with_new_random_test_user(&mut env).await;
+        let follower = auth::with_new_random_test_user(&mut env).await;
+        let page = PageQuery { page_index: 0, page_size: 20 };
+        env.api.call::<UserFollow>(&FollowReq { uid: followee.uid }).await.unwrap();
+        // Not notified of its own actions
+        assert_eq!(0, env.api.call::<UserNotificationUnreadCount>(&()).await.unwrap().count);
+
+        env.api.set_token(followee.token.access_token.clone());
+        assert_eq!(1, env.api.call::<UserNotificationUnreadCount>(&()).await.unwrap().count);
+        let notifications: PageNotificationResp = env.api.get_query_paged("/user/notifications/page", &PageNotificationReq { unread_only: true }, &page)
+            .await.parse_resp().await.unwrap();
+        assert_eq!("follow", notifications.items[0].r#type);
+        assert_eq!(Some(follower.uid), notifications.items[0].actor.as_ref().map(|x| x.uid));
+        assert!(!notifications.items[0].read);
+
+        let err = env.api.call::<UserNotificationMarkRead>(&MarkReadReq { ids: Some(vec![0; 101]) }).await.unwrap_err();
+        assert_eq!("too_many_ids", err.code);
+        let resp = env.api.call::<UserNotificationMarkRead>(&MarkReadReq { ids: Some(vec![notifications.items[0].id]) }).await.unwrap();
+        assert_eq!(1, resp.count);
+        let resp = env.api.call::<UserNotificationMarkRead>(&MarkReadReq { ids: None }).await.unwrap();
+        assert_eq!(0, resp.count);
+        assert_eq!(0, env.api.call::<UserNotificationUnreadCount>(&()).await.unwrap().count);
+    }).await
+}
+
+#[tokio::test]
+async fn test_language() {
+    with_test_environment(|mut env| async move {
+        auth::with_new_random_test_user(&mut env).await;
+        // Registered without `Accept-Language`
+        let resp = env.api.call::<UserLanguage>(&()).await.unwrap();
+        assert_eq!(Some("zh-CN".to_string()), resp.language);
+
+        let err = env.api.call::<UserSetLanguage>(&LanguageData { language: Some("fr".to_string()) }).await.unwrap_err();
+        assert_eq!("invalid_language", err.code);
+        assert_eq!(localize("invalid_language", Lang::ZhCn).unwrap(), err.msg);
+        env.api.call::<UserSetLanguage>(&LanguageData { language: Some("en-US".to_string()) }).await.unwrap();
+        let resp = env.api.call::<UserLanguage>(&()).await.unwrap();
+        assert_eq!(Some("en".to_string()), resp.language);
+        // The errors follow the saved language
+        let err = env.api.call::<UserSetLanguage>(&LanguageData { language: Some("fr".to_string()) }).await.unwrap_err();
+        assert_eq!(localize("invalid_language", Lang::En).unwrap(), err.msg);
+
+        env.api.call::<UserSetLanguage>(&LanguageData { language: None }).await.unwrap();
+        let resp = env.api.call::<UserLanguage>(&()).await.unwrap();
+        assert_eq!(None, resp.language);
+    }).await
+}
+
+#[tokio::test]
+async fn test_support_links() {
+    with_test_environment(|mut env| async move {
+        let user = auth::with_new_random_test_user(&mut env).await;
+        let link = |platform: &str, url: &str| SupportLink { platform: platform.to_string(), url: url.to_string() };
+
+        let err = env.api.call::<UserSetSupportLinks>(&SetSupportLinksReq {
+            links: vec![link("afdian", "https://www.patreon.com/hachimi")],
+        }).await.unwrap_err();
+        assert_eq!("invalid_support_link", err.code);
+        let err = env.api.call::<UserSetSupportLinks>(&SetSupportLinksReq {
+            links: vec![link("afdian", "https://afdian.com/a/1"), link("afdian", "https://afdian.com/a/2")],
+        }).await.unwrap_err();
+        assert_eq!("invalid_support_link", err.code);
+
+        let links = vec![link("patreon", "https://www.patreon.com/hachimi"), link("afdian", "https://afdian.com/a/hachimi")];
+        env.api.call::<UserSetSupportLinks>(&SetSupportLinksReq { links: links.clone() }).await.unwrap();
+        let profile = env.api.call::<UserProfile>(&GetProfileReq { uid: user.uid }).await.unwrap();
+        assert_eq!(links, profile.support_links);
+
+        env.api.call::<UserSetSupportLinks>(&SetSupportLinksReq { links: vec![] }).await.unwrap();
+        let profile = env.api.call::<UserProfile>(&GetProfileReq { uid: user.uid }).await.unwrap();
+        assert!(profile.support_links.is_empty());
+    }).await
+}
+
+#[tokio::test]
+async fn test_events_by_ticket() {
+    with_test_environment(|mut env| async move {
+        let user = auth::with_new_random_test_user(&mut env).await;
+        let ticket = env.api.call::<EventsTicket>(&()).await.unwrap();
+        assert_eq!(events::TICKET_TTL_SECS, ticket.expires_in);
+
+        // Like the EventSource, the connection has no authorization header
+        let anonymous = ApiClient::new(env::var("TEST_HTTP_BASE_URL").unwrap());
+        let req = StreamReq { ticket: Some(ticket.ticket.clone()) };
+        let mut resp = anonymous.get_query("/events", &req).await;
+        assert!(resp.status().is_success());
+
+        // Published through Redis and relayed by the subscriber of the server
+        events::publish(&mut env.redis, user.uid, events::EVENT_FOLLOWER, json!({"follower_uid": 1, "followed": true})).await;
+        let mut received = String::new();
+        while !received.contains(&format!("event: {}", events::EVENT_FOLLOWER)) {
+            let chunk = tokio::time::timeout(Duration::from_secs(10), resp.chunk()).await
+                .expect("the event is not received").unwrap().expect("the stream ended");
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+        assert!(received.contains("\"follower_uid\":1"));
+
+        // A ticket can be used once
+        let resp = anonymous.get_query("/events", &req).await;
+        assert_eq!(401, resp.status().as_u16());
+        let resp = anonymous.get_query("/events", &StreamReq { ticket: None }).await;
+        assert_eq!(401, resp.status().as_u16());
+    }).await;
+}
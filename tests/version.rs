@@ -1,6 +1,9 @@
+use crate::common::auth;
 use crate::common::with_test_environment;
 use crate::common::CommonParse;
-use chrono::Utc;
+use chrono::{DateTime, TimeDelta, Utc};
+use hachimi_world_server::web::api::{UserReleaseChannel, UserSetReleaseChannel, VersionLatest, VersionLatestBatchV2};
+use hachimi_world_server::web::routes::user::ReleaseChannelData;
 use hachimi_world_server::web::routes::version::{LatestVersionBatchReq, LatestVersionReq, LatestVersionResp, PublishVersionReq, PublishVersionResp};
 use std::env;
 
@@ -16,17 +19,20 @@ async fn test_publish_version() {
             version_number: 1,
             changelog: "Nothing changed".to_string(),
             variant: "test-android".to_string(),
+            channel: None,
             url: "https://test.example.com/android/latest.apk".to_string(),
             release_time: now,
         }).await.parse_resp::<PublishVersionResp>().await.unwrap();
         let id = resp.id;
         let resp = env.api.get_query("/version/latest", &LatestVersionReq {
             variant: "test-android".to_string(),
+            channel: None,
         }).await.parse_resp::<LatestVersionResp>().await.unwrap();
         assert_eq!(resp.version_name, "v1.0.0-test1");
         assert_eq!(resp.version_number, 1);
         assert_eq!(resp.changelog, "Nothing changed");
         assert_eq!(resp.variant, "test-android");
+        assert_eq!(resp.channel, "stable");
         assert_eq!(resp.url, "https://test.example.com/android/latest.apk");
         assert_eq!(resp.release_time, now);
         ()
@@ -38,7 +44,77 @@ async fn test_get_version_batch() {
     with_test_environment(|mut env| async move {
         let result = env.api.post("/version/latest_batch", &LatestVersionBatchReq{
             variants: vec!["dev-windows".to_string(), "dev-macos".to_string()],
+            channel: None,
         }).await.parse_resp::<Vec<LatestVersionResp>>().await.unwrap();
         println!("{:?}", result);
     }).await
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_get_version_batch_v2() {
+    with_test_environment(|env| async move {
+        let missing = format!("test-missing-{}", Utc::now().timestamp_millis());
+        let variants = vec!["dev-windows".to_string(), missing.clone()];
+        let result = env.api.call::<VersionLatestBatchV2>(&LatestVersionBatchReq {
+            variants: variants.clone(),
+            channel: None,
+        }).await.unwrap();
+        assert_eq!(variants, result.results.iter().map(|x| x.variant.clone()).collect::<Vec<_>>());
+        let item = &result.results[1];
+        assert!(item.version.is_none());
+        assert!(item.error.is_none());
+
+        // The missing variant is cached now
+        let result = env.api.call::<VersionLatestBatchV2>(&LatestVersionBatchReq {
+            variants: vec![missing],
+            channel: None,
+        }).await.unwrap();
+        assert!(result.results[0].version.is_none());
+    }).await
+}
+
+#[tokio::test]
+async fn test_release_channels() {
+    with_test_environment(|mut env| async move {
+        env.api.set_token(env::var("TEST_PUBLISH_VERSION_TOKEN").unwrap());
+        let now = Utc::now();
+        let variant = format!("test-channel-{}", now.timestamp_millis());
+        let publish = |version_number: i32, channel: &str, release_time: DateTime<Utc>| PublishVersionReq {
+            version_name: format!("v{}-{}", version_number, channel),
+            version_number,
+            changelog: String::new(),
+            variant: variant.clone(),
+            channel: Some(channel.to_string()),
+            url: "https://test.example.com/latest".to_string(),
+            release_time,
+        };
+        env.api.post("/version/publish", &publish(1, "stable", now - TimeDelta::minutes(2))).await.parse_resp::<PublishVersionResp>().await.unwrap();
+        env.api.post("/version/publish", &publish(2, "beta", now - TimeDelta::minutes(1))).await.parse_resp::<PublishVersionResp>().await.unwrap();
+        let err = env.api.post("/version/publish", &publish(3, "alpha", now)).await.parse_resp::<PublishVersionResp>().await.unwrap_err();
+        assert_eq!("invalid_channel", err.code);
+
+        for (channel, expected) in [(None, 1), (Some("stable"), 1), (Some("beta"), 2), (Some("nightly"), 2)] {
+            // The beta and nightly clients get the newer beta release
+            let resp = env.api.call::<VersionLatest>(&LatestVersionReq {
+                variant: variant.clone(),
+                channel: channel.map(|x| x.to_string()),
+            }).await.unwrap().unwrap();
+            assert_eq!(expected, resp.version_number, "{:?}", channel);
+        }
+    }).await
+}
+
+#[tokio::test]
+async fn test_user_release_channel() {
+    with_test_environment(|mut env| async move {
+        auth::with_new_random_test_user(&mut env).await;
+        let resp = env.api.call::<UserReleaseChannel>(&()).await.unwrap();
+        assert_eq!("stable", resp.channel);
+
+        let err = env.api.call::<UserSetReleaseChannel>(&ReleaseChannelData { channel: "alpha".to_string() }).await.unwrap_err();
+        assert_eq!("invalid_channel", err.code);
+        env.api.call::<UserSetReleaseChannel>(&ReleaseChannelData { channel: "beta".to_string() }).await.unwrap();
+        let resp = env.api.call::<UserReleaseChannel>(&()).await.unwrap();
+        assert_eq!("beta", resp.channel);
+    }).await
+}